@@ -1,43 +1,217 @@
+use std::collections::HashMap;
 use std::env;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use dup_code_check_core::ScanOptions;
+use dup_code_check_core::{
+    BudgetOrder, FileListing, ReportSection, ReportSort, ScanOptions, ScanSample, ScanShard,
+    SimilarityWindows, TestCodeFilter, UnicodeNormalization,
+};
 
-const HELP_TEXT_EN: &str = concat!(
+use crate::convert::Encoding;
+use crate::links::validate_link_template;
+use crate::locale::{Localization, tr};
+use crate::preset::Preset;
+use crate::render::text::ColorChoice;
+use crate::sections::SectionFilter;
+use crate::strict::StrictPolicy;
+
+/// Commits of history `resurrect` reads deleted files from without `--depth`.
+const DEFAULT_HISTORY_DEPTH: usize = 1000;
+
+/// Subcommand names, recognized as the first argument only.
+pub(crate) const SUBCOMMANDS: &[&str] = &[
+    "snapshot",
+    "merge-shards",
+    "explain",
+    "convert",
+    "genealogy",
+    "html-diff",
+    "self-diff",
+    "resurrect",
+    "cache",
+];
+
+/// The subcommand `argv` starts with, if any.
+pub(crate) fn subcommand(argv: &[String]) -> Option<&str> {
+    argv.first()
+        .map(String::as_str)
+        .filter(|arg| SUBCOMMANDS.contains(arg))
+}
+
+pub(crate) const HELP_TEXT_EN: &str = concat!(
     "dup-code-check (duplicate files / suspected duplicate code spans)\n",
     "\n",
     "Usage:\n",
     "  dup-code-check [options] [root ...]\n",
+    "  dup-code-check snapshot -o <file> [--shard <i/N>] [options] [root ...]\n",
+    "  dup-code-check merge-shards [options] <partial ...>\n",
+    "  dup-code-check explain <group-id> <report.json> [root ...]\n",
+    "  dup-code-check convert <input> <output>\n",
+    "  dup-code-check genealogy [options] <old.dcs> <new.dcs>\n",
+    "  dup-code-check html-diff <old.json> <new.json> [-o <file>]\n",
+    "  dup-code-check self-diff --before <ref> --after <ref> [options] [repo]\n",
+    "  dup-code-check resurrect --repo <path> [--depth <n>] [options]\n",
+    "  dup-code-check cache <status|clear|gc> --cache-dir <dir> [--max-cache-size <bytes>]\n",
+    "\n",
+    "Commands:\n",
+    "  snapshot                Save the tokenized roots to a snapshot file (-o, --output)\n",
+    "  merge-shards            Merge snapshot files (e.g. one per --shard) and output a report\n",
+    "  explain                 Re-derive one group of a saved --report --json output: aligned\n",
+    "                          occurrences and the thresholds that decided it\n",
+    "  convert                 Re-encode a saved JSON output; the encodings follow the file\n",
+    "                          extensions (.json, .json.gz, .msgpack)\n",
+    "  genealogy               Classify each clone group of two snapshots as added, removed,\n",
+    "                          grown, shrunk, moved or unchanged\n",
+    "  html-diff               Render the groups added, removed or changed between two saved\n",
+    "                          --report --json outputs as an HTML page (-o, --output)\n",
+    "  self-diff               List the clone groups added or grown between two git refs of\n",
+    "                          one repo (read from git, no checkout)\n",
+    "  resurrect               Report live code that duplicates files deleted within the last\n",
+    "                          --depth commits (default: 1000) instead of being reverted\n",
+    "  cache                   Show (status), empty (clear) or prune (gc) a --cache-dir\n",
     "\n",
     "Options:\n",
-    "  --localization <en|zh>  Set output language (default: en)\n",
+    "  --localization <lang>   Set output language: en, zh, ja, de, es (default: en); ja, de\n",
+    "                          and es fall back to English for help and untranslated messages\n",
     "  --report                Run all detectors and output a report\n",
+    "  --preset <name>         Start from quick (files + blocks, 64 MiB budget), balanced\n",
+    "                          (exact detectors, 256 MiB) or thorough (everything, no budget)\n",
+    "  --group-by <group|file> (report) List duplicates per group (default) or per file\n",
+    "  --aggregate ownership   (report) Split groups into cross-team, intra-team and unowned\n",
+    "                          ones by the CODEOWNERS owners of their places\n",
+    "  --codeowners <file>     (aggregate) CODEOWNERS file for every root instead of each\n",
+    "                          root's .github/, root, docs/ or .gitlab/ CODEOWNERS\n",
+    "  --only <sections>       (report) Write out only these sections, e.g.\n",
+    "                          file-duplicates,block-duplicates (`*` wildcards allowed)\n",
+    "  --omit <sections>       (report) Leave these sections out, e.g. similar-*\n",
     "  --code-spans            Find suspected duplicate code spans\n",
-    "  --json                  Output JSON\n",
+    "  --emit-blocks           Print the brace block tree of each given file: line ranges,\n",
+    "                          nesting and the token counts the block detectors compare\n",
+    "  --against-snapshot <file>  (Report) Also compare against a saved snapshot\n",
+    "  --stdin-json            (Report) Also scan files read from stdin as\n",
+    "                          [{\"path\": ..., \"content\": ...}]\n",
+    "  --shard <i/N>           (snapshot) Only scan shard i of N (1-based, by path hash)\n",
+    "  --json                  Output JSON (same as --format json)\n",
+    "  --format <name>         Output format: text, json, jsonl, json.gz, msgpack (msgpack\n",
+    "                          feature), review-comments (report), or an external formatter (runs\n",
+    "                          dup-code-check-format-<name> from PATH)\n",
+    "  --baseline <report.json>  (review-comments) Skip occurrences already in this saved\n",
+    "                          --report --json output\n",
+    "  --suppressions <file>   Drop the groups listed in a suppressions.toml file from every\n",
+    "                          output format until their expiry date\n",
+    "  --color <when>          Color text output: auto, always, never (default: auto)\n",
+    "  --hyperlinks            Make text output locations clickable (OSC 8 file:// links)\n",
+    "  --link-template <url>   Print a code-hosting URL after each location (text, review\n",
+    "                          comments); {repo}, {rev} (git HEAD), {path}, {start}, {end}\n",
     "  --stats                 Include scan stats (JSON) or print to stderr\n",
+    "  --progress-json         Write NDJSON progress events to stderr\n",
+    "  --log-skips <file>      Write an NDJSON record (path, reason, size) per skipped file\n",
     "  --strict                Exit non-zero on fatal skips (perm/traversal/budget/bucket/relativize)\n",
+    "  --strict-policy <list>  Fatal-skip categories that fail --strict (implies --strict):\n",
+    "                          permission,paths,walk,budget,bucket (default: all)\n",
     "  --cross-repo-only       Only report groups spanning >= 2 roots\n",
+    "  --absolute-paths        Print absolute file paths instead of root-relative ones\n",
+    "  --path-prefix-map <from>=<to>  Rewrite output paths starting with <from> to start with <to>\n",
+    "                          (repeatable; the last matching one wins)\n",
+    "  --exclude-tests         Ignore test code (test dirs/files, #[cfg(test)] items)\n",
+    "  --tests-only            Only report duplication within test code\n",
     "  --no-gitignore          Do not respect .gitignore rules\n",
+    "  --global-gitignore      Apply global Git ignores (core.excludesFile) outside Git repos\n",
+    "  --file-listing <mode>   List files with auto|git|walker (default: auto = git ls-files,\n",
+    "                          falling back to the built-in walker)\n",
     "  --gitignore             Respect .gitignore rules (default: on)\n",
     "  --min-match-len <n>     Code spans: minimum normalized length (default: 50)\n",
     "  --min-token-len <n>     Token-based: minimum token length (default: 50)\n",
-    "  --similarity-threshold <f>  Similarity: 0..1 (default: 0.85)\n",
+    "  --language-scale <lang>=<f>  Scale --min-token-len for one language, e.g. java=1.5\n",
+    "                          (repeatable; overrides the built-in per-language table)\n",
+    "  --adaptive-window <n>   Widen the fingerprint window of files past 16Ki chars/tokens,\n",
+    "                          up to n times; only matches n times the minimum stay guaranteed\n",
+    "  --minhash-threshold <f>  MinHash: min similarity 0..1 (default: 0.85)\n",
+    "                          (alias: --similarity-threshold)\n",
+    "  --simhash-threshold <f>  SimHash: min similarity 0..1 (1 - distance/64)\n",
     "  --simhash-max-distance <n>  SimHash: max Hamming distance (default: 3)\n",
+    "  --shingle-size <n>      MinHash/SimHash: tokens per shingle (default: 5)\n",
+    "  --minhash-signature-size <n>  MinHash: signature length (default: 32)\n",
+    "  --minhash-bands <n>     MinHash: LSH bands, must divide the signature size (default: 8)\n",
+    "  --similarity-window <n>  MinHash/SimHash: compare n-token windows instead of blocks\n",
+    "  --similarity-stride <n>  Tokens between window starts (default: half the window)\n",
+    "  --line-span-min-lines <n>  (report) Line spans: min lines per match (default: 2)\n",
+    "  --line-span-window <n>  (report) Line spans: winnowing window in lines (default: 8)\n",
+    "  --symbol-min-count <n>  (report) Symbol duplicates: min shared public symbols (default: 3)\n",
+    "  --import-min-count <n>  (report) Import duplicates: min imports per file (default: 3)\n",
+    "  --import-similarity <f>  (report) Import duplicates: min Jaccard 0..1 (default: 0.8)\n",
+    "  --data-min-bytes <n>    (report) Data duplicates: min JSON/YAML/CSV/XML file size (default: 1024)\n",
+    "  --min-entropy <bits>    (report) Drop span groups below this token entropy, e.g. runs of\n",
+    "                          braces (default: 0, keep all)\n",
+    "  --unicode-normalize <nfc|nfkc>  Normalize Unicode before comparing code (default: off)\n",
+    "  --sort <order>          (report) Section order: occurrences, size, score, path\n",
+    "                          (default: occurrences)\n",
+    "  --preview-chars <n>     Max preview length in chars (default: 80 code spans, 120 others)\n",
+    "  --context <n>           Include n lines before/after the span in previews (default: 0)\n",
+    "  --similarity-diff       (Report) Include a line diff for each similar pair\n",
+    "  --file-coverage         (Report) Include per-file duplicated line ranges\n",
+    "  --clusters              (Report) Cluster related groups into refactoring work items\n",
+    "  --similarity-clusters   (Report) Join similar pairs sharing a block into clusters\n",
+    "  --occurrence-ages       (Report) Date occurrences via git log -L; list drifted copies first\n",
+    "  --occurrence-sources    Attach each root's git origin URL and commit to every occurrence\n",
+    "                          in JSON output\n",
+    "  --license-conflicts     (Report) Flag cross-repo groups whose files declare different licenses\n",
+    "  --suggest-excludes      (Report) Print ignore patterns for vendored, fixture and generated\n",
+    "                          files in the report, with the groups each would remove (stderr)\n",
+    "  --idioms <k>            (Report) List short token sequences copied into at least k files\n",
+    "  --idiom-token-len <n>   (Report) Min tokens of a common idiom (default: 12)\n",
+    "  --file-similarity-matrix <file>  (Report) Write pairwise file similarity (MinHash) as CSV\n",
+    "  --cache-dir <dir>       (Report) Reuse the last report for these roots and options while\n",
+    "                          no scanned file changed\n",
+    "  --max-cache-size <bytes>  (Report, cache gc) Evict the least recently used --cache-dir\n",
+    "                          entries beyond this size\n",
+    "  --dedupe-detectors      (Report) Merge identical groups found by several detectors\n",
+    "  --no-prune-contained    (Report) Keep groups nested inside a longer group's occurrences\n",
     "  --max-report-items <n>  Limit items per report section (default: 200)\n",
+    "  --max-items <section>=<n>  (Report) Limit one section instead, e.g.\n",
+    "                          similarBlocksMinhash=50 (repeatable; JSON section names)\n",
+    "  --section-offset <n>    (report) Skip the first n items of each section (default: 0)\n",
+    "  --section-limit <n>     (report) Items per section after the offset\n",
+    "                          (default: --max-report-items)\n",
+    "  --min-occurrences <n>   Only report groups with >= n occurrences (default: 2)\n",
+    "  --max-occurrences-per-group <n>  List at most n occurrences per group\n",
+    "  --filter-path <glob>    (Report) Only keep groups with an occurrence matching a\n",
+    "                          gitignore-style pattern (repeatable)\n",
+    "  --filter-path-trim      (Report) Also drop the occurrences that do not match\n",
     "  --max-files <n>         Stop after scanning n files\n",
+    "  --sample <fraction|n>   Only scan a deterministic sample of files (by path hash)\n",
     "  --max-total-bytes <n>   Skip files that would exceed total scanned bytes\n",
+    "  --budget-order <order>  Which files budgets keep: walk|smallest|largest|path (default: walk)\n",
     "  --max-file-size <n>     Skip files larger than n bytes (default: 10485760)\n",
     "  --max-normalized-chars <n>  Stop after storing n normalized code characters\n",
     "  --max-tokens <n>        (Report) Stop after storing n tokens\n",
+    "  --max-tokens-per-file <n>  (Report) Token detectors use at most n tokens per file\n",
+    "  --max-groups-per-detector <n>  Span detectors only count groups past n, flagging their\n",
+    "                          section degraded (default: 1000000; 0 = no cap)\n",
+    "  --max-file-time <ms>    Give up on a file whose normalization/tokenization takes longer\n",
+    "  --threads <n>           (Report) Detector worker threads (default: 0 = one per CPU)\n",
     "  --ignore-dir <name>     Add an ignored directory name (repeatable)\n",
+    "  --exclude <glob>        Skip files matching a gitignore-style pattern (repeatable)\n",
+    "  --import-ignores <file> Add the exclusions of a jscpd config (*.json) or .cpdignore\n",
+    "  --skip-snippet <file>   Treat the file's code as boilerplate that seeds no span match,\n",
+    "                          like the built-in skip-list (repeatable)\n",
+    "  --no-builtin-skips      Do not skip the built-in boilerplate (license headers,\n",
+    "                          generated-code banners, serde impls, getters/setters)\n",
+    "  --no-default-excludes   Also scan lockfiles and well-known generated files\n",
+    "                          (Cargo.lock, yarn.lock, *.min.js, *.pb.go, *_generated.*, ...)\n",
+    "  --stop-sequences <file> (Report) Per-language idioms (`<lang>: <code>` lines) that seed\n",
+    "                          no token-span match, replacing the built-in list (repeatable)\n",
     "  --follow-symlinks       Follow symlinks (within each root; default: off)\n",
-    "  -V, --version           Show version\n",
+    "  -V, --version           Show version (with --json: version and capabilities as JSON)\n",
     "  -h, --help              Show help\n",
     "\n",
     "Notes:\n",
     "  - --cross-repo-only requires 2+ roots (roots are the CLI paths)\n",
     "  - In text mode, --stats prints to stderr\n",
     "  - In --report mode, --max-total-bytes defaults to 256 MiB (268435456 bytes); override with --max-total-bytes\n",
+    "  - DUP_CODE_CHECK_<FLAG> env vars set defaults (e.g. DUP_CODE_CHECK_MAX_FILES=1000,\n",
+    "    DUP_CODE_CHECK_IGNORE_DIRS=vendor,gen, DUP_CODE_CHECK_STRICT=1); CLI flags win\n",
     "\n",
     "Examples:\n",
     "  dup-code-check .\n",
@@ -45,6 +219,18 @@ const HELP_TEXT_EN: &str = concat!(
     "  dup-code-check --code-spans --cross-repo-only /repoA /repoB\n",
     "  dup-code-check --report --cross-repo-only /repoA /repoB\n",
     "  dup-code-check --ignore-dir vendor --ignore-dir .venv .\n",
+    "  dup-code-check snapshot -o vendor.dcs /vendor\n",
+    "  dup-code-check --against-snapshot vendor.dcs --cross-repo-only .\n",
+    "  build-tool --emit-json | dup-code-check --stdin-json --cross-repo-only src\n",
+    "  dup-code-check snapshot --shard 1/4 -o shard1.partial .\n",
+    "  dup-code-check merge-shards shard*.partial\n",
+    "  dup-code-check explain 6300950429c7392d report.json\n",
+    "  dup-code-check genealogy before.dcs after.dcs\n",
+    "  dup-code-check html-diff v1.json v2.json -o diff.html\n",
+    "  dup-code-check self-diff --before v1.0.0 --after v1.1.0 .\n",
+    "  dup-code-check resurrect --repo . --depth 200\n",
+    "  dup-code-check cache gc --cache-dir .cache/dcc --max-cache-size 100000000\n",
+    "  dup-code-check --report --format json.gz . > report.json.gz\n",
     "\n"
 );
 
@@ -53,36 +239,169 @@ const HELP_TEXT_ZH: &str = concat!(
     "\n",
     "用法:\n",
     "  dup-code-check [options] [root ...]\n",
+    "  dup-code-check snapshot -o <file> [--shard <i/N>] [options] [root ...]\n",
+    "  dup-code-check merge-shards [options] <partial ...>\n",
+    "  dup-code-check explain <group-id> <report.json> [root ...]\n",
+    "  dup-code-check convert <input> <output>\n",
+    "  dup-code-check genealogy [options] <old.dcs> <new.dcs>\n",
+    "  dup-code-check html-diff <old.json> <new.json> [-o <file>]\n",
+    "  dup-code-check self-diff --before <ref> --after <ref> [options] [repo]\n",
+    "  dup-code-check resurrect --repo <path> [--depth <n>] [options]\n",
+    "  dup-code-check cache <status|clear|gc> --cache-dir <dir> [--max-cache-size <bytes>]\n",
+    "\n",
+    "命令:\n",
+    "  snapshot                将 root 的 token 化结果保存为快照文件（-o, --output）\n",
+    "  merge-shards            合并多个快照文件（如每个 --shard 一个）并输出报告\n",
+    "  explain                 重新推导已保存的 --report --json 输出中的一个重复组：\n",
+    "                          对齐后的出现位置及起决定作用的阈值\n",
+    "  convert                 重新编码已保存的 JSON 输出；编码由文件扩展名决定\n",
+    "                          （.json、.json.gz、.msgpack）\n",
+    "  genealogy               将两个快照中的每个克隆组分类为新增、移除、增多、减少、\n",
+    "                          移动或未变\n",
+    "  html-diff               将两份已保存的 --report --json 输出之间新增、移除或变化的\n",
+    "                          重复组渲染为 HTML 页面（-o, --output）\n",
+    "  self-diff               列出同一仓库两个 git ref 之间新增或增多的克隆组\n",
+    "                          （直接从 git 读取，无需 checkout）\n",
+    "  resurrect               报告与最近 --depth 个提交（默认: 1000）中被删除的文件重复的\n",
+    "                          现存代码（复制旧代码而非 revert）\n",
+    "  cache                   查看（status）、清空（clear）或清理（gc）--cache-dir 缓存\n",
     "\n",
     "选项:\n",
-    "  --localization <en|zh>  输出语言（默认: en）\n",
+    "  --localization <lang>   输出语言：en、zh、ja、de、es（默认: en）；ja、de、es 的帮助\n",
+    "                          及未翻译的消息回退为英文\n",
     "  --report                运行全部检测器并输出报告\n",
+    "  --preset <name>         预设起点：quick（文件 + 代码块，64 MiB 预算）、balanced\n",
+    "                          （精确检测器，256 MiB）或 thorough（全部检测器，无预算）\n",
+    "  --group-by <group|file> （报告模式）按重复组（默认）或按文件列出重复\n",
+    "  --aggregate ownership   （报告模式）按出现位置的 CODEOWNERS 归属把重复组分为\n",
+    "                          跨团队、团队内和无归属三类\n",
+    "  --codeowners <file>     （aggregate）所有根目录都使用该 CODEOWNERS 文件，而不是各根目录\n",
+    "                          .github/、根目录、docs/ 或 .gitlab/ 下的 CODEOWNERS\n",
+    "  --only <sections>       （报告模式）只输出这些 section，例如\n",
+    "                          file-duplicates,block-duplicates（支持 `*` 通配）\n",
+    "  --omit <sections>       （报告模式）不输出这些 section，例如 similar-*\n",
     "  --code-spans            查找疑似重复代码片段\n",
-    "  --json                  输出 JSON\n",
+    "  --emit-blocks           输出每个给定文件的花括号块树：行范围、嵌套层级，\n",
+    "                          以及块检测器比较的 token 数\n",
+    "  --against-snapshot <file>  （Report）同时与已保存的快照比对\n",
+    "  --stdin-json            （Report）同时扫描从 stdin 读取的文件，\n",
+    "                          格式为 [{\"path\": ..., \"content\": ...}]\n",
+    "  --shard <i/N>           （snapshot）只扫描第 i 个分片（共 N 个，从 1 开始，按路径哈希划分）\n",
+    "  --json                  输出 JSON（等同于 --format json）\n",
+    "  --format <name>         输出格式：text、json、jsonl、json.gz、msgpack（需 msgpack 特性）、\n",
+    "                          review-comments（报告模式）或外部格式化程序\n",
+    "                          （运行 PATH 中的 dup-code-check-format-<name>）\n",
+    "  --baseline <report.json>  （review-comments）跳过已在该 --report --json 输出中的出现位置\n",
+    "  --suppressions <file>   在到期日之前，从所有输出格式中移除 suppressions.toml 文件列出的重复组\n",
+    "  --color <when>          文本输出着色：auto、always、never（默认: auto）\n",
+    "  --hyperlinks            文本输出中的位置可点击（OSC 8 file:// 链接）\n",
+    "  --link-template <url>   在每个位置后输出代码托管平台 URL（文本、评审意见）；\n",
+    "                          支持 {repo}、{rev}（git HEAD）、{path}、{start}、{end}\n",
     "  --stats                 输出扫描统计（JSON 模式合并到输出；文本模式写 stderr）\n",
+    "  --progress-json         向 stderr 输出 NDJSON 进度事件\n",
+    "  --log-skips <file>      为每个被跳过的文件写一条 NDJSON 记录（路径、原因、大小）\n",
     "  --strict                若出现“致命跳过”（权限/遍历错误/预算中断/bucket 截断/无法相对化路径）则退出码非 0\n",
+    "  --strict-policy <list>  哪些致命跳过类别会让 --strict 失败（隐含 --strict）：\n",
+    "                          permission,paths,walk,budget,bucket（默认: 全部）\n",
     "  --cross-repo-only       仅输出跨 >= 2 个 root 的重复组\n",
+    "  --absolute-paths        输出绝对路径（默认为相对 root 的路径）\n",
+    "  --path-prefix-map <from>=<to>  将以 <from> 开头的输出路径改写为以 <to> 开头\n",
+    "                          （可重复；多个匹配时以最后一个为准）\n",
+    "  --exclude-tests         忽略测试代码（测试目录/文件、#[cfg(test)] 项）\n",
+    "  --tests-only            仅输出测试代码内部的重复\n",
     "  --no-gitignore          不尊重 .gitignore 规则\n",
+    "  --global-gitignore      在非 Git 仓库中也应用全局 Git 忽略规则（core.excludesFile）\n",
+    "  --file-listing <mode>   文件列举方式 auto|git|walker（默认: auto，即 git ls-files，\n",
+    "                          失败时回退到内置 walker）\n",
     "  --gitignore             启用 .gitignore 过滤（默认：开启）\n",
     "  --min-match-len <n>     code spans：最小归一化长度（默认: 50）\n",
     "  --min-token-len <n>     token 检测：最小 token 长度（默认: 50）\n",
-    "  --similarity-threshold <f>  相似度阈值：0..1（默认: 0.85）\n",
+    "  --language-scale <lang>=<f>  按语言缩放 --min-token-len，例如 java=1.5\n",
+    "                          （可重复；覆盖内置的按语言阈值表）\n",
+    "  --adaptive-window <n>   超过 16Ki 字符/token 的文件加宽指纹窗口，最多 n 倍；\n",
+    "                          仅保证找到长度达最小值 n 倍的匹配\n",
+    "  --minhash-threshold <f>  MinHash 最低相似度 0..1（默认: 0.85）\n",
+    "                          （别名: --similarity-threshold）\n",
+    "  --simhash-threshold <f>  SimHash 最低相似度 0..1（1 - 距离/64）\n",
     "  --simhash-max-distance <n>  SimHash 最大汉明距离（默认: 3）\n",
+    "  --shingle-size <n>      MinHash/SimHash 每个 shingle 的 token 数（默认: 5）\n",
+    "  --minhash-signature-size <n>  MinHash 签名长度（默认: 32）\n",
+    "  --minhash-bands <n>     MinHash LSH 分段数，必须整除签名长度（默认: 8）\n",
+    "  --similarity-window <n>  MinHash/SimHash 比较 n 个 token 的滑动窗口，而非代码块\n",
+    "  --similarity-stride <n>  相邻窗口起点间隔的 token 数（默认: 窗口的一半）\n",
+    "  --line-span-min-lines <n>  （report）行级片段每个匹配的最少行数（默认: 2）\n",
+    "  --line-span-window <n>  （report）行级片段的 winnowing 窗口行数（默认: 8）\n",
+    "  --symbol-min-count <n>  （report）公开符号重复至少共享的符号数（默认: 3）\n",
+    "  --import-min-count <n>  （report）import 重复：文件至少的 import 数（默认: 3）\n",
+    "  --import-similarity <f>  （report）import 重复：最低 Jaccard 0..1（默认: 0.8）\n",
+    "  --data-min-bytes <n>    （report）数据文件重复：JSON/YAML/CSV/XML 文件的最小字节数（默认: 1024）\n",
+    "  --min-entropy <bits>    （report）丢弃 token 熵低于该值的片段重复组，例如成串的括号\n",
+    "                          （默认: 0，全部保留）\n",
+    "  --unicode-normalize <nfc|nfkc>  比较代码前先做 Unicode 归一化（默认: 关闭）\n",
+    "  --sort <order>          （报告模式）section 排序：occurrences、size、score、path\n",
+    "                          （默认: occurrences）\n",
+    "  --preview-chars <n>     预览最大字符数（默认: code spans 为 80，其他为 120）\n",
+    "  --context <n>           预览中包含片段前后各 n 行（默认: 0）\n",
+    "  --similarity-diff       （Report）为每个相似对附带行级 diff\n",
+    "  --file-coverage         （Report）输出每个文件的重复行区间\n",
+    "  --clusters              （Report）将相关的重复组聚类为重构任务\n",
+    "  --similarity-clusters   （Report）将共享同一代码块的相似对合并为簇\n",
+    "  --occurrence-ages       （Report）用 git log -L 标注各出现位置的修改时间，已分化的副本优先\n",
+    "  --occurrence-sources    在 JSON 输出的每个出现位置上附加所属 root 的 git origin URL 与 commit\n",
+    "  --license-conflicts     （Report）标记文件许可证声明不一致的跨仓库重复组\n",
+    "  --suggest-excludes      （Report）为报告中的第三方代码、测试数据和生成代码输出忽略模式，\n",
+    "                          并估计每个模式可去掉的重复组数（输出到 stderr）\n",
+    "  --idioms <k>            （Report）列出至少被复制到 k 个文件中的短 token 序列\n",
+    "  --idiom-token-len <n>   （Report）常见惯用片段的最少 token 数（默认: 12）\n",
+    "  --file-similarity-matrix <file>  （Report）将文件两两相似度（MinHash）写入 CSV\n",
+    "  --cache-dir <dir>       （Report）在被扫描的文件均未改动时，复用这些 root 与选项的上次报告\n",
+    "  --max-cache-size <bytes>  （Report、cache gc）超出该大小时淘汰最久未使用的 --cache-dir 条目\n",
+    "  --dedupe-detectors      （Report）合并多个检测器报告的相同重复组\n",
+    "  --no-prune-contained    （Report）保留完全落在更长重复组出现位置内的重复组\n",
     "  --max-report-items <n>  每个报告 section 的最大条目数（默认: 200）\n",
+    "  --max-items <section>=<n>  （Report）单独限制某个 section，例如\n",
+    "                          similarBlocksMinhash=50（可重复；使用 JSON section 名）\n",
+    "  --section-offset <n>    （report）跳过每个 section 的前 n 条（默认: 0）\n",
+    "  --section-limit <n>     （report）offset 之后每个 section 输出的条目数\n",
+    "                          （默认: --max-report-items）\n",
+    "  --min-occurrences <n>   仅输出出现次数 >= n 的重复组（默认: 2）\n",
+    "  --max-occurrences-per-group <n>  每组最多列出 n 个出现位置\n",
+    "  --filter-path <glob>    （Report）仅保留有出现位置匹配 gitignore 风格模式的重复组\n",
+    "                          （可重复）\n",
+    "  --filter-path-trim      （Report）同时去掉不匹配的出现位置\n",
     "  --max-files <n>         最多扫描 n 个文件\n",
+    "  --sample <fraction|n>   只扫描确定性抽样的文件（按路径哈希）\n",
     "  --max-total-bytes <n>   跳过会导致累计扫描字节数超出预算的文件\n",
+    "  --budget-order <order>  预算保留哪些文件：walk|smallest|largest|path（默认: walk）\n",
     "  --max-file-size <n>     跳过大于 n 字节的文件（默认: 10485760）\n",
     "  --max-normalized-chars <n>  最多保存 n 个归一化后的 code-span 字符\n",
     "  --max-tokens <n>        （Report）最多保存 n 个 token\n",
+    "  --max-tokens-per-file <n>  （Report）token 类检测器每个文件最多使用 n 个 token\n",
+    "  --max-groups-per-detector <n>  片段检测器超过 n 个重复组后只计数，并将该 section 标记为\n",
+    "                          degraded（默认: 1000000；0 = 不限制）\n",
+    "  --max-file-time <ms>    单个文件的规范化/分词超过该毫秒数即放弃该文件\n",
+    "  --threads <n>           （Report）检测器工作线程数（默认: 0 = 每个 CPU 一个）\n",
     "  --ignore-dir <name>     忽略目录名（可重复）\n",
+    "  --exclude <glob>        跳过匹配 gitignore 风格模式的文件（可重复）\n",
+    "  --import-ignores <file> 导入 jscpd 配置（*.json）或 .cpdignore 中的排除规则\n",
+    "  --skip-snippet <file>   将文件中的代码视为样板，与内置跳过列表一样不作为片段匹配的起点\n",
+    "                          （可重复）\n",
+    "  --no-builtin-skips      不跳过内置样板（许可证头、生成代码标记、serde 实现、\n",
+    "                          getter/setter）\n",
+    "  --no-default-excludes   同时扫描锁文件与常见生成文件\n",
+    "                          （Cargo.lock、yarn.lock、*.min.js、*.pb.go、*_generated.* 等）\n",
+    "  --stop-sequences <file> （Report）按语言的惯用写法（`<lang>: <code>` 行），不作为 token\n",
+    "                          片段匹配的起点，替换内置列表（可重复）\n",
     "  --follow-symlinks       跟随符号链接（仅限 root 内；默认: 关闭）\n",
-    "  -V, --version           显示版本\n",
+    "  -V, --version           显示版本（配合 --json：以 JSON 输出版本与支持的能力）\n",
     "  -h, --help              显示帮助\n",
     "\n",
     "说明:\n",
     "  - --cross-repo-only 需要 2+ 个 root（root 即命令行路径）\n",
     "  - 文本模式下 --stats 输出到 stderr\n",
     "  - 在 --report 模式下，--max-total-bytes 默认 256 MiB（268435456 bytes），可用 --max-total-bytes 覆盖\n",
+    "  - 环境变量 DUP_CODE_CHECK_<FLAG> 可设置默认值（如 DUP_CODE_CHECK_MAX_FILES=1000、\n",
+    "    DUP_CODE_CHECK_IGNORE_DIRS=vendor,gen、DUP_CODE_CHECK_STRICT=1）；CLI 参数优先\n",
     "\n",
     "示例:\n",
     "  dup-code-check .\n",
@@ -90,50 +409,124 @@ const HELP_TEXT_ZH: &str = concat!(
     "  dup-code-check --code-spans --cross-repo-only /repoA /repoB\n",
     "  dup-code-check --report --cross-repo-only /repoA /repoB\n",
     "  dup-code-check --ignore-dir vendor --ignore-dir .venv .\n",
+    "  dup-code-check snapshot -o vendor.dcs /vendor\n",
+    "  dup-code-check --against-snapshot vendor.dcs --cross-repo-only .\n",
+    "  build-tool --emit-json | dup-code-check --stdin-json --cross-repo-only src\n",
+    "  dup-code-check snapshot --shard 1/4 -o shard1.partial .\n",
+    "  dup-code-check merge-shards shard*.partial\n",
+    "  dup-code-check explain 6300950429c7392d report.json\n",
+    "  dup-code-check genealogy before.dcs after.dcs\n",
+    "  dup-code-check html-diff v1.json v2.json -o diff.html\n",
+    "  dup-code-check cache gc --cache-dir .cache/dcc --max-cache-size 100000000\n",
+    "  dup-code-check --report --format json.gz . > report.json.gz\n",
     "\n"
 );
 
+/// Help is written in English and Chinese; the other locales fall back to English.
+pub(crate) fn print_help(localization: Localization) {
+    let help = localization
+        .fallbacks()
+        .iter()
+        .find_map(|locale| match locale {
+            Localization::En => Some(HELP_TEXT_EN),
+            Localization::Zh => Some(HELP_TEXT_ZH),
+            _ => None,
+        });
+    print!("{}", help.unwrap_or(HELP_TEXT_EN));
+}
+
+/// `cache` subcommand action.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) enum Localization {
-    En,
-    Zh,
+pub(crate) enum CacheCommand {
+    Status,
+    Clear,
+    Gc,
 }
 
-impl Localization {
+impl CacheCommand {
     fn parse(raw: &str) -> Option<Self> {
-        match raw.trim().to_ascii_lowercase().as_str() {
-            "en" | "en-us" | "en_us" => Some(Self::En),
-            "zh" | "zh-cn" | "zh_cn" | "cn" => Some(Self::Zh),
+        match raw {
+            "status" => Some(Self::Status),
+            "clear" => Some(Self::Clear),
+            "gc" => Some(Self::Gc),
             _ => None,
         }
     }
 }
 
-pub(crate) fn tr(localization: Localization, en: &'static str, zh: &'static str) -> &'static str {
-    match localization {
-        Localization::En => en,
-        Localization::Zh => zh,
-    }
-}
-
-pub(crate) fn print_help(localization: Localization) {
-    print!(
-        "{}",
-        match localization {
-            Localization::En => HELP_TEXT_EN,
-            Localization::Zh => HELP_TEXT_ZH,
-        }
-    );
-}
-
 #[derive(Debug, Clone)]
 pub(crate) struct ParsedArgs {
     pub(crate) localization: Localization,
     pub(crate) json: bool,
+    /// Output format name (`text`, `json`, or an external formatter).
+    pub(crate) format: String,
+    pub(crate) color: ColorChoice,
+    /// `--hyperlinks`: wrap text output locations in OSC 8 `file://` links.
+    pub(crate) hyperlinks: bool,
+    /// `--link-template`: URL template for code-hosting links to each occurrence.
+    pub(crate) link_template: Option<String>,
+    /// `--occurrence-sources`: attach each root's git remote and commit to its occurrences.
+    pub(crate) occurrence_sources: bool,
+    /// `--suggest-excludes`: print ignore patterns for low-value places of the report.
+    pub(crate) suggest_excludes: bool,
+    /// `--emit-blocks`: print the brace block tree of each file in `roots` instead of scanning.
+    pub(crate) emit_blocks: bool,
     pub(crate) stats: bool,
+    pub(crate) progress_json: bool,
+    /// `--log-skips`: NDJSON file listing every skipped file.
+    pub(crate) log_skips: Option<PathBuf>,
     pub(crate) strict: bool,
+    /// Fatal-skip categories that fail `--strict`.
+    pub(crate) strict_policy: StrictPolicy,
     pub(crate) report: bool,
+    /// `--group-by file`: list the report per file instead of per group.
+    pub(crate) group_by_file: bool,
+    /// `--aggregate ownership`: split the report by CODEOWNERS owners.
+    pub(crate) aggregate_ownership: bool,
+    /// `--codeowners`: CODEOWNERS file used for every root.
+    pub(crate) codeowners: Option<PathBuf>,
+    /// `--only` / `--omit`: report sections left out of the output.
+    pub(crate) section_filter: SectionFilter,
     pub(crate) code_spans: bool,
+    /// `snapshot` subcommand: where to write the snapshot.
+    pub(crate) snapshot_output: Option<PathBuf>,
+    pub(crate) against_snapshot: Option<PathBuf>,
+    /// `--stdin-json`: also scan `[{"path", "content"}]` files read from stdin.
+    pub(crate) stdin_json: bool,
+    /// `--baseline`: saved report whose occurrences `review-comments` skips.
+    pub(crate) baseline: Option<PathBuf>,
+    /// `--suppressions`: file of groups removed from the output until they expire.
+    pub(crate) suppressions: Option<PathBuf>,
+    /// `--file-similarity-matrix`: CSV file for `DuplicationReport::file_similarity`.
+    pub(crate) file_similarity_matrix: Option<PathBuf>,
+    /// `--cache-dir`: where report results are cached by corpus and options.
+    pub(crate) cache_dir: Option<PathBuf>,
+    /// `--max-cache-size`: byte limit `--cache-dir` is pruned to after each store and by
+    /// `cache gc`.
+    pub(crate) max_cache_size: Option<u64>,
+    /// `cache` subcommand: the action to run on `cache_dir`.
+    pub(crate) cache_command: Option<CacheCommand>,
+    /// `merge-shards` subcommand: `roots` holds the snapshot files to merge.
+    pub(crate) merge_shards: bool,
+    /// `explain` subcommand: group id and saved report. `roots`, when given, replace the
+    /// report's roots (by repo id).
+    pub(crate) explain: Option<(String, PathBuf)>,
+    /// `convert` subcommand: input and output files.
+    pub(crate) convert: Option<(PathBuf, PathBuf)>,
+    /// `genealogy` subcommand: old and new snapshot files.
+    pub(crate) genealogy: Option<(PathBuf, PathBuf)>,
+    /// `html-diff` subcommand: old and new reports, and the `-o` file (stdout if absent).
+    pub(crate) html_diff: Option<(PathBuf, PathBuf, Option<PathBuf>)>,
+    /// `self-diff` subcommand: the `--before` and `--after` git refs of the one root.
+    pub(crate) self_diff: Option<(String, String)>,
+    /// `resurrect` subcommand: how many commits back the one root's deleted files are read.
+    pub(crate) resurrect: Option<usize>,
+    /// `--import-ignores` files, read into `options.ignore_patterns` before scanning.
+    pub(crate) import_ignores: Vec<PathBuf>,
+    /// `--skip-snippet` files, read into `options.skip_snippets` before scanning.
+    pub(crate) skip_snippets: Vec<PathBuf>,
+    /// `--stop-sequences` files, read into `options.stop_sequences` before scanning.
+    pub(crate) stop_sequences: Vec<PathBuf>,
     pub(crate) roots: Vec<PathBuf>,
     pub(crate) options: ScanOptions,
 }
@@ -202,6 +595,24 @@ fn parse_f64(localization: Localization, name: &str, raw: &str) -> Result<f64, S
     })
 }
 
+/// Parse a similarity in `0..=1`.
+fn parse_unit_interval(localization: Localization, name: &str, raw: &str) -> Result<f64, String> {
+    let value = parse_f64(localization, name, raw)?;
+    if !value.is_finite() || !(0.0..=1.0).contains(&value) {
+        return Err(format!(
+            "{name} {}",
+            tr(localization, "must be 0..1", "必须在 0..1 范围内")
+        ));
+    }
+    Ok(value)
+}
+
+/// The largest SimHash Hamming distance whose score (`1 - distance / 64`) is still `>= threshold`.
+fn simhash_distance_for_threshold(threshold: f64) -> u32 {
+    // The epsilon keeps e.g. 0.75 (exactly 16 bits) from rounding down to 15.
+    ((1.0 - threshold) * 64.0 + 1e-9).floor() as u32
+}
+
 pub(crate) fn detect_localization(argv: &[String]) -> Result<Localization, String> {
     let mut localization = Localization::En;
 
@@ -213,16 +624,18 @@ pub(crate) fn detect_localization(argv: &[String]) -> Result<Localization, Strin
         }
 
         if let Some(raw) = arg.strip_prefix("--localization=") {
-            localization = Localization::parse(raw)
-                .ok_or_else(|| "--localization must be one of: en, zh (or zh-CN)".to_string())?;
+            localization = Localization::parse(raw).ok_or_else(|| {
+                "--localization must be one of: en, zh (or zh-CN), ja, de, es".to_string()
+            })?;
             i += 1;
             continue;
         }
 
         if arg == "--localization" {
             let raw = argv.get(i + 1).ok_or("--localization requires a value")?;
-            localization = Localization::parse(raw)
-                .ok_or_else(|| "--localization must be one of: en, zh (or zh-CN)".to_string())?;
+            localization = Localization::parse(raw).ok_or_else(|| {
+                "--localization must be one of: en, zh (or zh-CN), ja, de, es".to_string()
+            })?;
             i += 2;
             continue;
         }
@@ -239,26 +652,117 @@ pub(crate) fn parse_args(
 ) -> Result<ParsedArgs, String> {
     let mut roots: Vec<PathBuf> = Vec::new();
     let mut ignore_dirs: Vec<String> = Vec::new();
+    let mut ignore_patterns: Vec<String> = Vec::new();
+    let mut import_ignores: Vec<PathBuf> = Vec::new();
+    let mut skip_snippets: Vec<PathBuf> = Vec::new();
+    let mut stop_sequences: Vec<PathBuf> = Vec::new();
+    let mut builtin_skips = true;
+    let mut default_excludes = true;
     let mut report = false;
     let mut code_spans = false;
     let mut json = false;
+    let mut format: Option<String> = None;
     let mut stats = false;
+    let mut progress_json = false;
+    let mut color = ColorChoice::default();
+    let mut hyperlinks = false;
+    let mut occurrence_sources = false;
+    let mut suggest_excludes = false;
+    let mut emit_blocks = false;
+    let mut link_template: Option<String> = None;
+    let mut group_by_file = false;
+    let mut aggregate_ownership = false;
+    let mut codeowners: Option<PathBuf> = None;
+    let mut section_filter = SectionFilter::default();
+    let mut preset: Option<Preset> = None;
     let mut strict = false;
+    let mut strict_policy = StrictPolicy::default();
     let mut cross_repo_only = false;
+    let mut path_filter: Vec<String> = Vec::new();
+    let mut path_filter_trim = false;
     let mut respect_gitignore = true;
+    let mut use_global_gitignore = false;
+    let mut file_listing = FileListing::Auto;
     let mut follow_symlinks = false;
+    let mut similarity_diff = false;
+    let mut file_coverage = false;
+    let mut clone_clusters = false;
+    let mut similarity_clusters = false;
+    let mut occurrence_ages = false;
+    let mut license_conflicts = false;
+    let mut dedupe_detectors = false;
+    let mut prune_contained = true;
+    let mut absolute_paths = false;
+    let mut path_prefix_map: Vec<(PathBuf, PathBuf)> = Vec::new();
     let mut max_file_size: Option<u64> = None;
     let mut max_files: Option<usize> = None;
     let mut max_total_bytes: Option<u64> = None;
+    let mut budget_order: Option<BudgetOrder> = None;
     let mut max_normalized_chars: Option<usize> = None;
     let mut max_tokens: Option<usize> = None;
+    let mut max_tokens_per_file: Option<usize> = None;
+    let mut max_groups_per_detector: Option<usize> = None;
+    let mut max_file_time: Option<u64> = None;
+    let mut threads: Option<usize> = None;
+    let mut report_sort: Option<ReportSort> = None;
+    let mut language_token_scales: Vec<(String, f64)> = Vec::new();
+    let mut preview_max_chars: Option<usize> = None;
+    let mut preview_context_lines: Option<usize> = None;
     let mut min_match_len: Option<usize> = None;
     let mut min_token_len: Option<usize> = None;
+    let mut adaptive_window: Option<usize> = None;
     let mut similarity_threshold: Option<f64> = None;
     let mut simhash_max_distance: Option<u32> = None;
+    let mut simhash_threshold: Option<f64> = None;
+    let mut shingle_size: Option<usize> = None;
+    let mut minhash_signature_size: Option<usize> = None;
+    let mut minhash_bands: Option<usize> = None;
+    let mut similarity_window: Option<usize> = None;
+    let mut similarity_stride: Option<usize> = None;
+    let mut line_span_min_lines: Option<usize> = None;
+    let mut line_span_window: Option<usize> = None;
+    let mut symbol_min_count: Option<usize> = None;
+    let mut idiom_min_files: Option<usize> = None;
+    let mut idiom_token_len: Option<usize> = None;
+    let mut import_min_count: Option<usize> = None;
+    let mut data_min_bytes: Option<u64> = None;
+    let mut min_entropy: Option<f64> = None;
+    let mut import_similarity: Option<f64> = None;
     let mut max_report_items: Option<usize> = None;
+    let mut max_items_per_detector: HashMap<ReportSection, usize> = HashMap::new();
+    let mut section_offset: Option<usize> = None;
+    let mut section_limit: Option<usize> = None;
+    let mut min_occurrences: Option<usize> = None;
+    let mut max_occurrences_per_group: Option<usize> = None;
+    let command = subcommand(argv);
+    let snapshot = command == Some("snapshot");
+    let merge_shards = command == Some("merge-shards");
+    let explain_command = command == Some("explain");
+    let convert_command = command == Some("convert");
+    let genealogy_command = command == Some("genealogy");
+    let html_diff_command = command == Some("html-diff");
+    let self_diff_command = command == Some("self-diff");
+    let resurrect_command = command == Some("resurrect");
+    let cache_subcommand = command == Some("cache");
+    let mut before_ref: Option<String> = None;
+    let mut after_ref: Option<String> = None;
+    let mut resurrect_repo: Option<PathBuf> = None;
+    let mut history_depth: Option<usize> = None;
+    let mut snapshot_output: Option<PathBuf> = None;
+    let mut against_snapshot: Option<PathBuf> = None;
+    let mut stdin_json = false;
+    let mut baseline: Option<PathBuf> = None;
+    let mut suppressions: Option<PathBuf> = None;
+    let mut file_similarity_matrix: Option<PathBuf> = None;
+    let mut cache_dir: Option<PathBuf> = None;
+    let mut max_cache_size: Option<u64> = None;
+    let mut log_skips: Option<PathBuf> = None;
+    let mut shard: Option<ScanShard> = None;
+    let mut sample: Option<ScanSample> = None;
+    let mut unicode_normalization: Option<UnicodeNormalization> = None;
+    let mut test_code_filter: Option<TestCodeFilter> = None;
 
-    let mut i = 0;
+    let mut i = usize::from(command.is_some());
     while i < argv.len() {
         let arg = &argv[i];
         if arg == "--" {
@@ -286,6 +790,11 @@ pub(crate) fn parse_args(
             i += 1;
             continue;
         }
+        if arg == "--stdin-json" {
+            stdin_json = true;
+            i += 1;
+            continue;
+        }
         if arg == "--code-spans" {
             code_spans = true;
             i += 1;
@@ -296,21 +805,306 @@ pub(crate) fn parse_args(
             i += 1;
             continue;
         }
+        if arg == "--format" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--format requires a value",
+                    "--format 需要一个值",
+                )
+                .to_string()
+            })?;
+            // Built-in names may contain dots (`json.gz`); external formatter names may not.
+            let valid = Encoding::from_format(value).is_some()
+                || !value.is_empty()
+                    && value
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(format!(
+                    "--format {} {value}",
+                    tr(
+                        localization,
+                        "must be a name of [A-Za-z0-9_-] characters, got",
+                        "必须由 [A-Za-z0-9_-] 字符组成，实际为",
+                    ),
+                ));
+            }
+            format = Some(value.to_string());
+            i += 2;
+            continue;
+        }
         if arg == "--stats" {
             stats = true;
             i += 1;
             continue;
         }
+        if arg == "--progress-json" {
+            progress_json = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--color" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--color requires a value",
+                    "--color 需要一个值",
+                )
+                .to_string()
+            })?;
+            color = ColorChoice::parse(raw).ok_or_else(|| {
+                format!(
+                    "--color {} {raw}",
+                    tr(
+                        localization,
+                        "must be always, never or auto, got",
+                        "必须是 always、never 或 auto，实际为",
+                    ),
+                )
+            })?;
+            i += 2;
+            continue;
+        }
+        if arg == "--hyperlinks" {
+            hyperlinks = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--occurrence-sources" {
+            occurrence_sources = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--suggest-excludes" {
+            suggest_excludes = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--emit-blocks" {
+            emit_blocks = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--link-template" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--link-template requires a value",
+                    "--link-template 需要一个值",
+                )
+                .to_string()
+            })?;
+            validate_link_template(localization, value)?;
+            link_template = Some(value.clone());
+            i += 2;
+            continue;
+        }
+        if arg == "--only" || arg == "--omit" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                format!(
+                    "{arg} {}",
+                    tr(localization, "requires a value", "需要一个值")
+                )
+            })?;
+            section_filter.add(localization, arg, raw, arg == "--only")?;
+            i += 2;
+            continue;
+        }
+        if arg == "--preset" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--preset requires a value",
+                    "--preset 需要一个值",
+                )
+                .to_string()
+            })?;
+            preset = Some(Preset::parse(raw).ok_or_else(|| {
+                format!(
+                    "--preset {} {raw}",
+                    tr(
+                        localization,
+                        "must be quick, balanced or thorough, got",
+                        "必须是 quick、balanced 或 thorough，实际为",
+                    ),
+                )
+            })?);
+            i += 2;
+            continue;
+        }
+        if arg == "--group-by" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--group-by requires a value",
+                    "--group-by 需要一个值",
+                )
+                .to_string()
+            })?;
+            group_by_file = match raw.as_str() {
+                "group" => false,
+                "file" => true,
+                _ => {
+                    return Err(format!(
+                        "--group-by {} {raw}",
+                        tr(
+                            localization,
+                            "must be group or file, got",
+                            "必须是 group 或 file，实际为",
+                        ),
+                    ));
+                }
+            };
+            i += 2;
+            continue;
+        }
+        if arg == "--aggregate" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--aggregate requires a value",
+                    "--aggregate 需要一个值",
+                )
+                .to_string()
+            })?;
+            if raw != "ownership" {
+                return Err(format!(
+                    "--aggregate {} {raw}",
+                    tr(
+                        localization,
+                        "must be ownership, got",
+                        "必须是 ownership，实际为",
+                    ),
+                ));
+            }
+            aggregate_ownership = true;
+            i += 2;
+            continue;
+        }
+        if arg == "--codeowners" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--codeowners requires a value",
+                    "--codeowners 需要一个值",
+                )
+                .to_string()
+            })?;
+            codeowners = Some(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
         if arg == "--strict" {
             strict = true;
             i += 1;
             continue;
         }
+        if arg == "--strict-policy" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--strict-policy requires a value",
+                    "--strict-policy 需要一个值",
+                )
+                .to_string()
+            })?;
+            strict_policy = StrictPolicy::parse(localization, raw)?;
+            strict = true;
+            i += 2;
+            continue;
+        }
         if arg == "--cross-repo-only" {
             cross_repo_only = true;
             i += 1;
             continue;
         }
+        if arg == "--filter-path" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--filter-path requires a value",
+                    "--filter-path 需要一个值",
+                )
+                .to_string()
+            })?;
+            path_filter.push(value.to_string());
+            i += 2;
+            continue;
+        }
+        if arg == "--filter-path-trim" {
+            path_filter_trim = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--no-builtin-skips" {
+            builtin_skips = false;
+            i += 1;
+            continue;
+        }
+        if arg == "--no-default-excludes" {
+            default_excludes = false;
+            i += 1;
+            continue;
+        }
+        if arg == "--skip-snippet" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--skip-snippet requires a value",
+                    "--skip-snippet 需要一个值",
+                )
+                .to_string()
+            })?;
+            skip_snippets.push(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--stop-sequences" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--stop-sequences requires a value",
+                    "--stop-sequences 需要一个值",
+                )
+                .to_string()
+            })?;
+            stop_sequences.push(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--global-gitignore" {
+            use_global_gitignore = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--file-listing" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--file-listing requires a value",
+                    "--file-listing 需要一个值",
+                )
+                .to_string()
+            })?;
+            file_listing = match raw.as_str() {
+                "auto" => FileListing::Auto,
+                "git" => FileListing::GitCli,
+                "walker" => FileListing::Walker,
+                _ => {
+                    return Err(format!(
+                        "--file-listing {} {raw}",
+                        tr(
+                            localization,
+                            "must be auto, git or walker, got",
+                            "必须是 auto、git 或 walker，实际为",
+                        ),
+                    ));
+                }
+            };
+            i += 2;
+            continue;
+        }
         if arg == "--no-gitignore" {
             respect_gitignore = false;
             i += 1;
@@ -326,6 +1120,92 @@ pub(crate) fn parse_args(
             i += 1;
             continue;
         }
+        if arg == "--similarity-diff" {
+            similarity_diff = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--file-coverage" {
+            file_coverage = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--clusters" {
+            clone_clusters = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--similarity-clusters" {
+            similarity_clusters = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--occurrence-ages" {
+            occurrence_ages = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--license-conflicts" {
+            license_conflicts = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--dedupe-detectors" {
+            dedupe_detectors = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--no-prune-contained" {
+            prune_contained = false;
+            i += 1;
+            continue;
+        }
+        if arg == "--absolute-paths" {
+            absolute_paths = true;
+            i += 1;
+            continue;
+        }
+        if arg == "--path-prefix-map" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--path-prefix-map requires a value",
+                    "--path-prefix-map 需要一个值",
+                )
+                .to_string()
+            })?;
+            let Some((from, to)) = raw.split_once('=').filter(|(from, _)| !from.is_empty()) else {
+                return Err(format!(
+                    "--path-prefix-map {} {raw}",
+                    tr(
+                        localization,
+                        "must be <from>=<to> with a non-empty <from>, got",
+                        "必须是 <from>=<to> 且 <from> 不能为空，实际为",
+                    ),
+                ));
+            };
+            path_prefix_map.push((PathBuf::from(from), PathBuf::from(to)));
+            i += 2;
+            continue;
+        }
+        if arg == "--exclude-tests" || arg == "--tests-only" {
+            let filter = if arg == "--exclude-tests" {
+                TestCodeFilter::ExcludeTests
+            } else {
+                TestCodeFilter::TestsOnly
+            };
+            if test_code_filter.is_some_and(|existing| existing != filter) {
+                return Err(tr(
+                    localization,
+                    "--exclude-tests conflicts with --tests-only",
+                    "--exclude-tests 与 --tests-only 不能同时使用",
+                )
+                .to_string());
+            }
+            test_code_filter = Some(filter);
+            i += 1;
+            continue;
+        }
         if arg == "--max-files" {
             let raw = argv.get(i + 1).ok_or_else(|| {
                 tr(
@@ -361,6 +1241,34 @@ pub(crate) fn parse_args(
             i += 2;
             continue;
         }
+        if arg == "--budget-order" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--budget-order requires a value",
+                    "--budget-order 需要一个值",
+                )
+                .to_string()
+            })?;
+            budget_order = Some(match raw.as_str() {
+                "walk" => BudgetOrder::WalkOrder,
+                "smallest" => BudgetOrder::SmallestFirst,
+                "largest" => BudgetOrder::LargestFirst,
+                "path" => BudgetOrder::PathSorted,
+                _ => {
+                    return Err(format!(
+                        "--budget-order {} {raw}",
+                        tr(
+                            localization,
+                            "must be walk, smallest, largest or path, got",
+                            "必须是 walk、smallest、largest 或 path，实际为",
+                        ),
+                    ));
+                }
+            });
+            i += 2;
+            continue;
+        }
         if arg == "--max-normalized-chars" {
             let raw = argv.get(i + 1).ok_or_else(|| {
                 tr(
@@ -403,71 +1311,428 @@ pub(crate) fn parse_args(
             i += 2;
             continue;
         }
-        if arg == "--max-file-size" {
+        if arg == "--max-tokens-per-file" {
             let raw = argv.get(i + 1).ok_or_else(|| {
                 tr(
                     localization,
-                    "--max-file-size requires a value",
-                    "--max-file-size 需要一个值",
+                    "--max-tokens-per-file requires a value",
+                    "--max-tokens-per-file 需要一个值",
                 )
                 .to_string()
             })?;
-            let value = parse_u64_non_negative_safe(localization, "--max-file-size", raw)?;
-            max_file_size = Some(value);
+            let value =
+                parse_u32_in_range(localization, "--max-tokens-per-file", raw, 1, u32::MAX)?;
+            max_tokens_per_file = Some(value as usize);
             i += 2;
             continue;
         }
-        if arg == "--min-match-len" {
+        if arg == "--max-groups-per-detector" {
             let raw = argv.get(i + 1).ok_or_else(|| {
                 tr(
                     localization,
-                    "--min-match-len requires a value",
-                    "--min-match-len 需要一个值",
+                    "--max-groups-per-detector requires a value",
+                    "--max-groups-per-detector 需要一个值",
                 )
                 .to_string()
             })?;
-            let value = parse_u32_in_range(localization, "--min-match-len", raw, 1, u32::MAX)?;
-            min_match_len = Some(value as usize);
+            let value =
+                parse_u32_in_range(localization, "--max-groups-per-detector", raw, 0, u32::MAX)?;
+            max_groups_per_detector = Some(value as usize);
             i += 2;
             continue;
         }
-        if arg == "--min-token-len" {
+        if arg == "--max-file-time" {
             let raw = argv.get(i + 1).ok_or_else(|| {
                 tr(
                     localization,
-                    "--min-token-len requires a value",
-                    "--min-token-len 需要一个值",
+                    "--max-file-time requires a value",
+                    "--max-file-time 需要一个值",
                 )
                 .to_string()
             })?;
-            let value = parse_u32_in_range(localization, "--min-token-len", raw, 1, u32::MAX)?;
-            min_token_len = Some(value as usize);
+            let value = parse_u32_in_range(localization, "--max-file-time", raw, 1, u32::MAX)?;
+            max_file_time = Some(u64::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--sort" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(localization, "--sort requires a value", "--sort 需要一个值").to_string()
+            })?;
+            report_sort = Some(match raw.as_str() {
+                "occurrences" => ReportSort::Occurrences,
+                "size" => ReportSort::Size,
+                "score" => ReportSort::Score,
+                "path" => ReportSort::Path,
+                _ => {
+                    return Err(format!(
+                        "--sort {} {raw}",
+                        tr(
+                            localization,
+                            "must be occurrences, size, score or path, got",
+                            "必须是 occurrences、size、score 或 path，实际为",
+                        ),
+                    ));
+                }
+            });
             i += 2;
             continue;
         }
-        if arg == "--similarity-threshold" {
+        if arg == "--language-scale" {
             let raw = argv.get(i + 1).ok_or_else(|| {
                 tr(
                     localization,
-                    "--similarity-threshold requires a value",
-                    "--similarity-threshold 需要一个值",
+                    "--language-scale requires a value",
+                    "--language-scale 需要一个值",
                 )
                 .to_string()
             })?;
-            let value = parse_f64(localization, "--similarity-threshold", raw)?;
-            if !value.is_finite() || !(0.0..=1.0).contains(&value) {
+            let Some((language, scale)) = raw.split_once('=') else {
+                return Err(format!(
+                    "--language-scale {} {raw}",
+                    tr(
+                        localization,
+                        "must be <language>=<factor>, got",
+                        "必须是 <language>=<factor>，实际为",
+                    ),
+                ));
+            };
+            let scale = parse_f64(localization, "--language-scale", scale)?;
+            if !scale.is_finite() || scale <= 0.0 {
                 return Err(tr(
                     localization,
-                    "--similarity-threshold must be 0..1",
-                    "--similarity-threshold 必须在 0..1 范围内",
+                    "--language-scale factor must be > 0",
+                    "--language-scale 的系数必须 > 0",
                 )
                 .to_string());
             }
-            similarity_threshold = Some(value);
+            language_token_scales.push((language.trim().to_ascii_lowercase(), scale));
             i += 2;
             continue;
         }
-        if arg == "--simhash-max-distance" {
+        if arg == "--preview-chars" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--preview-chars requires a value",
+                    "--preview-chars 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--preview-chars", raw, 0, u32::MAX)?;
+            preview_max_chars = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--context" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--context requires a value",
+                    "--context 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--context", raw, 0, u32::MAX)?;
+            preview_context_lines = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--threads" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--threads requires a value",
+                    "--threads 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--threads", raw, 0, u32::MAX)?;
+            threads = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--max-file-size" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--max-file-size requires a value",
+                    "--max-file-size 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u64_non_negative_safe(localization, "--max-file-size", raw)?;
+            max_file_size = Some(value);
+            i += 2;
+            continue;
+        }
+        if arg == "--min-match-len" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--min-match-len requires a value",
+                    "--min-match-len 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--min-match-len", raw, 1, u32::MAX)?;
+            min_match_len = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--min-token-len" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--min-token-len requires a value",
+                    "--min-token-len 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--min-token-len", raw, 1, u32::MAX)?;
+            min_token_len = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--adaptive-window" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--adaptive-window requires a value",
+                    "--adaptive-window 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--adaptive-window", raw, 2, 1024)?;
+            adaptive_window = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--similarity-threshold" || arg == "--minhash-threshold" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                format!(
+                    "{arg} {}",
+                    tr(localization, "requires a value", "需要一个值")
+                )
+            })?;
+            similarity_threshold = Some(parse_unit_interval(localization, arg, raw)?);
+            i += 2;
+            continue;
+        }
+        if arg == "--shingle-size" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--shingle-size requires a value",
+                    "--shingle-size 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--shingle-size", raw, 1, 1024)?;
+            shingle_size = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--minhash-signature-size" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--minhash-signature-size requires a value",
+                    "--minhash-signature-size 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--minhash-signature-size", raw, 1, 1024)?;
+            minhash_signature_size = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--minhash-bands" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--minhash-bands requires a value",
+                    "--minhash-bands 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--minhash-bands", raw, 1, 1024)?;
+            minhash_bands = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--similarity-window" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--similarity-window requires a value",
+                    "--similarity-window 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--similarity-window", raw, 1, 1 << 20)?;
+            similarity_window = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--similarity-stride" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--similarity-stride requires a value",
+                    "--similarity-stride 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--similarity-stride", raw, 1, 1 << 20)?;
+            similarity_stride = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--line-span-min-lines" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--line-span-min-lines requires a value",
+                    "--line-span-min-lines 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--line-span-min-lines", raw, 1, 1024)?;
+            line_span_min_lines = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--line-span-window" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--line-span-window requires a value",
+                    "--line-span-window 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--line-span-window", raw, 1, 1024)?;
+            line_span_window = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--idioms" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--idioms requires a value",
+                    "--idioms 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--idioms", raw, 2, 1_000_000)?;
+            idiom_min_files = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--idiom-token-len" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--idiom-token-len requires a value",
+                    "--idiom-token-len 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--idiom-token-len", raw, 1, 1_000_000)?;
+            idiom_token_len = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--symbol-min-count" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--symbol-min-count requires a value",
+                    "--symbol-min-count 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--symbol-min-count", raw, 1, 1024)?;
+            symbol_min_count = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--import-min-count" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--import-min-count requires a value",
+                    "--import-min-count 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--import-min-count", raw, 1, 1024)?;
+            import_min_count = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--import-similarity" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--import-similarity requires a value",
+                    "--import-similarity 需要一个值",
+                )
+                .to_string()
+            })?;
+            import_similarity = Some(parse_unit_interval(localization, arg, raw)?);
+            i += 2;
+            continue;
+        }
+        if arg == "--data-min-bytes" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--data-min-bytes requires a value",
+                    "--data-min-bytes 需要一个值",
+                )
+                .to_string()
+            })?;
+            data_min_bytes = Some(parse_u64_non_negative_safe(localization, arg, raw)?);
+            i += 2;
+            continue;
+        }
+        if arg == "--min-entropy" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--min-entropy requires a value",
+                    "--min-entropy 需要一个值",
+                )
+                .to_string()
+            })?;
+            let bits = parse_f64(localization, arg, raw)?;
+            if !bits.is_finite() || bits < 0.0 {
+                return Err(format!(
+                    "{arg} {}",
+                    tr(localization, "must be >= 0", "必须 >= 0")
+                ));
+            }
+            min_entropy = Some(bits);
+            i += 2;
+            continue;
+        }
+        if arg == "--simhash-threshold" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--simhash-threshold requires a value",
+                    "--simhash-threshold 需要一个值",
+                )
+                .to_string()
+            })?;
+            simhash_threshold = Some(parse_unit_interval(localization, arg, raw)?);
+            i += 2;
+            continue;
+        }
+        if arg == "--simhash-max-distance" {
             let raw = argv.get(i + 1).ok_or_else(|| {
                 tr(
                     localization,
@@ -495,120 +1760,1965 @@ pub(crate) fn parse_args(
             i += 2;
             continue;
         }
-        if arg == "--ignore-dir" {
-            let value = argv.get(i + 1).ok_or_else(|| {
-                tr(
-                    localization,
-                    "--ignore-dir requires a value",
-                    "--ignore-dir 需要一个值",
+        if arg == "--max-items" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--max-items requires a value",
+                    "--max-items 需要一个值",
+                )
+                .to_string()
+            })?;
+            let parsed = raw
+                .split_once('=')
+                .and_then(|(section, n)| Some((ReportSection::parse(section.trim())?, n)));
+            let Some((section, n)) = parsed else {
+                return Err(format!(
+                    "--max-items {} {raw}",
+                    tr(
+                        localization,
+                        "must be <section>=<n> with a JSON section name such as fileDuplicates, got",
+                        "必须是 <section>=<n>，section 为 fileDuplicates 等 JSON section 名，实际为",
+                    ),
+                ));
+            };
+            let value = parse_u32_in_range(localization, "--max-items", n.trim(), 0, u32::MAX)?;
+            max_items_per_detector.insert(section, value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--section-offset" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--section-offset requires a value",
+                    "--section-offset 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--section-offset", raw, 0, u32::MAX)?;
+            section_offset = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--section-limit" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--section-limit requires a value",
+                    "--section-limit 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--section-limit", raw, 1, u32::MAX)?;
+            section_limit = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--min-occurrences" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--min-occurrences requires a value",
+                    "--min-occurrences 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--min-occurrences", raw, 2, u32::MAX)?;
+            min_occurrences = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--max-occurrences-per-group" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--max-occurrences-per-group requires a value",
+                    "--max-occurrences-per-group 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(
+                localization,
+                "--max-occurrences-per-group",
+                raw,
+                1,
+                u32::MAX,
+            )?;
+            max_occurrences_per_group = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--ignore-dir" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--ignore-dir requires a value",
+                    "--ignore-dir 需要一个值",
+                )
+                .to_string()
+            })?;
+            ignore_dirs.push(value.to_string());
+            i += 2;
+            continue;
+        }
+        if arg == "--exclude" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--exclude requires a value",
+                    "--exclude 需要一个值",
+                )
+                .to_string()
+            })?;
+            ignore_patterns.push(value.to_string());
+            i += 2;
+            continue;
+        }
+        if arg == "--import-ignores" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--import-ignores requires a value",
+                    "--import-ignores 需要一个值",
+                )
+                .to_string()
+            })?;
+            import_ignores.push(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "-o" || arg == "--output" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                format!(
+                    "{arg} {}",
+                    tr(localization, "requires a value", "需要一个值")
+                )
+            })?;
+            snapshot_output = Some(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--against-snapshot" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--against-snapshot requires a value",
+                    "--against-snapshot 需要一个值",
+                )
+                .to_string()
+            })?;
+            against_snapshot = Some(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--file-similarity-matrix" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--file-similarity-matrix requires a value",
+                    "--file-similarity-matrix 需要一个值",
+                )
+                .to_string()
+            })?;
+            file_similarity_matrix = Some(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--cache-dir" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--cache-dir requires a value",
+                    "--cache-dir 需要一个值",
+                )
+                .to_string()
+            })?;
+            cache_dir = Some(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--max-cache-size" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--max-cache-size requires a value",
+                    "--max-cache-size 需要一个值",
+                )
+                .to_string()
+            })?;
+            max_cache_size = Some(parse_u64_non_negative_safe(
+                localization,
+                "--max-cache-size",
+                raw,
+            )?);
+            i += 2;
+            continue;
+        }
+        if arg == "--log-skips" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--log-skips requires a value",
+                    "--log-skips 需要一个值",
+                )
+                .to_string()
+            })?;
+            log_skips = Some(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--before" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--before requires a value",
+                    "--before 需要一个值",
+                )
+                .to_string()
+            })?;
+            before_ref = Some(value.clone());
+            i += 2;
+            continue;
+        }
+        if arg == "--after" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--after requires a value",
+                    "--after 需要一个值",
+                )
+                .to_string()
+            })?;
+            after_ref = Some(value.clone());
+            i += 2;
+            continue;
+        }
+        if arg == "--repo" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(localization, "--repo requires a value", "--repo 需要一个值").to_string()
+            })?;
+            resurrect_repo = Some(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--depth" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--depth requires a value",
+                    "--depth 需要一个值",
+                )
+                .to_string()
+            })?;
+            let value = parse_u32_in_range(localization, "--depth", raw, 1, u32::MAX)?;
+            history_depth = Some(value as usize);
+            i += 2;
+            continue;
+        }
+        if arg == "--baseline" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--baseline requires a value",
+                    "--baseline 需要一个值",
+                )
+                .to_string()
+            })?;
+            baseline = Some(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--suppressions" {
+            let value = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--suppressions requires a value",
+                    "--suppressions 需要一个值",
+                )
+                .to_string()
+            })?;
+            suppressions = Some(PathBuf::from(value));
+            i += 2;
+            continue;
+        }
+        if arg == "--sample" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--sample requires a value",
+                    "--sample 需要一个值",
+                )
+                .to_string()
+            })?;
+            sample = Some(parse_sample(localization, raw)?);
+            i += 2;
+            continue;
+        }
+        if arg == "--shard" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--shard requires a value",
+                    "--shard 需要一个值",
+                )
+                .to_string()
+            })?;
+            shard = Some(parse_shard(localization, raw)?);
+            i += 2;
+            continue;
+        }
+        if arg == "--unicode-normalize" {
+            let raw = argv.get(i + 1).ok_or_else(|| {
+                tr(
+                    localization,
+                    "--unicode-normalize requires a value",
+                    "--unicode-normalize 需要一个值",
+                )
+                .to_string()
+            })?;
+            unicode_normalization = Some(match raw.as_str() {
+                "nfc" => UnicodeNormalization::Nfc,
+                "nfkc" => UnicodeNormalization::Nfkc,
+                _ => {
+                    return Err(format!(
+                        "--unicode-normalize {} {raw}",
+                        tr(
+                            localization,
+                            "must be nfc or nfkc, got",
+                            "必须是 nfc 或 nfkc，实际为",
+                        ),
+                    ));
+                }
+            });
+            i += 2;
+            continue;
+        }
+        if arg == "-h" || arg == "--help" {
+            i += 1;
+            continue;
+        }
+        if arg == "-V" || arg == "--version" {
+            i += 1;
+            continue;
+        }
+        if arg.starts_with('-') {
+            return Err(format!(
+                "{} {arg}",
+                tr(localization, "Unknown option:", "未知参数:"),
+            ));
+        }
+        roots.push(PathBuf::from(arg));
+        i += 1;
+    }
+
+    if report && code_spans {
+        return Err(tr(
+            localization,
+            "--report conflicts with --code-spans",
+            "--report 与 --code-spans 不能同时使用",
+        )
+        .to_string());
+    }
+    if snapshot {
+        if snapshot_output.is_none() {
+            return Err(tr(
+                localization,
+                "snapshot requires -o <file>",
+                "snapshot 需要 -o <file>",
+            )
+            .to_string());
+        }
+        if report || code_spans || against_snapshot.is_some() {
+            return Err(tr(
+                localization,
+                "snapshot cannot be combined with --report, --code-spans or --against-snapshot",
+                "snapshot 不能与 --report、--code-spans 或 --against-snapshot 同时使用",
+            )
+            .to_string());
+        }
+    } else if snapshot_output.is_some() && !html_diff_command {
+        return Err(tr(
+            localization,
+            "-o/--output is only valid with the snapshot and html-diff commands",
+            "-o/--output 仅适用于 snapshot 和 html-diff 命令",
+        )
+        .to_string());
+    } else if shard.is_some() {
+        return Err(tr(
+            localization,
+            "--shard is only valid with the snapshot command",
+            "--shard 仅适用于 snapshot 命令",
+        )
+        .to_string());
+    }
+    if stdin_json
+        && (snapshot
+            || merge_shards
+            || explain_command
+            || convert_command
+            || genealogy_command
+            || html_diff_command
+            || self_diff_command
+            || code_spans
+            || against_snapshot.is_some()
+            || sample.is_some())
+    {
+        return Err(tr(
+            localization,
+            "--stdin-json cannot be combined with snapshot, merge-shards, explain, convert, genealogy, html-diff, self-diff, --code-spans, --against-snapshot or --sample",
+            "--stdin-json 不能与 snapshot、merge-shards、explain、convert、genealogy、html-diff、self-diff、--code-spans、--against-snapshot 或 --sample 同时使用",
+        )
+        .to_string());
+    }
+    if sample.is_some() && (snapshot || merge_shards || against_snapshot.is_some()) {
+        return Err(tr(
+            localization,
+            "--sample cannot be combined with snapshot, merge-shards or --against-snapshot",
+            "--sample 不能与 snapshot、merge-shards 或 --against-snapshot 同时使用",
+        )
+        .to_string());
+    }
+    if merge_shards {
+        if roots.is_empty() {
+            return Err(tr(
+                localization,
+                "merge-shards requires at least one snapshot file",
+                "merge-shards 需要至少一个快照文件",
+            )
+            .to_string());
+        }
+        if code_spans || against_snapshot.is_some() {
+            return Err(tr(
+                localization,
+                "merge-shards cannot be combined with --code-spans or --against-snapshot",
+                "merge-shards 不能与 --code-spans 或 --against-snapshot 同时使用",
+            )
+            .to_string());
+        }
+    }
+    let explain = if explain_command {
+        if roots.len() < 2 {
+            return Err(tr(
+                localization,
+                "explain requires <group-id> <report.json>",
+                "explain 需要 <group-id> <report.json>",
+            )
+            .to_string());
+        }
+        if report || code_spans || against_snapshot.is_some() || group_by_file {
+            return Err(tr(
+                localization,
+                "explain cannot be combined with --report, --code-spans, --group-by or --against-snapshot",
+                "explain 不能与 --report、--code-spans、--group-by 或 --against-snapshot 同时使用",
+            )
+            .to_string());
+        }
+        let report_file = roots.remove(1);
+        let group_id = roots.remove(0).to_string_lossy().into_owned();
+        Some((group_id, report_file))
+    } else {
+        None
+    };
+    let convert = if convert_command {
+        if roots.len() != 2 {
+            return Err(tr(
+                localization,
+                "convert requires <input> <output>",
+                "convert 需要 <input> <output>",
+            )
+            .to_string());
+        }
+        let output = roots.remove(1);
+        Some((roots.remove(0), output))
+    } else {
+        None
+    };
+    let genealogy = if genealogy_command {
+        if roots.len() != 2 {
+            return Err(tr(
+                localization,
+                "genealogy requires <old.dcs> <new.dcs>",
+                "genealogy 需要 <old.dcs> <new.dcs>",
+            )
+            .to_string());
+        }
+        if report
+            || code_spans
+            || group_by_file
+            || against_snapshot.is_some()
+            || sample.is_some()
+            || cache_dir.is_some()
+        {
+            return Err(tr(
+                localization,
+                "genealogy cannot be combined with --report, --code-spans, --group-by, --against-snapshot, --sample or --cache-dir",
+                "genealogy 不能与 --report、--code-spans、--group-by、--against-snapshot、--sample 或 --cache-dir 同时使用",
+            )
+            .to_string());
+        }
+        let new = roots.remove(1);
+        Some((roots.remove(0), new))
+    } else {
+        None
+    };
+    let html_diff = if html_diff_command {
+        if roots.len() != 2 {
+            return Err(tr(
+                localization,
+                "html-diff requires <old.json> <new.json>",
+                "html-diff 需要 <old.json> <new.json>",
+            )
+            .to_string());
+        }
+        if report || code_spans || group_by_file || against_snapshot.is_some() || sample.is_some() {
+            return Err(tr(
+                localization,
+                "html-diff cannot be combined with --report, --code-spans, --group-by, --against-snapshot or --sample",
+                "html-diff 不能与 --report、--code-spans、--group-by、--against-snapshot 或 --sample 同时使用",
+            )
+            .to_string());
+        }
+        let new = roots.remove(1);
+        Some((roots.remove(0), new, snapshot_output.take()))
+    } else {
+        None
+    };
+    let self_diff = if self_diff_command {
+        let (Some(before), Some(after)) = (before_ref, after_ref) else {
+            return Err(tr(
+                localization,
+                "self-diff requires --before <ref> and --after <ref>",
+                "self-diff 需要 --before <ref> 与 --after <ref>",
+            )
+            .to_string());
+        };
+        if roots.len() > 1 {
+            return Err(tr(
+                localization,
+                "self-diff takes a single repo",
+                "self-diff 只接受一个仓库",
+            )
+            .to_string());
+        }
+        if report
+            || code_spans
+            || group_by_file
+            || against_snapshot.is_some()
+            || sample.is_some()
+            || cache_dir.is_some()
+        {
+            return Err(tr(
+                localization,
+                "self-diff cannot be combined with --report, --code-spans, --group-by, --against-snapshot, --sample or --cache-dir",
+                "self-diff 不能与 --report、--code-spans、--group-by、--against-snapshot、--sample 或 --cache-dir 同时使用",
+            )
+            .to_string());
+        }
+        Some((before, after))
+    } else {
+        if before_ref.is_some() || after_ref.is_some() {
+            return Err(tr(
+                localization,
+                "--before and --after are only valid with the self-diff command",
+                "--before 与 --after 仅适用于 self-diff 命令",
+            )
+            .to_string());
+        }
+        None
+    };
+    let resurrect = if resurrect_command {
+        roots.extend(resurrect_repo);
+        if roots.len() > 1 {
+            return Err(tr(
+                localization,
+                "resurrect takes a single repo",
+                "resurrect 只接受一个仓库",
+            )
+            .to_string());
+        }
+        if code_spans
+            || against_snapshot.is_some()
+            || stdin_json
+            || sample.is_some()
+            || cache_dir.is_some()
+        {
+            return Err(tr(
+                localization,
+                "resurrect cannot be combined with --code-spans, --against-snapshot, --stdin-json, --sample or --cache-dir",
+                "resurrect 不能与 --code-spans、--against-snapshot、--stdin-json、--sample 或 --cache-dir 同时使用",
+            )
+            .to_string());
+        }
+        Some(history_depth.unwrap_or(DEFAULT_HISTORY_DEPTH))
+    } else {
+        if resurrect_repo.is_some() || history_depth.is_some() {
+            return Err(tr(
+                localization,
+                "--repo and --depth are only valid with the resurrect command",
+                "--repo 与 --depth 仅适用于 resurrect 命令",
+            )
+            .to_string());
+        }
+        None
+    };
+    let cache_command = if cache_subcommand {
+        let command = match roots.as_slice() {
+            [action] => CacheCommand::parse(&action.to_string_lossy()),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            tr(
+                localization,
+                "cache requires one of status, clear or gc",
+                "cache 需要 status、clear 或 gc 之一",
+            )
+            .to_string()
+        })?;
+        roots.clear();
+        if cache_dir.is_none() {
+            return Err(tr(
+                localization,
+                "cache requires --cache-dir",
+                "cache 需要配合 --cache-dir 使用",
+            )
+            .to_string());
+        }
+        if report || code_spans || stdin_json || against_snapshot.is_some() {
+            return Err(tr(
+                localization,
+                "cache cannot be combined with --report, --code-spans, --stdin-json or --against-snapshot",
+                "cache 不能与 --report、--code-spans、--stdin-json 或 --against-snapshot 同时使用",
+            )
+            .to_string());
+        }
+        if max_cache_size.is_some() && command != CacheCommand::Gc {
+            return Err(tr(
+                localization,
+                "--max-cache-size is only valid with cache gc or --report --cache-dir",
+                "--max-cache-size 仅适用于 cache gc 或 --report --cache-dir",
+            )
+            .to_string());
+        }
+        Some(command)
+    } else {
+        None
+    };
+    if against_snapshot.is_some() && code_spans {
+        return Err(tr(
+            localization,
+            "--against-snapshot conflicts with --code-spans",
+            "--against-snapshot 与 --code-spans 不能同时使用",
+        )
+        .to_string());
+    }
+    let format = match format {
+        Some(format) if json && format != "json" => {
+            return Err(tr(
+                localization,
+                "--json conflicts with --format (use --format json)",
+                "--json 与 --format 冲突（请使用 --format json）",
+            )
+            .to_string());
+        }
+        Some(format) => format,
+        None if json => "json".to_string(),
+        None => "text".to_string(),
+    };
+    let json = format == "json";
+    // Comparing against or merging snapshots, scanning stdin files or git history always
+    // produces a report.
+    let report =
+        report || against_snapshot.is_some() || merge_shards || stdin_json || resurrect.is_some();
+    if !section_filter.is_empty() && !report {
+        return Err(tr(
+            localization,
+            "--only/--omit require --report",
+            "--only/--omit 需要配合 --report 使用",
+        )
+        .to_string());
+    }
+    if group_by_file && !report {
+        return Err(tr(
+            localization,
+            "--group-by file requires --report",
+            "--group-by file 需要配合 --report 使用",
+        )
+        .to_string());
+    }
+    if suggest_excludes && !report {
+        return Err(tr(
+            localization,
+            "--suggest-excludes requires --report",
+            "--suggest-excludes 需要配合 --report 使用",
+        )
+        .to_string());
+    }
+    if emit_blocks {
+        if report
+            || code_spans
+            || stdin_json
+            || against_snapshot.is_some()
+            || snapshot
+            || merge_shards
+            || explain_command
+            || convert_command
+            || genealogy_command
+            || html_diff_command
+            || self_diff_command
+            || resurrect_command
+            || cache_subcommand
+        {
+            return Err(tr(
+                localization,
+                "--emit-blocks cannot be combined with --report, --code-spans, --stdin-json, --against-snapshot or a command",
+                "--emit-blocks 不能与 --report、--code-spans、--stdin-json、--against-snapshot 或子命令同时使用",
+            )
+            .to_string());
+        }
+        if roots.is_empty() {
+            return Err(tr(
+                localization,
+                "--emit-blocks requires at least one file",
+                "--emit-blocks 需要至少一个文件",
+            )
+            .to_string());
+        }
+    }
+    if aggregate_ownership && !report {
+        return Err(tr(
+            localization,
+            "--aggregate ownership requires --report",
+            "--aggregate ownership 需要配合 --report 使用",
+        )
+        .to_string());
+    }
+    if aggregate_ownership && group_by_file {
+        return Err(tr(
+            localization,
+            "--aggregate ownership cannot be combined with --group-by file",
+            "--aggregate ownership 不能与 --group-by file 同时使用",
+        )
+        .to_string());
+    }
+    if codeowners.is_some() && !aggregate_ownership {
+        return Err(tr(
+            localization,
+            "--codeowners requires --aggregate ownership",
+            "--codeowners 需要配合 --aggregate ownership 使用",
+        )
+        .to_string());
+    }
+    if format == "review-comments" && aggregate_ownership {
+        return Err(tr(
+            localization,
+            "--format review-comments cannot be combined with --aggregate",
+            "--format review-comments 不能与 --aggregate 同时使用",
+        )
+        .to_string());
+    }
+    if format == "review-comments" && !report {
+        return Err(tr(
+            localization,
+            "--format review-comments requires --report",
+            "--format review-comments 需要配合 --report 使用",
+        )
+        .to_string());
+    }
+    if suppressions.is_some()
+        && (snapshot
+            || explain_command
+            || convert_command
+            || genealogy_command
+            || html_diff_command
+            || self_diff_command
+            || cache_subcommand)
+    {
+        return Err(tr(
+            localization,
+            "--suppressions cannot be combined with snapshot, explain, convert, genealogy, html-diff, self-diff or cache",
+            "--suppressions 不能与 snapshot、explain、convert、genealogy、html-diff、self-diff 或 cache 同时使用",
+        )
+        .to_string());
+    }
+    if baseline.is_some() && format != "review-comments" {
+        return Err(tr(
+            localization,
+            "--baseline requires --format review-comments",
+            "--baseline 需要配合 --format review-comments 使用",
+        )
+        .to_string());
+    }
+    if max_cache_size.is_some() && cache_dir.is_none() {
+        return Err(tr(
+            localization,
+            "--max-cache-size requires --cache-dir",
+            "--max-cache-size 需要配合 --cache-dir 使用",
+        )
+        .to_string());
+    }
+    if cache_dir.is_some() && !report && cache_command.is_none() {
+        return Err(tr(
+            localization,
+            "--cache-dir requires --report",
+            "--cache-dir 需要配合 --report 使用",
+        )
+        .to_string());
+    }
+    if cache_dir.is_some()
+        && (merge_shards
+            || stdin_json
+            || against_snapshot.is_some()
+            || file_similarity_matrix.is_some())
+    {
+        return Err(tr(
+            localization,
+            "--cache-dir cannot be combined with merge-shards, --stdin-json, --against-snapshot or --file-similarity-matrix",
+            "--cache-dir 不能与 merge-shards、--stdin-json、--against-snapshot 或 --file-similarity-matrix 同时使用",
+        )
+        .to_string());
+    }
+    if file_similarity_matrix.is_some() && !report {
+        return Err(tr(
+            localization,
+            "--file-similarity-matrix requires --report",
+            "--file-similarity-matrix 需要配合 --report 使用",
+        )
+        .to_string());
+    }
+    if !path_filter.is_empty() && !report {
+        return Err(tr(
+            localization,
+            "--filter-path requires --report",
+            "--filter-path 需要配合 --report 使用",
+        )
+        .to_string());
+    }
+    if simhash_threshold.is_some() && simhash_max_distance.is_some() {
+        return Err(tr(
+            localization,
+            "--simhash-threshold conflicts with --simhash-max-distance",
+            "--simhash-threshold 与 --simhash-max-distance 不能同时使用",
+        )
+        .to_string());
+    }
+    if let Some(threshold) = simhash_threshold {
+        simhash_max_distance = Some(simhash_distance_for_threshold(threshold));
+    }
+    let defaults = ScanOptions::default();
+    let signature_size = minhash_signature_size.unwrap_or(defaults.minhash_signature_size);
+    let bands = minhash_bands.unwrap_or(defaults.minhash_bands);
+    if !signature_size.is_multiple_of(bands) {
+        return Err(format!(
+            "{} ({signature_size} / {bands})",
+            tr(
+                localization,
+                "--minhash-bands must divide --minhash-signature-size",
+                "--minhash-bands 必须能整除 --minhash-signature-size",
+            )
+        ));
+    }
+    if path_filter_trim && path_filter.is_empty() {
+        return Err(tr(
+            localization,
+            "--filter-path-trim requires --filter-path",
+            "--filter-path-trim 需要配合 --filter-path 使用",
+        )
+        .to_string());
+    }
+    if similarity_stride.is_some() && similarity_window.is_none() {
+        return Err(tr(
+            localization,
+            "--similarity-stride requires --similarity-window",
+            "--similarity-stride 需要配合 --similarity-window 使用",
+        )
+        .to_string());
+    }
+    let similarity_windows = similarity_window
+        .map(|size| {
+            let stride = similarity_stride.unwrap_or((size / 2).max(1));
+            SimilarityWindows::new(size, stride)
+        })
+        .transpose()
+        .map_err(|err| err.to_string())?;
+
+    let mut options = ScanOptions::default();
+    // Presets go first so that every other flag overrides them.
+    if let Some(preset) = preset {
+        preset.apply(&mut options);
+    }
+    options.respect_gitignore = respect_gitignore;
+    options.use_global_gitignore = use_global_gitignore;
+    options.file_listing = file_listing;
+    options.cross_repo_only = cross_repo_only;
+    options.path_filter = path_filter;
+    options.path_filter_trim = path_filter_trim;
+    options.builtin_skips = builtin_skips;
+    if !default_excludes {
+        options.default_excludes.clear();
+    }
+    options.follow_symlinks = follow_symlinks;
+    options.similarity_diff = similarity_diff;
+    options.file_coverage = file_coverage;
+    options.clone_clusters = clone_clusters;
+    options.similarity_clusters = similarity_clusters;
+    options.occurrence_ages = occurrence_ages;
+    options.license_conflicts = license_conflicts;
+    options.file_similarity = file_similarity_matrix.is_some();
+    options.deduplicate_across_detectors = dedupe_detectors;
+    options.prune_contained_groups = prune_contained;
+    options.emit_absolute_paths = absolute_paths;
+    options.path_prefix_map = path_prefix_map;
+    options.shard = shard;
+    options.sample = sample;
+    options.unicode_normalization = unicode_normalization;
+    options.test_code_filter = test_code_filter;
+    if let Some(max_file_size) = max_file_size {
+        options.max_file_size = Some(max_file_size);
+    }
+    if let Some(max_files) = max_files {
+        options.max_files = Some(max_files);
+    }
+    if let Some(budget_order) = budget_order {
+        options.budget_order = budget_order;
+    }
+    if let Some(max_total_bytes) = max_total_bytes {
+        options.max_total_bytes = Some(max_total_bytes);
+    }
+    if let Some(max_normalized_chars) = max_normalized_chars {
+        options.max_normalized_chars = Some(max_normalized_chars);
+    }
+    if let Some(max_tokens) = max_tokens {
+        options.max_tokens = Some(max_tokens);
+    }
+    if let Some(max_tokens_per_file) = max_tokens_per_file {
+        options.max_tokens_per_file = Some(max_tokens_per_file);
+    }
+    if let Some(max_groups) = max_groups_per_detector {
+        // `0` lifts the cap.
+        options.max_groups_per_detector = (max_groups > 0).then_some(max_groups);
+    }
+    if let Some(max_file_time) = max_file_time {
+        options.max_file_time = Some(Duration::from_millis(max_file_time));
+    }
+    if let Some(threads) = threads {
+        options.threads = threads;
+    }
+    if let Some(report_sort) = report_sort {
+        options.report_sort = report_sort;
+    }
+    if !language_token_scales.is_empty() {
+        options.language_token_scales = language_token_scales;
+    }
+    if let Some(preview_max_chars) = preview_max_chars {
+        options.preview_max_chars = Some(preview_max_chars);
+    }
+    if let Some(preview_context_lines) = preview_context_lines {
+        options.preview_context_lines = preview_context_lines;
+    }
+    if let Some(min_match_len) = min_match_len {
+        options.min_match_len = min_match_len;
+    }
+    if let Some(min_token_len) = min_token_len {
+        options.min_token_len = min_token_len;
+    }
+    options.adaptive_window = adaptive_window;
+    if let Some(similarity_threshold) = similarity_threshold {
+        options.similarity_threshold = similarity_threshold;
+    }
+    if let Some(simhash_max_distance) = simhash_max_distance {
+        options.simhash_max_distance = simhash_max_distance;
+    }
+    if let Some(shingle_size) = shingle_size {
+        options.shingle_size = shingle_size;
+    }
+    options.minhash_signature_size = signature_size;
+    options.minhash_bands = bands;
+    options.similarity_windows = similarity_windows;
+    if let Some(line_span_min_lines) = line_span_min_lines {
+        options.line_span_min_lines = line_span_min_lines;
+    }
+    if let Some(line_span_window) = line_span_window {
+        options.line_span_window = line_span_window;
+    }
+    if let Some(symbol_min_count) = symbol_min_count {
+        options.symbol_min_count = symbol_min_count;
+    }
+    options.idiom_min_files = idiom_min_files;
+    if let Some(idiom_token_len) = idiom_token_len {
+        options.idiom_token_len = idiom_token_len;
+    }
+    if let Some(data_min_bytes) = data_min_bytes {
+        options.data_min_bytes = data_min_bytes;
+    }
+    if let Some(min_entropy) = min_entropy {
+        options.min_entropy = min_entropy;
+    }
+    if let Some(import_min_count) = import_min_count {
+        options.import_min_count = import_min_count;
+    }
+    if let Some(import_similarity) = import_similarity {
+        options.import_similarity_threshold = import_similarity;
+    }
+    if let Some(max_report_items) = max_report_items {
+        options.max_report_items = max_report_items;
+    }
+    options
+        .max_items_per_detector
+        .extend(max_items_per_detector);
+    if let Some(section_offset) = section_offset {
+        options.section_offset = section_offset;
+    }
+    options.section_limit = section_limit;
+    if let Some(min_occurrences) = min_occurrences {
+        options.min_occurrences = min_occurrences;
+    }
+    if let Some(max_occurrences_per_group) = max_occurrences_per_group {
+        options.max_occurrences_per_group = Some(max_occurrences_per_group);
+    }
+    options.ignore_dirs.extend(ignore_dirs);
+    options.ignore_patterns.extend(ignore_patterns);
+
+    // `explain` falls back to the roots recorded in the report; stdin files need no root.
+    let roots = if roots.is_empty()
+        && explain.is_none()
+        && convert.is_none()
+        && genealogy.is_none()
+        && html_diff.is_none()
+        && cache_command.is_none()
+        && !stdin_json
+    {
+        vec![env::current_dir().map_err(|e| {
+            format!(
+                "{} {e}",
+                tr(localization, "failed to get cwd:", "无法获取当前目录:"),
+            )
+        })?]
+    } else {
+        roots
+    };
+
+    // Stdin files count as one more root.
+    if cross_repo_only
+        && roots.len() + usize::from(stdin_json) < 2
+        && against_snapshot.is_none()
+        && !merge_shards
+        && explain.is_none()
+        && convert.is_none()
+        && genealogy.is_none()
+        && html_diff.is_none()
+        && cache_command.is_none()
+        && resurrect.is_none()
+    {
+        return Err(tr(
+            localization,
+            "--cross-repo-only requires at least 2 roots",
+            "--cross-repo-only 需要至少 2 个 root",
+        )
+        .to_string());
+    }
+
+    Ok(ParsedArgs {
+        localization,
+        json,
+        format,
+        color,
+        hyperlinks,
+        link_template,
+        occurrence_sources,
+        suggest_excludes,
+        emit_blocks,
+        stats,
+        progress_json,
+        log_skips,
+        strict,
+        strict_policy,
+        report,
+        group_by_file,
+        aggregate_ownership,
+        codeowners,
+        section_filter,
+        code_spans,
+        snapshot_output,
+        against_snapshot,
+        stdin_json,
+        baseline,
+        suppressions,
+        file_similarity_matrix,
+        cache_dir,
+        max_cache_size,
+        cache_command,
+        merge_shards,
+        explain,
+        convert,
+        genealogy,
+        html_diff,
+        self_diff,
+        resurrect,
+        import_ignores,
+        skip_snippets,
+        stop_sequences,
+        roots,
+        options,
+    })
+}
+
+/// Parse `--shard <i/N>` (1-based `i`) into a core [`ScanShard`].
+fn parse_shard(localization: Localization, raw: &str) -> Result<ScanShard, String> {
+    let invalid = || {
+        format!(
+            "--shard {} {raw}",
+            tr(
+                localization,
+                "must be <i/N> with 1 <= i <= N, got",
+                "必须是 <i/N> 且 1 <= i <= N，实际为",
+            ),
+        )
+    };
+    let (index, count) = raw.split_once('/').ok_or_else(invalid)?;
+    let index = index.parse::<u32>().map_err(|_| invalid())?;
+    let count = count.parse::<u32>().map_err(|_| invalid())?;
+    if index == 0 {
+        return Err(invalid());
+    }
+    ScanShard::new(index - 1, count).map_err(|_| invalid())
+}
+
+/// `--sample`: a fraction when the value has a decimal point (`0.1`), a file count otherwise.
+fn parse_sample(localization: Localization, raw: &str) -> Result<ScanSample, String> {
+    let invalid = || {
+        format!(
+            "--sample {} {raw}",
+            tr(
+                localization,
+                "must be a fraction in (0, 1] or a file count >= 1, got",
+                "必须是 (0, 1] 内的比例或 >= 1 的文件数，实际为",
+            ),
+        )
+    };
+    let sample = if raw.contains('.') {
+        ScanSample::fraction(raw.parse::<f64>().map_err(|_| invalid())?)
+    } else {
+        ScanSample::files(raw.parse::<u64>().map_err(|_| invalid())?)
+    };
+    sample.map_err(|_| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn snapshot_command_requires_output() {
+        let err = parse_args(&argv(&["snapshot", "."]), Localization::En).unwrap_err();
+        assert!(err.contains("-o"));
+
+        let parsed =
+            parse_args(&argv(&["snapshot", "-o", "c.dcs", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.snapshot_output, Some(PathBuf::from("c.dcs")));
+        assert!(!parsed.report);
+
+        let err = parse_args(&argv(&["-o", "c.dcs", "."]), Localization::En).unwrap_err();
+        assert!(err.contains("snapshot"));
+    }
+
+    #[test]
+    fn explain_command_takes_group_id_report_and_optional_roots() {
+        let parsed = parse_args(&argv(&["explain", "ab12", "r.json"]), Localization::En).unwrap();
+        assert_eq!(
+            parsed.explain,
+            Some(("ab12".to_string(), PathBuf::from("r.json")))
+        );
+        assert!(parsed.roots.is_empty());
+
+        let parsed = parse_args(
+            &argv(&["explain", "ab12", "r.json", "/a", "/b"]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.roots, [PathBuf::from("/a"), PathBuf::from("/b")]);
+
+        assert!(parse_args(&argv(&["explain", "ab12"]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["explain", "--report", "ab12", "r.json"]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn convert_command_takes_input_and_output() {
+        let parsed =
+            parse_args(&argv(&["convert", "r.json.gz", "r.json"]), Localization::En).unwrap();
+        assert_eq!(
+            parsed.convert,
+            Some((PathBuf::from("r.json.gz"), PathBuf::from("r.json")))
+        );
+        assert!(parsed.roots.is_empty());
+        assert!(parse_args(&argv(&["convert", "r.json"]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn html_diff_command_takes_two_reports_and_an_optional_output() {
+        let parsed = parse_args(
+            &argv(&["html-diff", "v1.json", "v2.json", "-o", "diff.html"]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.html_diff,
+            Some((
+                PathBuf::from("v1.json"),
+                PathBuf::from("v2.json"),
+                Some(PathBuf::from("diff.html"))
+            ))
+        );
+        assert!(parsed.snapshot_output.is_none());
+        assert!(parsed.roots.is_empty());
+
+        let parsed = parse_args(
+            &argv(&["html-diff", "v1.json", "v2.json"]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.html_diff.map(|(_, _, output)| output), Some(None));
+        assert!(parse_args(&argv(&["html-diff", "v1.json"]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["html-diff", "--report", "v1.json", "v2.json"]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn genealogy_command_takes_old_and_new_snapshots() {
+        let parsed = parse_args(
+            &argv(&["genealogy", "--min-token-len", "20", "old.dcs", "new.dcs"]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.genealogy,
+            Some((PathBuf::from("old.dcs"), PathBuf::from("new.dcs")))
+        );
+        assert_eq!(parsed.options.min_token_len, 20);
+        assert!(parsed.roots.is_empty());
+        assert!(parse_args(&argv(&["genealogy", "old.dcs"]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["genealogy", "--report", "old.dcs", "new.dcs"]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn resurrect_command_reads_history_of_one_repo() {
+        let parsed = parse_args(
+            &argv(&["resurrect", "--repo", "repo", "--depth", "50"]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.resurrect, Some(50));
+        assert_eq!(parsed.roots, [PathBuf::from("repo")]);
+        assert!(parsed.report);
+        let parsed = parse_args(&argv(&["resurrect"]), Localization::En).unwrap();
+        assert_eq!(parsed.resurrect, Some(DEFAULT_HISTORY_DEPTH));
+        assert_eq!(parsed.roots.len(), 1);
+        assert!(parse_args(&argv(&["resurrect", "--repo", "a", "b"]), Localization::En).is_err());
+        assert!(parse_args(&argv(&["resurrect", "--depth", "0"]), Localization::En).is_err());
+        assert!(parse_args(&argv(&["resurrect", "--code-spans", "."]), Localization::En).is_err());
+        assert!(parse_args(&argv(&["--repo", "a"]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn self_diff_command_takes_two_refs_of_one_repo() {
+        let parsed = parse_args(
+            &argv(&["self-diff", "--before", "v1", "--after", "HEAD", "repo"]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.self_diff,
+            Some(("v1".to_string(), "HEAD".to_string()))
+        );
+        assert_eq!(parsed.roots, [PathBuf::from("repo")]);
+        let parsed = parse_args(
+            &argv(&["self-diff", "--before", "v1", "--after", "v2"]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.roots.len(), 1);
+        assert!(
+            parse_args(
+                &argv(&["self-diff", "--before", "v1", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
+        assert!(
+            parse_args(
+                &argv(&["self-diff", "--before", "a", "--after", "b", "x", "y"]),
+                Localization::En
+            )
+            .is_err()
+        );
+        assert!(
+            parse_args(
+                &argv(&["self-diff", "--report", "--before", "a", "--after", "b"]),
+                Localization::En
+            )
+            .is_err()
+        );
+        assert!(parse_args(&argv(&["--before", "a", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn cache_command_takes_an_action_and_the_cache_dir() {
+        let parsed = parse_args(
+            &argv(&[
+                "cache",
+                "gc",
+                "--cache-dir",
+                "c",
+                "--max-cache-size",
+                "1000",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.cache_command, Some(CacheCommand::Gc));
+        assert_eq!(parsed.cache_dir, Some(PathBuf::from("c")));
+        assert_eq!(parsed.max_cache_size, Some(1000));
+        assert!(parsed.roots.is_empty());
+        for args in [
+            &["cache", "--cache-dir", "c"][..],
+            &["cache", "purge", "--cache-dir", "c"],
+            &["cache", "status"],
+            &[
+                "cache",
+                "status",
+                "--cache-dir",
+                "c",
+                "--max-cache-size",
+                "1",
+            ],
+            &["cache", "clear", "--cache-dir", "c", "--report"],
+            &["--report", "--max-cache-size", "1", "."],
+        ] {
+            assert!(
+                parse_args(&argv(args), Localization::En).is_err(),
+                "{args:?}"
+            );
+        }
+        let parsed = parse_args(
+            &argv(&["--report", "--cache-dir", "c", "--max-cache-size", "1", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.max_cache_size, Some(1));
+    }
+
+    #[test]
+    fn per_detector_similarity_thresholds() {
+        let parsed = parse_args(
+            &argv(&[
+                "--minhash-threshold",
+                "0.9",
+                "--simhash-threshold",
+                "0.75",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.similarity_threshold, 0.9);
+        assert_eq!(parsed.options.simhash_max_distance, 16);
+        assert_eq!(simhash_distance_for_threshold(0.95), 3);
+        assert_eq!(simhash_distance_for_threshold(1.0), 0);
+        assert_eq!(simhash_distance_for_threshold(0.0), 64);
+
+        let err = parse_args(
+            &argv(&[
+                "--simhash-threshold",
+                "0.9",
+                "--simhash-max-distance",
+                "2",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap_err();
+        assert!(err.contains("conflicts"));
+        let err =
+            parse_args(&argv(&["--minhash-threshold", "2", "."]), Localization::En).unwrap_err();
+        assert_eq!(err, "--minhash-threshold must be 0..1");
+    }
+
+    #[test]
+    fn minhash_bands_must_divide_signature_size() {
+        let parsed = parse_args(
+            &argv(&[
+                "--shingle-size",
+                "3",
+                "--minhash-signature-size",
+                "64",
+                "--minhash-bands",
+                "16",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.shingle_size, 3);
+        assert_eq!(parsed.options.minhash_signature_size, 64);
+        assert_eq!(parsed.options.minhash_bands, 16);
+
+        // Checked against the default signature size (32) too.
+        let err = parse_args(&argv(&["--minhash-bands", "5", "."]), Localization::En).unwrap_err();
+        assert!(err.contains("(32 / 5)"), "{err}");
+        assert!(parse_args(&argv(&["--shingle-size", "0", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn section_offset_and_limit_select_a_page() {
+        let parsed = parse_args(
+            &argv(&[
+                "--report",
+                "--section-offset",
+                "40",
+                "--section-limit",
+                "20",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.section_offset, 40);
+        assert_eq!(parsed.options.section_limit, Some(20));
+        assert!(parse_args(&argv(&["--section-limit", "0", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn similarity_windows_default_to_half_window_stride() {
+        let parsed =
+            parse_args(&argv(&["--similarity-window", "60", "."]), Localization::En).unwrap();
+        let windows = parsed.options.similarity_windows.unwrap();
+        assert_eq!((windows.size(), windows.stride()), (60, 30));
+
+        let parsed = parse_args(
+            &argv(&[
+                "--similarity-window",
+                "60",
+                "--similarity-stride",
+                "10",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.similarity_windows.unwrap().stride(), 10);
+
+        let parsed = parse_args(&argv(&["."]), Localization::En).unwrap();
+        assert!(parsed.options.similarity_windows.is_none());
+        let err =
+            parse_args(&argv(&["--similarity-stride", "10", "."]), Localization::En).unwrap_err();
+        assert_eq!(err, "--similarity-stride requires --similarity-window");
+    }
+
+    #[test]
+    fn idiom_flags_enable_the_common_idioms_section() {
+        let parsed = parse_args(&argv(&["."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.idiom_min_files, None);
+        assert_eq!(parsed.options.idiom_token_len, 12);
+        let parsed = parse_args(
+            &argv(&["--idioms", "5", "--idiom-token-len", "8", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.idiom_min_files, Some(5));
+        assert_eq!(parsed.options.idiom_token_len, 8);
+        assert!(parse_args(&argv(&["--idioms", "1", "."]), Localization::En).is_err());
+        assert!(parse_args(&argv(&["--idiom-token-len", "0", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn max_items_limits_single_sections() {
+        let parsed = parse_args(
+            &argv(&[
+                "--max-items",
+                "similarBlocksMinhash=50",
+                "--max-items",
+                "fileDuplicates=5000",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.max_report_items, 200);
+        assert_eq!(
+            parsed.options.max_items_per_detector,
+            HashMap::from([
+                (ReportSection::SimilarBlocksMinhash, 50),
+                (ReportSection::FileDuplicates, 5000),
+            ])
+        );
+        assert!(parse_args(&argv(&["--max-items", "minhash=5", "."]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["--max-items", "fileDuplicates", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn adaptive_window_takes_a_growth_cap() {
+        let parsed = parse_args(&argv(&["."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.adaptive_window, None);
+        let parsed = parse_args(&argv(&["--adaptive-window", "4", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.adaptive_window, Some(4));
+        assert!(parse_args(&argv(&["--adaptive-window", "1", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn line_span_knobs_override_defaults() {
+        let parsed = parse_args(
+            &argv(&["--line-span-min-lines", "4", "--line-span-window", "3", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.line_span_min_lines, 4);
+        assert_eq!(parsed.options.line_span_window, 3);
+
+        let parsed = parse_args(&argv(&["."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.line_span_min_lines, 2);
+        assert_eq!(parsed.options.line_span_window, 8);
+        assert_eq!(parsed.options.symbol_min_count, 3);
+        let parsed =
+            parse_args(&argv(&["--symbol-min-count", "5", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.symbol_min_count, 5);
+        let parsed = parse_args(
+            &argv(&["--import-min-count", "2", "--import-similarity", "0.5", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.import_min_count, 2);
+        assert_eq!(parsed.options.import_similarity_threshold, 0.5);
+        let parsed = parse_args(&argv(&["--data-min-bytes", "64", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.data_min_bytes, 64);
+        let parsed = parse_args(&argv(&["--min-entropy", "1.5", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.min_entropy, 1.5);
+        assert!(parse_args(&argv(&["--min-entropy", "-1", "."]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["--line-span-min-lines", "0", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn format_flag_selects_formatter_and_conflicts_with_json() {
+        let parsed = parse_args(&argv(&["--format", "json", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.format, "json");
+        assert!(parsed.json);
+
+        let parsed = parse_args(&argv(&["--json", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.format, "json");
+
+        assert!(
+            parse_args(
+                &argv(&["--json", "--format", "sarif", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
+        assert!(parse_args(&argv(&["--format", "../x", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn sample_takes_a_fraction_or_a_file_count() {
+        let sample = |raw: &str| {
+            parse_args(&argv(&["--report", "--sample", raw, "."]), Localization::En)
+                .map(|parsed| parsed.options.sample.unwrap())
+        };
+        assert_eq!(sample("0.25").unwrap().as_fraction(), Some(0.25));
+        assert_eq!(sample("1.0").unwrap().as_fraction(), Some(1.0));
+        assert_eq!(sample("500").unwrap().as_files(), Some(500));
+        for bad in ["0", "0.0", "1.5", "-3", "ten", "10%"] {
+            assert!(sample(bad).is_err(), "{bad}");
+        }
+        assert!(
+            parse_args(
+                &argv(&["snapshot", "--sample", "0.5", "-o", "s", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn shard_is_one_based_and_snapshot_only() {
+        let parsed = parse_args(
+            &argv(&["snapshot", "--shard", "2/4", "-o", "s.partial", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        let shard = parsed.options.shard.unwrap();
+        assert_eq!((shard.index(), shard.count()), (1, 4));
+
+        for bad in ["0/4", "5/4", "1/0", "1", "a/b"] {
+            assert!(
+                parse_args(
+                    &argv(&["snapshot", "--shard", bad, "-o", "s", "."]),
+                    Localization::En
                 )
-                .to_string()
-            })?;
-            ignore_dirs.push(value.to_string());
-            i += 2;
-            continue;
-        }
-        if arg == "-h" || arg == "--help" {
-            i += 1;
-            continue;
-        }
-        if arg == "-V" || arg == "--version" {
-            i += 1;
-            continue;
-        }
-        if arg.starts_with('-') {
-            return Err(format!(
-                "{} {arg}",
-                tr(localization, "Unknown option:", "未知参数:"),
-            ));
+                .is_err(),
+                "{bad}"
+            );
         }
-        roots.push(PathBuf::from(arg));
-        i += 1;
+        assert!(parse_args(&argv(&["--shard", "1/2", "."]), Localization::En).is_err());
     }
 
-    if report && code_spans {
-        return Err(tr(
-            localization,
-            "--report conflicts with --code-spans",
-            "--report 与 --code-spans 不能同时使用",
+    #[test]
+    fn merge_shards_takes_partials_and_implies_report() {
+        let parsed = parse_args(
+            &argv(&["merge-shards", "a.partial", "b.partial"]),
+            Localization::En,
         )
-        .to_string());
+        .unwrap();
+        assert!(parsed.merge_shards);
+        assert!(parsed.report);
+        assert_eq!(parsed.roots.len(), 2);
+
+        assert!(parse_args(&argv(&["merge-shards"]), Localization::En).is_err());
     }
 
-    let mut options = ScanOptions::default();
-    options.respect_gitignore = respect_gitignore;
-    options.cross_repo_only = cross_repo_only;
-    options.follow_symlinks = follow_symlinks;
-    if let Some(max_file_size) = max_file_size {
-        options.max_file_size = Some(max_file_size);
+    #[test]
+    fn test_code_filters_are_mutually_exclusive() {
+        let parsed = parse_args(&argv(&["--tests-only", "."]), Localization::En).unwrap();
+        assert_eq!(
+            parsed.options.test_code_filter,
+            Some(TestCodeFilter::TestsOnly)
+        );
+        let err = parse_args(
+            &argv(&["--exclude-tests", "--tests-only", "."]),
+            Localization::En,
+        )
+        .unwrap_err();
+        assert!(err.contains("--tests-only"));
     }
-    if let Some(max_files) = max_files {
-        options.max_files = Some(max_files);
+
+    #[test]
+    fn unicode_normalize_accepts_nfc_and_nfkc() {
+        let parsed = parse_args(
+            &argv(&["--unicode-normalize", "nfkc", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.options.unicode_normalization,
+            Some(UnicodeNormalization::Nfkc)
+        );
+        assert!(
+            parse_args(&argv(&["."]), Localization::En)
+                .unwrap()
+                .options
+                .unicode_normalization
+                .is_none()
+        );
+        assert!(
+            parse_args(
+                &argv(&["--unicode-normalize", "nfd", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
     }
-    if let Some(max_total_bytes) = max_total_bytes {
-        options.max_total_bytes = Some(max_total_bytes);
+
+    #[test]
+    fn suppressions_apply_to_scans_but_not_to_subcommands() {
+        let parsed = parse_args(
+            &argv(&["--code-spans", "--suppressions", "s.toml", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.suppressions, Some(PathBuf::from("s.toml")));
+        assert!(
+            parse_args(
+                &argv(&["genealogy", "--suppressions", "s.toml", "a.dcs", "b.dcs"]),
+                Localization::En
+            )
+            .is_err()
+        );
     }
-    if let Some(max_normalized_chars) = max_normalized_chars {
-        options.max_normalized_chars = Some(max_normalized_chars);
+
+    #[test]
+    fn review_comments_require_report_and_own_the_baseline() {
+        let parsed = parse_args(
+            &argv(&[
+                "--report",
+                "--format",
+                "review-comments",
+                "--baseline",
+                "main.json",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.baseline, Some(PathBuf::from("main.json")));
+        assert!(
+            parse_args(
+                &argv(&["--format", "review-comments", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
+        assert!(
+            parse_args(
+                &argv(&["--report", "--baseline", "main.json", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
     }
-    if let Some(max_tokens) = max_tokens {
-        options.max_tokens = Some(max_tokens);
+
+    #[test]
+    fn file_similarity_matrix_requires_report() {
+        let parsed = parse_args(
+            &argv(&["--report", "--file-similarity-matrix", "sim.csv", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.file_similarity_matrix,
+            Some(PathBuf::from("sim.csv"))
+        );
+        assert!(parsed.options.file_similarity);
+        assert!(
+            parse_args(
+                &argv(&["--file-similarity-matrix", "sim.csv", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
     }
-    if let Some(min_match_len) = min_match_len {
-        options.min_match_len = min_match_len;
+
+    #[test]
+    fn max_file_time_is_in_milliseconds() {
+        let parsed = parse_args(&argv(&["--max-file-time", "250", "."]), Localization::En).unwrap();
+        assert_eq!(
+            parsed.options.max_file_time,
+            Some(Duration::from_millis(250))
+        );
+        assert!(parse_args(&argv(&["--max-file-time", "0", "."]), Localization::En).is_err());
     }
-    if let Some(min_token_len) = min_token_len {
-        options.min_token_len = min_token_len;
+
+    #[test]
+    fn max_groups_per_detector_zero_lifts_the_cap() {
+        let parsed = parse_args(&argv(&["."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.max_groups_per_detector, Some(1_000_000));
+        let parsed = parse_args(
+            &argv(&["--max-groups-per-detector", "50", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.max_groups_per_detector, Some(50));
+        let parsed = parse_args(
+            &argv(&["--max-groups-per-detector", "0", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.max_groups_per_detector, None);
     }
-    if let Some(similarity_threshold) = similarity_threshold {
-        options.similarity_threshold = similarity_threshold;
+
+    #[test]
+    fn log_skips_takes_a_path() {
+        let parsed = parse_args(
+            &argv(&["--log-skips", "skips.ndjson", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.log_skips, Some(PathBuf::from("skips.ndjson")));
+        assert!(parse_args(&argv(&["--log-skips"]), Localization::En).is_err());
     }
-    if let Some(simhash_max_distance) = simhash_max_distance {
-        options.simhash_max_distance = simhash_max_distance;
+
+    #[test]
+    fn filter_path_requires_report() {
+        let parsed = parse_args(
+            &argv(&[
+                "--report",
+                "--filter-path",
+                "src/payments/**",
+                "--filter-path-trim",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.path_filter, ["src/payments/**"]);
+        assert!(parsed.options.path_filter_trim);
+        assert!(parse_args(&argv(&["--filter-path", "src/**", "."]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["--report", "--filter-path-trim", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
     }
-    if let Some(max_report_items) = max_report_items {
-        options.max_report_items = max_report_items;
+
+    #[test]
+    fn exclude_and_import_ignores_are_repeatable() {
+        let parsed = parse_args(
+            &argv(&[
+                "--exclude",
+                "*.min.js",
+                "--import-ignores",
+                ".jscpd.json",
+                "--exclude",
+                "gen/",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.ignore_patterns, ["*.min.js", "gen/"]);
+        assert_eq!(parsed.import_ignores, [PathBuf::from(".jscpd.json")]);
     }
-    options.ignore_dirs.extend(ignore_dirs);
 
-    let roots = if roots.is_empty() {
-        vec![env::current_dir().map_err(|e| {
-            format!(
-                "{} {e}",
-                tr(localization, "failed to get cwd:", "无法获取当前目录:"),
-            )
-        })?]
-    } else {
-        roots
-    };
+    #[test]
+    fn contained_groups_are_pruned_unless_disabled() {
+        let parsed = parse_args(&argv(&["--report", "."]), Localization::En).unwrap();
+        assert!(parsed.options.prune_contained_groups);
+        let parsed = parse_args(
+            &argv(&["--report", "--no-prune-contained", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert!(!parsed.options.prune_contained_groups);
+    }
 
-    if cross_repo_only && roots.len() < 2 {
-        return Err(tr(
-            localization,
-            "--cross-repo-only requires at least 2 roots",
-            "--cross-repo-only 需要至少 2 个 root",
+    #[test]
+    fn default_excludes_are_on_unless_disabled() {
+        let parsed = parse_args(&argv(&["."]), Localization::En).unwrap();
+        assert!(
+            parsed
+                .options
+                .default_excludes
+                .contains(&"Cargo.lock".to_string())
+        );
+        let parsed = parse_args(&argv(&["--no-default-excludes", "."]), Localization::En).unwrap();
+        assert!(parsed.options.default_excludes.is_empty());
+    }
+
+    #[test]
+    fn builtin_skips_are_on_unless_disabled() {
+        let parsed = parse_args(&argv(&["."]), Localization::En).unwrap();
+        assert!(parsed.options.builtin_skips);
+
+        let parsed = parse_args(
+            &argv(&[
+                "--no-builtin-skips",
+                "--skip-snippet",
+                "header.txt",
+                "--skip-snippet",
+                "getters.java",
+                "--stop-sequences",
+                "stops.txt",
+                ".",
+            ]),
+            Localization::En,
         )
-        .to_string());
+        .unwrap();
+        assert!(!parsed.options.builtin_skips);
+        assert_eq!(
+            parsed.skip_snippets,
+            [PathBuf::from("header.txt"), PathBuf::from("getters.java")]
+        );
+        assert_eq!(parsed.stop_sequences, [PathBuf::from("stops.txt")]);
     }
 
-    Ok(ParsedArgs {
-        localization,
-        json,
-        stats,
-        strict,
-        report,
-        code_spans,
-        roots,
-        options,
-    })
-}
+    #[test]
+    fn against_snapshot_implies_report_and_allows_single_root_cross_repo() {
+        let parsed = parse_args(
+            &argv(&["--against-snapshot", "c.dcs", "--cross-repo-only", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert!(parsed.report);
+        assert_eq!(parsed.against_snapshot, Some(PathBuf::from("c.dcs")));
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn cache_dir_requires_a_plain_report() {
+        let parsed = parse_args(
+            &argv(&["--report", "--cache-dir", ".cache/dcc", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.cache_dir, Some(PathBuf::from(".cache/dcc")));
+        for args in [
+            &["--cache-dir", "c", "."][..],
+            &["--cache-dir", "c", "--stdin-json"],
+            &["--cache-dir", "c", "--against-snapshot", "s.dcs", "."],
+            &[
+                "--report",
+                "--cache-dir",
+                "c",
+                "--file-similarity-matrix",
+                "m.csv",
+                ".",
+            ],
+        ] {
+            assert!(
+                parse_args(&argv(args), Localization::En).is_err(),
+                "{args:?}"
+            );
+        }
+    }
 
-    fn argv(args: &[&str]) -> Vec<String> {
-        args.iter().map(|s| s.to_string()).collect()
+    #[test]
+    fn stdin_json_implies_report_and_needs_no_root() {
+        let parsed = parse_args(&argv(&["--stdin-json"]), Localization::En).unwrap();
+        assert!(parsed.report);
+        assert!(parsed.stdin_json);
+        assert!(parsed.roots.is_empty());
+
+        let parsed = parse_args(
+            &argv(&["--stdin-json", "--cross-repo-only", "src"]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.roots, [PathBuf::from("src")]);
+        assert!(
+            parse_args(
+                &argv(&["--stdin-json", "--cross-repo-only"]),
+                Localization::En
+            )
+            .is_err()
+        );
+        assert!(parse_args(&argv(&["--stdin-json", "--code-spans"]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["--stdin-json", "--against-snapshot", "c.dcs"]),
+                Localization::En
+            )
+            .is_err()
+        );
     }
 
     #[test]
@@ -655,6 +3765,306 @@ mod tests {
         assert!(err.contains("至少 2"));
     }
 
+    #[test]
+    fn threads_flag_sets_worker_count() {
+        let parsed = parse_args(&argv(&["--threads", "4", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.threads, 4);
+        let parsed = parse_args(&argv(&["."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.threads, 0);
+        assert!(parse_args(&argv(&["--threads", "-1", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn sort_flag_sets_report_sort() {
+        let parsed = parse_args(
+            &argv(&["--report", "--sort", "size", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.report_sort, ReportSort::Size);
+        assert!(parse_args(&argv(&["--sort", "name", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn file_listing_flag_forces_a_strategy() {
+        let parsed =
+            parse_args(&argv(&["--file-listing", "walker", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.file_listing, FileListing::Walker);
+        let parsed = parse_args(&argv(&["--file-listing", "git", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.options.file_listing, FileListing::GitCli);
+        let err = parse_args(&argv(&["--file-listing", "ls", "."]), Localization::En).unwrap_err();
+        assert!(err.contains("auto, git or walker"));
+    }
+
+    #[test]
+    fn budget_order_flag_sets_the_read_order() {
+        let parsed = parse_args(
+            &argv(&["--max-files", "10", "--budget-order", "smallest", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.budget_order, BudgetOrder::SmallestFirst);
+        assert!(parse_args(&argv(&["--budget-order", "size", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn path_prefix_map_flags_accumulate() {
+        let parsed = parse_args(
+            &argv(&[
+                "--path-prefix-map",
+                "/builds/runner=/home/dev",
+                "--path-prefix-map",
+                "/tmp/x=",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.options.path_prefix_map,
+            [
+                (PathBuf::from("/builds/runner"), PathBuf::from("/home/dev")),
+                (PathBuf::from("/tmp/x"), PathBuf::new()),
+            ]
+        );
+        for bad in ["/builds", "=/home"] {
+            assert!(parse_args(&argv(&["--path-prefix-map", bad, "."]), Localization::En).is_err());
+        }
+    }
+
+    #[test]
+    fn language_scale_flags_accumulate() {
+        let parsed = parse_args(
+            &argv(&[
+                "--report",
+                "--language-scale",
+                "Java=2",
+                "--language-scale",
+                "python=0.5",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.options.language_token_scales,
+            [("java".to_string(), 2.0), ("python".to_string(), 0.5)]
+        );
+        for bad in ["java", "java=0", "java=x"] {
+            assert!(parse_args(&argv(&["--language-scale", bad, "."]), Localization::En).is_err());
+        }
+    }
+
+    #[test]
+    fn preview_flags_set_length_and_context() {
+        let parsed = parse_args(
+            &argv(&["--preview-chars", "40", "--context", "2", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert_eq!(parsed.options.preview_max_chars, Some(40));
+        assert_eq!(parsed.options.preview_context_lines, 2);
+        assert!(parse_args(&argv(&["--context"]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn color_flag_accepts_always_never_auto() {
+        let parsed = parse_args(&argv(&["--color", "always", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.color, ColorChoice::Always);
+        let parsed = parse_args(&argv(&["."]), Localization::En).unwrap();
+        assert_eq!(parsed.color, ColorChoice::Auto);
+        assert!(parse_args(&argv(&["--color", "yes", "."]), Localization::En).is_err());
+        assert!(!parsed.hyperlinks);
+        let parsed = parse_args(&argv(&["--hyperlinks", "."]), Localization::En).unwrap();
+        assert!(parsed.hyperlinks);
+        assert!(!parsed.occurrence_sources);
+        let parsed = parse_args(&argv(&["--occurrence-sources", "."]), Localization::En).unwrap();
+        assert!(parsed.occurrence_sources);
+    }
+
+    #[test]
+    fn link_template_is_validated() {
+        let template = "https://github.com/org/{repo}/blob/{rev}/{path}#L{start}-L{end}";
+        let parsed =
+            parse_args(&argv(&["--link-template", template, "."]), Localization::En).unwrap();
+        assert_eq!(parsed.link_template.as_deref(), Some(template));
+        assert!(
+            parse_args(
+                &argv(&["--link-template", "https://x/{file}", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn only_and_omit_filter_report_sections() {
+        let parsed = parse_args(
+            &argv(&[
+                "--report",
+                "--only",
+                "file-duplicates,blockDuplicates",
+                "--omit",
+                "block-*",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert!(parsed.section_filter.keeps("fileDuplicates"));
+        assert!(!parsed.section_filter.keeps("blockDuplicates"));
+        assert!(!parsed.section_filter.keeps("tokenSpanDuplicates"));
+        assert!(parse_args(&argv(&["--omit", "similar-*", "."]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["--report", "--only", "nope", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn preset_values_yield_to_explicit_flags() {
+        let parsed = parse_args(
+            &argv(&[
+                "--preset",
+                "quick",
+                "--max-total-bytes",
+                "1000",
+                "--max-items",
+                "tokenSpanDuplicates=10",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        let options = &parsed.options;
+        assert_eq!(options.max_total_bytes, Some(1000));
+        assert_eq!(
+            options
+                .max_items_per_detector
+                .get(&ReportSection::TokenSpanDuplicates),
+            Some(&10)
+        );
+        assert_eq!(
+            options
+                .max_items_per_detector
+                .get(&ReportSection::SimilarBlocksMinhash),
+            Some(&0)
+        );
+        assert!(parse_args(&argv(&["--preset", "fast", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn group_by_file_requires_report() {
+        let parsed = parse_args(
+            &argv(&["--report", "--group-by", "file", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert!(parsed.group_by_file);
+        assert!(parse_args(&argv(&["--group-by", "file", "."]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["--report", "--group-by", "dir", "."]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn suggest_excludes_requires_report() {
+        let parsed = parse_args(
+            &argv(&["--report", "--suggest-excludes", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert!(parsed.suggest_excludes);
+        assert!(parse_args(&argv(&["--suggest-excludes", "."]), Localization::En).is_err());
+    }
+
+    #[test]
+    fn emit_blocks_takes_files_and_no_scan_mode() {
+        let parsed = parse_args(&argv(&["--emit-blocks", "src/lib.rs"]), Localization::En).unwrap();
+        assert!(parsed.emit_blocks);
+        assert_eq!(parsed.roots, [PathBuf::from("src/lib.rs")]);
+        assert!(parse_args(&argv(&["--emit-blocks"]), Localization::En).is_err());
+        assert!(
+            parse_args(
+                &argv(&["--emit-blocks", "--report", "a.rs"]),
+                Localization::En
+            )
+            .is_err()
+        );
+        assert!(
+            parse_args(
+                &argv(&["explain", "--emit-blocks", "a", "b"]),
+                Localization::En
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn aggregate_ownership_requires_report_and_owns_codeowners() {
+        let parsed = parse_args(
+            &argv(&[
+                "--report",
+                "--aggregate",
+                "ownership",
+                "--codeowners",
+                "OWNERS",
+                ".",
+            ]),
+            Localization::En,
+        )
+        .unwrap();
+        assert!(parsed.aggregate_ownership);
+        assert_eq!(parsed.codeowners, Some(PathBuf::from("OWNERS")));
+        for args in [
+            &["--aggregate", "ownership", "."][..],
+            &["--report", "--aggregate", "team", "."],
+            &["--report", "--codeowners", "OWNERS", "."],
+            &[
+                "--report",
+                "--aggregate",
+                "ownership",
+                "--group-by",
+                "file",
+                ".",
+            ],
+            &[
+                "--report",
+                "--aggregate",
+                "ownership",
+                "--format",
+                "review-comments",
+                ".",
+            ],
+        ] {
+            assert!(
+                parse_args(&argv(args), Localization::En).is_err(),
+                "{args:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn strict_policy_implies_strict() {
+        let parsed = parse_args(
+            &argv(&["--strict-policy", "budget,bucket", "."]),
+            Localization::En,
+        )
+        .unwrap();
+        assert!(parsed.strict);
+        assert!(parsed.strict_policy.budget && parsed.strict_policy.bucket);
+        assert!(!parsed.strict_policy.permission);
+        let parsed = parse_args(&argv(&["--strict", "."]), Localization::En).unwrap();
+        assert_eq!(parsed.strict_policy, StrictPolicy::default());
+    }
+
     #[test]
     fn dashdash_terminates_option_parsing() {
         let parsed = parse_args(&argv(&["--", "--cross-repo-only"]), Localization::En).unwrap();
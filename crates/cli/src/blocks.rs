@@ -0,0 +1,178 @@
+use std::io;
+use std::path::PathBuf;
+
+use dup_code_check_core::ScanOptions;
+use dup_code_check_core::tokenize::{FileBlocks, blocks_for_file};
+use serde::Serialize;
+
+use crate::json::write_json;
+use crate::locale::{Localization, tr};
+use crate::render::text::Style;
+
+/// `--emit-blocks --json`: the block tree of one file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonFileBlocks {
+    path: String,
+    min_token_len: usize,
+    token_cap_hit: bool,
+    blocks: Vec<JsonBlock>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonBlock {
+    start_line: u32,
+    end_line: u32,
+    depth: u32,
+    /// Indices of the directly nested blocks in `blocks`.
+    children: Vec<usize>,
+    token_len: usize,
+    subtree_len: usize,
+}
+
+/// `--emit-blocks`: print the brace block tree of each of `files`, labelled with the paths
+/// given on the command line.
+pub(crate) fn run_emit_blocks(
+    localization: Localization,
+    labels: &[PathBuf],
+    files: &[PathBuf],
+    options: &ScanOptions,
+    json: bool,
+    style: Style,
+) -> io::Result<()> {
+    let mut trees = Vec::with_capacity(files.len());
+    for (label, file) in labels.iter().zip(files) {
+        let label = label.display().to_string();
+        let with_path = |err: io::Error| io::Error::new(err.kind(), format!("{label}: {err}"));
+        if file.is_dir() {
+            return Err(with_path(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                tr(
+                    localization,
+                    "--emit-blocks expects files, not directories",
+                    "--emit-blocks 需要文件而不是目录",
+                ),
+            )));
+        }
+        let tree = blocks_for_file(file, options).map_err(with_path)?;
+        trees.push((label, tree));
+    }
+
+    if json {
+        let trees: Vec<JsonFileBlocks> = trees
+            .into_iter()
+            .map(|(path, tree)| JsonFileBlocks {
+                path,
+                min_token_len: tree.min_token_len,
+                token_cap_hit: tree.token_cap_hit,
+                blocks: tree
+                    .blocks
+                    .into_iter()
+                    .map(|block| JsonBlock {
+                        start_line: block.start_line,
+                        end_line: block.end_line,
+                        depth: block.depth,
+                        children: block.children,
+                        token_len: block.token_len,
+                        subtree_len: block.subtree_len,
+                    })
+                    .collect(),
+            })
+            .collect();
+        return write_json(&trees);
+    }
+    for (path, tree) in &trees {
+        print!("{}", format_blocks(localization, path, tree, style));
+    }
+    Ok(())
+}
+
+/// One file's blocks, indented by depth, each with what the block detectors made of it.
+fn format_blocks(
+    localization: Localization,
+    path: &str,
+    tree: &FileBlocks,
+    style: Style,
+) -> String {
+    let mut out = format!(
+        "{} (minTokenLen {}, {} {})\n",
+        style.path(path),
+        tree.min_token_len,
+        tree.blocks.len(),
+        tr(localization, "blocks", "个块"),
+    );
+    if tree.token_cap_hit {
+        out.push_str(tr(
+            localization,
+            "  (token stream cut at --max-tokens-per-file; later blocks are missing)\n",
+            "  （token 流在 --max-tokens-per-file 处截断；之后的块缺失）\n",
+        ));
+    }
+    for block in &tree.blocks {
+        let verdict = if block.token_len == 0 {
+            tr(
+                localization,
+                "empty or unclosed, skipped",
+                "空块或未闭合，跳过",
+            )
+        } else if block.token_len < tree.min_token_len {
+            tr(
+                localization,
+                "below minTokenLen, skipped",
+                "低于 minTokenLen，跳过",
+            )
+        } else if block.subtree_len < tree.min_token_len {
+            tr(
+                localization,
+                "block only, subtree below minTokenLen",
+                "仅 block，子树低于 minTokenLen",
+            )
+        } else {
+            tr(localization, "block + AST subtree", "block + AST 子树")
+        };
+        out.push_str(&format!(
+            "{:indent$}{}-{}: {} {}, {} {} ({verdict})\n",
+            "",
+            block.start_line,
+            block.end_line,
+            block.token_len,
+            tr(localization, "tokens", "个 token"),
+            tr(localization, "subtree", "子树"),
+            block.subtree_len,
+            indent = block.depth as usize * 2,
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn blocks_are_indented_with_their_verdicts() {
+        let dir = std::env::temp_dir().join(format!("dcc-emit-blocks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.rs");
+        fs::write(
+            &path,
+            "fn a() {\n    if x {\n        y();\n    }\n}\nfn b() {}\n",
+        )
+        .unwrap();
+        let mut options = ScanOptions::default();
+        options.min_token_len = 5;
+        let tree = blocks_for_file(&path, &options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            format_blocks(Localization::En, "a.rs", &tree, Style::new(false)),
+            "a.rs (minTokenLen 5, 3 blocks)\n\
+             \x20 1-5: 8 tokens, subtree 4 (block only, subtree below minTokenLen)\n\
+             \x20   2-4: 4 tokens, subtree 4 (below minTokenLen, skipped)\n\
+             \x20 6-6: 0 tokens, subtree 0 (empty or unclosed, skipped)\n"
+        );
+    }
+}
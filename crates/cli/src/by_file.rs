@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::json::{JsonDuplicateFile, JsonDuplicateSpanOccurrence, JsonDuplicationReport};
+
+/// `--group-by file`: the report inverted so each file lists every group it takes part in.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonFileView {
+    pub(crate) files: Vec<JsonFileEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonFileEntry {
+    pub(crate) repo_id: usize,
+    pub(crate) repo_label: Arc<str>,
+    pub(crate) path: Arc<str>,
+    pub(crate) duplicates: Vec<JsonFileDuplicate>,
+}
+
+/// One group (or similar pair) seen from one of its places in a file.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonFileDuplicate {
+    /// JSON key of the report section the group comes from.
+    pub(crate) section: &'static str,
+    /// Group hash; `None` for similar pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) hash: Option<String>,
+    /// Similarity score; only for similar pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) score: Option<f64>,
+    /// Lines of this file in the group; `None` for whole-file duplicates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) start_line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) end_line: Option<u32>,
+    /// The group's other places, in report order.
+    pub(crate) partners: Vec<JsonFileLocation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonFileLocation {
+    pub(crate) repo_id: usize,
+    pub(crate) repo_label: Arc<str>,
+    pub(crate) path: Arc<str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) start_line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) end_line: Option<u32>,
+}
+
+impl JsonFileLocation {
+    /// `path` or `path:start-end`.
+    pub(crate) fn location(&self) -> String {
+        match (self.start_line, self.end_line) {
+            (Some(start), Some(end)) => format!("{}:{start}-{end}", self.path),
+            _ => self.path.to_string(),
+        }
+    }
+}
+
+impl From<&JsonDuplicateSpanOccurrence> for JsonFileLocation {
+    fn from(occ: &JsonDuplicateSpanOccurrence) -> Self {
+        Self {
+            repo_id: occ.repo_id,
+            repo_label: Arc::clone(&occ.repo_label),
+            path: Arc::clone(&occ.path),
+            start_line: Some(occ.start_line),
+            end_line: Some(occ.end_line),
+        }
+    }
+}
+
+#[derive(Default)]
+struct FileViewBuilder {
+    files: BTreeMap<(usize, Arc<str>), JsonFileEntry>,
+}
+
+impl FileViewBuilder {
+    /// Record one group: every place gets an entry whose partners are all the other places.
+    fn add_group(
+        &mut self,
+        section: &'static str,
+        hash: Option<&str>,
+        score: Option<f64>,
+        places: &[JsonFileLocation],
+    ) {
+        for (index, place) in places.iter().enumerate() {
+            let partners = places
+                .iter()
+                .enumerate()
+                .filter(|&(other, _)| other != index)
+                .map(|(_, partner)| partner.clone())
+                .collect();
+            self.files
+                .entry((place.repo_id, Arc::clone(&place.path)))
+                .or_insert_with(|| JsonFileEntry {
+                    repo_id: place.repo_id,
+                    repo_label: Arc::clone(&place.repo_label),
+                    path: Arc::clone(&place.path),
+                    duplicates: Vec::new(),
+                })
+                .duplicates
+                .push(JsonFileDuplicate {
+                    section,
+                    hash: hash.map(str::to_string),
+                    score,
+                    start_line: place.start_line,
+                    end_line: place.end_line,
+                    partners,
+                });
+        }
+    }
+
+    /// Files with the most duplicates first (then by root and path); each file's duplicates
+    /// in line order, whole-file duplicates first.
+    fn finish(self) -> JsonFileView {
+        let mut files: Vec<JsonFileEntry> = self.files.into_values().collect();
+        for file in &mut files {
+            file.duplicates.sort_by_key(|d| (d.start_line, d.end_line));
+        }
+        files.sort_by_key(|file| std::cmp::Reverse(file.duplicates.len()));
+        JsonFileView { files }
+    }
+}
+
+pub(crate) fn group_report_by_file(report: &JsonDuplicationReport) -> JsonFileView {
+    let mut builder = FileViewBuilder::default();
+    for_each_group(report, |section, hash, score, places| {
+        builder.add_group(section, hash, score, places);
+    });
+    builder.finish()
+}
+
+/// Visit every group and similar pair of `report` with its section key, hash or score, and
+/// places, in report order.
+pub(crate) fn for_each_group(
+    report: &JsonDuplicationReport,
+    mut visit: impl FnMut(&'static str, Option<&str>, Option<f64>, &[JsonFileLocation]),
+) {
+    for group in &report.file_duplicates {
+        let places = whole_file_locations(&group.files);
+        visit("fileDuplicates", Some(&group.hash), None, &places);
+    }
+    for (section, groups) in [
+        ("codeSpanDuplicates", &report.code_span_duplicates),
+        ("lineSpanDuplicates", &report.line_span_duplicates),
+        ("tokenSpanDuplicates", &report.token_span_duplicates),
+        ("blockDuplicates", &report.block_duplicates),
+        ("astSubtreeDuplicates", &report.ast_subtree_duplicates),
+    ] {
+        for group in groups {
+            let places: Vec<JsonFileLocation> = group
+                .occurrences
+                .iter()
+                .map(JsonFileLocation::from)
+                .collect();
+            visit(section, Some(&group.hash), None, &places);
+        }
+    }
+    for group in &report.symbol_duplicates {
+        let places = whole_file_locations(&group.files);
+        visit("symbolDuplicates", Some(&group.hash), None, &places);
+    }
+    for pair in &report.import_duplicates {
+        let places = whole_file_locations([&pair.a, &pair.b]);
+        visit("importDuplicates", None, Some(pair.score), &places);
+    }
+    for group in &report.data_duplicates {
+        let places = whole_file_locations(&group.files);
+        visit("dataDuplicates", Some(&group.hash), None, &places);
+    }
+    for (section, pairs) in [
+        ("similarBlocksMinhash", &report.similar_blocks_minhash),
+        ("similarBlocksSimhash", &report.similar_blocks_simhash),
+    ] {
+        for pair in pairs {
+            let places = [
+                JsonFileLocation::from(&pair.a),
+                JsonFileLocation::from(&pair.b),
+            ];
+            visit(section, None, Some(pair.score), &places);
+        }
+    }
+}
+
+fn whole_file_locations<'a>(
+    files: impl IntoIterator<Item = &'a JsonDuplicateFile>,
+) -> Vec<JsonFileLocation> {
+    files
+        .into_iter()
+        .map(|file| JsonFileLocation {
+            repo_id: file.repo_id,
+            repo_label: Arc::clone(&file.repo_label),
+            path: Arc::clone(&file.path),
+            start_line: None,
+            end_line: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::JsonDuplicateSpanGroup;
+
+    fn occurrence(repo_id: usize, path: &str, start_line: u32) -> JsonDuplicateSpanOccurrence {
+        JsonDuplicateSpanOccurrence {
+            repo_id,
+            repo_label: format!("r{repo_id}").into(),
+            path: path.into(),
+            start_line,
+            end_line: start_line + 4,
+            start_col: None,
+            end_col: None,
+            is_test: false,
+            scope: None,
+            last_modified: None,
+            source: None,
+        }
+    }
+
+    fn group(hash: &str, occurrences: Vec<JsonDuplicateSpanOccurrence>) -> JsonDuplicateSpanGroup {
+        JsonDuplicateSpanGroup {
+            hash: hash.to_string(),
+            normalized_len: 10,
+            preview: String::new(),
+            occurrences,
+            truncated_occurrences: 0,
+            duplicated_lines: 0,
+            potential_savings_lines: 0,
+            also_detected_by: Vec::new(),
+            occurrence_previews: Vec::new(),
+            quality: None,
+            age_spread_days: None,
+        }
+    }
+
+    #[test]
+    fn files_list_every_group_with_their_partners() {
+        let mut builder = FileViewBuilder::default();
+        for group in [
+            group(
+                "aa",
+                vec![occurrence(0, "a.rs", 20), occurrence(1, "b.rs", 1)],
+            ),
+            group(
+                "bb",
+                vec![
+                    occurrence(0, "a.rs", 3),
+                    occurrence(0, "c.rs", 7),
+                    occurrence(1, "b.rs", 30),
+                ],
+            ),
+        ] {
+            let places: Vec<JsonFileLocation> = group
+                .occurrences
+                .iter()
+                .map(JsonFileLocation::from)
+                .collect();
+            builder.add_group("tokenSpanDuplicates", Some(&group.hash), None, &places);
+        }
+        let view = builder.finish();
+
+        let paths: Vec<&str> = view.files.iter().map(|f| f.path.as_ref()).collect();
+        assert_eq!(paths, ["a.rs", "b.rs", "c.rs"]);
+        let a = &view.files[0];
+        assert_eq!(a.duplicates.len(), 2);
+        assert_eq!(a.duplicates[0].hash.as_deref(), Some("bb"));
+        assert_eq!(a.duplicates[0].start_line, Some(3));
+        let partners: Vec<String> = a.duplicates[0]
+            .partners
+            .iter()
+            .map(JsonFileLocation::location)
+            .collect();
+        assert_eq!(partners, ["c.rs:7-11", "b.rs:30-34"]);
+        assert_eq!(view.files[2].duplicates.len(), 1);
+    }
+}
@@ -0,0 +1,523 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use dup_code_check_core::{ScanOptions, ScanStats, corpus_fingerprint};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::json::{JsonDuplicationReport, JsonScanOptions, JsonScanStats};
+
+/// Hit/miss counters of the `--cache-dir` runs since the cache was last cleared.
+const USAGE_FILE: &str = "usage.json";
+/// Temporary files older than this are left over from an interrupted write.
+const STALE_TMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// One `--cache-dir` entry: the last report computed for a set of roots and options.
+///
+/// The file is named after a hash of the roots, options and tool version, so each option set
+/// keeps exactly one entry, which is reused while [`corpus_fingerprint`] is unchanged and
+/// replaced by the next scan otherwise.
+pub(crate) struct ReportCache {
+    path: PathBuf,
+    key: Value,
+    corpus: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntryRef<'a> {
+    key: &'a Value,
+    corpus: &'a str,
+    scan_stats: &'a JsonScanStats,
+    report: &'a JsonDuplicationReport,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    key: Value,
+    corpus: String,
+    scan_stats: JsonScanStats,
+    report: JsonDuplicationReport,
+}
+
+/// The part of an entry `cache status` and `cache gc` look at.
+#[derive(Deserialize)]
+struct CacheEntryKey {
+    key: Value,
+}
+
+/// Outcome of the last `--cache-dir` run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum CacheLookup {
+    Hit,
+    Miss,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CacheUsage {
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) last_run: Option<CacheLookup>,
+}
+
+impl CacheUsage {
+    pub(crate) fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+/// `cache status`: what a cache directory holds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CacheStatus {
+    pub(crate) entries: usize,
+    /// Entries no run can hit any more: a root was deleted, the tool version changed or the
+    /// file is unreadable. `cache gc` removes them.
+    pub(crate) stale_entries: usize,
+    /// Size of the entries, the usage counters and any temporary files.
+    pub(crate) bytes: u64,
+    #[serde(flatten)]
+    pub(crate) usage: CacheUsage,
+    pub(crate) hit_rate: Option<f64>,
+}
+
+/// What `cache clear` or `cache gc` removed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CacheEviction {
+    pub(crate) entries: usize,
+    pub(crate) bytes: u64,
+}
+
+/// One file of a cache directory.
+struct CacheFile {
+    path: PathBuf,
+    kind: CacheFileKind,
+    bytes: u64,
+    modified: SystemTime,
+}
+
+#[derive(PartialEq, Eq)]
+enum CacheFileKind {
+    Entry,
+    Usage,
+    Tmp,
+}
+
+impl ReportCache {
+    /// Fingerprint the corpus `roots` and `options` select (this reads every file once).
+    pub(crate) fn open(dir: &Path, roots: &[PathBuf], options: &ScanOptions) -> io::Result<Self> {
+        let roots: Vec<PathBuf> = roots
+            .iter()
+            .map(|root| fs::canonicalize(root).unwrap_or_else(|_| root.clone()))
+            .collect();
+        let key = cache_key(&roots, options);
+        let name = format!("report-{:016x}.json", fnv1a64(key.to_string().as_bytes()));
+        Ok(Self {
+            path: dir.join(name),
+            key,
+            corpus: format!("{:016x}", corpus_fingerprint(&roots, options)?),
+        })
+    }
+
+    /// The cached report and its scan counters, if the entry matches this corpus. A missing,
+    /// unreadable or stale entry is a miss. A hit marks the entry as recently used for
+    /// [`gc_cache`].
+    pub(crate) fn load(&self) -> Option<(JsonDuplicationReport, ScanStats)> {
+        let bytes = fs::read(&self.path).ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&bytes).ok()?;
+        if entry.key != self.key || entry.corpus != self.corpus {
+            return None;
+        }
+        if let Ok(file) = fs::File::options().append(true).open(&self.path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some((entry.report, ScanStats::from(&entry.scan_stats)))
+    }
+
+    /// Count this run in the directory's usage counters (best effort: concurrent runs may
+    /// lose an update).
+    pub(crate) fn record(&self, lookup: CacheLookup) -> io::Result<()> {
+        let Some(dir) = self.path.parent() else {
+            return Ok(());
+        };
+        let mut usage = read_usage(dir);
+        match lookup {
+            CacheLookup::Hit => usage.hits += 1,
+            CacheLookup::Miss => usage.misses += 1,
+        }
+        usage.last_run = Some(lookup);
+        let bytes = serde_json::to_vec(&usage)
+            .map_err(|e| io::Error::other(format!("json encode: {e}")))?;
+        write_atomically(&dir.join(USAGE_FILE), &bytes)
+    }
+
+    /// Replace the entry (written to a temporary file and renamed, so readers never see a
+    /// partial entry).
+    pub(crate) fn store(
+        &self,
+        report: &JsonDuplicationReport,
+        scan_stats: &JsonScanStats,
+    ) -> io::Result<()> {
+        let entry = CacheEntryRef {
+            key: &self.key,
+            corpus: &self.corpus,
+            scan_stats,
+            report,
+        };
+        let bytes = serde_json::to_vec(&entry)
+            .map_err(|e| io::Error::other(format!("json encode: {e}")))?;
+        write_atomically(&self.path, &bytes)
+    }
+}
+
+/// What a cached report depends on: the tool version, the roots and every scan option.
+///
+/// [`JsonScanOptions`] is the `meta` summary of the options and leaves some out (e.g. only the
+/// number of `--skip-snippet` files), so the options it summarizes are added in full under
+/// `exact`; snippets by hash, to keep entries small.
+fn cache_key(roots: &[PathBuf], options: &ScanOptions) -> Value {
+    let skip_snippets: Vec<String> = options
+        .skip_snippets
+        .iter()
+        .map(|snippet| format!("{:016x}", fnv1a64(snippet.as_bytes())))
+        .collect();
+    serde_json::json!({
+        "toolVersion": env!("CARGO_PKG_VERSION"),
+        "roots": roots,
+        "options": JsonScanOptions::from(options),
+        "exact": {
+            "skipSnippets": skip_snippets,
+            "stopSequences": options.stop_sequences,
+            "languageTokenScales": options.language_token_scales,
+            "groupSuppressions": options
+                .group_suppressions
+                .iter()
+                .map(|suppression| {
+                    (
+                        suppression.section().map(|section| section.as_str()),
+                        format!("{:016x}", suppression.content_hash()),
+                        suppression.is_active(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+            "maxFileTimeNanos": options
+                .max_file_time
+                .map(|limit| u64::try_from(limit.as_nanos()).unwrap_or(u64::MAX)),
+        },
+    })
+}
+
+/// `cache status`: count the entries of `dir` (a missing directory is an empty cache).
+pub(crate) fn cache_status(dir: &Path) -> io::Result<CacheStatus> {
+    let files = cache_files(dir)?;
+    let usage = read_usage(dir);
+    Ok(CacheStatus {
+        entries: files
+            .iter()
+            .filter(|file| file.kind == CacheFileKind::Entry)
+            .count(),
+        stale_entries: files
+            .iter()
+            .filter(|file| file.kind == CacheFileKind::Entry && is_stale_entry(&file.path))
+            .count(),
+        bytes: files.iter().map(|file| file.bytes).sum(),
+        hit_rate: usage.hit_rate(),
+        usage,
+    })
+}
+
+/// `cache clear`: remove every entry, the usage counters and temporary files. Other files in
+/// `dir`, and `dir` itself, are left alone.
+pub(crate) fn clear_cache(dir: &Path) -> io::Result<CacheEviction> {
+    let files = cache_files(dir)?;
+    remove_files(files.iter())
+}
+
+/// `cache gc`: remove stale entries (see [`CacheStatus::stale_entries`]) and leftover
+/// temporary files, then, with `max_bytes`, the least recently used entries until the
+/// directory holds at most `max_bytes`.
+pub(crate) fn gc_cache(dir: &Path, max_bytes: Option<u64>) -> io::Result<CacheEviction> {
+    let now = SystemTime::now();
+    let (mut dead, mut live): (Vec<CacheFile>, Vec<CacheFile>) = cache_files(dir)?
+        .into_iter()
+        .partition(|file| match file.kind {
+            CacheFileKind::Entry => is_stale_entry(&file.path),
+            CacheFileKind::Usage => false,
+            CacheFileKind::Tmp => now
+                .duration_since(file.modified)
+                .is_ok_and(|age| age >= STALE_TMP_AGE),
+        });
+    if let Some(max_bytes) = max_bytes {
+        // Oldest first; ties broken by name so the result does not depend on listing order.
+        live.sort_by(|a, b| (a.modified, &a.path).cmp(&(b.modified, &b.path)));
+        let mut bytes: u64 = live.iter().map(|file| file.bytes).sum();
+        for file in live {
+            if bytes > max_bytes && file.kind == CacheFileKind::Entry {
+                bytes -= file.bytes;
+                dead.push(file);
+            }
+        }
+    }
+    remove_files(dead.iter())
+}
+
+fn cache_files(dir: &Path) -> io::Result<Vec<CacheFile>> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut files = Vec::new();
+    for entry in read_dir {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let kind = if name == USAGE_FILE {
+            CacheFileKind::Usage
+        } else if name.ends_with(".tmp")
+            && (name.starts_with("report-") || name.starts_with("usage.json."))
+        {
+            CacheFileKind::Tmp
+        } else if name.starts_with("report-") && name.ends_with(".json") {
+            CacheFileKind::Entry
+        } else {
+            continue;
+        };
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        files.push(CacheFile {
+            path: entry.path(),
+            kind,
+            bytes: metadata.len(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    Ok(files)
+}
+
+fn remove_files<'a>(files: impl Iterator<Item = &'a CacheFile>) -> io::Result<CacheEviction> {
+    let mut eviction = CacheEviction::default();
+    for file in files {
+        match fs::remove_file(&file.path) {
+            Ok(()) => {}
+            // Removed by a concurrent run.
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        }
+        eviction.bytes += file.bytes;
+        if file.kind == CacheFileKind::Entry {
+            eviction.entries += 1;
+        }
+    }
+    Ok(eviction)
+}
+
+/// An entry written by another tool version, for a root that no longer exists, or that
+/// cannot be read.
+fn is_stale_entry(path: &Path) -> bool {
+    let Some(entry) = fs::read(path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<CacheEntryKey>(&bytes).ok())
+    else {
+        return true;
+    };
+    let key = &entry.key;
+    key["toolVersion"] != env!("CARGO_PKG_VERSION")
+        || key["roots"].as_array().is_none_or(|roots| {
+            roots
+                .iter()
+                .any(|root| root.as_str().is_none_or(|root| !Path::new(root).exists()))
+        })
+}
+
+fn read_usage(dir: &Path) -> CacheUsage {
+    fs::read(dir.join(USAGE_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// Write to a temporary file and rename it, so readers never see a partial file.
+fn write_atomically(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension(format!("json.{}.tmp", std::process::id()));
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use dup_code_check_core::GroupSuppression;
+
+    use super::*;
+    use crate::json::map_report;
+
+    fn temp_dir(suffix: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock should be monotonic")
+            .as_nanos();
+        std::env::temp_dir().join(format!("dup-code-check-cli-{suffix}-{nanos}"))
+    }
+
+    #[test]
+    fn reuses_the_report_until_the_corpus_or_options_change() -> io::Result<()> {
+        let root = temp_dir("cache-root");
+        let cache_dir = temp_dir("cache-dir");
+        fs::create_dir_all(&root)?;
+        let code = "fn main() {\n    let total = compute(1, 2);\n    println!(\"{total}\");\n}\n";
+        fs::write(root.join("a.rs"), code)?;
+        fs::write(root.join("b.rs"), code)?;
+        let roots = [root.clone()];
+        let options = ScanOptions::default();
+
+        let cache = ReportCache::open(&cache_dir, &roots, &options)?;
+        assert!(cache.load().is_none());
+        let outcome =
+            dup_code_check_core::generate_duplication_report_with_stats(&roots, &options)?;
+        let report = map_report(outcome.result);
+        cache.store(&report, &JsonScanStats::from(&outcome.stats))?;
+
+        let (cached, stats) = ReportCache::open(&cache_dir, &roots, &options)?
+            .load()
+            .expect("unchanged corpus hits the cache");
+        assert_eq!(
+            serde_json::to_value(&cached).unwrap(),
+            serde_json::to_value(&report).unwrap()
+        );
+        assert_eq!(stats, outcome.stats);
+
+        let mut other_options = options.clone();
+        other_options.min_match_len = 10;
+        assert!(
+            ReportCache::open(&cache_dir, &roots, &other_options)?
+                .load()
+                .is_none()
+        );
+
+        fs::write(root.join("b.rs"), "fn other() {}\n")?;
+        assert!(
+            ReportCache::open(&cache_dir, &roots, &options)?
+                .load()
+                .is_none()
+        );
+
+        fs::remove_dir_all(&root)?;
+        fs::remove_dir_all(&cache_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn keys_cover_the_options_meta_only_summarizes() {
+        let roots = [PathBuf::from("/repo")];
+        let key = |edit: fn(&mut ScanOptions)| {
+            let mut options = ScanOptions::default();
+            edit(&mut options);
+            cache_key(&roots, &options)
+        };
+        let with_snippet = key(|options| options.skip_snippets = vec!["a()".to_string()]);
+        assert_ne!(
+            with_snippet,
+            key(|options| options.skip_snippets = vec!["b()".to_string()])
+        );
+        assert_ne!(with_snippet, key(|_| {}));
+        assert_ne!(
+            key(|options| {
+                options.stop_sequences = vec![
+                    ("rust".to_string(), String::new()),
+                    ("rust".to_string(), "a".to_string()),
+                ];
+            }),
+            key(|options| {
+                options.stop_sequences = vec![
+                    ("rust".to_string(), "a".to_string()),
+                    ("rust".to_string(), String::new()),
+                ];
+            })
+        );
+        assert_ne!(
+            key(|options| options.max_file_time = Some(Duration::from_micros(1500))),
+            key(|options| options.max_file_time = Some(Duration::from_micros(1900)))
+        );
+        // An expired suppression keeps its group in the report.
+        assert_ne!(
+            key(|options| options.group_suppressions = vec![GroupSuppression::new(None, 1, true)]),
+            key(|options| options.group_suppressions = vec![GroupSuppression::new(None, 1, false)])
+        );
+        assert_eq!(
+            with_snippet,
+            key(|options| options.skip_snippets = vec!["a()".to_string()])
+        );
+    }
+
+    #[test]
+    fn gc_evicts_stale_and_least_recently_used_entries() -> io::Result<()> {
+        let root = temp_dir("cache-gc-root");
+        let cache_dir = temp_dir("cache-gc-dir");
+        fs::create_dir_all(&root)?;
+        fs::write(root.join("a.rs"), "fn main() {}\n")?;
+        let roots = [root.clone()];
+        let report = map_report(dup_code_check_core::generate_duplication_report(
+            &roots,
+            &ScanOptions::default(),
+        )?);
+        let stats = JsonScanStats::from(&ScanStats::default());
+        let store = |min_match_len: usize| -> io::Result<ReportCache> {
+            let mut options = ScanOptions::default();
+            options.min_match_len = min_match_len;
+            let cache = ReportCache::open(&cache_dir, &roots, &options)?;
+            cache.store(&report, &stats)?;
+            Ok(cache)
+        };
+
+        let old = store(10)?;
+        fs::File::options()
+            .append(true)
+            .open(&old.path)?
+            .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1))?;
+        let new = store(20)?;
+        new.record(CacheLookup::Hit)?;
+        new.record(CacheLookup::Miss)?;
+        let status = cache_status(&cache_dir)?;
+        assert_eq!((status.entries, status.stale_entries), (2, 0));
+        assert_eq!((status.usage.hits, status.usage.misses), (1, 1));
+        assert_eq!(status.usage.last_run, Some(CacheLookup::Miss));
+        assert_eq!(status.hit_rate, Some(0.5));
+
+        let removed = gc_cache(&cache_dir, Some(status.bytes - 1))?;
+        assert_eq!(removed.entries, 1);
+        assert!(!old.path.exists() && new.path.exists());
+        assert_eq!(gc_cache(&cache_dir, None)?, CacheEviction::default());
+
+        fs::remove_dir_all(&root)?;
+        assert_eq!(cache_status(&cache_dir)?.stale_entries, 1);
+        assert_eq!(gc_cache(&cache_dir, None)?.entries, 1);
+
+        new.record(CacheLookup::Hit)?;
+        clear_cache(&cache_dir)?;
+        assert_eq!(cache_status(&cache_dir)?, CacheStatus::default());
+        fs::remove_dir_all(&cache_dir)?;
+        Ok(())
+    }
+}
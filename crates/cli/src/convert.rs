@@ -0,0 +1,162 @@
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::Serialize;
+
+/// How a JSON document (report, groups, envelope) is written to or read from bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    /// Pretty-printed JSON, the `--format json` output.
+    Json,
+    /// Compact JSON, gzip-compressed.
+    JsonGz,
+    /// MessagePack with field names, so it converts back to the same JSON.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+}
+
+impl Encoding {
+    /// The `--format` names of the binary-safe encodings, for `--version --json`.
+    pub(crate) const FORMAT_NAMES: &[&str] = &[
+        "json.gz",
+        #[cfg(feature = "msgpack")]
+        "msgpack",
+    ];
+
+    /// `--format <name>` for the encodings other than plain `json`.
+    pub(crate) fn from_format(name: &str) -> Option<Self> {
+        match name {
+            "json.gz" => Some(Self::JsonGz),
+            #[cfg(feature = "msgpack")]
+            "msgpack" => Some(Self::MessagePack),
+            _ => None,
+        }
+    }
+
+    /// Pick the encoding of a `convert` argument from its extension.
+    pub(crate) fn from_path(path: &Path) -> io::Result<Self> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".json.gz") {
+            return Ok(Self::JsonGz);
+        }
+        if name.ends_with(".json") {
+            return Ok(Self::Json);
+        }
+        #[cfg(feature = "msgpack")]
+        if name.ends_with(".msgpack") || name.ends_with(".mpk") {
+            return Ok(Self::MessagePack);
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{}: unknown report encoding (expected .json, .json.gz{})",
+                path.display(),
+                if cfg!(feature = "msgpack") {
+                    " or .msgpack"
+                } else {
+                    "; .msgpack needs the msgpack feature"
+                }
+            ),
+        ))
+    }
+
+    pub(crate) fn write<T: Serialize>(self, out: impl Write, value: &T) -> io::Result<()> {
+        let encode_err = |e: &dyn std::fmt::Display| io::Error::other(format!("encode: {e}"));
+        let mut out = BufWriter::new(out);
+        match self {
+            Self::Json => {
+                serde_json::to_writer_pretty(&mut out, value).map_err(|e| encode_err(&e))?;
+                out.write_all(b"\n")?;
+            }
+            Self::JsonGz => {
+                let mut gz = GzEncoder::new(&mut out, Compression::default());
+                serde_json::to_writer(&mut gz, value).map_err(|e| encode_err(&e))?;
+                gz.finish()?;
+            }
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => {
+                rmp_serde::encode::write_named(&mut out, value).map_err(|e| encode_err(&e))?;
+            }
+        }
+        out.flush()
+    }
+
+    pub(crate) fn read(self, input: impl Read) -> io::Result<serde_json::Value> {
+        let decode_err = |e: &dyn std::fmt::Display| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("decode: {e}"))
+        };
+        let input = BufReader::new(input);
+        match self {
+            Self::Json => serde_json::from_reader(input).map_err(|e| decode_err(&e)),
+            Self::JsonGz => {
+                serde_json::from_reader(GzDecoder::new(input)).map_err(|e| decode_err(&e))
+            }
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => rmp_serde::from_read(input).map_err(|e| decode_err(&e)),
+        }
+    }
+}
+
+/// Read a saved output (`--baseline`, `explain`); files without a known extension are JSON.
+pub(crate) fn read_saved(path: &Path) -> io::Result<serde_json::Value> {
+    read_file(path, Encoding::from_path(path).unwrap_or(Encoding::Json))
+}
+
+/// `convert <input> <output>`: re-encode a saved output, picking both encodings by extension.
+pub(crate) fn run_convert(input: &Path, output: &Path) -> io::Result<()> {
+    let (from, to) = (Encoding::from_path(input)?, Encoding::from_path(output)?);
+    let value = read_file(input, from)?;
+    let file = fs::File::create(output).map_err(|err| with_path(output, err))?;
+    to.write(file, &value).map_err(|err| with_path(output, err))
+}
+
+fn read_file(path: &Path, encoding: Encoding) -> io::Result<serde_json::Value> {
+    let file = fs::File::open(path).map_err(|err| with_path(path, err))?;
+    encoding.read(file).map_err(|err| with_path(path, err))
+}
+
+fn with_path(path: &Path, err: io::Error) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodings_round_trip_and_follow_extensions() {
+        let value = serde_json::json!({ "meta": { "mode": "report" }, "fileDuplicates": [] });
+        let encodings = [
+            Encoding::Json,
+            Encoding::JsonGz,
+            #[cfg(feature = "msgpack")]
+            Encoding::MessagePack,
+        ];
+        for encoding in encodings {
+            let mut bytes = Vec::new();
+            encoding.write(&mut bytes, &value).unwrap();
+            assert_eq!(encoding.read(bytes.as_slice()).unwrap(), value);
+        }
+
+        let mut gz = Vec::new();
+        Encoding::JsonGz.write(&mut gz, &value).unwrap();
+        assert_eq!(gz[..2], [0x1f, 0x8b]);
+
+        assert_eq!(
+            Encoding::from_path(Path::new("out/report.JSON.GZ")).unwrap(),
+            Encoding::JsonGz
+        );
+        assert_eq!(
+            Encoding::from_path(Path::new("report.json")).unwrap(),
+            Encoding::Json
+        );
+        assert!(Encoding::from_path(Path::new("report.txt")).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+use crate::args::subcommand;
+use crate::locale::{Localization, tr};
+
+/// Prefix of the environment variables that provide option defaults.
+pub(crate) const ENV_PREFIX: &str = "DUP_CODE_CHECK_";
+
+enum EnvKind {
+    /// `--flag <value>`.
+    Value,
+    /// `--flag` when the variable is true.
+    Switch,
+    /// `--flag <item>` for each comma-separated item.
+    List,
+}
+
+/// An option that can be set from `DUP_CODE_CHECK_<NAME>`.
+struct EnvOption {
+    name: &'static str,
+    flag: &'static str,
+    kind: EnvKind,
+    /// CLI flags that override the variable (besides `flag` itself).
+    overridden_by: &'static [&'static str],
+}
+
+const fn value(name: &'static str, flag: &'static str) -> EnvOption {
+    EnvOption {
+        name,
+        flag,
+        kind: EnvKind::Value,
+        overridden_by: &[],
+    }
+}
+
+const fn switch(
+    name: &'static str,
+    flag: &'static str,
+    overridden_by: &'static [&'static str],
+) -> EnvOption {
+    EnvOption {
+        name,
+        flag,
+        kind: EnvKind::Switch,
+        overridden_by,
+    }
+}
+
+const ENV_OPTIONS: &[EnvOption] = &[
+    EnvOption {
+        name: "IGNORE_DIRS",
+        flag: "--ignore-dir",
+        kind: EnvKind::List,
+        overridden_by: &[],
+    },
+    EnvOption {
+        name: "EXCLUDES",
+        flag: "--exclude",
+        kind: EnvKind::List,
+        overridden_by: &[],
+    },
+    EnvOption {
+        name: "IMPORT_IGNORES",
+        flag: "--import-ignores",
+        kind: EnvKind::List,
+        overridden_by: &[],
+    },
+    EnvOption {
+        name: "SKIP_SNIPPETS",
+        flag: "--skip-snippet",
+        kind: EnvKind::List,
+        overridden_by: &[],
+    },
+    EnvOption {
+        name: "STOP_SEQUENCES",
+        flag: "--stop-sequences",
+        kind: EnvKind::List,
+        overridden_by: &[],
+    },
+    EnvOption {
+        name: "MAX_ITEMS",
+        flag: "--max-items",
+        kind: EnvKind::List,
+        overridden_by: &[],
+    },
+    EnvOption {
+        name: "PATH_PREFIX_MAP",
+        flag: "--path-prefix-map",
+        kind: EnvKind::List,
+        overridden_by: &[],
+    },
+    EnvOption {
+        name: "LANGUAGE_SCALES",
+        flag: "--language-scale",
+        kind: EnvKind::List,
+        overridden_by: &[],
+    },
+    EnvOption {
+        name: "FORMAT",
+        flag: "--format",
+        kind: EnvKind::Value,
+        overridden_by: &["--json"],
+    },
+    value("COLOR", "--color"),
+    value("STRICT_POLICY", "--strict-policy"),
+    value("MAX_FILE_SIZE", "--max-file-size"),
+    value("MAX_FILES", "--max-files"),
+    value("MAX_TOTAL_BYTES", "--max-total-bytes"),
+    value("BUDGET_ORDER", "--budget-order"),
+    value("MAX_NORMALIZED_CHARS", "--max-normalized-chars"),
+    value("MAX_TOKENS", "--max-tokens"),
+    value("MAX_TOKENS_PER_FILE", "--max-tokens-per-file"),
+    value("MAX_GROUPS_PER_DETECTOR", "--max-groups-per-detector"),
+    value("MAX_FILE_TIME", "--max-file-time"),
+    value("MIN_MATCH_LEN", "--min-match-len"),
+    value("MIN_TOKEN_LEN", "--min-token-len"),
+    value("ADAPTIVE_WINDOW", "--adaptive-window"),
+    EnvOption {
+        name: "MINHASH_THRESHOLD",
+        flag: "--minhash-threshold",
+        kind: EnvKind::Value,
+        overridden_by: &["--similarity-threshold"],
+    },
+    EnvOption {
+        name: "SIMILARITY_THRESHOLD",
+        flag: "--similarity-threshold",
+        kind: EnvKind::Value,
+        overridden_by: &["--minhash-threshold"],
+    },
+    EnvOption {
+        name: "SIMHASH_THRESHOLD",
+        flag: "--simhash-threshold",
+        kind: EnvKind::Value,
+        overridden_by: &["--simhash-max-distance"],
+    },
+    EnvOption {
+        name: "SIMHASH_MAX_DISTANCE",
+        flag: "--simhash-max-distance",
+        kind: EnvKind::Value,
+        overridden_by: &["--simhash-threshold"],
+    },
+    value("SHINGLE_SIZE", "--shingle-size"),
+    value("MINHASH_SIGNATURE_SIZE", "--minhash-signature-size"),
+    value("MINHASH_BANDS", "--minhash-bands"),
+    value("SIMILARITY_WINDOW", "--similarity-window"),
+    value("SIMILARITY_STRIDE", "--similarity-stride"),
+    value("LINE_SPAN_MIN_LINES", "--line-span-min-lines"),
+    value("LINE_SPAN_WINDOW", "--line-span-window"),
+    value("SYMBOL_MIN_COUNT", "--symbol-min-count"),
+    value("IDIOMS", "--idioms"),
+    value("IDIOM_TOKEN_LEN", "--idiom-token-len"),
+    value("IMPORT_MIN_COUNT", "--import-min-count"),
+    value("DATA_MIN_BYTES", "--data-min-bytes"),
+    value("MIN_ENTROPY", "--min-entropy"),
+    value("IMPORT_SIMILARITY", "--import-similarity"),
+    value("MAX_REPORT_ITEMS", "--max-report-items"),
+    value("SECTION_OFFSET", "--section-offset"),
+    value("SECTION_LIMIT", "--section-limit"),
+    value("MIN_OCCURRENCES", "--min-occurrences"),
+    value("MAX_OCCURRENCES_PER_GROUP", "--max-occurrences-per-group"),
+    value("THREADS", "--threads"),
+    value("SORT", "--sort"),
+    value("PREVIEW_CHARS", "--preview-chars"),
+    value("CONTEXT", "--context"),
+    value("UNICODE_NORMALIZE", "--unicode-normalize"),
+    switch("STATS", "--stats", &[]),
+    switch("HYPERLINKS", "--hyperlinks", &[]),
+    value("LINK_TEMPLATE", "--link-template"),
+    value("PRESET", "--preset"),
+    switch("PROGRESS_JSON", "--progress-json", &[]),
+    switch("STRICT", "--strict", &[]),
+    switch("CROSS_REPO_ONLY", "--cross-repo-only", &[]),
+    switch("NO_GITIGNORE", "--no-gitignore", &["--gitignore"]),
+    switch("GLOBAL_GITIGNORE", "--global-gitignore", &[]),
+    value("FILE_LISTING", "--file-listing"),
+    switch("FOLLOW_SYMLINKS", "--follow-symlinks", &[]),
+    switch("SIMILARITY_DIFF", "--similarity-diff", &[]),
+    switch("FILE_COVERAGE", "--file-coverage", &[]),
+    switch("CLUSTERS", "--clusters", &[]),
+    switch("SIMILARITY_CLUSTERS", "--similarity-clusters", &[]),
+    switch("OCCURRENCE_AGES", "--occurrence-ages", &[]),
+    switch("OCCURRENCE_SOURCES", "--occurrence-sources", &[]),
+    switch("LICENSE_CONFLICTS", "--license-conflicts", &[]),
+    switch("DEDUPE_DETECTORS", "--dedupe-detectors", &[]),
+    switch("NO_PRUNE_CONTAINED", "--no-prune-contained", &[]),
+    switch("ABSOLUTE_PATHS", "--absolute-paths", &[]),
+    switch("NO_BUILTIN_SKIPS", "--no-builtin-skips", &[]),
+    switch("NO_DEFAULT_EXCLUDES", "--no-default-excludes", &[]),
+    switch("EXCLUDE_TESTS", "--exclude-tests", &["--tests-only"]),
+    switch("TESTS_ONLY", "--tests-only", &["--exclude-tests"]),
+];
+
+fn parse_switch(localization: Localization, var: &str, raw: &str) -> Result<bool, String> {
+    match raw.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(format!(
+            "{var} {} {raw}",
+            tr(
+                localization,
+                "must be 1/0 or true/false, got",
+                "必须是 1/0 或 true/false，实际为",
+            ),
+        )),
+    }
+}
+
+/// Put flags from `DUP_CODE_CHECK_*` variables (read via `lookup`) in front of `argv`, after
+/// the subcommand if any. Variables whose flag (or a conflicting one) is already on the command
+/// line are skipped, so CLI flags always win; list variables add to the CLI's items.
+pub(crate) fn args_with_env_defaults(
+    argv: &[String],
+    localization: Localization,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<String>, String> {
+    let subcommand = usize::from(subcommand(argv).is_some());
+    let cli_flags = argv[subcommand..]
+        .iter()
+        .take_while(|arg| *arg != "--")
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    let mut env_args: Vec<String> = Vec::new();
+    for option in ENV_OPTIONS {
+        let var = format!("{ENV_PREFIX}{}", option.name);
+        let Some(raw) = lookup(&var).filter(|raw| !raw.is_empty()) else {
+            continue;
+        };
+        let overridden = cli_flags.contains(&option.flag)
+            || option
+                .overridden_by
+                .iter()
+                .any(|flag| cli_flags.contains(flag));
+        match option.kind {
+            EnvKind::List => {
+                for item in raw.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                    env_args.push(option.flag.to_string());
+                    env_args.push(item.to_string());
+                }
+            }
+            EnvKind::Value if !overridden => {
+                env_args.push(option.flag.to_string());
+                env_args.push(raw);
+            }
+            EnvKind::Switch if !overridden => {
+                if parse_switch(localization, &var, &raw)? {
+                    env_args.push(option.flag.to_string());
+                }
+            }
+            EnvKind::Value | EnvKind::Switch => {}
+        }
+    }
+
+    let mut out = Vec::with_capacity(argv.len() + env_args.len());
+    out.extend_from_slice(&argv[..subcommand]);
+    out.extend(env_args);
+    out.extend_from_slice(&argv[subcommand..]);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn with_env(args: &[&str], vars: &[(&str, &str)]) -> Result<Vec<String>, String> {
+        args_with_env_defaults(&argv(args), Localization::En, |name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn env_values_go_before_cli_flags_which_win() {
+        let out = with_env(
+            &["snapshot", "--max-files", "5", "-o", "s", "."],
+            &[
+                ("DUP_CODE_CHECK_MAX_FILES", "100"),
+                ("DUP_CODE_CHECK_MAX_TOTAL_BYTES", "2048"),
+                ("DUP_CODE_CHECK_IGNORE_DIRS", "vendor, third_party"),
+                ("DUP_CODE_CHECK_STRICT", "true"),
+                ("DUP_CODE_CHECK_CROSS_REPO_ONLY", "0"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            argv(&[
+                "snapshot",
+                "--ignore-dir",
+                "vendor",
+                "--ignore-dir",
+                "third_party",
+                "--max-total-bytes",
+                "2048",
+                "--strict",
+                "--max-files",
+                "5",
+                "-o",
+                "s",
+                "."
+            ])
+        );
+    }
+
+    /// Flags without a variable, as listed in docs/cli.md: modes, per-run inputs and outputs,
+    /// options rejected outside one mode (a default for them would break the other modes), and
+    /// flags read before the variables.
+    const FLAG_ONLY: &[&str] = &[
+        "--report",
+        "--code-spans",
+        "--emit-blocks",
+        "-o",
+        "--against-snapshot",
+        "--stdin-json",
+        "--baseline",
+        "--suppressions",
+        "--file-similarity-matrix",
+        "--cache-dir",
+        "--max-cache-size",
+        "--log-skips",
+        "--shard",
+        "--sample",
+        "--before",
+        "--after",
+        "--repo",
+        "--depth",
+        "--group-by",
+        "--aggregate",
+        "--codeowners",
+        "--only",
+        "--omit",
+        "--filter-path",
+        "--filter-path-trim",
+        "--suggest-excludes",
+        "--localization",
+        "--json",
+        "--gitignore",
+        "--output",
+        "--version",
+        "-V",
+        "--help",
+        "-h",
+    ];
+
+    #[test]
+    fn every_flag_has_a_variable_or_is_flag_only() {
+        // Option lines of the help text: `  -V, --version   Show version`.
+        let flags: std::collections::BTreeSet<&str> = crate::args::HELP_TEXT_EN
+            .lines()
+            .filter(|line| line.starts_with("  -") && !line.starts_with("  - "))
+            .map(str::trim_start)
+            .filter_map(|line| line.split("  ").next())
+            .flat_map(|spec| spec.split(", "))
+            .filter_map(|flag| flag.split(' ').next())
+            .collect();
+        assert!(flags.contains("--max-files") && flags.contains("-V"));
+        let missing: Vec<&str> = flags
+            .into_iter()
+            .filter(|flag| {
+                !FLAG_ONLY.contains(flag) && !ENV_OPTIONS.iter().any(|option| option.flag == *flag)
+            })
+            .collect();
+        assert!(missing.is_empty(), "flags without a variable: {missing:?}");
+    }
+
+    #[test]
+    fn env_flags_go_after_every_subcommand() {
+        let cases: &[&[&str]] = &[
+            &["snapshot", "-o", "s.dcs", "."],
+            &["merge-shards", "a.partial", "b.partial"],
+            &["explain", "ab12", "r.json"],
+            &["convert", "r.json", "r.json.gz"],
+            &["genealogy", "old.dcs", "new.dcs"],
+            &["html-diff", "old.json", "new.json"],
+            &["self-diff", "--before", "v1", "--after", "v2", "repo"],
+            &["resurrect", "--repo", "."],
+            &["cache", "status", "--cache-dir", "c"],
+        ];
+        assert_eq!(cases.len(), crate::args::SUBCOMMANDS.len());
+        for args in cases {
+            let out = with_env(args, &[("DUP_CODE_CHECK_MAX_FILES", "100")]).unwrap();
+            assert_eq!(out[0], args[0]);
+            assert_eq!(out[1..3], argv(&["--max-files", "100"]));
+            let parsed = crate::args::parse_args(&out, Localization::En);
+            assert!(parsed.is_ok(), "{args:?}: {:?}", parsed.err());
+        }
+    }
+
+    #[test]
+    fn conflicting_cli_flags_override_env_switches() {
+        let out = with_env(
+            &["--tests-only", "--json", "."],
+            &[
+                ("DUP_CODE_CHECK_EXCLUDE_TESTS", "1"),
+                ("DUP_CODE_CHECK_FORMAT", "sarif"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(out, argv(&["--tests-only", "--json", "."]));
+        assert!(with_env(&["."], &[("DUP_CODE_CHECK_STATS", "maybe")]).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use dup_code_check_core::debug::{FileExplanation, explain_file};
+use dup_code_check_core::{ReportSection, ScanOptions, UnicodeNormalization};
+
+use crate::by_file::JsonFileLocation;
+use crate::convert::read_saved;
+use crate::locale::{Localization, tr};
+use crate::render::text::Style;
+
+/// How many aligned tokens `explain` prints before eliding the rest.
+const MAX_ALIGNED_TOKENS_SHOWN: usize = 60;
+
+/// A hash-identified group read back from a saved `--report --json` output.
+#[derive(Debug)]
+struct SavedGroup {
+    section: &'static str,
+    hash: String,
+    normalized_len: usize,
+    occurrences: Vec<JsonFileLocation>,
+}
+
+/// One occurrence, re-tokenized from the roots.
+struct Occurrence {
+    location: JsonFileLocation,
+    lines: Vec<String>,
+    /// Token ids inside the occurrence's lines, with their line numbers.
+    tokens: Vec<(u32, u32)>,
+    explanation: FileExplanation,
+}
+
+/// `dup-code-check explain <group-id> <report.json> [root ...]`.
+pub(crate) fn run_explain(
+    localization: Localization,
+    group_id: &str,
+    report_path: &Path,
+    roots: &[PathBuf],
+    style: Style,
+) -> io::Result<()> {
+    let saved = read_saved(report_path)?;
+    let report = saved.get("report").unwrap_or(&saved);
+    let group = find_group(report, group_id)?;
+    let options = options_from_meta(&saved["meta"]["options"]);
+    let roots = resolve_roots(report, roots)?;
+
+    let mut occurrences = Vec::with_capacity(group.occurrences.len());
+    for location in &group.occurrences {
+        occurrences.push(load_occurrence(location, &roots, &options)?);
+    }
+    print!(
+        "{}",
+        format_explanation(localization, &group, &occurrences, &options, style)
+    );
+    Ok(())
+}
+
+/// Find the group whose `hash` is `group_id` (optionally `<section>:<hash>`).
+fn find_group(report: &serde_json::Value, group_id: &str) -> io::Result<SavedGroup> {
+    let (wanted_section, hash) = match group_id.split_once(':') {
+        Some((section, hash)) => (Some(section), hash),
+        None => (None, group_id),
+    };
+    let mut found = Vec::new();
+    for section in ReportSection::ALL.map(ReportSection::as_str) {
+        if wanted_section.is_some_and(|wanted| wanted != section) {
+            continue;
+        }
+        let items = report[section].as_array().into_iter().flatten();
+        for item in items.filter(|item| item["hash"].as_str() == Some(hash)) {
+            let places = item["occurrences"]
+                .as_array()
+                .or_else(|| item["files"].as_array());
+            let occurrences = places
+                .into_iter()
+                .flatten()
+                .filter_map(location_from_json)
+                .collect();
+            found.push(SavedGroup {
+                section,
+                hash: hash.to_string(),
+                normalized_len: item["normalizedLen"].as_u64().unwrap_or(0) as usize,
+                occurrences,
+            });
+        }
+    }
+
+    match found.len() {
+        0 => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no group with id {group_id} (similar pairs have no id)"),
+        )),
+        1 => Ok(found.remove(0)),
+        _ => {
+            let ids = found
+                .iter()
+                .map(|group| format!("{}:{}", group.section, group.hash))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("group id {group_id} is ambiguous, use one of: {ids}"),
+            ))
+        }
+    }
+}
+
+fn location_from_json(place: &serde_json::Value) -> Option<JsonFileLocation> {
+    let line = |key: &str| place[key].as_u64().map(|line| line as u32);
+    Some(JsonFileLocation {
+        repo_id: place["repoId"].as_u64()? as usize,
+        repo_label: place["repoLabel"].as_str().unwrap_or_default().into(),
+        path: place["path"].as_str()?.into(),
+        start_line: line("startLine"),
+        end_line: line("endLine"),
+    })
+}
+
+/// The options that produced the report, from its `meta.options`.
+fn options_from_meta(meta: &serde_json::Value) -> ScanOptions {
+    let mut options = ScanOptions::default();
+    let number = |key: &str| meta[key].as_u64().map(|value| value as usize);
+    if let Some(min_token_len) = number("minTokenLen") {
+        options.min_token_len = min_token_len;
+    }
+    if let Some(min_match_len) = number("minMatchLen") {
+        options.min_match_len = min_match_len;
+    }
+    if let Some(min_occurrences) = number("minOccurrences") {
+        options.min_occurrences = min_occurrences;
+    }
+    options.max_tokens_per_file = number("maxTokensPerFile");
+    options.unicode_normalization = match meta["unicodeNormalization"].as_str() {
+        Some("nfc") => Some(UnicodeNormalization::Nfc),
+        Some("nfkc") => Some(UnicodeNormalization::Nfkc),
+        _ => None,
+    };
+    if let Some(scales) = meta["languageTokenScales"].as_object() {
+        options.language_token_scales = scales
+            .iter()
+            .filter_map(|(language, scale)| Some((language.clone(), scale.as_f64()?)))
+            .collect();
+    }
+    options
+}
+
+/// Roots by repo id: the ones given on the command line, else the report's.
+fn resolve_roots(report: &serde_json::Value, cli_roots: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    if !cli_roots.is_empty() {
+        return Ok(cli_roots.to_vec());
+    }
+    let roots = report["roots"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|root| root["path"].as_str().map(PathBuf::from))
+        .collect::<Vec<_>>();
+    if roots.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the report lists no roots; pass them after the report file",
+        ));
+    }
+    Ok(roots)
+}
+
+fn load_occurrence(
+    location: &JsonFileLocation,
+    roots: &[PathBuf],
+    options: &ScanOptions,
+) -> io::Result<Occurrence> {
+    let rel_path = Path::new(location.path.as_ref());
+    let path = if rel_path.is_absolute() {
+        rel_path.to_path_buf()
+    } else {
+        let root = roots.get(location.repo_id).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "no root for repo {} ({})",
+                    location.repo_id, location.repo_label
+                ),
+            )
+        })?;
+        root.join(rel_path)
+    };
+    let with_path =
+        |err: io::Error| io::Error::new(err.kind(), format!("{}: {err}", path.display()));
+    let bytes = fs::read(&path).map_err(with_path)?;
+    let explanation = explain_file(&path, options).map_err(with_path)?;
+
+    // Split like the scanner: `\n`, `\r\n` and a lone `\r` each end a line.
+    let text = String::from_utf8_lossy(&bytes).replace("\r\n", "\n");
+    let text = text.strip_suffix(['\n', '\r']).unwrap_or(&text);
+    let all_lines: Vec<&str> = text.split(['\n', '\r']).collect();
+    let start_line = location.start_line.unwrap_or(1);
+    let end_line = location.end_line.unwrap_or(all_lines.len() as u32);
+    let lines = all_lines
+        .iter()
+        .skip(start_line.saturating_sub(1) as usize)
+        .take(end_line.saturating_sub(start_line) as usize + 1)
+        .map(|line| line.to_string())
+        .collect();
+    let tokens = explanation
+        .tokens
+        .iter()
+        .filter(|token| (start_line..=end_line).contains(&token.line))
+        .map(|token| (token.id, token.line))
+        .collect();
+    Ok(Occurrence {
+        location: location.clone(),
+        lines,
+        tokens,
+        explanation,
+    })
+}
+
+/// Longest run of tokens shared by `a` and `b`: `(start_a, start_b, len)`.
+fn longest_common_run(a: &[u32], b: &[u32]) -> (usize, usize, usize) {
+    let mut best = (0, 0, 0);
+    let mut prev = vec![0usize; b.len() + 1];
+    let mut cur = vec![0usize; b.len() + 1];
+    for (i, &token_a) in a.iter().enumerate() {
+        for (j, &token_b) in b.iter().enumerate() {
+            cur[j + 1] = if token_a == token_b { prev[j] + 1 } else { 0 };
+            if cur[j + 1] > best.2 {
+                best = (i + 1 - cur[j + 1], j + 1 - cur[j + 1], cur[j + 1]);
+            }
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    best
+}
+
+/// The largest global `min_token_len` whose per-language scaling stays within `len`.
+fn max_min_token_len(len: usize, scale: f64) -> usize {
+    let scaled = |min_token_len: usize| ((min_token_len as f64 * scale).round() as usize).max(1);
+    (1..)
+        .take_while(|&min_token_len| scaled(min_token_len) <= len)
+        .last()
+        .unwrap_or(0)
+}
+
+fn format_explanation(
+    localization: Localization,
+    group: &SavedGroup,
+    occurrences: &[Occurrence],
+    options: &ScanOptions,
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&style.header(&format!(
+        "{} {} ({} {}, {} {})\n",
+        group.section,
+        group.hash,
+        occurrences.len(),
+        tr(localization, "occurrences", "处出现"),
+        tr(localization, "normalized length", "归一化长度"),
+        group.normalized_len,
+    )));
+
+    // Align every occurrence with the first one.
+    let first = occurrences.first().map_or(&[][..], |occ| &occ.tokens[..]);
+    let first_ids: Vec<u32> = first.iter().map(|&(id, _)| id).collect();
+    let mut aligned_lines: Vec<BTreeSet<u32>> = vec![BTreeSet::new(); occurrences.len()];
+    let mut alignments = Vec::new();
+    for (idx, occ) in occurrences.iter().enumerate().skip(1) {
+        let ids: Vec<u32> = occ.tokens.iter().map(|&(id, _)| id).collect();
+        let (start_a, start_b, len) = longest_common_run(&first_ids, &ids);
+        aligned_lines[0].extend(first[start_a..start_a + len].iter().map(|&(_, line)| line));
+        aligned_lines[idx].extend(occ.tokens[start_b..start_b + len].iter().map(|&(_, l)| l));
+        alignments.push((idx, start_a, start_b, len));
+    }
+
+    for (idx, occ) in occurrences.iter().enumerate() {
+        let explanation = &occ.explanation;
+        out.push_str(&format!(
+            "\n#{} [{}] {} ({}, minTokenLen {})\n",
+            idx + 1,
+            occ.location.repo_label,
+            style.path(&occ.location.location()),
+            explanation.language.unwrap_or("?"),
+            explanation.min_token_len,
+        ));
+        let first_line = occ.location.start_line.unwrap_or(1);
+        for (offset, text) in occ.lines.iter().enumerate() {
+            let line = first_line + offset as u32;
+            if aligned_lines[idx].contains(&line) {
+                out.push_str(&style.matched(&format!("{line:>5} = {text}")));
+            } else {
+                out.push_str(&format!("{line:>5}   {text}"));
+            }
+            out.push('\n');
+        }
+    }
+
+    out.push('\n');
+    out.push_str(&style.header(tr(localization, "alignment:\n", "对齐:\n")));
+    let labels: HashMap<u32, &str> = occurrences.first().map_or_else(HashMap::new, |occ| {
+        occ.explanation
+            .vocabulary
+            .iter()
+            .map(|(id, label)| (*id, label.as_str()))
+            .collect()
+    });
+    for &(idx, start_a, start_b, len) in &alignments {
+        out.push_str(&format!(
+            "  #{} ~ #1: {len} {} (#1 {} {}-{}, #{} {} {}-{})\n",
+            idx + 1,
+            tr(localization, "aligned tokens", "个对齐 token"),
+            tr(localization, "tokens", "token"),
+            start_a,
+            (start_a + len).saturating_sub(1),
+            idx + 1,
+            tr(localization, "tokens", "token"),
+            start_b,
+            (start_b + len).saturating_sub(1),
+        ));
+    }
+    if let Some(&(_, start_a, _, len)) = alignments.iter().max_by_key(|a| a.3) {
+        let mut shown = first_ids[start_a..start_a + len]
+            .iter()
+            .take(MAX_ALIGNED_TOKENS_SHOWN)
+            .map(|id| labels.get(id).copied().unwrap_or("?"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if len > MAX_ALIGNED_TOKENS_SHOWN {
+            shown.push_str(" …");
+        }
+        out.push_str(&format!("  {}\n", style.matched(&shown)));
+    }
+
+    out.push('\n');
+    out.push_str(&style.header(tr(localization, "thresholds:\n", "阈值:\n")));
+    out.push_str(&format_thresholds(
+        localization,
+        group,
+        occurrences,
+        options,
+    ));
+    out
+}
+
+fn format_thresholds(
+    localization: Localization,
+    group: &SavedGroup,
+    occurrences: &[Occurrence],
+    options: &ScanOptions,
+) -> String {
+    let kept_while = tr(localization, "kept while", "保留条件");
+    let mut out = String::new();
+    match group.section {
+        "codeSpanDuplicates" | "lineSpanDuplicates" => {
+            out.push_str(&format!(
+                "  minMatchLen {} <= {} ({kept_while} minMatchLen <= {}) {}\n",
+                options.min_match_len,
+                group.normalized_len,
+                group.normalized_len,
+                tr(localization, "<- decisive", "<- 起决定作用"),
+            ));
+        }
+        "tokenSpanDuplicates" | "blockDuplicates" | "astSubtreeDuplicates" => {
+            // The occurrence with the largest per-language scale is the first to drop out.
+            let decisive = occurrences.iter().min_by_key(|occ| {
+                max_min_token_len(group.normalized_len, occ.explanation.token_scale)
+            });
+            if let Some(occ) = decisive {
+                let limit = max_min_token_len(group.normalized_len, occ.explanation.token_scale);
+                out.push_str(&format!(
+                    "  minTokenLen {} -> {} <= {} ({kept_while} minTokenLen <= {limit}) {} {}\n",
+                    options.min_token_len,
+                    occ.explanation.min_token_len,
+                    group.normalized_len,
+                    tr(localization, "<- decisive:", "<- 起决定作用:"),
+                    occ.location.path,
+                ));
+            }
+        }
+        _ => {}
+    }
+    out.push_str(&format!(
+        "  minOccurrences {} <= {} ({kept_while} minOccurrences <= {})\n",
+        options.min_occurrences,
+        occurrences.len(),
+        occurrences.len(),
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_groups_by_hash_or_section_qualified_id() {
+        let report = serde_json::json!({
+            "fileDuplicates": [{
+                "hash": "aa",
+                "normalizedLen": 10,
+                "files": [{ "repoId": 0, "repoLabel": "r", "path": "x.rs" }]
+            }],
+            "tokenSpanDuplicates": [{
+                "hash": "aa",
+                "normalizedLen": 50,
+                "occurrences": [{
+                    "repoId": 0, "repoLabel": "r", "path": "a.rs", "startLine": 3, "endLine": 9
+                }]
+            }]
+        });
+        assert!(find_group(&report, "aa").is_err());
+        assert!(find_group(&report, "bb").is_err());
+        let group = find_group(&report, "tokenSpanDuplicates:aa").unwrap();
+        assert_eq!(group.normalized_len, 50);
+        assert_eq!(group.occurrences[0].location(), "a.rs:3-9");
+    }
+
+    #[test]
+    fn aligns_the_longest_common_token_run() {
+        assert_eq!(
+            longest_common_run(&[9, 1, 2, 3, 4], &[1, 2, 3, 7]),
+            (1, 0, 3)
+        );
+        assert_eq!(longest_common_run(&[1], &[2]), (0, 0, 0));
+        assert_eq!(max_min_token_len(50, 1.0), 50);
+        assert_eq!(max_min_token_len(50, 1.5), 33);
+    }
+}
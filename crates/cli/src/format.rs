@@ -0,0 +1,291 @@
+use std::env;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::locale::Localization;
+use serde::Serialize;
+
+use crate::by_file::JsonFileView;
+use crate::convert::Encoding;
+use crate::json::{
+    JsonCloneGenealogy, JsonDuplicateGroup, JsonDuplicateSpanGroup, JsonDuplicationReport,
+    JsonMeta, JsonScanStats,
+};
+use crate::jsonl::JsonlFormatter;
+use crate::links::LinkTemplate;
+use crate::ownership::JsonOwnershipView;
+use crate::render::text::{
+    Style, format_text, format_text_by_file, format_text_by_ownership, format_text_code_spans,
+    format_text_genealogy, format_text_report,
+};
+use crate::review::{ReviewCommentsFormatter, read_baseline};
+
+/// Prefix of external formatter executables looked up on `PATH` (`--format <name>`).
+pub(crate) const EXTERNAL_FORMATTER_PREFIX: &str = "dup-code-check-format-";
+
+/// Result of one CLI run, in its JSON shape.
+#[derive(Debug)]
+pub(crate) enum ScanOutput {
+    Files(Vec<JsonDuplicateGroup>),
+    CodeSpans(Vec<JsonDuplicateSpanGroup>),
+    Report(Box<JsonDuplicationReport>),
+    /// `--group-by file`.
+    ReportByFile(Box<JsonFileView>),
+    /// `--aggregate ownership`.
+    ReportByOwnership(Box<JsonOwnershipView>),
+    /// `genealogy` subcommand.
+    Genealogy(Box<JsonCloneGenealogy>),
+}
+
+impl ScanOutput {
+    pub(crate) fn mode(&self) -> &'static str {
+        match self {
+            ScanOutput::Files(_) => "files",
+            ScanOutput::CodeSpans(_) => "codeSpans",
+            ScanOutput::Report(_) => "report",
+            ScanOutput::ReportByFile(_) => "reportByFile",
+            ScanOutput::ReportByOwnership(_) => "reportByOwnership",
+            ScanOutput::Genealogy(_) => "genealogy",
+        }
+    }
+}
+
+pub(crate) struct FormatInput<'a> {
+    pub(crate) output: &'a ScanOutput,
+    pub(crate) stats: &'a JsonScanStats,
+    pub(crate) meta: &'a JsonMeta,
+    /// Whether `--stats` was passed (built-in formats only embed stats when asked to).
+    pub(crate) include_stats: bool,
+    pub(crate) localization: Localization,
+    /// Whether the text format may use ANSI colors (`--color`, resolved against stdout).
+    pub(crate) color: bool,
+    /// `--hyperlinks`: the text format links locations to files under `meta.roots`.
+    pub(crate) hyperlinks: bool,
+    /// `--link-template`: code-hosting URLs for the text format and review comments.
+    pub(crate) links: Option<&'a LinkTemplate>,
+}
+
+/// Renders a scan result to stdout.
+///
+/// Built-in formats implement this directly; any other `--format <name>` is served by an
+/// external `dup-code-check-format-<name>` executable (see [`ExternalFormatter`]).
+pub(crate) trait ReportFormatter {
+    fn write(&self, input: &FormatInput<'_>) -> io::Result<()>;
+}
+
+pub(crate) struct TextFormatter;
+
+impl ReportFormatter for TextFormatter {
+    fn write(&self, input: &FormatInput<'_>) -> io::Result<()> {
+        let mut style = Style::new(input.color);
+        if input.hyperlinks {
+            style = style.with_hyperlinks(&input.meta.roots);
+        }
+        if let Some(links) = input.links {
+            style = style.with_link_template(links);
+        }
+        let text = match input.output {
+            ScanOutput::Files(groups) => format_text(input.localization, groups, style),
+            ScanOutput::CodeSpans(groups) => {
+                format_text_code_spans(input.localization, groups, style)
+            }
+            ScanOutput::Report(report) => format_text_report(input.localization, report, style),
+            ScanOutput::ReportByFile(view) => format_text_by_file(input.localization, view, style),
+            ScanOutput::ReportByOwnership(view) => {
+                format_text_by_ownership(input.localization, view, style)
+            }
+            ScanOutput::Genealogy(genealogy) => {
+                format_text_genealogy(input.localization, genealogy, style)
+            }
+        };
+        print!("{text}");
+        Ok(())
+    }
+}
+
+/// `--format json`, and the `json.gz`/`msgpack` encodings of the same documents.
+pub(crate) struct JsonFormatter {
+    pub(crate) encoding: Encoding,
+}
+
+impl JsonFormatter {
+    fn emit<T: Serialize>(&self, value: &T) -> io::Result<()> {
+        self.encoding.write(io::stdout().lock(), value)
+    }
+}
+
+/// A report or genealogy object with the `meta` header in front of its fields.
+#[derive(Serialize)]
+struct WithMeta<'a, T> {
+    meta: &'a JsonMeta,
+    #[serde(flatten)]
+    output: &'a T,
+}
+
+impl ReportFormatter for JsonFormatter {
+    fn write(&self, input: &FormatInput<'_>) -> io::Result<()> {
+        // Bare group arrays stay as they are; every JSON object carries `meta`.
+        if !input.include_stats {
+            return match input.output {
+                ScanOutput::Files(groups) => self.emit(groups),
+                ScanOutput::CodeSpans(groups) => self.emit(groups),
+                ScanOutput::Report(report) => self.emit(&WithMeta {
+                    meta: input.meta,
+                    output: report.as_ref(),
+                }),
+                ScanOutput::Genealogy(genealogy) => self.emit(&WithMeta {
+                    meta: input.meta,
+                    output: genealogy.as_ref(),
+                }),
+                ScanOutput::ReportByFile(view) => self.emit(&serde_json::json!({
+                    "meta": input.meta,
+                    "files": view.files,
+                })),
+                ScanOutput::ReportByOwnership(view) => self.emit(&WithMeta {
+                    meta: input.meta,
+                    output: view.as_ref(),
+                }),
+            };
+        }
+        match input.output {
+            ScanOutput::Files(groups) => self.emit(&serde_json::json!({
+                "meta": input.meta,
+                "groups": groups,
+                "scanStats": input.stats,
+            })),
+            ScanOutput::CodeSpans(groups) => self.emit(&serde_json::json!({
+                "meta": input.meta,
+                "groups": groups,
+                "scanStats": input.stats,
+            })),
+            ScanOutput::Report(report) => self.emit(&serde_json::json!({
+                "meta": input.meta,
+                "report": report,
+                "scanStats": input.stats,
+            })),
+            ScanOutput::ReportByFile(view) => self.emit(&serde_json::json!({
+                "meta": input.meta,
+                "files": view.files,
+                "scanStats": input.stats,
+            })),
+            ScanOutput::ReportByOwnership(view) => self.emit(&serde_json::json!({
+                "meta": input.meta,
+                "ownership": view,
+                "scanStats": input.stats,
+            })),
+            ScanOutput::Genealogy(genealogy) => self.emit(&serde_json::json!({
+                "meta": input.meta,
+                "genealogy": genealogy,
+                "scanStats": input.stats,
+            })),
+        }
+    }
+}
+
+/// Pipes the JSON envelope to an external formatter and forwards its stdout.
+pub(crate) struct ExternalFormatter {
+    pub(crate) program: PathBuf,
+}
+
+impl ReportFormatter for ExternalFormatter {
+    fn write(&self, input: &FormatInput<'_>) -> io::Result<()> {
+        let (mode, meta, stats) = (input.output.mode(), input.meta, input.stats);
+        let envelope = match input.output {
+            ScanOutput::Files(groups) => serde_json::json!({
+                "mode": mode, "meta": meta, "groups": groups, "scanStats": stats
+            }),
+            ScanOutput::CodeSpans(groups) => serde_json::json!({
+                "mode": mode, "meta": meta, "groups": groups, "scanStats": stats
+            }),
+            ScanOutput::Report(report) => serde_json::json!({
+                "mode": mode, "meta": meta, "report": report, "scanStats": stats
+            }),
+            ScanOutput::ReportByFile(view) => serde_json::json!({
+                "mode": mode, "meta": meta, "files": view.files, "scanStats": stats
+            }),
+            ScanOutput::ReportByOwnership(view) => serde_json::json!({
+                "mode": mode, "meta": meta, "ownership": view, "scanStats": stats
+            }),
+            ScanOutput::Genealogy(genealogy) => serde_json::json!({
+                "mode": mode, "meta": meta, "genealogy": genealogy, "scanStats": stats
+            }),
+        };
+        let payload = serde_json::to_vec(&envelope)
+            .map_err(|e| io::Error::other(format!("json encode: {e}")))?;
+
+        let mut child = Command::new(&self.program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|err| {
+                io::Error::new(err.kind(), format!("{}: {err}", self.program.display()))
+            })?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&payload)?;
+        }
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "{} exited with {status}",
+                self.program.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Resolve `--format <name>` to a formatter: built-ins first, then `PATH` plugins.
+/// `baseline` is the `--baseline` report, only used by `review-comments`.
+pub(crate) fn resolve_formatter(
+    name: &str,
+    baseline: Option<&Path>,
+) -> io::Result<Box<dyn ReportFormatter>> {
+    match name {
+        "text" => return Ok(Box::new(TextFormatter)),
+        "json" => {
+            return Ok(Box::new(JsonFormatter {
+                encoding: Encoding::Json,
+            }));
+        }
+        "jsonl" => return Ok(Box::new(JsonlFormatter)),
+        "review-comments" => {
+            let baseline = baseline.map(read_baseline).transpose()?;
+            return Ok(Box::new(ReviewCommentsFormatter { baseline }));
+        }
+        _ => {}
+    }
+    if let Some(encoding) = Encoding::from_format(name) {
+        return Ok(Box::new(JsonFormatter { encoding }));
+    }
+    let program = find_on_path(&format!("{EXTERNAL_FORMATTER_PREFIX}{name}")).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "unknown format {name:?} (no {EXTERNAL_FORMATTER_PREFIX}{name} executable on PATH)"
+            ),
+        )
+    })?;
+    Ok(Box::new(ExternalFormatter { program }))
+}
+
+fn find_on_path(program: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path).find_map(|dir| executable_in(&dir, program))
+}
+
+fn executable_in(dir: &Path, program: &str) -> Option<PathBuf> {
+    let candidate = dir.join(program);
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+    #[cfg(windows)]
+    {
+        let candidate = dir.join(format!("{program}.exe"));
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+    }
+    None
+}
@@ -0,0 +1,333 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use dup_code_check_core::ReportSection;
+
+use crate::convert::read_saved;
+use crate::locale::{Localization, tr};
+
+/// How a report item changed between the two reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Change {
+    Added,
+    Removed,
+    /// Same group, different occurrences.
+    Changed,
+}
+
+impl Change {
+    fn class(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Changed => "changed",
+        }
+    }
+
+    fn label(self, localization: Localization) -> &'static str {
+        match self {
+            Self::Added => tr(localization, "added", "新增"),
+            Self::Removed => tr(localization, "removed", "移除"),
+            Self::Changed => tr(localization, "changed", "变化"),
+        }
+    }
+}
+
+/// One group or pair of a saved report, reduced to what the diff shows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Item {
+    title: String,
+    locations: BTreeSet<String>,
+}
+
+#[derive(Debug, Default)]
+struct SectionDiff {
+    entries: Vec<(Change, Item)>,
+    unchanged: usize,
+}
+
+/// `html-diff <old> <new>`: render the groups added, removed or changed between two saved
+/// `--report --json` outputs as a standalone HTML page, written to `output` or stdout.
+pub(crate) fn run_html_diff(
+    localization: Localization,
+    old: &Path,
+    new: &Path,
+    output: Option<&Path>,
+) -> io::Result<()> {
+    let old_items = read_items(old)?;
+    let new_items = read_items(new)?;
+    let diff = diff_sections(old_items, new_items);
+    let html = render_html(localization, old, new, &diff);
+    match output {
+        Some(path) => fs::write(path, html)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display()))),
+        None => io::stdout().lock().write_all(html.as_bytes()),
+    }
+}
+
+/// The items of every section, keyed by `hash` (groups) or by both locations (pairs).
+fn read_items(path: &Path) -> io::Result<BTreeMap<&'static str, BTreeMap<String, Item>>> {
+    let value = read_saved(path)?;
+    let report = value.get("report").unwrap_or(&value);
+    if !report.is_object() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: not a --report --json output", path.display()),
+        ));
+    }
+
+    Ok(section_items(report))
+}
+
+fn section_items(report: &serde_json::Value) -> BTreeMap<&'static str, BTreeMap<String, Item>> {
+    ReportSection::ALL
+        .map(ReportSection::as_str)
+        .into_iter()
+        .map(|section| {
+            let items = report[section].as_array().into_iter().flatten();
+            (section, items.filter_map(item_of).collect())
+        })
+        .collect()
+}
+
+fn item_of(value: &serde_json::Value) -> Option<(String, Item)> {
+    if let (Some(a), Some(b)) = (location(&value["a"]), location(&value["b"])) {
+        let (first, second) = if a <= b { (a, b) } else { (b, a) };
+        let key = format!("{first} ~ {second}");
+        let title = value["score"]
+            .as_f64()
+            .map_or_else(String::new, |score| format!("score={score:.2}"));
+        return Some((
+            key,
+            Item {
+                title,
+                locations: BTreeSet::from([first, second]),
+            },
+        ));
+    }
+
+    let hash = value["hash"].as_str()?;
+    let title = value["preview"]
+        .as_str()
+        .map(str::to_string)
+        .or_else(|| {
+            let symbols = value["symbols"].as_array()?;
+            let symbols: Vec<&str> = symbols.iter().filter_map(|s| s.as_str()).collect();
+            Some(symbols.join(", "))
+        })
+        .unwrap_or_else(|| format!("hash={hash}"));
+    let places = value["occurrences"]
+        .as_array()
+        .or_else(|| value["files"].as_array());
+    let locations = places.into_iter().flatten().filter_map(location).collect();
+    Some((hash.to_string(), Item { title, locations }))
+}
+
+/// `[label] path[:start-end]`.
+fn location(place: &serde_json::Value) -> Option<String> {
+    let path = place["path"].as_str()?;
+    let label = place["repoLabel"].as_str().unwrap_or_default();
+    Some(
+        match (place["startLine"].as_u64(), place["endLine"].as_u64()) {
+            (Some(start), Some(end)) => format!("[{label}] {path}:{start}-{end}"),
+            _ => format!("[{label}] {path}"),
+        },
+    )
+}
+
+fn diff_sections(
+    mut old: BTreeMap<&'static str, BTreeMap<String, Item>>,
+    new: BTreeMap<&'static str, BTreeMap<String, Item>>,
+) -> Vec<(&'static str, SectionDiff)> {
+    let mut out = Vec::new();
+    for section in ReportSection::ALL.map(ReportSection::as_str) {
+        let mut old_items = old.remove(section).unwrap_or_default();
+        let mut diff = SectionDiff::default();
+        for (key, item) in new.get(section).into_iter().flatten() {
+            match old_items.remove(key) {
+                None => diff.entries.push((Change::Added, item.clone())),
+                Some(before) if before.locations != item.locations => {
+                    diff.entries.push((Change::Changed, item.clone()));
+                }
+                Some(_) => diff.unchanged += 1,
+            }
+        }
+        diff.entries
+            .extend(old_items.into_values().map(|item| (Change::Removed, item)));
+        diff.entries.sort_by_key(|(change, _)| *change);
+        out.push((section, diff));
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:.3em .7em;text-align:right}\
+th:first-child,td:first-child{text-align:left}\
+.item{border-left:.4em solid;margin:.5em 0;padding:.3em .8em}\
+.added{border-color:#2da44e;background:#e6ffec}\
+.removed{border-color:#cf222e;background:#ffebe9}\
+.changed{border-color:#bf8700;background:#fff8c5}\
+.tag{font-weight:bold;text-transform:uppercase;font-size:.8em;margin-right:.6em}\
+pre{white-space:pre-wrap;margin:.3em 0}\
+ul{margin:.2em 0;font-family:monospace}";
+
+fn render_html(
+    localization: Localization,
+    old: &Path,
+    new: &Path,
+    diff: &[(&'static str, SectionDiff)],
+) -> String {
+    let count = |diff: &SectionDiff, change: Change| {
+        diff.entries.iter().filter(|(c, _)| *c == change).count()
+    };
+    let title = tr(localization, "Duplication diff", "重复代码差异");
+    let mut out = String::new();
+    let _ = write!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n\
+         <p>{} <code>{}</code> → <code>{}</code></p>\n",
+        localization.tag(),
+        tr(localization, "Comparing", "对比"),
+        escape(&old.display().to_string()),
+        escape(&new.display().to_string()),
+    );
+
+    let _ = write!(
+        out,
+        "<table>\n<tr><th>{}</th><th class=\"added\">{}</th><th class=\"removed\">{}</th>\
+         <th class=\"changed\">{}</th><th>{}</th></tr>\n",
+        tr(localization, "section", "section"),
+        Change::Added.label(localization),
+        Change::Removed.label(localization),
+        Change::Changed.label(localization),
+        tr(localization, "unchanged", "未变"),
+    );
+    for (section, diff) in diff {
+        let _ = writeln!(
+            out,
+            "<tr><td><a href=\"#{section}\">{section}</a></td><td>{}</td><td>{}</td><td>{}</td>\
+             <td>{}</td></tr>",
+            count(diff, Change::Added),
+            count(diff, Change::Removed),
+            count(diff, Change::Changed),
+            diff.unchanged,
+        );
+    }
+    out.push_str("</table>\n");
+
+    for (section, diff) in diff.iter().filter(|(_, diff)| !diff.entries.is_empty()) {
+        let _ = writeln!(out, "<h2 id=\"{section}\">{section}</h2>");
+        for (change, item) in &diff.entries {
+            let _ = write!(
+                out,
+                "<div class=\"item {}\"><span class=\"tag\">{}</span><pre>{}</pre>\n<ul>\n",
+                change.class(),
+                change.label(localization),
+                escape(&item.title),
+            );
+            for location in &item.locations {
+                let _ = writeln!(out, "<li>{}</li>", escape(location));
+            }
+            out.push_str("</ul></div>\n");
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(report: serde_json::Value) -> BTreeMap<&'static str, BTreeMap<String, Item>> {
+        section_items(&report)
+    }
+
+    fn group(hash: &str, paths: &[&str]) -> serde_json::Value {
+        let occurrences: Vec<_> = paths
+            .iter()
+            .map(|path| serde_json::json!({ "repoLabel": "r", "path": path, "startLine": 1, "endLine": 5 }))
+            .collect();
+        serde_json::json!({ "hash": hash, "preview": format!("<{hash}>"), "occurrences": occurrences })
+    }
+
+    #[test]
+    fn groups_are_classified_by_hash_and_occurrences() {
+        let old = items(serde_json::json!({
+            "blockDuplicates": [group("aa", &["a.rs", "b.rs"]), group("bb", &["c.rs", "d.rs"]), group("cc", &["e.rs", "f.rs"])],
+        }));
+        let new = items(serde_json::json!({
+            "blockDuplicates": [group("aa", &["a.rs", "b.rs"]), group("bb", &["c.rs", "x.rs"]), group("dd", &["g.rs", "h.rs"])],
+        }));
+        let diff = diff_sections(old, new);
+        let (_, blocks) = diff
+            .iter()
+            .find(|(section, _)| *section == "blockDuplicates")
+            .unwrap();
+        let changes: Vec<(Change, &str)> = blocks
+            .entries
+            .iter()
+            .map(|(change, item)| (*change, item.title.as_str()))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (Change::Added, "<dd>"),
+                (Change::Removed, "<cc>"),
+                (Change::Changed, "<bb>"),
+            ]
+        );
+        assert_eq!(blocks.unchanged, 1);
+
+        let html = render_html(
+            Localization::En,
+            Path::new("old.json"),
+            Path::new("new.json"),
+            &diff,
+        );
+        assert!(html.contains("<pre>&lt;dd&gt;</pre>"));
+        assert!(html.contains("<div class=\"item removed\">"));
+        assert!(html.contains("<li>[r] h.rs:1-5</li>"));
+        assert!(!html.contains("id=\"fileDuplicates\""));
+    }
+
+    #[test]
+    fn pairs_match_regardless_of_side() {
+        let pair = |a: &str, b: &str| {
+            serde_json::json!({
+                "a": { "repoLabel": "r", "path": a, "startLine": 1, "endLine": 2 },
+                "b": { "repoLabel": "r", "path": b, "startLine": 1, "endLine": 2 },
+                "score": 0.9,
+            })
+        };
+        let old = items(serde_json::json!({ "similarBlocksMinhash": [pair("a.rs", "b.rs")] }));
+        let new = items(serde_json::json!({ "similarBlocksMinhash": [pair("b.rs", "a.rs")] }));
+        let diff = diff_sections(old, new);
+        let (_, pairs) = diff
+            .iter()
+            .find(|(section, _)| *section == "similarBlocksMinhash")
+            .unwrap();
+        assert!(pairs.entries.is_empty());
+        assert_eq!(pairs.unchanged, 1);
+    }
+}
@@ -0,0 +1,108 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Read the exclusions of another duplicate-code tool (`--import-ignores`) as
+/// [`ScanOptions::ignore_patterns`](dup_code_check_core::ScanOptions) entries.
+///
+/// `*.json` files are jscpd configs: their `ignore` globs are translated. Anything else is read
+/// as a `.cpdignore`-style list: one gitignore-style pattern or path per line, `#` comments.
+pub(crate) fn import_ignore_patterns(path: &Path) -> io::Result<Vec<String>> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        patterns_from_jscpd_config(&text)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))
+    } else {
+        Ok(patterns_from_lines(&text))
+    }
+}
+
+fn patterns_from_jscpd_config(text: &str) -> io::Result<Vec<String>> {
+    let config: serde_json::Value = serde_json::from_str(text)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    let Some(ignore) = config.get("ignore") else {
+        return Ok(Vec::new());
+    };
+    let globs = ignore
+        .as_array()
+        .and_then(|globs| {
+            globs
+                .iter()
+                .map(|glob| glob.as_str())
+                .collect::<Option<Vec<_>>>()
+        })
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "jscpd \"ignore\" must be an array of strings",
+            )
+        })?;
+    Ok(globs
+        .into_iter()
+        .filter_map(glob_to_ignore_pattern)
+        .collect())
+}
+
+/// jscpd globs are matched against root-relative paths, so a glob without a `/` only matches
+/// top-level entries; gitignore would match it at any depth, hence the leading `/`.
+fn glob_to_ignore_pattern(glob: &str) -> Option<String> {
+    let glob = glob.trim();
+    let (negated, glob) = match glob.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, glob),
+    };
+    let glob = glob.trim_start_matches("./");
+    if glob.is_empty() {
+        return None;
+    }
+    let anchor = if glob.trim_end_matches('/').contains('/') {
+        ""
+    } else {
+        "/"
+    };
+    let negation = if negated { "!" } else { "" };
+    Some(format!("{negation}{anchor}{glob}"))
+}
+
+fn patterns_from_lines(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.trim_start_matches("./").to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jscpd_globs_become_anchored_ignore_patterns() {
+        let patterns = patterns_from_jscpd_config(
+            r#"{"threshold": 1, "ignore": ["**/node_modules/**", "./dist/**", "*.min.js", "!src/keep.js"]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            patterns,
+            ["**/node_modules/**", "dist/**", "/*.min.js", "!src/keep.js"]
+        );
+        assert!(
+            patterns_from_jscpd_config(r#"{"reporters": ["console"]}"#)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(patterns_from_jscpd_config(r#"{"ignore": "dist"}"#).is_err());
+    }
+
+    #[test]
+    fn cpdignore_lines_skip_comments_and_blanks() {
+        assert_eq!(
+            patterns_from_lines("# generated\n./src/gen/\n\n  *.pb.go  \n"),
+            ["src/gen/", "*.pb.go"]
+        );
+    }
+}
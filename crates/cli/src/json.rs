@@ -1,105 +1,201 @@
+use std::collections::{BTreeMap, HashSet};
 use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use dup_code_check_core::ScanStats;
-use serde::Serialize;
+use dup_code_check_core::{ReportSection, ScanOptions, ScanStats};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
 
-#[derive(Debug, Clone, Serialize)]
+use crate::convert::Encoding;
+use crate::format::EXTERNAL_FORMATTER_PREFIX;
+
+/// `scanStats`: core's serde form of [`ScanStats`] plus the derived `fingerprintsPerKb`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct JsonScanStats {
-    pub(crate) candidate_files: u64,
-    pub(crate) scanned_files: u64,
-    pub(crate) scanned_bytes: u64,
-    pub(crate) git_fast_path_fallbacks: u64,
-    pub(crate) skipped_not_found: u64,
-    pub(crate) skipped_permission_denied: u64,
-    pub(crate) skipped_too_large: u64,
-    pub(crate) skipped_binary: u64,
-    pub(crate) skipped_outside_root: u64,
-    pub(crate) skipped_relativize_failed: u64,
-    pub(crate) skipped_walk_errors: u64,
-    pub(crate) skipped_budget_max_files: u64,
-    pub(crate) skipped_budget_max_total_bytes: u64,
-    pub(crate) skipped_budget_max_normalized_chars: u64,
-    pub(crate) skipped_budget_max_tokens: u64,
-    pub(crate) skipped_bucket_truncated: u64,
+    #[serde(flatten)]
+    pub(crate) stats: ScanStats,
+    /// Derived from `fingerprints` and `scanned_bytes`; ignored when read back.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fingerprints_per_kb: Option<f64>,
 }
 
 impl From<&ScanStats> for JsonScanStats {
     fn from(stats: &ScanStats) -> Self {
         Self {
-            candidate_files: stats.candidate_files,
-            scanned_files: stats.scanned_files,
-            scanned_bytes: stats.scanned_bytes,
-            git_fast_path_fallbacks: stats.git_fast_path_fallbacks,
-            skipped_not_found: stats.skipped_not_found,
-            skipped_permission_denied: stats.skipped_permission_denied,
-            skipped_too_large: stats.skipped_too_large,
-            skipped_binary: stats.skipped_binary,
-            skipped_outside_root: stats.skipped_outside_root,
-            skipped_relativize_failed: stats.skipped_relativize_failed,
-            skipped_walk_errors: stats.skipped_walk_errors,
-            skipped_budget_max_files: stats.skipped_budget_max_files,
-            skipped_budget_max_total_bytes: stats.skipped_budget_max_total_bytes,
-            skipped_budget_max_normalized_chars: stats.skipped_budget_max_normalized_chars,
-            skipped_budget_max_tokens: stats.skipped_budget_max_tokens,
-            skipped_bucket_truncated: stats.skipped_bucket_truncated,
+            stats: stats.clone(),
+            fingerprints_per_kb: stats.fingerprints_per_kb(),
         }
     }
 }
 
 impl From<ScanStats> for JsonScanStats {
     fn from(stats: ScanStats) -> Self {
-        Self::from(&stats)
+        Self {
+            fingerprints_per_kb: stats.fingerprints_per_kb(),
+            stats,
+        }
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+impl From<&JsonScanStats> for ScanStats {
+    fn from(stats: &JsonScanStats) -> Self {
+        stats.stats.clone()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct JsonDuplicateFile {
     pub(crate) repo_id: usize,
-    pub(crate) repo_label: String,
-    pub(crate) path: String,
+    pub(crate) repo_label: Arc<str>,
+    pub(crate) path: Arc<str>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub(crate) is_test: bool,
+    /// The root's git origin and commit (`--occurrence-sources`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) source: Option<Arc<JsonOccurrenceSource>>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+/// Where a root's files came from, so merged reports stay traceable to exact sources.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonOccurrenceSource {
+    /// URL of the `origin` remote, without credentials; `null` when there is none.
+    pub(crate) remote: Option<String>,
+    /// `git rev-parse HEAD`.
+    pub(crate) commit: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct JsonDuplicateGroup {
     pub(crate) hash: String,
     pub(crate) normalized_len: usize,
     pub(crate) files: Vec<JsonDuplicateFile>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub(crate) truncated_files: usize,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonSymbolDuplicateGroup {
+    pub(crate) hash: String,
+    pub(crate) symbols: Vec<String>,
+    pub(crate) files: Vec<JsonDuplicateFile>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub(crate) truncated_files: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonImportDuplicatePair {
+    pub(crate) a: JsonDuplicateFile,
+    pub(crate) b: JsonDuplicateFile,
+    pub(crate) score: f64,
+    pub(crate) shared_imports: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct JsonDuplicateSpanOccurrence {
     pub(crate) repo_id: usize,
-    pub(crate) repo_label: String,
-    pub(crate) path: String,
+    pub(crate) repo_label: Arc<str>,
+    pub(crate) path: Arc<str>,
     pub(crate) start_line: u32,
     pub(crate) end_line: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) start_col: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) end_col: Option<u32>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub(crate) is_test: bool,
+    /// Innermost named function/type around the occurrence, e.g. `PaymentService::refund`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) scope: Option<Arc<str>>,
+    /// RFC 3339 time of the last commit touching the lines (`--occurrence-ages`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) last_modified: Option<String>,
+    /// The root's git origin and commit (`--occurrence-sources`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) source: Option<Arc<JsonOccurrenceSource>>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+impl JsonDuplicateSpanOccurrence {
+    /// `path:line[:col]-line[:col]`, with columns when the detector knows them.
+    pub(crate) fn location(&self) -> String {
+        match (self.start_col, self.end_col) {
+            (Some(start_col), Some(end_col)) => format!(
+                "{}:{}:{start_col}-{}:{end_col}",
+                self.path, self.start_line, self.end_line
+            ),
+            _ => format!("{}:{}-{}", self.path, self.start_line, self.end_line),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct JsonDuplicateSpanGroup {
     pub(crate) hash: String,
     pub(crate) normalized_len: usize,
     pub(crate) preview: String,
     pub(crate) occurrences: Vec<JsonDuplicateSpanOccurrence>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub(crate) truncated_occurrences: usize,
+    pub(crate) duplicated_lines: u64,
+    pub(crate) potential_savings_lines: u64,
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        deserialize_with = "section_names"
+    )]
+    pub(crate) also_detected_by: Vec<KnownName>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) occurrence_previews: Vec<JsonOccurrencePreview>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) quality: Option<JsonGroupQuality>,
+    /// Days between the most and least recently changed occurrence (`--occurrence-ages`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) age_spread_days: Option<u64>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonGroupQuality {
+    /// Bits per token.
+    pub(crate) token_entropy: f64,
+    pub(crate) punctuation_ratio: f64,
+    pub(crate) identifier_diversity: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonOccurrencePreview {
+    pub(crate) occurrence: usize,
+    pub(crate) preview: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct JsonSimilarityPair {
+    /// `minhash` or `simhash`.
+    #[serde(deserialize_with = "similarity_detector")]
+    pub(crate) detector: KnownName,
     pub(crate) a: JsonDuplicateSpanOccurrence,
     pub(crate) b: JsonDuplicateSpanOccurrence,
     pub(crate) score: f64,
     pub(crate) distance: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) diff: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub(crate) struct JsonDuplicationReport {
+    pub(crate) roots: Vec<JsonReportRoot>,
     pub(crate) file_duplicates: Vec<JsonDuplicateGroup>,
     pub(crate) code_span_duplicates: Vec<JsonDuplicateSpanGroup>,
     pub(crate) line_span_duplicates: Vec<JsonDuplicateSpanGroup>,
@@ -108,10 +204,247 @@ pub(crate) struct JsonDuplicationReport {
     pub(crate) ast_subtree_duplicates: Vec<JsonDuplicateSpanGroup>,
     pub(crate) similar_blocks_minhash: Vec<JsonSimilarityPair>,
     pub(crate) similar_blocks_simhash: Vec<JsonSimilarityPair>,
+    pub(crate) symbol_duplicates: Vec<JsonSymbolDuplicateGroup>,
+    pub(crate) import_duplicates: Vec<JsonImportDuplicatePair>,
+    pub(crate) data_duplicates: Vec<JsonDuplicateGroup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) file_coverage: Option<Vec<JsonFileCoverage>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) clone_clusters: Option<Vec<JsonCloneCluster>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) similar_block_clusters: Option<Vec<JsonSimilarBlockCluster>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) license_conflicts: Option<Vec<JsonLicenseConflict>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) common_idioms: Option<Vec<JsonDuplicateSpanGroup>>,
+    /// Sections emptied by `--only` / `--omit`, by JSON key.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) omitted_sections: Vec<String>,
+    /// Each section's group count before `--max-report-items` truncation.
+    pub(crate) section_totals: Vec<JsonSectionTotal>,
+    /// Sum of `potentialSavingsLines` over the span sections.
+    pub(crate) potential_savings_lines: u64,
+    /// Present when `--sample` limited the scan; everything else describes the sample only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) sample: Option<JsonReportSample>,
+}
+
+impl JsonDuplicationReport {
+    pub(crate) fn section_total(&self, section: &str) -> Option<&JsonSectionTotal> {
+        self.section_totals
+            .iter()
+            .find(|total| total.section == section)
+    }
+
+    /// Whether `--only` / `--omit` dropped the section with JSON key `section`.
+    pub(crate) fn omits(&self, section: &str) -> bool {
+        self.omitted_sections
+            .iter()
+            .any(|omitted| omitted == section)
+    }
+
+    /// Empty the section with JSON key `section` and record it in `omitted_sections`; optional
+    /// sections the run did not produce are left out of the list.
+    pub(crate) fn omit_section(&mut self, section: &str) {
+        match section {
+            "fileDuplicates" => self.file_duplicates.clear(),
+            "codeSpanDuplicates" => self.code_span_duplicates.clear(),
+            "lineSpanDuplicates" => self.line_span_duplicates.clear(),
+            "tokenSpanDuplicates" => self.token_span_duplicates.clear(),
+            "blockDuplicates" => self.block_duplicates.clear(),
+            "astSubtreeDuplicates" => self.ast_subtree_duplicates.clear(),
+            "similarBlocksMinhash" => self.similar_blocks_minhash.clear(),
+            "similarBlocksSimhash" => self.similar_blocks_simhash.clear(),
+            "symbolDuplicates" => self.symbol_duplicates.clear(),
+            "importDuplicates" => self.import_duplicates.clear(),
+            "dataDuplicates" => self.data_duplicates.clear(),
+            "fileCoverage" if self.file_coverage.take().is_some() => {}
+            "cloneClusters" if self.clone_clusters.take().is_some() => {}
+            "similarBlockClusters" if self.similar_block_clusters.take().is_some() => {}
+            "licenseConflicts" if self.license_conflicts.take().is_some() => {}
+            "commonIdioms" if self.common_idioms.take().is_some() => {}
+            _ => return,
+        }
+        if !self.omits(section) {
+            self.omitted_sections.push(section.to_string());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonSectionTotal {
+    /// The section's key, e.g. `codeSpanDuplicates`.
+    #[serde(deserialize_with = "section_name")]
+    pub(crate) section: KnownName,
+    pub(crate) total_groups: usize,
+    /// `true` when the section lists fewer groups than `totalGroups`.
+    pub(crate) truncated: bool,
+    /// `true` when the detector hit `maxGroupsPerDetector` and only counted the groups past it.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub(crate) degraded: bool,
+    /// Groups only counted past `maxGroupsPerDetector` (an upper bound), not in `totalGroups`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub(crate) capped_groups: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonReportSample {
+    pub(crate) sampled_files: u64,
+    pub(crate) candidate_files: u64,
+    /// `sampledFiles / candidateFiles`.
+    pub(crate) ratio: f64,
+    /// `potentialSavingsLines / ratio`; closer to a lower bound, since cross-file duplicates
+    /// are only found when every copy is sampled.
+    pub(crate) estimated_potential_savings_lines: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonReportRoot {
+    pub(crate) id: usize,
+    pub(crate) label: String,
+    /// `null` for roots loaded from a snapshot.
+    pub(crate) path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonLineRange {
+    pub(crate) start_line: u32,
+    pub(crate) end_line: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonFileCoverage {
+    pub(crate) repo_id: usize,
+    pub(crate) repo_label: Arc<str>,
+    pub(crate) path: Arc<str>,
+    pub(crate) total_lines: u32,
+    pub(crate) covered_lines: u32,
+    pub(crate) ranges: Vec<JsonLineRange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonCloneCluster {
+    /// Lines covered by the cluster, each line counted once.
+    pub(crate) affected_lines: u64,
+    pub(crate) groups: Vec<JsonClusterMember>,
+    pub(crate) files: Vec<JsonFileCoverage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonClusterMember {
+    #[serde(deserialize_with = "section_name")]
+    pub(crate) section: KnownName,
+    /// Index into the section's array.
+    pub(crate) index: usize,
+    /// The group's `hash`; absent for similar pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonSimilarBlockCluster {
+    /// `minhash` or `simhash`.
+    #[serde(deserialize_with = "similarity_detector")]
+    pub(crate) detector: KnownName,
+    /// Source lines of the first (most connected) block.
+    pub(crate) preview: String,
+    pub(crate) min_score: f64,
+    pub(crate) max_score: f64,
+    pub(crate) blocks: Vec<JsonDuplicateSpanOccurrence>,
+    /// Indices into the detector's `similarBlocks*` array.
+    pub(crate) pairs: Vec<usize>,
+}
+
+/// `genealogy` output: how each span group changed between two snapshots.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonCloneGenealogy {
+    pub(crate) summary: JsonGenealogySummary,
+    pub(crate) lineages: Vec<JsonCloneLineage>,
+}
+
+/// Group counts by change, and the duplication each corpus could save.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonGenealogySummary {
+    pub(crate) added: usize,
+    pub(crate) removed: usize,
+    pub(crate) grown: usize,
+    pub(crate) shrunk: usize,
+    pub(crate) moved: usize,
+    pub(crate) unchanged: usize,
+    pub(crate) old_potential_savings_lines: u64,
+    pub(crate) new_potential_savings_lines: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonCloneLineage {
+    pub(crate) section: &'static str,
+    /// `added`, `removed`, `grown`, `shrunk`, `moved` or `unchanged`.
+    pub(crate) change: &'static str,
+    /// The group in the old snapshot; `null` when added.
+    pub(crate) old: Option<JsonDuplicateSpanGroup>,
+    /// The group in the new snapshot; `null` when removed.
+    pub(crate) new: Option<JsonDuplicateSpanGroup>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonLicenseConflict {
+    #[serde(deserialize_with = "section_name")]
+    pub(crate) section: KnownName,
+    /// Index into the section's array.
+    pub(crate) index: usize,
+    /// The distinct licenses of `files`, sorted; `null` stands for files without a license
+    /// header.
+    pub(crate) licenses: Vec<Option<String>>,
+    pub(crate) files: Vec<JsonLicensedFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonLicensedFile {
+    pub(crate) repo_id: usize,
+    pub(crate) repo_label: Arc<str>,
+    pub(crate) path: Arc<str>,
+    /// SPDX identifier or expression; `null` when the header declares no recognizable license.
+    pub(crate) license: Option<String>,
+}
+
+/// Shares one allocation per distinct repo label / path across everything mapped with it, so
+/// a report listing the same file in thousands of occurrences stores its path once.
+#[derive(Default)]
+struct Interner(HashSet<Arc<str>>);
+
+impl Interner {
+    fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(existing) = self.0.get(s) {
+            return Arc::clone(existing);
+        }
+        let interned: Arc<str> = Arc::from(s);
+        self.0.insert(Arc::clone(&interned));
+        interned
+    }
 }
 
 pub(crate) fn map_duplicate_groups(
     groups: Vec<dup_code_check_core::DuplicateGroup>,
+) -> Vec<JsonDuplicateGroup> {
+    map_duplicate_groups_with(groups, &mut Interner::default())
+}
+
+fn map_duplicate_groups_with(
+    groups: Vec<dup_code_check_core::DuplicateGroup>,
+    interner: &mut Interner,
 ) -> Vec<JsonDuplicateGroup> {
     groups
         .into_iter()
@@ -123,96 +456,828 @@ pub(crate) fn map_duplicate_groups(
                 .into_iter()
                 .map(|f| JsonDuplicateFile {
                     repo_id: f.repo_id(),
-                    repo_label: f.repo_label().to_string(),
-                    path: f.path().to_string(),
+                    repo_label: interner.intern(f.repo_label()),
+                    path: interner.intern(f.path()),
+                    is_test: f.is_test(),
+                    source: None,
                 })
                 .collect(),
+            truncated_files: g.truncated_files,
         })
         .collect()
 }
 
-pub(crate) fn map_span_groups(
-    groups: Vec<dup_code_check_core::DuplicateSpanGroup>,
-) -> Vec<JsonDuplicateSpanGroup> {
+fn map_import_pairs(
+    pairs: &[dup_code_check_core::ImportDuplicatePair],
+    interner: &mut Interner,
+) -> Vec<JsonImportDuplicatePair> {
+    let mut file = |f: &dup_code_check_core::DuplicateFile| JsonDuplicateFile {
+        repo_id: f.repo_id(),
+        repo_label: interner.intern(f.repo_label()),
+        path: interner.intern(f.path()),
+        is_test: f.is_test(),
+        source: None,
+    };
+    pairs
+        .iter()
+        .map(|pair| JsonImportDuplicatePair {
+            a: file(pair.a()),
+            b: file(pair.b()),
+            score: pair.score(),
+            shared_imports: pair.shared_imports().to_vec(),
+        })
+        .collect()
+}
+
+fn map_symbol_groups_with(
+    groups: Vec<dup_code_check_core::SymbolDuplicateGroup>,
+    interner: &mut Interner,
+) -> Vec<JsonSymbolDuplicateGroup> {
     groups
         .into_iter()
-        .map(|g| JsonDuplicateSpanGroup {
+        .map(|g| JsonSymbolDuplicateGroup {
             hash: format!("{:016x}", g.content_hash),
-            normalized_len: g.normalized_len,
-            preview: g.preview,
-            occurrences: g
-                .occurrences
+            symbols: g.symbols,
+            files: g
+                .files
                 .into_iter()
-                .map(|o| JsonDuplicateSpanOccurrence {
-                    repo_id: o.repo_id(),
-                    repo_label: o.repo_label().to_string(),
-                    path: o.path().to_string(),
-                    start_line: o.start_line(),
-                    end_line: o.end_line(),
+                .map(|f| JsonDuplicateFile {
+                    repo_id: f.repo_id(),
+                    repo_label: interner.intern(f.repo_label()),
+                    path: interner.intern(f.path()),
+                    is_test: f.is_test(),
+                    source: None,
                 })
                 .collect(),
+            truncated_files: g.truncated_files,
         })
         .collect()
 }
 
+pub(crate) fn map_span_groups(
+    groups: Vec<dup_code_check_core::DuplicateSpanGroup>,
+) -> Vec<JsonDuplicateSpanGroup> {
+    map_span_groups_with(groups, &mut Interner::default())
+}
+
+fn map_span_groups_with(
+    groups: Vec<dup_code_check_core::DuplicateSpanGroup>,
+    interner: &mut Interner,
+) -> Vec<JsonDuplicateSpanGroup> {
+    groups
+        .into_iter()
+        .map(|g| map_span_group(g, interner))
+        .collect()
+}
+
+fn map_span_group(
+    g: dup_code_check_core::DuplicateSpanGroup,
+    interner: &mut Interner,
+) -> JsonDuplicateSpanGroup {
+    let age_spread_days = g.age_spread().map(|secs| secs / 86_400);
+    JsonDuplicateSpanGroup {
+        hash: format!("{:016x}", g.content_hash),
+        normalized_len: g.normalized_len,
+        preview: g.preview,
+        occurrences: g
+            .occurrences
+            .iter()
+            .map(|o| map_occurrence(o, interner))
+            .collect(),
+        truncated_occurrences: g.truncated_occurrences,
+        duplicated_lines: g.duplicated_lines,
+        potential_savings_lines: g.potential_savings_lines,
+        also_detected_by: g.also_detected_by.iter().map(|s| s.as_str()).collect(),
+        occurrence_previews: g
+            .occurrence_previews
+            .into_iter()
+            .map(|p| JsonOccurrencePreview {
+                occurrence: p.occurrence,
+                preview: p.preview,
+            })
+            .collect(),
+        quality: g.quality.map(|q| JsonGroupQuality {
+            token_entropy: q.token_entropy,
+            punctuation_ratio: q.punctuation_ratio,
+            identifier_diversity: q.identifier_diversity,
+        }),
+        age_spread_days,
+    }
+}
+
+pub(crate) fn map_clone_genealogy(
+    genealogy: &dup_code_check_core::CloneGenealogy,
+) -> JsonCloneGenealogy {
+    use dup_code_check_core::GenealogyChange;
+
+    let interner = &mut Interner::default();
+    let count = |change| genealogy.count(change);
+    JsonCloneGenealogy {
+        summary: JsonGenealogySummary {
+            added: count(GenealogyChange::Added),
+            removed: count(GenealogyChange::Removed),
+            grown: count(GenealogyChange::Grown),
+            shrunk: count(GenealogyChange::Shrunk),
+            moved: count(GenealogyChange::Moved),
+            unchanged: count(GenealogyChange::Unchanged),
+            old_potential_savings_lines: genealogy.old_potential_savings_lines(),
+            new_potential_savings_lines: genealogy.new_potential_savings_lines(),
+        },
+        lineages: genealogy
+            .lineages()
+            .iter()
+            .map(|lineage| JsonCloneLineage {
+                section: lineage.section().as_str(),
+                change: lineage.change().as_str(),
+                old: lineage
+                    .before()
+                    .map(|group| map_span_group(group.clone(), interner)),
+                new: lineage
+                    .after()
+                    .map(|group| map_span_group(group.clone(), interner)),
+            })
+            .collect(),
+    }
+}
+
 pub(crate) fn map_report(report: dup_code_check_core::DuplicationReport) -> JsonDuplicationReport {
+    let potential_savings_lines = report.potential_savings_lines();
+    let interner = &mut Interner::default();
+    let clone_clusters = report
+        .clone_clusters
+        .as_ref()
+        .map(|clusters| map_clone_clusters(clusters, &report, interner));
     JsonDuplicationReport {
-        file_duplicates: map_duplicate_groups(report.file_duplicates),
-        code_span_duplicates: map_span_groups(report.code_span_duplicates),
-        line_span_duplicates: map_span_groups(report.line_span_duplicates),
-        token_span_duplicates: map_span_groups(report.token_span_duplicates),
-        block_duplicates: map_span_groups(report.block_duplicates),
-        ast_subtree_duplicates: map_span_groups(report.ast_subtree_duplicates),
-        similar_blocks_minhash: report
-            .similar_blocks_minhash
-            .into_iter()
-            .map(|p| JsonSimilarityPair {
-                a: JsonDuplicateSpanOccurrence {
-                    repo_id: p.a.repo_id(),
-                    repo_label: p.a.repo_label().to_string(),
-                    path: p.a.path().to_string(),
-                    start_line: p.a.start_line(),
-                    end_line: p.a.end_line(),
-                },
-                b: JsonDuplicateSpanOccurrence {
-                    repo_id: p.b.repo_id(),
-                    repo_label: p.b.repo_label().to_string(),
-                    path: p.b.path().to_string(),
-                    start_line: p.b.start_line(),
-                    end_line: p.b.end_line(),
-                },
-                score: p.score,
-                distance: p.distance,
+        roots: report
+            .roots
+            .iter()
+            .map(|root| JsonReportRoot {
+                id: root.id,
+                label: root.label.clone(),
+                path: root
+                    .path
+                    .as_ref()
+                    .map(|path| path.to_string_lossy().into_owned()),
             })
             .collect(),
-        similar_blocks_simhash: report
-            .similar_blocks_simhash
-            .into_iter()
-            .map(|p| JsonSimilarityPair {
-                a: JsonDuplicateSpanOccurrence {
-                    repo_id: p.a.repo_id(),
-                    repo_label: p.a.repo_label().to_string(),
-                    path: p.a.path().to_string(),
-                    start_line: p.a.start_line(),
-                    end_line: p.a.end_line(),
-                },
-                b: JsonDuplicateSpanOccurrence {
-                    repo_id: p.b.repo_id(),
-                    repo_label: p.b.repo_label().to_string(),
-                    path: p.b.path().to_string(),
-                    start_line: p.b.start_line(),
-                    end_line: p.b.end_line(),
-                },
-                score: p.score,
-                distance: p.distance,
+        file_duplicates: map_duplicate_groups_with(report.file_duplicates, interner),
+        code_span_duplicates: map_span_groups_with(report.code_span_duplicates, interner),
+        line_span_duplicates: map_span_groups_with(report.line_span_duplicates, interner),
+        token_span_duplicates: map_span_groups_with(report.token_span_duplicates, interner),
+        block_duplicates: map_span_groups_with(report.block_duplicates, interner),
+        ast_subtree_duplicates: map_span_groups_with(report.ast_subtree_duplicates, interner),
+        similar_blocks_minhash: map_similarity_pairs(
+            report.similar_blocks_minhash,
+            "minhash",
+            interner,
+        ),
+        similar_blocks_simhash: map_similarity_pairs(
+            report.similar_blocks_simhash,
+            "simhash",
+            interner,
+        ),
+        symbol_duplicates: map_symbol_groups_with(report.symbol_duplicates, interner),
+        import_duplicates: map_import_pairs(&report.import_duplicates, interner),
+        data_duplicates: map_duplicate_groups_with(report.data_duplicates, interner),
+        file_coverage: report
+            .file_coverage
+            .map(|coverage| map_file_coverage(&coverage, interner)),
+        clone_clusters,
+        similar_block_clusters: report
+            .similar_block_clusters
+            .map(|clusters| map_similar_block_clusters(&clusters, interner)),
+        license_conflicts: report
+            .license_conflicts
+            .map(|conflicts| map_license_conflicts(&conflicts, interner)),
+        common_idioms: report
+            .common_idioms
+            .map(|idioms| map_span_groups_with(idioms, interner)),
+        omitted_sections: Vec::new(),
+        section_totals: report
+            .section_totals
+            .iter()
+            .map(|total| JsonSectionTotal {
+                section: total.section().as_str(),
+                total_groups: total.total_groups(),
+                truncated: total.truncated(),
+                degraded: total.degraded(),
+                capped_groups: total.capped_groups(),
             })
             .collect(),
+        potential_savings_lines,
+        sample: report.sample.map(|sample| JsonReportSample {
+            sampled_files: sample.sampled_files(),
+            candidate_files: sample.candidate_files(),
+            ratio: sample.ratio(),
+            estimated_potential_savings_lines: sample.extrapolate(potential_savings_lines),
+        }),
+    }
+}
+
+fn map_clone_clusters(
+    clusters: &[dup_code_check_core::CloneCluster],
+    report: &dup_code_check_core::DuplicationReport,
+    interner: &mut Interner,
+) -> Vec<JsonCloneCluster> {
+    use dup_code_check_core::ReportSection;
+
+    let span_groups = |section| match section {
+        ReportSection::CodeSpanDuplicates => Some(&report.code_span_duplicates),
+        ReportSection::LineSpanDuplicates => Some(&report.line_span_duplicates),
+        ReportSection::TokenSpanDuplicates => Some(&report.token_span_duplicates),
+        ReportSection::BlockDuplicates => Some(&report.block_duplicates),
+        ReportSection::AstSubtreeDuplicates => Some(&report.ast_subtree_duplicates),
+        ReportSection::FileDuplicates
+        | ReportSection::SimilarBlocksMinhash
+        | ReportSection::SimilarBlocksSimhash
+        | ReportSection::SymbolDuplicates
+        | ReportSection::ImportDuplicates
+        | ReportSection::DataDuplicates => None,
+    };
+    let hash_of = |member: &dup_code_check_core::ClusterMember| {
+        let hash = match member.section {
+            ReportSection::FileDuplicates => report
+                .file_duplicates
+                .get(member.index)
+                .map(|group| group.content_hash),
+            section => span_groups(section)
+                .and_then(|groups| groups.get(member.index))
+                .map(|group| group.content_hash),
+        };
+        hash.map(|hash| format!("{hash:016x}"))
+    };
+    clusters
+        .iter()
+        .map(|cluster| JsonCloneCluster {
+            affected_lines: cluster.affected_lines(),
+            groups: cluster
+                .members()
+                .iter()
+                .map(|member| JsonClusterMember {
+                    section: member.section.as_str(),
+                    index: member.index,
+                    hash: hash_of(member),
+                })
+                .collect(),
+            files: map_file_coverage(cluster.files(), interner),
+        })
+        .collect()
+}
+
+fn map_similar_block_clusters(
+    clusters: &[dup_code_check_core::SimilarBlockCluster],
+    interner: &mut Interner,
+) -> Vec<JsonSimilarBlockCluster> {
+    use dup_code_check_core::ReportSection;
+
+    clusters
+        .iter()
+        .map(|cluster| JsonSimilarBlockCluster {
+            detector: match cluster.section() {
+                ReportSection::SimilarBlocksSimhash => "simhash",
+                _ => "minhash",
+            },
+            preview: cluster.preview().to_string(),
+            min_score: cluster.min_score(),
+            max_score: cluster.max_score(),
+            blocks: cluster
+                .blocks()
+                .iter()
+                .map(|block| map_occurrence(block, interner))
+                .collect(),
+            pairs: cluster.pairs().to_vec(),
+        })
+        .collect()
+}
+
+fn map_license_conflicts(
+    conflicts: &[dup_code_check_core::LicenseConflict],
+    interner: &mut Interner,
+) -> Vec<JsonLicenseConflict> {
+    conflicts
+        .iter()
+        .map(|conflict| JsonLicenseConflict {
+            section: conflict.group().section.as_str(),
+            index: conflict.group().index,
+            licenses: conflict
+                .licenses()
+                .into_iter()
+                .map(|license| license.map(str::to_string))
+                .collect(),
+            files: conflict
+                .files()
+                .iter()
+                .map(|file| JsonLicensedFile {
+                    repo_id: file.repo_id(),
+                    repo_label: interner.intern(file.repo_label()),
+                    path: interner.intern(file.path()),
+                    license: file.license().map(str::to_string),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn map_file_coverage(
+    coverage: &[dup_code_check_core::FileCoverage],
+    interner: &mut Interner,
+) -> Vec<JsonFileCoverage> {
+    coverage
+        .iter()
+        .map(|c| JsonFileCoverage {
+            repo_id: c.repo_id(),
+            repo_label: interner.intern(c.repo_label()),
+            path: interner.intern(c.path()),
+            total_lines: c.total_lines(),
+            covered_lines: c.covered_lines(),
+            ranges: c
+                .covered_ranges()
+                .iter()
+                .map(|r| JsonLineRange {
+                    start_line: r.start_line,
+                    end_line: r.end_line,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+fn map_occurrence(
+    o: &dup_code_check_core::DuplicateSpanOccurrence,
+    interner: &mut Interner,
+) -> JsonDuplicateSpanOccurrence {
+    JsonDuplicateSpanOccurrence {
+        repo_id: o.repo_id(),
+        repo_label: interner.intern(o.repo_label()),
+        path: interner.intern(o.path()),
+        start_line: o.start_line(),
+        end_line: o.end_line(),
+        start_col: o.start_col(),
+        end_col: o.end_col(),
+        is_test: o.is_test(),
+        scope: o.scope().map(|scope| interner.intern(scope)),
+        last_modified: o.last_modified().map(format_rfc3339_utc),
+        source: None,
+    }
+}
+
+fn map_similarity_pairs(
+    pairs: Vec<dup_code_check_core::SimilarityPair>,
+    detector: &'static str,
+    interner: &mut Interner,
+) -> Vec<JsonSimilarityPair> {
+    pairs
+        .into_iter()
+        .map(|p| JsonSimilarityPair {
+            detector,
+            a: map_occurrence(&p.a, interner),
+            b: map_occurrence(&p.b, interner),
+            score: p.score,
+            distance: p.distance,
+            diff: p.diff,
+        })
+        .collect()
+}
+
+fn is_zero<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// A `&'static str` read back by [`section_name`] or [`similarity_detector`]. Spelled as an
+/// alias because serde's derive makes `Deserialize<'de>` require `'de: 'static` for fields
+/// written as `&'static str`, which would rule out deserializing from a buffer.
+type KnownName = &'static str;
+
+/// Read back a `ReportSection` key written by `ReportSection::as_str`.
+fn section_name<'de, D: Deserializer<'de>>(deserializer: D) -> Result<&'static str, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    ReportSection::ALL
+        .into_iter()
+        .map(ReportSection::as_str)
+        .find(|section| *section == name)
+        .ok_or_else(|| D::Error::custom(format!("unknown report section {name:?}")))
+}
+
+fn section_names<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<&'static str>, D::Error> {
+    #[derive(Deserialize)]
+    struct Name(#[serde(deserialize_with = "section_name")] KnownName);
+    let names = Vec::<Name>::deserialize(deserializer)?;
+    Ok(names.into_iter().map(|Name(name)| name).collect())
+}
+
+fn similarity_detector<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<&'static str, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    ["minhash", "simhash"]
+        .into_iter()
+        .find(|detector| *detector == name)
+        .ok_or_else(|| D::Error::custom(format!("unknown similarity detector {name:?}")))
+}
+
+/// Bumped whenever a JSON field is renamed/removed or changes meaning (additions don't count).
+pub(crate) const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Header of every JSON object output, so archived results stay interpretable.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonMeta {
+    pub(crate) schema_version: u32,
+    pub(crate) tool_version: &'static str,
+    /// RFC 3339 UTC timestamp of the run.
+    pub(crate) generated_at: String,
+    pub(crate) mode: &'static str,
+    pub(crate) roots: Vec<String>,
+    pub(crate) options: JsonScanOptions,
+}
+
+impl JsonMeta {
+    pub(crate) fn new(mode: &'static str, roots: &[PathBuf], options: &ScanOptions) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Self {
+            schema_version: JSON_SCHEMA_VERSION,
+            tool_version: env!("CARGO_PKG_VERSION"),
+            generated_at: format_rfc3339_utc(now),
+            mode,
+            roots: roots
+                .iter()
+                .map(|root| options.map_output_path(root).to_string_lossy().into_owned())
+                .collect(),
+            options: JsonScanOptions::from(options),
+        }
+    }
+}
+
+/// `--sample`: exactly one of the fields is set.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonSampleOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) fraction: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) files: Option<u64>,
+    /// How files are picked: lowest FNV-1a hash of the root-relative path first.
+    pub(crate) method: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonSimilarityWindows {
+    pub(crate) size: usize,
+    pub(crate) stride: usize,
+}
+
+/// `--version --json`: what this build supports, so wrappers can feature-detect instead of
+/// parsing help text.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonVersion {
+    pub(crate) tool_version: &'static str,
+    pub(crate) core_version: &'static str,
+    pub(crate) schema_version: u32,
+    /// Optional cargo features compiled in (`msgpack`).
+    pub(crate) features: Vec<&'static str>,
+    /// Built-in `--format` values; other names run `externalFormatterPrefix<name>`.
+    pub(crate) formats: Vec<&'static str>,
+    pub(crate) external_formatter_prefix: &'static str,
+    /// Report section keys, one per detector.
+    pub(crate) detectors: Vec<&'static str>,
+}
+
+impl JsonVersion {
+    pub(crate) fn current() -> Self {
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION"),
+            core_version: dup_code_check_core::VERSION,
+            schema_version: JSON_SCHEMA_VERSION,
+            features: vec![
+                #[cfg(feature = "msgpack")]
+                "msgpack",
+            ],
+            formats: ["text", "json", "jsonl", "review-comments"]
+                .into_iter()
+                .chain(Encoding::FORMAT_NAMES.iter().copied())
+                .collect(),
+            external_formatter_prefix: EXTERNAL_FORMATTER_PREFIX,
+            detectors: ReportSection::ALL
+                .into_iter()
+                .map(ReportSection::as_str)
+                .collect(),
+        }
+    }
+}
+
+/// The scan options a run used; `null` means the core default for that mode applied.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonScanOptions {
+    pub(crate) ignore_dirs: Vec<String>,
+    pub(crate) ignore_patterns: Vec<String>,
+    pub(crate) default_excludes: Vec<String>,
+    pub(crate) respect_gitignore: bool,
+    pub(crate) use_global_gitignore: bool,
+    pub(crate) file_listing: &'static str,
+    pub(crate) follow_symlinks: bool,
+    pub(crate) cross_repo_only: bool,
+    pub(crate) max_file_size: Option<u64>,
+    pub(crate) max_files: Option<usize>,
+    pub(crate) max_total_bytes: Option<u64>,
+    pub(crate) budget_order: &'static str,
+    pub(crate) max_normalized_chars: Option<usize>,
+    pub(crate) max_tokens: Option<usize>,
+    pub(crate) max_tokens_per_file: Option<usize>,
+    pub(crate) max_file_time_ms: Option<u64>,
+    pub(crate) min_match_len: usize,
+    pub(crate) min_token_len: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) adaptive_window: Option<usize>,
+    pub(crate) similarity_threshold: f64,
+    pub(crate) simhash_max_distance: u32,
+    pub(crate) shingle_size: usize,
+    pub(crate) minhash_signature_size: usize,
+    pub(crate) minhash_bands: usize,
+    pub(crate) similarity_windows: Option<JsonSimilarityWindows>,
+    pub(crate) line_span_min_lines: usize,
+    pub(crate) line_span_window: usize,
+    pub(crate) symbol_min_count: usize,
+    pub(crate) import_min_count: usize,
+    pub(crate) data_min_bytes: u64,
+    pub(crate) import_similarity_threshold: f64,
+    pub(crate) min_entropy: f64,
+    pub(crate) max_report_items: usize,
+    /// `--max-items` overrides of `max_report_items`, by section name.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) max_items_per_detector: BTreeMap<String, usize>,
+    pub(crate) max_groups_per_detector: Option<usize>,
+    pub(crate) min_occurrences: usize,
+    pub(crate) max_occurrences_per_group: Option<usize>,
+    pub(crate) similarity_diff: bool,
+    pub(crate) file_coverage: bool,
+    pub(crate) file_similarity: bool,
+    pub(crate) clone_clusters: bool,
+    pub(crate) similarity_clusters: bool,
+    pub(crate) occurrence_ages: bool,
+    pub(crate) license_conflicts: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) idiom_min_files: Option<usize>,
+    pub(crate) idiom_token_len: usize,
+    pub(crate) deduplicate_across_detectors: bool,
+    pub(crate) emit_absolute_paths: bool,
+    /// `"<from>=<to>"`, as passed to `--path-prefix-map`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) path_prefix_map: Vec<String>,
+    /// `"i/N"` (1-based), as passed to `--shard`.
+    pub(crate) shard: Option<String>,
+    pub(crate) sample: Option<JsonSampleOptions>,
+    pub(crate) unicode_normalization: Option<&'static str>,
+    pub(crate) test_code_filter: Option<&'static str>,
+    pub(crate) threads: usize,
+    pub(crate) preview_max_chars: Option<usize>,
+    pub(crate) preview_context_lines: usize,
+    pub(crate) report_sort: &'static str,
+    pub(crate) section_offset: usize,
+    pub(crate) section_limit: Option<usize>,
+    /// `--language-scale` overrides; the built-in table is not repeated here.
+    pub(crate) language_token_scales: BTreeMap<String, f64>,
+    pub(crate) path_filter: Vec<String>,
+    pub(crate) path_filter_trim: bool,
+    pub(crate) builtin_skips: bool,
+    pub(crate) prune_contained_groups: bool,
+    /// Number of `--skip-snippet` snippets; their text is not repeated here.
+    pub(crate) skip_snippets: usize,
+    /// `--stop-sequences` entries by language; the built-in lists are not repeated here.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) stop_sequences: BTreeMap<String, Vec<String>>,
+}
+
+impl From<&ScanOptions> for JsonScanOptions {
+    fn from(options: &ScanOptions) -> Self {
+        let mut ignore_dirs: Vec<String> = options.ignore_dirs.iter().cloned().collect();
+        ignore_dirs.sort();
+        Self {
+            ignore_dirs,
+            ignore_patterns: options.ignore_patterns.clone(),
+            default_excludes: options.default_excludes.clone(),
+            respect_gitignore: options.respect_gitignore,
+            use_global_gitignore: options.use_global_gitignore,
+            file_listing: options.file_listing.as_str(),
+            follow_symlinks: options.follow_symlinks,
+            cross_repo_only: options.cross_repo_only,
+            max_file_size: options.max_file_size,
+            max_files: options.max_files,
+            max_total_bytes: options.max_total_bytes,
+            budget_order: options.budget_order.as_str(),
+            max_normalized_chars: options.max_normalized_chars,
+            max_tokens: options.max_tokens,
+            max_tokens_per_file: options.max_tokens_per_file,
+            max_file_time_ms: options
+                .max_file_time
+                .map(|limit| u64::try_from(limit.as_millis()).unwrap_or(u64::MAX)),
+            min_match_len: options.min_match_len,
+            min_token_len: options.min_token_len,
+            adaptive_window: options.adaptive_window,
+            similarity_threshold: options.similarity_threshold,
+            simhash_max_distance: options.simhash_max_distance,
+            shingle_size: options.shingle_size,
+            minhash_signature_size: options.minhash_signature_size,
+            minhash_bands: options.minhash_bands,
+            similarity_windows: options
+                .similarity_windows
+                .map(|windows| JsonSimilarityWindows {
+                    size: windows.size(),
+                    stride: windows.stride(),
+                }),
+            line_span_min_lines: options.line_span_min_lines,
+            line_span_window: options.line_span_window,
+            symbol_min_count: options.symbol_min_count,
+            import_min_count: options.import_min_count,
+            data_min_bytes: options.data_min_bytes,
+            min_entropy: options.min_entropy,
+            import_similarity_threshold: options.import_similarity_threshold,
+            max_report_items: options.max_report_items,
+            max_items_per_detector: options
+                .max_items_per_detector
+                .iter()
+                .map(|(section, &items)| (section.as_str().to_string(), items))
+                .collect(),
+            max_groups_per_detector: options.max_groups_per_detector,
+            min_occurrences: options.min_occurrences,
+            max_occurrences_per_group: options.max_occurrences_per_group,
+            similarity_diff: options.similarity_diff,
+            file_coverage: options.file_coverage,
+            file_similarity: options.file_similarity,
+            clone_clusters: options.clone_clusters,
+            similarity_clusters: options.similarity_clusters,
+            occurrence_ages: options.occurrence_ages,
+            license_conflicts: options.license_conflicts,
+            idiom_min_files: options.idiom_min_files,
+            idiom_token_len: options.idiom_token_len,
+            deduplicate_across_detectors: options.deduplicate_across_detectors,
+            emit_absolute_paths: options.emit_absolute_paths,
+            path_prefix_map: options
+                .path_prefix_map
+                .iter()
+                .map(|(from, to)| format!("{}={}", from.display(), to.display()))
+                .collect(),
+            shard: options
+                .shard
+                .map(|shard| format!("{}/{}", shard.index() + 1, shard.count())),
+            sample: options.sample.map(|sample| JsonSampleOptions {
+                fraction: sample.as_fraction(),
+                files: sample.as_files(),
+                method: "pathHash",
+            }),
+            unicode_normalization: options.unicode_normalization.map(|form| form.as_str()),
+            test_code_filter: options.test_code_filter.map(|filter| filter.as_str()),
+            threads: options.threads,
+            preview_max_chars: options.preview_max_chars,
+            preview_context_lines: options.preview_context_lines,
+            report_sort: options.report_sort.as_str(),
+            section_offset: options.section_offset,
+            section_limit: options.section_limit,
+            language_token_scales: options.language_token_scales.iter().cloned().collect(),
+            path_filter: options.path_filter.clone(),
+            path_filter_trim: options.path_filter_trim,
+            builtin_skips: options.builtin_skips,
+            prune_contained_groups: options.prune_contained_groups,
+            skip_snippets: options.skip_snippets.len(),
+            stop_sequences: options.stop_sequences.iter().fold(
+                BTreeMap::new(),
+                |mut by_language, (language, code)| {
+                    let sequences: &mut Vec<String> =
+                        by_language.entry(language.clone()).or_default();
+                    if !code.is_empty() {
+                        sequences.push(code.clone());
+                    }
+                    by_language
+                },
+            ),
+        }
     }
 }
 
+/// Format seconds since the Unix epoch as `YYYY-MM-DDTHH:MM:SSZ`.
+pub(crate) fn format_rfc3339_utc(secs: u64) -> String {
+    let days = secs / 86_400;
+    let rem = secs % 86_400;
+    // Civil-from-days (proleptic Gregorian), shifted so the era starts on 0000-03-01.
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
 pub(crate) fn write_json<T: Serialize>(value: &T) -> io::Result<()> {
     let json = serde_json::to_string_pretty(value)
         .map_err(|e| io::Error::other(format!("json encode: {e}")))?;
     println!("{json}");
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_json_lists_formats_and_detectors() {
+        let version = serde_json::to_value(JsonVersion::current()).unwrap();
+        assert_eq!(version["toolVersion"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            version["formats"].as_array().unwrap()[..5],
+            ["text", "json", "jsonl", "review-comments", "json.gz"]
+        );
+        assert_eq!(version["detectors"].as_array().unwrap().len(), 11);
+        assert_eq!(version["detectors"][0], "fileDuplicates");
+    }
+
+    #[test]
+    fn truncated_reports_serialize_byte_identically_across_runs() {
+        let root = std::env::temp_dir().join(format!("dcc-tied-json-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        for i in 0..20 {
+            for copy in ["a", "b"] {
+                let text = format!("fn tied_{i:02}() {{ return compute({i}, other_{i:02}); }}\n");
+                std::fs::write(root.join(format!("{copy}{i:02}.rs")), text.repeat(3)).unwrap();
+            }
+        }
+        let mut options = dup_code_check_core::ScanOptions::default();
+        options.max_report_items = 3;
+        options.threads = 4;
+        let roots = std::slice::from_ref(&root);
+        let run = || {
+            let report = dup_code_check_core::generate_duplication_report(roots, &options);
+            serde_json::to_string(&map_report(report.unwrap())).unwrap()
+        };
+        let first = run();
+        for _ in 0..4 {
+            assert_eq!(run(), first);
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+        assert!(first.contains("\"truncated\":true"), "{first}");
+    }
+
+    #[test]
+    fn rfc3339_timestamps_are_utc_calendar_dates() {
+        assert_eq!(format_rfc3339_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc3339_utc(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_rfc3339_utc(1_709_251_199), "2024-02-29T23:59:59Z");
+    }
+
+    #[test]
+    fn interner_shares_one_allocation_per_string() {
+        let mut interner = Interner::default();
+        let a = interner.intern("src/lib.rs");
+        let b = interner.intern(&String::from("src/lib.rs"));
+        let c = interner.intern("src/main.rs");
+        assert!(Arc::ptr_eq(&a, &b));
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert_eq!(serde_json::to_string(&a).unwrap(), "\"src/lib.rs\"");
+    }
+
+    #[test]
+    fn scan_stats_json_uses_core_serde_form() {
+        let mut root = ScanStats::default();
+        root.scanned_files = 2;
+        root.skipped_too_large = 1;
+        let mut stats = root.clone();
+        stats.scanned_bytes = 2048;
+        stats.fingerprints = 10;
+        stats.per_repo = vec![dup_code_check_core::RepoScanStats::new(
+            0,
+            "app".to_string(),
+            root,
+        )];
+
+        let json = serde_json::to_value(JsonScanStats::from(&stats)).unwrap();
+        assert_eq!(json["scannedFiles"], 2);
+        assert_eq!(json["fingerprintsPerKb"], 5.0);
+        assert_eq!(json["perRepo"][0]["repoLabel"], "app");
+        assert_eq!(json["perRepo"][0]["skippedTooLarge"], 1);
+        assert!(json["perRepo"][0].get("perRepo").is_none());
+        let back: JsonScanStats = serde_json::from_value(json).unwrap();
+        assert_eq!(ScanStats::from(&back), stats);
+
+        let sparse: JsonScanStats = serde_json::from_str(r#"{"scannedFiles":3}"#).unwrap();
+        assert_eq!(sparse.stats.scanned_files, 3);
+        assert_eq!(sparse.stats.fingerprints, 0);
+    }
+}
@@ -0,0 +1,434 @@
+use std::io::{self, BufWriter, Write};
+
+use serde::Serialize;
+use serde::ser::{
+    Impossible, SerializeSeq, SerializeStruct, SerializeTuple, SerializeTupleStruct, Serializer,
+};
+
+use crate::format::{FormatInput, ReportFormatter, ScanOutput};
+
+/// `--format jsonl`: the JSON Lines output format, one self-contained JSON object per line, so
+/// pipelines can filter a large result without parsing one huge document.
+///
+/// Every line carries a `type`: the key the value has in the `--format json` document (`meta`,
+/// `groups`, `files`, a report section such as `tokenSpanDuplicates`, `scanStats`, ...). Arrays
+/// are split into one line per element; an element or value that is not an object is wrapped
+/// as `{"type": ..., "value": ...}`.
+///
+/// This formats the finished result like any other formatter; it does not stream groups while
+/// the scan runs. Each line is serialized on its own, so no JSON tree of the whole result is
+/// built on top of it.
+pub(crate) struct JsonlFormatter;
+
+impl ReportFormatter for JsonlFormatter {
+    fn write(&self, input: &FormatInput<'_>) -> io::Result<()> {
+        let mut out = BufWriter::new(io::stdout().lock());
+        write_jsonl(&mut out, input)?;
+        out.flush()
+    }
+}
+
+fn write_jsonl(out: &mut impl Write, input: &FormatInput<'_>) -> io::Result<()> {
+    write_entry(out, "meta", input.meta)?;
+    match input.output {
+        ScanOutput::Files(groups) => write_entry(out, "groups", groups)?,
+        ScanOutput::CodeSpans(groups) => write_entry(out, "groups", groups)?,
+        ScanOutput::ReportByFile(view) => write_entry(out, "files", &view.files)?,
+        ScanOutput::Report(report) => write_fields(out, report)?,
+        ScanOutput::ReportByOwnership(view) => write_fields(out, view)?,
+        ScanOutput::Genealogy(genealogy) => write_fields(out, genealogy)?,
+    }
+    if input.include_stats {
+        write_entry(out, "scanStats", input.stats)?;
+    }
+    Ok(())
+}
+
+/// Write each field of a struct (a report, ownership view or genealogy) under its key.
+fn write_fields<T: Serialize + ?Sized>(out: &mut impl Write, value: &T) -> io::Result<()> {
+    value
+        .serialize(Lines { out, key: "" })
+        .map_err(encode_error)
+}
+
+/// Write `value` under `key`: one line per element for an array, else a single line.
+fn write_entry<T: Serialize + ?Sized>(
+    out: &mut impl Write,
+    key: &str,
+    value: &T,
+) -> io::Result<()> {
+    if !write_object_line(out, key, value)? {
+        value.serialize(Lines { out, key }).map_err(encode_error)?;
+    }
+    Ok(())
+}
+
+/// Write `value` with its fields after `type` when it serializes to an object; returns `false`
+/// (having written nothing) otherwise. Flattening a `null` succeeds without writing a field, so
+/// a line without fields is only kept when the value is an empty object.
+fn write_object_line<T: Serialize + ?Sized>(
+    out: &mut impl Write,
+    key: &str,
+    value: &T,
+) -> io::Result<bool> {
+    #[derive(Serialize)]
+    struct ObjectLine<'a, T: ?Sized> {
+        #[serde(rename = "type")]
+        kind: &'a str,
+        #[serde(flatten)]
+        value: &'a T,
+    }
+
+    let mut line = Vec::new();
+    if serde_json::to_writer(&mut line, &ObjectLine { kind: key, value }).is_err() {
+        return Ok(false);
+    }
+    let bare_len = serde_json::to_vec(key).map_err(encode_error)?.len() + "{\"type\":}".len();
+    if line.len() == bare_len && serde_json::to_vec(value).map_err(encode_error)? == b"null" {
+        return Ok(false);
+    }
+    line.push(b'\n');
+    out.write_all(&line)?;
+    Ok(true)
+}
+
+/// Write `value` as one line: its fields after `type` for an object, else wrapped as
+/// `{"type": key, "value": value}`.
+fn write_line<T: Serialize + ?Sized>(out: &mut impl Write, key: &str, value: &T) -> io::Result<()> {
+    #[derive(Serialize)]
+    struct ValueLine<'a, T: ?Sized> {
+        #[serde(rename = "type")]
+        kind: &'a str,
+        value: &'a T,
+    }
+
+    if !write_object_line(out, key, value)? {
+        serde_json::to_writer(&mut *out, &ValueLine { kind: key, value }).map_err(encode_error)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn encode_error(err: serde_json::Error) -> io::Error {
+    io::Error::other(format!("json encode: {err}"))
+}
+
+/// Serializer that writes a value that is not an object under `key`: one line per element of
+/// an array (or tuple), a wrapped line for a scalar. A struct is split into its fields, each
+/// written under its own key, which is how [`write_fields`] splits a report.
+struct Lines<'a, W> {
+    out: &'a mut W,
+    key: &'a str,
+}
+
+impl<W: Write> Lines<'_, W> {
+    fn scalar<T: Serialize + ?Sized>(self, value: &T) -> Result<(), serde_json::Error> {
+        write_line(self.out, self.key, value).map_err(serde_json::Error::io)
+    }
+}
+
+macro_rules! scalars {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(self, value: $ty) -> Result<(), serde_json::Error> {
+                self.scalar(&value)
+            }
+        )*
+    };
+}
+
+impl<'a, W: Write> Serializer for Lines<'a, W> {
+    type Ok = ();
+    type Error = serde_json::Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), serde_json::Error>;
+    type SerializeMap = Impossible<(), serde_json::Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), serde_json::Error>;
+
+    scalars!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+    );
+
+    fn serialize_none(self) -> Result<(), serde_json::Error> {
+        self.scalar(&())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), serde_json::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), serde_json::Error> {
+        self.scalar(&())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), serde_json::Error> {
+        self.scalar(&())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), serde_json::Error> {
+        self.scalar(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), serde_json::Error> {
+        Err(serde::ser::Error::custom("jsonl: unexpected enum variant"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, serde_json::Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, serde_json::Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self, serde_json::Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, serde_json::Error> {
+        Err(serde::ser::Error::custom("jsonl: unexpected enum variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, serde_json::Error> {
+        Err(serde::ser::Error::custom("jsonl: unexpected map"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, serde_json::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, serde_json::Error> {
+        Err(serde::ser::Error::custom("jsonl: unexpected enum variant"))
+    }
+}
+
+impl<W: Write> SerializeSeq for Lines<'_, W> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        write_line(self.out, self.key, value).map_err(serde_json::Error::io)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> SerializeTuple for Lines<'_, W> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> SerializeTupleStruct for Lines<'_, W> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+}
+
+impl<W: Write> SerializeStruct for Lines<'_, W> {
+    type Ok = ();
+    type Error = serde_json::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), serde_json::Error> {
+        write_entry(self.out, key, value).map_err(serde_json::Error::io)
+    }
+
+    fn end(self) -> Result<(), serde_json::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use dup_code_check_core::{ScanOptions, ScanStats};
+
+    use super::*;
+    use crate::json::{JsonDuplicateFile, JsonDuplicateGroup, JsonMeta, JsonScanStats};
+    use crate::locale::Localization;
+
+    fn lines(output: &ScanOutput, include_stats: bool) -> Vec<serde_json::Value> {
+        let meta = JsonMeta::new(output.mode(), &[], &ScanOptions::default());
+        let stats = JsonScanStats::from(&ScanStats::default());
+        let input = FormatInput {
+            output,
+            stats: &stats,
+            meta: &meta,
+            include_stats,
+            localization: Localization::En,
+            color: false,
+            hyperlinks: false,
+            links: None,
+        };
+        let mut out = Vec::new();
+        write_jsonl(&mut out, &input).unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn writes_one_line_per_group_after_the_meta_line() {
+        let group = |hash: &str| JsonDuplicateGroup {
+            hash: hash.to_string(),
+            normalized_len: 10,
+            files: vec![JsonDuplicateFile {
+                repo_id: 0,
+                repo_label: "repo".into(),
+                path: "a.rs".into(),
+                is_test: false,
+                source: None,
+            }],
+            truncated_files: 0,
+        };
+        let output = ScanOutput::Files(vec![group("01"), group("02")]);
+
+        let lines = lines(&output, false);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["type"], "meta");
+        assert_eq!(lines[0]["mode"], "files");
+        assert_eq!(lines[1]["type"], "groups");
+        assert_eq!(lines[1]["hash"], "01");
+        assert_eq!(lines[2]["hash"], "02");
+
+        let with_stats = super::tests::lines(&output, true);
+        assert_eq!(with_stats.len(), 4);
+        assert_eq!(with_stats.last().unwrap()["type"], "scanStats");
+        assert_eq!(with_stats.last().unwrap()["scannedFiles"], 0);
+    }
+
+    #[test]
+    fn report_lines_follow_the_json_document() {
+        let root = std::env::temp_dir().join(format!("dcc-jsonl-report-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        for (name, text) in [("a", "one"), ("b", "one"), ("c", "two"), ("d", "two")] {
+            std::fs::write(root.join(format!("{name}.txt")), text).unwrap();
+        }
+        let report = crate::json::map_report(
+            dup_code_check_core::generate_duplication_report(
+                std::slice::from_ref(&root),
+                &ScanOptions::default(),
+            )
+            .unwrap(),
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+        let document = serde_json::to_value(&report).unwrap();
+        let output = ScanOutput::Report(Box::new(report));
+
+        let mut expected = vec![serde_json::json!("meta")];
+        for (key, value) in document.as_object().unwrap() {
+            match value {
+                serde_json::Value::Array(items) => {
+                    expected.extend(items.iter().map(|_| serde_json::json!(key)));
+                }
+                _ => expected.push(serde_json::json!(key)),
+            }
+        }
+        let lines = lines(&output, false);
+        let types: Vec<_> = lines.iter().map(|line| line["type"].clone()).collect();
+        assert_eq!(types, expected);
+        let files: Vec<_> = lines
+            .iter()
+            .filter(|line| line["type"] == "fileDuplicates")
+            .collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0]["files"], document["fileDuplicates"][0]["files"]);
+    }
+
+    #[test]
+    fn non_object_values_are_wrapped() {
+        let mut out = Vec::new();
+        write_entry(&mut out, "potentialSavingsLines", &12u64).unwrap();
+        write_entry(&mut out, "roots", &Vec::<String>::new()).unwrap();
+        write_entry(&mut out, "baseline", &None::<u64>).unwrap();
+        write_entry(&mut out, "tags", &["a", "b"]).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "{\"type\":\"potentialSavingsLines\",\"value\":12}\n\
+             {\"type\":\"baseline\",\"value\":null}\n\
+             {\"type\":\"tags\",\"value\":\"a\"}\n\
+             {\"type\":\"tags\",\"value\":\"b\"}\n"
+        );
+    }
+}
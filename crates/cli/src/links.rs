@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::locale::{Localization, tr};
+
+const PLACEHOLDERS: [&str; 5] = ["{repo}", "{rev}", "{path}", "{start}", "{end}"];
+
+/// `--link-template`: turns an occurrence into a code-hosting URL, e.g.
+/// `https://github.com/org/{repo}/blob/{rev}/{path}#L{start}-L{end}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct LinkTemplate {
+    template: String,
+    /// Scanned roots by repo id, for making `--absolute-paths` output relative again.
+    roots: Vec<PathBuf>,
+    /// `{repo}` of each root: the directory name of its git checkout, else of the root itself.
+    labels: Vec<String>,
+    /// `git rev-parse HEAD` of each root (by repo id); `None` outside a git checkout.
+    revs: Vec<Option<String>>,
+    /// Where each root sits in its git checkout (`git rev-parse --show-prefix`), so `{path}` is
+    /// relative to the repository even when a subdirectory was scanned.
+    prefixes: Vec<String>,
+}
+
+/// Reject templates with no `{path}` or with an unknown `{...}` placeholder.
+pub(crate) fn validate_link_template(
+    localization: Localization,
+    template: &str,
+) -> Result<(), String> {
+    if !template.contains("{path}") {
+        return Err(tr(
+            localization,
+            "--link-template must contain {path}",
+            "--link-template 必须包含 {path}",
+        )
+        .to_string());
+    }
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        let placeholder = rest[open..]
+            .find('}')
+            .map(|close| &rest[open..=open + close])
+            .filter(|placeholder| PLACEHOLDERS.contains(placeholder));
+        let Some(placeholder) = placeholder else {
+            return Err(format!(
+                "{}: {}",
+                tr(
+                    localization,
+                    "--link-template supports {repo}, {rev}, {path}, {start} and {end}",
+                    "--link-template 仅支持 {repo}、{rev}、{path}、{start} 与 {end}",
+                ),
+                template
+            ));
+        };
+        rest = &rest[open + placeholder.len()..];
+    }
+    Ok(())
+}
+
+impl LinkTemplate {
+    /// Resolve `{rev}` and the repository-relative location of every root.
+    pub(crate) fn new(template: &str, roots: &[PathBuf]) -> Self {
+        let mut labels = Vec::with_capacity(roots.len());
+        let mut revs = Vec::with_capacity(roots.len());
+        let mut prefixes = Vec::with_capacity(roots.len());
+        for (id, root) in roots.iter().enumerate() {
+            let git = git_location(root);
+            let top = git.as_ref().map_or(root.as_path(), |git| git.top.as_path());
+            labels.push(
+                top.file_name()
+                    .and_then(|name| name.to_str())
+                    .filter(|name| !name.is_empty())
+                    .map_or_else(|| format!("repo{id}"), str::to_string),
+            );
+            revs.push(git.as_ref().map(|git| git.rev.clone()));
+            prefixes.push(git.map(|git| git.prefix).unwrap_or_default());
+        }
+        Self {
+            template: template.to_string(),
+            roots: roots.to_vec(),
+            labels,
+            revs,
+            prefixes,
+        }
+    }
+
+    /// The URL of `path` (lines `start..=end` when known) in repo `repo_id`; `None` for repos
+    /// that are not scanned roots (e.g. snapshot or stdin files), for a `{rev}` that could not
+    /// be resolved, and for an absolute path outside its root. Without lines, the template's
+    /// `#...` fragment is dropped if it refers to them.
+    pub(crate) fn url(
+        &self,
+        repo_id: usize,
+        path: &str,
+        lines: Option<(u32, u32)>,
+    ) -> Option<String> {
+        let root = self.roots.get(repo_id)?;
+        let label = &self.labels[repo_id];
+        let rev = if self.template.contains("{rev}") {
+            self.revs.get(repo_id)?.as_deref()?
+        } else {
+            ""
+        };
+        let path = if Path::new(path).is_absolute() {
+            Path::new(path)
+                .strip_prefix(root)
+                .ok()?
+                .to_string_lossy()
+                .replace('\\', "/")
+        } else {
+            path.to_string()
+        };
+        let path = format!("{}{path}", self.prefixes[repo_id]);
+        let template = match (lines, self.template.split_once('#')) {
+            (None, Some((base, fragment)))
+                if fragment.contains("{start}") || fragment.contains("{end}") =>
+            {
+                base
+            }
+            _ => self.template.as_str(),
+        };
+        let (start, end) = lines.map_or((String::new(), String::new()), |(start, end)| {
+            (start.to_string(), end.to_string())
+        });
+        Some(
+            template
+                .replace("{repo}", &encode(label))
+                .replace("{rev}", &encode(rev))
+                .replace("{path}", &encode(&path))
+                .replace("{start}", &start)
+                .replace("{end}", &end),
+        )
+    }
+}
+
+/// The git checkout containing a root.
+struct GitLocation {
+    rev: String,
+    top: PathBuf,
+    /// The root's path inside the checkout: empty or ending in `/`.
+    prefix: String,
+}
+
+fn git_location(root: &Path) -> Option<GitLocation> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["rev-parse", "HEAD", "--show-toplevel", "--show-prefix"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8(output.stdout).ok()?;
+    let mut lines = stdout.lines();
+    let rev = lines.next()?.trim();
+    let top = lines.next()?.trim();
+    let prefix = lines.next().unwrap_or_default().trim();
+    (!rev.is_empty()).then(|| GitLocation {
+        rev: rev.to_string(),
+        top: PathBuf::from(top),
+        prefix: prefix.to_string(),
+    })
+}
+
+/// Percent-encode everything but unreserved characters and `/`.
+fn encode(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    for byte in raw.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            out.push(char::from(byte));
+        } else {
+            out.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(template: &str, revs: Vec<Option<String>>) -> LinkTemplate {
+        LinkTemplate {
+            template: template.to_string(),
+            roots: vec![PathBuf::from("/work/app"); revs.len()],
+            labels: vec!["app".to_string(); revs.len()],
+            prefixes: vec!["sub/".to_string(); revs.len()],
+            revs,
+        }
+    }
+
+    #[test]
+    fn urls_fill_in_the_placeholders() {
+        let links = template(
+            "https://github.com/org/{repo}/blob/{rev}/{path}#L{start}-L{end}",
+            vec![Some("abc123".to_string())],
+        );
+        assert_eq!(
+            links.url(0, "src/my file.rs", Some((3, 9))).as_deref(),
+            Some("https://github.com/org/app/blob/abc123/sub/src/my%20file.rs#L3-L9")
+        );
+        assert_eq!(
+            links.url(0, "/work/app/src/a.rs", None).as_deref(),
+            Some("https://github.com/org/app/blob/abc123/sub/src/a.rs")
+        );
+        assert_eq!(links.url(0, "/elsewhere/a.rs", None), None);
+        assert_eq!(links.url(1, "a.rs", Some((1, 2))), None);
+        assert_eq!(
+            template("https://x/{rev}/{path}", vec![None]).url(0, "a.rs", None),
+            None
+        );
+    }
+
+    #[test]
+    fn templates_need_a_path_and_known_placeholders() {
+        let ok = |raw: &str| validate_link_template(Localization::En, raw).is_ok();
+        assert!(ok("https://x/{repo}/{rev}/{path}#L{start}-L{end}"));
+        assert!(!ok("https://x/{repo}"));
+        assert!(!ok("https://x/{path}?{branch}"));
+        assert!(!ok("https://x/{path}{"));
+    }
+}
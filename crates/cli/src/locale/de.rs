@@ -0,0 +1,210 @@
+//! German messages, keyed by the English text passed to [`super::tr`].
+
+pub(super) const MESSAGES: &[(&str, &str)] = &[
+    (
+        "  (token stream cut at --max-tokens-per-file; later blocks are missing)\n",
+        "  (Tokenstrom bei --max-tokens-per-file abgeschnitten; spätere Blöcke fehlen)\n",
+    ),
+    ("  diff (A -> B):\n", "  Diff (A -> B):\n"),
+    (" (test)", " (Test)"),
+    (" in ", " in "),
+    (" modified ", " geändert "),
+    (
+        "# no vendored, fixture or generated files in the report\n",
+        "# keine Fremd-, Fixture- oder generierten Dateien im Bericht\n",
+    ),
+    (
+        "# save as a file and pass it to --import-ignores, or pass each pattern to --exclude\n",
+        "# als Datei speichern und an --import-ignores übergeben oder jedes Muster an --exclude übergeben\n",
+    ),
+    ("(none)", "(keine)"),
+    (
+        "--emit-blocks expects files, not directories",
+        "--emit-blocks erwartet Dateien, keine Verzeichnisse",
+    ),
+    (
+        "--link-template must contain {path}",
+        "--link-template muss {path} enthalten",
+    ),
+    (
+        "--link-template supports {repo}, {rev}, {path}, {start} and {end}",
+        "--link-template unterstützt nur {repo}, {rev}, {path}, {start} und {end}",
+    ),
+    ("<- decisive", "<- entscheidend"),
+    ("<- decisive:", "<- entscheidend:"),
+    (
+        "== AST subtree duplicates ==\n",
+        "== doppelte AST-Teilbäume ==\n",
+    ),
+    ("== block duplicates ==\n", "== doppelte Blöcke ==\n"),
+    ("== clone clusters ==\n", "== Klon-Cluster ==\n"),
+    ("== clone genealogy ==\n", "== Klon-Genealogie ==\n"),
+    (
+        "== code span duplicates ==\n",
+        "== doppelte Codeabschnitte ==\n",
+    ),
+    ("== common idioms ==\n", "== häufige Idiome ==\n"),
+    (
+        "== cross-team duplicates ==\n",
+        "== teamübergreifende Duplikate ==\n",
+    ),
+    (
+        "== data file duplicates ==\n",
+        "== doppelte Datendateien ==\n",
+    ),
+    ("== file coverage ==\n", "== Dateiabdeckung ==\n"),
+    ("== file duplicates ==\n", "== doppelte Dateien ==\n"),
+    ("== import duplicates ==\n", "== doppelte Imports ==\n"),
+    (
+        "== intra-team duplicates ==\n",
+        "== Duplikate innerhalb eines Teams ==\n",
+    ),
+    ("== license conflicts ==\n", "== Lizenzkonflikte ==\n"),
+    (
+        "== line span duplicates ==\n",
+        "== doppelte Zeilenabschnitte ==\n",
+    ),
+    ("== scan stats ==\n", "== Scan-Statistik ==\n"),
+    (
+        "== similar block clusters ==\n",
+        "== Cluster ähnlicher Blöcke ==\n",
+    ),
+    (
+        "== similar blocks (minhash) ==\n",
+        "== ähnliche Blöcke (minhash) ==\n",
+    ),
+    (
+        "== similar blocks (simhash) ==\n",
+        "== ähnliche Blöcke (simhash) ==\n",
+    ),
+    (
+        "== suggested excludes ==\n",
+        "== vorgeschlagene Ausschlüsse ==\n",
+    ),
+    ("== symbol duplicates ==\n", "== doppelte Symbole ==\n"),
+    (
+        "== token span duplicates ==\n",
+        "== doppelte Token-Abschnitte ==\n",
+    ),
+    (
+        "== unowned duplicates ==\n",
+        "== Duplikate ohne Zuständige ==\n",
+    ),
+    ("Comparing", "Vergleich"),
+    ("Duplication diff", "Duplikat-Diff"),
+    ("Error", "Fehler"),
+    (
+        "Re-run with --stats for full details.\n",
+        "Für alle Details mit --stats erneut ausführen.\n",
+    ),
+    (
+        "SAMPLED REPORT, figures below cover sampled files only",
+        "STICHPROBENBERICHT, die Zahlen unten betreffen nur die Stichprobe",
+    ),
+    ("Warning", "Warnung"),
+    (
+        "Warning: scan was incomplete (fatal skips):\n",
+        "Warnung: Scan unvollständig (fatale Überspringungen):\n",
+    ),
+    ("added", "hinzugefügt"),
+    ("aligned tokens", "ausgerichtete Tokens"),
+    ("alignment:\n", "Ausrichtung:\n"),
+    ("also_detected_by", "auch_erkannt_von"),
+    (
+        "below minTokenLen, skipped",
+        "unter minTokenLen, übersprungen",
+    ),
+    ("block + AST subtree", "Block + AST-Teilbaum"),
+    (
+        "block only, subtree below minTokenLen",
+        "nur Block, Teilbaum unter minTokenLen",
+    ),
+    ("blocks", "Blöcke"),
+    ("bytes", "Bytes"),
+    ("cache", "Cache"),
+    ("changed", "geändert"),
+    ("clusters", "Cluster"),
+    ("conflicts", "Konflikte"),
+    ("degraded, kept", "herabgestuft, behalten"),
+    (
+        "duplicate code span groups",
+        "Gruppen doppelter Codeabschnitte",
+    ),
+    ("duplicate groups", "Duplikatgruppen"),
+    (
+        "empty or unclosed, skipped",
+        "leer oder nicht geschlossen, übersprungen",
+    ),
+    ("entries", "Einträge"),
+    (
+        "entries must be permission, paths, walk, budget or bucket, got",
+        "Einträge müssen permission, paths, walk, budget oder bucket sein, erhalten:",
+    ),
+    ("expired on", "abgelaufen am"),
+    ("files", "Dateien"),
+    ("files with duplicates", "Dateien mit Duplikaten"),
+    ("generated code", "generierter Code"),
+    ("groups", "Gruppen"),
+    (
+        "groups and only counted up to",
+        "Gruppen, nur gezählt bis zu",
+    ),
+    ("hit", "Treffer"),
+    ("hit rate", "Trefferquote"),
+    ("hits", "Treffer"),
+    ("import pairs", "Import-Paare"),
+    (
+        "in total (raise --max-report-items to see more)",
+        "insgesamt (--max-report-items erhöhen, um mehr zu sehen)",
+    ),
+    ("it no longer applies", "sie gilt nicht mehr"),
+    ("kept while", "behalten solange"),
+    ("last run", "letzter Lauf"),
+    ("matches no group", "passt zu keiner Gruppe"),
+    (
+        "matches no report section",
+        "passt zu keinem Berichtsabschnitt",
+    ),
+    ("miss", "Fehlschlag"),
+    ("misses", "Fehlschläge"),
+    (
+        "more (raise --max-groups-per-detector to list them)",
+        "weitere (--max-groups-per-detector erhöhen, um sie aufzulisten)",
+    ),
+    ("more (truncated)", "weitere (gekürzt)"),
+    ("more groups", "weitere Gruppen"),
+    (
+        "must be 1/0 or true/false, got",
+        "muss 1/0 oder true/false sein, erhalten:",
+    ),
+    ("normalized length", "normalisierte Länge"),
+    ("occurrences", "Vorkommen"),
+    ("per root:", "pro Root:"),
+    ("potential savings (lines)", "mögliche Einsparung (Zeilen)"),
+    (
+        "potential savings, extrapolated to all files (lines)",
+        "mögliche Einsparung, auf alle Dateien hochgerechnet (Zeilen)",
+    ),
+    ("removed", "entfernt"),
+    ("savings_lines", "einsparbare_Zeilen"),
+    ("section", "Abschnitt"),
+    ("shared API", "gemeinsame API"),
+    ("shared imports", "gemeinsame Imports"),
+    ("similar pairs", "ähnliche Paare"),
+    ("size", "Größe"),
+    ("skipped:", "übersprungen:"),
+    ("snapshot", "Snapshot"),
+    ("stale", "veraltet"),
+    ("subtree", "Teilbaum"),
+    ("suppressed groups", "unterdrückte Gruppen"),
+    ("suppression", "Unterdrückung"),
+    ("symbol groups", "Symbolgruppen"),
+    ("test fixtures", "Test-Fixtures"),
+    ("thresholds:\n", "Schwellenwerte:\n"),
+    ("tokens", "Tokens"),
+    ("truncated,", "gekürzt,"),
+    ("unchanged", "unverändert"),
+    ("vendored code", "Fremdcode"),
+    ("was", "vorher"),
+    ("whole file", "ganze Datei"),
+];
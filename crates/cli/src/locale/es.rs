@@ -0,0 +1,210 @@
+//! Spanish messages, keyed by the English text passed to [`super::tr`].
+
+pub(super) const MESSAGES: &[(&str, &str)] = &[
+    (
+        "  (token stream cut at --max-tokens-per-file; later blocks are missing)\n",
+        "  (flujo de tokens cortado en --max-tokens-per-file; faltan los bloques posteriores)\n",
+    ),
+    ("  diff (A -> B):\n", "  diferencias (A -> B):\n"),
+    (" (test)", " (prueba)"),
+    (" in ", " en "),
+    (" modified ", " modificado "),
+    (
+        "# no vendored, fixture or generated files in the report\n",
+        "# no hay archivos de terceros, de fixtures ni generados en el informe\n",
+    ),
+    (
+        "# save as a file and pass it to --import-ignores, or pass each pattern to --exclude\n",
+        "# guárdelo en un archivo y páselo a --import-ignores, o pase cada patrón a --exclude\n",
+    ),
+    ("(none)", "(ninguna)"),
+    (
+        "--emit-blocks expects files, not directories",
+        "--emit-blocks espera archivos, no directorios",
+    ),
+    (
+        "--link-template must contain {path}",
+        "--link-template debe contener {path}",
+    ),
+    (
+        "--link-template supports {repo}, {rev}, {path}, {start} and {end}",
+        "--link-template solo admite {repo}, {rev}, {path}, {start} y {end}",
+    ),
+    ("<- decisive", "<- decisivo"),
+    ("<- decisive:", "<- decisivo:"),
+    (
+        "== AST subtree duplicates ==\n",
+        "== subárboles AST duplicados ==\n",
+    ),
+    ("== block duplicates ==\n", "== bloques duplicados ==\n"),
+    ("== clone clusters ==\n", "== clústeres de clones ==\n"),
+    ("== clone genealogy ==\n", "== genealogía de clones ==\n"),
+    (
+        "== code span duplicates ==\n",
+        "== fragmentos de código duplicados ==\n",
+    ),
+    ("== common idioms ==\n", "== modismos comunes ==\n"),
+    (
+        "== cross-team duplicates ==\n",
+        "== duplicados entre equipos ==\n",
+    ),
+    (
+        "== data file duplicates ==\n",
+        "== archivos de datos duplicados ==\n",
+    ),
+    ("== file coverage ==\n", "== cobertura por archivo ==\n"),
+    ("== file duplicates ==\n", "== archivos duplicados ==\n"),
+    ("== import duplicates ==\n", "== imports duplicados ==\n"),
+    (
+        "== intra-team duplicates ==\n",
+        "== duplicados dentro de un equipo ==\n",
+    ),
+    (
+        "== license conflicts ==\n",
+        "== conflictos de licencia ==\n",
+    ),
+    (
+        "== line span duplicates ==\n",
+        "== fragmentos de líneas duplicados ==\n",
+    ),
+    ("== scan stats ==\n", "== estadísticas del escaneo ==\n"),
+    (
+        "== similar block clusters ==\n",
+        "== clústeres de bloques similares ==\n",
+    ),
+    (
+        "== similar blocks (minhash) ==\n",
+        "== bloques similares (minhash) ==\n",
+    ),
+    (
+        "== similar blocks (simhash) ==\n",
+        "== bloques similares (simhash) ==\n",
+    ),
+    (
+        "== suggested excludes ==\n",
+        "== exclusiones sugeridas ==\n",
+    ),
+    ("== symbol duplicates ==\n", "== símbolos duplicados ==\n"),
+    (
+        "== token span duplicates ==\n",
+        "== fragmentos de tokens duplicados ==\n",
+    ),
+    (
+        "== unowned duplicates ==\n",
+        "== duplicados sin responsable ==\n",
+    ),
+    ("Comparing", "Comparando"),
+    ("Duplication diff", "Diferencias de duplicación"),
+    ("Error", "Error"),
+    (
+        "Re-run with --stats for full details.\n",
+        "Vuelva a ejecutar con --stats para ver todos los detalles.\n",
+    ),
+    (
+        "SAMPLED REPORT, figures below cover sampled files only",
+        "INFORME MUESTREADO, las cifras siguientes solo cubren los archivos muestreados",
+    ),
+    ("Warning", "Advertencia"),
+    (
+        "Warning: scan was incomplete (fatal skips):\n",
+        "Aviso: el escaneo quedó incompleto (omisiones fatales):\n",
+    ),
+    ("added", "añadido"),
+    ("aligned tokens", "tokens alineados"),
+    ("alignment:\n", "alineación:\n"),
+    ("also_detected_by", "también_detectado_por"),
+    (
+        "below minTokenLen, skipped",
+        "por debajo de minTokenLen, omitido",
+    ),
+    ("block + AST subtree", "bloque + subárbol AST"),
+    (
+        "block only, subtree below minTokenLen",
+        "solo bloque, subárbol por debajo de minTokenLen",
+    ),
+    ("blocks", "bloques"),
+    ("bytes", "bytes"),
+    ("cache", "caché"),
+    ("changed", "cambiado"),
+    ("clusters", "clústeres"),
+    ("conflicts", "conflictos"),
+    ("degraded, kept", "degradado, conservados"),
+    (
+        "duplicate code span groups",
+        "grupos de fragmentos de código duplicados",
+    ),
+    ("duplicate groups", "grupos duplicados"),
+    ("empty or unclosed, skipped", "vacío o sin cerrar, omitido"),
+    ("entries", "entradas"),
+    (
+        "entries must be permission, paths, walk, budget or bucket, got",
+        "las entradas deben ser permission, paths, walk, budget o bucket; se recibió",
+    ),
+    ("expired on", "caducó el"),
+    ("files", "archivos"),
+    ("files with duplicates", "archivos con duplicados"),
+    ("generated code", "código generado"),
+    ("groups", "grupos"),
+    (
+        "groups and only counted up to",
+        "grupos y solo contados hasta",
+    ),
+    ("hit", "acierto"),
+    ("hit rate", "tasa de aciertos"),
+    ("hits", "aciertos"),
+    ("import pairs", "pares de imports"),
+    (
+        "in total (raise --max-report-items to see more)",
+        "en total (aumente --max-report-items para ver más)",
+    ),
+    ("it no longer applies", "ya no se aplica"),
+    ("kept while", "se mantiene si"),
+    ("last run", "última ejecución"),
+    ("matches no group", "no coincide con ningún grupo"),
+    (
+        "matches no report section",
+        "no coincide con ninguna sección del informe",
+    ),
+    ("miss", "fallo"),
+    ("misses", "fallos"),
+    (
+        "more (raise --max-groups-per-detector to list them)",
+        "más (aumente --max-groups-per-detector para listarlos)",
+    ),
+    ("more (truncated)", "más (truncado)"),
+    ("more groups", "grupos más"),
+    (
+        "must be 1/0 or true/false, got",
+        "debe ser 1/0 o true/false; se recibió",
+    ),
+    ("normalized length", "longitud normalizada"),
+    ("occurrences", "apariciones"),
+    ("per root:", "por raíz:"),
+    ("potential savings (lines)", "ahorro potencial (líneas)"),
+    (
+        "potential savings, extrapolated to all files (lines)",
+        "ahorro potencial, extrapolado a todos los archivos (líneas)",
+    ),
+    ("removed", "eliminado"),
+    ("savings_lines", "líneas_ahorrables"),
+    ("section", "sección"),
+    ("shared API", "API compartida"),
+    ("shared imports", "imports compartidos"),
+    ("similar pairs", "pares similares"),
+    ("size", "tamaño"),
+    ("skipped:", "omitidos:"),
+    ("snapshot", "instantánea"),
+    ("stale", "obsoletas"),
+    ("subtree", "subárbol"),
+    ("suppressed groups", "grupos suprimidos"),
+    ("suppression", "supresión"),
+    ("symbol groups", "grupos de símbolos"),
+    ("test fixtures", "fixtures de prueba"),
+    ("thresholds:\n", "umbrales:\n"),
+    ("tokens", "tokens"),
+    ("truncated,", "truncado,"),
+    ("unchanged", "sin cambios"),
+    ("vendored code", "código de terceros"),
+    ("was", "antes"),
+    ("whole file", "archivo completo"),
+];
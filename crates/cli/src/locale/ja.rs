@@ -0,0 +1,183 @@
+//! Japanese messages, keyed by the English text passed to [`super::tr`].
+
+pub(super) const MESSAGES: &[(&str, &str)] = &[
+    (
+        "  (token stream cut at --max-tokens-per-file; later blocks are missing)\n",
+        "  （トークン列が --max-tokens-per-file で打ち切られたため、以降のブロックは含まれません）\n",
+    ),
+    ("  diff (A -> B):\n", "  差分 (A -> B):\n"),
+    (" (test)", "（テスト）"),
+    (" in ", " 位置 "),
+    (" modified ", " 更新 "),
+    (
+        "# no vendored, fixture or generated files in the report\n",
+        "# レポートにサードパーティ・テストデータ・生成コードのファイルはありません\n",
+    ),
+    (
+        "# save as a file and pass it to --import-ignores, or pass each pattern to --exclude\n",
+        "# ファイルに保存して --import-ignores に渡すか、各パターンを --exclude に渡してください\n",
+    ),
+    ("(none)", "（なし）"),
+    (
+        "--emit-blocks expects files, not directories",
+        "--emit-blocks にはディレクトリではなくファイルを指定してください",
+    ),
+    (
+        "--link-template must contain {path}",
+        "--link-template には {path} が必要です",
+    ),
+    (
+        "--link-template supports {repo}, {rev}, {path}, {start} and {end}",
+        "--link-template で使えるのは {repo}、{rev}、{path}、{start}、{end} のみです",
+    ),
+    ("<- decisive", "<- 決め手"),
+    ("<- decisive:", "<- 決め手:"),
+    (
+        "== AST subtree duplicates ==\n",
+        "== AST 部分木の重複（近似） ==\n",
+    ),
+    ("== block duplicates ==\n", "== ブロックの重複 ==\n"),
+    ("== clone clusters ==\n", "== クローンクラスタ ==\n"),
+    ("== clone genealogy ==\n", "== クローンの系譜 ==\n"),
+    ("== code span duplicates ==\n", "== 重複コード断片 ==\n"),
+    ("== common idioms ==\n", "== よくあるイディオム ==\n"),
+    ("== cross-team duplicates ==\n", "== チーム間の重複 ==\n"),
+    ("== data file duplicates ==\n", "== 重複データファイル ==\n"),
+    ("== file coverage ==\n", "== ファイルごとの重複範囲 ==\n"),
+    ("== file duplicates ==\n", "== 重複ファイル ==\n"),
+    ("== import duplicates ==\n", "== import 部の重複 ==\n"),
+    ("== intra-team duplicates ==\n", "== チーム内の重複 ==\n"),
+    ("== license conflicts ==\n", "== ライセンスの競合 ==\n"),
+    ("== line span duplicates ==\n", "== 行断片の重複 ==\n"),
+    ("== scan stats ==\n", "== スキャン統計 ==\n"),
+    (
+        "== similar block clusters ==\n",
+        "== 類似ブロッククラスタ ==\n",
+    ),
+    (
+        "== similar blocks (minhash) ==\n",
+        "== 類似ブロック（minhash） ==\n",
+    ),
+    (
+        "== similar blocks (simhash) ==\n",
+        "== 類似ブロック（simhash） ==\n",
+    ),
+    ("== suggested excludes ==\n", "== 除外の提案 ==\n"),
+    ("== symbol duplicates ==\n", "== 公開シンボルの重複 ==\n"),
+    (
+        "== token span duplicates ==\n",
+        "== トークン断片の重複 ==\n",
+    ),
+    ("== unowned duplicates ==\n", "== 担当者のいない重複 ==\n"),
+    ("Comparing", "比較"),
+    ("Duplication diff", "重複コードの差分"),
+    ("Error", "エラー"),
+    (
+        "Re-run with --stats for full details.\n",
+        "詳細は --stats を付けて再実行してください。\n",
+    ),
+    (
+        "SAMPLED REPORT, figures below cover sampled files only",
+        "サンプリングされたレポート: 以下の数値はサンプル対象ファイルのみ",
+    ),
+    ("Warning", "警告"),
+    (
+        "Warning: scan was incomplete (fatal skips):\n",
+        "警告: スキャンが不完全です（致命的なスキップ）:\n",
+    ),
+    ("added", "追加"),
+    ("aligned tokens", "個の一致トークン"),
+    ("alignment:\n", "アラインメント:\n"),
+    ("also_detected_by", "他の検出器"),
+    (
+        "below minTokenLen, skipped",
+        "minTokenLen 未満のためスキップ",
+    ),
+    ("block + AST subtree", "ブロック + AST 部分木"),
+    (
+        "block only, subtree below minTokenLen",
+        "ブロックのみ（部分木は minTokenLen 未満）",
+    ),
+    ("blocks", "ブロック"),
+    ("bytes", "バイト"),
+    ("cache", "キャッシュ"),
+    ("changed", "変更"),
+    ("clusters", "クラスタ数"),
+    ("conflicts", "競合数"),
+    ("degraded, kept", "縮退、保持"),
+    ("duplicate code span groups", "重複コード断片グループ"),
+    ("duplicate groups", "重複グループ"),
+    (
+        "empty or unclosed, skipped",
+        "空または閉じられていないためスキップ",
+    ),
+    ("entries", "エントリ"),
+    (
+        "entries must be permission, paths, walk, budget or bucket, got",
+        "の値は permission、paths、walk、budget、bucket のいずれかである必要があります。指定値:",
+    ),
+    ("expired on", "有効期限"),
+    ("files", "ファイル数"),
+    ("files with duplicates", "重複のあるファイル数"),
+    ("generated code", "生成コード"),
+    ("groups", "グループ"),
+    ("groups and only counted up to", "グループ、さらに最大"),
+    ("hit", "ヒット"),
+    ("hit rate", "ヒット率"),
+    ("hits", "ヒット"),
+    ("import pairs", "import ペア"),
+    (
+        "in total (raise --max-report-items to see more)",
+        "件（--max-report-items を増やすとさらに表示）",
+    ),
+    ("it no longer applies", "期限切れのため適用されません"),
+    ("kept while", "保持条件"),
+    ("last run", "前回の実行"),
+    ("matches no group", "に一致するグループがありません"),
+    (
+        "matches no report section",
+        "に一致するレポートセクションがありません",
+    ),
+    ("miss", "ミス"),
+    ("misses", "ミス"),
+    (
+        "more (raise --max-groups-per-detector to list them)",
+        "グループは集計のみ（一覧表示するには --max-groups-per-detector を増やしてください）",
+    ),
+    ("more (truncated)", "件省略"),
+    ("more groups", "グループ省略"),
+    (
+        "must be 1/0 or true/false, got",
+        "は 1/0 または true/false である必要があります。指定値:",
+    ),
+    ("normalized length", "正規化後の長さ"),
+    ("occurrences", "箇所"),
+    ("per root:", "root ごと:"),
+    ("potential savings (lines)", "削減可能な行数（推定）"),
+    (
+        "potential savings, extrapolated to all files (lines)",
+        "削減可能な行数（全ファイルに外挿）",
+    ),
+    ("removed", "削除"),
+    ("savings_lines", "削減可能行数"),
+    ("section", "セクション"),
+    ("shared API", "共通 API"),
+    ("shared imports", "共通 import"),
+    ("similar pairs", "類似ペア"),
+    ("size", "サイズ"),
+    ("skipped:", "スキップ:"),
+    ("snapshot", "スナップショット"),
+    ("stale", "失効"),
+    ("subtree", "部分木"),
+    ("suppressed groups", "抑制されたグループ"),
+    ("suppression", "抑制"),
+    ("symbol groups", "シンボルグループ"),
+    ("test fixtures", "テストデータ"),
+    ("thresholds:\n", "しきい値:\n"),
+    ("tokens", "トークン"),
+    ("truncated,", "省略あり、合計"),
+    ("unchanged", "変更なし"),
+    ("vendored code", "サードパーティコード"),
+    ("was", "旧"),
+    ("whole file", "ファイル全体"),
+];
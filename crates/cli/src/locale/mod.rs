@@ -0,0 +1,228 @@
+//! Output languages. English and Chinese messages are written inline at each [`tr`] call; the
+//! other locales look the English message up in their catalog and fall back along
+//! [`Localization::fallbacks`] for messages it does not cover.
+//!
+//! The Japanese, German and Spanish catalogs are partial: they cover every message outside
+//! command-line parsing (`args.rs`), whose errors, like the help text, stay in English.
+
+mod de;
+mod es;
+mod ja;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Localization {
+    En,
+    Zh,
+    Ja,
+    De,
+    Es,
+}
+
+impl Localization {
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim().to_ascii_lowercase().replace('_', "-");
+        match raw.as_str() {
+            "en" | "en-us" => Some(Self::En),
+            "zh" | "zh-cn" | "cn" => Some(Self::Zh),
+            "ja" | "ja-jp" => Some(Self::Ja),
+            "de" | "de-de" | "de-at" | "de-ch" => Some(Self::De),
+            "es" | "es-es" | "es-mx" => Some(Self::Es),
+            _ => None,
+        }
+    }
+
+    /// BCP 47 tag, e.g. for the `lang` attribute of HTML output.
+    pub(crate) fn tag(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Zh => "zh-CN",
+            Self::Ja => "ja",
+            Self::De => "de",
+            Self::Es => "es",
+        }
+    }
+
+    /// The locales tried in order for a message, ending with English.
+    pub(crate) fn fallbacks(self) -> &'static [Self] {
+        match self {
+            Self::En => &[Self::En],
+            Self::Zh => &[Self::Zh, Self::En],
+            Self::Ja => &[Self::Ja, Self::En],
+            Self::De => &[Self::De, Self::En],
+            Self::Es => &[Self::Es, Self::En],
+        }
+    }
+
+    /// Messages keyed by their English text, sorted for binary search; English and Chinese
+    /// have none since their text is at the call site.
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::En | Self::Zh => &[],
+            Self::Ja => ja::MESSAGES,
+            Self::De => de::MESSAGES,
+            Self::Es => es::MESSAGES,
+        }
+    }
+
+    fn message(self, en: &'static str, zh: &'static str) -> Option<&'static str> {
+        match self {
+            Self::En => Some(en),
+            Self::Zh => Some(zh),
+            _ => {
+                let catalog = self.catalog();
+                let found = catalog.binary_search_by_key(&en, |&(key, _)| key);
+                found.ok().map(|index| catalog[index].1)
+            }
+        }
+    }
+}
+
+/// `en` translated into `localization`, or into the first of its fallbacks that has it.
+pub(crate) fn tr(localization: Localization, en: &'static str, zh: &'static str) -> &'static str {
+    localization
+        .fallbacks()
+        .iter()
+        .find_map(|locale| locale.message(en, zh))
+        .unwrap_or(en)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::Path;
+
+    use super::*;
+
+    const ALL: [Localization; 5] = [
+        Localization::En,
+        Localization::Zh,
+        Localization::Ja,
+        Localization::De,
+        Localization::Es,
+    ];
+
+    #[test]
+    fn catalogs_are_sorted_and_keep_line_breaks() {
+        for locale in ALL {
+            let catalog = locale.catalog();
+            assert!(
+                catalog.windows(2).all(|pair| pair[0].0 < pair[1].0),
+                "{locale:?} catalog must be sorted without duplicates"
+            );
+            for (en, text) in catalog {
+                assert_eq!(
+                    en.ends_with('\n'),
+                    text.ends_with('\n'),
+                    "{locale:?}: {en:?}"
+                );
+            }
+        }
+    }
+
+    /// The English text of every [`tr`] call under `src/` outside `args.rs` and this module.
+    fn translated_call_keys() -> BTreeSet<String> {
+        fn visit(dir: &Path, keys: &mut BTreeSet<String>) {
+            for entry in fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    if !path.ends_with("locale") {
+                        visit(&path, keys);
+                    }
+                } else if path.extension().is_some_and(|ext| ext == "rs")
+                    && !path.ends_with("args.rs")
+                {
+                    let source = fs::read_to_string(&path).unwrap();
+                    keys.extend(tr_keys(&source));
+                }
+            }
+        }
+        let mut keys = BTreeSet::new();
+        visit(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut keys,
+        );
+        keys
+    }
+
+    /// The first string literal after the first argument of each `tr(` in `source`.
+    fn tr_keys(source: &str) -> Vec<String> {
+        let mut keys = Vec::new();
+        for (start, _) in source.match_indices("tr(") {
+            let before = source[..start].chars().next_back();
+            if before.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                continue;
+            }
+            let rest = &source[start + 3..];
+            let Some(comma) = rest.find(',') else {
+                continue;
+            };
+            let mut chars = rest[comma + 1..].trim_start().chars();
+            if chars.next() != Some('"') {
+                continue;
+            }
+            let mut key = String::new();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => match chars.next() {
+                        Some('n') => key.push('\n'),
+                        Some('t') => key.push('\t'),
+                        Some(c) => key.push(c),
+                        None => break,
+                    },
+                    c => key.push(c),
+                }
+            }
+            keys.push(key);
+        }
+        keys
+    }
+
+    #[test]
+    fn catalogs_cover_every_message_outside_argument_parsing() {
+        let keys = translated_call_keys();
+        assert!(keys.contains("== file duplicates ==\n"), "{keys:?}");
+        for locale in [Localization::Ja, Localization::De, Localization::Es] {
+            let catalog: BTreeSet<String> = locale
+                .catalog()
+                .iter()
+                .map(|(en, _)| en.to_string())
+                .collect();
+            let missing: Vec<_> = keys.difference(&catalog).collect();
+            assert!(missing.is_empty(), "{locale:?} catalog misses {missing:?}");
+            let unused: Vec<_> = catalog.difference(&keys).collect();
+            assert!(
+                unused.is_empty(),
+                "{locale:?} catalog has unused {unused:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn messages_fall_back_to_english() {
+        assert_eq!(
+            tr(
+                Localization::De,
+                "== file duplicates ==\n",
+                "== 重复文件 ==\n"
+            ),
+            "== doppelte Dateien ==\n"
+        );
+        assert_eq!(tr(Localization::Ja, "files", "文件数"), "ファイル数");
+        assert_eq!(tr(Localization::Zh, "files", "文件数"), "文件数");
+        assert_eq!(
+            tr(Localization::Es, "not in any catalog", "无"),
+            "not in any catalog"
+        );
+    }
+
+    #[test]
+    fn tags_parse_back() {
+        for locale in ALL {
+            assert_eq!(Localization::parse(locale.tag()), Some(locale));
+        }
+        assert_eq!(Localization::parse("de_CH"), Some(Localization::De));
+        assert_eq!(Localization::parse("fr"), None);
+    }
+}
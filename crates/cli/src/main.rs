@@ -1,21 +1,56 @@
 #![forbid(unsafe_code)]
 
 mod args;
+mod blocks;
+mod by_file;
+mod cache;
+mod convert;
+mod env_args;
+mod explain;
+mod format;
+mod html_diff;
+mod import_ignores;
 mod json;
+mod jsonl;
+mod links;
+mod locale;
+mod matrix;
+mod ownership;
 mod path;
-mod text;
+mod preset;
+mod progress;
+mod render;
+mod review;
+mod sections;
+mod skip_log;
+mod sources;
+mod stdin_files;
+mod stop_sequences;
+mod strict;
+mod suggest_excludes;
+mod suppressions;
 
 use std::env;
+use std::fs;
 use std::io;
-use std::path::PathBuf;
-
-use crate::args::{Localization, ParsedArgs, detect_localization, parse_args, print_help, tr};
-use crate::json::{JsonScanStats, map_duplicate_groups, map_report, map_span_groups, write_json};
-use crate::path::resolve_path;
-use crate::text::{
-    format_fatal_skip_warning, format_scan_stats, format_text, format_text_code_spans,
-    format_text_report,
+use std::path::{Path, PathBuf};
+
+use crate::args::{CacheCommand, ParsedArgs, detect_localization, parse_args, print_help};
+use dup_code_check_core::{ScanOptions, ScanStats};
+
+use crate::by_file::group_report_by_file;
+use crate::cache::{CacheLookup, ReportCache};
+use crate::env_args::args_with_env_defaults;
+use crate::format::{FormatInput, ScanOutput, resolve_formatter};
+use crate::json::{
+    JsonDuplicationReport, JsonMeta, JsonScanStats, JsonVersion, map_clone_genealogy,
+    map_duplicate_groups, map_report, map_span_groups, write_json,
 };
+use crate::links::LinkTemplate;
+use crate::locale::{Localization, tr};
+use crate::path::{dedupe_roots, resolve_path};
+use crate::render::text::{Style, format_fatal_skip_warning, format_scan_stats};
+use crate::suppressions::Suppression;
 
 fn args_before_dashdash(args: &[String]) -> &[String] {
     match args.iter().position(|a| a == "--") {
@@ -28,7 +63,14 @@ fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
     let pre_dashdash = args_before_dashdash(&args);
     if pre_dashdash.iter().any(|a| a == "-V" || a == "--version") {
-        println!("dup-code-check {}", env!("CARGO_PKG_VERSION"));
+        if pre_dashdash.iter().any(|a| a == "--json") {
+            if let Err(err) = write_json(&JsonVersion::current()) {
+                eprintln!("Error: {err}");
+                std::process::exit(1);
+            }
+        } else {
+            println!("dup-code-check {}", env!("CARGO_PKG_VERSION"));
+        }
         return;
     }
     let localization = match detect_localization(&args) {
@@ -45,7 +87,15 @@ fn main() {
         return;
     }
 
-    let parsed = match parse_args(&args, localization) {
+    let args = match args_with_env_defaults(&args, localization, |name| env::var(name).ok()) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}: {message}\n", tr(localization, "Error", "错误"),);
+            print_help(localization);
+            std::process::exit(2);
+        }
+    };
+    let mut parsed = match parse_args(&args, localization) {
         Ok(parsed) => parsed,
         Err(message) => {
             eprintln!("{}: {message}\n", tr(localization, "Error", "错误"),);
@@ -54,6 +104,69 @@ fn main() {
         }
     };
 
+    if parsed.progress_json {
+        parsed.options.progress = Some(progress::ndjson_progress_to_stderr());
+    }
+    let skip_log = match parsed.log_skips.as_deref().map(skip_log::SkipLog::create) {
+        Some(Ok(log)) => {
+            parsed.options.skip_log = Some(log.callback());
+            Some(log)
+        }
+        Some(Err(err)) => {
+            eprintln!("{}: {err}", tr(localization, "Error", "错误"));
+            std::process::exit(1);
+        }
+        None => None,
+    };
+    for path in &parsed.skip_snippets {
+        match fs::read_to_string(path) {
+            Ok(snippet) => parsed.options.skip_snippets.push(snippet),
+            Err(err) => {
+                eprintln!(
+                    "{}: {}",
+                    tr(localization, "Error", "错误"),
+                    with_path(err, path)
+                );
+                std::process::exit(1);
+            }
+        }
+    }
+    for path in &parsed.stop_sequences {
+        match stop_sequences::read_stop_sequences(path) {
+            Ok(sequences) => parsed.options.stop_sequences.extend(sequences),
+            Err(err) => {
+                eprintln!("{}: {err}", tr(localization, "Error", "错误"));
+                std::process::exit(1);
+            }
+        }
+    }
+    for path in &parsed.import_ignores {
+        match import_ignores::import_ignore_patterns(path) {
+            Ok(patterns) => parsed.options.ignore_patterns.extend(patterns),
+            Err(err) => {
+                eprintln!("{}: {err}", tr(localization, "Error", "错误"));
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let suppressions = match parsed
+        .suppressions
+        .as_deref()
+        .map(suppressions::read_suppressions)
+        .transpose()
+    {
+        Ok(suppressions) => suppressions,
+        Err(err) => {
+            eprintln!("{}: {err}", tr(localization, "Error", "错误"));
+            std::process::exit(1);
+        }
+    };
+    if let Some(suppressions) = &suppressions {
+        parsed.options.group_suppressions =
+            suppressions::group_suppressions(suppressions, &suppressions::today_utc());
+    }
+
     let roots: Vec<PathBuf> = match parsed
         .roots
         .iter()
@@ -67,7 +180,11 @@ fn main() {
         }
     };
 
-    match run(&parsed, &roots) {
+    let result = run(&parsed, &roots, suppressions.as_deref()).and_then(|exit_code| {
+        skip_log.map_or(Ok(()), skip_log::SkipLog::finish)?;
+        Ok(exit_code)
+    });
+    match result {
         Ok(exit_code) => std::process::exit(exit_code),
         Err(err) => {
             eprintln!("{}: {err}", tr(localization, "Error", "错误"));
@@ -76,74 +193,417 @@ fn main() {
     }
 }
 
-fn run(parsed: &ParsedArgs, roots: &[PathBuf]) -> io::Result<i32> {
-    if parsed.report {
-        let outcome =
-            dup_code_check_core::generate_duplication_report_with_stats(roots, &parsed.options)?;
-        let report = map_report(outcome.result);
-        let scan_stats = outcome.stats;
-
-        if parsed.json {
-            if parsed.stats {
-                write_json(&serde_json::json!({
-                    "report": report,
-                    "scanStats": Some(JsonScanStats::from(&scan_stats)),
-                }))?;
-            } else {
-                write_json(&report)?;
-            }
-        } else {
-            print!("{}", format_text_report(parsed.localization, &report));
-        }
-        return finalize_scan(parsed, &scan_stats);
+/// `suppressions` is the `--suppressions` file, already passed to core as
+/// `parsed.options.group_suppressions`.
+fn run(
+    parsed: &ParsedArgs,
+    roots: &[PathBuf],
+    suppressions: Option<&[Suppression]>,
+) -> io::Result<i32> {
+    if let Some((input, output)) = &parsed.convert {
+        convert::run_convert(input, output)?;
+        return Ok(0);
+    }
+    if let Some((group_id, report)) = &parsed.explain {
+        explain::run_explain(
+            parsed.localization,
+            group_id,
+            report,
+            roots,
+            Style::new(parsed.color.enabled()),
+        )?;
+        return Ok(0);
+    }
+    if parsed.emit_blocks {
+        blocks::run_emit_blocks(
+            parsed.localization,
+            &parsed.roots,
+            roots,
+            &parsed.options,
+            parsed.json,
+            Style::new(parsed.color.enabled()),
+        )?;
+        return Ok(0);
+    }
+    if let Some((old, new, output)) = &parsed.html_diff {
+        html_diff::run_html_diff(parsed.localization, old, new, output.as_deref())?;
+        return Ok(0);
+    }
+    // Core would drop these roots itself, shifting the `repoId`s of later roots; dropping them
+    // here keeps `meta.roots`, links, sources and CODEOWNERS indexed like the report.
+    let (roots, duplicate_roots) = if parsed.merge_shards {
+        (roots.to_vec(), 0)
+    } else {
+        dedupe_roots(roots)
+    };
+    let roots = roots.as_slice();
+    if let Some(output) = &parsed.snapshot_output {
+        return run_snapshot(parsed, roots, output, duplicate_roots);
+    }
+    if let Some((old, new)) = &parsed.genealogy {
+        return run_genealogy(parsed, old, new);
+    }
+    if let Some((before, after)) = &parsed.self_diff {
+        return run_self_diff(parsed, &roots[0], before, after);
+    }
+    if let (Some(command), Some(dir)) = (parsed.cache_command, &parsed.cache_dir) {
+        return run_cache(parsed, command, dir);
     }
 
-    if parsed.code_spans {
+    let formatter = resolve_formatter(&parsed.format, parsed.baseline.as_deref())?;
+    let (mut output, mut scan_stats) = if parsed.report {
+        let (report, scan_stats) = if let Some(dir) = &parsed.cache_dir {
+            cached_report(dir, roots, &parsed.options, parsed.max_cache_size)
+                .map_err(|err| with_path(err, dir))?
+        } else {
+            generate_report(parsed, roots)?
+        };
+        (ScanOutput::Report(Box::new(report)), scan_stats)
+    } else if parsed.code_spans {
         let outcome =
             dup_code_check_core::find_duplicate_code_spans_with_stats(roots, &parsed.options)?;
-        let groups = map_span_groups(outcome.result);
-        let scan_stats = outcome.stats;
-
-        if parsed.json {
-            if parsed.stats {
-                write_json(&serde_json::json!({
-                    "groups": groups,
-                    "scanStats": Some(JsonScanStats::from(&scan_stats)),
-                }))?;
-            } else {
-                write_json(&groups)?;
-            }
+        (
+            ScanOutput::CodeSpans(map_span_groups(outcome.result)),
+            outcome.stats,
+        )
+    } else {
+        let outcome = dup_code_check_core::find_duplicate_files_with_stats(roots, &parsed.options)?;
+        (
+            ScanOutput::Files(map_duplicate_groups(outcome.result)),
+            outcome.stats,
+        )
+    };
+    scan_stats.skipped_duplicate_roots = scan_stats
+        .skipped_duplicate_roots
+        .saturating_add(duplicate_roots);
+    let mut unknown_suppressions = false;
+    if let Some(suppressions) = suppressions {
+        let outcome = suppressions::apply_suppressions(
+            &mut output,
+            suppressions,
+            &parsed.options,
+            &scan_stats,
+        );
+        eprint!(
+            "{}",
+            suppressions::format_suppression_warnings(parsed.localization, &outcome)
+        );
+        if parsed.stats {
+            eprintln!(
+                "{}: {}",
+                tr(parsed.localization, "suppressed groups", "已抑制的重复组"),
+                outcome.suppressed_groups
+            );
+        }
+        unknown_suppressions = !outcome.unknown.is_empty();
+    }
+    if parsed.occurrence_sources {
+        sources::attach_sources(&mut output, &sources::resolve_sources(roots));
+    }
+    if let ScanOutput::Report(report) = &mut output {
+        parsed.section_filter.apply(report);
+        sections::omit_disabled_sections(report, &parsed.options);
+    }
+    if parsed.suggest_excludes
+        && let ScanOutput::Report(report) = &output
+    {
+        let (suggestions, total_groups) = suggest_excludes::suggest_excludes(report, roots);
+        eprint!(
+            "{}",
+            suggest_excludes::format_suggestions(parsed.localization, &suggestions, total_groups)
+        );
+    }
+    if parsed.group_by_file
+        && let ScanOutput::Report(report) = &output
+    {
+        output = ScanOutput::ReportByFile(Box::new(group_report_by_file(report)));
+    }
+    if parsed.aggregate_ownership
+        && let ScanOutput::Report(report) = &output
+    {
+        let codeowners = ownership::load_codeowners(roots, parsed.codeowners.as_deref())?;
+        output = ScanOutput::ReportByOwnership(Box::new(ownership::aggregate_by_ownership(
+            report,
+            &codeowners,
+        )));
+    }
+
+    let links = parsed
+        .link_template
+        .as_deref()
+        .map(|template| LinkTemplate::new(template, roots));
+    formatter.write(&FormatInput {
+        meta: &JsonMeta::new(output.mode(), roots, &parsed.options),
+        output: &output,
+        stats: &JsonScanStats::from(&scan_stats),
+        include_stats: parsed.stats,
+        localization: parsed.localization,
+        color: parsed.color.enabled(),
+        hyperlinks: parsed.hyperlinks,
+        links: links.as_ref(),
+    })?;
+    let exit_code = finalize_scan(parsed, &scan_stats)?;
+    // `--strict` also fails on suppressions for groups that no longer exist.
+    if parsed.strict && unknown_suppressions {
+        return Ok(1);
+    }
+    Ok(exit_code)
+}
+
+/// Run the report `parsed` asks for (over roots, merged shards, stdin files or a snapshot).
+fn generate_report(
+    parsed: &ParsedArgs,
+    roots: &[PathBuf],
+) -> io::Result<(JsonDuplicationReport, ScanStats)> {
+    let outcome = if parsed.merge_shards {
+        // `roots` are the partial snapshot files here.
+        let partials = roots
+            .iter()
+            .map(|path| read_snapshot(path))
+            .collect::<io::Result<Vec<_>>>()?;
+        dup_code_check_core::generate_duplication_report_from_snapshot(
+            dup_code_check_core::CorpusSnapshot::merge(partials)?,
+            &parsed.options,
+        )?
+    } else if parsed.stdin_json {
+        dup_code_check_core::generate_duplication_report_with_virtual_files(
+            roots,
+            stdin_files::STDIN_ROOT_LABEL,
+            stdin_files::read_virtual_files(io::stdin().lock())?,
+            &parsed.options,
+        )?
+    } else if let Some(depth) = parsed.resurrect {
+        dup_code_check_core::generate_resurrection_report(&roots[0], depth, &parsed.options)?
+    } else if let Some(path) = &parsed.against_snapshot {
+        dup_code_check_core::generate_duplication_report_against_snapshot(
+            roots,
+            read_snapshot(path)?,
+            &parsed.options,
+        )?
+    } else {
+        dup_code_check_core::generate_duplication_report_with_stats(roots, &parsed.options)?
+    };
+    if let Some(path) = &parsed.file_similarity_matrix {
+        let pairs = outcome
+            .result
+            .file_similarity
+            .as_deref()
+            .unwrap_or_default();
+        matrix::write_file_similarity_csv(path, pairs)?;
+    }
+    Ok((map_report(outcome.result), outcome.stats))
+}
+
+/// `--cache-dir`: the cached report while no scanned file changed; otherwise a fresh report,
+/// which replaces the cache entry (and, with `--max-cache-size`, prunes the directory).
+fn cached_report(
+    dir: &Path,
+    roots: &[PathBuf],
+    options: &ScanOptions,
+    max_cache_size: Option<u64>,
+) -> io::Result<(JsonDuplicationReport, ScanStats)> {
+    let cache = ReportCache::open(dir, roots, options)?;
+    if let Some(hit) = cache.load() {
+        cache.record(CacheLookup::Hit)?;
+        return Ok(hit);
+    }
+    let outcome = dup_code_check_core::generate_duplication_report_with_stats(roots, options)?;
+    let report = map_report(outcome.result);
+    cache.store(&report, &JsonScanStats::from(&outcome.stats))?;
+    cache.record(CacheLookup::Miss)?;
+    if max_cache_size.is_some() {
+        cache::gc_cache(dir, max_cache_size)?;
+    }
+    Ok((report, outcome.stats))
+}
+
+/// `cache status|clear|gc`: inspect or prune a `--cache-dir`.
+fn run_cache(parsed: &ParsedArgs, command: CacheCommand, dir: &Path) -> io::Result<i32> {
+    let removed = match command {
+        CacheCommand::Status => None,
+        CacheCommand::Clear => Some(cache::clear_cache(dir)),
+        CacheCommand::Gc => Some(cache::gc_cache(dir, parsed.max_cache_size)),
+    }
+    .transpose()
+    .map_err(|err| with_path(err, dir))?;
+    let status = cache::cache_status(dir).map_err(|err| with_path(err, dir))?;
+
+    if parsed.json {
+        let meta = JsonMeta::new("cache", &[dir.to_path_buf()], &parsed.options);
+        if let Some(removed) = removed {
+            write_json(&serde_json::json!({
+                "meta": meta,
+                "removed": removed,
+                "cache": status,
+            }))?;
         } else {
-            print!("{}", format_text_code_spans(parsed.localization, &groups));
+            write_json(&serde_json::json!({ "meta": meta, "cache": status }))?;
         }
-        return finalize_scan(parsed, &scan_stats);
+        return Ok(0);
+    }
+
+    let localization = parsed.localization;
+    let bytes = tr(localization, "bytes", "字节");
+    if let Some(removed) = removed {
+        println!(
+            "{}: {} {} ({} {bytes})",
+            tr(localization, "removed", "已移除"),
+            removed.entries,
+            tr(localization, "entries", "个条目"),
+            removed.bytes,
+        );
     }
+    println!("{}: {}", tr(localization, "cache", "缓存"), dir.display());
+    println!(
+        "{}: {} ({} {})",
+        tr(localization, "entries", "条目"),
+        status.entries,
+        status.stale_entries,
+        tr(localization, "stale", "已失效"),
+    );
+    println!(
+        "{}: {} {bytes}",
+        tr(localization, "size", "大小"),
+        status.bytes
+    );
+    println!(
+        "{}: {} {}: {} {}: {}",
+        tr(localization, "hits", "命中"),
+        status.usage.hits,
+        tr(localization, "misses", "未命中"),
+        status.usage.misses,
+        tr(localization, "hit rate", "命中率"),
+        status
+            .hit_rate
+            .map_or_else(|| "-".to_string(), |rate| format!("{:.1}%", rate * 100.0)),
+    );
+    println!(
+        "{}: {}",
+        tr(localization, "last run", "上次运行"),
+        match status.usage.last_run {
+            Some(CacheLookup::Hit) => tr(localization, "hit", "命中"),
+            Some(CacheLookup::Miss) => tr(localization, "miss", "未命中"),
+            None => "-",
+        }
+    );
+    Ok(0)
+}
 
-    let outcome = dup_code_check_core::find_duplicate_files_with_stats(roots, &parsed.options)?;
-    let groups = map_duplicate_groups(outcome.result);
-    let scan_stats = outcome.stats;
+/// `genealogy <old.dcs> <new.dcs>`: trace every clone group from the old snapshot to the new.
+fn run_genealogy(parsed: &ParsedArgs, old: &Path, new: &Path) -> io::Result<i32> {
+    let formatter = resolve_formatter(&parsed.format, None)?;
+    let genealogy = dup_code_check_core::clone_genealogy(
+        read_snapshot(old)?,
+        read_snapshot(new)?,
+        &parsed.options,
+    )?;
+    let output = ScanOutput::Genealogy(Box::new(map_clone_genealogy(&genealogy)));
+    formatter.write(&FormatInput {
+        meta: &JsonMeta::new(
+            output.mode(),
+            &[old.to_path_buf(), new.to_path_buf()],
+            &parsed.options,
+        ),
+        output: &output,
+        // Snapshots are not re-scanned, so every counter stays zero.
+        stats: &JsonScanStats::from(&ScanStats::default()),
+        include_stats: parsed.stats,
+        localization: parsed.localization,
+        color: parsed.color.enabled(),
+        hyperlinks: false,
+        links: None,
+    })?;
+    Ok(0)
+}
+
+/// `self-diff --before <ref> --after <ref> [repo]`: the clone groups a repo gained between two
+/// of its git refs. Only added and grown lineages are listed; the summary still counts all, and
+/// the stats describe the `--after` side.
+fn run_self_diff(parsed: &ParsedArgs, repo: &Path, before: &str, after: &str) -> io::Result<i32> {
+    let formatter = resolve_formatter(&parsed.format, None)?;
+    let old = dup_code_check_core::build_corpus_snapshot_at_git_ref(repo, before, &parsed.options)?;
+    let new = dup_code_check_core::build_corpus_snapshot_at_git_ref(repo, after, &parsed.options)?;
+    let scan_stats = new.stats;
+    let genealogy = dup_code_check_core::clone_genealogy(old.result, new.result, &parsed.options)?;
+    let mut genealogy = map_clone_genealogy(&genealogy);
+    genealogy
+        .lineages
+        .retain(|lineage| matches!(lineage.change, "added" | "grown"));
+    let output = ScanOutput::Genealogy(Box::new(genealogy));
+    formatter.write(&FormatInput {
+        meta: &JsonMeta::new(output.mode(), &[repo.to_path_buf()], &parsed.options),
+        output: &output,
+        stats: &JsonScanStats::from(&scan_stats),
+        include_stats: parsed.stats,
+        localization: parsed.localization,
+        color: parsed.color.enabled(),
+        hyperlinks: false,
+        links: None,
+    })?;
+    Ok(0)
+}
+
+fn run_snapshot(
+    parsed: &ParsedArgs,
+    roots: &[PathBuf],
+    output: &Path,
+    duplicate_roots: u64,
+) -> io::Result<i32> {
+    let outcome = dup_code_check_core::build_corpus_snapshot(roots, &parsed.options)?;
+    let snapshot = outcome.result;
+    let mut scan_stats = outcome.stats;
+    scan_stats.skipped_duplicate_roots = scan_stats
+        .skipped_duplicate_roots
+        .saturating_add(duplicate_roots);
+
+    let file = fs::File::create(output).map_err(|err| with_path(err, output))?;
+    snapshot
+        .write_to(file)
+        .map_err(|err| with_path(err, output))?;
 
     if parsed.json {
+        let summary = serde_json::json!({
+            "output": output.to_string_lossy(),
+            "roots": snapshot.repo_labels().collect::<Vec<_>>(),
+            "files": snapshot.file_count(),
+        });
+        let meta = JsonMeta::new("snapshot", roots, &parsed.options);
         if parsed.stats {
             write_json(&serde_json::json!({
-                "groups": groups,
+                "meta": meta,
+                "snapshot": summary,
                 "scanStats": Some(JsonScanStats::from(&scan_stats)),
             }))?;
         } else {
-            write_json(&groups)?;
+            write_json(&serde_json::json!({ "meta": meta, "snapshot": summary }))?;
         }
     } else {
-        print!("{}", format_text(parsed.localization, &groups));
+        println!(
+            "{}: {} {} -> {}",
+            tr(parsed.localization, "snapshot", "快照"),
+            snapshot.file_count(),
+            tr(parsed.localization, "files", "个文件"),
+            output.display()
+        );
     }
 
     finalize_scan(parsed, &scan_stats)
 }
 
+fn read_snapshot(path: &Path) -> io::Result<dup_code_check_core::CorpusSnapshot> {
+    let file = fs::File::open(path).map_err(|err| with_path(err, path))?;
+    dup_code_check_core::CorpusSnapshot::read_from(file).map_err(|err| with_path(err, path))
+}
+
+fn with_path(err: io::Error, path: &Path) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
+
 fn finalize_scan(
     parsed: &ParsedArgs,
     scan_stats: &dup_code_check_core::ScanStats,
 ) -> io::Result<i32> {
-    if parsed.stats && !parsed.json {
+    if parsed.stats && parsed.format == "text" {
         eprint!("{}", format_scan_stats(parsed.localization, scan_stats));
     }
 
@@ -159,7 +619,7 @@ fn finalize_scan(
         );
     }
 
-    if parsed.strict && has_fatal_skips {
+    if parsed.strict && parsed.strict_policy.is_violated_by(scan_stats) {
         if !parsed.stats {
             eprint!("{}", format_scan_stats(parsed.localization, scan_stats));
         }
@@ -0,0 +1,60 @@
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use dup_code_check_core::FileSimilarity;
+
+/// Write `--file-similarity-matrix`: the upper triangle of a sparse, symmetric file similarity
+/// matrix, one CSV row per file pair. Pairs that are not listed are below detection.
+pub(crate) fn write_file_similarity_csv(path: &Path, pairs: &[FileSimilarity]) -> io::Result<()> {
+    let with_path =
+        |err: io::Error| io::Error::new(err.kind(), format!("{}: {err}", path.display()));
+    let file = fs::File::create(path).map_err(with_path)?;
+    let mut out = io::BufWriter::new(file);
+    write_rows(&mut out, pairs)
+        .and_then(|()| out.flush())
+        .map_err(with_path)
+}
+
+fn write_rows(out: &mut impl Write, pairs: &[FileSimilarity]) -> io::Result<()> {
+    writeln!(out, "repo_a,path_a,repo_b,path_b,similarity")?;
+    for pair in pairs {
+        let (a, b) = (pair.a(), pair.b());
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            csv_field(a.repo_label()),
+            csv_field(a.path()),
+            csv_field(b.repo_label()),
+            csv_field(b.path()),
+            pair.score()
+        )?;
+    }
+    Ok(())
+}
+
+/// Quote a field (RFC 4180) when it contains a separator, quote or line break.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("src/a.rs"), "src/a.rs");
+        assert_eq!(csv_field("odd,name.rs"), "\"odd,name.rs\"");
+        assert_eq!(csv_field("say \"hi\".rs"), "\"say \"\"hi\"\".rs\"");
+
+        let mut out = Vec::new();
+        write_rows(&mut out, &[]).unwrap();
+        assert_eq!(out, b"repo_a,path_a,repo_b,path_b,similarity\n");
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use dup_code_check_core::CodeOwners;
+use serde::Serialize;
+
+use crate::by_file::{JsonFileLocation, for_each_group};
+use crate::json::JsonDuplicationReport;
+
+/// Where GitHub and GitLab look for a CODEOWNERS file, in their lookup order.
+const CODEOWNERS_PATHS: &[&str] = &[
+    ".github/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+    ".gitlab/CODEOWNERS",
+];
+
+/// `--aggregate ownership`: report groups split by the CODEOWNERS owners of their places.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonOwnershipView {
+    /// Groups whose places belong to two or more owner sets, most owner sets first.
+    pub(crate) cross_team: Vec<JsonOwnedGroup>,
+    /// Groups owned by a single owner set, per owner set with the most groups first.
+    pub(crate) intra_team: Vec<JsonTeamGroups>,
+    /// Groups none of whose places has an owner.
+    pub(crate) unowned: Vec<JsonOwnedGroup>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonTeamGroups {
+    pub(crate) owners: Vec<String>,
+    pub(crate) groups: Vec<JsonOwnedGroup>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonOwnedGroup {
+    /// JSON key of the report section the group comes from.
+    pub(crate) section: &'static str,
+    /// Group hash; `None` for similar pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) hash: Option<String>,
+    /// Similarity score; only for similar pairs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) score: Option<f64>,
+    /// The distinct owner sets of the group's owned places.
+    pub(crate) teams: Vec<Vec<String>>,
+    pub(crate) places: Vec<JsonOwnedPlace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonOwnedPlace {
+    #[serde(flatten)]
+    pub(crate) location: JsonFileLocation,
+    /// Empty when no CODEOWNERS rule covers the place.
+    pub(crate) owners: Vec<String>,
+}
+
+/// The CODEOWNERS of each root (by repo id): `file` for every root when given, otherwise the
+/// first of [`CODEOWNERS_PATHS`] found in the root. `None` for roots without one.
+pub(crate) fn load_codeowners(
+    roots: &[PathBuf],
+    file: Option<&Path>,
+) -> io::Result<Vec<Option<CodeOwners>>> {
+    if let Some(file) = file {
+        let text = fs::read_to_string(file)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", file.display())))?;
+        return Ok(vec![Some(CodeOwners::parse(&text)); roots.len()]);
+    }
+    Ok(roots
+        .iter()
+        .map(|root| {
+            CODEOWNERS_PATHS
+                .iter()
+                .find_map(|path| fs::read_to_string(root.join(path)).ok())
+                .map(|text| CodeOwners::parse(&text))
+        })
+        .collect())
+}
+
+/// Split the groups of `report` into cross-team, intra-team and unowned ones. Places in repos
+/// without CODEOWNERS (including snapshot and stdin files) are unowned.
+pub(crate) fn aggregate_by_ownership(
+    report: &JsonDuplicationReport,
+    codeowners: &[Option<CodeOwners>],
+) -> JsonOwnershipView {
+    let mut cross_team = Vec::new();
+    let mut intra_team: BTreeMap<Vec<String>, Vec<JsonOwnedGroup>> = BTreeMap::new();
+    let mut unowned = Vec::new();
+    for_each_group(report, |section, hash, score, places| {
+        let places: Vec<JsonOwnedPlace> = places
+            .iter()
+            .map(|place| JsonOwnedPlace {
+                owners: codeowners
+                    .get(place.repo_id)
+                    .and_then(Option::as_ref)
+                    .map_or_else(Vec::new, |owners| owners.owners_of(&place.path).to_vec()),
+                location: place.clone(),
+            })
+            .collect();
+        let teams: BTreeSet<&Vec<String>> = places
+            .iter()
+            .map(|place| &place.owners)
+            .filter(|owners| !owners.is_empty())
+            .collect();
+        let teams: Vec<Vec<String>> = teams.into_iter().cloned().collect();
+        let group = JsonOwnedGroup {
+            section,
+            hash: hash.map(str::to_string),
+            score,
+            teams,
+            places,
+        };
+        match group.teams.len() {
+            0 => unowned.push(group),
+            1 => intra_team
+                .entry(group.teams[0].clone())
+                .or_default()
+                .push(group),
+            _ => cross_team.push(group),
+        }
+    });
+    cross_team.sort_by_key(|group| std::cmp::Reverse(group.teams.len()));
+    let mut intra_team: Vec<JsonTeamGroups> = intra_team
+        .into_iter()
+        .map(|(owners, groups)| JsonTeamGroups { owners, groups })
+        .collect();
+    intra_team.sort_by_key(|team| std::cmp::Reverse(team.groups.len()));
+    JsonOwnershipView {
+        cross_team,
+        intra_team,
+        unowned,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::map_report;
+
+    #[test]
+    fn groups_are_split_by_owner_sets() {
+        let root = std::env::temp_dir().join(format!("dcc-ownership-{}", std::process::id()));
+        for dir in [".github", "billing", "web", "tools"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(
+            root.join(".github/CODEOWNERS"),
+            "/billing/ @org/billing\n/web/ @org/web\n",
+        )
+        .unwrap();
+        for (path, text) in [
+            ("billing/a.txt", "one"),
+            ("billing/b.txt", "one"),
+            ("billing/c.txt", "two"),
+            ("web/c.txt", "two"),
+            ("tools/d.txt", "three"),
+            ("tools/e.txt", "three"),
+        ] {
+            fs::write(root.join(path), text).unwrap();
+        }
+        let roots = [root.clone()];
+        let report = map_report(
+            dup_code_check_core::generate_duplication_report(
+                &roots,
+                &dup_code_check_core::ScanOptions::default(),
+            )
+            .unwrap(),
+        );
+        let codeowners = load_codeowners(&roots, None).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+        let view = aggregate_by_ownership(&report, &codeowners);
+
+        let paths = |groups: &[JsonOwnedGroup]| -> Vec<String> {
+            groups
+                .iter()
+                .flat_map(|group| &group.places)
+                .map(|place| place.location.path.to_string())
+                .collect()
+        };
+        assert_eq!(paths(&view.cross_team), ["billing/c.txt", "web/c.txt"]);
+        assert_eq!(
+            view.cross_team[0].teams,
+            [
+                vec!["@org/billing".to_string()],
+                vec!["@org/web".to_string()]
+            ]
+        );
+        assert_eq!(view.intra_team.len(), 1);
+        assert_eq!(view.intra_team[0].owners, ["@org/billing"]);
+        assert_eq!(
+            paths(&view.intra_team[0].groups),
+            ["billing/a.txt", "billing/b.txt"]
+        );
+        assert_eq!(paths(&view.unowned), ["tools/d.txt", "tools/e.txt"]);
+
+        let json = serde_json::to_value(&view.cross_team[0]).unwrap();
+        assert_eq!(json["section"], "fileDuplicates");
+        assert_eq!(json["places"][1]["path"], "web/c.txt");
+        assert_eq!(json["places"][1]["owners"][0], "@org/web");
+    }
+}
@@ -11,3 +11,43 @@ pub(crate) fn resolve_path(p: &Path) -> io::Result<PathBuf> {
     };
     fs::canonicalize(base.join(p))
 }
+
+/// `roots` (resolved by [`resolve_path`], so aliases such as `a` and `./a` are equal) without
+/// the ones repeating an earlier root, and how many were dropped, as core counts them in
+/// `skipped_duplicate_roots`.
+pub(crate) fn dedupe_roots(roots: &[PathBuf]) -> (Vec<PathBuf>, u64) {
+    let mut unique: Vec<PathBuf> = Vec::with_capacity(roots.len());
+    for root in roots {
+        if !unique.contains(root) {
+            unique.push(root.clone());
+        }
+    }
+    let dropped = (roots.len() - unique.len()) as u64;
+    (unique, dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliased_roots_are_dropped_after_the_first() {
+        let dir = env::temp_dir().join(format!("dcc-dedupe-roots-{}", std::process::id()));
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::create_dir_all(dir.join("b")).unwrap();
+        let roots: Vec<PathBuf> = [
+            dir.join("a"),
+            dir.join("./a"),
+            dir.join("b"),
+            dir.join("a/"),
+        ]
+        .iter()
+        .map(|root| resolve_path(root).unwrap())
+        .collect();
+        let (unique, dropped) = dedupe_roots(&roots);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(unique, [roots[0].clone(), roots[2].clone()]);
+        assert_eq!(dropped, 2);
+    }
+}
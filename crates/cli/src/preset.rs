@@ -0,0 +1,102 @@
+use dup_code_check_core::{ReportSection, ScanOptions};
+
+/// `--preset`: a starting point of detectors, thresholds and budgets. Applied before the other
+/// flags, so any of them still overrides a preset value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Preset {
+    /// File and block duplicates within a 64 MiB budget: a first look at a large tree.
+    Quick,
+    /// Every exact detector within a 256 MiB budget; the near-miss similarity detectors are
+    /// left out.
+    Balanced,
+    /// Every detector with lower thresholds, longer sections and no budget.
+    Thorough,
+}
+
+const MIB: u64 = 1024 * 1024;
+
+impl Preset {
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "quick" => Some(Self::Quick),
+            "balanced" => Some(Self::Balanced),
+            "thorough" => Some(Self::Thorough),
+            _ => None,
+        }
+    }
+
+    /// The sections the preset runs; the others are limited to 0 items, which skips them.
+    fn sections(self) -> &'static [ReportSection] {
+        match self {
+            Self::Quick => &[
+                ReportSection::FileDuplicates,
+                ReportSection::BlockDuplicates,
+                ReportSection::DataDuplicates,
+            ],
+            Self::Balanced => &[
+                ReportSection::FileDuplicates,
+                ReportSection::CodeSpanDuplicates,
+                ReportSection::LineSpanDuplicates,
+                ReportSection::TokenSpanDuplicates,
+                ReportSection::BlockDuplicates,
+                ReportSection::AstSubtreeDuplicates,
+                ReportSection::SymbolDuplicates,
+                ReportSection::ImportDuplicates,
+                ReportSection::DataDuplicates,
+            ],
+            Self::Thorough => &ReportSection::ALL,
+        }
+    }
+
+    pub(crate) fn apply(self, options: &mut ScanOptions) {
+        options.max_items_per_detector = ReportSection::ALL
+            .into_iter()
+            .filter(|section| !self.sections().contains(section))
+            .map(|section| (section, 0))
+            .collect();
+        match self {
+            Self::Quick => options.max_total_bytes = Some(64 * MIB),
+            Self::Balanced => options.max_total_bytes = Some(256 * MIB),
+            Self::Thorough => {
+                options.max_total_bytes = None;
+                options.max_files = None;
+                options.min_match_len = 30;
+                options.min_token_len = 30;
+                options.max_report_items = 1000;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_select_detectors_and_budgets() {
+        let mut options = ScanOptions::default();
+        Preset::Quick.apply(&mut options);
+        assert_eq!(options.max_total_bytes, Some(64 * MIB));
+        assert_eq!(
+            options
+                .max_items_per_detector
+                .get(&ReportSection::CodeSpanDuplicates),
+            Some(&0)
+        );
+        assert!(
+            !options
+                .max_items_per_detector
+                .contains_key(&ReportSection::BlockDuplicates)
+        );
+
+        let mut options = ScanOptions::default();
+        Preset::Balanced.apply(&mut options);
+        assert_eq!(options.max_items_per_detector.len(), 2);
+
+        let mut options = ScanOptions::default();
+        Preset::Thorough.apply(&mut options);
+        assert!(options.max_items_per_detector.is_empty());
+        assert_eq!(options.max_total_bytes, None);
+        assert!(options.validate_for_report().is_ok());
+    }
+}
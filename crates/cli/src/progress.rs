@@ -0,0 +1,56 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use dup_code_check_core::{ProgressCallback, ScanPhase, ScanProgress};
+use serde::Serialize;
+
+/// Minimum interval between `scan` events; phase changes are always written.
+const SCAN_EVENT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonProgressEvent {
+    phase: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    section: Option<&'static str>,
+    candidate_files: u64,
+    scanned_files: u64,
+    scanned_bytes: u64,
+    elapsed_ms: u64,
+}
+
+/// Progress hook that writes NDJSON events to stderr (`--progress-json`).
+pub(crate) fn ndjson_progress_to_stderr() -> ProgressCallback {
+    let start = Instant::now();
+    let last_scan_event: Mutex<Option<Instant>> = Mutex::new(None);
+
+    ProgressCallback::new(move |progress: &ScanProgress| {
+        let now = Instant::now();
+        if progress.phase == ScanPhase::Scanning {
+            let mut last = last_scan_event
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if last.is_some_and(|last| now.duration_since(last) < SCAN_EVENT_INTERVAL) {
+                return;
+            }
+            *last = Some(now);
+        }
+
+        let event = JsonProgressEvent {
+            phase: progress.phase.as_str(),
+            section: match progress.phase {
+                ScanPhase::Detecting(section) => Some(section.as_str()),
+                _ => None,
+            },
+            candidate_files: progress.candidate_files,
+            scanned_files: progress.scanned_files,
+            scanned_bytes: progress.scanned_bytes,
+            elapsed_ms: u64::try_from(now.duration_since(start).as_millis()).unwrap_or(u64::MAX),
+        };
+        if let Ok(line) = serde_json::to_string(&event) {
+            // Progress is best-effort; a closed stderr must not abort the scan.
+            let _ = writeln!(io::stderr().lock(), "{line}");
+        }
+    })
+}
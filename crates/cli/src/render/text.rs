@@ -0,0 +1,1496 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+use dup_code_check_core::ScanStats;
+
+use crate::by_file::JsonFileView;
+use crate::json::{
+    JsonCloneCluster, JsonCloneGenealogy, JsonDuplicateGroup, JsonDuplicateSpanGroup,
+    JsonDuplicationReport, JsonFileCoverage, JsonImportDuplicatePair, JsonLicenseConflict,
+    JsonSimilarBlockCluster, JsonSimilarityPair, JsonSymbolDuplicateGroup,
+};
+use crate::links::LinkTemplate;
+use crate::locale::{Localization, tr};
+use crate::ownership::{JsonOwnedGroup, JsonOwnershipView};
+
+/// `--color`: whether text output uses ANSI colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub(crate) enum ColorChoice {
+    /// Color when stdout is a terminal, `NO_COLOR` is unset and `TERM` is not `dumb`.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub(crate) fn parse(raw: &str) -> Option<Self> {
+        match raw {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn enabled(self) -> bool {
+        match self {
+            Self::Always => true,
+            Self::Never => false,
+            Self::Auto => {
+                env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && env::var_os("TERM").is_none_or(|v| v != "dumb")
+                    && io::stdout().is_terminal()
+            }
+        }
+    }
+}
+
+/// ANSI styling of stdout text output; with colors off every method returns the text as is.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Style<'a> {
+    color: bool,
+    /// `--hyperlinks`: the scanned roots (by repo id) that relative paths are resolved against.
+    link_roots: Option<&'a [String]>,
+    /// `--link-template`: code-hosting URLs printed after each location.
+    links: Option<&'a LinkTemplate>,
+}
+
+impl<'a> Style<'a> {
+    pub(crate) fn new(color: bool) -> Self {
+        Self {
+            color,
+            link_roots: None,
+            links: None,
+        }
+    }
+
+    /// Wrap locations in OSC 8 hyperlinks to `file://` URLs under `roots`.
+    pub(crate) fn with_hyperlinks(self, roots: &'a [String]) -> Self {
+        Self {
+            link_roots: Some(roots),
+            ..self
+        }
+    }
+
+    /// Print the `--link-template` URL of every location after it.
+    pub(crate) fn with_link_template(self, links: &'a LinkTemplate) -> Self {
+        Self {
+            links: Some(links),
+            ..self
+        }
+    }
+
+    fn paint(self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{code}m{text}\x1b[0m")
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Section header line; the trailing newline stays outside the escape codes.
+    pub(crate) fn header(self, line: &str) -> String {
+        match line.strip_suffix('\n') {
+            Some(text) => self.paint("1", text) + "\n",
+            None => self.paint("1", line),
+        }
+    }
+
+    fn hash(self, hash: &str) -> String {
+        self.paint("2", hash)
+    }
+
+    pub(crate) fn path(self, path: &str) -> String {
+        self.paint("36", path)
+    }
+
+    /// `text` (a path or `path:line` location) styled like [`Self::path`]; with hyperlinks on,
+    /// it links to the file, with a `#L<start>` fragment when `lines` are known. With a link
+    /// template, the location's URL follows it.
+    pub(crate) fn location(
+        self,
+        repo_id: usize,
+        path: &str,
+        lines: Option<(u32, u32)>,
+        text: &str,
+    ) -> String {
+        let painted = self.path(text);
+        let mut out = match self.file_url(repo_id, path, lines.map(|(start, _)| start)) {
+            Some(url) => format!("\x1b]8;;{url}\x1b\\{painted}\x1b]8;;\x1b\\"),
+            None => painted,
+        };
+        if let Some(url) = self.links.and_then(|links| links.url(repo_id, path, lines)) {
+            out.push(' ');
+            out.push_str(&url);
+        }
+        out
+    }
+
+    fn file_url(self, repo_id: usize, path: &str, line: Option<u32>) -> Option<String> {
+        let roots = self.link_roots?;
+        // `--absolute-paths` output is already absolute.
+        let abs = if Path::new(path).is_absolute() {
+            path.to_string()
+        } else {
+            Path::new(roots.get(repo_id)?)
+                .join(path)
+                .to_string_lossy()
+                .into_owned()
+        };
+        let abs = abs.replace('\\', "/");
+        let mut url = String::from("file://");
+        if !abs.starts_with('/') {
+            // Windows drive paths: `file:///C:/...`.
+            url.push('/');
+        }
+        for byte in abs.bytes() {
+            if byte.is_ascii_alphanumeric() || b"/-._~:".contains(&byte) {
+                url.push(char::from(byte));
+            } else {
+                url.push_str(&format!("%{byte:02X}"));
+            }
+        }
+        if let Some(line) = line {
+            url.push_str(&format!("#L{line}"));
+        }
+        Some(url)
+    }
+
+    /// Source lines that are part of a match.
+    pub(crate) fn matched(self, text: &str) -> String {
+        self.paint("32", text)
+    }
+
+    /// Red for near-identical pairs, yellow for close ones, green otherwise.
+    fn score(self, score: f64) -> String {
+        let code = if score >= 0.95 {
+            "31"
+        } else if score >= 0.9 {
+            "33"
+        } else {
+            "32"
+        };
+        self.paint(code, &score.to_string())
+    }
+}
+
+/// Width of the widest `[label]` column, so the paths of one list line up.
+fn label_width<'a>(labels: impl IntoIterator<Item = &'a str>) -> usize {
+    labels
+        .into_iter()
+        .map(|label| label.chars().count() + 2)
+        .max()
+        .unwrap_or(0)
+}
+
+fn label_column(label: &str, width: usize) -> String {
+    format!("{:<width$}", format!("[{label}]"))
+}
+
+pub(crate) fn format_fatal_skip_warning(
+    localization: Localization,
+    stats: &ScanStats,
+    has_stats: bool,
+) -> String {
+    if !stats.has_fatal_skips() {
+        return String::new();
+    }
+
+    let mut out = String::new();
+    out.push_str(tr(
+        localization,
+        "Warning: scan was incomplete (fatal skips):\n",
+        "警告：扫描不完整（致命跳过）：\n",
+    ));
+
+    let push_item = |out: &mut String,
+                     key_camel: &str,
+                     key_snake: &str,
+                     value: u64,
+                     hint_en: &'static str,
+                     hint_zh: &'static str| {
+        if value == 0 {
+            return;
+        }
+        out.push_str(&format!(
+            "- {key_camel}={value} ({key_snake}): {}\n",
+            tr(localization, hint_en, hint_zh)
+        ));
+    };
+
+    push_item(
+        &mut out,
+        "skippedPermissionDenied",
+        "permission_denied",
+        stats.skipped_permission_denied,
+        "some files could not be read; check permissions.",
+        "部分文件无法读取；请检查权限。",
+    );
+    push_item(
+        &mut out,
+        "skippedOutsideRoot",
+        "outside_root",
+        stats.skipped_outside_root,
+        "some paths were outside roots (symlink targets or unsafe paths); consider disabling --follow-symlinks or checking for unexpected paths.",
+        "部分路径位于 root 之外（可能是符号链接目标或不安全路径）；可考虑关闭 --follow-symlinks 或检查是否存在异常路径。",
+    );
+    push_item(
+        &mut out,
+        "skippedRelativizeFailed",
+        "relativize_failed",
+        stats.skipped_relativize_failed,
+        "some paths could not be made relative to the provided roots (unexpected); re-run with --stats and report a bug if this persists.",
+        "部分路径无法相对化到提供的 root（不符合预期）；请使用 --stats 重新运行，若持续出现请提交 issue。",
+    );
+    push_item(
+        &mut out,
+        "skippedDuplicateRoots",
+        "duplicate_roots",
+        stats.skipped_duplicate_roots,
+        "some roots were the same directory as an earlier root (another spelling or a symlink) and were scanned once; remove the aliases.",
+        "部分 root 与之前的 root 是同一目录（写法不同或经由符号链接），只扫描了一次；请去掉重复的 root。",
+    );
+    push_item(
+        &mut out,
+        "skippedWalkErrors",
+        "walk_errors",
+        stats.skipped_walk_errors,
+        "filesystem traversal/read errors occurred; check the underlying errors.",
+        "文件系统遍历/读取出错；请检查底层错误。",
+    );
+    push_item(
+        &mut out,
+        "skippedBucketTruncated",
+        "bucket_truncated",
+        stats.skipped_bucket_truncated,
+        "high-frequency fingerprints were truncated; consider increasing --min-match-len/--min-token-len or using --ignore-dir to skip generated/vendor dirs.",
+        "高频 fingerprint bucket 被截断；可考虑提高 --min-match-len/--min-token-len，或用 --ignore-dir 跳过生成物/依赖目录。",
+    );
+    push_item(
+        &mut out,
+        "skippedGroupCap",
+        "group_cap",
+        stats.skipped_group_cap,
+        "a span detector hit --max-groups-per-detector and only counted the rest; skip generated/vendor dirs with --ignore-dir or raise the cap.",
+        "片段检测器触发 --max-groups-per-detector，其余重复组只计数；请用 --ignore-dir 跳过生成物/依赖目录，或提高上限。",
+    );
+    push_item(
+        &mut out,
+        "skippedBudgetMaxFiles",
+        "budget_max_files",
+        stats.skipped_budget_max_files,
+        "hit --max-files; increase the budget or remove the limit.",
+        "触发 --max-files 预算；请提高预算或移除限制。",
+    );
+    push_item(
+        &mut out,
+        "skippedBudgetMaxTotalBytes",
+        "budget_max_total_bytes",
+        stats.skipped_budget_max_total_bytes,
+        "hit --max-total-bytes; increase the budget or remove the limit.",
+        "触发 --max-total-bytes 预算；请提高预算或移除限制。",
+    );
+    push_item(
+        &mut out,
+        "skippedBudgetMaxNormalizedChars",
+        "budget_max_normalized_chars",
+        stats.skipped_budget_max_normalized_chars,
+        "hit --max-normalized-chars; increase the budget or remove the limit.",
+        "触发 --max-normalized-chars 预算；请提高预算或移除限制。",
+    );
+    push_item(
+        &mut out,
+        "skippedBudgetMaxTokens",
+        "budget_max_tokens",
+        stats.skipped_budget_max_tokens,
+        "hit --max-tokens; increase the budget or remove the limit.",
+        "触发 --max-tokens 预算；请提高预算或移除限制。",
+    );
+
+    if !has_stats {
+        out.push_str(tr(
+            localization,
+            "Re-run with --stats for full details.\n",
+            "请使用 --stats 重新运行以查看完整统计。\n",
+        ));
+    }
+
+    out
+}
+
+pub(crate) fn format_scan_stats(localization: Localization, stats: &ScanStats) -> String {
+    let mut out = String::new();
+    out.push_str(tr(localization, "== scan stats ==\n", "== 扫描统计 ==\n"));
+    // The counters are core's `Display` summary; only its headings are localized.
+    for line in stats.to_string().lines() {
+        out.push_str(match line {
+            "skipped:" => tr(localization, "skipped:", "跳过:"),
+            "per root:" => tr(localization, "per root:", "按 root:"),
+            line => line,
+        });
+        out.push('\n');
+    }
+    out.push('\n');
+    out
+}
+
+fn test_marker(localization: Localization, is_test: bool) -> &'static str {
+    if is_test {
+        tr(localization, " (test)", "（测试）")
+    } else {
+        ""
+    }
+}
+
+/// ` in PaymentService::refund` after an occurrence inside a named function or type.
+fn scope_marker(localization: Localization, scope: Option<&str>) -> String {
+    scope.map_or_else(String::new, |scope| {
+        format!("{}{scope}", tr(localization, " in ", " 位于 "))
+    })
+}
+
+/// ` modified 2024-05-01` after an occurrence dated by `--occurrence-ages`.
+fn modified_marker(localization: Localization, last_modified: Option<&str>) -> String {
+    last_modified.map_or_else(String::new, |date| {
+        let day = date.get(..10).unwrap_or(date);
+        format!("{}{day}", tr(localization, " modified ", " 修改于 "))
+    })
+}
+
+fn format_truncated_marker(localization: Localization, truncated: usize) -> String {
+    format!(
+        "- ... {truncated} {}\n",
+        tr(localization, "more (truncated)", "个已省略")
+    )
+}
+
+/// A note under a section that `--max-report-items` or `--max-groups-per-detector` cut short;
+/// empty otherwise.
+fn format_section_total_note(
+    localization: Localization,
+    report: &JsonDuplicationReport,
+    section: &str,
+) -> String {
+    match report.section_total(section) {
+        Some(total) if total.degraded => format!(
+            "\n- ... {} {} {} {} {}",
+            tr(localization, "degraded, kept", "已降级，保留"),
+            total.total_groups,
+            tr(
+                localization,
+                "groups and only counted up to",
+                "组，另有最多"
+            ),
+            total.capped_groups,
+            tr(
+                localization,
+                "more (raise --max-groups-per-detector to list them)",
+                "组只计数（调大 --max-groups-per-detector 可列出）"
+            )
+        ),
+        Some(total) if total.truncated => format!(
+            "\n- ... {} {} {}",
+            tr(localization, "truncated,", "已截断，共"),
+            total.total_groups,
+            tr(
+                localization,
+                "in total (raise --max-report-items to see more)",
+                "组（调大 --max-report-items 可查看更多）"
+            )
+        ),
+        _ => String::new(),
+    }
+}
+
+pub(crate) fn format_text(
+    localization: Localization,
+    groups: &[JsonDuplicateGroup],
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(localization, "duplicate groups", "重复文件组"),
+        groups.len()
+    ));
+
+    for group in groups {
+        out.push('\n');
+        out.push_str(&format!(
+            "hash={} normalized_len={} files={}\n",
+            style.hash(&group.hash),
+            group.normalized_len,
+            group.files.len() + group.truncated_files
+        ));
+        let width = label_width(group.files.iter().map(|f| f.repo_label.as_ref()));
+        for file in &group.files {
+            out.push_str(&format!(
+                "- {} {}{}\n",
+                label_column(&file.repo_label, width),
+                style.location(file.repo_id, &file.path, None, &file.path),
+                test_marker(localization, file.is_test)
+            ));
+        }
+        if group.truncated_files > 0 {
+            out.push_str(&format_truncated_marker(
+                localization,
+                group.truncated_files,
+            ));
+        }
+    }
+
+    out.push('\n');
+    out
+}
+
+fn format_text_symbol_groups(
+    localization: Localization,
+    groups: &[JsonSymbolDuplicateGroup],
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(localization, "symbol groups", "公开符号重复组"),
+        groups.len()
+    ));
+
+    for group in groups {
+        out.push('\n');
+        out.push_str(&format!(
+            "hash={} symbols={} files={}\n",
+            style.hash(&group.hash),
+            group.symbols.len(),
+            group.files.len() + group.truncated_files
+        ));
+        out.push_str(&format!(
+            "  {}: {}\n",
+            tr(localization, "shared API", "共同 API"),
+            group.symbols.join(", ")
+        ));
+        let width = label_width(group.files.iter().map(|f| f.repo_label.as_ref()));
+        for file in &group.files {
+            out.push_str(&format!(
+                "- {} {}{}\n",
+                label_column(&file.repo_label, width),
+                style.location(file.repo_id, &file.path, None, &file.path),
+                test_marker(localization, file.is_test)
+            ));
+        }
+        if group.truncated_files > 0 {
+            out.push_str(&format_truncated_marker(
+                localization,
+                group.truncated_files,
+            ));
+        }
+    }
+
+    out.push('\n');
+    out
+}
+
+fn format_text_import_pairs(
+    localization: Localization,
+    pairs: &[JsonImportDuplicatePair],
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(localization, "import pairs", "import 相似文件对"),
+        pairs.len()
+    ));
+
+    for pair in pairs {
+        out.push('\n');
+        out.push_str(&format!(
+            "score={:.2} shared={}\n",
+            pair.score,
+            pair.shared_imports.len()
+        ));
+        out.push_str(&format!(
+            "  {}: {}\n",
+            tr(localization, "shared imports", "共同 import"),
+            pair.shared_imports.join(", ")
+        ));
+        let width = label_width([pair.a.repo_label.as_ref(), pair.b.repo_label.as_ref()]);
+        for file in [&pair.a, &pair.b] {
+            out.push_str(&format!(
+                "- {} {}{}\n",
+                label_column(&file.repo_label, width),
+                style.location(file.repo_id, &file.path, None, &file.path),
+                test_marker(localization, file.is_test)
+            ));
+        }
+    }
+
+    out.push('\n');
+    out
+}
+
+pub(crate) fn format_text_code_spans(
+    localization: Localization,
+    groups: &[JsonDuplicateSpanGroup],
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(
+            localization,
+            "duplicate code span groups",
+            "疑似重复代码片段组"
+        ),
+        groups.len()
+    ));
+
+    for group in groups {
+        out.push('\n');
+        out.push_str(&format!(
+            "hash={} normalized_len={} occurrences={} savings_lines={}",
+            style.hash(&group.hash),
+            group.normalized_len,
+            group.occurrences.len() + group.truncated_occurrences,
+            group.potential_savings_lines
+        ));
+        if let Some(days) = group.age_spread_days {
+            out.push_str(&format!(" age_spread_days={days}"));
+        }
+        out.push('\n');
+        out.push_str(&format!("preview={}\n", group.preview));
+        if !group.also_detected_by.is_empty() {
+            out.push_str(&format!(
+                "{}={}\n",
+                tr(localization, "also_detected_by", "同时被检测于"),
+                group.also_detected_by.join(",")
+            ));
+        }
+        let width = label_width(group.occurrences.iter().map(|o| o.repo_label.as_ref()));
+        for (index, occ) in group.occurrences.iter().enumerate() {
+            out.push_str(&format!(
+                "- {} {}{}{}{}\n",
+                label_column(&occ.repo_label, width),
+                style.location(
+                    occ.repo_id,
+                    &occ.path,
+                    Some((occ.start_line, occ.end_line)),
+                    &occ.location()
+                ),
+                scope_marker(localization, occ.scope.as_deref()),
+                modified_marker(localization, occ.last_modified.as_deref()),
+                test_marker(localization, occ.is_test)
+            ));
+            if let Some(preview) = group
+                .occurrence_previews
+                .iter()
+                .find(|p| p.occurrence == index)
+            {
+                out.push_str(&format!("  preview={}\n", preview.preview));
+            }
+        }
+        if group.truncated_occurrences > 0 {
+            out.push_str(&format_truncated_marker(
+                localization,
+                group.truncated_occurrences,
+            ));
+        }
+    }
+
+    out.push('\n');
+    out
+}
+
+pub(crate) fn format_text_similar_pairs(
+    localization: Localization,
+    pairs: &[JsonSimilarityPair],
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(localization, "similar pairs", "相似对"),
+        pairs.len()
+    ));
+    for pair in pairs {
+        let score = style.score(pair.score);
+        let detector = pair.detector;
+        if let Some(distance) = pair.distance {
+            out.push_str(&format!(
+                "detector={detector} score={score} distance={distance}\n"
+            ));
+        } else {
+            out.push_str(&format!("detector={detector} score={score}\n"));
+        }
+        let width = label_width([pair.a.repo_label.as_ref(), pair.b.repo_label.as_ref()]);
+        out.push_str(&format!(
+            "- A {} {}{}{}\n",
+            label_column(&pair.a.repo_label, width),
+            style.location(
+                pair.a.repo_id,
+                &pair.a.path,
+                Some((pair.a.start_line, pair.a.end_line)),
+                &pair.a.location()
+            ),
+            scope_marker(localization, pair.a.scope.as_deref()),
+            test_marker(localization, pair.a.is_test)
+        ));
+        out.push_str(&format!(
+            "- B {} {}{}{}\n",
+            label_column(&pair.b.repo_label, width),
+            style.location(
+                pair.b.repo_id,
+                &pair.b.path,
+                Some((pair.b.start_line, pair.b.end_line)),
+                &pair.b.location()
+            ),
+            scope_marker(localization, pair.b.scope.as_deref()),
+            test_marker(localization, pair.b.is_test)
+        ));
+        if let Some(diff) = &pair.diff {
+            out.push_str(tr(
+                localization,
+                "  diff (A -> B):\n",
+                "  差异（A -> B）：\n",
+            ));
+            for line in diff.lines() {
+                out.push_str(&format!("  {line}\n"));
+            }
+        }
+    }
+    out.push('\n');
+    out
+}
+
+pub(crate) fn format_text_report(
+    localization: Localization,
+    report: &JsonDuplicationReport,
+    style: Style,
+) -> String {
+    let mut out = String::new();
+
+    if let Some(sample) = &report.sample {
+        out.push_str(&format!(
+            "{}: {}/{} ({:.1}%)\n\n",
+            tr(
+                localization,
+                "SAMPLED REPORT, figures below cover sampled files only",
+                "抽样报告，以下数据仅覆盖抽样文件"
+            ),
+            sample.sampled_files,
+            sample.candidate_files,
+            sample.ratio * 100.0
+        ));
+    }
+
+    if !report.omits("fileDuplicates") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== file duplicates ==\n",
+            "== 重复文件 ==\n",
+        )));
+        out.push_str(format_text(localization, &report.file_duplicates, style).trim_end());
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "fileDuplicates",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("codeSpanDuplicates") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== code span duplicates ==\n",
+            "== 重复代码片段 ==\n",
+        )));
+        out.push_str(
+            format_text_code_spans(localization, &report.code_span_duplicates, style).trim_end(),
+        );
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "codeSpanDuplicates",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("lineSpanDuplicates") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== line span duplicates ==\n",
+            "== 行片段重复 ==\n",
+        )));
+        out.push_str(
+            format_text_code_spans(localization, &report.line_span_duplicates, style).trim_end(),
+        );
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "lineSpanDuplicates",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("tokenSpanDuplicates") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== token span duplicates ==\n",
+            "== Token 片段重复 ==\n",
+        )));
+        out.push_str(
+            format_text_code_spans(localization, &report.token_span_duplicates, style).trim_end(),
+        );
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "tokenSpanDuplicates",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("blockDuplicates") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== block duplicates ==\n",
+            "== 块重复 ==\n",
+        )));
+        out.push_str(
+            format_text_code_spans(localization, &report.block_duplicates, style).trim_end(),
+        );
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "blockDuplicates",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("astSubtreeDuplicates") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== AST subtree duplicates ==\n",
+            "== AST 子树重复（近似） ==\n",
+        )));
+        out.push_str(
+            format_text_code_spans(localization, &report.ast_subtree_duplicates, style).trim_end(),
+        );
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "astSubtreeDuplicates",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("similarBlocksMinhash") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== similar blocks (minhash) ==\n",
+            "== 相似块对（minhash） ==\n",
+        )));
+        out.push_str(
+            format_text_similar_pairs(localization, &report.similar_blocks_minhash, style)
+                .trim_end(),
+        );
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "similarBlocksMinhash",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("similarBlocksSimhash") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== similar blocks (simhash) ==\n",
+            "== 相似块对（simhash） ==\n",
+        )));
+        out.push_str(
+            format_text_similar_pairs(localization, &report.similar_blocks_simhash, style)
+                .trim_end(),
+        );
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "similarBlocksSimhash",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("symbolDuplicates") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== symbol duplicates ==\n",
+            "== 公开符号重复 ==\n",
+        )));
+        out.push_str(
+            format_text_symbol_groups(localization, &report.symbol_duplicates, style).trim_end(),
+        );
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "symbolDuplicates",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("importDuplicates") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== import duplicates ==\n",
+            "== import 区段重复 ==\n",
+        )));
+        out.push_str(
+            format_text_import_pairs(localization, &report.import_duplicates, style).trim_end(),
+        );
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "importDuplicates",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if !report.omits("dataDuplicates") {
+        out.push_str(&style.header(tr(
+            localization,
+            "== data file duplicates ==\n",
+            "== 重复数据文件 ==\n",
+        )));
+        out.push_str(format_text(localization, &report.data_duplicates, style).trim_end());
+        out.push_str(&format_section_total_note(
+            localization,
+            report,
+            "dataDuplicates",
+        ));
+        out.push_str("\n\n");
+    }
+
+    if let Some(coverage) = &report.file_coverage {
+        out.push_str(&style.header(tr(
+            localization,
+            "== file coverage ==\n",
+            "== 文件重复覆盖 ==\n",
+        )));
+        out.push_str(format_text_file_coverage(localization, coverage, style).trim_end());
+        out.push_str("\n\n");
+    }
+
+    if let Some(clusters) = &report.clone_clusters {
+        out.push_str(&style.header(tr(localization, "== clone clusters ==\n", "== 克隆簇 ==\n")));
+        out.push_str(format_text_clone_clusters(localization, clusters, style).trim_end());
+        out.push_str("\n\n");
+    }
+
+    if let Some(clusters) = &report.similar_block_clusters {
+        out.push_str(&style.header(tr(
+            localization,
+            "== similar block clusters ==\n",
+            "== 相似块簇 ==\n",
+        )));
+        out.push_str(format_text_similar_block_clusters(localization, clusters, style).trim_end());
+        out.push_str("\n\n");
+    }
+
+    if let Some(conflicts) = &report.license_conflicts {
+        out.push_str(&style.header(tr(
+            localization,
+            "== license conflicts ==\n",
+            "== 许可证冲突 ==\n",
+        )));
+        out.push_str(format_text_license_conflicts(localization, conflicts, style).trim_end());
+        out.push_str("\n\n");
+    }
+
+    if let Some(idioms) = &report.common_idioms {
+        out.push_str(&style.header(tr(
+            localization,
+            "== common idioms ==\n",
+            "== 常见惯用片段 ==\n",
+        )));
+        out.push_str(format_text_code_spans(localization, idioms, style).trim_end());
+        out.push_str("\n\n");
+    }
+
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(
+            localization,
+            "potential savings (lines)",
+            "去重可减少行数（估算）"
+        ),
+        report.potential_savings_lines
+    ));
+    if let Some(sample) = &report.sample {
+        out.push_str(&format!(
+            "{}: {}\n",
+            tr(
+                localization,
+                "potential savings, extrapolated to all files (lines)",
+                "去重可减少行数（按全部文件外推）"
+            ),
+            sample.estimated_potential_savings_lines
+        ));
+    }
+
+    out
+}
+
+pub(crate) fn format_text_file_coverage(
+    localization: Localization,
+    coverage: &[JsonFileCoverage],
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(localization, "files", "文件数"),
+        coverage.len()
+    ));
+    let width = label_width(coverage.iter().map(|f| f.repo_label.as_ref()));
+    for file in coverage {
+        let percent = if file.total_lines == 0 {
+            0.0
+        } else {
+            f64::from(file.covered_lines) * 100.0 / f64::from(file.total_lines)
+        };
+        let ranges = file
+            .ranges
+            .iter()
+            .map(|r| format!("{}-{}", r.start_line, r.end_line))
+            .collect::<Vec<_>>()
+            .join(",");
+        out.push_str(&format!(
+            "- {} {} {}/{} ({percent:.1}%) {ranges}\n",
+            label_column(&file.repo_label, width),
+            style.location(file.repo_id, &file.path, None, &file.path),
+            file.covered_lines,
+            file.total_lines
+        ));
+    }
+    out.push('\n');
+    out
+}
+
+/// Groups listed per cluster in text output; the JSON output lists all of them.
+const CLUSTER_GROUPS_SHOWN: usize = 5;
+
+pub(crate) fn format_text_clone_clusters(
+    localization: Localization,
+    clusters: &[JsonCloneCluster],
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(localization, "clusters", "簇数"),
+        clusters.len()
+    ));
+    for (n, cluster) in clusters.iter().enumerate() {
+        out.push('\n');
+        out.push_str(&format!(
+            "#{} affected_lines={} groups={} files={}\n",
+            n + 1,
+            cluster.affected_lines,
+            cluster.groups.len(),
+            cluster.files.len()
+        ));
+        for group in cluster.groups.iter().take(CLUSTER_GROUPS_SHOWN) {
+            match &group.hash {
+                Some(hash) => out.push_str(&format!("  {}:{}\n", group.section, style.hash(hash))),
+                None => out.push_str(&format!("  {}#{}\n", group.section, group.index)),
+            }
+        }
+        if cluster.groups.len() > CLUSTER_GROUPS_SHOWN {
+            out.push_str(&format!(
+                "  ... {} {}\n",
+                cluster.groups.len() - CLUSTER_GROUPS_SHOWN,
+                tr(localization, "more groups", "个组已省略")
+            ));
+        }
+        let width = label_width(cluster.files.iter().map(|f| f.repo_label.as_ref()));
+        for file in &cluster.files {
+            let ranges = file
+                .ranges
+                .iter()
+                .map(|r| format!("{}-{}", r.start_line, r.end_line))
+                .collect::<Vec<_>>()
+                .join(",");
+            out.push_str(&format!(
+                "- {} {} {ranges}\n",
+                label_column(&file.repo_label, width),
+                style.location(file.repo_id, &file.path, None, &file.path)
+            ));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+pub(crate) fn format_text_similar_block_clusters(
+    localization: Localization,
+    clusters: &[JsonSimilarBlockCluster],
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(localization, "clusters", "簇数"),
+        clusters.len()
+    ));
+    for (n, cluster) in clusters.iter().enumerate() {
+        out.push('\n');
+        out.push_str(&format!(
+            "#{} detector={} blocks={} pairs={} score={}..{}\n",
+            n + 1,
+            cluster.detector,
+            cluster.blocks.len(),
+            cluster.pairs.len(),
+            style.score(cluster.min_score),
+            style.score(cluster.max_score)
+        ));
+        if !cluster.preview.is_empty() {
+            out.push_str(&format!("preview={}\n", cluster.preview));
+        }
+        let width = label_width(cluster.blocks.iter().map(|b| b.repo_label.as_ref()));
+        for block in &cluster.blocks {
+            out.push_str(&format!(
+                "- {} {}{}{}\n",
+                label_column(&block.repo_label, width),
+                style.location(
+                    block.repo_id,
+                    &block.path,
+                    Some((block.start_line, block.end_line)),
+                    &block.location()
+                ),
+                scope_marker(localization, block.scope.as_deref()),
+                test_marker(localization, block.is_test)
+            ));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+pub(crate) fn format_text_license_conflicts(
+    localization: Localization,
+    conflicts: &[JsonLicenseConflict],
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(localization, "conflicts", "冲突数"),
+        conflicts.len()
+    ));
+    let unlicensed = tr(localization, "(none)", "（无）");
+    for conflict in conflicts {
+        out.push('\n');
+        let licenses = conflict
+            .licenses
+            .iter()
+            .map(|license| license.as_deref().unwrap_or(unlicensed))
+            .collect::<Vec<_>>()
+            .join(" vs ");
+        out.push_str(&format!(
+            "{}#{} {licenses}\n",
+            conflict.section, conflict.index
+        ));
+        let width = label_width(conflict.files.iter().map(|f| f.repo_label.as_ref()));
+        for file in &conflict.files {
+            out.push_str(&format!(
+                "- {} {} {}\n",
+                label_column(&file.repo_label, width),
+                style.location(file.repo_id, &file.path, None, &file.path),
+                file.license.as_deref().unwrap_or(unlicensed)
+            ));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+pub(crate) fn format_text_by_file(
+    localization: Localization,
+    view: &JsonFileView,
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{}: {}\n",
+        tr(localization, "files with duplicates", "存在重复的文件数"),
+        view.files.len()
+    ));
+    for file in &view.files {
+        out.push('\n');
+        out.push_str(&format!(
+            "[{}] {} duplicates={}\n",
+            file.repo_label,
+            style.location(file.repo_id, &file.path, None, &file.path),
+            file.duplicates.len()
+        ));
+        for dup in &file.duplicates {
+            let lines = match (dup.start_line, dup.end_line) {
+                (Some(start), Some(end)) => format!("{start}-{end}"),
+                _ => tr(localization, "whole file", "整个文件").to_string(),
+            };
+            let detail = match (&dup.hash, dup.score) {
+                (_, Some(score)) => format!("score={}", style.score(score)),
+                (Some(hash), None) => format!("hash={}", style.hash(hash)),
+                (None, None) => String::new(),
+            };
+            out.push_str(&format!("- {lines} {} {detail}\n", dup.section));
+            let width = label_width(dup.partners.iter().map(|p| p.repo_label.as_ref()));
+            for partner in &dup.partners {
+                out.push_str(&format!(
+                    "    {} {}\n",
+                    label_column(&partner.repo_label, width),
+                    style.location(
+                        partner.repo_id,
+                        &partner.path,
+                        partner.start_line.zip(partner.end_line),
+                        &partner.location()
+                    )
+                ));
+            }
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// `--aggregate ownership`: cross-team groups with each place's owners, then the intra-team
+/// groups of each owner set, then unowned groups.
+pub(crate) fn format_text_by_ownership(
+    localization: Localization,
+    view: &JsonOwnershipView,
+    style: Style,
+) -> String {
+    let mut out = String::new();
+    out.push_str(&style.header(tr(
+        localization,
+        "== cross-team duplicates ==\n",
+        "== 跨团队重复 ==\n",
+    )));
+    for group in &view.cross_team {
+        push_owned_group(&mut out, group, true, style);
+    }
+    out.push('\n');
+    out.push_str(&style.header(tr(
+        localization,
+        "== intra-team duplicates ==\n",
+        "== 团队内重复 ==\n",
+    )));
+    for team in &view.intra_team {
+        out.push_str(&format!(
+            "{} groups={}\n",
+            team.owners.join(" "),
+            team.groups.len()
+        ));
+        for group in &team.groups {
+            push_owned_group(&mut out, group, false, style);
+        }
+    }
+    out.push('\n');
+    out.push_str(&style.header(tr(
+        localization,
+        "== unowned duplicates ==\n",
+        "== 无归属的重复 ==\n",
+    )));
+    for group in &view.unowned {
+        push_owned_group(&mut out, group, false, style);
+    }
+    out.push('\n');
+    out
+}
+
+fn push_owned_group(out: &mut String, group: &JsonOwnedGroup, with_owners: bool, style: Style) {
+    let detail = match (&group.hash, group.score) {
+        (_, Some(score)) => format!("score={}", style.score(score)),
+        (Some(hash), None) => format!("hash={}", style.hash(hash)),
+        (None, None) => String::new(),
+    };
+    out.push_str(&format!("- {} {detail}\n", group.section));
+    let width = label_width(
+        group
+            .places
+            .iter()
+            .map(|place| place.location.repo_label.as_ref()),
+    );
+    for place in &group.places {
+        let location = &place.location;
+        let mut line = format!(
+            "    {} {}",
+            label_column(&location.repo_label, width),
+            style.location(
+                location.repo_id,
+                &location.path,
+                location.start_line.zip(location.end_line),
+                &location.location()
+            )
+        );
+        if with_owners {
+            let owners = if place.owners.is_empty() {
+                "-".to_string()
+            } else {
+                place.owners.join(" ")
+            };
+            line.push_str(&format!(" ({owners})"));
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+}
+
+/// `genealogy`: the summary, then every group that changed. Locations are not hyperlinked
+/// since they refer to the snapshotted trees.
+pub(crate) fn format_text_genealogy(
+    localization: Localization,
+    genealogy: &JsonCloneGenealogy,
+    style: Style,
+) -> String {
+    let summary = &genealogy.summary;
+    let mut out = String::new();
+    out.push_str(&style.header(tr(
+        localization,
+        "== clone genealogy ==\n",
+        "== 克隆演化 ==\n",
+    )));
+    out.push_str(&format!(
+        "added={} removed={} grown={} shrunk={} moved={} unchanged={}\n",
+        summary.added,
+        summary.removed,
+        summary.grown,
+        summary.shrunk,
+        summary.moved,
+        summary.unchanged
+    ));
+    let (old, new) = (
+        summary.old_potential_savings_lines,
+        summary.new_potential_savings_lines,
+    );
+    out.push_str(&format!(
+        "{}: {old} -> {new} ({:+})\n",
+        tr(localization, "savings_lines", "可节省行数"),
+        i128::from(new) - i128::from(old)
+    ));
+
+    for lineage in genealogy
+        .lineages
+        .iter()
+        .filter(|lineage| lineage.change != "unchanged")
+    {
+        let Some(group) = lineage.new.as_ref().or(lineage.old.as_ref()) else {
+            continue;
+        };
+        let occurrences =
+            |group: &JsonDuplicateSpanGroup| group.occurrences.len() + group.truncated_occurrences;
+        let counts = match (&lineage.old, &lineage.new) {
+            (Some(old), Some(new)) => format!("{} -> {}", occurrences(old), occurrences(new)),
+            _ => occurrences(group).to_string(),
+        };
+        out.push('\n');
+        out.push_str(&format!(
+            "[{}] {} hash={} occurrences={counts}\n",
+            lineage.change,
+            lineage.section,
+            style.hash(&group.hash)
+        ));
+        out.push_str(&format!("preview={}\n", group.preview));
+        let width = label_width(group.occurrences.iter().map(|o| o.repo_label.as_ref()));
+        for occ in &group.occurrences {
+            out.push_str(&format!(
+                "- {} {}\n",
+                label_column(&occ.repo_label, width),
+                style.path(&occ.location())
+            ));
+        }
+        if let (Some(old), Some(_)) = (&lineage.old, &lineage.new) {
+            for occ in &old.occurrences {
+                out.push_str(&format!(
+                    "  {} {}\n",
+                    tr(localization, "was", "原为"),
+                    style.path(&occ.location())
+                ));
+            }
+        }
+    }
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::json::JsonDuplicateSpanOccurrence;
+
+    #[test]
+    fn bucket_truncated_is_fatal_skip() {
+        let mut stats = ScanStats::default();
+        stats.skipped_bucket_truncated = 1;
+        assert!(stats.has_fatal_skips());
+    }
+
+    #[test]
+    fn relativize_failed_is_fatal_skip() {
+        let mut stats = ScanStats::default();
+        stats.skipped_relativize_failed = 1;
+        assert!(stats.has_fatal_skips());
+    }
+
+    #[test]
+    fn duplicate_roots_warn_and_fail_the_paths_policy() {
+        let mut stats = ScanStats::default();
+        stats.skipped_duplicate_roots = 1;
+        assert!(stats.has_fatal_skips());
+        let msg = format_fatal_skip_warning(Localization::En, &stats, false);
+        assert!(msg.contains("skippedDuplicateRoots=1"));
+        assert!(msg.contains("remove the aliases"));
+        assert!(
+            crate::strict::StrictPolicy::parse(Localization::En, "paths")
+                .unwrap()
+                .is_violated_by(&stats)
+        );
+    }
+
+    #[test]
+    fn fatal_skip_warning_is_actionable_en() {
+        let mut stats = ScanStats::default();
+        stats.skipped_bucket_truncated = 3;
+        let msg = format_fatal_skip_warning(Localization::En, &stats, false);
+        assert!(msg.contains("skippedBucketTruncated=3"));
+        assert!(msg.contains("(bucket_truncated)"));
+        assert!(msg.contains("--ignore-dir"));
+        assert!(msg.contains("--stats"));
+    }
+
+    #[test]
+    fn fatal_skip_warning_is_actionable_zh() {
+        let mut stats = ScanStats::default();
+        stats.skipped_budget_max_files = 1;
+        let msg = format_fatal_skip_warning(Localization::Zh, &stats, false);
+        assert!(msg.contains("skippedBudgetMaxFiles=1"));
+        assert!(msg.contains("(budget_max_files)"));
+        assert!(msg.contains("请使用 --stats"));
+    }
+
+    #[test]
+    fn link_template_urls_follow_locations() {
+        // Not a git checkout, so `{repo}` is the root's directory name.
+        let roots = [PathBuf::from("/nonexistent/app")];
+        let links = LinkTemplate::new("https://git.example.com/{repo}/{path}#L{start}", &roots);
+        let style = Style::new(false).with_link_template(&links);
+        assert_eq!(
+            style.location(0, "src/a.rs", Some((3, 4)), "src/a.rs:3-4"),
+            "src/a.rs:3-4 https://git.example.com/app/src/a.rs#L3"
+        );
+        assert_eq!(
+            style.location(0, "b.rs", None, "b.rs"),
+            "b.rs https://git.example.com/app/b.rs"
+        );
+        assert_eq!(style.location(1, "c.rs", None, "c.rs"), "c.rs");
+    }
+
+    #[test]
+    fn hyperlinks_wrap_locations_in_osc8_file_urls() {
+        let roots = ["/work/my repo".to_string()];
+        let style = Style::new(false).with_hyperlinks(&roots);
+        assert_eq!(
+            style.location(0, "src/a.rs", Some((12, 30)), "src/a.rs:12-30"),
+            "\x1b]8;;file:///work/my%20repo/src/a.rs#L12\x1b\\src/a.rs:12-30\x1b]8;;\x1b\\"
+        );
+        assert_eq!(
+            style.location(0, "/abs/b.rs", None, "/abs/b.rs"),
+            "\x1b]8;;file:///abs/b.rs\x1b\\/abs/b.rs\x1b]8;;\x1b\\"
+        );
+        // Unknown repo ids (e.g. snapshot repos) and plain styles stay unlinked.
+        assert_eq!(
+            style.location(1, "c.rs", Some((1, 2)), "c.rs:1-2"),
+            "c.rs:1-2"
+        );
+        assert_eq!(
+            Style::new(false).location(0, "a.rs", Some((1, 2)), "a.rs:1-2"),
+            "a.rs:1-2"
+        );
+    }
+
+    #[test]
+    fn occurrence_lists_align_paths_and_color_only_when_enabled() {
+        let occurrence = |label: &str, path: &str| JsonDuplicateSpanOccurrence {
+            repo_id: 0,
+            repo_label: label.into(),
+            path: path.into(),
+            start_line: 1,
+            end_line: 3,
+            start_col: None,
+            end_col: None,
+            is_test: false,
+            scope: (path == "y.rs").then(|| "Service::run".into()),
+            last_modified: (path == "x.rs").then(|| "2024-05-01T10:00:00Z".to_string()),
+            source: None,
+        };
+        let groups = vec![JsonDuplicateSpanGroup {
+            hash: "00000000000000ff".to_string(),
+            normalized_len: 10,
+            preview: "fn main".to_string(),
+            occurrences: vec![occurrence("a", "x.rs"), occurrence("longer", "y.rs")],
+            truncated_occurrences: 0,
+            duplicated_lines: 6,
+            potential_savings_lines: 3,
+            also_detected_by: Vec::new(),
+            occurrence_previews: Vec::new(),
+            quality: None,
+            age_spread_days: None,
+        }];
+
+        let plain = format_text_code_spans(Localization::En, &groups, Style::new(false));
+        assert!(plain.contains("- [a]      x.rs:1-3 modified 2024-05-01\n"));
+        assert!(plain.contains("- [longer] y.rs:1-3 in Service::run\n"));
+        assert!(!plain.contains('\x1b'));
+
+        let colored = format_text_code_spans(Localization::En, &groups, Style::new(true));
+        assert!(colored.contains("hash=\x1b[2m00000000000000ff\x1b[0m"));
+        assert!(colored.contains("- [a]      \x1b[36mx.rs:1-3\x1b[0m modified 2024-05-01\n"));
+    }
+
+    #[test]
+    fn scan_stats_render_core_display_with_localized_headings() {
+        let mut stats = ScanStats::default();
+        stats.scanned_files = 4;
+        stats.skipped_binary = 2;
+        let en = format_scan_stats(Localization::En, &stats);
+        assert_eq!(en, format!("== scan stats ==\n{stats}\n"));
+        let zh = format_scan_stats(Localization::Zh, &stats);
+        assert!(zh.contains("跳过:\n- binary=2\n"), "{zh}");
+    }
+
+    #[test]
+    fn truncated_sections_report_their_total() {
+        let root = std::env::temp_dir().join(format!("dcc-section-totals-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        for (name, text) in [("a", "one"), ("b", "one"), ("c", "two"), ("d", "two")] {
+            std::fs::write(root.join(format!("{name}.txt")), text).unwrap();
+        }
+        let mut options = dup_code_check_core::ScanOptions::default();
+        options.max_report_items = 1;
+        let report = crate::json::map_report(
+            dup_code_check_core::generate_duplication_report(std::slice::from_ref(&root), &options)
+                .unwrap(),
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let files = report.section_total("fileDuplicates").unwrap();
+        assert_eq!((files.total_groups, files.truncated), (2, true));
+        let text = format_text_report(Localization::En, &report, Style::new(false));
+        assert!(
+            text.contains("- ... truncated, 2 in total (raise --max-report-items to see more)\n"),
+            "{text}"
+        );
+        assert_eq!(text.matches("truncated, ").count(), 1);
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use dup_code_check_core::ReportSection;
+use serde::Serialize;
+
+use crate::by_file::{JsonFileDuplicate, JsonFileLocation, JsonFileView, group_report_by_file};
+use crate::convert::read_saved;
+use crate::format::{FormatInput, ReportFormatter, ScanOutput};
+use crate::json::write_json;
+use crate::links::LinkTemplate;
+
+/// One comment of `--format review-comments`, shaped for the GitHub/GitLab review APIs.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct JsonReviewComment {
+    pub(crate) path: Arc<str>,
+    /// First line of the occurrence (`1` for whole-file duplicates).
+    pub(crate) line: u32,
+    pub(crate) body: String,
+}
+
+/// Identifies an occurrence across runs: `(section, group, path)`. Line numbers are left out
+/// because unrelated edits shift them; similar pairs have no hash, so the partner's path stands
+/// in for the group.
+type OccurrenceKey = (String, String, String);
+
+fn occurrence_key(duplicate: &JsonFileDuplicate, path: &str) -> OccurrenceKey {
+    let group = match &duplicate.hash {
+        Some(hash) => hash.clone(),
+        None => duplicate
+            .partners
+            .first()
+            .map(|partner| partner.path.to_string())
+            .unwrap_or_default(),
+    };
+    (duplicate.section.to_string(), group, path.to_string())
+}
+
+/// `--format review-comments`: one comment per occurrence that is not in the `--baseline`
+/// report (every occurrence without one).
+pub(crate) struct ReviewCommentsFormatter {
+    pub(crate) baseline: Option<HashSet<OccurrenceKey>>,
+}
+
+impl ReportFormatter for ReviewCommentsFormatter {
+    fn write(&self, input: &FormatInput<'_>) -> io::Result<()> {
+        let grouped;
+        let view = match input.output {
+            ScanOutput::Report(report) => {
+                grouped = group_report_by_file(report);
+                &grouped
+            }
+            ScanOutput::ReportByFile(view) => view.as_ref(),
+            ScanOutput::ReportByOwnership(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--format review-comments cannot be combined with --aggregate",
+                ));
+            }
+            ScanOutput::Files(_) | ScanOutput::CodeSpans(_) | ScanOutput::Genealogy(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--format review-comments requires --report",
+                ));
+            }
+        };
+        write_json(&review_comments(view, self.baseline.as_ref(), input.links))
+    }
+}
+
+fn review_comments(
+    view: &JsonFileView,
+    baseline: Option<&HashSet<OccurrenceKey>>,
+    links: Option<&LinkTemplate>,
+) -> Vec<JsonReviewComment> {
+    let mut comments = Vec::new();
+    for file in &view.files {
+        for duplicate in &file.duplicates {
+            let known =
+                baseline.is_some_and(|keys| keys.contains(&occurrence_key(duplicate, &file.path)));
+            if known {
+                continue;
+            }
+            comments.push(JsonReviewComment {
+                path: Arc::clone(&file.path),
+                line: duplicate.start_line.unwrap_or(1),
+                body: comment_body(duplicate, links),
+            });
+        }
+    }
+    comments
+}
+
+fn comment_body(duplicate: &JsonFileDuplicate, links: Option<&LinkTemplate>) -> String {
+    let what = match (duplicate.score, duplicate.start_line) {
+        (Some(score), _) => format!("Similar code (score {score:.2})"),
+        (None, None) => "Duplicate file".to_string(),
+        (None, Some(_)) => "Duplicated code".to_string(),
+    };
+    let lines = match (duplicate.start_line, duplicate.end_line) {
+        (Some(start), Some(end)) => format!(" (lines {start}-{end})"),
+        _ => String::new(),
+    };
+    let partners = duplicate
+        .partners
+        .iter()
+        .map(|partner| {
+            let url = links.and_then(|links| {
+                links.url(
+                    partner.repo_id,
+                    &partner.path,
+                    partner.start_line.zip(partner.end_line),
+                )
+            });
+            match url {
+                Some(url) => format!("- [`{}`]({url})", partner_location(partner)),
+                None => format!("- `{}`", partner_location(partner)),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        "{what}{lines}, found by `{}`. Also at:\n{partners}",
+        duplicate.section
+    )
+}
+
+fn partner_location(partner: &JsonFileLocation) -> String {
+    format!("[{}] {}", partner.repo_label, partner.location())
+}
+
+/// Read the occurrences of a saved `--report --json` output (with or without `--stats`).
+pub(crate) fn read_baseline(path: &Path) -> io::Result<HashSet<OccurrenceKey>> {
+    let invalid = |message: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {message}", path.display()),
+        )
+    };
+    let value = read_saved(path)?;
+    let report = value.get("report").unwrap_or(&value);
+    if !report.is_object() {
+        return Err(invalid("not a --report --json output".to_string()));
+    }
+
+    let path_of = |place: &serde_json::Value| place["path"].as_str().map(str::to_string);
+    let mut keys = HashSet::new();
+    for section in ReportSection::ALL.map(ReportSection::as_str) {
+        let Some(items) = report[section].as_array() else {
+            continue;
+        };
+        for item in items {
+            if let (Some(a), Some(b)) = (path_of(&item["a"]), path_of(&item["b"])) {
+                keys.insert((section.to_string(), b.clone(), a.clone()));
+                keys.insert((section.to_string(), a, b));
+                continue;
+            }
+            let Some(hash) = item["hash"].as_str() else {
+                continue;
+            };
+            let places = item["occurrences"]
+                .as_array()
+                .or_else(|| item["files"].as_array());
+            for place in places.into_iter().flatten() {
+                if let Some(path) = path_of(place) {
+                    keys.insert((section.to_string(), hash.to_string(), path));
+                }
+            }
+        }
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::by_file::JsonFileEntry;
+
+    fn place(path: &str, start_line: u32) -> JsonFileLocation {
+        JsonFileLocation {
+            repo_id: 0,
+            repo_label: "repo".into(),
+            path: path.into(),
+            start_line: Some(start_line),
+            end_line: Some(start_line + 9),
+        }
+    }
+
+    fn view() -> JsonFileView {
+        let duplicate =
+            |hash: &str, start_line: u32, partner: JsonFileLocation| JsonFileDuplicate {
+                section: "tokenSpanDuplicates",
+                hash: Some(hash.to_string()),
+                score: None,
+                start_line: Some(start_line),
+                end_line: Some(start_line + 9),
+                partners: vec![partner],
+            };
+        JsonFileView {
+            files: vec![
+                JsonFileEntry {
+                    repo_id: 0,
+                    repo_label: "repo".into(),
+                    path: "src/new.rs".into(),
+                    duplicates: vec![duplicate("aa", 12, place("src/old.rs", 40))],
+                },
+                JsonFileEntry {
+                    repo_id: 0,
+                    repo_label: "repo".into(),
+                    path: "src/old.rs".into(),
+                    duplicates: vec![duplicate("aa", 40, place("src/new.rs", 12))],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn comments_cover_occurrences_missing_from_the_baseline() {
+        let comments = review_comments(&view(), None, None);
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].line, 12);
+        assert_eq!(
+            comments[0].body,
+            "Duplicated code (lines 12-21), found by `tokenSpanDuplicates`. Also at:\n\
+             - `[repo] src/old.rs:40-49`"
+        );
+
+        let baseline = HashSet::from([(
+            "tokenSpanDuplicates".to_string(),
+            "aa".to_string(),
+            "src/old.rs".to_string(),
+        )]);
+        let comments = review_comments(&view(), Some(&baseline), None);
+        let paths: Vec<&str> = comments.iter().map(|c| c.path.as_ref()).collect();
+        assert_eq!(paths, ["src/new.rs"]);
+
+        let links = LinkTemplate::new(
+            "https://git.example.com/{repo}/src/{path}#L{start}-{end}",
+            &[PathBuf::from("/work/repo")],
+        );
+        let comments = review_comments(&view(), None, Some(&links));
+        assert!(comments[0].body.ends_with(
+            "- [`[repo] src/old.rs:40-49`](https://git.example.com/repo/src/src/old.rs#L40-49)"
+        ));
+    }
+
+    #[test]
+    fn baseline_reads_saved_reports_with_and_without_stats() {
+        let dir = std::env::temp_dir().join(format!("dcc-baseline-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let report = serde_json::json!({
+            "tokenSpanDuplicates": [{
+                "hash": "aa",
+                "occurrences": [{ "path": "src/old.rs" }, { "path": "src/lib.rs" }]
+            }],
+            "similarBlocksMinhash": [{ "a": { "path": "x.rs" }, "b": { "path": "y.rs" } }]
+        });
+        let plain = dir.join("plain.json");
+        fs::write(&plain, report.to_string()).unwrap();
+        let with_stats = dir.join("stats.json");
+        fs::write(
+            &with_stats,
+            serde_json::json!({ "report": report, "scanStats": {} }).to_string(),
+        )
+        .unwrap();
+
+        let keys = read_baseline(&plain).unwrap();
+        assert_eq!(keys.len(), 4);
+        assert!(keys.contains(&(
+            "similarBlocksMinhash".to_string(),
+            "x.rs".to_string(),
+            "y.rs".to_string()
+        )));
+        assert_eq!(read_baseline(&with_stats).unwrap(), keys);
+        fs::write(&plain, "[]").unwrap();
+        assert!(read_baseline(&plain).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,177 @@
+use dup_code_check_core::{ReportSection, ScanOptions};
+
+use crate::json::JsonDuplicationReport;
+use crate::locale::{Localization, tr};
+
+/// Report keys `--only` / `--omit` can drop besides [`ReportSection::ALL`].
+const OPTIONAL_SECTIONS: [&str; 5] = [
+    "fileCoverage",
+    "cloneClusters",
+    "similarBlockClusters",
+    "licenseConflicts",
+    "commonIdioms",
+];
+
+fn section_keys() -> impl Iterator<Item = &'static str> {
+    ReportSection::ALL
+        .into_iter()
+        .map(ReportSection::as_str)
+        .chain(OPTIONAL_SECTIONS)
+}
+
+/// `fileDuplicates` → `file-duplicates`; kebab-case input is returned as is.
+fn kebab_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('-');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// `*` matches any run of characters, everything else itself.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let Some((head, rest)) = pattern.split_once('*') else {
+        return pattern == name;
+    };
+    let Some(mut name) = name.strip_prefix(head) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let tail = parts.pop().unwrap_or_default();
+    for part in parts {
+        match name.find(part) {
+            Some(at) => name = &name[at + part.len()..],
+            None => return false,
+        }
+    }
+    name.len() >= tail.len() && name.ends_with(tail)
+}
+
+/// `--only` / `--omit`: which report sections are written out, by JSON key or its kebab-case
+/// form (`file-duplicates`), with `*` wildcards. Applied to the finished report, so detectors
+/// still run and `sectionTotals` still lists every section.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SectionFilter {
+    only: Vec<String>,
+    omit: Vec<String>,
+}
+
+impl SectionFilter {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.omit.is_empty()
+    }
+
+    /// Add the comma-separated patterns of `--only` (`only = true`) or `--omit`; each has to
+    /// match some section.
+    pub(crate) fn add(
+        &mut self,
+        localization: Localization,
+        flag: &str,
+        raw: &str,
+        only: bool,
+    ) -> Result<(), String> {
+        for pattern in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let pattern = kebab_case(pattern);
+            if !section_keys().any(|key| glob_matches(&pattern, &kebab_case(key))) {
+                return Err(format!(
+                    "{flag} {}: {pattern}",
+                    tr(
+                        localization,
+                        "matches no report section",
+                        "未匹配任何报告 section",
+                    ),
+                ));
+            }
+            if only {
+                self.only.push(pattern);
+            } else {
+                self.omit.push(pattern);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the section with JSON key `key` is written out.
+    pub(crate) fn keeps(&self, key: &str) -> bool {
+        let key = kebab_case(key);
+        let matches = |patterns: &[String]| patterns.iter().any(|p| glob_matches(p, &key));
+        (self.only.is_empty() || matches(&self.only)) && !matches(&self.omit)
+    }
+
+    /// Empty the dropped sections and list them in `omittedSections`. Cluster and license
+    /// conflict indices keep pointing at the unfiltered sections.
+    pub(crate) fn apply(&self, report: &mut JsonDuplicationReport) {
+        if self.is_empty() {
+            return;
+        }
+        for key in section_keys().filter(|key| !self.keeps(key)) {
+            report.omit_section(key);
+        }
+    }
+}
+
+/// Omit the sections `--max-items <section>=0` (or a `--preset`) kept from running, so they do
+/// not read as "no duplicates found".
+pub(crate) fn omit_disabled_sections(report: &mut JsonDuplicationReport, options: &ScanOptions) {
+    for section in ReportSection::ALL {
+        if options.max_items_per_detector.get(&section) == Some(&0) {
+            report.omit_section(section.as_str());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(only: &str, omit: &str) -> SectionFilter {
+        let mut filter = SectionFilter::default();
+        filter.add(Localization::En, "--only", only, true).unwrap();
+        filter.add(Localization::En, "--omit", omit, false).unwrap();
+        filter
+    }
+
+    #[test]
+    fn patterns_accept_json_keys_kebab_case_and_wildcards() {
+        let only = filter("file-duplicates,blockDuplicates", "");
+        assert!(only.keeps("fileDuplicates"));
+        assert!(only.keeps("blockDuplicates"));
+        assert!(!only.keeps("similarBlocksMinhash"));
+        assert!(!only.keeps("cloneClusters"));
+
+        let omit = filter("", "similar-*");
+        assert!(!omit.keeps("similarBlocksMinhash"));
+        assert!(!omit.keeps("similarBlocksSimhash"));
+        assert!(!omit.keeps("similarBlockClusters"));
+        assert!(omit.keeps("fileDuplicates"));
+
+        let both = filter("*-duplicates", "data-*");
+        assert!(both.keeps("codeSpanDuplicates"));
+        assert!(!both.keeps("dataDuplicates"));
+        assert!(!both.keeps("commonIdioms"));
+    }
+
+    #[test]
+    fn unknown_sections_are_rejected() {
+        let mut filter = SectionFilter::default();
+        let err = filter
+            .add(Localization::En, "--omit", "file-dupes", false)
+            .unwrap_err();
+        assert!(err.contains("file-dupes"));
+        assert!(filter.is_empty());
+    }
+
+    #[test]
+    fn glob_handles_inner_and_trailing_wildcards() {
+        assert!(glob_matches("a*c", "abc"));
+        assert!(glob_matches("a*b*c", "axxbyyc"));
+        assert!(!glob_matches("a*c", "abcd"));
+        assert!(glob_matches("*", "anything"));
+        assert!(!glob_matches("ab*ba", "aba"));
+    }
+}
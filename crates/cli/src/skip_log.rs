@@ -0,0 +1,116 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use dup_code_check_core::{SkipCallback, SkippedFile};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonSkippedFile<'a> {
+    path: &'a str,
+    reason: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
+}
+
+struct SkipLogState {
+    out: BufWriter<File>,
+    /// The first write error; later records are dropped and [`SkipLog::finish`] reports it.
+    error: Option<io::Error>,
+}
+
+/// NDJSON log of skipped files (`--log-skips <path>`), one record per skipped file.
+pub(crate) struct SkipLog {
+    state: Arc<Mutex<SkipLogState>>,
+}
+
+impl SkipLog {
+    pub(crate) fn create(path: &Path) -> io::Result<Self> {
+        let file = File::create(path)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))?;
+        Ok(Self {
+            state: Arc::new(Mutex::new(SkipLogState {
+                out: BufWriter::new(file),
+                error: None,
+            })),
+        })
+    }
+
+    pub(crate) fn callback(&self) -> SkipCallback {
+        let state = Arc::clone(&self.state);
+        SkipCallback::new(move |skipped: &SkippedFile<'_>| {
+            let path = skipped.path.to_string_lossy();
+            let record = JsonSkippedFile {
+                path: &path,
+                reason: skipped.reason.as_str(),
+                size: skipped.size,
+            };
+            let mut state = state
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if state.error.is_some() {
+                return;
+            }
+            let result = serde_json::to_writer(&mut state.out, &record)
+                .map_err(io::Error::from)
+                .and_then(|()| state.out.write_all(b"\n"));
+            if let Err(err) = result {
+                state.error = Some(err);
+            }
+        })
+    }
+
+    /// Flush the log and report the first write error, if any.
+    pub(crate) fn finish(self) -> io::Result<()> {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(err) = state.error.take() {
+            return Err(err);
+        }
+        state.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn skip_log_writes_one_record_per_skipped_file() {
+        let dir = std::env::temp_dir().join(format!("dcc-skip-log-{}", std::process::id()));
+        let root = dir.join("repo");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("a.txt"), "same\n").unwrap();
+        fs::write(root.join("big.txt"), "x".repeat(64)).unwrap();
+        fs::write(root.join("blob.bin"), b"\0\x01\x02").unwrap();
+
+        let log_path = dir.join("skips.ndjson");
+        let log = SkipLog::create(&log_path).unwrap();
+        let mut options = dup_code_check_core::ScanOptions::default();
+        options.max_file_size = Some(32);
+        options.skip_log = Some(log.callback());
+        dup_code_check_core::find_duplicate_files(std::slice::from_ref(&root), &options).unwrap();
+        log.finish().unwrap();
+
+        let mut records = fs::read_to_string(&log_path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        records.sort_by_key(|record| record["path"].as_str().unwrap().to_owned());
+        assert_eq!(records.len(), 2);
+        assert!(records[0]["path"].as_str().unwrap().ends_with("big.txt"));
+        assert_eq!(records[0]["reason"], "tooLarge");
+        assert_eq!(records[0]["size"], 64);
+        assert!(records[1]["path"].as_str().unwrap().ends_with("blob.bin"));
+        assert_eq!(records[1]["reason"], "binary");
+        assert_eq!(records[1]["size"], 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+use std::io;
+
+use dup_code_check_core::VirtualFile;
+use serde::Deserialize;
+
+/// Label of the extra root the `--stdin-json` files form.
+pub(crate) const STDIN_ROOT_LABEL: &str = "stdin";
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonVirtualFile {
+    path: String,
+    content: String,
+}
+
+/// Read the `--stdin-json` input: a JSON array of `{"path": ..., "content": ...}` objects.
+pub(crate) fn read_virtual_files(reader: impl io::Read) -> io::Result<Vec<VirtualFile>> {
+    let files: Vec<JsonVirtualFile> = serde_json::from_reader(reader).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("--stdin-json: expected [{{\"path\", \"content\"}}]: {err}"),
+        )
+    })?;
+    Ok(files
+        .into_iter()
+        .map(|file| VirtualFile {
+            path: file.path,
+            content: file.content,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_path_content_pairs() {
+        let input =
+            r#"[{"path": "gen/a.js", "content": "let a = 1;\n"}, {"content": "", "path": "b.py"}]"#;
+        let files = read_virtual_files(input.as_bytes()).unwrap();
+        assert_eq!(
+            files,
+            [
+                VirtualFile {
+                    path: "gen/a.js".to_string(),
+                    content: "let a = 1;\n".to_string(),
+                },
+                VirtualFile {
+                    path: "b.py".to_string(),
+                    content: String::new(),
+                },
+            ]
+        );
+        assert!(read_virtual_files("[]".as_bytes()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        for input in [
+            "",
+            "{}",
+            r#"[{"path": "a.js"}]"#,
+            r#"[{"path": "a.js", "content": 1}]"#,
+            r#"[{"path": "a.js", "content": "", "size": 0}]"#,
+        ] {
+            let err = read_virtual_files(input.as_bytes()).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{input}");
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Read a `--stop-sequences` file as
+/// [`ScanOptions::stop_sequences`](dup_code_check_core::ScanOptions) entries.
+///
+/// One `<language>: <code>` entry per line; blank lines and `#` comments are skipped. A
+/// `<language>:` line without code clears the language's built-in list.
+pub(crate) fn read_stop_sequences(path: &Path) -> io::Result<Vec<(String, String)>> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))?;
+    stop_sequences_from_lines(&text)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))
+}
+
+fn stop_sequences_from_lines(text: &str) -> io::Result<Vec<(String, String)>> {
+    text.lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_no, line)| {
+            let (language, code) = line.split_once(':').ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {line_no}: expected <language>: <code>"),
+                )
+            })?;
+            Ok((
+                language.trim().to_ascii_lowercase(),
+                code.trim().to_string(),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_become_language_code_pairs() {
+        assert_eq!(
+            stop_sequences_from_lines(
+                "# idioms\nGo: if err != nil { return err }\n\nrust:\npython: return self\n"
+            )
+            .unwrap(),
+            [
+                ("go".to_string(), "if err != nil { return err }".to_string()),
+                ("rust".to_string(), String::new()),
+                ("python".to_string(), "return self".to_string()),
+            ]
+        );
+        let err = stop_sequences_from_lines("go\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("line 1"));
+    }
+}
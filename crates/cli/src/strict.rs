@@ -0,0 +1,108 @@
+use dup_code_check_core::ScanStats;
+
+use crate::locale::{Localization, tr};
+
+/// Which fatal-skip categories make `--strict` exit non-zero (`--strict-policy`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StrictPolicy {
+    /// `skippedPermissionDenied`.
+    pub(crate) permission: bool,
+    /// `skippedOutsideRoot`, `skippedRelativizeFailed` and `skippedDuplicateRoots`.
+    pub(crate) paths: bool,
+    /// `skippedWalkErrors`.
+    pub(crate) walk: bool,
+    /// Any `skippedBudget*` counter.
+    pub(crate) budget: bool,
+    /// `skippedBucketTruncated` and `skippedGroupCap`.
+    pub(crate) bucket: bool,
+}
+
+impl Default for StrictPolicy {
+    /// Every category, matching [`ScanStats::has_fatal_skips`].
+    fn default() -> Self {
+        Self {
+            permission: true,
+            paths: true,
+            walk: true,
+            budget: true,
+            bucket: true,
+        }
+    }
+}
+
+impl StrictPolicy {
+    const NONE: Self = Self {
+        permission: false,
+        paths: false,
+        walk: false,
+        budget: false,
+        bucket: false,
+    };
+
+    /// Parse a comma-separated category list such as `permission,budget`.
+    pub(crate) fn parse(localization: Localization, raw: &str) -> Result<Self, String> {
+        let mut policy = Self::NONE;
+        for name in raw.split(',').map(str::trim) {
+            match name {
+                "permission" => policy.permission = true,
+                "paths" => policy.paths = true,
+                "walk" => policy.walk = true,
+                "budget" => policy.budget = true,
+                "bucket" => policy.bucket = true,
+                _ => {
+                    return Err(format!(
+                        "--strict-policy {} {name:?}",
+                        tr(
+                            localization,
+                            "entries must be permission, paths, walk, budget or bucket, got",
+                            "的取值必须是 permission、paths、walk、budget 或 bucket，实际为",
+                        ),
+                    ));
+                }
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Whether `stats` has a fatal skip in one of the selected categories.
+    pub(crate) fn is_violated_by(&self, stats: &ScanStats) -> bool {
+        (self.permission && stats.skipped_permission_denied > 0)
+            || (self.paths
+                && (stats.skipped_outside_root > 0
+                    || stats.skipped_relativize_failed > 0
+                    || stats.skipped_duplicate_roots > 0))
+            || (self.walk && stats.skipped_walk_errors > 0)
+            || (self.budget
+                && (stats.skipped_budget_max_files > 0
+                    || stats.skipped_budget_max_total_bytes > 0
+                    || stats.skipped_budget_max_normalized_chars > 0
+                    || stats.skipped_budget_max_tokens > 0))
+            || (self.bucket && (stats.skipped_bucket_truncated > 0 || stats.skipped_group_cap > 0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_matches_has_fatal_skips() {
+        let mut stats = ScanStats::default();
+        stats.skipped_outside_root = 1;
+        assert!(stats.has_fatal_skips());
+        assert!(StrictPolicy::default().is_violated_by(&stats));
+        assert!(!StrictPolicy::default().is_violated_by(&ScanStats::default()));
+    }
+
+    #[test]
+    fn policy_only_fails_on_selected_categories() {
+        let policy = StrictPolicy::parse(Localization::En, "permission, walk").unwrap();
+        let mut budget_hit = ScanStats::default();
+        budget_hit.skipped_budget_max_files = 1;
+        assert!(!policy.is_violated_by(&budget_hit));
+        let mut permission_denied = ScanStats::default();
+        permission_denied.skipped_permission_denied = 2;
+        assert!(policy.is_violated_by(&permission_denied));
+        assert!(StrictPolicy::parse(Localization::En, "permission,disk").is_err());
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::by_file::for_each_group;
+use crate::json::JsonDuplicationReport;
+use crate::locale::{Localization, tr};
+
+/// Most suggestions printed; the rest cover less of the report.
+const MAX_SUGGESTIONS: usize = 20;
+
+/// How much of a file is searched for a generated-code banner.
+const HEADER_BYTES: u64 = 1024;
+
+/// Directory names of third-party code copied into the tree.
+const VENDORED_DIRS: &[&str] = &[
+    "vendor",
+    "vendored",
+    "third_party",
+    "third-party",
+    "thirdparty",
+    "external",
+    "extern",
+    "bower_components",
+    "Pods",
+];
+
+/// Directory names of test data, recorded outputs and snapshots.
+const FIXTURE_DIRS: &[&str] = &[
+    "fixtures",
+    "__fixtures__",
+    "testdata",
+    "test-data",
+    "test_data",
+    "golden",
+    "goldens",
+    "snapshots",
+    "__snapshots__",
+];
+
+/// Directory names of generated sources.
+const GENERATED_DIRS: &[&str] = &["generated", "__generated__", "gen", "autogen", "codegen"];
+
+/// Banners of generated files, matched case-insensitively in their first [`HEADER_BYTES`].
+const GENERATED_MARKERS: &[&str] = &[
+    "@generated",
+    "do not edit",
+    "auto-generated",
+    "autogenerated",
+    "automatically generated",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum ExcludeReason {
+    Vendored,
+    Fixtures,
+    Generated,
+}
+
+impl ExcludeReason {
+    fn label(self, localization: Localization) -> &'static str {
+        match self {
+            Self::Vendored => tr(localization, "vendored code", "第三方代码"),
+            Self::Fixtures => tr(localization, "test fixtures", "测试数据"),
+            Self::Generated => tr(localization, "generated code", "生成代码"),
+        }
+    }
+}
+
+/// `--suggest-excludes`: one ignore pattern and how much of the report it would remove.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ExcludeSuggestion {
+    /// Gitignore-style pattern relative to each root.
+    pub(crate) pattern: String,
+    pub(crate) reason: ExcludeReason,
+    /// Groups left with fewer than two places once the pattern's places are gone.
+    pub(crate) removed_groups: usize,
+    /// Places matching the pattern.
+    pub(crate) removed_places: usize,
+}
+
+/// Suggest excludes for the vendored, fixture and generated files among the places of
+/// `report`, the ones removing the most groups first. Generated files are recognized by
+/// their directory or by a banner in their header, read from `roots` (by repo id).
+pub(crate) fn suggest_excludes(
+    report: &JsonDuplicationReport,
+    roots: &[PathBuf],
+) -> (Vec<ExcludeSuggestion>, usize) {
+    let mut groups: Vec<Vec<Option<(String, ExcludeReason)>>> = Vec::new();
+    let mut banners: HashMap<(usize, String), bool> = HashMap::new();
+    for_each_group(report, |_, _, _, places| {
+        groups.push(
+            places
+                .iter()
+                .map(|place| {
+                    directory_candidate(&place.path).or_else(|| {
+                        let generated = *banners
+                            .entry((place.repo_id, place.path.to_string()))
+                            .or_insert_with(|| {
+                                roots.get(place.repo_id).is_some_and(|root| {
+                                    has_generated_banner(&root.join(place.path.as_ref()))
+                                })
+                            });
+                        generated.then(|| (format!("/{}", place.path), ExcludeReason::Generated))
+                    })
+                })
+                .collect(),
+        );
+    });
+
+    let mut suggestions: BTreeMap<String, ExcludeSuggestion> = BTreeMap::new();
+    for places in &groups {
+        let mut matched: BTreeMap<&str, (ExcludeReason, usize)> = BTreeMap::new();
+        for (pattern, reason) in places.iter().flatten() {
+            matched.entry(pattern).or_insert((*reason, 0)).1 += 1;
+        }
+        for (pattern, (reason, count)) in matched {
+            let suggestion =
+                suggestions
+                    .entry(pattern.to_string())
+                    .or_insert_with(|| ExcludeSuggestion {
+                        pattern: pattern.to_string(),
+                        reason,
+                        removed_groups: 0,
+                        removed_places: 0,
+                    });
+            suggestion.removed_places += count;
+            if places.len() - count < 2 {
+                suggestion.removed_groups += 1;
+            }
+        }
+    }
+    let mut suggestions: Vec<ExcludeSuggestion> = suggestions.into_values().collect();
+    suggestions.sort_by_key(|s| {
+        (
+            std::cmp::Reverse(s.removed_groups),
+            std::cmp::Reverse(s.removed_places),
+        )
+    });
+    suggestions.truncate(MAX_SUGGESTIONS);
+    (suggestions, groups.len())
+}
+
+/// The outermost vendored, fixture or generated directory containing `path`, as an anchored
+/// directory pattern.
+fn directory_candidate(path: &str) -> Option<(String, ExcludeReason)> {
+    let (dirs, _) = path.rsplit_once('/')?;
+    let mut end = 0;
+    for name in dirs.split('/') {
+        end += name.len();
+        let reason = if VENDORED_DIRS.contains(&name) {
+            ExcludeReason::Vendored
+        } else if FIXTURE_DIRS.contains(&name) {
+            ExcludeReason::Fixtures
+        } else if GENERATED_DIRS.contains(&name) {
+            ExcludeReason::Generated
+        } else {
+            end += 1;
+            continue;
+        };
+        return Some((format!("/{}/", &dirs[..end]), reason));
+    }
+    None
+}
+
+fn has_generated_banner(path: &Path) -> bool {
+    let mut header = Vec::new();
+    let read = File::open(path).and_then(|file| file.take(HEADER_BYTES).read_to_end(&mut header));
+    if read.is_err() {
+        return false;
+    }
+    let header = String::from_utf8_lossy(&header).to_lowercase();
+    GENERATED_MARKERS
+        .iter()
+        .any(|marker| header.contains(marker))
+}
+
+/// The suggestions as a commented list of patterns, usable as an `--import-ignores` file.
+pub(crate) fn format_suggestions(
+    localization: Localization,
+    suggestions: &[ExcludeSuggestion],
+    total_groups: usize,
+) -> String {
+    let mut out = String::from(tr(
+        localization,
+        "== suggested excludes ==\n",
+        "== 建议排除 ==\n",
+    ));
+    if suggestions.is_empty() {
+        out.push_str(tr(
+            localization,
+            "# no vendored, fixture or generated files in the report\n",
+            "# 报告中没有第三方代码、测试数据或生成代码\n",
+        ));
+        return out;
+    }
+    out.push_str(tr(
+        localization,
+        "# save as a file and pass it to --import-ignores, or pass each pattern to --exclude\n",
+        "# 保存为文件并通过 --import-ignores 传入，或把每个模式传给 --exclude\n",
+    ));
+    for suggestion in suggestions {
+        let percent = suggestion.removed_groups as f64 * 100.0 / total_groups.max(1) as f64;
+        out.push_str(&format!(
+            "# {}: -{} {} ({percent:.1}%), -{} {}\n{}\n",
+            suggestion.reason.label(localization),
+            suggestion.removed_groups,
+            tr(localization, "groups", "个重复组"),
+            suggestion.removed_places,
+            tr(localization, "occurrences", "处出现"),
+            suggestion.pattern,
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::json::map_report;
+
+    #[test]
+    fn directories_are_suggested_outermost_first() {
+        assert_eq!(
+            directory_candidate("web/vendor/lib/gen/a.js"),
+            Some(("/web/vendor/".to_string(), ExcludeReason::Vendored))
+        );
+        assert_eq!(
+            directory_candidate("tests/fixtures/a.json"),
+            Some(("/tests/fixtures/".to_string(), ExcludeReason::Fixtures))
+        );
+        assert_eq!(directory_candidate("src/generated.rs"), None);
+        assert_eq!(directory_candidate("vendor.rs"), None);
+    }
+
+    #[test]
+    fn suggestions_estimate_the_groups_they_remove() {
+        let root = std::env::temp_dir().join(format!("dcc-suggest-{}", std::process::id()));
+        for dir in ["src", "vendor/lib", "tests/fixtures"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for (path, text) in [
+            ("src/a.txt", "shared"),
+            ("vendor/lib/a.txt", "shared"),
+            ("vendor/lib/b.txt", "vendored twice"),
+            ("vendor/lib/c.txt", "vendored twice"),
+            ("tests/fixtures/x.txt", "fixture"),
+            ("tests/fixtures/y.txt", "fixture"),
+            ("src/api.txt", "// Code generated by hand. DO NOT EDIT.\nx"),
+            (
+                "src/api_copy.txt",
+                "// Code generated by hand. DO NOT EDIT.\nx",
+            ),
+            ("src/b.txt", "plain"),
+            ("src/c.txt", "plain"),
+        ] {
+            fs::write(root.join(path), text).unwrap();
+        }
+        let roots = [root.clone()];
+        let report = map_report(
+            dup_code_check_core::generate_duplication_report(
+                &roots,
+                &dup_code_check_core::ScanOptions::default(),
+            )
+            .unwrap(),
+        );
+        let (suggestions, total) = suggest_excludes(&report, &roots);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(total, 5);
+        let found: Vec<(&str, usize, usize)> = suggestions
+            .iter()
+            .map(|s| (s.pattern.as_str(), s.removed_groups, s.removed_places))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("/vendor/", 2, 3),
+                ("/tests/fixtures/", 1, 2),
+                ("/src/api.txt", 1, 1),
+                ("/src/api_copy.txt", 1, 1),
+            ]
+        );
+        let text = format_suggestions(Localization::En, &suggestions, total);
+        assert!(
+            text.contains("# vendored code: -2 groups (40.0%), -3 occurrences\n/vendor/\n"),
+            "{text}"
+        );
+    }
+}
@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use dup_code_check_core::{GroupSuppression, ReportSection, ScanOptions, ScanStats};
+
+use crate::format::ScanOutput;
+use crate::json::{JsonDuplicateGroup, JsonDuplicateSpanGroup, format_rfc3339_utc};
+use crate::locale::{Localization, tr};
+
+/// One `[[suppression]]` table of a `--suppressions` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Suppression {
+    /// `<hash>` or `<section>:<hash>`, as accepted by `explain`.
+    pub(crate) group_id: String,
+    pub(crate) reason: String,
+    pub(crate) author: Option<String>,
+    /// Last day (`YYYY-MM-DD`, UTC) the suppression applies.
+    pub(crate) expires: Option<String>,
+}
+
+impl Suppression {
+    fn is_expired(&self, today: &str) -> bool {
+        self.expires
+            .as_deref()
+            .is_some_and(|expires| expires < today)
+    }
+}
+
+/// How a `--suppressions` file applied to one output.
+#[derive(Debug, Default)]
+pub(crate) struct SuppressionOutcome<'a> {
+    /// Groups removed from the output.
+    pub(crate) suppressed_groups: usize,
+    /// Suppressions past their `expires` date that still match a group (which they no longer
+    /// remove).
+    pub(crate) expired: Vec<&'a Suppression>,
+    /// Suppressions that match no group (in a report, listed or not).
+    pub(crate) unknown: Vec<&'a Suppression>,
+}
+
+/// Read a `--suppressions` file: a TOML document of `[[suppression]]` tables with the keys
+/// `group_id` and `reason` (required), `author` and `expires` (a `YYYY-MM-DD` date).
+///
+/// Only this subset of TOML is accepted: basic and literal strings, bare dates and `#`
+/// comments.
+pub(crate) fn read_suppressions(path: &Path) -> io::Result<Vec<Suppression>> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))?;
+    suppressions_from_toml(&text)
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))
+}
+
+fn suppressions_from_toml(text: &str) -> io::Result<Vec<Suppression>> {
+    let invalid = |line_no: usize, message: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("line {line_no}: {message}"),
+        )
+    };
+    let mut tables: Vec<(usize, HashMap<&str, String>)> = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("[[suppression]]") {
+            if !is_blank_or_comment(rest) {
+                return Err(invalid(line_no, "unexpected text after [[suppression]]"));
+            }
+            tables.push((line_no, HashMap::new()));
+            continue;
+        }
+        let (key, raw) = line
+            .split_once('=')
+            .ok_or_else(|| invalid(line_no, "expected [[suppression]] or key = value"))?;
+        let key = key.trim();
+        let Some((_, table)) = tables.last_mut() else {
+            return Err(invalid(line_no, "key outside a [[suppression]] table"));
+        };
+        let key = ["group_id", "reason", "author", "expires"]
+            .into_iter()
+            .find(|known| *known == key)
+            .ok_or_else(|| invalid(line_no, &format!("unknown key {key:?}")))?;
+        let (value, quoted) = parse_value(raw.trim())
+            .ok_or_else(|| invalid(line_no, &format!("invalid value for {key}")))?;
+        if !quoted && key != "expires" {
+            return Err(invalid(line_no, &format!("{key} must be a string")));
+        }
+        if table.insert(key, value).is_some() {
+            return Err(invalid(line_no, &format!("duplicate key {key}")));
+        }
+    }
+
+    tables
+        .into_iter()
+        .map(|(line_no, mut table)| {
+            let group_id = table
+                .remove("group_id")
+                .ok_or_else(|| invalid(line_no, "suppression without group_id"))?;
+            if !is_group_id(&group_id) {
+                return Err(invalid(
+                    line_no,
+                    &format!("invalid group_id {group_id:?} (expected <hash> or <section>:<hash>)"),
+                ));
+            }
+            let reason = table
+                .remove("reason")
+                .filter(|reason| !reason.trim().is_empty())
+                .ok_or_else(|| invalid(line_no, "suppression without reason"))?;
+            let expires = table.remove("expires");
+            if let Some(expires) = &expires
+                && !is_date(expires)
+            {
+                return Err(invalid(
+                    line_no,
+                    &format!("invalid expires {expires:?} (expected YYYY-MM-DD)"),
+                ));
+            }
+            Ok(Suppression {
+                group_id,
+                reason,
+                author: table.remove("author"),
+                expires,
+            })
+        })
+        .collect()
+}
+
+/// A basic (`"..."`) or literal (`'...'`) string, or a bare token such as a date; the flag is
+/// `true` for strings.
+fn parse_value(raw: &str) -> Option<(String, bool)> {
+    let mut chars = raw.char_indices();
+    match chars.next()? {
+        (_, '"') => {
+            let mut value = String::new();
+            while let Some((at, c)) = chars.next() {
+                match c {
+                    '"' => return is_blank_or_comment(&raw[at + 1..]).then_some((value, true)),
+                    '\\' => value.push(match chars.next()?.1 {
+                        '"' => '"',
+                        '\\' => '\\',
+                        'n' => '\n',
+                        't' => '\t',
+                        'r' => '\r',
+                        _ => return None,
+                    }),
+                    c => value.push(c),
+                }
+            }
+            None
+        }
+        (_, '\'') => {
+            let (value, rest) = raw[1..].split_once('\'')?;
+            is_blank_or_comment(rest).then(|| (value.to_string(), true))
+        }
+        _ => {
+            let value = raw.split_once('#').map_or(raw, |(value, _)| value).trim();
+            (!value.is_empty() && !value.contains(char::is_whitespace))
+                .then(|| (value.to_string(), false))
+        }
+    }
+}
+
+fn is_blank_or_comment(rest: &str) -> bool {
+    let rest = rest.trim();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+fn is_group_id(group_id: &str) -> bool {
+    let hash = match group_id.split_once(':') {
+        Some((section, hash)) => {
+            if ReportSection::parse(section).is_none() {
+                return false;
+            }
+            hash
+        }
+        None => group_id,
+    };
+    (1..=16).contains(&hash.len()) && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_date(raw: &str) -> bool {
+    let bytes = raw.as_bytes();
+    let digits = |range: std::ops::Range<usize>| {
+        bytes[range.clone()]
+            .iter()
+            .all(u8::is_ascii_digit)
+            .then(|| raw[range].parse::<u32>())
+    };
+    if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return false;
+    }
+    matches!(
+        (digits(0..4), digits(5..7), digits(8..10)),
+        (Some(Ok(_)), Some(Ok(1..=12)), Some(Ok(1..=31)))
+    )
+}
+
+/// The current UTC date, `YYYY-MM-DD`.
+pub(crate) fn today_utc() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    format_rfc3339_utc(now)[..10].to_string()
+}
+
+/// The `--suppressions` entries as core [`ScanOptions::group_suppressions`], in the same order;
+/// entries past their `expires` date (`today` is the current UTC date, `YYYY-MM-DD`) are
+/// inactive.
+pub(crate) fn group_suppressions(
+    suppressions: &[Suppression],
+    today: &str,
+) -> Vec<GroupSuppression> {
+    suppressions
+        .iter()
+        .map(|suppression| {
+            let (section, hash) = match suppression.group_id.split_once(':') {
+                Some((section, hash)) => (ReportSection::parse(section), hash),
+                None => (None, suppression.group_id.as_str()),
+            };
+            GroupSuppression::new(
+                section,
+                // Checked by `is_group_id` when the file is read.
+                u64::from_str_radix(hash, 16).unwrap_or_default(),
+                !suppression.is_expired(today),
+            )
+        })
+        .collect()
+}
+
+/// Classify `suppressions` (listed in `options.group_suppressions` in the same order) after a
+/// scan. Core already dropped the suppressed report groups before truncation and recorded the
+/// matches in `stats`; file and code-span lists are filtered here.
+pub(crate) fn apply_suppressions<'a>(
+    output: &mut ScanOutput,
+    suppressions: &'a [Suppression],
+    options: &ScanOptions,
+    stats: &ScanStats,
+) -> SuppressionOutcome<'a> {
+    let group_suppressions = &options.group_suppressions;
+    let mut matched = vec![false; suppressions.len()];
+    let mut filter = |section: ReportSection, hash: &str| {
+        let Ok(hash) = u64::from_str_radix(hash, 16) else {
+            return false;
+        };
+        let mut suppressed = false;
+        for (index, suppression) in group_suppressions.iter().enumerate() {
+            if suppression.content_hash() == hash
+                && suppression.section().is_none_or(|wanted| wanted == section)
+            {
+                matched[index] = true;
+                suppressed |= suppression.is_active();
+            }
+        }
+        suppressed
+    };
+    let suppressed_groups = match output {
+        ScanOutput::Files(groups) => {
+            retain_unsuppressed(ReportSection::FileDuplicates, groups, &mut filter)
+        }
+        ScanOutput::CodeSpans(groups) => {
+            retain_unsuppressed(ReportSection::CodeSpanDuplicates, groups, &mut filter)
+        }
+        ScanOutput::Report(_) => {
+            for &index in &stats.matched_suppressions {
+                if let Some(matched) = matched.get_mut(index) {
+                    *matched = true;
+                }
+            }
+            usize::try_from(stats.suppressed_groups).unwrap_or(usize::MAX)
+        }
+        // Grouped by file or owner after suppression; genealogies are not filtered.
+        ScanOutput::ReportByFile(_)
+        | ScanOutput::ReportByOwnership(_)
+        | ScanOutput::Genealogy(_) => 0,
+    };
+    let mut outcome = SuppressionOutcome {
+        suppressed_groups,
+        ..SuppressionOutcome::default()
+    };
+    for ((suppression, group_suppression), matched) in
+        suppressions.iter().zip(group_suppressions).zip(matched)
+    {
+        if !matched {
+            outcome.unknown.push(suppression);
+        } else if !group_suppression.is_active() {
+            outcome.expired.push(suppression);
+        }
+    }
+    outcome
+}
+
+/// A listed group with a `hash`.
+trait HashedGroup {
+    fn hash(&self) -> &str;
+}
+
+impl HashedGroup for JsonDuplicateGroup {
+    fn hash(&self) -> &str {
+        &self.hash
+    }
+}
+
+impl HashedGroup for JsonDuplicateSpanGroup {
+    fn hash(&self) -> &str {
+        &self.hash
+    }
+}
+
+/// Drop the groups of `section` that `filter` suppresses; returns how many were dropped.
+fn retain_unsuppressed<T: HashedGroup>(
+    section: ReportSection,
+    groups: &mut Vec<T>,
+    filter: &mut impl FnMut(ReportSection, &str) -> bool,
+) -> usize {
+    let before = groups.len();
+    groups.retain(|group| !filter(section, group.hash()));
+    before - groups.len()
+}
+
+/// Warnings for expired and unknown suppressions (empty when there are none).
+pub(crate) fn format_suppression_warnings(
+    localization: Localization,
+    outcome: &SuppressionOutcome<'_>,
+) -> String {
+    let mut out = String::new();
+    let warning = tr(localization, "Warning", "警告");
+    for suppression in &outcome.expired {
+        out.push_str(&format!(
+            "{warning}: {} {} {} {} ({}{}); {}\n",
+            tr(localization, "suppression", "抑制项"),
+            suppression.group_id,
+            tr(localization, "expired on", "已于"),
+            suppression.expires.as_deref().unwrap_or_default(),
+            suppression.reason,
+            suppression
+                .author
+                .as_deref()
+                .map(|author| format!(", {author}"))
+                .unwrap_or_default(),
+            tr(localization, "it no longer applies", "过期，不再生效"),
+        ));
+    }
+    for suppression in &outcome.unknown {
+        out.push_str(&format!(
+            "{warning}: {} {} {}\n",
+            tr(localization, "suppression", "抑制项"),
+            suppression.group_id,
+            tr(localization, "matches no group", "未匹配任何重复组"),
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use dup_code_check_core::ScanOptions;
+
+    use super::*;
+    use crate::json::{map_duplicate_groups, map_report};
+
+    #[test]
+    fn tables_become_suppressions() {
+        let parsed = suppressions_from_toml(concat!(
+            "# reviewed duplicates\n",
+            "[[suppression]]\n",
+            "group_id = \"tokenSpanDuplicates:6300950429c7392d\"\n",
+            "reason = \"generated \\\"proto\\\" code\" # see #12\n",
+            "author = 'alice'\n",
+            "expires = 2026-12-31\n",
+            "\n",
+            "[[suppression]] # no expiry\n",
+            "group_id = \"7c20e326b309a5f5\"\n",
+            "reason = \"vendored\"\n",
+        ))
+        .unwrap();
+        assert_eq!(
+            parsed,
+            [
+                Suppression {
+                    group_id: "tokenSpanDuplicates:6300950429c7392d".to_string(),
+                    reason: "generated \"proto\" code".to_string(),
+                    author: Some("alice".to_string()),
+                    expires: Some("2026-12-31".to_string()),
+                },
+                Suppression {
+                    group_id: "7c20e326b309a5f5".to_string(),
+                    reason: "vendored".to_string(),
+                    author: None,
+                    expires: None,
+                },
+            ]
+        );
+        for (text, message) in [
+            ("group_id = \"aa\"\n", "line 1: key outside"),
+            (
+                "[[suppression]]\nreason = \"r\"\n",
+                "line 1: suppression without group_id",
+            ),
+            (
+                "[[suppression]]\ngroup_id = \"x:aa\"\nreason = \"r\"\n",
+                "invalid group_id",
+            ),
+            (
+                "[[suppression]]\ngroup_id = aa\n",
+                "line 2: group_id must be a string",
+            ),
+            ("[[suppression]]\nowner = \"bob\"\n", "line 2: unknown key"),
+            (
+                "[[suppression]]\ngroup_id = \"aa\"\nreason = \"r\"\nexpires = 2026-13-01\n",
+                "invalid expires",
+            ),
+        ] {
+            let err = suppressions_from_toml(text).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains(message), "{err}");
+        }
+    }
+
+    #[test]
+    fn suppressed_groups_leave_the_report_until_they_expire() -> io::Result<()> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("clock should be monotonic")
+            .as_nanos();
+        let root: PathBuf =
+            std::env::temp_dir().join(format!("dup-code-check-cli-suppressions-{nanos}"));
+        fs::create_dir_all(&root)?;
+        let code = "fn main() {\n    let total = compute(1, 2);\n    println!(\"{total}\");\n}\n";
+        fs::write(root.join("a.rs"), code)?;
+        fs::write(root.join("b.rs"), code)?;
+        let roots = std::slice::from_ref(&root);
+        let mut options = ScanOptions::default();
+        let hash = format!(
+            "{:016x}",
+            dup_code_check_core::generate_duplication_report(roots, &options)?.file_duplicates[0]
+                .content_hash
+        );
+
+        let suppressions = [
+            Suppression {
+                group_id: format!("fileDuplicates:{hash}"),
+                reason: "r".to_string(),
+                author: None,
+                expires: Some("2026-06-30".to_string()),
+            },
+            Suppression {
+                group_id: "ffff".to_string(),
+                reason: "r".to_string(),
+                author: None,
+                expires: None,
+            },
+        ];
+        for (today, suppressed) in [("2026-06-30", 1), ("2026-07-01", 0)] {
+            options.group_suppressions = group_suppressions(&suppressions, today);
+            let outcome =
+                dup_code_check_core::generate_duplication_report_with_stats(roots, &options)?;
+            let mut output = ScanOutput::Report(Box::new(map_report(outcome.result)));
+            let classified =
+                apply_suppressions(&mut output, &suppressions, &options, &outcome.stats);
+            assert_eq!(classified.suppressed_groups, suppressed, "{today}");
+            assert_eq!(classified.unknown, [&suppressions[1]]);
+            let expired: &[&Suppression] = if suppressed == 0 {
+                &[&suppressions[0]]
+            } else {
+                &[]
+            };
+            assert_eq!(classified.expired, expired);
+            let ScanOutput::Report(report) = &output else {
+                unreachable!();
+            };
+            assert_eq!(report.file_duplicates.len(), 1 - suppressed);
+
+            let groups = dup_code_check_core::find_duplicate_files_with_stats(roots, &options)?;
+            let mut output = ScanOutput::Files(map_duplicate_groups(groups.result));
+            let classified =
+                apply_suppressions(&mut output, &suppressions, &options, &groups.stats);
+            assert_eq!(classified.suppressed_groups, suppressed, "{today}");
+            assert_eq!(classified.expired, expired);
+        }
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
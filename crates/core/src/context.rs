@@ -0,0 +1,391 @@
+//! Where in its file a piece of code sits: the function, method or type around it.
+
+use std::sync::Arc;
+
+use crate::language::comment_syntax_for_path;
+use crate::tokenize::{BlockNode, parse_brace_blocks, tokenize_for_dup_detection};
+use crate::types::VirtualFile;
+use crate::util::split_text_lines;
+
+/// The named scope enclosing a line, as found by [`enclosing_scope`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ScopeInfo {
+    /// Names of the enclosing scopes, outermost first, joined with `::`, e.g.
+    /// `PaymentService::refund`.
+    pub name: String,
+    /// Number of named scopes in [`name`](Self::name) (`1` for a top-level function).
+    pub depth: u32,
+    /// Lines of the innermost scope, from its opening to its closing line.
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
+/// A named scope of a scanned file; `name` is qualified as in [`ScopeInfo::name`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NamedScope {
+    pub(crate) name: Arc<str>,
+    pub(crate) depth: u32,
+    pub(crate) start_line: u32,
+    pub(crate) end_line: u32,
+}
+
+/// The innermost named scope (function, method, class, impl, module, ...) of `file` that
+/// contains `line` (1-based).
+///
+/// Scopes come from the brace block tree the block detector uses, named from the declaration in
+/// front of each block; anonymous blocks (`if`, loops, closures, object literals) are skipped.
+/// Python files use `def` / `class` lines and indentation instead. `None` at top level.
+pub fn enclosing_scope(file: &VirtualFile, line: u32) -> Option<ScopeInfo> {
+    let tokenized = tokenize_for_dup_detection(&file.content, comment_syntax_for_path(&file.path));
+    let blocks = parse_brace_blocks(&tokenized.tokens, &tokenized.token_lines);
+    let scopes = named_scopes(&file.content, &file.path, &blocks);
+    scope_at(&scopes, line, line).map(|scope| ScopeInfo {
+        name: scope.name.to_string(),
+        depth: scope.depth,
+        start_line: scope.start_line,
+        end_line: scope.end_line,
+    })
+}
+
+/// The innermost scope containing all of `start_line..=end_line`.
+pub(crate) fn scope_at(
+    scopes: &[NamedScope],
+    start_line: u32,
+    end_line: u32,
+) -> Option<&NamedScope> {
+    scopes
+        .iter()
+        .filter(|scope| scope.start_line <= start_line && end_line <= scope.end_line)
+        .max_by_key(|scope| scope.depth)
+}
+
+/// Every named scope of a file, in order of their opening lines.
+pub(crate) fn named_scopes(text: &str, path: &str, blocks: &[BlockNode]) -> Vec<NamedScope> {
+    let lines: Vec<&str> = split_text_lines(text).collect();
+    if is_python(path) {
+        return python_scopes(&lines);
+    }
+
+    let mut scopes = Vec::new();
+    // Enclosing named scopes: (closing token, qualified name).
+    let mut stack: Vec<(usize, Arc<str>)> = Vec::new();
+    for block in blocks {
+        while stack
+            .last()
+            .is_some_and(|&(end_token, _)| end_token < block.start_token)
+        {
+            stack.pop();
+        }
+        let Some(name) = block_header(&lines, block.start_line).and_then(|h| scope_name(&h)) else {
+            continue;
+        };
+        let name: Arc<str> = match stack.last() {
+            Some((_, parent)) => Arc::from(format!("{parent}::{name}")),
+            None => Arc::from(name),
+        };
+        scopes.push(NamedScope {
+            name: Arc::clone(&name),
+            depth: stack.len() as u32 + 1,
+            start_line: block.start_line,
+            end_line: block.end_line,
+        });
+        stack.push((block.end_token, name));
+    }
+    scopes
+}
+
+fn is_python(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".py") || path.ends_with(".pyi")
+}
+
+/// The declaration in front of the block opening on `line`: the text before its `{`, plus the
+/// lines before it back to the end of the previous statement or block (at most 8 lines).
+/// Comment, attribute and annotation lines are left out.
+fn block_header(lines: &[&str], line: u32) -> Option<String> {
+    let index = (line as usize).checked_sub(1)?;
+    let first = lines.get(index)?;
+    let mut header =
+        strip_line_comment(&first[..first.find('{').unwrap_or(first.len())]).to_string();
+    for prev in lines[..index].iter().rev().take(8) {
+        if header.contains([';', '{', '}']) {
+            break;
+        }
+        let trimmed = prev.trim_start();
+        if ["//", "/*", "*", "#", "@"]
+            .iter()
+            .any(|prefix| trimmed.starts_with(prefix))
+        {
+            continue;
+        }
+        header = format!("{}\n{header}", strip_line_comment(prev));
+    }
+    let start = header.rfind([';', '{', '}']).map_or(0, |pos| pos + 1);
+    Some(header[start..].to_string())
+}
+
+fn strip_line_comment(line: &str) -> &str {
+    line.find("//").map_or(line, |pos| &line[..pos])
+}
+
+/// Keywords followed by the name they declare.
+const DECLARATION_KEYWORDS: &[&str] = &[
+    "fn",
+    "func",
+    "function",
+    "def",
+    "class",
+    "struct",
+    "enum",
+    "union",
+    "trait",
+    "interface",
+    "impl",
+    "mod",
+    "module",
+    "namespace",
+    "object",
+    "record",
+    "protocol",
+    "extension",
+];
+
+/// Keywords that open anonymous blocks.
+const CONTROL_KEYWORDS: &[&str] = &[
+    "if",
+    "else",
+    "for",
+    "foreach",
+    "while",
+    "do",
+    "loop",
+    "switch",
+    "match",
+    "case",
+    "when",
+    "select",
+    "try",
+    "catch",
+    "finally",
+    "return",
+    "unsafe",
+    "async",
+    "synchronized",
+    "lock",
+    "using",
+    "with",
+    "defer",
+    "go",
+    "new",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Word<'a> {
+    Ident(&'a str),
+    Punct(char),
+}
+
+fn words(text: &str) -> Vec<Word<'_>> {
+    let mut out = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_alphabetic() || c == '_' || c == '$' {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '$') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            out.push(Word::Ident(&text[start..end]));
+        } else if !c.is_whitespace() {
+            out.push(Word::Punct(c));
+        }
+    }
+    out
+}
+
+/// The name a block header declares, or `None` for anonymous blocks.
+fn scope_name(header: &str) -> Option<String> {
+    let words = words(header);
+    if let Some(pos) = words
+        .iter()
+        .position(|word| matches!(word, Word::Ident(w) if DECLARATION_KEYWORDS.contains(w)))
+    {
+        let rest = &words[pos + 1..];
+        return match words[pos] {
+            Word::Ident("impl") => impl_name(rest),
+            Word::Ident("func") => go_func_name(rest),
+            _ => first_ident(rest),
+        };
+    }
+
+    if let Some(Word::Ident(first)) = words.first()
+        && CONTROL_KEYWORDS.contains(first)
+    {
+        return None;
+    }
+    // `Type name(args) {`: methods and functions of C-like languages. Assignments (`x = (...) =>`)
+    // and calls taking a lambda (`run(() => {`) have other words in front of the `(`.
+    let paren = words.iter().position(|&word| word == Word::Punct('('))?;
+    if words[..paren].contains(&Word::Punct('=')) {
+        return None;
+    }
+    match words[..paren] {
+        [.., Word::Ident(_), Word::Ident(name)] => Some(name.to_string()),
+        [.., Word::Punct('*' | '&' | '>' | ':'), Word::Ident(name)] if paren >= 2 => {
+            Some(name.to_string())
+        }
+        _ => None,
+    }
+}
+
+fn first_ident(words: &[Word<'_>]) -> Option<String> {
+    words.iter().find_map(|word| match word {
+        Word::Ident(name) => Some(name.to_string()),
+        Word::Punct(_) => None,
+    })
+}
+
+/// `impl<T> Type<T>` and `impl Trait for Type`: the implementing type.
+fn impl_name(words: &[Word<'_>]) -> Option<String> {
+    let mut depth = 0usize;
+    let mut name = None;
+    for word in words {
+        match word {
+            Word::Punct('<') => depth += 1,
+            Word::Punct('>') => depth = depth.saturating_sub(1),
+            Word::Ident("for") if depth == 0 => name = None,
+            Word::Ident("where") if depth == 0 => break,
+            Word::Ident(ident) if depth == 0 && name.is_none() => name = Some(ident.to_string()),
+            _ => {}
+        }
+    }
+    name
+}
+
+/// `func Name(` and `func (r *Receiver) Name(`.
+fn go_func_name(words: &[Word<'_>]) -> Option<String> {
+    let rest = match words.first() {
+        Some(Word::Punct('(')) => {
+            let close = words.iter().position(|&word| word == Word::Punct(')'))?;
+            &words[close + 1..]
+        }
+        _ => words,
+    };
+    match rest.first() {
+        Some(Word::Ident(name)) => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+/// `def` / `class` scopes of a Python file, ending before the next line indented no deeper.
+fn python_scopes(lines: &[&str]) -> Vec<NamedScope> {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let mut scopes: Vec<NamedScope> = Vec::new();
+    // Open scopes: (index into `scopes`, indentation).
+    let mut stack: Vec<(usize, usize)> = Vec::new();
+    let mut last_code_line = 0u32;
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let line_no = index as u32 + 1;
+        let level = indent(line);
+        while stack.last().is_some_and(|&(_, open)| level <= open) {
+            let (scope, _) = stack.pop().unwrap_or_default();
+            scopes[scope].end_line = last_code_line;
+        }
+        last_code_line = line_no;
+
+        let declaration = trimmed.strip_prefix("async ").unwrap_or(trimmed);
+        let Some(rest) = declaration
+            .strip_prefix("def ")
+            .or_else(|| declaration.strip_prefix("class "))
+        else {
+            continue;
+        };
+        let Some(Word::Ident(name)) = words(rest).first().copied() else {
+            continue;
+        };
+        let name: Arc<str> = match stack.last() {
+            Some(&(parent, _)) => Arc::from(format!("{}::{name}", scopes[parent].name)),
+            None => Arc::from(name),
+        };
+        stack.push((scopes.len(), level));
+        scopes.push(NamedScope {
+            name,
+            depth: stack.len() as u32,
+            start_line: line_no,
+            end_line: line_no,
+        });
+    }
+    for (scope, _) in stack {
+        scopes[scope].end_line = last_code_line;
+    }
+    scopes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(path: &str, content: &str, line: u32) -> Option<(String, u32)> {
+        let file = VirtualFile {
+            path: path.to_string(),
+            content: content.to_string(),
+        };
+        enclosing_scope(&file, line).map(|scope| (scope.name, scope.depth))
+    }
+
+    #[test]
+    fn names_come_from_the_declarations_around_a_line() {
+        let rust = "struct Payment;\n\nimpl<T> Refund for PaymentService<T> {\n    fn refund(&self) -> u32 {\n        if self.ok() {\n            1\n        } else {\n            0\n        }\n    }\n}\n";
+        assert_eq!(
+            scope("a.rs", rust, 6),
+            Some(("PaymentService::refund".to_string(), 2))
+        );
+        assert_eq!(scope("a.rs", rust, 1), None);
+
+        let java = "public class PaymentService\n{\n    /**\n     * Refunds any class of order.\n     */\n    @Override\n    public Result<Void> refund(Order order) // see interface Billing\n    {\n        return run(() -> {\n            order.cancel();\n        });\n    }\n}\n";
+        assert_eq!(
+            scope("A.java", java, 10),
+            Some(("PaymentService::refund".to_string(), 2))
+        );
+
+        let go = "package p\n\nfunc (s *Service) Refund(\n\tid int,\n) error {\n\treturn nil\n}\n";
+        assert_eq!(scope("a.go", go, 6), Some(("Refund".to_string(), 1)));
+
+        let js = "const handler = (req) => {\n  return 1;\n};\nexport function refund(id) {\n  return id;\n}\n";
+        assert_eq!(scope("a.js", js, 2), None);
+        assert_eq!(scope("a.js", js, 5), Some(("refund".to_string(), 1)));
+    }
+
+    #[test]
+    fn python_scopes_follow_indentation() {
+        let py = "class PaymentService:\n    def refund(self):\n        if self.ok:\n            return 1\n\n        return 0\n\n    x = 1\n\ndef main():\n    pass\n";
+        assert_eq!(
+            scope("a.py", py, 4),
+            Some(("PaymentService::refund".to_string(), 2))
+        );
+        assert_eq!(
+            scope("a.py", py, 8),
+            Some(("PaymentService".to_string(), 1))
+        );
+        assert_eq!(scope("a.py", py, 11), Some(("main".to_string(), 1)));
+        assert_eq!(scope("a.py", py, 9), None);
+    }
+
+    #[test]
+    fn lone_carriage_returns_end_lines() {
+        let py = "class PaymentService:\r    def refund(self):\r        return 1\r\rdef main():\r    pass\r";
+        assert_eq!(
+            scope("a.py", py, 3),
+            Some(("PaymentService::refund".to_string(), 2))
+        );
+        assert_eq!(scope("a.py", py, 6), Some(("main".to_string(), 1)));
+    }
+}
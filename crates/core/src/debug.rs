@@ -0,0 +1,207 @@
+//! Inspect how a single file is seen by the token-based detectors.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::language::{
+    comment_syntax_for_path, detect_language, min_token_len_for_path, token_scale_for_path,
+};
+use crate::tokenize::{token_label, tokenize_for_dup_detection};
+use crate::types::ScanOptions;
+use crate::util::{normalize_unicode, winnowed_fingerprints};
+
+/// The token stream and fingerprints of one file, as produced by [`explain_file`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct FileExplanation {
+    /// Language detected from the file extension, if any.
+    pub language: Option<&'static str>,
+    /// Per-language scale applied to `min_token_len` (`1.0` for unknown languages).
+    pub token_scale: f64,
+    /// `min_token_len` for this file after the per-language scale.
+    pub min_token_len: usize,
+    /// Tokens per fingerprint (k).
+    pub fingerprint_len: usize,
+    /// Winnowing window, in fingerprints.
+    pub window_size: usize,
+    /// Whether `max_tokens_per_file` cut the token stream short.
+    pub token_cap_hit: bool,
+    pub tokens: Vec<ExplainedToken>,
+    /// Every token id in [`tokens`](Self::tokens) with its label, sorted by id.
+    pub vocabulary: Vec<(u32, String)>,
+    /// One fingerprint per token position; `selected` marks the ones winnowing keeps.
+    pub fingerprints: Vec<ExplainedFingerprint>,
+}
+
+impl FileExplanation {
+    /// The fingerprints kept by winnowing, i.e. the ones matches are seeded from.
+    pub fn selected_fingerprints(&self) -> impl Iterator<Item = &ExplainedFingerprint> {
+        self.fingerprints.iter().filter(|fp| fp.selected)
+    }
+}
+
+/// A normalized token: identifiers, numbers and strings lose their text, keywords and
+/// punctuation keep theirs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExplainedToken {
+    pub id: u32,
+    pub line: u32,
+}
+
+/// The hash of the `fingerprint_len` tokens starting at `start_token`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ExplainedFingerprint {
+    pub hash: u64,
+    pub start_token: usize,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub selected: bool,
+}
+
+/// Tokenize and fingerprint `path` the way `tokenSpanDuplicates` does, to see why two regions
+/// did or didn't match.
+///
+/// Thresholds are derived from this file alone. A scan winnows at the smallest per-language
+/// `min_token_len` among all scanned files, so with mixed languages its fingerprints can be
+/// shorter than the ones reported here.
+pub fn explain_file(path: &Path, options: &ScanOptions) -> io::Result<FileExplanation> {
+    let bytes = fs::read(path)?;
+    let text_bytes = normalize_unicode(&bytes, options.unicode_normalization);
+    let text = String::from_utf8_lossy(&text_bytes);
+    let path_str = path.to_string_lossy().replace('\\', "/");
+    let mut tokenized = tokenize_for_dup_detection(&text, comment_syntax_for_path(&path_str));
+    let token_cap_hit = options
+        .max_tokens_per_file
+        .is_some_and(|cap| tokenized.tokens.len() > cap);
+    if let Some(cap) = options.max_tokens_per_file {
+        tokenized.tokens.truncate(cap);
+        tokenized.token_lines.truncate(cap);
+    }
+
+    let min_token_len = min_token_len_for_path(options, &path_str);
+    let fingerprint_len = min_token_len.clamp(1, 25);
+    let window_size = min_token_len
+        .saturating_sub(fingerprint_len)
+        .saturating_add(1);
+
+    let selected: BTreeSet<usize> =
+        winnowed_fingerprints(&tokenized.tokens, fingerprint_len, window_size)
+            .into_iter()
+            .map(|(_, pos)| pos)
+            .collect();
+    // With a window of one, winnowing keeps every position.
+    let fingerprints = winnowed_fingerprints(&tokenized.tokens, fingerprint_len, 1)
+        .into_iter()
+        .map(|(hash, start_token)| ExplainedFingerprint {
+            hash,
+            start_token,
+            start_line: tokenized.token_lines[start_token],
+            end_line: tokenized.token_lines[start_token + fingerprint_len - 1],
+            selected: selected.contains(&start_token),
+        })
+        .collect();
+
+    let vocabulary = tokenized
+        .tokens
+        .iter()
+        .copied()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|id| (id, token_label(id)))
+        .collect();
+    let tokens = tokenized
+        .tokens
+        .iter()
+        .zip(&tokenized.token_lines)
+        .map(|(&id, &line)| ExplainedToken { id, line })
+        .collect();
+
+    Ok(FileExplanation {
+        language: detect_language(&path_str).map(|language| language.name),
+        token_scale: token_scale_for_path(options, &path_str),
+        min_token_len,
+        fingerprint_len,
+        window_size,
+        token_cap_hit,
+        tokens,
+        vocabulary,
+        fingerprints,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explains_tokens_vocabulary_and_selected_fingerprints() {
+        let dir = std::env::temp_dir().join(format!("dcc-explain-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.py");
+        fs::write(&path, "if x:\n    return foo(1, \"s\")\n").unwrap();
+
+        let options = ScanOptions {
+            min_token_len: 5,
+            ..ScanOptions::default()
+        };
+        let explained = explain_file(&path, &options).unwrap();
+        assert_eq!(explained.language, Some("python"));
+        assert_eq!(explained.token_scale, 0.8);
+        assert_eq!(explained.min_token_len, 4);
+        assert_eq!((explained.fingerprint_len, explained.window_size), (4, 1));
+        assert_eq!(explained.tokens.len(), 10);
+        assert_eq!(explained.tokens[2].line, 1);
+        assert_eq!(explained.tokens[3].line, 2);
+        let labels: Vec<&str> = explained
+            .vocabulary
+            .iter()
+            .map(|(_, label)| label.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "<ident>", "<num>", "<str>", "if", "return", "(", ")", ",", ":"
+            ]
+        );
+        assert_eq!(explained.fingerprints.len(), 7);
+        assert_eq!(explained.selected_fingerprints().count(), 7);
+
+        let options = ScanOptions {
+            min_token_len: 5,
+            language_token_scales: vec![("python".to_string(), 1.6)],
+            max_tokens_per_file: Some(9),
+            ..ScanOptions::default()
+        };
+        let explained = explain_file(&path, &options).unwrap();
+        assert!(explained.token_cap_hit);
+        assert_eq!((explained.fingerprint_len, explained.window_size), (8, 1));
+        assert_eq!(explained.fingerprints.len(), 2);
+
+        let long = dir.join("long.txt");
+        fs::write(&long, "let a = b + c * (d - e) / f;\n".repeat(8)).unwrap();
+        let options = ScanOptions {
+            min_token_len: 30,
+            ..ScanOptions::default()
+        };
+        let explained = explain_file(&long, &options).unwrap();
+        assert_eq!(explained.language, None);
+        assert_eq!((explained.fingerprint_len, explained.window_size), (25, 6));
+        assert_eq!(explained.fingerprints.len(), 8 * 15 - 24);
+        let selected: Vec<usize> = explained
+            .selected_fingerprints()
+            .map(|fp| fp.start_token)
+            .collect();
+        assert!(selected.len() < explained.fingerprints.len());
+        assert!(
+            explained
+                .fingerprints
+                .windows(6)
+                .all(|window| window.iter().any(|fp| selected.contains(&fp.start_token)))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,9 +3,10 @@ use std::io;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use crate::skips::SkipList;
 use crate::types::{DuplicateFile, DuplicateGroup, DuplicateSpanGroup, ScanOptions, ScanStats};
 use crate::util::{
-    NormalizedCodeFileView, fnv1a64, make_preview_ascii, whitespace_insensitive_fingerprint,
+    NormalizedCodeFileView, fnv1a64, is_test_path, whitespace_insensitive_fingerprint,
 };
 use crate::winnowing::{WinnowingParams, detect_duplicate_span_groups_winnowing_ascii};
 
@@ -73,9 +74,15 @@ impl FileDuplicateGrouper {
         }
     }
 
+    /// Re-read every candidate file and regroup by exact whitespace-normalized bytes.
+    ///
+    /// Candidates are keyed by hashes and samples only, so a collision (or a file changing
+    /// mid-scan) could otherwise mis-group files. Files that do not land in the largest verified
+    /// subgroup of their candidate group are counted in `stats.file_verification_mismatches`.
     pub(crate) fn into_groups_verified<R, L>(
         self,
         cross_repo_only: bool,
+        stats: &mut ScanStats,
         mut read_bytes: R,
         mut repo_label_for: L,
     ) -> io::Result<Vec<DuplicateGroup>>
@@ -108,6 +115,7 @@ impl FileDuplicateGrouper {
                 repo_ids: HashSet<usize>,
             }
 
+            let candidate_count = builder.files.len();
             let mut verified: HashMap<Vec<u8>, VerifiedGroup> = HashMap::new();
             for file in builder.files {
                 let Some(bytes) = read_bytes(file.repo_id, &file.rel_path)? else {
@@ -122,6 +130,12 @@ impl FileDuplicateGrouper {
                 group.files.push(file);
             }
 
+            let largest = verified.values().map(|g| g.files.len()).max().unwrap_or(0);
+            let mismatches = u64::try_from(candidate_count - largest).unwrap_or(u64::MAX);
+            stats.file_verification_mismatches = stats
+                .file_verification_mismatches
+                .saturating_add(mismatches);
+
             for (normalized, mut group) in verified {
                 if group.files.len() <= 1 {
                     continue;
@@ -142,6 +156,7 @@ impl FileDuplicateGrouper {
                     .map(|file| DuplicateFile {
                         repo_id: file.repo_id,
                         repo_label: repo_label_for(file.repo_id),
+                        is_test: is_test_path(&file.path_display),
                         path: file.path_display,
                     })
                     .collect();
@@ -149,6 +164,7 @@ impl FileDuplicateGrouper {
                     content_hash,
                     normalized_len,
                     files,
+                    truncated_files: 0,
                 });
             }
         }
@@ -174,10 +190,12 @@ pub(crate) fn detect_duplicate_code_spans_winnowing<'a>(
             min_len: min_match_len,
             fingerprint_len,
             window_size,
+            max_window_growth: options.adaptive_window.unwrap_or(1),
             cross_repo_only: options.cross_repo_only,
+            max_groups: options.max_groups_per_detector,
         },
+        &SkipList::new(options).code_span_fingerprints(fingerprint_len),
         |_file_id, _start, _len| true,
-        |_file_id, _start_line, _end_line, sample| make_preview_ascii(sample, 80),
         stats,
     )
 }
@@ -201,14 +219,46 @@ mod tests {
         // Simulate a file changing between scan and verification.
         content.insert(PathBuf::from("b.txt"), b"xyz".to_vec());
 
+        let mut stats = ScanStats::default();
         let verified = groups
             .into_groups_verified(
                 false,
+                &mut stats,
                 |_repo_id, path| Ok(content.get(path).cloned()),
                 |_repo_id| Arc::from("repo0"),
             )
             .expect("verification should not fail");
 
         assert!(verified.is_empty());
+        assert_eq!(stats.file_verification_mismatches, 1);
+    }
+
+    #[test]
+    fn mismatching_candidates_are_split_and_counted() {
+        let mut groups = FileDuplicateGrouper::default();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            groups.push_bytes(b"abc", 0, PathBuf::from(name), Arc::from(name));
+        }
+
+        // Pretend `c.txt` collided with the others: same candidate key, different bytes.
+        let mut content: HashMap<PathBuf, Vec<u8>> = HashMap::new();
+        content.insert(PathBuf::from("a.txt"), b"a b c".to_vec());
+        content.insert(PathBuf::from("b.txt"), b"abc\n".to_vec());
+        content.insert(PathBuf::from("c.txt"), b"abd".to_vec());
+
+        let mut stats = ScanStats::default();
+        let verified = groups
+            .into_groups_verified(
+                false,
+                &mut stats,
+                |_repo_id, path| Ok(content.get(path).cloned()),
+                |_repo_id| Arc::from("repo0"),
+            )
+            .expect("verification should not fail");
+
+        assert_eq!(verified.len(), 1);
+        let paths: Vec<&str> = verified[0].files.iter().map(|f| f.path()).collect();
+        assert_eq!(paths, ["a.txt", "b.txt"]);
+        assert_eq!(stats.file_verification_mismatches, 1);
     }
 }
@@ -1,14 +1,28 @@
+use std::collections::HashMap;
 use std::io;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::dedupe::{FileDuplicateGrouper, detect_duplicate_code_spans_winnowing};
+use crate::progress::ScanPhase;
 use crate::scan::{
-    Repo, read_repo_file_bytes, read_repo_file_bytes_for_verification, repo_label, validate_roots,
-    visit_repo_files,
+    ReadTargets, Repo, apply_sample, read_repo_file_bytes, read_repo_file_bytes_for_verification,
+    repo_label, scan_repos, validate_roots, visit_repo_files,
+};
+use crate::skip_log::SkipReason;
+use crate::types::{
+    DuplicateGroup, DuplicateSpanGroup, DuplicateSpanOccurrence, ReportSection, ScanOptions,
+    ScanOutcome, ScanStats,
+};
+use crate::util::{
+    CODE_SPAN_PREVIEW_CHARS, FileDeadline, LINE_PREVIEW_CHARS, NormalizedCodeFile,
+    NormalizedCodeFileView, OutputPaths, TestRegions, apply_test_filter_to_groups,
+    apply_test_filter_to_span_groups, cap_group_occurrences, cap_span_group_occurrences,
+    cfg_test_line_ranges, fill_code_span_previews, fill_occurrence_previews,
+    fill_span_group_line_stats, fnv1a64, mark_test_occurrences, normalize_for_code_spans,
+    normalize_unicode, preview_from_file_lines, read_unchanged, resolve_code_span_columns,
+    retain_groups_with_min_occurrences, retain_span_groups_with_min_occurrences,
 };
-use crate::types::{DuplicateGroup, DuplicateSpanGroup, ScanOptions, ScanOutcome, ScanStats};
-use crate::util::{NormalizedCodeFile, NormalizedCodeFileView, normalize_for_code_spans};
 
 pub fn find_duplicate_files(
     roots: &[PathBuf],
@@ -28,18 +42,21 @@ pub fn find_duplicate_files_with_stats(
         });
     }
 
-    validate_roots(roots)?;
+    let mut stats = ScanStats::default();
+    let roots = &validate_roots(roots, &mut stats)?;
     options.validate_for_file_duplicates()?;
 
-    let repos: Vec<Repo> = roots
+    let mut repos: Vec<Repo> = roots
         .iter()
         .enumerate()
         .map(|(id, root)| Repo {
             id,
             root: root.clone(),
             label: Arc::from(repo_label(root, id)),
+            sample_cutoff: None,
         })
         .collect();
+    apply_sample(&mut repos, options)?;
 
     let canonical_roots = if options.follow_symlinks {
         Some(
@@ -51,46 +68,56 @@ pub fn find_duplicate_files_with_stats(
     } else {
         None
     };
+    let mut read_targets = ReadTargets::new();
 
-    let mut stats = ScanStats::default();
     let mut groups = FileDuplicateGrouper::default();
 
-    for repo in &repos {
+    scan_repos(&repos, &mut stats, |repo, stats| {
         let canonical_root = canonical_roots
             .as_ref()
             .map(|roots| roots[repo.id].as_path());
 
-        if let std::ops::ControlFlow::Break(()) =
-            visit_repo_files(repo, options, &mut stats, |stats, repo_file| {
-                let Some(bytes) = read_repo_file_bytes(&repo_file, canonical_root, options, stats)?
-                else {
+        visit_repo_files(repo, options, stats, |stats, repo_file| {
+            let Some(bytes) = read_repo_file_bytes(
+                &repo_file,
+                repo.id,
+                canonical_root,
+                &mut read_targets,
+                options,
+                stats,
+            )?
+            else {
+                return Ok(std::ops::ControlFlow::Continue(()));
+            };
+
+            let rel_path_for_verification = match repo_file.abs_path.strip_prefix(&repo.root) {
+                Ok(rel) => rel.to_path_buf(),
+                Err(_) => {
+                    stats.skipped_relativize_failed =
+                        stats.skipped_relativize_failed.saturating_add(1);
+                    options.log_skip(
+                        &repo_file.abs_path,
+                        SkipReason::RelativizeFailed,
+                        Some(bytes.len() as u64),
+                    );
                     return Ok(std::ops::ControlFlow::Continue(()));
-                };
-
-                let rel_path_for_verification = match repo_file.abs_path.strip_prefix(&repo.root) {
-                    Ok(rel) => rel.to_path_buf(),
-                    Err(_) => {
-                        stats.skipped_relativize_failed =
-                            stats.skipped_relativize_failed.saturating_add(1);
-                        return Ok(std::ops::ControlFlow::Continue(()));
-                    }
-                };
-                let rel_path = Arc::<str>::from(
-                    rel_path_for_verification
-                        .to_string_lossy()
-                        .replace('\\', "/"),
-                );
-                groups.push_bytes(&bytes, repo.id, rel_path_for_verification, rel_path);
+                }
+            };
+            let rel_path = Arc::<str>::from(
+                rel_path_for_verification
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+            );
+            groups.push_bytes(&bytes, repo.id, rel_path_for_verification, rel_path);
 
-                Ok(std::ops::ControlFlow::Continue(()))
-            })?
-        {
-            break;
-        }
-    }
+            Ok(std::ops::ControlFlow::Continue(()))
+        })
+    })?;
 
+    options.emit_progress(ScanPhase::Detecting(ReportSection::FileDuplicates), &stats);
     let mut out = groups.into_groups_verified(
         options.cross_repo_only,
+        &mut stats,
         |repo_id, path| {
             let repo = &repos[repo_id];
             let canonical_root = canonical_roots
@@ -108,6 +135,8 @@ pub fn find_duplicate_files_with_stats(
         |repo_id| Arc::clone(&repos[repo_id].label),
     )?;
 
+    apply_test_filter_to_groups(&mut out, options.test_code_filter, options.cross_repo_only);
+    retain_groups_with_min_occurrences(&mut out, options.min_occurrences);
     out.sort_by(|a, b| {
         (a.content_hash, a.normalized_len, a.files.len()).cmp(&(
             b.content_hash,
@@ -115,6 +144,11 @@ pub fn find_duplicate_files_with_stats(
             b.files.len(),
         ))
     });
+    cap_group_occurrences(&mut out, options.max_occurrences_per_group);
+    if OutputPaths::is_active(options) {
+        OutputPaths::new(roots, options).rewrite_groups(&mut out);
+    }
+    options.emit_progress(ScanPhase::Finished, &stats);
     Ok(ScanOutcome { result: out, stats })
 }
 
@@ -136,20 +170,23 @@ pub fn find_duplicate_code_spans_with_stats(
         });
     }
 
-    validate_roots(roots)?;
+    let mut stats = ScanStats::default();
+    let roots = &validate_roots(roots, &mut stats)?;
     options.validate_for_code_spans()?;
 
     let min_match_len = options.min_match_len.max(1);
 
-    let repos: Vec<Repo> = roots
+    let mut repos: Vec<Repo> = roots
         .iter()
         .enumerate()
         .map(|(id, root)| Repo {
             id,
             root: root.clone(),
             label: Arc::from(repo_label(root, id)),
+            sample_cutoff: None,
         })
         .collect();
+    apply_sample(&mut repos, options)?;
 
     let canonical_roots = if options.follow_symlinks {
         Some(
@@ -161,59 +198,93 @@ pub fn find_duplicate_code_spans_with_stats(
     } else {
         None
     };
+    let mut read_targets = ReadTargets::new();
 
-    let mut stats = ScanStats::default();
     let mut files = Vec::new();
     let mut total_normalized_chars: usize = 0;
 
-    for repo in &repos {
+    scan_repos(&repos, &mut stats, |repo, stats| {
         let canonical_root = canonical_roots
             .as_ref()
             .map(|roots| roots[repo.id].as_path());
 
-        if let std::ops::ControlFlow::Break(()) =
-            visit_repo_files(repo, options, &mut stats, |stats, repo_file| {
-                let Some(bytes) = read_repo_file_bytes(&repo_file, canonical_root, options, stats)?
-                else {
-                    return Ok(std::ops::ControlFlow::Continue(()));
-                };
+        visit_repo_files(repo, options, stats, |stats, repo_file| {
+            let Some(bytes) = read_repo_file_bytes(
+                &repo_file,
+                repo.id,
+                canonical_root,
+                &mut read_targets,
+                options,
+                stats,
+            )?
+            else {
+                return Ok(std::ops::ControlFlow::Continue(()));
+            };
 
-                let normalized = normalize_for_code_spans(&bytes);
-                if normalized.chars.len() < min_match_len {
-                    return Ok(std::ops::ControlFlow::Continue(()));
+            let deadline = FileDeadline::start(options.max_file_time);
+            let content_hash = fnv1a64(&bytes);
+            let bytes = normalize_unicode(&bytes, options.unicode_normalization);
+            let normalized = normalize_for_code_spans(&bytes);
+            if normalized.chars.len() < min_match_len {
+                return Ok(std::ops::ControlFlow::Continue(()));
+            }
+            let test_ranges = cfg_test_line_ranges(&bytes);
+            if deadline.expired() {
+                stats.skipped_slow_file = stats.skipped_slow_file.saturating_add(1);
+                stats.skipped_slow_file_bytes = stats
+                    .skipped_slow_file_bytes
+                    .saturating_add(bytes.len() as u64);
+                options.log_skip(
+                    &repo_file.abs_path,
+                    SkipReason::SlowFile,
+                    Some(bytes.len() as u64),
+                );
+                return Ok(std::ops::ControlFlow::Continue(()));
+            }
+            if let Some(max_normalized_chars) = options.max_normalized_chars {
+                let next_total = total_normalized_chars.saturating_add(normalized.chars.len());
+                if next_total > max_normalized_chars {
+                    stats.skipped_budget_max_normalized_chars =
+                        stats.skipped_budget_max_normalized_chars.saturating_add(1);
+                    stats.skipped_budget_max_normalized_chars_bytes = stats
+                        .skipped_budget_max_normalized_chars_bytes
+                        .saturating_add(bytes.len() as u64);
+                    options.log_skip(
+                        &repo_file.abs_path,
+                        SkipReason::BudgetMaxNormalizedChars,
+                        Some(bytes.len() as u64),
+                    );
+                    return Ok(std::ops::ControlFlow::Break(()));
                 }
-                if let Some(max_normalized_chars) = options.max_normalized_chars {
-                    let next_total = total_normalized_chars.saturating_add(normalized.chars.len());
-                    if next_total > max_normalized_chars {
-                        stats.skipped_budget_max_normalized_chars =
-                            stats.skipped_budget_max_normalized_chars.saturating_add(1);
-                        return Ok(std::ops::ControlFlow::Break(()));
-                    }
-                    total_normalized_chars = next_total;
+                total_normalized_chars = next_total;
+            }
+
+            let rel_path = match repo_file.abs_path.strip_prefix(&repo.root) {
+                Ok(rel) => rel.to_string_lossy().replace('\\', "/"),
+                Err(_) => {
+                    stats.skipped_relativize_failed =
+                        stats.skipped_relativize_failed.saturating_add(1);
+                    options.log_skip(
+                        &repo_file.abs_path,
+                        SkipReason::RelativizeFailed,
+                        Some(bytes.len() as u64),
+                    );
+                    return Ok(std::ops::ControlFlow::Continue(()));
                 }
+            };
+            files.push(NormalizedCodeFile {
+                repo_id: repo.id,
+                repo_label: Arc::clone(&repo.label),
+                rel_path: Arc::from(rel_path),
+                normalized: normalized.chars,
+                line_starts: normalized.line_starts,
+                test_ranges,
+                content_hash,
+            });
 
-                let rel_path = match repo_file.abs_path.strip_prefix(&repo.root) {
-                    Ok(rel) => rel.to_string_lossy().replace('\\', "/"),
-                    Err(_) => {
-                        stats.skipped_relativize_failed =
-                            stats.skipped_relativize_failed.saturating_add(1);
-                        return Ok(std::ops::ControlFlow::Continue(()));
-                    }
-                };
-                files.push(NormalizedCodeFile {
-                    repo_id: repo.id,
-                    repo_label: Arc::clone(&repo.label),
-                    rel_path: Arc::from(rel_path),
-                    normalized: normalized.chars,
-                    line_starts: normalized.line_starts,
-                });
-
-                Ok(std::ops::ControlFlow::Continue(()))
-            })?
-        {
-            break;
-        }
-    }
+            Ok(std::ops::ControlFlow::Continue(()))
+        })
+    })?;
 
     let views: Vec<NormalizedCodeFileView<'_>> = files
         .iter()
@@ -232,6 +303,74 @@ pub fn find_duplicate_code_spans_with_stats(
         })
         .collect();
 
-    let out = detect_duplicate_code_spans_winnowing(&views, options, &mut stats);
+    options.emit_progress(
+        ScanPhase::Detecting(ReportSection::CodeSpanDuplicates),
+        &stats,
+    );
+    let mut out = detect_duplicate_code_spans_winnowing(&views, options, &mut stats);
+    let test_regions: TestRegions<'_> = files
+        .iter()
+        .filter(|file| !file.test_ranges.is_empty())
+        .map(|file| {
+            (
+                (file.repo_id, file.rel_path.as_ref()),
+                file.test_ranges.as_slice(),
+            )
+        })
+        .collect();
+    mark_test_occurrences(
+        out.iter_mut().flat_map(|g| g.occurrences.iter_mut()),
+        &test_regions,
+    );
+    apply_test_filter_to_span_groups(&mut out, options.test_code_filter, options.cross_repo_only);
+    retain_span_groups_with_min_occurrences(&mut out, options.min_occurrences);
+    fill_span_group_line_stats(&mut out);
+    cap_span_group_occurrences(&mut out, options.max_occurrences_per_group);
+    let max_chars = options.preview_max_chars(CODE_SPAN_PREVIEW_CHARS);
+    let lines_preview = |occ: &DuplicateSpanOccurrence, max_chars: usize| {
+        preview_from_file_lines(
+            &repos[occ.repo_id].root.join(occ.path.as_ref()),
+            occ.start_line,
+            occ.end_line,
+            options.preview_context_lines,
+            max_chars,
+        )
+    };
+    if options.preview_context_lines > 0 && max_chars > 0 {
+        for group in &mut out {
+            let Some(occ) = group.occurrences.first() else {
+                continue;
+            };
+            group.preview = lines_preview(occ, max_chars);
+        }
+    }
+    fill_code_span_previews(&mut out, &views, max_chars);
+    // Every occurrence normalizes to the same text, so other repos are previewed from their
+    // source lines.
+    let lines_max_chars = options.preview_max_chars(LINE_PREVIEW_CHARS);
+    if lines_max_chars > 0 {
+        fill_occurrence_previews(&mut out, |occ| lines_preview(occ, lines_max_chars));
+    }
+    let content_hashes: HashMap<(usize, &str), u64> = files
+        .iter()
+        .map(|file| ((file.repo_id, file.rel_path.as_ref()), file.content_hash))
+        .collect();
+    let mut contents: HashMap<(usize, Arc<str>), Option<Vec<u8>>> = HashMap::new();
+    for occ in out.iter_mut().flat_map(|g| g.occurrences.iter_mut()) {
+        let bytes = contents
+            .entry((occ.repo_id, Arc::clone(&occ.path)))
+            .or_insert_with(|| {
+                let content_hash = *content_hashes.get(&(occ.repo_id, occ.path.as_ref()))?;
+                read_unchanged(
+                    &repos[occ.repo_id].root.join(occ.path.as_ref()),
+                    content_hash,
+                )
+            });
+        resolve_code_span_columns(occ, bytes.as_deref(), options.unicode_normalization);
+    }
+    if OutputPaths::is_active(options) {
+        OutputPaths::new(roots, options).rewrite_span_groups(&mut out);
+    }
+    options.emit_progress(ScanPhase::Finished, &stats);
     Ok(ScanOutcome { result: out, stats })
 }
@@ -0,0 +1,93 @@
+use std::io;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::scan::{
+    ReadTargets, Repo, apply_sample, read_repo_file_bytes, repo_label, scan_repos, validate_roots,
+    visit_repo_files,
+};
+use crate::types::{ScanOptions, ScanStats};
+use crate::util::fnv1a64;
+
+/// Hash of the corpus a scan of `roots` with `options` would read: the root-relative path and
+/// content of every file that passes the ignore rules, shard, sample and per-file read limits.
+///
+/// Two calls return the same value as long as no such file was added, removed, renamed or
+/// edited, so callers can reuse the result of an earlier scan (e.g. a cached report) keyed by
+/// this hash and the options. Files are read but not tokenized; progress and skip callbacks are
+/// not invoked.
+pub fn corpus_fingerprint(roots: &[PathBuf], options: &ScanOptions) -> io::Result<u64> {
+    let options = &ScanOptions {
+        progress: None,
+        skip_log: None,
+        ..options.clone()
+    };
+    let mut stats = ScanStats::default();
+    let roots = &validate_roots(roots, &mut stats)?;
+
+    let mut repos: Vec<Repo> = roots
+        .iter()
+        .enumerate()
+        .map(|(id, root)| Repo {
+            id,
+            root: root.clone(),
+            label: Arc::from(repo_label(root, id)),
+            sample_cutoff: None,
+        })
+        .collect();
+    apply_sample(&mut repos, options)?;
+
+    let canonical_roots = if options.follow_symlinks {
+        Some(
+            repos
+                .iter()
+                .map(|repo| repo.root.canonicalize())
+                .collect::<io::Result<Vec<_>>>()?,
+        )
+    } else {
+        None
+    };
+    let mut read_targets = ReadTargets::new();
+
+    // Per-file hashes are sorted before combining, so the walk order does not matter.
+    let mut entries: Vec<(usize, PathBuf, u64, usize)> = Vec::new();
+    scan_repos(&repos, &mut stats, |repo, stats| {
+        let canonical_root = canonical_roots
+            .as_ref()
+            .map(|roots| roots[repo.id].as_path());
+        visit_repo_files(repo, options, stats, |stats, repo_file| {
+            if let Some(bytes) = read_repo_file_bytes(
+                &repo_file,
+                repo.id,
+                canonical_root,
+                &mut read_targets,
+                options,
+                stats,
+            )? {
+                entries.push((
+                    repo.id,
+                    repo_file
+                        .abs_path
+                        .strip_prefix(&repo.root)
+                        .map_or_else(|_| repo_file.abs_path.clone(), Path::to_path_buf),
+                    fnv1a64(&bytes),
+                    bytes.len(),
+                ));
+            }
+            Ok(ControlFlow::Continue(()))
+        })
+    })?;
+    entries.sort_unstable();
+
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&(roots.len() as u64).to_le_bytes());
+    for (repo_id, path, hash, len) in &entries {
+        buf.extend_from_slice(&(*repo_id as u64).to_le_bytes());
+        buf.extend_from_slice(path.to_string_lossy().as_bytes());
+        buf.push(0);
+        buf.extend_from_slice(&hash.to_le_bytes());
+        buf.extend_from_slice(&(*len as u64).to_le_bytes());
+    }
+    Ok(fnv1a64(&buf))
+}
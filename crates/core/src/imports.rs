@@ -0,0 +1,428 @@
+use crate::language::detect_language;
+use crate::util::split_text_lines;
+
+/// Upper bound on the lines one import statement may span before it is given up on.
+const MAX_STATEMENT_LINES: usize = 64;
+
+/// How a language spells its imports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportSyntax {
+    /// `use a::{b, c};` (Rust).
+    RustUse,
+    /// `import { a } from "x";`, `require("x")` (JavaScript, TypeScript).
+    EsModule,
+    /// `import a.b`, `from a import (b, c)` (Python).
+    Python,
+    /// `import "x"`, `import ( ... )` (Go).
+    Go,
+    /// `import a.b.C;`, `import a.{B, C}` (Java, Kotlin, Scala, Swift).
+    Dotted,
+    /// `using A.B;` (C#).
+    Using,
+    /// `#include <x>` (C, C++).
+    Include,
+    /// `use A\B;`, `require_once "x";` (PHP).
+    Php,
+    /// `require "x"`, `require_relative "x"` (Ruby).
+    Ruby,
+}
+
+fn import_syntax_for_path(path: &str) -> Option<ImportSyntax> {
+    let syntax = match detect_language(path)?.name {
+        "rust" => ImportSyntax::RustUse,
+        "javascript" | "typescript" => ImportSyntax::EsModule,
+        "python" => ImportSyntax::Python,
+        "go" => ImportSyntax::Go,
+        "java" | "kotlin" | "scala" | "swift" => ImportSyntax::Dotted,
+        "csharp" => ImportSyntax::Using,
+        "c" | "cpp" => ImportSyntax::Include,
+        "php" => ImportSyntax::Php,
+        "ruby" => ImportSyntax::Ruby,
+        _ => return None,
+    };
+    Some(syntax)
+}
+
+/// Paths imported by the file at `path` (`std::io::Read`, `./store`, `os.path`, `<stdio.h>`
+/// becomes `stdio.h`), sorted and deduplicated.
+///
+/// A line-based heuristic over the language's import statements; brace groups are expanded
+/// (`use a::{b, c}` imports `a::b` and `a::c`) and aliases dropped. Files of unknown languages
+/// import nothing.
+pub(crate) fn imported_paths(text: &str, path: &str) -> Vec<String> {
+    let Some(syntax) = import_syntax_for_path(path) else {
+        return Vec::new();
+    };
+    let mut imports = Vec::new();
+    let mut lines = split_text_lines(text);
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        let Some((statement, terminator)) = statement_start(syntax, trimmed) else {
+            continue;
+        };
+        let mut statement = statement.to_string();
+        if let Some(terminators) = terminator {
+            for _ in 0..MAX_STATEMENT_LINES {
+                if terminators.iter().any(|end| statement.contains(end)) {
+                    break;
+                }
+                let Some(line) = lines.next() else {
+                    break;
+                };
+                statement.push(' ');
+                statement.push_str(line.trim());
+            }
+        }
+        collect_imports(syntax, &statement, &mut imports);
+    }
+    imports.sort_unstable();
+    imports.dedup();
+    imports
+}
+
+/// The start of an import statement on `line`, and the texts that end it when it may continue
+/// on the following lines.
+fn statement_start(
+    syntax: ImportSyntax,
+    line: &str,
+) -> Option<(&str, Option<&'static [&'static str]>)> {
+    match syntax {
+        ImportSyntax::RustUse => {
+            let rest = line
+                .strip_prefix("pub")
+                .map(|rest| {
+                    rest.split_once(')')
+                        .map_or(rest, |(_, after)| after)
+                        .trim_start()
+                })
+                .unwrap_or(line);
+            let rest = strip_keyword(rest, "use").or_else(|| {
+                strip_keyword(rest, "extern")
+                    .and_then(|rest| strip_keyword(rest.trim_start(), "crate"))
+            })?;
+            Some((rest, Some(&[";"])))
+        }
+        ImportSyntax::EsModule => {
+            let export = strip_keyword(line, "export").map(str::trim_start);
+            if strip_keyword(line, "import").is_some()
+                || line.starts_with("import{")
+                || export
+                    .is_some_and(|rest| rest.starts_with(['{', '*']) || rest.starts_with("type {"))
+            {
+                let ends: Option<&'static [&'static str]> =
+                    if line.contains(['\'', '"']) || line.ends_with(';') {
+                        None
+                    } else {
+                        Some(&["from", ";"])
+                    };
+                return Some((line, ends));
+            }
+            line.contains("require(").then_some((line, None))
+        }
+        ImportSyntax::Python => {
+            if let Some(rest) = strip_keyword(line, "import") {
+                return Some((rest, None));
+            }
+            strip_keyword(line, "from")?;
+            let ends: Option<&'static [&'static str]> =
+                (line.contains('(') && !line.contains(')')).then_some(&[")"]);
+            Some((line, ends))
+        }
+        ImportSyntax::Go => {
+            let rest = line.strip_prefix("import")?;
+            if !rest.starts_with([' ', '\t', '(']) {
+                return None;
+            }
+            let ends: Option<&'static [&'static str]> =
+                rest.trim_start().starts_with('(').then_some(&[")"]);
+            Some((rest, ends))
+        }
+        ImportSyntax::Dotted => {
+            let rest = line.strip_prefix("@testable ").unwrap_or(line);
+            strip_keyword(rest, "import").map(|rest| (rest, None))
+        }
+        ImportSyntax::Using => {
+            let rest = strip_keyword(line, "using")?;
+            let target = rest.trim_start();
+            let statement = !target.starts_with('(') && !target.starts_with("var ");
+            (statement && rest.contains(';')).then_some((rest, None))
+        }
+        ImportSyntax::Include => {
+            let rest = line.strip_prefix('#')?.trim_start();
+            rest.strip_prefix("include").map(|rest| (rest, None))
+        }
+        ImportSyntax::Php => {
+            if let Some(rest) = strip_keyword(line, "use") {
+                return Some((rest, Some(&[";"])));
+            }
+            ["require_once", "require", "include_once", "include"]
+                .iter()
+                .any(|keyword| line.starts_with(keyword))
+                .then_some((line, None))
+        }
+        ImportSyntax::Ruby => ["require_relative", "require"]
+            .iter()
+            .any(|keyword| line.starts_with(keyword))
+            .then_some((line, None)),
+    }
+}
+
+fn collect_imports(syntax: ImportSyntax, statement: &str, out: &mut Vec<String>) {
+    match syntax {
+        ImportSyntax::RustUse => {
+            let path = statement.split(';').next().unwrap_or_default();
+            expand_braces(&without_aliases(path), "::", out);
+        }
+        ImportSyntax::EsModule => {
+            let module = match statement.split_once("require(") {
+                Some((_, call)) if !statement.starts_with("import") => first_quoted(call),
+                _ if statement.starts_with("export") => statement
+                    .split_once("from")
+                    .and_then(|(_, rest)| first_quoted(rest)),
+                _ => first_quoted(statement),
+            };
+            out.extend(module.map(str::to_string));
+        }
+        ImportSyntax::Python => {
+            if let Some(rest) = strip_keyword(statement, "from") {
+                let Some((module, names)) = rest.split_once(" import ") else {
+                    return;
+                };
+                let module = module.trim();
+                let names = names.replace(['(', ')', '\\'], " ");
+                for name in names
+                    .split(',')
+                    .filter_map(|name| name.split_whitespace().next())
+                {
+                    let separator = if module.ends_with('.') { "" } else { "." };
+                    out.push(format!("{module}{separator}{name}"));
+                }
+            } else {
+                out.extend(
+                    statement
+                        .split(',')
+                        .filter_map(|name| name.split_whitespace().next())
+                        .map(str::to_string),
+                );
+            }
+        }
+        ImportSyntax::Go => out.extend(quoted_strings(statement).map(str::to_string)),
+        ImportSyntax::Dotted => {
+            let path = statement.split(';').next().unwrap_or_default();
+            let path = path.trim_start();
+            let path = [
+                "static ",
+                "struct ",
+                "class ",
+                "enum ",
+                "protocol ",
+                "func ",
+            ]
+            .iter()
+            .find_map(|kind| path.strip_prefix(kind))
+            .unwrap_or(path);
+            expand_braces(&without_aliases(path), ".", out);
+        }
+        ImportSyntax::Using => {
+            let path = statement.split(';').next().unwrap_or_default().trim();
+            let path = path.strip_prefix("static ").unwrap_or(path);
+            let path = path.split_once('=').map_or(path, |(_, target)| target);
+            out.push(path.split_whitespace().collect());
+        }
+        ImportSyntax::Include => {
+            let rest = statement.trim_start();
+            let header = match rest.strip_prefix('<') {
+                Some(angled) => angled.split_once('>').map(|(header, _)| header),
+                None => first_quoted(rest),
+            };
+            out.extend(header.map(str::to_string));
+        }
+        ImportSyntax::Php => {
+            if statement.starts_with("require") || statement.starts_with("include") {
+                out.extend(first_quoted(statement).map(str::to_string));
+                return;
+            }
+            let path = statement.split(';').next().unwrap_or_default();
+            let path = ["function ", "const "]
+                .iter()
+                .find_map(|kind| path.trim_start().strip_prefix(kind))
+                .unwrap_or(path);
+            expand_braces(&without_aliases(path), "\\", out);
+        }
+        ImportSyntax::Ruby => out.extend(first_quoted(statement).map(str::to_string)),
+    }
+}
+
+/// `line` after a leading `keyword` followed by whitespace.
+fn strip_keyword<'a>(line: &'a str, keyword: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(keyword)?;
+    rest.starts_with(char::is_whitespace).then_some(rest)
+}
+
+/// The contents of the `'...'`, `"..."` and `` `...` `` strings in `text`, skipping empty ones.
+fn quoted_strings(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        loop {
+            let start = rest.find(['\'', '"', '`'])?;
+            let quote = rest[start..].chars().next()?;
+            let body = &rest[start + 1..];
+            let end = body.find(quote)?;
+            rest = &body[end + 1..];
+            if end > 0 {
+                return Some(&body[..end]);
+            }
+        }
+    })
+}
+
+fn first_quoted(text: &str) -> Option<&str> {
+    quoted_strings(text).next()
+}
+
+/// `path` with whitespace and `as alias` renames removed.
+fn without_aliases(path: &str) -> String {
+    let mut spaced = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '{' | '}' | ',') {
+            spaced.extend([' ', c, ' ']);
+        } else {
+            spaced.push(c);
+        }
+    }
+    let mut out = String::new();
+    let mut words = spaced.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == "as" {
+            words.next();
+            continue;
+        }
+        out.push_str(word);
+    }
+    out
+}
+
+/// Push every path a brace group stands for: `a::{b, c::{d, e}}` is `a::b`, `a::c::d` and
+/// `a::c::e`; `self` refers to the prefix itself.
+fn expand_braces(path: &str, separator: &str, out: &mut Vec<String>) {
+    let Some(open) = path.find('{') else {
+        if !path.is_empty() {
+            out.push(path.to_string());
+        }
+        return;
+    };
+    let prefix = &path[..open];
+    let mut depth = 0usize;
+    let mut items = Vec::new();
+    let mut item_start = open + 1;
+    let mut close = path.len();
+    for (i, c) in path.char_indices().skip(open) {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    items.push(&path[item_start..i]);
+                    close = i;
+                    break;
+                }
+            }
+            ',' if depth == 1 => {
+                items.push(&path[item_start..i]);
+                item_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let suffix = path.get(close + 1..).unwrap_or_default();
+    for item in items.into_iter().filter(|item| !item.is_empty()) {
+        if item == "self" {
+            let own = prefix.strip_suffix(separator).unwrap_or(prefix);
+            expand_braces(&format!("{own}{suffix}"), separator, out);
+        } else {
+            expand_braces(&format!("{prefix}{item}{suffix}"), separator, out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_imports_per_language() {
+        let rust = "use std::io;\nuse std::collections::{HashMap, HashSet as Set};\n\
+                    pub(crate) use crate::types::{\n    self,\n    ScanOptions,\n};\n\
+                    fn f() {}\n";
+        assert_eq!(
+            imported_paths(rust, "lib.rs"),
+            [
+                "crate::types",
+                "crate::types::ScanOptions",
+                "std::collections::HashMap",
+                "std::collections::HashSet",
+                "std::io",
+            ]
+        );
+
+        let js = "import fs from 'fs';\nimport {\n  a,\n  b,\n} from \"./store\";\n\
+                  import './polyfill';\nconst path = require('path');\n\
+                  export { x } from './x';\nexport const y = 'not an import';\n";
+        assert_eq!(
+            imported_paths(js, "app.js"),
+            ["./polyfill", "./store", "./x", "fs", "path"]
+        );
+
+        let python = "import os, sys\nfrom os import path as p, sep\n\
+                      from .models import (\n    User,\n    Group,\n)\n";
+        assert_eq!(
+            imported_paths(python, "app.py"),
+            [
+                ".models.Group",
+                ".models.User",
+                "os",
+                "os.path",
+                "os.sep",
+                "sys"
+            ]
+        );
+
+        let go = "import \"fmt\"\nimport (\n\t\"os\"\n\tlog \"github.com/x/log\"\n)\n";
+        assert_eq!(
+            imported_paths(go, "main.go"),
+            ["fmt", "github.com/x/log", "os"]
+        );
+
+        let java = "import java.util.List;\nimport static java.lang.Math.max;\n";
+        assert_eq!(
+            imported_paths(java, "A.java"),
+            ["java.lang.Math.max", "java.util.List"]
+        );
+        assert_eq!(
+            imported_paths("import scala.collection.{Map, Seq}\n", "a.scala"),
+            ["scala.collection.Map", "scala.collection.Seq"]
+        );
+        assert_eq!(
+            imported_paths("using System.IO;\nusing (var f = open()) {}\n", "a.cs"),
+            ["System.IO"]
+        );
+        assert_eq!(
+            imported_paths("#include <stdio.h>\n# include \"util.h\"\n", "a.c"),
+            ["stdio.h", "util.h"]
+        );
+        assert_eq!(
+            imported_paths("require 'json'\nrequire_relative \"lib/x\"\n", "a.rb"),
+            ["json", "lib/x"]
+        );
+        assert!(imported_paths("import x\n", "notes.txt").is_empty());
+    }
+
+    #[test]
+    fn lone_carriage_returns_end_lines() {
+        let python = "import os\rfrom .models import (\r    User,\r)\rimport json\r";
+        assert_eq!(
+            imported_paths(python, "a.py"),
+            [".models.User", "json", "os"]
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::types::ScanOptions;
+
+/// Which comments the tokenizer drops for a language (see [`comment_syntax_for_path`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct CommentSyntax {
+    /// `// ...` to end of line.
+    pub(crate) slash_line: bool,
+    /// `/* ... */`.
+    pub(crate) block: bool,
+    /// Block comments nest (`/* /* */ */` is one comment), as in Rust, Swift, Kotlin and Scala.
+    pub(crate) nested_blocks: bool,
+    pub(crate) hash: HashComments,
+}
+
+/// How a `#` outside a string is treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HashComments {
+    /// `#` is code (Rust and PHP attributes, JavaScript private fields).
+    Code,
+    /// `#` as the first token of a line starts a preprocessor directive, dropped to end of line.
+    LineStart,
+    /// `#` anywhere starts a comment to end of line (Python, Ruby).
+    Anywhere,
+}
+
+const C_LIKE: CommentSyntax = CommentSyntax {
+    slash_line: true,
+    block: true,
+    nested_blocks: false,
+    hash: HashComments::Code,
+};
+const C_LIKE_NESTED: CommentSyntax = CommentSyntax {
+    nested_blocks: true,
+    ..C_LIKE
+};
+const C_PREPROCESSED: CommentSyntax = CommentSyntax {
+    hash: HashComments::LineStart,
+    ..C_LIKE
+};
+const HASH_ONLY: CommentSyntax = CommentSyntax {
+    slash_line: false,
+    block: false,
+    nested_blocks: false,
+    hash: HashComments::Anywhere,
+};
+
+/// Files of unknown languages: C-style comments plus `#` directives/comments at line start,
+/// which also covers shell-style comment lines.
+pub(crate) const DEFAULT_COMMENT_SYNTAX: CommentSyntax = C_PREPROCESSED;
+
+/// A language recognized by file extension, with its default `min_token_len` scale.
+#[derive(Debug)]
+pub(crate) struct Language {
+    pub(crate) name: &'static str,
+    extensions: &'static [&'static str],
+    /// Verbose languages need more tokens before a copy is worth reporting; terse ones fewer.
+    token_scale: f64,
+    pub(crate) comments: CommentSyntax,
+}
+
+const fn language(
+    name: &'static str,
+    extensions: &'static [&'static str],
+    token_scale: f64,
+    comments: CommentSyntax,
+) -> Language {
+    Language {
+        name,
+        extensions,
+        token_scale,
+        comments,
+    }
+}
+
+/// Languages with a per-language threshold. Files with other extensions use `min_token_len`.
+pub(crate) const LANGUAGES: &[Language] = &[
+    language(
+        "apl",
+        &["apl", "apla", "dyalog"],
+        0.4,
+        DEFAULT_COMMENT_SYNTAX,
+    ),
+    language("c", &["c", "h"], 1.0, C_PREPROCESSED),
+    language(
+        "cpp",
+        &["cc", "cpp", "cxx", "hh", "hpp", "hxx"],
+        1.0,
+        C_PREPROCESSED,
+    ),
+    language("csharp", &["cs"], 1.5, C_PREPROCESSED),
+    language("go", &["go"], 1.2, C_LIKE),
+    language("java", &["java"], 1.5, C_LIKE),
+    language("javascript", &["cjs", "js", "jsx", "mjs"], 1.0, C_LIKE),
+    language("kotlin", &["kt", "kts"], 1.2, C_LIKE_NESTED),
+    language("php", &["php"], 1.0, C_LIKE),
+    language("python", &["py", "pyi"], 0.8, HASH_ONLY),
+    language("ruby", &["rb"], 0.8, HASH_ONLY),
+    language("rust", &["rs"], 1.0, C_LIKE_NESTED),
+    language("scala", &["scala"], 1.0, C_LIKE_NESTED),
+    language("swift", &["swift"], 1.0, C_LIKE_NESTED),
+    language("typescript", &["cts", "mts", "ts", "tsx"], 1.0, C_LIKE),
+];
+
+/// Extensions of data files (fixtures, golden outputs) that report mode compares in
+/// `dataDuplicates` instead of running the code detectors on them.
+const DATA_EXTENSIONS: &[&str] = &[
+    "csv", "golden", "json", "jsonl", "ndjson", "snap", "tsv", "xml", "yaml", "yml",
+];
+
+pub(crate) fn language_by_name(name: &str) -> Option<&'static Language> {
+    LANGUAGES.iter().find(|language| language.name == name)
+}
+
+/// The lowercased extension of a `/`-separated path.
+fn extension_of(path: &str) -> Option<String> {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    let (_, extension) = file_name.rsplit_once('.')?;
+    Some(extension.to_ascii_lowercase())
+}
+
+/// Detect the language of a `/`-separated path from its extension (case-insensitive).
+pub(crate) fn detect_language(path: &str) -> Option<&'static Language> {
+    let extension = extension_of(path)?;
+    LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension.as_str()))
+}
+
+/// Whether the `/`-separated path is a data file (JSON, YAML, CSV, XML, snapshots) by its
+/// extension (case-insensitive).
+pub(crate) fn is_data_file(path: &str) -> bool {
+    extension_of(path).is_some_and(|extension| DATA_EXTENSIONS.contains(&extension.as_str()))
+}
+
+/// The comments the tokenizer drops in the file at `path`; [`DEFAULT_COMMENT_SYNTAX`] for
+/// unknown languages.
+pub(crate) fn comment_syntax_for_path(path: &str) -> CommentSyntax {
+    detect_language(path).map_or(DEFAULT_COMMENT_SYNTAX, |language| language.comments)
+}
+
+/// The `min_token_len` scale of the file at `path`: an override in
+/// [`ScanOptions::language_token_scales`] wins over the built-in scale of its language; `1.0`
+/// for unknown languages.
+pub(crate) fn token_scale_for_path(options: &ScanOptions, path: &str) -> f64 {
+    let Some(language) = detect_language(path) else {
+        return 1.0;
+    };
+    options
+        .language_token_scales
+        .iter()
+        .rev()
+        .find(|(name, _)| name == language.name)
+        .map_or(language.token_scale, |&(_, scale)| scale)
+}
+
+/// The `min_token_len` that applies to the file at `path`: the global value scaled by
+/// [`token_scale_for_path`], never below 1.
+pub(crate) fn min_token_len_for_path(options: &ScanOptions, path: &str) -> usize {
+    let min_token_len = options.min_token_len.max(1);
+    ((min_token_len as f64 * token_scale_for_path(options, path)).round() as usize).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_languages_by_extension() {
+        assert_eq!(
+            detect_language("src/Main.JAVA").map(|l| l.name),
+            Some("java")
+        );
+        assert_eq!(
+            detect_language("lib/app.test.tsx").map(|l| l.name),
+            Some("typescript")
+        );
+        assert!(detect_language("v1.2/Makefile").is_none());
+        assert!(detect_language("notes.txt").is_none());
+        assert!(is_data_file("tests/fixtures/Users.JSON"));
+        assert!(is_data_file("ci/deploy.yml"));
+        assert!(!is_data_file("v1.json/main.rs"));
+
+        let options = ScanOptions {
+            min_token_len: 50,
+            language_token_scales: vec![("python".to_string(), 0.5)],
+            ..ScanOptions::default()
+        };
+        assert_eq!(min_token_len_for_path(&options, "a.java"), 75);
+        assert_eq!(min_token_len_for_path(&options, "a.py"), 25);
+        assert_eq!(min_token_len_for_path(&options, "a.txt"), 50);
+    }
+}
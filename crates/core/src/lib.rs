@@ -1,23 +1,57 @@
 #![forbid(unsafe_code)]
 
+pub mod context;
+pub mod debug;
 mod dedupe;
 mod duplicates;
+mod fingerprint;
+mod imports;
+mod language;
+mod license;
+mod owners;
+mod parallel;
+mod progress;
 mod report;
 mod scan;
-mod tokenize;
+mod skip_log;
+mod skips;
+mod symbols;
+pub mod tokenize;
 mod types;
 mod util;
 mod winnowing;
 
+/// Version of this crate, for callers reporting what they were built against.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
 pub use duplicates::{
     find_duplicate_code_spans, find_duplicate_code_spans_with_stats, find_duplicate_files,
     find_duplicate_files_with_stats,
 };
 
-pub use report::{generate_duplication_report, generate_duplication_report_with_stats};
+pub use fingerprint::corpus_fingerprint;
+
+pub use owners::CodeOwners;
+
+pub use progress::{ProgressCallback, ScanPhase, ScanProgress};
+
+pub use skip_log::{SkipCallback, SkipReason, SkippedFile};
+
+pub use report::{
+    CorpusIndex, CorpusSnapshot, build_corpus_snapshot, build_corpus_snapshot_at_git_ref,
+    clone_genealogy, generate_duplication_report, generate_duplication_report_against_snapshot,
+    generate_duplication_report_from_snapshot, generate_duplication_report_with_stats,
+    generate_duplication_report_with_virtual_files, generate_resurrection_report,
+};
 
 pub use types::{
+    BudgetOrder, CloneCluster, CloneGenealogy, CloneLineage, ClusterMember,
     DEFAULT_MAX_FILE_SIZE_BYTES, DuplicateFile, DuplicateGroup, DuplicateSpanGroup,
-    DuplicateSpanOccurrence, DuplicationReport, ScanOptions, ScanOutcome, ScanStats,
-    SimilarityPair, default_ignore_dirs,
+    DuplicateSpanOccurrence, DuplicationPartner, DuplicationReport, FileCoverage, FileDuplication,
+    FileListing, FileSimilarity, GenealogyChange, GroupQuality, GroupSuppression,
+    ImportDuplicatePair, LicenseConflict, LicensedFile, LineRange, OccurrencePreview,
+    RepoScanStats, ReportRoot, ReportSample, ReportSection, ReportSort, ScanOptions, ScanOutcome,
+    ScanSample, ScanShard, ScanStats, SectionTotal, SimilarBlockCluster, SimilarityPair,
+    SimilarityWindows, SymbolDuplicateGroup, TestCodeFilter, UnicodeNormalization, VirtualFile,
+    default_exclude_patterns, default_ignore_dirs,
 };
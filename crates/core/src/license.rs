@@ -0,0 +1,164 @@
+use crate::util::split_text_lines;
+
+/// How far into a file a license header is looked for.
+const HEADER_MAX_LINES: usize = 60;
+
+const SPDX_TAG: &str = "SPDX-License-Identifier:";
+
+/// Phrases of common license texts, matched against the lowercased header with whitespace
+/// collapsed. Earlier entries win, so more specific texts (LGPL) precede the ones they contain
+/// (GPL).
+const FINGERPRINTS: &[(&str, License)] = &[
+    (
+        "licensed under the apache license, version 2.0",
+        License::Fixed("Apache-2.0"),
+    ),
+    ("gnu affero general public license", License::Gnu("AGPL")),
+    ("gnu lesser general public license", License::Gnu("LGPL")),
+    ("gnu library general public license", License::Gnu("LGPL")),
+    ("gnu general public license", License::Gnu("GPL")),
+    ("mozilla public license, v. 2.0", License::Fixed("MPL-2.0")),
+    ("eclipse public license v. 2.0", License::Fixed("EPL-2.0")),
+    (
+        "permission is hereby granted, free of charge",
+        License::Fixed("MIT"),
+    ),
+    (
+        "permission to use, copy, modify, and/or distribute this software for any purpose",
+        License::Fixed("ISC"),
+    ),
+    (
+        "redistribution and use in source and binary forms",
+        License::Bsd,
+    ),
+    (
+        "this is free and unencumbered software released into the public domain",
+        License::Fixed("Unlicense"),
+    ),
+];
+
+/// The SPDX identifier a fingerprint stands for.
+#[derive(Debug, Clone, Copy)]
+enum License {
+    Fixed(&'static str),
+    /// A GNU license family; the version comes from the header text.
+    Gnu(&'static str),
+    /// BSD-2-Clause, or BSD-3-Clause with the non-endorsement clause.
+    Bsd,
+}
+
+/// The license declared by the header of `text`, as an SPDX identifier or expression.
+///
+/// An `SPDX-License-Identifier:` tag wins; otherwise the first [`HEADER_MAX_LINES`] lines are
+/// matched against fingerprints of common license texts (MIT, Apache-2.0, the GNU family, BSD,
+/// MPL, ...). `None` when the header declares no recognizable license.
+pub(crate) fn detect_license(text: &str) -> Option<String> {
+    let header: Vec<&str> = split_text_lines(text).take(HEADER_MAX_LINES).collect();
+    if let Some(expression) = header.iter().find_map(|line| spdx_expression(line)) {
+        return Some(expression);
+    }
+
+    let mut normalized = String::new();
+    for word in header.iter().flat_map(|line| line.split_whitespace()) {
+        let word = word.trim_matches(|c: char| matches!(c, '*' | '#' | '/' | '-' | ';'));
+        if word.is_empty() {
+            continue;
+        }
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.push_str(&word.to_lowercase());
+    }
+
+    let (_, license) = FINGERPRINTS
+        .iter()
+        .find(|(phrase, _)| normalized.contains(phrase))?;
+    let id = match *license {
+        License::Fixed(id) => id.to_string(),
+        License::Gnu(family) => {
+            let version = if normalized.contains("version 3") {
+                "3.0"
+            } else if family == "LGPL" && normalized.contains("version 2.1") {
+                "2.1"
+            } else if family == "AGPL" {
+                "3.0"
+            } else {
+                "2.0"
+            };
+            let suffix = if normalized.contains("any later version") {
+                "-or-later"
+            } else {
+                "-only"
+            };
+            format!("{family}-{version}{suffix}")
+        }
+        License::Bsd if normalized.contains("neither the name") => "BSD-3-Clause".to_string(),
+        License::Bsd => "BSD-2-Clause".to_string(),
+    };
+    Some(id)
+}
+
+/// The expression of an `SPDX-License-Identifier:` tag on `line`, without trailing comment
+/// closers (`*/`, `-->`).
+fn spdx_expression(line: &str) -> Option<String> {
+    let (_, rest) = line.split_once(SPDX_TAG)?;
+    let rest = rest.trim();
+    let rest = rest
+        .strip_suffix("*/")
+        .or_else(|| rest.strip_suffix("-->"))
+        .unwrap_or(rest)
+        .trim();
+    (!rest.is_empty()).then(|| rest.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_spdx_tags_and_license_text_fingerprints() {
+        assert_eq!(
+            detect_license("// SPDX-License-Identifier: MIT OR Apache-2.0\nfn main() {}\n")
+                .as_deref(),
+            Some("MIT OR Apache-2.0")
+        );
+        assert_eq!(
+            detect_license("/* SPDX-License-Identifier: GPL-2.0-only */\n").as_deref(),
+            Some("GPL-2.0-only")
+        );
+
+        let apache = "/*\n * Licensed under the Apache License, Version 2.0 (the \"License\");\n\
+                      * you may not use this file except in compliance with the License.\n */\n";
+        assert_eq!(detect_license(apache).as_deref(), Some("Apache-2.0"));
+
+        let gpl = "# This program is free software: you can redistribute it and/or modify\n\
+                   # it under the terms of the GNU General Public License as published by\n\
+                   # the Free Software Foundation, either version 3 of the License, or\n\
+                   # (at your option) any later version.\n";
+        assert_eq!(detect_license(gpl).as_deref(), Some("GPL-3.0-or-later"));
+
+        let lgpl = "// under the terms of the GNU Lesser General Public\n\
+                    // License version 2.1 as published by the Free Software Foundation.\n";
+        assert_eq!(detect_license(lgpl).as_deref(), Some("LGPL-2.1-only"));
+
+        let bsd = "// Redistribution and use in source and binary forms, with or without\n\
+                   // modification, are permitted provided that ...\n\
+                   // Neither the name of the copyright holder nor ...\n";
+        assert_eq!(detect_license(bsd).as_deref(), Some("BSD-3-Clause"));
+
+        let mit = " * Permission is hereby granted, free of charge, to any person obtaining\n";
+        assert_eq!(detect_license(mit).as_deref(), Some("MIT"));
+
+        assert_eq!(detect_license("fn main() {}\n"), None);
+        let late = format!("{}// SPDX-License-Identifier: MIT\n", "\n".repeat(100));
+        assert_eq!(detect_license(&late), None);
+    }
+
+    #[test]
+    fn lone_carriage_returns_end_lines() {
+        let tagged = "// SPDX-License-Identifier: MIT\r// Copyright (c) Example\r";
+        assert_eq!(detect_license(tagged).as_deref(), Some("MIT"));
+        let late = format!("{}// SPDX-License-Identifier: MIT\r", "\r".repeat(100));
+        assert_eq!(detect_license(&late), None);
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+/// Owners declared by a CODEOWNERS file.
+///
+/// Each rule is a gitignore-style pattern followed by owners (`@user`, `@org/team` or an
+/// email address); as on GitHub and GitLab, the last rule matching a path wins, and a rule
+/// without owners leaves the paths it matches unowned.
+#[derive(Debug, Clone)]
+pub struct CodeOwners {
+    matcher: Gitignore,
+    /// Rule index and owners by pattern; a repeated pattern keeps its last rule.
+    rules: HashMap<String, (usize, Vec<String>)>,
+}
+
+impl CodeOwners {
+    /// Parse the text of a CODEOWNERS file.
+    ///
+    /// Comments, GitLab `[Section]` headers and lines GitHub would reject (negated or invalid
+    /// patterns) are skipped rather than failing the whole file.
+    pub fn parse(text: &str) -> Self {
+        let mut builder = GitignoreBuilder::new("");
+        let mut rules = HashMap::new();
+        for (index, line) in text.lines().enumerate() {
+            let mut fields = line
+                .split_whitespace()
+                .take_while(|field| !field.starts_with('#'));
+            let Some(pattern) = fields.next() else {
+                continue;
+            };
+            if is_section_header(pattern) {
+                continue;
+            }
+            if pattern.starts_with('!') || builder.add_line(None, pattern).is_err() {
+                continue;
+            }
+            let owners = fields.map(str::to_string).collect();
+            rules.insert(pattern.to_string(), (index, owners));
+        }
+        let matcher = builder.build().unwrap_or_else(|_| Gitignore::empty());
+        Self { matcher, rules }
+    }
+
+    /// The owners of `path` (`/`-separated, relative to the repository root); empty when no
+    /// rule matches it.
+    pub fn owners_of(&self, path: &str) -> &[String] {
+        // A rule matches the path or one of its directories. The matcher only reports the
+        // last rule matching at one level, so the last rule overall is the latest among the
+        // levels.
+        Path::new(path)
+            .ancestors()
+            .filter(|level| !level.as_os_str().is_empty())
+            .enumerate()
+            .filter_map(
+                |(depth, level)| match self.matcher.matched(level, depth > 0) {
+                    Match::Ignore(glob) => self.rules.get(glob.original()),
+                    Match::None | Match::Whitelist(_) => None,
+                },
+            )
+            .max_by_key(|(index, _)| *index)
+            .map_or(&[], |(_, owners)| owners.as_slice())
+    }
+}
+
+/// GitLab section headers: `[Name]`, `^[Name]` (optional) or `[Name][2]` (approvals), with
+/// optional default owners after the brackets.
+fn is_section_header(line: &str) -> bool {
+    line.strip_prefix('^').unwrap_or(line).starts_with('[')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_matching_rule_wins() {
+        let owners = CodeOwners::parse(
+            "# default owners\n\
+             *       @org/core\n\
+             *.md    docs@example.com\n\
+             /web/   @org/frontend @alice  # UI\n\
+             [Generated]\n\
+             web/gen/\n\
+             !web/keep.ts @nobody\n",
+        );
+        assert_eq!(owners.owners_of("src/lib.rs"), ["@org/core"]);
+        assert_eq!(owners.owners_of("guide/intro.md"), ["docs@example.com"]);
+        assert_eq!(
+            owners.owners_of("web/app/main.ts"),
+            ["@org/frontend", "@alice"]
+        );
+        assert!(owners.owners_of("web/gen/api.ts").is_empty());
+        assert_eq!(owners.owners_of("web/keep.ts"), ["@org/frontend", "@alice"]);
+        assert_eq!(owners.owners_of("app/web/x.ts"), ["@org/core"]);
+    }
+
+    #[test]
+    fn empty_file_owns_nothing() {
+        assert!(CodeOwners::parse("").owners_of("a.rs").is_empty());
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+pub(crate) type Job<'a, T> = Box<dyn FnOnce() -> T + Send + 'a>;
+
+/// Run `jobs` on up to `threads` scoped worker threads.
+///
+/// Results come back in job order, so callers see the same output whatever the thread count or
+/// scheduling. With `threads <= 1` (or a single job) everything runs on the calling thread.
+pub(crate) fn run_jobs<T: Send>(threads: usize, jobs: Vec<Job<'_, T>>) -> Vec<T> {
+    let workers = threads.min(jobs.len());
+    if workers <= 1 {
+        return jobs.into_iter().map(|job| job()).collect();
+    }
+
+    let mut results: Vec<Option<T>> = Vec::with_capacity(jobs.len());
+    results.resize_with(jobs.len(), || None);
+    let results = Mutex::new(results);
+    let queue = Mutex::new(jobs.into_iter().enumerate());
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                loop {
+                    let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                    let Some((index, job)) = next else {
+                        break;
+                    };
+                    let result = job();
+                    results.lock().unwrap_or_else(PoisonError::into_inner)[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    // A panicking job re-panics out of `thread::scope`, so every slot is filled here.
+    results
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner)
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Resolve [`crate::ScanOptions::threads`]: `0` means one thread per available CPU.
+pub(crate) fn resolve_threads(threads: usize) -> usize {
+    if threads > 0 {
+        return threads;
+    }
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::types::{ReportSection, ScanStats};
+
+/// What a scan is currently doing (see [`ScanProgress`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanPhase {
+    /// Walking roots and reading files; emitted after every visited file.
+    Scanning,
+    /// Running the detector for one report section.
+    Detecting(ReportSection),
+    /// The scan finished; emitted once per API call.
+    Finished,
+}
+
+impl ScanPhase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanPhase::Scanning => "scan",
+            ScanPhase::Detecting(_) => "detect",
+            ScanPhase::Finished => "done",
+        }
+    }
+}
+
+/// Snapshot of scan progress passed to a [`ProgressCallback`].
+///
+/// The total number of files is not known up front (roots are walked lazily), so
+/// `candidate_files` is the number discovered so far.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ScanProgress {
+    pub phase: ScanPhase,
+    pub candidate_files: u64,
+    pub scanned_files: u64,
+    pub scanned_bytes: u64,
+}
+
+/// Progress hook stored in [`crate::ScanOptions::progress`].
+///
+/// Called synchronously on the scanning thread (detector events may come from worker threads
+/// when [`crate::ScanOptions::threads`] is not 1); keep it cheap (throttle on the receiving side).
+#[derive(Clone)]
+pub struct ProgressCallback(Arc<dyn Fn(&ScanProgress) + Send + Sync>);
+
+impl ProgressCallback {
+    pub fn new(callback: impl Fn(&ScanProgress) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn emit(&self, phase: ScanPhase, stats: &ScanStats) {
+        (self.0)(&ScanProgress {
+            phase,
+            candidate_files: stats.candidate_files,
+            scanned_files: stats.scanned_files,
+            scanned_bytes: stats.scanned_bytes,
+        });
+    }
+}
+
+impl fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressCallback(..)")
+    }
+}
@@ -0,0 +1,71 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::parallel::{Job, resolve_threads, run_jobs};
+use crate::scan::{is_git_work_tree, line_range_last_modified};
+use crate::types::{DuplicateSpanGroup, ScanOptions};
+
+/// Groups per section dated for [`ScanOptions::occurrence_ages`], taken in `--sort` order; the
+/// rest stay undated and keep their place after the dated ones.
+const MAX_DATED_GROUPS: usize = 500;
+
+/// Dates span occurrences under the roots that are Git checkouts, for
+/// [`ScanOptions::occurrence_ages`].
+pub(super) struct OccurrenceDater<'a> {
+    roots: &'a [PathBuf],
+    in_git: Vec<bool>,
+    threads: usize,
+}
+
+impl<'a> OccurrenceDater<'a> {
+    /// `None` unless [`ScanOptions::occurrence_ages`] is set.
+    pub(super) fn new(roots: &'a [PathBuf], options: &ScanOptions) -> Option<Self> {
+        options.occurrence_ages.then(|| Self {
+            roots,
+            in_git: roots.iter().map(|root| is_git_work_tree(root)).collect(),
+            threads: resolve_threads(options.threads),
+        })
+    }
+
+    /// Date the occurrences of the first [`MAX_DATED_GROUPS`] of the sorted `groups` by the
+    /// last commit touching their lines, then list the groups by
+    /// [`DuplicateSpanGroup::age_spread`], widest first. The sort is stable, so groups with
+    /// equal (or no) spread keep their order.
+    ///
+    /// Runs before the section is truncated, so a drifted group past the report page still
+    /// moves up into it.
+    pub(super) fn rank_by_age_spread(&self, groups: &mut [DuplicateSpanGroup]) {
+        let dated = groups.len().min(MAX_DATED_GROUPS);
+
+        type Key = (usize, Arc<str>, u32, u32);
+        let mut keys: Vec<Key> = groups[..dated]
+            .iter()
+            .flat_map(|group| &group.occurrences)
+            .filter(|o| self.in_git.get(o.repo_id).copied().unwrap_or(false))
+            .map(|o| (o.repo_id, o.path.clone(), o.start_line, o.end_line))
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        let jobs: Vec<Job<'_, Option<u64>>> = keys
+            .iter()
+            .map(|(repo_id, path, start_line, end_line)| {
+                let root = &self.roots[*repo_id];
+                Box::new(move || line_range_last_modified(root, path, *start_line, *end_line))
+                    as Job<'_, Option<u64>>
+            })
+            .collect();
+        let dates: HashMap<&Key, Option<u64>> =
+            keys.iter().zip(run_jobs(self.threads, jobs)).collect();
+
+        for group in &mut groups[..dated] {
+            for o in &mut group.occurrences {
+                let key = (o.repo_id, o.path.clone(), o.start_line, o.end_line);
+                o.last_modified = dates.get(&key).copied().flatten();
+            }
+        }
+        groups.sort_by_key(|group| Reverse(group.age_spread()));
+    }
+}
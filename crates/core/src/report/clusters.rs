@@ -0,0 +1,364 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use crate::types::{
+    CloneCluster, ClusterMember, DuplicateSpanOccurrence, DuplicationReport, FileCoverage,
+    LineRange, ReportSection, ScanOptions, ScanStats, SimilarBlockCluster,
+};
+use crate::util::LINE_PREVIEW_CHARS;
+
+use super::ScannedTextFile;
+use super::coverage::merge_line_ranges;
+use super::util::{SourceFiles, occurrence_sort_key};
+
+/// A region of one file covered by a report group.
+struct Place {
+    repo_id: usize,
+    path: Arc<str>,
+    range: LineRange,
+}
+
+/// Group the report's groups into [`CloneCluster`]s: two groups end up in the same cluster
+/// when an occurrence of one overlaps an occurrence of the other, when both span exactly the
+/// same set of files, or when they share a content hash.
+pub(super) fn cluster_report_groups(
+    repo_labels: &[Arc<str>],
+    files: &[ScannedTextFile],
+    report: &DuplicationReport,
+) -> Vec<CloneCluster> {
+    let line_counts: HashMap<(usize, &str), u32> = files
+        .iter()
+        .map(|file| ((file.repo_id, file.path.as_ref()), file.line_count))
+        .collect();
+    let (members, places, hashes) = collect_members(report, &line_counts);
+    let mut sets = DisjointSets::new(members.len());
+
+    // Overlapping regions: sweep each file's ranges in start order and join every range that
+    // starts before the furthest end seen so far with the group that reached it.
+    let mut by_file: HashMap<(usize, &str), Vec<(LineRange, usize)>> = HashMap::new();
+    for (member, member_places) in places.iter().enumerate() {
+        for place in member_places {
+            by_file
+                .entry((place.repo_id, place.path.as_ref()))
+                .or_default()
+                .push((place.range, member));
+        }
+    }
+    for ranges in by_file.values_mut() {
+        ranges.sort();
+        let mut reach: Option<(u32, usize)> = None;
+        for &(range, member) in ranges.iter() {
+            match reach {
+                Some((end, other)) if range.start_line <= end => {
+                    sets.union(member, other);
+                    if range.end_line > end {
+                        reach = Some((range.end_line, member));
+                    }
+                }
+                _ => reach = Some((range.end_line, member)),
+            }
+        }
+    }
+
+    // Same file set: duplicates between the same files are extracted together.
+    let mut by_file_set: HashMap<Vec<(usize, &str)>, usize> = HashMap::new();
+    for (member, member_places) in places.iter().enumerate() {
+        let mut file_set: Vec<(usize, &str)> = member_places
+            .iter()
+            .map(|place| (place.repo_id, place.path.as_ref()))
+            .collect();
+        file_set.sort_unstable();
+        file_set.dedup();
+        if file_set.len() < 2 {
+            continue;
+        }
+        match by_file_set.get(&file_set) {
+            Some(&other) => sets.union(member, other),
+            None => {
+                by_file_set.insert(file_set, member);
+            }
+        }
+    }
+
+    // Same content hash, e.g. a file duplicate and a span detector reporting the same text.
+    let mut by_hash: HashMap<u64, usize> = HashMap::new();
+    for (member, hash) in hashes.iter().enumerate() {
+        let Some(hash) = *hash else {
+            continue;
+        };
+        match by_hash.get(&hash) {
+            Some(&other) => sets.union(member, other),
+            None => {
+                by_hash.insert(hash, member);
+            }
+        }
+    }
+
+    let mut components: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for member in 0..members.len() {
+        components
+            .entry(sets.find(member))
+            .or_default()
+            .push(member);
+    }
+    let mut clusters: Vec<CloneCluster> = components
+        .into_values()
+        .map(|component| {
+            let mut ranges: BTreeMap<(usize, Arc<str>), Vec<LineRange>> = BTreeMap::new();
+            for &member in &component {
+                for place in &places[member] {
+                    ranges
+                        .entry((place.repo_id, Arc::clone(&place.path)))
+                        .or_default()
+                        .push(place.range);
+                }
+            }
+            let files = ranges
+                .into_iter()
+                .map(|((repo_id, path), ranges)| FileCoverage {
+                    repo_id,
+                    repo_label: Arc::clone(&repo_labels[repo_id]),
+                    total_lines: line_counts
+                        .get(&(repo_id, path.as_ref()))
+                        .copied()
+                        .unwrap_or(0),
+                    path,
+                    covered_ranges: merge_line_ranges(ranges),
+                })
+                .collect();
+            CloneCluster {
+                members: component
+                    .into_iter()
+                    .map(|member| members[member])
+                    .collect(),
+                files,
+            }
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.affected_lines()
+            .cmp(&a.affected_lines())
+            .then_with(|| a.members.cmp(&b.members))
+    });
+    clusters
+}
+
+/// Join the pairs of each similarity section into [`SimilarBlockCluster`]s. Blocks are the
+/// same when their locations are, so a block in nine pairs becomes one node with nine edges.
+pub(super) fn cluster_similarity_pairs(
+    files: &[ScannedTextFile],
+    report: &DuplicationReport,
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) -> Vec<SimilarBlockCluster> {
+    let max_chars = options.preview_max_chars(LINE_PREVIEW_CHARS);
+    let mut sources = SourceFiles::new(files);
+    let mut clusters = Vec::new();
+    for (section, pairs) in [
+        (
+            ReportSection::SimilarBlocksMinhash,
+            &report.similar_blocks_minhash,
+        ),
+        (
+            ReportSection::SimilarBlocksSimhash,
+            &report.similar_blocks_simhash,
+        ),
+    ] {
+        let mut blocks: Vec<&DuplicateSpanOccurrence> = Vec::new();
+        let mut ids = HashMap::new();
+        let mut edges = Vec::with_capacity(pairs.len());
+        for pair in pairs {
+            let [a, b] = [&pair.a, &pair.b].map(|occ| {
+                *ids.entry(occurrence_sort_key(occ)).or_insert_with(|| {
+                    blocks.push(occ);
+                    blocks.len() - 1
+                })
+            });
+            edges.push((a, b));
+        }
+
+        let mut sets = DisjointSets::new(blocks.len());
+        let mut degree = vec![0usize; blocks.len()];
+        for &(a, b) in &edges {
+            sets.union(a, b);
+            degree[a] += 1;
+            degree[b] += 1;
+        }
+        let mut components: BTreeMap<usize, (Vec<usize>, Vec<usize>)> = BTreeMap::new();
+        for block in 0..blocks.len() {
+            components
+                .entry(sets.find(block))
+                .or_default()
+                .0
+                .push(block);
+        }
+        for (index, &(a, _)) in edges.iter().enumerate() {
+            components.entry(sets.find(a)).or_default().1.push(index);
+        }
+
+        let mut section_clusters: Vec<SimilarBlockCluster> = components
+            .into_values()
+            .map(|(mut members, pair_indices)| {
+                members.sort_by(|&x, &y| {
+                    degree[y].cmp(&degree[x]).then_with(|| {
+                        occurrence_sort_key(blocks[x]).cmp(&occurrence_sort_key(blocks[y]))
+                    })
+                });
+                let scores = pair_indices.iter().map(|&index| pairs[index].score);
+                let preview = if max_chars == 0 {
+                    String::new()
+                } else {
+                    sources.preview(blocks[members[0]], options, max_chars, stats)
+                };
+                SimilarBlockCluster {
+                    section,
+                    blocks: members.iter().map(|&block| blocks[block].clone()).collect(),
+                    preview,
+                    min_score: scores.clone().fold(f64::INFINITY, f64::min),
+                    max_score: scores.fold(f64::NEG_INFINITY, f64::max),
+                    pairs: pair_indices,
+                }
+            })
+            .collect();
+        section_clusters.sort_by(|a, b| {
+            b.blocks
+                .len()
+                .cmp(&a.blocks.len())
+                .then_with(|| a.pairs.cmp(&b.pairs))
+        });
+        clusters.extend(section_clusters);
+    }
+    clusters
+}
+
+/// Every report group with the regions it covers and its content hash, in section order.
+fn collect_members(
+    report: &DuplicationReport,
+    line_counts: &HashMap<(usize, &str), u32>,
+) -> (Vec<ClusterMember>, Vec<Vec<Place>>, Vec<Option<u64>>) {
+    let mut members = Vec::new();
+    let mut places = Vec::new();
+    let mut hashes = Vec::new();
+    let mut push = |section, index, member_places: Vec<Place>, hash| {
+        members.push(ClusterMember { section, index });
+        places.push(member_places);
+        hashes.push(hash);
+    };
+
+    for (index, group) in report.file_duplicates.iter().enumerate() {
+        let member_places = group
+            .files
+            .iter()
+            .filter_map(|file| {
+                let total_lines = *line_counts.get(&(file.repo_id, file.path.as_ref()))?;
+                (total_lines > 0).then(|| Place {
+                    repo_id: file.repo_id,
+                    path: Arc::clone(&file.path),
+                    range: LineRange {
+                        start_line: 1,
+                        end_line: total_lines,
+                    },
+                })
+            })
+            .collect();
+        push(
+            ReportSection::FileDuplicates,
+            index,
+            member_places,
+            Some(group.content_hash),
+        );
+    }
+
+    for (section, groups) in [
+        (
+            ReportSection::CodeSpanDuplicates,
+            &report.code_span_duplicates,
+        ),
+        (
+            ReportSection::LineSpanDuplicates,
+            &report.line_span_duplicates,
+        ),
+        (
+            ReportSection::TokenSpanDuplicates,
+            &report.token_span_duplicates,
+        ),
+        (ReportSection::BlockDuplicates, &report.block_duplicates),
+        (
+            ReportSection::AstSubtreeDuplicates,
+            &report.ast_subtree_duplicates,
+        ),
+    ] {
+        for (index, group) in groups.iter().enumerate() {
+            let member_places = group
+                .occurrences
+                .iter()
+                .map(|occ| Place {
+                    repo_id: occ.repo_id,
+                    path: Arc::clone(&occ.path),
+                    range: LineRange {
+                        start_line: occ.start_line,
+                        end_line: occ.end_line,
+                    },
+                })
+                .collect();
+            push(section, index, member_places, Some(group.content_hash));
+        }
+    }
+
+    for (section, pairs) in [
+        (
+            ReportSection::SimilarBlocksMinhash,
+            &report.similar_blocks_minhash,
+        ),
+        (
+            ReportSection::SimilarBlocksSimhash,
+            &report.similar_blocks_simhash,
+        ),
+    ] {
+        for (index, pair) in pairs.iter().enumerate() {
+            let member_places = [&pair.a, &pair.b]
+                .into_iter()
+                .map(|occ| Place {
+                    repo_id: occ.repo_id,
+                    path: Arc::clone(&occ.path),
+                    range: LineRange {
+                        start_line: occ.start_line,
+                        end_line: occ.end_line,
+                    },
+                })
+                .collect();
+            push(section, index, member_places, None);
+        }
+    }
+
+    (members, places, hashes)
+}
+
+/// Union-find over member indices, with path halving.
+struct DisjointSets {
+    parent: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut item: usize) -> usize {
+        while self.parent[item] != item {
+            self.parent[item] = self.parent[self.parent[item]];
+            item = self.parent[item];
+        }
+        item
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a != b {
+            // Keep the smaller index as the root so components come out in member order.
+            self.parent[a.max(b)] = a.min(b);
+        }
+    }
+}
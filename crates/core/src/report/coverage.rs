@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::types::{DuplicateGroup, DuplicateSpanGroup, FileCoverage, LineRange};
+
+use super::ScannedTextFile;
+
+/// Lines covered by the file-duplicate and span groups, recorded as each section is finalized
+/// (after its filters, before `max_report_items` truncation, paging and occurrence caps), so
+/// coverage does not depend on which groups end up listed.
+#[derive(Debug, Default)]
+pub(super) struct CoveredLines {
+    /// Files of file-duplicate groups, covered in full.
+    whole_files: Vec<(usize, Arc<str>)>,
+    spans: Vec<(usize, Arc<str>, LineRange)>,
+}
+
+impl CoveredLines {
+    pub(super) fn add_file_groups(&mut self, groups: &[DuplicateGroup]) {
+        self.whole_files.extend(
+            groups
+                .iter()
+                .flat_map(|group| &group.files)
+                .map(|file| (file.repo_id, Arc::clone(&file.path))),
+        );
+    }
+
+    pub(super) fn add_span_groups(&mut self, groups: &[DuplicateSpanGroup]) {
+        self.spans.extend(
+            groups
+                .iter()
+                .flat_map(|group| &group.occurrences)
+                .filter(|occ| occ.start_line > 0 && occ.end_line >= occ.start_line)
+                .map(|occ| {
+                    (
+                        occ.repo_id,
+                        Arc::clone(&occ.path),
+                        LineRange {
+                            start_line: occ.start_line,
+                            end_line: occ.end_line,
+                        },
+                    )
+                }),
+        );
+    }
+
+    pub(super) fn extend(&mut self, other: CoveredLines) {
+        self.whole_files.extend(other.whole_files);
+        self.spans.extend(other.spans);
+    }
+}
+
+pub(super) fn compute_file_coverage(
+    repo_labels: &[Arc<str>],
+    files: &[ScannedTextFile],
+    covered: CoveredLines,
+) -> Vec<FileCoverage> {
+    let mut by_path: HashMap<(usize, &str), usize> = HashMap::new();
+    for (file_id, file) in files.iter().enumerate() {
+        by_path.insert((file.repo_id, file.path.as_ref()), file_id);
+    }
+
+    let mut ranges: HashMap<usize, Vec<LineRange>> = HashMap::new();
+    for (repo_id, path) in &covered.whole_files {
+        let Some(&file_id) = by_path.get(&(*repo_id, path.as_ref())) else {
+            continue;
+        };
+        let total_lines = files[file_id].line_count;
+        if total_lines > 0 {
+            ranges.entry(file_id).or_default().push(LineRange {
+                start_line: 1,
+                end_line: total_lines,
+            });
+        }
+    }
+    for (repo_id, path, range) in covered.spans {
+        if let Some(&file_id) = by_path.get(&(repo_id, path.as_ref())) {
+            ranges.entry(file_id).or_default().push(range);
+        }
+    }
+
+    let mut out: Vec<FileCoverage> = ranges
+        .into_iter()
+        .map(|(file_id, ranges)| {
+            let file = &files[file_id];
+            FileCoverage {
+                repo_id: file.repo_id,
+                repo_label: Arc::clone(&repo_labels[file.repo_id]),
+                path: Arc::clone(&file.path),
+                total_lines: file.line_count,
+                covered_ranges: merge_line_ranges(ranges),
+            }
+        })
+        .collect();
+    out.sort_by(|a, b| (a.repo_id, a.path.as_ref()).cmp(&(b.repo_id, b.path.as_ref())));
+    out
+}
+
+/// Sort and merge overlapping or adjacent ranges.
+pub(super) fn merge_line_ranges(mut ranges: Vec<LineRange>) -> Vec<LineRange> {
+    ranges.sort();
+    let mut out: Vec<LineRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        if let Some(last) = out.last_mut()
+            && range.start_line <= last.end_line.saturating_add(1)
+        {
+            last.end_line = last.end_line.max(range.end_line);
+            continue;
+        }
+        out.push(range);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn r(start_line: u32, end_line: u32) -> LineRange {
+        LineRange {
+            start_line,
+            end_line,
+        }
+    }
+
+    #[test]
+    fn merge_line_ranges_merges_overlapping_and_adjacent() {
+        let merged = merge_line_ranges(vec![r(10, 12), r(1, 3), r(4, 5), r(11, 20), r(30, 30)]);
+        assert_eq!(merged, vec![r(1, 5), r(10, 20), r(30, 30)]);
+    }
+}
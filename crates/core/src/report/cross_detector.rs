@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::types::{DuplicateSpanGroup, DuplicationReport, ReportSection, ScanOptions};
+
+use super::util::take_report_page;
+
+type OccurrenceKey = (Vec<(usize, Arc<str>, u32, u32)>, usize);
+
+/// Fold span groups whose occurrence sets are identical into the most specific section, then
+/// take each section's report page and subtract the folded groups from its total.
+///
+/// Sections are visited from most to least specific; the first group seen for a given set of
+/// occurrences is kept and later matches are removed and recorded in `also_detected_by`. Under
+/// [`ScanOptions::deduplicate_across_detectors`] the span detectors leave their sections
+/// unpaged, so folding sees every group and each page is filled with the groups that remain.
+pub(super) fn deduplicate_across_detectors(report: &mut DuplicationReport, options: &ScanOptions) {
+    let mut kept: HashMap<OccurrenceKey, (ReportSection, usize)> = HashMap::new();
+    let mut folded = Vec::new();
+
+    for section in [
+        ReportSection::AstSubtreeDuplicates,
+        ReportSection::BlockDuplicates,
+        ReportSection::TokenSpanDuplicates,
+        ReportSection::LineSpanDuplicates,
+        ReportSection::CodeSpanDuplicates,
+    ] {
+        let groups = std::mem::take(section_groups(report, section));
+        let before = groups.len();
+        let mut retained = Vec::with_capacity(groups.len());
+        for group in groups {
+            let key = occurrence_key(&group);
+            if let Some(&(kept_section, idx)) = kept.get(&key) {
+                let target = &mut section_groups(report, kept_section)[idx];
+                if !target.also_detected_by.contains(&section) {
+                    target.also_detected_by.push(section);
+                }
+                continue;
+            }
+            kept.insert(key, (section, retained.len()));
+            retained.push(group);
+        }
+        folded.push((section, before - retained.len()));
+        *section_groups(report, section) = retained;
+    }
+
+    for (section, folded) in folded {
+        let groups = section_groups(report, section);
+        take_report_page(groups, section, options);
+        let listed = groups.len();
+        if let Some(total) = report
+            .section_totals
+            .iter_mut()
+            .find(|total| total.section == section)
+        {
+            total.total_groups = total.total_groups.saturating_sub(folded);
+            total.truncated = total.degraded || listed < total.total_groups;
+        }
+    }
+}
+
+fn occurrence_key(group: &DuplicateSpanGroup) -> OccurrenceKey {
+    let mut occurrences: Vec<_> = group
+        .occurrences
+        .iter()
+        .map(|o| (o.repo_id, Arc::clone(&o.path), o.start_line, o.end_line))
+        .collect();
+    occurrences.sort();
+    (occurrences, group.truncated_occurrences)
+}
+
+fn section_groups(
+    report: &mut DuplicationReport,
+    section: ReportSection,
+) -> &mut Vec<DuplicateSpanGroup> {
+    match section {
+        ReportSection::CodeSpanDuplicates => &mut report.code_span_duplicates,
+        ReportSection::LineSpanDuplicates => &mut report.line_span_duplicates,
+        ReportSection::TokenSpanDuplicates => &mut report.token_span_duplicates,
+        ReportSection::BlockDuplicates => &mut report.block_duplicates,
+        ReportSection::AstSubtreeDuplicates => &mut report.ast_subtree_duplicates,
+        ReportSection::FileDuplicates
+        | ReportSection::SimilarBlocksMinhash
+        | ReportSection::SimilarBlocksSimhash
+        | ReportSection::SymbolDuplicates
+        | ReportSection::ImportDuplicates
+        | ReportSection::DataDuplicates => {
+            unreachable!("not a span group section: {}", section.as_str())
+        }
+    }
+}
@@ -1,11 +1,16 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use crate::types::{DuplicateSpanGroup, DuplicateSpanOccurrence, ScanOptions};
+use crate::language::min_token_len_for_path;
+use crate::types::{
+    DuplicateSpanGroup, DuplicateSpanOccurrence, ReportSection, ScanOptions, ScanStats,
+};
 use crate::util::fnv1a64_u32;
 
-use super::super::ScannedTextFile;
-use super::super::util::{fill_missing_previews_from_files, sort_span_groups_for_report};
+use super::super::ages::OccurrenceDater;
+use super::super::coverage::CoveredLines;
+use super::super::util::{fill_missing_previews_from_files, finalize_span_groups_for_report};
+use super::super::{ScannedTextFile, Section};
 use super::repo_label_arc;
 
 #[derive(Debug, Clone, Copy)]
@@ -19,7 +24,6 @@ struct SampleRef {
 struct ReportSpanGroupBuilder {
     content_hash: u64,
     normalized_len: usize,
-    preview: String,
     occurrences: Vec<DuplicateSpanOccurrence>,
     occurrence_keys: HashSet<(usize, usize)>,
     repo_ids: HashSet<usize>,
@@ -59,8 +63,14 @@ fn finalize_report_span_groups(
         out.push(DuplicateSpanGroup {
             content_hash: builder.content_hash,
             normalized_len: builder.normalized_len,
-            preview: builder.preview,
+            preview: String::new(),
             occurrences: builder.occurrences,
+            truncated_occurrences: 0,
+            duplicated_lines: 0,
+            potential_savings_lines: 0,
+            occurrence_previews: Vec::new(),
+            also_detected_by: Vec::new(),
+            quality: None,
         });
     }
     out
@@ -70,12 +80,14 @@ pub(in crate::report) fn detect_duplicate_blocks(
     repo_labels: &[Arc<str>],
     files: &[ScannedTextFile],
     options: &ScanOptions,
-) -> Vec<DuplicateSpanGroup> {
-    let min_token_len = options.min_token_len.max(1);
-
+    stats: &mut ScanStats,
+    covered: &mut CoveredLines,
+    ages: Option<&OccurrenceDater<'_>>,
+) -> Section<DuplicateSpanGroup> {
     let mut groups: HashMap<(u64, usize), Vec<ReportSpanGroupBuilder>> = HashMap::new();
 
     for (file_id, file) in files.iter().enumerate() {
+        let min_token_len = min_token_len_for_path(options, &file.path);
         for node in &file.blocks {
             let start = node.start_token.saturating_add(1);
             if node.end_token <= start {
@@ -102,13 +114,18 @@ pub(in crate::report) fn detect_duplicate_blocks(
                     bucket.push(ReportSpanGroupBuilder {
                         content_hash,
                         normalized_len: slice.len(),
-                        preview: String::new(),
                         occurrences: vec![DuplicateSpanOccurrence {
                             repo_id: file.repo_id,
                             repo_label: repo_label_arc(repo_labels, file.repo_id),
                             path: Arc::clone(&file.path),
                             start_line: node.start_line,
                             end_line: node.end_line,
+                            start_col: 0,
+                            end_col: 0,
+                            code_char_cols: None,
+                            is_test: false,
+                            scope: None,
+                            last_modified: None,
                         }],
                         occurrence_keys: HashSet::from([(file_id, node.start_token)]),
                         repo_ids: HashSet::from([file.repo_id]),
@@ -132,28 +149,43 @@ pub(in crate::report) fn detect_duplicate_blocks(
                 path: Arc::clone(&file.path),
                 start_line: node.start_line,
                 end_line: node.end_line,
+                start_col: 0,
+                end_col: 0,
+                code_char_cols: None,
+                is_test: false,
+                scope: None,
+                last_modified: None,
             });
         }
     }
 
     let mut out =
         finalize_report_span_groups(groups.into_values().flatten(), options.cross_repo_only);
-    sort_span_groups_for_report(&mut out);
-    out.truncate(options.max_report_items);
-    fill_missing_previews_from_files(files, &mut out, 120);
-    out
+    let total = finalize_span_groups_for_report(
+        &mut out,
+        ReportSection::BlockDuplicates,
+        files,
+        options,
+        stats,
+        covered,
+        ages,
+    );
+    fill_missing_previews_from_files(files, &mut out, options, stats);
+    (out, total)
 }
 
 pub(in crate::report) fn detect_duplicate_ast_subtrees(
     repo_labels: &[Arc<str>],
     files: &[ScannedTextFile],
     options: &ScanOptions,
-) -> Vec<DuplicateSpanGroup> {
-    let min_token_len = options.min_token_len.max(1);
-
+    stats: &mut ScanStats,
+    covered: &mut CoveredLines,
+    ages: Option<&OccurrenceDater<'_>>,
+) -> Section<DuplicateSpanGroup> {
     let mut groups: HashMap<(u64, usize, u64), ReportSpanGroupBuilder> = HashMap::new();
 
     for (file_id, file) in files.iter().enumerate() {
+        let min_token_len = min_token_len_for_path(options, &file.path);
         let mut hashes: Vec<Option<u64>> = vec![None; file.blocks.len()];
         let mut by_depth: Vec<usize> = (0..file.blocks.len()).collect();
         by_depth.sort_by_key(|&i| std::cmp::Reverse(file.blocks[i].depth));
@@ -241,13 +273,18 @@ pub(in crate::report) fn detect_duplicate_ast_subtrees(
             let builder = groups.entry(key).or_insert_with(|| ReportSpanGroupBuilder {
                 content_hash,
                 normalized_len: repr_len,
-                preview: String::new(),
                 occurrences: vec![DuplicateSpanOccurrence {
                     repo_id: file.repo_id,
                     repo_label: repo_label_arc(repo_labels, file.repo_id),
                     path: Arc::clone(&file.path),
                     start_line: node.start_line,
                     end_line: node.end_line,
+                    start_col: 0,
+                    end_col: 0,
+                    code_char_cols: None,
+                    is_test: false,
+                    scope: None,
+                    last_modified: None,
                 }],
                 occurrence_keys: HashSet::from([(file_id, node.start_token)]),
                 repo_ids: HashSet::from([file.repo_id]),
@@ -264,13 +301,26 @@ pub(in crate::report) fn detect_duplicate_ast_subtrees(
                 path: Arc::clone(&file.path),
                 start_line: node.start_line,
                 end_line: node.end_line,
+                start_col: 0,
+                end_col: 0,
+                code_char_cols: None,
+                is_test: false,
+                scope: None,
+                last_modified: None,
             });
         }
     }
 
     let mut out = finalize_report_span_groups(groups.into_values(), options.cross_repo_only);
-    sort_span_groups_for_report(&mut out);
-    out.truncate(options.max_report_items);
-    fill_missing_previews_from_files(files, &mut out, 120);
-    out
+    let total = finalize_span_groups_for_report(
+        &mut out,
+        ReportSection::AstSubtreeDuplicates,
+        files,
+        options,
+        stats,
+        covered,
+        ages,
+    );
+    fill_missing_previews_from_files(files, &mut out, options, stats);
+    (out, total)
 }
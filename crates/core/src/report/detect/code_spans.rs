@@ -1,11 +1,19 @@
 use std::sync::Arc;
 
 use crate::dedupe::detect_duplicate_code_spans_winnowing;
-use crate::types::{DuplicateSpanGroup, ScanOptions, ScanStats};
-use crate::util::NormalizedCodeFileView;
+use crate::types::{DuplicateSpanGroup, ReportSection, ScanOptions, ScanStats};
+use crate::util::{
+    CODE_SPAN_PREVIEW_CHARS, NormalizedCodeFileView, fill_code_span_previews,
+    resolve_code_span_columns,
+};
 
-use super::super::ScannedTextFile;
-use super::super::util::sort_span_groups_for_report;
+use super::super::ages::OccurrenceDater;
+use super::super::coverage::CoveredLines;
+use super::super::util::{
+    SourceFiles, fill_missing_previews_from_files, fill_occurrence_previews_from_files,
+    finalize_span_groups_for_report,
+};
+use super::super::{ScannedTextFile, Section};
 use super::repo_label_arc;
 
 pub(in crate::report) fn detect_duplicate_code_spans(
@@ -13,7 +21,9 @@ pub(in crate::report) fn detect_duplicate_code_spans(
     files: &[ScannedTextFile],
     options: &ScanOptions,
     stats: &mut ScanStats,
-) -> Vec<DuplicateSpanGroup> {
+    covered: &mut CoveredLines,
+    ages: Option<&OccurrenceDater<'_>>,
+) -> Section<DuplicateSpanGroup> {
     let min_match_len = options.min_match_len.max(1);
 
     let mut normalized = Vec::new();
@@ -31,11 +41,35 @@ pub(in crate::report) fn detect_duplicate_code_spans(
     }
 
     if normalized.is_empty() {
-        return Vec::new();
+        return (Vec::new(), 0);
     }
 
     let mut out = detect_duplicate_code_spans_winnowing(&normalized, options, stats);
-    sort_span_groups_for_report(&mut out);
-    out.truncate(options.max_report_items);
-    out
+    let total = finalize_span_groups_for_report(
+        &mut out,
+        ReportSection::CodeSpanDuplicates,
+        files,
+        options,
+        stats,
+        covered,
+        ages,
+    );
+    if options.preview_context_lines > 0 {
+        fill_missing_previews_from_files(files, &mut out, options, stats);
+    } else {
+        // Every occurrence normalizes to the same text, so other repos are previewed from
+        // their source lines.
+        fill_occurrence_previews_from_files(files, &mut out, options, stats);
+    }
+    fill_code_span_previews(
+        &mut out,
+        &normalized,
+        options.preview_max_chars(CODE_SPAN_PREVIEW_CHARS),
+    );
+    let mut sources = SourceFiles::new(files);
+    for occ in out.iter_mut().flat_map(|g| g.occurrences.iter_mut()) {
+        let bytes = sources.contents(occ);
+        resolve_code_span_columns(occ, bytes, options.unicode_normalization);
+    }
+    (out, total)
 }
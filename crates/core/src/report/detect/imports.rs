@@ -0,0 +1,102 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::types::{DuplicateFile, ImportDuplicatePair, ScanOptions};
+use crate::util::is_test_path;
+
+use super::super::util::finalize_import_pairs_for_report;
+use super::super::{ScannedTextFile, Section};
+use super::repo_label_arc;
+
+/// Pair files whose import sets have a Jaccard similarity of at least
+/// [`ScanOptions::import_similarity_threshold`].
+///
+/// Exact, using prefix filtering: with imports ordered rarest first, two sets that reach the
+/// threshold always share one of the first `n - ceil(threshold * n) + 1` imports of each.
+pub(in crate::report) fn find_import_duplicates(
+    repo_labels: &[Arc<str>],
+    files: &[ScannedTextFile],
+    options: &ScanOptions,
+) -> Section<ImportDuplicatePair> {
+    let threshold = options.import_similarity_threshold;
+    let candidates: Vec<&ScannedTextFile> = files
+        .iter()
+        .filter(|file| file.imports.len() >= options.import_min_count.max(1))
+        .collect();
+
+    let mut frequency: HashMap<&str, usize> = HashMap::new();
+    for file in &candidates {
+        for import in &file.imports {
+            *frequency.entry(import.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut index: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut pairs = Vec::new();
+    for (id, file) in candidates.iter().enumerate() {
+        let mut ordered: Vec<&str> = file.imports.iter().map(String::as_str).collect();
+        ordered.sort_by_key(|import| (frequency[import], *import));
+        let required = (threshold * ordered.len() as f64 - 1e-9).ceil().max(0.0) as usize;
+        let prefix_len = (ordered.len() + 1)
+            .saturating_sub(required)
+            .min(ordered.len());
+
+        for import in &ordered[..prefix_len] {
+            let earlier = index.entry(import).or_default();
+            for &other in earlier.iter() {
+                if !seen.insert((other, id)) {
+                    continue;
+                }
+                let other_file = candidates[other];
+                if options.cross_repo_only && other_file.repo_id == file.repo_id {
+                    continue;
+                }
+                if let Some(pair) = import_pair(repo_labels, other_file, file, threshold) {
+                    pairs.push(pair);
+                }
+            }
+            earlier.push(id);
+        }
+    }
+
+    let total = finalize_import_pairs_for_report(&mut pairs, options);
+    (pairs, total)
+}
+
+fn import_pair(
+    repo_labels: &[Arc<str>],
+    a: &ScannedTextFile,
+    b: &ScannedTextFile,
+    threshold: f64,
+) -> Option<ImportDuplicatePair> {
+    let b_imports: HashSet<&str> = b.imports.iter().map(String::as_str).collect();
+    let shared_imports: Vec<String> = a
+        .imports
+        .iter()
+        .filter(|import| b_imports.contains(import.as_str()))
+        .cloned()
+        .collect();
+    let union = a.imports.len() + b.imports.len() - shared_imports.len();
+    let score = shared_imports.len() as f64 / union as f64;
+    if score < threshold {
+        return None;
+    }
+    let file_ref = |file: &ScannedTextFile| DuplicateFile {
+        repo_id: file.repo_id,
+        repo_label: repo_label_arc(repo_labels, file.repo_id),
+        path: Arc::clone(&file.path),
+        is_test: is_test_path(&file.path),
+    };
+    let (a, b) = if (a.repo_id, &a.path) <= (b.repo_id, &b.path) {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    Some(ImportDuplicatePair {
+        a: file_ref(a),
+        b: file_ref(b),
+        score,
+        shared_imports,
+    })
+}
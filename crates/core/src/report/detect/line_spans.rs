@@ -1,11 +1,14 @@
 use std::sync::Arc;
 
-use crate::types::{DuplicateSpanGroup, ScanOptions, ScanStats};
+use crate::skips::SkipList;
+use crate::types::{DuplicateSpanGroup, ReportSection, ScanOptions, ScanStats};
 use crate::util::NormalizedFileView;
 use crate::winnowing::WinnowingParams;
 
-use super::super::ScannedTextFile;
-use super::super::util::fill_missing_previews_from_files;
+use super::super::ages::OccurrenceDater;
+use super::super::coverage::CoveredLines;
+use super::super::util::{fill_missing_previews_from_files, finalize_span_groups_for_report};
+use super::super::{ScannedTextFile, Section};
 use super::repo_label_arc;
 use super::span_groups::detect_duplicate_span_groups_with_len_filter;
 
@@ -14,8 +17,15 @@ pub(in crate::report) fn detect_duplicate_line_spans(
     files: &[ScannedTextFile],
     options: &ScanOptions,
     stats: &mut ScanStats,
-) -> Vec<DuplicateSpanGroup> {
+    covered: &mut CoveredLines,
+    ages: Option<&OccurrenceDater<'_>>,
+) -> Section<DuplicateSpanGroup> {
+    if options.max_items(ReportSection::LineSpanDuplicates) == 0 {
+        return (Vec::new(), 0);
+    }
     let min_char_len = options.min_match_len.max(1);
+    let min_lines = options.line_span_min_lines.max(1);
+    let fingerprint_len = min_lines.min(2);
 
     let mut normalized = Vec::new();
     let mut file_line_lens = Vec::new();
@@ -30,6 +40,7 @@ pub(in crate::report) fn detect_duplicate_line_spans(
             rel_path: Arc::clone(&file.path),
             normalized: &file.line_tokens,
             line_map: &file.line_token_lines,
+            stop_runs: &[],
         });
         file_line_lens.push(file.line_token_char_lens.as_slice());
     }
@@ -37,12 +48,14 @@ pub(in crate::report) fn detect_duplicate_line_spans(
     let mut out = detect_duplicate_span_groups_with_len_filter(
         &normalized,
         WinnowingParams {
-            min_len: 2,
-            fingerprint_len: 2,
-            window_size: 8,
+            min_len: min_lines,
+            fingerprint_len,
+            window_size: options.line_span_window.max(1),
+            max_window_growth: options.adaptive_window.unwrap_or(1),
             cross_repo_only: options.cross_repo_only,
+            max_groups: options.max_groups_per_detector,
         },
-        options.max_report_items,
+        &SkipList::new(options).line_fingerprints(fingerprint_len),
         |file_id, start, len| {
             let lens = file_line_lens[file_id];
             let mut total = 0usize;
@@ -54,9 +67,17 @@ pub(in crate::report) fn detect_duplicate_line_spans(
             }
             false
         },
-        |_file_id, _start_line, _end_line| String::new(),
         stats,
     );
-    fill_missing_previews_from_files(files, &mut out, 120);
-    out
+    let total = finalize_span_groups_for_report(
+        &mut out,
+        ReportSection::LineSpanDuplicates,
+        files,
+        options,
+        stats,
+        covered,
+        ages,
+    );
+    fill_missing_previews_from_files(files, &mut out, options, stats);
+    (out, total)
 }
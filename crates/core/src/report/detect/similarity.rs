@@ -1,10 +1,15 @@
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
-use crate::types::{DuplicateSpanOccurrence, ScanOptions, SimilarityPair};
-use crate::util::fnv1a64_u32;
+use crate::language::min_token_len_for_path;
+use crate::types::{
+    DuplicateFile, DuplicateSpanOccurrence, FileSimilarity, ReportSection, ScanOptions,
+    SimilarityPair,
+};
+use crate::util::{fnv1a64_u32, is_test_path};
 
-use super::super::ScannedTextFile;
+use super::super::util::{fill_similarity_diffs_from_files, finalize_similarity_pairs_for_report};
+use super::super::{ScannedTextFile, Section};
 use super::repo_label_arc;
 
 fn splitmix64(mut x: u64) -> u64 {
@@ -15,34 +20,116 @@ fn splitmix64(mut x: u64) -> u64 {
     z ^ (z >> 31)
 }
 
-pub(in crate::report) fn find_similar_blocks_minhash(
-    repo_labels: &[Arc<str>],
-    files: &[ScannedTextFile],
-    options: &ScanOptions,
-) -> Vec<SimilarityPair> {
-    const SHINGLE: usize = 5;
-    const SIG_SIZE: usize = 32;
-    const BAND_SIZE: usize = 4;
-    const BANDS: usize = SIG_SIZE / BAND_SIZE;
-
-    let seeds: [u64; SIG_SIZE] = {
-        let mut out = [0u64; SIG_SIZE];
-        let mut s = 0x1234_5678_9abc_def0u64;
-        for v in &mut out {
+/// One hash seed per MinHash signature slot.
+fn minhash_seeds(sig_size: usize) -> Vec<u64> {
+    let mut s = 0x1234_5678_9abc_def0u64;
+    (0..sig_size)
+        .map(|_| {
             s = splitmix64(s);
-            *v = s;
+            s
+        })
+        .collect()
+}
+
+/// MinHash signature of the `shingle`-token windows of `tokens` (one slot per seed).
+fn minhash_signature(tokens: &[u32], shingle: usize, seeds: &[u64]) -> Vec<u32> {
+    let mut mins = vec![u32::MAX; seeds.len()];
+    for window in tokens.windows(shingle) {
+        let base = fnv1a64_u32(window);
+        for (min, seed) in mins.iter_mut().zip(seeds) {
+            let h = splitmix64(base ^ seed) as u32;
+            if h < *min {
+                *min = h;
+            }
         }
-        out
-    };
+    }
+    mins
+}
 
-    #[derive(Debug)]
-    struct BlockSig {
-        occ: DuplicateSpanOccurrence,
-        signature: [u32; SIG_SIZE],
+/// Fraction of equal slots between two MinHash signatures of the same length.
+fn minhash_score(a: &[u32], b: &[u32]) -> f64 {
+    let eq = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    eq as f64 / a.len().max(1) as f64
+}
+
+/// SimHash of the `shingle`-token windows of `tokens`.
+fn simhash(tokens: &[u32], shingle: usize) -> u64 {
+    let mut sums = [0i32; 64];
+    for window in tokens.windows(shingle) {
+        let base = fnv1a64_u32(window);
+        let h = splitmix64(base);
+        for (bit, sum) in sums.iter_mut().enumerate() {
+            if (h >> bit) & 1 == 1 {
+                *sum += 1;
+            } else {
+                *sum -= 1;
+            }
+        }
+    }
+
+    let mut hash = 0u64;
+    for (bit, sum) in sums.iter().enumerate() {
+        if *sum > 0 {
+            hash |= 1u64 << bit;
+        }
+    }
+    hash
+}
+
+/// A token slice the similarity detectors compare, with its location.
+struct Candidate<'a> {
+    occ: DuplicateSpanOccurrence,
+    tokens: &'a [u32],
+}
+
+/// Shallow brace blocks (depth <= 2) of every file or, with
+/// [`ScanOptions::similarity_windows`], fixed token windows laid over each file.
+fn similarity_candidates<'a>(
+    repo_labels: &[Arc<str>],
+    files: &'a [ScannedTextFile],
+    options: &ScanOptions,
+    shingle: usize,
+) -> Vec<Candidate<'a>> {
+    let occurrence =
+        |file: &ScannedTextFile, start_line: u32, end_line: u32| DuplicateSpanOccurrence {
+            repo_id: file.repo_id,
+            repo_label: repo_label_arc(repo_labels, file.repo_id),
+            path: Arc::clone(&file.path),
+            start_line,
+            end_line,
+            start_col: 0,
+            end_col: 0,
+            code_char_cols: None,
+            is_test: false,
+            scope: None,
+            last_modified: None,
+        };
+
+    let mut out = Vec::new();
+    if let Some(windows) = options.similarity_windows {
+        let size = windows.size().max(shingle);
+        for file in files {
+            let Some(last) = file.tokens.len().checked_sub(size) else {
+                continue;
+            };
+            let mut starts: Vec<usize> = (0..=last).step_by(windows.stride()).collect();
+            if starts.last() != Some(&last) {
+                // Cover the file's tail too.
+                starts.push(last);
+            }
+            for start in starts {
+                let end = start + size;
+                out.push(Candidate {
+                    occ: occurrence(file, file.token_lines[start], file.token_lines[end - 1]),
+                    tokens: &file.tokens[start..end],
+                });
+            }
+        }
+        return out;
     }
 
-    let mut blocks = Vec::new();
     for file in files {
+        let min_token_len = min_token_len_for_path(options, &file.path);
         for node in &file.blocks {
             if node.depth > 2 {
                 continue;
@@ -52,39 +139,61 @@ pub(in crate::report) fn find_similar_blocks_minhash(
                 continue;
             }
             let slice = &file.tokens[start..node.end_token];
-            if slice.len() < options.min_token_len || slice.len() < SHINGLE {
+            if slice.len() < min_token_len || slice.len() < shingle {
                 continue;
             }
-
-            let mut mins = [u32::MAX; SIG_SIZE];
-            for shingle in slice.windows(SHINGLE) {
-                let base = fnv1a64_u32(shingle);
-                for i in 0..SIG_SIZE {
-                    let h = splitmix64(base ^ seeds[i]) as u32;
-                    if h < mins[i] {
-                        mins[i] = h;
-                    }
-                }
-            }
-
-            blocks.push(BlockSig {
-                occ: DuplicateSpanOccurrence {
-                    repo_id: file.repo_id,
-                    repo_label: repo_label_arc(repo_labels, file.repo_id),
-                    path: Arc::clone(&file.path),
-                    start_line: node.start_line,
-                    end_line: node.end_line,
-                },
-                signature: mins,
+            out.push(Candidate {
+                occ: occurrence(file, node.start_line, node.end_line),
+                tokens: slice,
             });
         }
     }
+    out
+}
+
+/// Windows of one file overlap each other by design; they are not reported as similar.
+fn overlapping_windows(
+    options: &ScanOptions,
+    a: &DuplicateSpanOccurrence,
+    b: &DuplicateSpanOccurrence,
+) -> bool {
+    options.similarity_windows.is_some()
+        && a.repo_id == b.repo_id
+        && a.path == b.path
+        && a.start_line <= b.end_line
+        && b.start_line <= a.end_line
+}
+
+pub(in crate::report) fn find_similar_blocks_minhash(
+    repo_labels: &[Arc<str>],
+    files: &[ScannedTextFile],
+    options: &ScanOptions,
+) -> Section<SimilarityPair> {
+    let shingle = options.shingle_size.max(1);
+    let sig_size = options.minhash_signature_size.max(1);
+    let bands = options.minhash_bands.clamp(1, sig_size);
+    let band_size = sig_size / bands;
+    let seeds = minhash_seeds(sig_size);
+
+    #[derive(Debug)]
+    struct BlockSig {
+        occ: DuplicateSpanOccurrence,
+        signature: Vec<u32>,
+    }
+
+    let blocks: Vec<BlockSig> = similarity_candidates(repo_labels, files, options, shingle)
+        .into_iter()
+        .map(|candidate| BlockSig {
+            signature: minhash_signature(candidate.tokens, shingle, &seeds),
+            occ: candidate.occ,
+        })
+        .collect();
 
     let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
     for (idx, blk) in blocks.iter().enumerate() {
-        for band in 0..BANDS {
-            let start = band * BAND_SIZE;
-            let key_hash = fnv1a64_u32(&blk.signature[start..start + BAND_SIZE]);
+        for band in 0..bands {
+            let start = band * band_size;
+            let key_hash = fnv1a64_u32(&blk.signature[start..start + band_size]);
             buckets.entry((band, key_hash)).or_default().push(idx);
         }
     }
@@ -92,26 +201,28 @@ pub(in crate::report) fn find_similar_blocks_minhash(
     let mut seen = HashSet::new();
     let mut out = Vec::new();
     for ids in buckets.into_values() {
-        if ids.len() <= 1 {
+        if ids.len() <= 1
+            || (options.cross_repo_only
+                && ids
+                    .iter()
+                    .all(|&id| blocks[id].occ.repo_id == blocks[ids[0]].occ.repo_id))
+        {
             continue;
         }
         for i in 0..ids.len() {
             for j in (i + 1)..ids.len() {
                 let a = ids[i];
                 let b = ids[j];
-                let key = if a < b { (a, b) } else { (b, a) };
-                if !seen.insert(key) {
+                if options.cross_repo_only && blocks[a].occ.repo_id == blocks[b].occ.repo_id {
                     continue;
                 }
-                let sig_a = &blocks[key.0].signature;
-                let sig_b = &blocks[key.1].signature;
-                let eq = sig_a.iter().zip(sig_b).filter(|(x, y)| x == y).count();
-                let score = eq as f64 / SIG_SIZE as f64;
-                if score < options.similarity_threshold {
+                let key = if a < b { (a, b) } else { (b, a) };
+                if !seen.insert(key) || overlapping_windows(options, &blocks[a].occ, &blocks[b].occ)
+                {
                     continue;
                 }
-                if options.cross_repo_only && blocks[key.0].occ.repo_id == blocks[key.1].occ.repo_id
-                {
+                let score = minhash_score(&blocks[key.0].signature, &blocks[key.1].signature);
+                if score < options.similarity_threshold {
                     continue;
                 }
                 out.push(SimilarityPair {
@@ -119,17 +230,99 @@ pub(in crate::report) fn find_similar_blocks_minhash(
                     b: blocks[key.1].occ.clone(),
                     score,
                     distance: None,
+                    diff: None,
+                });
+            }
+        }
+    }
+
+    let total = finalize_similarity_pairs_for_report(
+        &mut out,
+        ReportSection::SimilarBlocksMinhash,
+        files,
+        options,
+    );
+    if options.similarity_diff {
+        fill_similarity_diffs_from_files(files, &mut out, 200, 4096);
+    }
+    (out, total)
+}
+
+/// Whole-file MinHash pairs for [`ScanOptions::file_similarity`]: every pair of files that
+/// share an LSH band, with its estimated similarity (no threshold, so the matrix stays usable
+/// for clustering).
+pub(in crate::report) fn find_similar_files_minhash(
+    repo_labels: &[Arc<str>],
+    files: &[ScannedTextFile],
+    options: &ScanOptions,
+) -> Vec<FileSimilarity> {
+    let shingle = options.shingle_size.max(1);
+    let sig_size = options.minhash_signature_size.max(1);
+    let bands = options.minhash_bands.clamp(1, sig_size);
+    let band_size = sig_size / bands;
+    let seeds = minhash_seeds(sig_size);
+
+    let signatures: Vec<(usize, Vec<u32>)> = files
+        .iter()
+        .enumerate()
+        .filter(|(_, file)| file.tokens.len() >= shingle)
+        .map(|(file_id, file)| (file_id, minhash_signature(&file.tokens, shingle, &seeds)))
+        .collect();
+
+    let mut buckets: HashMap<(usize, u64), Vec<usize>> = HashMap::new();
+    for (idx, (_, signature)) in signatures.iter().enumerate() {
+        for band in 0..bands {
+            let start = band * band_size;
+            let key_hash = fnv1a64_u32(&signature[start..start + band_size]);
+            buckets.entry((band, key_hash)).or_default().push(idx);
+        }
+    }
+
+    let file_ref = |file_id: usize| {
+        let file = &files[file_id];
+        DuplicateFile {
+            repo_id: file.repo_id,
+            repo_label: repo_label_arc(repo_labels, file.repo_id),
+            path: Arc::clone(&file.path),
+            is_test: is_test_path(&file.path),
+        }
+    };
+    let mut seen = HashSet::new();
+    let mut out = Vec::new();
+    for ids in buckets.into_values() {
+        for i in 0..ids.len() {
+            for j in (i + 1)..ids.len() {
+                let key = (ids[i].min(ids[j]), ids[i].max(ids[j]));
+                let (file_a, sig_a) = &signatures[key.0];
+                let (file_b, sig_b) = &signatures[key.1];
+                if options.cross_repo_only && files[*file_a].repo_id == files[*file_b].repo_id {
+                    continue;
+                }
+                if !seen.insert(key) {
+                    continue;
+                }
+                out.push(FileSimilarity {
+                    a: file_ref(*file_a),
+                    b: file_ref(*file_b),
+                    score: minhash_score(sig_a, sig_b),
                 });
             }
         }
     }
 
-    out.sort_by(|a, b| {
-        b.score
-            .partial_cmp(&a.score)
-            .unwrap_or(std::cmp::Ordering::Equal)
+    let file_key = |file: &DuplicateFile| (file.repo_id, Arc::clone(&file.path));
+    // Put the first file in `a`, whatever order the files were scanned in.
+    for pair in &mut out {
+        if file_key(&pair.b) < file_key(&pair.a) {
+            std::mem::swap(&mut pair.a, &mut pair.b);
+        }
+    }
+    out.sort_by(|x, y| {
+        y.score
+            .total_cmp(&x.score)
+            .then_with(|| file_key(&x.a).cmp(&file_key(&y.a)))
+            .then_with(|| file_key(&x.b).cmp(&file_key(&y.b)))
     });
-    out.truncate(options.max_report_items);
     out
 }
 
@@ -137,10 +330,10 @@ pub(in crate::report) fn find_similar_blocks_simhash(
     repo_labels: &[Arc<str>],
     files: &[ScannedTextFile],
     options: &ScanOptions,
-) -> Vec<SimilarityPair> {
-    const SHINGLE: usize = 5;
+) -> Section<SimilarityPair> {
     const BANDS: usize = 4;
     const BAND_BITS: u32 = 16;
+    let shingle = options.shingle_size.max(1);
 
     #[derive(Debug)]
     struct BlockHash {
@@ -148,53 +341,13 @@ pub(in crate::report) fn find_similar_blocks_simhash(
         hash: u64,
     }
 
-    let mut blocks = Vec::new();
-    for file in files {
-        for node in &file.blocks {
-            if node.depth > 2 {
-                continue;
-            }
-            let start = node.start_token.saturating_add(1);
-            if node.end_token <= start {
-                continue;
-            }
-            let slice = &file.tokens[start..node.end_token];
-            if slice.len() < options.min_token_len || slice.len() < SHINGLE {
-                continue;
-            }
-
-            let mut sums = [0i32; 64];
-            for shingle in slice.windows(SHINGLE) {
-                let base = fnv1a64_u32(shingle);
-                let h = splitmix64(base);
-                for (bit, sum) in sums.iter_mut().enumerate() {
-                    if (h >> bit) & 1 == 1 {
-                        *sum += 1;
-                    } else {
-                        *sum -= 1;
-                    }
-                }
-            }
-
-            let mut hash = 0u64;
-            for (bit, sum) in sums.iter().enumerate() {
-                if *sum > 0 {
-                    hash |= 1u64 << bit;
-                }
-            }
-
-            blocks.push(BlockHash {
-                occ: DuplicateSpanOccurrence {
-                    repo_id: file.repo_id,
-                    repo_label: repo_label_arc(repo_labels, file.repo_id),
-                    path: Arc::clone(&file.path),
-                    start_line: node.start_line,
-                    end_line: node.end_line,
-                },
-                hash,
-            });
-        }
-    }
+    let blocks: Vec<BlockHash> = similarity_candidates(repo_labels, files, options, shingle)
+        .into_iter()
+        .map(|candidate| BlockHash {
+            hash: simhash(candidate.tokens, shingle),
+            occ: candidate.occ,
+        })
+        .collect();
 
     let mut buckets: HashMap<(u32, u64), Vec<usize>> = HashMap::new();
     for (idx, blk) in blocks.iter().enumerate() {
@@ -211,41 +364,50 @@ pub(in crate::report) fn find_similar_blocks_simhash(
     let mut seen = HashSet::new();
     let mut out = Vec::new();
     for ids in buckets.into_values() {
-        if ids.len() <= 1 {
+        if ids.len() <= 1
+            || (options.cross_repo_only
+                && ids
+                    .iter()
+                    .all(|&id| blocks[id].occ.repo_id == blocks[ids[0]].occ.repo_id))
+        {
             continue;
         }
         for i in 0..ids.len() {
             for j in (i + 1)..ids.len() {
                 let a = ids[i];
                 let b = ids[j];
+                if options.cross_repo_only && blocks[a].occ.repo_id == blocks[b].occ.repo_id {
+                    continue;
+                }
                 let key = if a < b { (a, b) } else { (b, a) };
-                if !seen.insert(key) {
+                if !seen.insert(key) || overlapping_windows(options, &blocks[a].occ, &blocks[b].occ)
+                {
                     continue;
                 }
                 let hamming = (blocks[key.0].hash ^ blocks[key.1].hash).count_ones();
                 if hamming > options.simhash_max_distance {
                     continue;
                 }
-                if options.cross_repo_only && blocks[key.0].occ.repo_id == blocks[key.1].occ.repo_id
-                {
-                    continue;
-                }
                 let score = 1.0 - (hamming as f64 / 64.0);
                 out.push(SimilarityPair {
                     a: blocks[key.0].occ.clone(),
                     b: blocks[key.1].occ.clone(),
                     score,
                     distance: Some(hamming),
+                    diff: None,
                 });
             }
         }
     }
 
-    out.sort_by(|a, b| {
-        b.score
-            .partial_cmp(&a.score)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
-    out.truncate(options.max_report_items);
-    out
+    let total = finalize_similarity_pairs_for_report(
+        &mut out,
+        ReportSection::SimilarBlocksSimhash,
+        files,
+        options,
+    );
+    if options.similarity_diff {
+        fill_similarity_diffs_from_files(files, &mut out, 200, 4096);
+    }
+    (out, total)
 }
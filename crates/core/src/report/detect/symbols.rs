@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::types::{
+    DuplicateFile, DuplicateGroup, ReportSection, ScanOptions, ScanStats, SymbolDuplicateGroup,
+};
+use crate::util::{fnv1a64, is_test_path};
+
+use super::super::util::finalize_duplicate_groups_for_report;
+use super::super::{ScannedTextFile, Section};
+use super::repo_label_arc;
+
+/// Group files whose declared public symbols are identical (at least
+/// [`ScanOptions::symbol_min_count`] of them).
+pub(in crate::report) fn detect_duplicate_symbols(
+    repo_labels: &[Arc<str>],
+    files: &[ScannedTextFile],
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) -> Section<SymbolDuplicateGroup> {
+    let mut by_symbols: HashMap<&[String], Vec<&ScannedTextFile>> = HashMap::new();
+    for file in files
+        .iter()
+        .filter(|file| file.symbols.len() >= options.symbol_min_count)
+    {
+        by_symbols
+            .entry(file.symbols.as_slice())
+            .or_default()
+            .push(file);
+    }
+
+    // Groups go through the file-group pipeline (filters, sort, paging); the symbols are
+    // looked up again by hash afterwards.
+    let mut symbols_by_hash: HashMap<u64, &[String]> = HashMap::new();
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for (symbols, mut members) in by_symbols {
+        if members.len() < 2
+            || (options.cross_repo_only
+                && members
+                    .iter()
+                    .all(|file| file.repo_id == members[0].repo_id))
+        {
+            continue;
+        }
+        members.sort_by(|a, b| (a.repo_id, &a.path).cmp(&(b.repo_id, &b.path)));
+        let content_hash = fnv1a64(symbols.join("\n").as_bytes());
+        symbols_by_hash.insert(content_hash, symbols);
+        groups.push(DuplicateGroup {
+            content_hash,
+            normalized_len: symbols.len(),
+            files: members
+                .into_iter()
+                .map(|file| DuplicateFile {
+                    repo_id: file.repo_id,
+                    repo_label: repo_label_arc(repo_labels, file.repo_id),
+                    path: Arc::clone(&file.path),
+                    is_test: is_test_path(&file.path),
+                })
+                .collect(),
+            truncated_files: 0,
+        });
+    }
+
+    let total = finalize_duplicate_groups_for_report(
+        &mut groups,
+        ReportSection::SymbolDuplicates,
+        options,
+        stats,
+        None,
+    );
+    let groups = groups
+        .into_iter()
+        .map(|group| SymbolDuplicateGroup {
+            content_hash: group.content_hash,
+            symbols: symbols_by_hash[&group.content_hash].to_vec(),
+            files: group.files,
+            truncated_files: group.truncated_files,
+        })
+        .collect();
+    (groups, total)
+}
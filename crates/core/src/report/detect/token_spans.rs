@@ -1,11 +1,15 @@
 use std::sync::Arc;
 
-use crate::types::{DuplicateSpanGroup, ScanOptions, ScanStats};
+use crate::language::min_token_len_for_path;
+use crate::skips::{SkipList, StopSequences};
+use crate::types::{DuplicateSpanGroup, ReportSection, ScanOptions, ScanStats};
 use crate::util::NormalizedFileView;
 use crate::winnowing::WinnowingParams;
 
-use super::super::ScannedTextFile;
-use super::super::util::fill_missing_previews_from_files;
+use super::super::ages::OccurrenceDater;
+use super::super::coverage::CoveredLines;
+use super::super::util::{fill_missing_previews_from_files, finalize_span_groups_for_report};
+use super::super::{ScannedTextFile, Section};
 use super::repo_label_arc;
 use super::span_groups::detect_duplicate_span_groups_with_len_filter;
 
@@ -14,27 +18,49 @@ pub(in crate::report) fn detect_duplicate_token_spans(
     files: &[ScannedTextFile],
     options: &ScanOptions,
     stats: &mut ScanStats,
-) -> Vec<DuplicateSpanGroup> {
-    let min_token_len = options.min_token_len.max(1);
-    let fingerprint_len = min_token_len.clamp(1, 25);
-    let window_size = min_token_len
-        .saturating_sub(fingerprint_len)
-        .saturating_add(1);
-
-    let mut normalized = Vec::new();
+    covered: &mut CoveredLines,
+    ages: Option<&OccurrenceDater<'_>>,
+) -> Section<DuplicateSpanGroup> {
+    if options.max_items(ReportSection::TokenSpanDuplicates) == 0 {
+        return (Vec::new(), 0);
+    }
+    let stop_sequences = StopSequences::new(options);
+    let mut kept = Vec::new();
+    // Per-file `min_token_len` (by language), indexed like `kept`.
+    let mut file_min_lens = Vec::new();
 
     for file in files {
-        if file.tokens.len() < min_token_len {
+        let file_min_len = min_token_len_for_path(options, &file.path);
+        if file.tokens.len() < file_min_len {
             continue;
         }
-        normalized.push(NormalizedFileView {
+        file_min_lens.push(file_min_len);
+        kept.push((file, stop_sequences.covered_runs(&file.path, &file.tokens)));
+    }
+    let normalized: Vec<NormalizedFileView<'_>> = kept
+        .iter()
+        .map(|(file, stop_runs)| NormalizedFileView {
             repo_id: file.repo_id,
             repo_label: repo_label_arc(repo_labels, file.repo_id),
             rel_path: Arc::clone(&file.path),
             normalized: &file.tokens,
             line_map: &file.token_lines,
-        });
-    }
+            stop_runs,
+        })
+        .collect();
+
+    // Winnow at the smallest threshold in play; longer per-language thresholds are enforced
+    // for each side of a match below.
+    let min_token_len = file_min_lens
+        .iter()
+        .copied()
+        .min()
+        .unwrap_or(options.min_token_len)
+        .max(1);
+    let fingerprint_len = min_token_len.clamp(1, 25);
+    let window_size = min_token_len
+        .saturating_sub(fingerprint_len)
+        .saturating_add(1);
 
     let mut out = detect_duplicate_span_groups_with_len_filter(
         &normalized,
@@ -42,13 +68,23 @@ pub(in crate::report) fn detect_duplicate_token_spans(
             min_len: min_token_len,
             fingerprint_len,
             window_size,
+            max_window_growth: options.adaptive_window.unwrap_or(1),
             cross_repo_only: options.cross_repo_only,
+            max_groups: options.max_groups_per_detector,
         },
-        options.max_report_items,
-        |_file_id, _start, _len| true,
-        |_file_id, _start_line, _end_line| String::new(),
+        &SkipList::new(options).token_fingerprints(fingerprint_len),
+        |file_id, _start, len| len >= file_min_lens[file_id],
+        stats,
+    );
+    let total = finalize_span_groups_for_report(
+        &mut out,
+        ReportSection::TokenSpanDuplicates,
+        files,
+        options,
         stats,
+        covered,
+        ages,
     );
-    fill_missing_previews_from_files(files, &mut out, 120);
-    out
+    fill_missing_previews_from_files(files, &mut out, options, stats);
+    (out, total)
 }
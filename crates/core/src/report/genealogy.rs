@@ -0,0 +1,141 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::io;
+
+use crate::types::{
+    CloneGenealogy, CloneLineage, DuplicateSpanGroup, DuplicationReport, GenealogyChange,
+    ReportSection, ScanOptions,
+};
+
+use super::snapshot::{CorpusSnapshot, generate_duplication_report_from_snapshot};
+
+/// Trace every span group (code, line, token, block and AST-subtree duplicates) from the `old`
+/// corpus to the `new` one and classify how it changed: added, removed, grown, shrunk, moved
+/// or unchanged.
+///
+/// Both corpora are reported with `options`, except that no section is truncated: a group past
+/// the `max_report_items` cut on one side would otherwise look added or removed. Groups are
+/// matched by section, content hash and normalized length, so an edited clone shows up as one
+/// removed and one added group. Occurrences are compared by repo label and path, so the two
+/// snapshots should be built from roots with the same labels.
+pub fn clone_genealogy(
+    old: CorpusSnapshot,
+    new: CorpusSnapshot,
+    options: &ScanOptions,
+) -> io::Result<CloneGenealogy> {
+    let options = ScanOptions {
+        max_report_items: usize::MAX,
+        max_items_per_detector: HashMap::new(),
+        section_offset: 0,
+        section_limit: None,
+        ..options.clone()
+    };
+    let old = generate_duplication_report_from_snapshot(old, &options)?.result;
+    let new = generate_duplication_report_from_snapshot(new, &options)?.result;
+    let old_potential_savings_lines = old.potential_savings_lines();
+    let new_potential_savings_lines = new.potential_savings_lines();
+
+    let mut lineages = Vec::new();
+    for ((section, old_groups), (_, new_groups)) in span_sections(old).zip(span_sections(new)) {
+        trace_section(section, old_groups, new_groups, &mut lineages);
+    }
+    Ok(CloneGenealogy {
+        lineages,
+        old_potential_savings_lines,
+        new_potential_savings_lines,
+    })
+}
+
+fn span_sections(
+    report: DuplicationReport,
+) -> impl Iterator<Item = (ReportSection, Vec<DuplicateSpanGroup>)> {
+    [
+        (
+            ReportSection::CodeSpanDuplicates,
+            report.code_span_duplicates,
+        ),
+        (
+            ReportSection::LineSpanDuplicates,
+            report.line_span_duplicates,
+        ),
+        (
+            ReportSection::TokenSpanDuplicates,
+            report.token_span_duplicates,
+        ),
+        (ReportSection::BlockDuplicates, report.block_duplicates),
+        (
+            ReportSection::AstSubtreeDuplicates,
+            report.ast_subtree_duplicates,
+        ),
+    ]
+    .into_iter()
+}
+
+fn trace_section(
+    section: ReportSection,
+    old_groups: Vec<DuplicateSpanGroup>,
+    new_groups: Vec<DuplicateSpanGroup>,
+    lineages: &mut Vec<CloneLineage>,
+) {
+    // Groups sharing a key (rare) are paired in report order.
+    let mut old_by_key: HashMap<(u64, usize), VecDeque<usize>> = HashMap::new();
+    for (index, group) in old_groups.iter().enumerate() {
+        old_by_key
+            .entry((group.content_hash, group.normalized_len))
+            .or_default()
+            .push_back(index);
+    }
+    let mut old_groups: Vec<Option<DuplicateSpanGroup>> =
+        old_groups.into_iter().map(Some).collect();
+
+    for new_group in new_groups {
+        let old_group = old_by_key
+            .get_mut(&(new_group.content_hash, new_group.normalized_len))
+            .and_then(VecDeque::pop_front)
+            .and_then(|index| old_groups[index].take());
+        let change = old_group
+            .as_ref()
+            .map_or(GenealogyChange::Added, |old_group| {
+                classify(old_group, &new_group)
+            });
+        lineages.push(CloneLineage {
+            section,
+            change,
+            old: old_group,
+            new: Some(new_group),
+        });
+    }
+    lineages.extend(
+        old_groups
+            .into_iter()
+            .flatten()
+            .map(|old_group| CloneLineage {
+                section,
+                change: GenealogyChange::Removed,
+                old: Some(old_group),
+                new: None,
+            }),
+    );
+}
+
+fn classify(old: &DuplicateSpanGroup, new: &DuplicateSpanGroup) -> GenealogyChange {
+    let old_count = old.occurrences.len() + old.truncated_occurrences;
+    let new_count = new.occurrences.len() + new.truncated_occurrences;
+    if new_count > old_count {
+        GenealogyChange::Grown
+    } else if new_count < old_count {
+        GenealogyChange::Shrunk
+    } else if files(old) != files(new) {
+        GenealogyChange::Moved
+    } else {
+        GenealogyChange::Unchanged
+    }
+}
+
+/// The `(repo label, path)` of each occurrence; repo ids may differ between the corpora.
+fn files(group: &DuplicateSpanGroup) -> BTreeSet<(&str, &str)> {
+    group
+        .occurrences
+        .iter()
+        .map(|occ| (occ.repo_label.as_ref(), occ.path.as_ref()))
+        .collect()
+}
@@ -0,0 +1,202 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::types::{DuplicateSpanGroup, DuplicateSpanOccurrence, ScanOptions, ScanStats};
+use crate::util::{
+    cap_span_group_occurrences, fill_span_group_line_stats, fnv1a64_u32, winnowed_fingerprints,
+};
+
+use super::ScannedTextFile;
+use super::util::fill_missing_previews_from_files;
+
+/// Occurrences of one `idiom_token_len`-token sequence.
+#[derive(Debug, Default)]
+struct Sequence {
+    /// `(file_id, start_token)`, in file then position order.
+    occurrences: Vec<(usize, usize)>,
+    file_count: usize,
+}
+
+/// Find the token sequences of at least [`ScanOptions::idiom_token_len`] tokens that occur in
+/// `min_files` or more files, most widespread first (see
+/// [`crate::types::DuplicationReport::common_idioms`]).
+///
+/// Every file is winnowed with windows spanning `idiom_token_len` tokens, so a shared sequence
+/// contains a whole window and therefore a fingerprint selected in each file that has it.
+/// Only the sequences around fingerprints shared by `min_files` files are then counted. Of
+/// the sequences found in enough files, one that mostly overlaps an idiom already picked is the
+/// same idiom shifted by a few tokens and is skipped; a picked one is extended on both sides
+/// while all its occurrences agree, up to [`ScanOptions::min_token_len`] tokens (longer
+/// matches are what the span detectors report).
+pub(super) fn find_common_idioms(
+    repo_labels: &[Arc<str>],
+    files: &[ScannedTextFile],
+    min_files: usize,
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) -> Vec<DuplicateSpanGroup> {
+    let len = options.idiom_token_len.max(1);
+    let fingerprint_len = len.div_ceil(2);
+    let window_size = len - fingerprint_len + 1;
+    let max_len = options.min_token_len.max(len);
+
+    let fingerprints: Vec<Vec<(u64, usize)>> = files
+        .iter()
+        .map(|file| winnowed_fingerprints(&file.tokens, fingerprint_len, window_size))
+        .collect();
+    let mut files_per_fingerprint: HashMap<u64, usize> = HashMap::new();
+    for file_fingerprints in &fingerprints {
+        let distinct: HashSet<u64> = file_fingerprints.iter().map(|&(hash, _)| hash).collect();
+        for hash in distinct {
+            *files_per_fingerprint.entry(hash).or_default() += 1;
+        }
+    }
+
+    let mut sequences: HashMap<u64, Sequence> = HashMap::new();
+    for (file_id, file_fingerprints) in fingerprints.iter().enumerate() {
+        let tokens = &files[file_id].tokens;
+        let mut starts: Vec<usize> = file_fingerprints
+            .iter()
+            .filter(|(hash, _)| files_per_fingerprint[hash] >= min_files)
+            .flat_map(|&(_, pos)| pos.saturating_sub(len - fingerprint_len)..=pos)
+            .filter(|&start| start + len <= tokens.len())
+            .collect();
+        starts.sort_unstable();
+        starts.dedup();
+        for start in starts {
+            let sequence = sequences
+                .entry(fnv1a64_u32(&tokens[start..start + len]))
+                .or_default();
+            // Hash collisions are dropped rather than merged.
+            if let Some(&(first_file, first_start)) = sequence.occurrences.first()
+                && files[first_file].tokens[first_start..first_start + len]
+                    != tokens[start..start + len]
+            {
+                continue;
+            }
+            if sequence
+                .occurrences
+                .last()
+                .is_none_or(|&(last, _)| last != file_id)
+            {
+                sequence.file_count += 1;
+            }
+            sequence.occurrences.push((file_id, start));
+        }
+    }
+
+    let mut candidates: Vec<(u64, Sequence)> = sequences
+        .into_iter()
+        .filter(|(_, sequence)| sequence.file_count >= min_files)
+        .collect();
+    candidates.sort_by_key(|(hash, sequence)| {
+        (
+            Reverse(sequence.file_count),
+            Reverse(sequence.occurrences.len()),
+            *hash,
+        )
+    });
+
+    // Token ranges of the picked idioms, per file.
+    let mut picked: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+    let mut out = Vec::new();
+    for (_, sequence) in candidates {
+        if out.len() >= options.max_report_items {
+            break;
+        }
+        let overlapping = sequence
+            .occurrences
+            .iter()
+            .filter(|&&(file_id, start)| {
+                picked
+                    .get(&file_id)
+                    .is_some_and(|ranges| ranges.iter().any(|&(a, b)| start < b && a < start + len))
+            })
+            .count();
+        if overlapping * 2 >= sequence.occurrences.len() {
+            continue;
+        }
+        if options.cross_repo_only && !spans_repos(files, &sequence.occurrences) {
+            continue;
+        }
+
+        let (before, after) = extend(files, &sequence.occurrences, len, max_len);
+        let idiom_len = before + len + after;
+        let mut occurrences = Vec::with_capacity(sequence.occurrences.len());
+        for &(file_id, start) in &sequence.occurrences {
+            let file = &files[file_id];
+            let start = start - before;
+            let end = start + idiom_len;
+            picked.entry(file_id).or_default().push((start, end));
+            occurrences.push(DuplicateSpanOccurrence {
+                repo_id: file.repo_id,
+                repo_label: Arc::clone(&repo_labels[file.repo_id]),
+                path: Arc::clone(&file.path),
+                start_line: file.token_lines[start],
+                end_line: file.token_lines[end - 1],
+                start_col: 0,
+                end_col: 0,
+                code_char_cols: None,
+                is_test: false,
+                scope: None,
+                last_modified: None,
+            });
+        }
+        let (file_id, start) = sequence.occurrences[0];
+        let start = start - before;
+        out.push(DuplicateSpanGroup {
+            content_hash: fnv1a64_u32(&files[file_id].tokens[start..start + idiom_len]),
+            normalized_len: idiom_len,
+            preview: String::new(),
+            occurrences,
+            truncated_occurrences: 0,
+            duplicated_lines: 0,
+            potential_savings_lines: 0,
+            occurrence_previews: Vec::new(),
+            also_detected_by: Vec::new(),
+            quality: None,
+        });
+    }
+
+    fill_span_group_line_stats(&mut out);
+    cap_span_group_occurrences(&mut out, options.max_occurrences_per_group);
+    fill_missing_previews_from_files(files, &mut out, options, stats);
+    out
+}
+
+fn spans_repos(files: &[ScannedTextFile], occurrences: &[(usize, usize)]) -> bool {
+    let mut repo_ids = occurrences
+        .iter()
+        .map(|&(file_id, _)| files[file_id].repo_id);
+    let first = repo_ids.next();
+    repo_ids.any(|repo_id| Some(repo_id) != first)
+}
+
+/// How many tokens every occurrence can be extended by before and after its `len` tokens while
+/// all of them still agree, keeping the total within `max_len`.
+fn extend(
+    files: &[ScannedTextFile],
+    occurrences: &[(usize, usize)],
+    len: usize,
+    max_len: usize,
+) -> (usize, usize) {
+    let token_at = |&(file_id, pos): &(usize, usize), offset: isize| {
+        pos.checked_add_signed(offset)
+            .and_then(|pos| files[file_id].tokens.get(pos))
+    };
+    let agree = |offset: isize| {
+        let first = token_at(&occurrences[0], offset);
+        first.is_some() && occurrences.iter().all(|occ| token_at(occ, offset) == first)
+    };
+
+    let mut before = 0usize;
+    while len + before < max_len && agree(-(before as isize) - 1) {
+        before += 1;
+    }
+    let mut after = 0usize;
+    while len + before + after < max_len && agree((len + after) as isize) {
+        after += 1;
+    }
+    (before, after)
+}
@@ -0,0 +1,188 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::language::min_token_len_for_path;
+use crate::skips::SkipList;
+use crate::types::{
+    DuplicationPartner, FileDuplication, LineRange, ScanOptions, ScanStats, VirtualFile,
+};
+use crate::util::{maximal_match, winnowed_fingerprints};
+
+use super::coverage::merge_line_ranges;
+use super::scan_files::{normalize_virtual_path, scan_text_file};
+use super::snapshot::CorpusSnapshot;
+
+/// Token fingerprints of a [`CorpusSnapshot`], for asking how much of a single file is copied
+/// from the corpus without generating a report.
+///
+/// Matches follow the token-span detector: a file counts as covered where it shares a token run
+/// of at least `min_token_len` (scaled per language, see
+/// [`ScanOptions::language_token_scales`]) with a corpus file, and the built-in/custom skip-lists
+/// apply. The index keeps the snapshot plus one fingerprint per winnowing window of each file.
+#[derive(Debug)]
+pub struct CorpusIndex {
+    snapshot: CorpusSnapshot,
+    options: ScanOptions,
+    /// Per-file token-span threshold (by language), indexed like `snapshot.files`.
+    min_lens: Vec<usize>,
+    fingerprint_len: usize,
+    fingerprints: HashMap<u64, Vec<(usize, usize)>>,
+}
+
+impl CorpusIndex {
+    /// Index `snapshot` (from [`super::build_corpus_snapshot`] or
+    /// [`CorpusSnapshot::read_from`]). Queried files are tokenized with `options`, which should
+    /// match the ones the snapshot was built with.
+    pub fn new(snapshot: CorpusSnapshot, options: &ScanOptions) -> io::Result<Self> {
+        options.validate_for_report()?;
+        let min_lens: Vec<usize> = snapshot
+            .files
+            .iter()
+            .map(|file| min_token_len_for_path(options, &file.path))
+            .collect();
+        // As in the token-span detector, winnow at the smallest threshold in play: every match
+        // long enough for its corpus file then contains at least one indexed fingerprint.
+        let min_len = min_lens
+            .iter()
+            .copied()
+            .min()
+            .unwrap_or(options.min_token_len)
+            .max(1);
+        let fingerprint_len = min_len.clamp(1, 25);
+        let window_size = min_len - fingerprint_len + 1;
+        let skipped = SkipList::new(options).token_fingerprints(fingerprint_len);
+
+        let mut fingerprints: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+        for (file_id, file) in snapshot.files.iter().enumerate() {
+            for (hash, pos) in winnowed_fingerprints(&file.tokens, fingerprint_len, window_size) {
+                if !skipped.contains(&hash) {
+                    fingerprints.entry(hash).or_default().push((file_id, pos));
+                }
+            }
+        }
+
+        Ok(Self {
+            snapshot,
+            options: options.clone(),
+            min_lens,
+            fingerprint_len,
+            fingerprints,
+        })
+    }
+
+    pub fn snapshot(&self) -> &CorpusSnapshot {
+        &self.snapshot
+    }
+
+    /// Lines of `file` that match the corpus, and the corpus files they match.
+    ///
+    /// A corpus file with the same path is compared like any other, so query a changed file
+    /// against an index built without it. Errors on an invalid path (as for virtual files), on
+    /// binary content and on files over [`ScanOptions::max_file_size`] or
+    /// [`ScanOptions::max_file_time`].
+    pub fn file_duplication(&self, file: &VirtualFile) -> io::Result<FileDuplication> {
+        let path = normalize_virtual_path(&file.path)?;
+        self.duplication_of(Arc::from(path), file.content.as_bytes())
+    }
+
+    /// Like [`Self::file_duplication`], for a file read from disk; `path` is reported as given
+    /// and also picks the language.
+    pub fn file_duplication_at(&self, path: &Path) -> io::Result<FileDuplication> {
+        let bytes = fs::read(path)?;
+        let label = path.to_string_lossy().replace('\\', "/");
+        self.duplication_of(Arc::from(label), &bytes)
+    }
+
+    fn duplication_of(&self, path: Arc<str>, bytes: &[u8]) -> io::Result<FileDuplication> {
+        if bytes.contains(&0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{path} is a binary file"),
+            ));
+        }
+        if self
+            .options
+            .max_file_size
+            .is_some_and(|max| bytes.len() as u64 > max)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{path} is larger than max_file_size"),
+            ));
+        }
+        let mut stats = ScanStats::default();
+        let Some(file) = scan_text_file(0, path, None, bytes, &self.options, &mut stats) else {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "file analysis exceeded max_file_time",
+            ));
+        };
+
+        let query_min_len = min_token_len_for_path(&self.options, &file.path);
+        let mut shared: HashMap<usize, Vec<LineRange>> = HashMap::new();
+        let mut seen = HashSet::new();
+        // Every k-gram of the query is looked up, so each indexed fingerprint inside a match
+        // is found.
+        for (hash, pos) in winnowed_fingerprints(&file.tokens, self.fingerprint_len, 1) {
+            let Some(occs) = self.fingerprints.get(&hash) else {
+                continue;
+            };
+            for &(file_id, corpus_pos) in occs {
+                let corpus_file = &self.snapshot.files[file_id];
+                let Some((start, corpus_start, len)) = maximal_match(
+                    &file.tokens,
+                    pos,
+                    &corpus_file.tokens,
+                    corpus_pos,
+                    self.fingerprint_len,
+                ) else {
+                    continue;
+                };
+                if len < query_min_len.max(self.min_lens[file_id])
+                    || !seen.insert((file_id, start, corpus_start))
+                {
+                    continue;
+                }
+                shared.entry(file_id).or_default().push(LineRange {
+                    start_line: file.token_lines[start],
+                    end_line: file.token_lines[start + len - 1],
+                });
+            }
+        }
+
+        let mut covered = Vec::new();
+        let mut partners: Vec<DuplicationPartner> = shared
+            .into_iter()
+            .map(|(file_id, ranges)| {
+                let corpus_file = &self.snapshot.files[file_id];
+                let ranges = merge_line_ranges(ranges);
+                let shared_lines = ranges
+                    .iter()
+                    .map(LineRange::line_count)
+                    .fold(0u32, u32::saturating_add);
+                covered.extend(ranges);
+                DuplicationPartner {
+                    repo_id: corpus_file.repo_id,
+                    repo_label: Arc::clone(&self.snapshot.repo_labels[corpus_file.repo_id]),
+                    path: Arc::clone(&corpus_file.path),
+                    shared_lines,
+                }
+            })
+            .collect();
+        partners.sort_by(|a, b| {
+            b.shared_lines
+                .cmp(&a.shared_lines)
+                .then_with(|| (a.repo_id, a.path.as_ref()).cmp(&(b.repo_id, b.path.as_ref())))
+        });
+
+        Ok(FileDuplication {
+            path: file.path,
+            total_lines: file.line_count,
+            covered_ranges: merge_line_ranges(covered),
+            partners,
+        })
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::types::{
+    ClusterMember, DuplicationReport, LicenseConflict, LicensedFile, ReportSection,
+};
+
+use super::ScannedTextFile;
+
+/// Reported groups that span several repos and whose files declare different licenses.
+///
+/// A file without a license header counts as a license of its own, so a copy that dropped the
+/// original header is flagged too.
+pub(super) fn find_license_conflicts(
+    repo_labels: &[Arc<str>],
+    files: &[ScannedTextFile],
+    report: &DuplicationReport,
+) -> Vec<LicenseConflict> {
+    let licenses: HashMap<(usize, &str), Option<&Arc<str>>> = files
+        .iter()
+        .map(|file| ((file.repo_id, file.path.as_ref()), file.license.as_ref()))
+        .collect();
+
+    let mut conflicts = Vec::new();
+    let mut check = |section, index, group_files: Vec<(usize, &Arc<str>)>| {
+        if let Some(files) = licensed_files(repo_labels, &licenses, group_files) {
+            conflicts.push(LicenseConflict {
+                group: ClusterMember { section, index },
+                files,
+            });
+        }
+    };
+
+    for (index, group) in report.file_duplicates.iter().enumerate() {
+        let group_files = group
+            .files
+            .iter()
+            .map(|file| (file.repo_id, &file.path))
+            .collect();
+        check(ReportSection::FileDuplicates, index, group_files);
+    }
+    for (section, groups) in [
+        (
+            ReportSection::CodeSpanDuplicates,
+            &report.code_span_duplicates,
+        ),
+        (
+            ReportSection::LineSpanDuplicates,
+            &report.line_span_duplicates,
+        ),
+        (
+            ReportSection::TokenSpanDuplicates,
+            &report.token_span_duplicates,
+        ),
+        (ReportSection::BlockDuplicates, &report.block_duplicates),
+        (
+            ReportSection::AstSubtreeDuplicates,
+            &report.ast_subtree_duplicates,
+        ),
+    ] {
+        for (index, group) in groups.iter().enumerate() {
+            let group_files = group
+                .occurrences
+                .iter()
+                .map(|occ| (occ.repo_id, &occ.path))
+                .collect();
+            check(section, index, group_files);
+        }
+    }
+    for (section, pairs) in [
+        (
+            ReportSection::SimilarBlocksMinhash,
+            &report.similar_blocks_minhash,
+        ),
+        (
+            ReportSection::SimilarBlocksSimhash,
+            &report.similar_blocks_simhash,
+        ),
+    ] {
+        for (index, pair) in pairs.iter().enumerate() {
+            let group_files = vec![
+                (pair.a.repo_id, &pair.a.path),
+                (pair.b.repo_id, &pair.b.path),
+            ];
+            check(section, index, group_files);
+        }
+    }
+    conflicts
+}
+
+/// The distinct files of one group with their licenses, or `None` unless the group spans
+/// several repos and its files disagree on their license.
+fn licensed_files(
+    repo_labels: &[Arc<str>],
+    licenses: &HashMap<(usize, &str), Option<&Arc<str>>>,
+    mut group_files: Vec<(usize, &Arc<str>)>,
+) -> Option<Vec<LicensedFile>> {
+    group_files.sort_unstable();
+    group_files.dedup();
+    let first_repo = group_files.first()?.0;
+    if group_files
+        .iter()
+        .all(|&(repo_id, _)| repo_id == first_repo)
+    {
+        return None;
+    }
+
+    let files: Vec<LicensedFile> = group_files
+        .into_iter()
+        .map(|(repo_id, path)| LicensedFile {
+            repo_id,
+            repo_label: Arc::clone(&repo_labels[repo_id]),
+            path: Arc::clone(path),
+            license: licenses
+                .get(&(repo_id, path.as_ref()))
+                .copied()
+                .flatten()
+                .cloned(),
+        })
+        .collect();
+    let first_license = files[0].license();
+    files
+        .iter()
+        .any(|file| file.license() != first_license)
+        .then_some(files)
+}
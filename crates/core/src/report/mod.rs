@@ -1,23 +1,56 @@
+mod ages;
+mod clusters;
+mod coverage;
+mod cross_detector;
 mod detect;
+mod genealogy;
+mod idioms;
+mod index;
+mod licenses;
+mod quality;
 mod scan_files;
+mod snapshot;
 mod util;
 
 #[cfg(test)]
 mod tests;
 
+use std::collections::HashMap;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use crate::scan::validate_roots;
+use crate::context::{NamedScope, scope_at};
+use crate::parallel::{Job, resolve_threads, run_jobs};
+use crate::progress::ScanPhase;
+use crate::scan::{read_deleted_files_in_history, validate_roots};
 use crate::tokenize::BlockNode;
-use crate::types::{DuplicationReport, ScanOptions, ScanOutcome, ScanStats};
+use crate::types::{
+    DuplicateSpanOccurrence, DuplicationReport, LineRange, RepoScanStats, ReportRoot, ReportSample,
+    ReportSection, ScanOptions, ScanOutcome, ScanStats, SectionTotal, VirtualFile,
+};
+use crate::util::OutputPaths;
+
+use ages::OccurrenceDater;
+use coverage::CoveredLines;
+
+pub use genealogy::clone_genealogy;
+pub use index::CorpusIndex;
+pub use snapshot::{
+    CorpusSnapshot, build_corpus_snapshot, build_corpus_snapshot_at_git_ref,
+    generate_duplication_report_against_snapshot, generate_duplication_report_from_snapshot,
+};
 
 #[derive(Debug)]
 struct ScannedTextFile {
     repo_id: usize,
     path: Arc<str>,
-    abs_path: PathBuf,
+    /// `None` for files loaded from a [`CorpusSnapshot`], which carry their `source` instead.
+    abs_path: Option<PathBuf>,
+    /// [`crate::util::fnv1a64`] of the bytes that were scanned, so a file that changed before
+    /// it is re-read for previews is noticed (`0` for snapshot files).
+    content_hash: u64,
+    line_count: u32,
     code_chars: Vec<u8>,
     code_line_starts: Vec<u32>,
     line_tokens: Vec<u32>,
@@ -26,8 +59,25 @@ struct ScannedTextFile {
     tokens: Vec<u32>,
     token_lines: Vec<u32>,
     blocks: Vec<BlockNode>,
+    /// `#[cfg(test)]` items, used to tag test occurrences.
+    test_ranges: Vec<LineRange>,
+    /// Declared public symbol names, sorted (see [`crate::symbols::public_symbols`]).
+    symbols: Vec<String>,
+    /// Imported paths, sorted (see [`crate::imports::imported_paths`]).
+    imports: Vec<String>,
+    /// License declared by the file's header (see [`crate::license::detect_license`]).
+    license: Option<Arc<str>>,
+    /// Named functions/types of the file (see [`crate::context::named_scopes`]).
+    scopes: Vec<NamedScope>,
+    /// The scanned bytes of a file loaded from a [`CorpusSnapshot`], so previews, columns and
+    /// identifier stats do not need the original checkout; `None` for files read from disk and
+    /// for snapshot files whose bytes were not captured.
+    source: Option<Vec<u8>>,
 }
 
+/// A finalized report section and its group count before `max_report_items` truncation.
+type Section<T> = (Vec<T>, usize);
+
 fn empty_report() -> DuplicationReport {
     DuplicationReport {
         file_duplicates: Vec::new(),
@@ -38,7 +88,378 @@ fn empty_report() -> DuplicationReport {
         ast_subtree_duplicates: Vec::new(),
         similar_blocks_minhash: Vec::new(),
         similar_blocks_simhash: Vec::new(),
+        symbol_duplicates: Vec::new(),
+        import_duplicates: Vec::new(),
+        data_duplicates: Vec::new(),
+        file_coverage: None,
+        file_similarity: None,
+        clone_clusters: None,
+        similar_block_clusters: None,
+        license_conflicts: None,
+        common_idioms: None,
+        sample: None,
+        roots: Vec::new(),
+        section_totals: ReportSection::ALL
+            .into_iter()
+            .map(SectionTotal::empty)
+            .collect(),
+    }
+}
+
+fn section_total(
+    section: ReportSection,
+    total_groups: usize,
+    options: &ScanOptions,
+) -> SectionTotal {
+    let (offset, len) = options.report_page(section);
+    let listed = total_groups.saturating_sub(offset).min(len);
+    SectionTotal {
+        section,
+        total_groups,
+        truncated: listed < total_groups,
+        degraded: false,
+        capped_groups: 0,
+    }
+}
+
+fn record_section_total(report: &mut DuplicationReport, total: SectionTotal) {
+    if let Some(entry) = report
+        .section_totals
+        .iter_mut()
+        .find(|entry| entry.section == total.section)
+    {
+        *entry = total;
+    }
+}
+
+fn rewrite_report_paths(report: &mut DuplicationReport, roots: &[PathBuf], options: &ScanOptions) {
+    let mut paths = OutputPaths::new(roots, options);
+    paths.rewrite_groups(&mut report.file_duplicates);
+    paths.rewrite_groups(&mut report.data_duplicates);
+    for file in report
+        .symbol_duplicates
+        .iter_mut()
+        .flat_map(|group| group.files.iter_mut())
+    {
+        paths.rewrite(file.repo_id, &mut file.path);
+    }
+    for groups in [
+        &mut report.code_span_duplicates,
+        &mut report.line_span_duplicates,
+        &mut report.token_span_duplicates,
+        &mut report.block_duplicates,
+        &mut report.ast_subtree_duplicates,
+    ] {
+        paths.rewrite_span_groups(groups);
+    }
+    for pair in &mut report.import_duplicates {
+        paths.rewrite(pair.a.repo_id, &mut pair.a.path);
+        paths.rewrite(pair.b.repo_id, &mut pair.b.path);
+    }
+    for pair in report
+        .similar_blocks_minhash
+        .iter_mut()
+        .chain(report.similar_blocks_simhash.iter_mut())
+    {
+        paths.rewrite(pair.a.repo_id, &mut pair.a.path);
+        paths.rewrite(pair.b.repo_id, &mut pair.b.path);
+    }
+    for file in report.file_coverage.iter_mut().flatten() {
+        paths.rewrite(file.repo_id, &mut file.path);
+    }
+    for pair in report.file_similarity.iter_mut().flatten() {
+        paths.rewrite(pair.a.repo_id, &mut pair.a.path);
+        paths.rewrite(pair.b.repo_id, &mut pair.b.path);
+    }
+    for cluster in report.clone_clusters.iter_mut().flatten() {
+        for file in &mut cluster.files {
+            paths.rewrite(file.repo_id, &mut file.path);
+        }
+    }
+    for block in report
+        .similar_block_clusters
+        .iter_mut()
+        .flatten()
+        .flat_map(|cluster| cluster.blocks.iter_mut())
+    {
+        paths.rewrite(block.repo_id, &mut block.path);
+    }
+    for conflict in report.license_conflicts.iter_mut().flatten() {
+        for file in &mut conflict.files {
+            paths.rewrite(file.repo_id, &mut file.path);
+        }
+    }
+    if let Some(idioms) = &mut report.common_idioms {
+        paths.rewrite_span_groups(idioms);
+    }
+}
+
+/// Set [`DuplicateSpanOccurrence::scope`] of every span occurrence and similarity pair.
+fn fill_occurrence_scopes(files: &[ScannedTextFile], report: &mut DuplicationReport) {
+    let scopes: HashMap<(usize, &str), &[NamedScope]> = files
+        .iter()
+        .filter(|file| !file.scopes.is_empty())
+        .map(|file| ((file.repo_id, file.path.as_ref()), file.scopes.as_slice()))
+        .collect();
+    if scopes.is_empty() {
+        return;
+    }
+    let mut fill = |occ: &mut DuplicateSpanOccurrence| {
+        occ.scope = scopes
+            .get(&(occ.repo_id, occ.path.as_ref()))
+            .and_then(|file| scope_at(file, occ.start_line, occ.end_line))
+            .map(|scope| Arc::clone(&scope.name));
+    };
+    for group in [
+        &mut report.code_span_duplicates,
+        &mut report.line_span_duplicates,
+        &mut report.token_span_duplicates,
+        &mut report.block_duplicates,
+        &mut report.ast_subtree_duplicates,
+    ]
+    .into_iter()
+    .flatten()
+    .chain(report.common_idioms.iter_mut().flatten())
+    {
+        group.occurrences.iter_mut().for_each(&mut fill);
+    }
+    for pair in report
+        .similar_blocks_minhash
+        .iter_mut()
+        .chain(report.similar_blocks_simhash.iter_mut())
+    {
+        fill(&mut pair.a);
+        fill(&mut pair.b);
+    }
+    for cluster in report.similar_block_clusters.iter_mut().flatten() {
+        cluster.blocks.iter_mut().for_each(&mut fill);
+    }
+}
+
+/// Detector output plus the step that stores it in its report section.
+type DetectorResult = (
+    ScanStats,
+    SectionTotal,
+    CoveredLines,
+    Box<dyn FnOnce(&mut DuplicationReport) + Send>,
+);
+
+/// Run every section's detector (in parallel with [`ScanOptions::threads`] > 1) and store the
+/// results in `report`, and the lines their groups cover in `covered`.
+///
+/// Detectors only read the scanned files and each one sorts its section canonically before
+/// truncating, so the report does not depend on the thread count.
+fn run_detectors(
+    repo_labels: &[Arc<str>],
+    files: &[ScannedTextFile],
+    options: &ScanOptions,
+    report: &mut DuplicationReport,
+    stats: &mut ScanStats,
+    covered: &mut CoveredLines,
+    ages: Option<&OccurrenceDater<'_>>,
+) {
+    let scanned = stats.clone();
+    let start = |section: ReportSection| {
+        options.emit_progress(ScanPhase::Detecting(section), &scanned);
+    };
+    let jobs: Vec<(ReportSection, Job<'_, DetectorResult>)> = vec![
+        (
+            ReportSection::CodeSpanDuplicates,
+            Box::new(|| {
+                start(ReportSection::CodeSpanDuplicates);
+                let mut stats = ScanStats::default();
+                let mut covered = CoveredLines::default();
+                let (groups, total) = detect::detect_duplicate_code_spans(
+                    repo_labels,
+                    files,
+                    options,
+                    &mut stats,
+                    &mut covered,
+                    ages,
+                );
+                (
+                    stats,
+                    section_total(ReportSection::CodeSpanDuplicates, total, options),
+                    covered,
+                    Box::new(|report| report.code_span_duplicates = groups),
+                )
+            }),
+        ),
+        (
+            ReportSection::LineSpanDuplicates,
+            Box::new(|| {
+                start(ReportSection::LineSpanDuplicates);
+                let mut stats = ScanStats::default();
+                let mut covered = CoveredLines::default();
+                let (groups, total) = detect::detect_duplicate_line_spans(
+                    repo_labels,
+                    files,
+                    options,
+                    &mut stats,
+                    &mut covered,
+                    ages,
+                );
+                (
+                    stats,
+                    section_total(ReportSection::LineSpanDuplicates, total, options),
+                    covered,
+                    Box::new(|report| report.line_span_duplicates = groups),
+                )
+            }),
+        ),
+        (
+            ReportSection::TokenSpanDuplicates,
+            Box::new(|| {
+                start(ReportSection::TokenSpanDuplicates);
+                let mut stats = ScanStats::default();
+                let mut covered = CoveredLines::default();
+                let (groups, total) = detect::detect_duplicate_token_spans(
+                    repo_labels,
+                    files,
+                    options,
+                    &mut stats,
+                    &mut covered,
+                    ages,
+                );
+                (
+                    stats,
+                    section_total(ReportSection::TokenSpanDuplicates, total, options),
+                    covered,
+                    Box::new(|report| report.token_span_duplicates = groups),
+                )
+            }),
+        ),
+        (
+            ReportSection::BlockDuplicates,
+            Box::new(|| {
+                start(ReportSection::BlockDuplicates);
+                let mut stats = ScanStats::default();
+                let mut covered = CoveredLines::default();
+                let (groups, total) = detect::detect_duplicate_blocks(
+                    repo_labels,
+                    files,
+                    options,
+                    &mut stats,
+                    &mut covered,
+                    ages,
+                );
+                (
+                    stats,
+                    section_total(ReportSection::BlockDuplicates, total, options),
+                    covered,
+                    Box::new(|report| report.block_duplicates = groups),
+                )
+            }),
+        ),
+        (
+            ReportSection::AstSubtreeDuplicates,
+            Box::new(|| {
+                start(ReportSection::AstSubtreeDuplicates);
+                let mut stats = ScanStats::default();
+                let mut covered = CoveredLines::default();
+                let (groups, total) = detect::detect_duplicate_ast_subtrees(
+                    repo_labels,
+                    files,
+                    options,
+                    &mut stats,
+                    &mut covered,
+                    ages,
+                );
+                (
+                    stats,
+                    section_total(ReportSection::AstSubtreeDuplicates, total, options),
+                    covered,
+                    Box::new(|report| report.ast_subtree_duplicates = groups),
+                )
+            }),
+        ),
+        (
+            ReportSection::SimilarBlocksMinhash,
+            Box::new(|| {
+                start(ReportSection::SimilarBlocksMinhash);
+                let (groups, total) =
+                    detect::find_similar_blocks_minhash(repo_labels, files, options);
+                (
+                    ScanStats::default(),
+                    section_total(ReportSection::SimilarBlocksMinhash, total, options),
+                    CoveredLines::default(),
+                    Box::new(|report| report.similar_blocks_minhash = groups),
+                )
+            }),
+        ),
+        (
+            ReportSection::SimilarBlocksSimhash,
+            Box::new(|| {
+                start(ReportSection::SimilarBlocksSimhash);
+                let (groups, total) =
+                    detect::find_similar_blocks_simhash(repo_labels, files, options);
+                (
+                    ScanStats::default(),
+                    section_total(ReportSection::SimilarBlocksSimhash, total, options),
+                    CoveredLines::default(),
+                    Box::new(|report| report.similar_blocks_simhash = groups),
+                )
+            }),
+        ),
+        (
+            ReportSection::SymbolDuplicates,
+            Box::new(|| {
+                start(ReportSection::SymbolDuplicates);
+                let mut stats = ScanStats::default();
+                let (groups, total) =
+                    detect::detect_duplicate_symbols(repo_labels, files, options, &mut stats);
+                (
+                    stats,
+                    section_total(ReportSection::SymbolDuplicates, total, options),
+                    CoveredLines::default(),
+                    Box::new(|report| report.symbol_duplicates = groups),
+                )
+            }),
+        ),
+        (
+            ReportSection::ImportDuplicates,
+            Box::new(|| {
+                start(ReportSection::ImportDuplicates);
+                let (pairs, total) = detect::find_import_duplicates(repo_labels, files, options);
+                (
+                    ScanStats::default(),
+                    section_total(ReportSection::ImportDuplicates, total, options),
+                    CoveredLines::default(),
+                    Box::new(|report| report.import_duplicates = pairs),
+                )
+            }),
+        ),
+    ];
+    // A section limited to 0 items is not worth computing.
+    let jobs = jobs
+        .into_iter()
+        .filter(|(section, _)| options.max_items(*section) > 0)
+        .map(|(_, job)| job)
+        .collect();
+
+    for (detector_stats, mut total, detector_covered, store) in
+        run_jobs(resolve_threads(options.threads), jobs)
+    {
+        // Groups counted past `max_groups_per_detector` never reached the section.
+        if detector_stats.skipped_group_cap > 0 {
+            total.capped_groups = detector_stats.skipped_group_cap;
+            total.truncated = true;
+            total.degraded = true;
+        }
+        stats.skipped_bucket_truncated += detector_stats.skipped_bucket_truncated;
+        stats.skipped_group_cap += detector_stats.skipped_group_cap;
+        stats.stale_previews += detector_stats.stale_previews;
+        stats.fingerprints += detector_stats.fingerprints;
+        stats.suppressed_groups += detector_stats.suppressed_groups;
+        stats
+            .matched_suppressions
+            .extend(detector_stats.matched_suppressions);
+        covered.extend(detector_covered);
+        store(report);
+        record_section_total(report, total);
     }
+    stats.matched_suppressions.sort_unstable();
+    stats.matched_suppressions.dedup();
 }
 
 pub fn generate_duplication_report(
@@ -52,49 +473,235 @@ pub fn generate_duplication_report_with_stats(
     roots: &[PathBuf],
     options: &ScanOptions,
 ) -> io::Result<ScanOutcome<DuplicationReport>> {
-    if roots.is_empty() {
+    generate_report(roots, None, ScanStats::default(), options)
+}
+
+/// Generate a report over `roots` plus `files`, which form one extra root labeled `label`
+/// (its `repo_id` follows the distinct roots): sources a build tool generated or transformed in memory.
+///
+/// Virtual files get the per-file limits of a root scan (`max_file_size`, binary content,
+/// `max_tokens_per_file`, `max_file_time`) but not its ignore rules, sampling or budgets. As
+/// with [`generate_duplication_report_against_snapshot`], they take part in every span and
+/// similarity detector but not in `file_duplicates`, and data files among them are skipped.
+/// A path that is absolute, escapes its root or is given twice is an `InvalidInput` error.
+pub fn generate_duplication_report_with_virtual_files(
+    roots: &[PathBuf],
+    label: &str,
+    files: Vec<VirtualFile>,
+    options: &ScanOptions,
+) -> io::Result<ScanOutcome<DuplicationReport>> {
+    options.validate_for_report()?;
+    let mut stats = ScanStats::default();
+    let files = scan_files::scan_virtual_files(files, false, options, &mut stats)?;
+    let virtual_stats = stats.clone();
+    let roots = &validate_roots(roots, &mut stats)?;
+    let snapshot = CorpusSnapshot {
+        repo_labels: vec![Arc::from(label)],
+        root_paths: vec![Arc::from("")],
+        files,
+    };
+    let mut outcome = generate_report(roots, Some(snapshot), stats, options)?;
+    outcome.stats.per_repo.push(RepoScanStats::new(
+        roots.len(),
+        label.to_string(),
+        virtual_stats,
+    ));
+    Ok(outcome)
+}
+
+/// Generate a report of the code live in the git repository at `repo` that duplicates files
+/// deleted by the last `depth` commits of its history: code copied back from an old commit
+/// instead of reverting the deletion.
+///
+/// The deleted files form a second root labeled `deleted`, with each path prefixed by the
+/// deleting commit's abbreviated hash (`3f2a1c9b0d/src/old.rs`). Only groups spanning both roots
+/// are kept, as with `cross_repo_only`, and like the virtual files of
+/// [`generate_duplication_report_with_virtual_files`] the deleted files take no part in
+/// `file_duplicates`. `depth` must be `>= 1`; errors when git cannot be run or `repo` has no
+/// commits.
+pub fn generate_resurrection_report(
+    repo: &Path,
+    depth: usize,
+    options: &ScanOptions,
+) -> io::Result<ScanOutcome<DuplicationReport>> {
+    if depth == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "history depth must be >= 1",
+        ));
+    }
+    options.validate_for_report()?;
+    let mut stats = ScanStats::default();
+    let roots = &validate_roots(&[repo.to_path_buf()], &mut stats)?;
+    let files = read_deleted_files_in_history(repo, depth, options, &mut stats)?;
+    let files = scan_files::scan_virtual_files(files, false, options, &mut stats)?;
+    let deleted_stats = stats.clone();
+    let snapshot = CorpusSnapshot {
+        repo_labels: vec![Arc::from(DELETED_LABEL)],
+        root_paths: vec![Arc::from("")],
+        files,
+    };
+    let options = ScanOptions {
+        cross_repo_only: true,
+        ..options.clone()
+    };
+    let mut outcome = generate_report(roots, Some(snapshot), stats, &options)?;
+    outcome.stats.per_repo.push(RepoScanStats::new(
+        roots.len(),
+        DELETED_LABEL.to_string(),
+        deleted_stats,
+    ));
+    Ok(outcome)
+}
+
+/// Root label of the deleted files in [`generate_resurrection_report`].
+const DELETED_LABEL: &str = "deleted";
+
+/// `stats` holds counters of files scanned before the roots (virtual files); the roots' counters
+/// are added to them.
+fn generate_report(
+    roots: &[PathBuf],
+    snapshot: Option<CorpusSnapshot>,
+    mut stats: ScanStats,
+    options: &ScanOptions,
+) -> io::Result<ScanOutcome<DuplicationReport>> {
+    if roots.is_empty() && snapshot.is_none() {
         return Ok(ScanOutcome {
             result: empty_report(),
-            stats: ScanStats::default(),
+            stats,
         });
     }
 
-    validate_roots(roots)?;
+    let roots = &validate_roots(roots, &mut stats)?;
     options.validate_for_report()?;
-    if options.max_report_items == 0 {
+    if ReportSection::ALL
+        .into_iter()
+        .all(|section| options.max_items(section) == 0)
+    {
         return Ok(ScanOutcome {
             result: empty_report(),
-            stats: ScanStats::default(),
+            stats,
         });
     }
 
-    let mut stats = ScanStats::default();
-    let (repo_labels, files, file_duplicates) =
-        scan_files::scan_text_files_for_report(roots, options, &mut stats)?;
-
-    let code_span_duplicates =
-        detect::detect_duplicate_code_spans(&repo_labels, &files, options, &mut stats);
-    let line_span_duplicates =
-        detect::detect_duplicate_line_spans(&repo_labels, &files, options, &mut stats);
-    let token_span_duplicates =
-        detect::detect_duplicate_token_spans(&repo_labels, &files, options, &mut stats);
-    let block_duplicates = detect::detect_duplicate_blocks(&repo_labels, &files, options);
-    let ast_subtree_duplicates =
-        detect::detect_duplicate_ast_subtrees(&repo_labels, &files, options);
-    let similar_blocks_minhash = detect::find_similar_blocks_minhash(&repo_labels, &files, options);
-    let similar_blocks_simhash = detect::find_similar_blocks_simhash(&repo_labels, &files, options);
+    let mut covered = CoveredLines::default();
+    let (
+        mut repo_labels,
+        mut files,
+        (file_duplicates, file_duplicates_total),
+        (data_duplicates, data_duplicates_total),
+    ) = scan_files::scan_text_files_for_report(roots, options, &mut stats, &mut covered)?;
+    if let Some(snapshot) = snapshot {
+        let repo_offset = repo_labels.len();
+        repo_labels.extend(snapshot.repo_labels);
+        files.extend(snapshot.files.into_iter().map(|mut file| {
+            file.repo_id += repo_offset;
+            file
+        }));
+    }
+
+    let mut report = DuplicationReport {
+        file_duplicates,
+        data_duplicates,
+        ..empty_report()
+    };
+    record_section_total(
+        &mut report,
+        section_total(
+            ReportSection::FileDuplicates,
+            file_duplicates_total,
+            options,
+        ),
+    );
+    record_section_total(
+        &mut report,
+        section_total(
+            ReportSection::DataDuplicates,
+            data_duplicates_total,
+            options,
+        ),
+    );
+    run_detectors(
+        &repo_labels,
+        &files,
+        options,
+        &mut report,
+        &mut stats,
+        &mut covered,
+        OccurrenceDater::new(roots, options).as_ref(),
+    );
+    if options.deduplicate_across_detectors {
+        cross_detector::deduplicate_across_detectors(&mut report, options);
+    }
+    if options.file_coverage {
+        report.file_coverage = Some(coverage::compute_file_coverage(
+            &repo_labels,
+            &files,
+            covered,
+        ));
+    }
+    if options.clone_clusters {
+        report.clone_clusters = Some(clusters::cluster_report_groups(
+            &repo_labels,
+            &files,
+            &report,
+        ));
+    }
+    if options.similarity_clusters {
+        report.similar_block_clusters = Some(clusters::cluster_similarity_pairs(
+            &files, &report, options, &mut stats,
+        ));
+    }
+    if options.license_conflicts {
+        report.license_conflicts = Some(licenses::find_license_conflicts(
+            &repo_labels,
+            &files,
+            &report,
+        ));
+    }
+    if let Some(min_files) = options.idiom_min_files {
+        report.common_idioms = Some(idioms::find_common_idioms(
+            &repo_labels,
+            &files,
+            min_files,
+            options,
+            &mut stats,
+        ));
+    }
+    if options.file_similarity {
+        report.file_similarity = Some(detect::find_similar_files_minhash(
+            &repo_labels,
+            &files,
+            options,
+        ));
+    }
+    if options.sample.is_some() {
+        let candidate_files = stats
+            .candidate_files
+            .saturating_sub(stats.skipped_other_shard);
+        report.sample = Some(ReportSample {
+            sampled_files: candidate_files.saturating_sub(stats.skipped_not_sampled),
+            candidate_files,
+        });
+    }
+    // Paths are still root-relative up to here: previews, diffs and coverage look files up by them.
+    fill_occurrence_scopes(&files, &mut report);
+    if OutputPaths::is_active(options) {
+        rewrite_report_paths(&mut report, roots, options);
+    }
+    report.roots = repo_labels
+        .iter()
+        .enumerate()
+        .map(|(id, label)| ReportRoot {
+            id,
+            label: label.to_string(),
+            path: roots.get(id).map(|root| options.map_output_path(root)),
+        })
+        .collect();
 
+    options.emit_progress(ScanPhase::Finished, &stats);
     Ok(ScanOutcome {
-        result: DuplicationReport {
-            file_duplicates,
-            code_span_duplicates,
-            line_span_duplicates,
-            token_span_duplicates,
-            block_duplicates,
-            ast_subtree_duplicates,
-            similar_blocks_minhash,
-            similar_blocks_simhash,
-        },
+        result: report,
         stats,
     })
 }
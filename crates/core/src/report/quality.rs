@@ -0,0 +1,98 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::language::comment_syntax_for_path;
+use crate::tokenize::{identifier_names, is_punctuation};
+use crate::types::{DuplicateSpanGroup, GroupQuality};
+
+use super::ScannedTextFile;
+use super::util::SourceFiles;
+
+/// Set [`DuplicateSpanGroup::quality`] from the tokens of each group's first occurrence, leaving
+/// `identifier_diversity` for [`fill_identifier_diversity`].
+pub(super) fn fill_token_quality(groups: &mut [DuplicateSpanGroup], files: &[ScannedTextFile]) {
+    let by_path: HashMap<(usize, &str), &ScannedTextFile> = files
+        .iter()
+        .map(|file| ((file.repo_id, file.path.as_ref()), file))
+        .collect();
+    for group in groups {
+        let Some(occ) = group.occurrences.first() else {
+            continue;
+        };
+        let Some(file) = by_path.get(&(occ.repo_id, occ.path.as_ref())) else {
+            continue;
+        };
+        let start = file
+            .token_lines
+            .partition_point(|&line| line < occ.start_line);
+        let end = file
+            .token_lines
+            .partition_point(|&line| line <= occ.end_line);
+        group.quality = Some(token_quality(&file.tokens[start..end]));
+    }
+}
+
+/// Set [`GroupQuality::identifier_diversity`] by re-reading each group's first readable
+/// occurrence (only meant for the groups that are reported).
+pub(super) fn fill_identifier_diversity(
+    groups: &mut [DuplicateSpanGroup],
+    files: &[ScannedTextFile],
+) {
+    let mut sources = SourceFiles::new(files);
+    for group in groups {
+        let Some(quality) = group.quality.as_mut() else {
+            continue;
+        };
+        let Some(occ) = group
+            .occurrences
+            .iter()
+            .find(|occ| sources.is_readable(occ))
+        else {
+            continue;
+        };
+        let max_lines = (occ.end_line - occ.start_line) as usize + 1;
+        let Some(lines) = sources.lines(occ, max_lines) else {
+            continue;
+        };
+        let text = lines.join("\n");
+        quality.identifier_diversity =
+            identifier_diversity(&identifier_names(&text, comment_syntax_for_path(&occ.path)));
+    }
+}
+
+fn token_quality(tokens: &[u32]) -> GroupQuality {
+    if tokens.is_empty() {
+        return GroupQuality {
+            token_entropy: 0.0,
+            punctuation_ratio: 0.0,
+            identifier_diversity: None,
+        };
+    }
+    // Sorted so the floating-point sum runs in the same order on every scan.
+    let mut sorted = tokens.to_vec();
+    sorted.sort_unstable();
+    let total = tokens.len() as f64;
+    let token_entropy = sorted
+        .chunk_by(|a, b| a == b)
+        .map(|run| {
+            let p = run.len() as f64 / total;
+            p * (1.0 / p).log2()
+        })
+        .fold(0.0, |sum, term| sum + term);
+    let punctuation = tokens
+        .iter()
+        .filter(|&&token| is_punctuation(token))
+        .count();
+    GroupQuality {
+        token_entropy,
+        punctuation_ratio: punctuation as f64 / total,
+        identifier_diversity: None,
+    }
+}
+
+fn identifier_diversity(names: &[&str]) -> Option<f64> {
+    if names.is_empty() {
+        return None;
+    }
+    let distinct: HashSet<&str> = names.iter().copied().collect();
+    Some(distinct.len() as f64 / names.len() as f64)
+}
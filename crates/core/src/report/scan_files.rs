@@ -1,29 +1,50 @@
+use std::collections::HashSet;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use crate::context::named_scopes;
 use crate::dedupe::FileDuplicateGrouper;
+use crate::imports::imported_paths;
+use crate::language::{comment_syntax_for_path, is_data_file};
+use crate::license::detect_license;
 use crate::scan::{
-    Repo, read_repo_file_bytes_for_verification, read_repo_file_bytes_with_path, repo_label,
-    visit_repo_files,
+    ReadTargets, Repo, apply_sample, read_repo_file_bytes_for_verification,
+    read_repo_file_bytes_with_path, repo_label, scan_repos, visit_repo_files,
+};
+use crate::skip_log::SkipReason;
+use crate::symbols::public_symbols;
+use crate::tokenize::{parse_brace_blocks, tokenize_for_dup_detection_within};
+use crate::types::{DuplicateGroup, ReportSection, ScanOptions, ScanStats, VirtualFile};
+use crate::util::{
+    FileDeadline, cfg_test_line_ranges, fnv1a64, is_line_end, normalize_for_code_spans,
+    normalize_lines_for_dup_detection, normalize_unicode,
 };
-use crate::tokenize::{parse_brace_blocks, tokenize_for_dup_detection};
-use crate::types::{DuplicateGroup, ScanOptions, ScanStats};
-use crate::util::{fnv1a64_u32, fold_u64_to_u32, normalize_for_code_spans};
 
-use super::ScannedTextFile;
-use super::util::sort_duplicate_groups_for_report;
+use super::coverage::CoveredLines;
+use super::util::finalize_duplicate_groups_for_report;
+use super::{ScannedTextFile, Section};
 
 const DEFAULT_REPORT_MAX_TOTAL_BYTES: u64 = 256 * 1024 * 1024;
 const DEFAULT_REPORT_MAX_NORMALIZED_CHARS_DIVISOR: u64 = 1;
 const DEFAULT_REPORT_MAX_TOKENS_DIVISOR: u64 = 4;
 
-type ReportScanOutput = (Vec<Arc<str>>, Vec<ScannedTextFile>, Vec<DuplicateGroup>);
+/// Repo labels, scanned code files, then the `fileDuplicates` and `dataDuplicates` sections.
+type ReportScanOutput = (
+    Vec<Arc<str>>,
+    Vec<ScannedTextFile>,
+    Section<DuplicateGroup>,
+    Section<DuplicateGroup>,
+);
 
+/// Scan `roots` and finalize their file and data duplicate sections; with
+/// [`ScanOptions::file_coverage`], the files of every file-duplicate group are recorded in
+/// `covered`.
 pub(super) fn scan_text_files_for_report(
     roots: &[PathBuf],
     options: &ScanOptions,
     stats: &mut ScanStats,
+    covered: &mut CoveredLines,
 ) -> io::Result<ReportScanOutput> {
     let mut scan_options = options.clone();
     let max_total_bytes = scan_options
@@ -44,15 +65,17 @@ pub(super) fn scan_text_files_for_report(
         );
     }
 
-    let repos: Vec<Repo> = roots
+    let mut repos: Vec<Repo> = roots
         .iter()
         .enumerate()
         .map(|(id, root)| Repo {
             id,
             root: root.clone(),
             label: Arc::from(repo_label(root, id)),
+            sample_cutoff: None,
         })
         .collect();
+    apply_sample(&mut repos, options)?;
     let repo_labels: Vec<Arc<str>> = repos.iter().map(|repo| Arc::clone(&repo.label)).collect();
 
     let canonical_roots = if options.follow_symlinks {
@@ -65,167 +88,303 @@ pub(super) fn scan_text_files_for_report(
     } else {
         None
     };
+    let mut read_targets = ReadTargets::new();
 
     let mut file_groups = FileDuplicateGrouper::default();
+    let mut data_groups = FileDuplicateGrouper::default();
     let mut files = Vec::new();
     let mut total_normalized_chars: usize = 0;
     let mut total_tokens: usize = 0;
     let max_normalized_chars = scan_options.max_normalized_chars;
     let max_tokens = scan_options.max_tokens;
 
-    for repo in &repos {
+    scan_repos(&repos, stats, |repo, stats| {
         let canonical_root = canonical_roots
             .as_ref()
             .map(|roots| roots[repo.id].as_path());
 
-        if let std::ops::ControlFlow::Break(()) =
-            visit_repo_files(repo, &scan_options, stats, |stats, repo_file| {
-                let Some((bytes, read_path)) = read_repo_file_bytes_with_path(
-                    &repo_file,
-                    canonical_root,
-                    &scan_options,
-                    stats,
-                )?
-                else {
+        visit_repo_files(repo, &scan_options, stats, |stats, repo_file| {
+            let Some((bytes, read_path)) = read_repo_file_bytes_with_path(
+                &repo_file,
+                repo.id,
+                canonical_root,
+                &mut read_targets,
+                &scan_options,
+                stats,
+            )?
+            else {
+                return Ok(std::ops::ControlFlow::Continue(()));
+            };
+
+            let rel_path_for_verification = match repo_file.abs_path.strip_prefix(&repo.root) {
+                Ok(rel) => rel.to_path_buf(),
+                Err(_) => {
+                    stats.skipped_relativize_failed =
+                        stats.skipped_relativize_failed.saturating_add(1);
+                    scan_options.log_skip(
+                        &repo_file.abs_path,
+                        SkipReason::RelativizeFailed,
+                        Some(bytes.len() as u64),
+                    );
                     return Ok(std::ops::ControlFlow::Continue(()));
-                };
-
-                let rel_path_for_verification = match repo_file.abs_path.strip_prefix(&repo.root) {
-                    Ok(rel) => rel.to_path_buf(),
-                    Err(_) => {
-                        stats.skipped_relativize_failed =
-                            stats.skipped_relativize_failed.saturating_add(1);
-                        return Ok(std::ops::ControlFlow::Continue(()));
-                    }
-                };
-
-                let rel_path = Arc::<str>::from(
-                    rel_path_for_verification
-                        .to_string_lossy()
-                        .replace('\\', "/"),
+                }
+            };
+
+            let rel_path = Arc::<str>::from(
+                rel_path_for_verification
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+            );
+
+            // Data files only take part in `dataDuplicates`, under their own size threshold.
+            if is_data_file(&rel_path) {
+                if bytes.len() as u64 >= scan_options.data_min_bytes {
+                    data_groups.push_bytes(&bytes, repo.id, rel_path_for_verification, rel_path);
+                }
+                return Ok(std::ops::ControlFlow::Continue(()));
+            }
+
+            // Text-based detectors
+            let Some(file) = scan_text_file(
+                repo.id,
+                Arc::clone(&rel_path),
+                Some(read_path),
+                &bytes,
+                &scan_options,
+                stats,
+            ) else {
+                stats.skipped_slow_file = stats.skipped_slow_file.saturating_add(1);
+                stats.skipped_slow_file_bytes = stats
+                    .skipped_slow_file_bytes
+                    .saturating_add(bytes.len() as u64);
+                scan_options.log_skip(
+                    &repo_file.abs_path,
+                    SkipReason::SlowFile,
+                    Some(bytes.len() as u64),
                 );
+                return Ok(std::ops::ControlFlow::Continue(()));
+            };
 
-                // Text-based detectors
-                let text = String::from_utf8_lossy(&bytes);
-                let code_norm = normalize_for_code_spans(&bytes);
-                let line_norm = normalize_lines_for_dup_detection(&bytes);
-                let tokenized = tokenize_for_dup_detection(&text);
-                let blocks = parse_brace_blocks(&tokenized.tokens, &tokenized.token_lines);
-
-                if let Some(max_normalized_chars) = max_normalized_chars {
-                    let next_total = total_normalized_chars.saturating_add(code_norm.chars.len());
-                    if next_total > max_normalized_chars {
-                        stats.skipped_budget_max_normalized_chars =
-                            stats.skipped_budget_max_normalized_chars.saturating_add(1);
-                        return Ok(std::ops::ControlFlow::Break(()));
-                    }
-                    total_normalized_chars = next_total;
+            if let Some(max_normalized_chars) = max_normalized_chars {
+                let next_total = total_normalized_chars.saturating_add(file.code_chars.len());
+                if next_total > max_normalized_chars {
+                    stats.skipped_budget_max_normalized_chars =
+                        stats.skipped_budget_max_normalized_chars.saturating_add(1);
+                    stats.skipped_budget_max_normalized_chars_bytes = stats
+                        .skipped_budget_max_normalized_chars_bytes
+                        .saturating_add(bytes.len() as u64);
+                    scan_options.log_skip(
+                        &repo_file.abs_path,
+                        SkipReason::BudgetMaxNormalizedChars,
+                        Some(bytes.len() as u64),
+                    );
+                    return Ok(std::ops::ControlFlow::Break(()));
                 }
-                if let Some(max_tokens) = max_tokens {
-                    let next_total = total_tokens.saturating_add(tokenized.tokens.len());
-                    if next_total > max_tokens {
-                        stats.skipped_budget_max_tokens =
-                            stats.skipped_budget_max_tokens.saturating_add(1);
-                        return Ok(std::ops::ControlFlow::Break(()));
-                    }
-                    total_tokens = next_total;
+                total_normalized_chars = next_total;
+            }
+            if let Some(max_tokens) = max_tokens {
+                let next_total = total_tokens.saturating_add(file.tokens.len());
+                if next_total > max_tokens {
+                    stats.skipped_budget_max_tokens =
+                        stats.skipped_budget_max_tokens.saturating_add(1);
+                    stats.skipped_budget_max_tokens_bytes = stats
+                        .skipped_budget_max_tokens_bytes
+                        .saturating_add(bytes.len() as u64);
+                    scan_options.log_skip(
+                        &repo_file.abs_path,
+                        SkipReason::BudgetMaxTokens,
+                        Some(bytes.len() as u64),
+                    );
+                    return Ok(std::ops::ControlFlow::Break(()));
                 }
+                total_tokens = next_total;
+            }
 
-                // File duplicates (whitespace-insensitive)
-                file_groups.push_bytes(
-                    &bytes,
-                    repo.id,
-                    rel_path_for_verification,
-                    Arc::clone(&rel_path),
-                );
+            // File duplicates (whitespace-insensitive)
+            file_groups.push_bytes(&bytes, repo.id, rel_path_for_verification, rel_path);
+            files.push(file);
 
-                files.push(ScannedTextFile {
-                    repo_id: repo.id,
-                    path: rel_path,
-                    abs_path: read_path,
-                    code_chars: code_norm.chars,
-                    code_line_starts: code_norm.line_starts,
-                    line_tokens: line_norm.line_tokens,
-                    line_token_lines: line_norm.line_lines,
-                    line_token_char_lens: line_norm.line_lens,
-                    tokens: tokenized.tokens,
-                    token_lines: tokenized.token_lines,
-                    blocks,
-                });
-
-                Ok(std::ops::ControlFlow::Continue(()))
-            })?
-        {
-            break;
-        }
-    }
+            Ok(std::ops::ControlFlow::Continue(()))
+        })
+    })?;
 
     let follow_symlinks = scan_options.follow_symlinks;
     let max_file_size = scan_options.max_file_size;
     let canonical_roots = canonical_roots.as_deref();
-    let mut file_duplicates = file_groups.into_groups_verified(
-        options.cross_repo_only,
-        |repo_id, path| {
-            let repo = &repos[repo_id];
-            let canonical_root = canonical_roots.map(|roots| roots[repo_id].as_path());
-
-            read_repo_file_bytes_for_verification(
-                &repo.root,
-                path.as_path(),
-                canonical_root,
-                follow_symlinks,
-                max_file_size,
-            )
-        },
-        |repo_id| Arc::clone(&repos[repo_id].label),
-    )?;
-
-    sort_duplicate_groups_for_report(&mut file_duplicates);
-    file_duplicates.truncate(options.max_report_items);
+    let mut verified_section = |groups: FileDuplicateGrouper,
+                                section: ReportSection,
+                                covered: Option<&mut CoveredLines>|
+     -> io::Result<Section<DuplicateGroup>> {
+        let mut groups = groups.into_groups_verified(
+            options.cross_repo_only,
+            stats,
+            |repo_id, path| {
+                let repo = &repos[repo_id];
+                let canonical_root = canonical_roots.map(|roots| roots[repo_id].as_path());
 
-    Ok((repo_labels, files, file_duplicates))
-}
+                read_repo_file_bytes_for_verification(
+                    &repo.root,
+                    path.as_path(),
+                    canonical_root,
+                    follow_symlinks,
+                    max_file_size,
+                )
+            },
+            |repo_id| Arc::clone(&repos[repo_id].label),
+        )?;
+        let total =
+            finalize_duplicate_groups_for_report(&mut groups, section, options, stats, covered);
+        Ok((groups, total))
+    };
+    let file_duplicates =
+        verified_section(file_groups, ReportSection::FileDuplicates, Some(covered))?;
+    let data_duplicates = verified_section(data_groups, ReportSection::DataDuplicates, None)?;
 
-#[derive(Debug)]
-struct LineNormalizedText {
-    line_tokens: Vec<u32>,
-    line_lines: Vec<u32>,
-    line_lens: Vec<usize>,
+    Ok((repo_labels, files, file_duplicates, data_duplicates))
 }
 
-fn normalize_lines_for_dup_detection(bytes: &[u8]) -> LineNormalizedText {
-    let mut line: u32 = 1;
-    let mut current: Vec<u32> = Vec::new();
-
-    let mut line_tokens = Vec::new();
-    let mut line_lines = Vec::new();
-    let mut line_lens = Vec::new();
+/// Analyze virtual files as repo `0` of their own corpus, with the per-file limits of a root
+/// scan; data files are skipped. Errors on a path that is absolute, escapes the root, is empty or
+/// appears twice. With `keep_source`, each file keeps its bytes as [`ScannedTextFile::source`].
+pub(super) fn scan_virtual_files(
+    virtual_files: Vec<VirtualFile>,
+    keep_source: bool,
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) -> io::Result<Vec<ScannedTextFile>> {
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for virtual_file in virtual_files {
+        let path = normalize_virtual_path(&virtual_file.path)?;
+        if !seen.insert(path.clone()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("duplicate virtual file path: {path}"),
+            ));
+        }
 
-    for &b in bytes {
-        if b == b'\n' {
-            if !current.is_empty() {
-                line_lens.push(current.len());
-                line_tokens.push(fold_u64_to_u32(fnv1a64_u32(&current)));
-                line_lines.push(line);
-            }
-            current.clear();
-            line = line.saturating_add(1);
+        let bytes = virtual_file.content.into_bytes();
+        let size = bytes.len() as u64;
+        stats.candidate_files = stats.candidate_files.saturating_add(1);
+        stats.scanned_files = stats.scanned_files.saturating_add(1);
+        stats.scanned_bytes = stats.scanned_bytes.saturating_add(size);
+        if bytes.contains(&0) {
+            stats.skipped_binary = stats.skipped_binary.saturating_add(1);
+            stats.skipped_binary_bytes = stats.skipped_binary_bytes.saturating_add(size);
+            options.log_skip(Path::new(&path), SkipReason::Binary, Some(size));
             continue;
         }
-        if b.is_ascii_alphanumeric() || b == b'_' {
-            current.push(u32::from(b));
+        if options.max_file_size.is_some_and(|max| size > max) {
+            stats.skipped_too_large = stats.skipped_too_large.saturating_add(1);
+            stats.skipped_too_large_bytes = stats.skipped_too_large_bytes.saturating_add(size);
+            options.log_skip(Path::new(&path), SkipReason::TooLarge, Some(size));
+            continue;
         }
+        if is_data_file(&path) {
+            continue;
+        }
+
+        let Some(mut file) =
+            scan_text_file(0, Arc::from(path.as_str()), None, &bytes, options, stats)
+        else {
+            stats.skipped_slow_file = stats.skipped_slow_file.saturating_add(1);
+            stats.skipped_slow_file_bytes = stats.skipped_slow_file_bytes.saturating_add(size);
+            options.log_skip(Path::new(&path), SkipReason::SlowFile, Some(size));
+            continue;
+        };
+        if keep_source {
+            file.source = Some(bytes);
+        }
+        files.push(file);
     }
+    Ok(files)
+}
 
-    if !current.is_empty() {
-        line_lens.push(current.len());
-        line_tokens.push(fold_u64_to_u32(fnv1a64_u32(&current)));
-        line_lines.push(line);
+/// `path` with `\` turned into `/` and empty or `.` components dropped.
+pub(super) fn normalize_virtual_path(path: &str) -> io::Result<String> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("virtual file path must be relative and stay inside its root: {path:?}"),
+        )
+    };
+    let path = path.replace('\\', "/");
+    let bytes = path.as_bytes();
+    if path.starts_with('/') || (bytes.len() >= 2 && bytes[1] == b':') {
+        return Err(invalid());
+    }
+    let mut parts = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return Err(invalid()),
+            part => parts.push(part),
+        }
+    }
+    if parts.is_empty() {
+        return Err(invalid());
     }
+    Ok(parts.join("/"))
+}
 
-    LineNormalizedText {
-        line_tokens,
-        line_lines,
-        line_lens,
+/// Normalize and tokenize one file for the report detectors, applying
+/// [`ScanOptions::max_tokens_per_file`]; `None` when the analysis exceeded
+/// [`ScanOptions::max_file_time`] (the caller counts and logs the skip).
+pub(super) fn scan_text_file(
+    repo_id: usize,
+    rel_path: Arc<str>,
+    abs_path: Option<PathBuf>,
+    bytes: &[u8],
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) -> Option<ScannedTextFile> {
+    let deadline = FileDeadline::start(options.max_file_time);
+    let text_bytes = normalize_unicode(bytes, options.unicode_normalization);
+    let text = String::from_utf8_lossy(&text_bytes);
+    let code_norm = normalize_for_code_spans(&text_bytes);
+    let line_norm = normalize_lines_for_dup_detection(&text_bytes);
+    let tokenized =
+        tokenize_for_dup_detection_within(&text, comment_syntax_for_path(&rel_path), deadline);
+    let test_ranges = cfg_test_line_ranges(&text_bytes);
+    let symbols = public_symbols(&text, &rel_path);
+    let imports = imported_paths(&text, &rel_path);
+    let license = detect_license(&text).map(Arc::from);
+    let mut tokenized = tokenized.filter(|_| !deadline.expired())?;
+    if let Some(cap) = options.max_tokens_per_file
+        && tokenized.tokens.len() > cap
+    {
+        tokenized.tokens.truncate(cap);
+        tokenized.token_lines.truncate(cap);
+        stats.skipped_token_cap = stats.skipped_token_cap.saturating_add(1);
     }
+    let blocks = parse_brace_blocks(&tokenized.tokens, &tokenized.token_lines);
+    let scopes = named_scopes(&text, &rel_path, &blocks);
+
+    Some(ScannedTextFile {
+        repo_id,
+        path: rel_path,
+        abs_path,
+        content_hash: fnv1a64(bytes),
+        line_count: count_lines(bytes),
+        code_chars: code_norm.chars,
+        code_line_starts: code_norm.line_starts,
+        line_tokens: line_norm.line_tokens,
+        line_token_lines: line_norm.line_lines,
+        line_token_char_lens: line_norm.line_lens,
+        tokens: tokenized.tokens,
+        token_lines: tokenized.token_lines,
+        blocks,
+        test_ranges,
+        symbols,
+        imports,
+        license,
+        scopes,
+        source: None,
+    })
+}
+
+fn count_lines(bytes: &[u8]) -> u32 {
+    let line_ends = (0..bytes.len()).filter(|&i| is_line_end(bytes, i)).count();
+    let trailing = usize::from(bytes.last().is_some_and(|&b| b != b'\n' && b != b'\r'));
+    u32::try_from(line_ends + trailing).unwrap_or(u32::MAX)
 }
@@ -0,0 +1,461 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::context::NamedScope;
+use crate::progress::ScanPhase;
+use crate::scan::{
+    CASE_INSENSITIVE_PATHS, path_key, read_files_at_git_ref, repo_label, validate_roots,
+};
+use crate::tokenize::BlockNode;
+use crate::types::{LineRange, ScanOptions, ScanOutcome, ScanStats};
+use crate::util::read_unchanged;
+
+use super::ScannedTextFile;
+use super::coverage::CoveredLines;
+use super::scan_files::{scan_text_files_for_report, scan_virtual_files};
+
+const MAGIC: &[u8; 4] = b"DCS\0";
+const FORMAT_VERSION: u32 = 8;
+/// Upper bound for a single up-front allocation while decoding (guards corrupt length fields).
+const MAX_PREALLOC: usize = 1 << 16;
+
+/// Pre-tokenized corpus that later report scans can compare against without re-reading it.
+///
+/// Build one with [`build_corpus_snapshot`], persist it with [`CorpusSnapshot::write_to`], and
+/// pass it to [`generate_duplication_report_against_snapshot`]. The binary format is versioned
+/// and little-endian; snapshots from other format versions are rejected.
+#[derive(Debug)]
+pub struct CorpusSnapshot {
+    pub(super) repo_labels: Vec<Arc<str>>,
+    /// Canonical path of each root (by repo id); empty for roots of virtual files.
+    pub(super) root_paths: Vec<Arc<str>>,
+    pub(super) files: Vec<ScannedTextFile>,
+}
+
+impl CorpusSnapshot {
+    pub fn repo_labels(&self) -> impl Iterator<Item = &str> {
+        self.repo_labels.iter().map(|label| label.as_ref())
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Combine the per-shard partials of one scan into one snapshot.
+    ///
+    /// Every partial must have been taken of the same roots, in the same order (compared by
+    /// canonical path); otherwise this is an `InvalidInput` error. When the same root-relative
+    /// path appears in more than one partial, the first copy wins (paths differing only in case
+    /// are the same path where [`crate::scan::CASE_INSENSITIVE_PATHS`] holds).
+    pub fn merge(snapshots: impl IntoIterator<Item = CorpusSnapshot>) -> io::Result<Self> {
+        let mut snapshots = snapshots.into_iter();
+        let Some(first) = snapshots.next() else {
+            return Ok(Self {
+                repo_labels: Vec::new(),
+                root_paths: Vec::new(),
+                files: Vec::new(),
+            });
+        };
+        let mut seen: HashSet<(usize, Arc<str>)> = HashSet::new();
+        let mut files = Vec::new();
+        let repo_labels = first.repo_labels.clone();
+        let root_paths = first.root_paths.clone();
+
+        for snapshot in std::iter::once(first).chain(snapshots) {
+            if snapshot.root_paths != root_paths || snapshot.repo_labels != repo_labels {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "snapshot partials were taken of different roots: [{}] vs [{}]",
+                        root_paths.join(", "),
+                        snapshot.root_paths.join(", "),
+                    ),
+                ));
+            }
+            for file in snapshot.files {
+                let key = match path_key(&file.path, CASE_INSENSITIVE_PATHS) {
+                    Cow::Borrowed(_) => Arc::clone(&file.path),
+                    Cow::Owned(folded) => Arc::from(folded),
+                };
+                if seen.insert((file.repo_id, key)) {
+                    files.push(file);
+                }
+            }
+        }
+
+        Ok(Self {
+            repo_labels,
+            root_paths,
+            files,
+        })
+    }
+
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        w.write_all(MAGIC)?;
+        write_u32(&mut w, FORMAT_VERSION)?;
+
+        write_len(&mut w, self.repo_labels.len())?;
+        for (label, path) in self.repo_labels.iter().zip(&self.root_paths) {
+            write_str(&mut w, label)?;
+            write_str(&mut w, path)?;
+        }
+
+        write_len(&mut w, self.files.len())?;
+        for file in &self.files {
+            write_len(&mut w, file.repo_id)?;
+            write_str(&mut w, &file.path)?;
+            write_u32(&mut w, file.line_count)?;
+            write_len(&mut w, file.code_chars.len())?;
+            w.write_all(&file.code_chars)?;
+            write_u32s(&mut w, &file.code_line_starts)?;
+            write_u32s(&mut w, &file.line_tokens)?;
+            write_u32s(&mut w, &file.line_token_lines)?;
+            write_len(&mut w, file.line_token_char_lens.len())?;
+            for &len in &file.line_token_char_lens {
+                write_len(&mut w, len)?;
+            }
+            write_u32s(&mut w, &file.tokens)?;
+            write_u32s(&mut w, &file.token_lines)?;
+            write_len(&mut w, file.blocks.len())?;
+            for block in &file.blocks {
+                write_len(&mut w, block.start_token)?;
+                write_len(&mut w, block.end_token)?;
+                write_u32(&mut w, block.start_line)?;
+                write_u32(&mut w, block.end_line)?;
+                write_u32(&mut w, block.depth)?;
+                write_len(&mut w, block.children.len())?;
+                for &child in &block.children {
+                    write_len(&mut w, child)?;
+                }
+            }
+            write_len(&mut w, file.test_ranges.len())?;
+            for range in &file.test_ranges {
+                write_u32(&mut w, range.start_line)?;
+                write_u32(&mut w, range.end_line)?;
+            }
+            write_len(&mut w, file.symbols.len())?;
+            for symbol in &file.symbols {
+                write_str(&mut w, symbol)?;
+            }
+            write_len(&mut w, file.imports.len())?;
+            for import in &file.imports {
+                write_str(&mut w, import)?;
+            }
+            write_str(&mut w, file.license.as_deref().unwrap_or_default())?;
+            write_len(&mut w, file.scopes.len())?;
+            for scope in &file.scopes {
+                write_str(&mut w, &scope.name)?;
+                write_u32(&mut w, scope.depth)?;
+                write_u32(&mut w, scope.start_line)?;
+                write_u32(&mut w, scope.end_line)?;
+            }
+            // The bytes as scanned, so a report over the snapshot can preview and locate code
+            // without the checkout; empty when the file changed since.
+            let source = match &file.source {
+                Some(source) => Cow::Borrowed(source.as_slice()),
+                None => Cow::Owned(
+                    file.abs_path
+                        .as_deref()
+                        .and_then(|path| read_unchanged(path, file.content_hash))
+                        .unwrap_or_default(),
+                ),
+            };
+            write_len(&mut w, source.len())?;
+            w.write_all(&source)?;
+        }
+        w.flush()
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> io::Result<Self> {
+        let mut r = BufReader::new(reader);
+
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a dup-code-check snapshot"));
+        }
+        let version = read_u32(&mut r)?;
+        if version != FORMAT_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported snapshot format version {version} (expected {FORMAT_VERSION})"
+            )));
+        }
+
+        let repo_count = read_len(&mut r)?;
+        let mut repo_labels = Vec::with_capacity(repo_count.min(MAX_PREALLOC));
+        let mut root_paths = Vec::with_capacity(repo_count.min(MAX_PREALLOC));
+        for _ in 0..repo_count {
+            repo_labels.push(Arc::<str>::from(read_string(&mut r)?));
+            root_paths.push(Arc::<str>::from(read_string(&mut r)?));
+        }
+
+        let file_count = read_len(&mut r)?;
+        let mut files = Vec::with_capacity(file_count.min(MAX_PREALLOC));
+        for _ in 0..file_count {
+            let repo_id = read_len(&mut r)?;
+            if repo_id >= repo_labels.len() {
+                return Err(invalid_data("snapshot file references an unknown repo"));
+            }
+            let path = Arc::<str>::from(read_string(&mut r)?);
+            let line_count = read_u32(&mut r)?;
+            let code_chars = read_bytes(&mut r)?;
+            let code_line_starts = read_u32s(&mut r)?;
+            let line_tokens = read_u32s(&mut r)?;
+            let line_token_lines = read_u32s(&mut r)?;
+            let len = read_len(&mut r)?;
+            let mut line_token_char_lens = Vec::with_capacity(len.min(MAX_PREALLOC));
+            for _ in 0..len {
+                line_token_char_lens.push(read_len(&mut r)?);
+            }
+            let tokens = read_u32s(&mut r)?;
+            let token_lines = read_u32s(&mut r)?;
+            let block_count = read_len(&mut r)?;
+            let mut blocks = Vec::with_capacity(block_count.min(MAX_PREALLOC));
+            for _ in 0..block_count {
+                let start_token = read_len(&mut r)?;
+                let end_token = read_len(&mut r)?;
+                let start_line = read_u32(&mut r)?;
+                let end_line = read_u32(&mut r)?;
+                let depth = read_u32(&mut r)?;
+                let child_count = read_len(&mut r)?;
+                let mut children = Vec::with_capacity(child_count.min(MAX_PREALLOC));
+                for _ in 0..child_count {
+                    children.push(read_len(&mut r)?);
+                }
+                blocks.push(BlockNode {
+                    start_token,
+                    end_token,
+                    start_line,
+                    end_line,
+                    depth,
+                    children,
+                });
+            }
+            let range_count = read_len(&mut r)?;
+            let mut test_ranges = Vec::with_capacity(range_count.min(MAX_PREALLOC));
+            for _ in 0..range_count {
+                test_ranges.push(LineRange {
+                    start_line: read_u32(&mut r)?,
+                    end_line: read_u32(&mut r)?,
+                });
+            }
+            let symbol_count = read_len(&mut r)?;
+            let mut symbols = Vec::with_capacity(symbol_count.min(MAX_PREALLOC));
+            for _ in 0..symbol_count {
+                symbols.push(read_string(&mut r)?);
+            }
+            let import_count = read_len(&mut r)?;
+            let mut imports = Vec::with_capacity(import_count.min(MAX_PREALLOC));
+            for _ in 0..import_count {
+                imports.push(read_string(&mut r)?);
+            }
+            let license = Some(read_string(&mut r)?)
+                .filter(|license| !license.is_empty())
+                .map(Arc::from);
+            let scope_count = read_len(&mut r)?;
+            let mut scopes = Vec::with_capacity(scope_count.min(MAX_PREALLOC));
+            for _ in 0..scope_count {
+                scopes.push(NamedScope {
+                    name: Arc::from(read_string(&mut r)?),
+                    depth: read_u32(&mut r)?,
+                    start_line: read_u32(&mut r)?,
+                    end_line: read_u32(&mut r)?,
+                });
+            }
+            let source = Some(read_bytes(&mut r)?).filter(|source| !source.is_empty());
+
+            if line_token_lines.len() != line_tokens.len()
+                || line_token_char_lens.len() != line_tokens.len()
+                || token_lines.len() != tokens.len()
+                || blocks.iter().any(|b| {
+                    b.start_token > b.end_token
+                        || b.end_token >= tokens.len()
+                        || b.children.iter().any(|&c| c >= block_count)
+                })
+            {
+                return Err(invalid_data(format!(
+                    "snapshot file {path} is inconsistent"
+                )));
+            }
+
+            files.push(ScannedTextFile {
+                repo_id,
+                path,
+                abs_path: None,
+                content_hash: 0,
+                line_count,
+                code_chars,
+                code_line_starts,
+                line_tokens,
+                line_token_lines,
+                line_token_char_lens,
+                tokens,
+                token_lines,
+                blocks,
+                test_ranges,
+                symbols,
+                imports,
+                license,
+                scopes,
+                source,
+            });
+        }
+
+        Ok(Self {
+            repo_labels,
+            root_paths,
+            files,
+        })
+    }
+}
+
+/// The canonical path of each of `roots`, as recorded in [`CorpusSnapshot::root_paths`].
+fn canonical_root_paths(roots: &[PathBuf]) -> io::Result<Vec<Arc<str>>> {
+    roots
+        .iter()
+        .map(|root| Ok(Arc::from(root.canonicalize()?.to_string_lossy().as_ref())))
+        .collect()
+}
+
+/// Scan `roots` and capture the tokenized files as a [`CorpusSnapshot`].
+///
+/// Uses the same scan budgets and normalization as [`super::generate_duplication_report`].
+pub fn build_corpus_snapshot(
+    roots: &[PathBuf],
+    options: &ScanOptions,
+) -> io::Result<ScanOutcome<CorpusSnapshot>> {
+    let mut stats = ScanStats::default();
+    let roots = validate_roots(roots, &mut stats)?;
+    options.validate_for_report()?;
+
+    let (repo_labels, files, ..) =
+        scan_text_files_for_report(&roots, options, &mut stats, &mut CoveredLines::default())?;
+    options.emit_progress(ScanPhase::Finished, &stats);
+    Ok(ScanOutcome {
+        result: CorpusSnapshot {
+            repo_labels,
+            root_paths: canonical_root_paths(&roots)?,
+            files,
+        },
+        stats,
+    })
+}
+
+/// Capture the files tracked at `git_ref` (a commit, branch or tag) of the git repository at
+/// `repo` as a [`CorpusSnapshot`], without checking the ref out.
+///
+/// The snapshot has one root, labeled as a scan of `repo` would be, so snapshots of two refs can
+/// be compared with [`super::clone_genealogy`]. Files get the per-file limits of a root scan plus
+/// [`ScanOptions::ignore_dirs`] and [`ScanOptions::ignore_patterns`]; `.gitignore` does not
+/// matter since only tracked files are read, and data files are skipped. Errors when git cannot
+/// be run or `git_ref` names no commit or tree.
+pub fn build_corpus_snapshot_at_git_ref(
+    repo: &Path,
+    git_ref: &str,
+    options: &ScanOptions,
+) -> io::Result<ScanOutcome<CorpusSnapshot>> {
+    let mut stats = ScanStats::default();
+    validate_roots(&[repo.to_path_buf()], &mut stats)?;
+    options.validate_for_report()?;
+
+    let files = read_files_at_git_ref(repo, git_ref, options, &mut stats)?;
+    let files = scan_virtual_files(files, true, options, &mut stats)?;
+    options.emit_progress(ScanPhase::Finished, &stats);
+    Ok(ScanOutcome {
+        result: CorpusSnapshot {
+            repo_labels: vec![Arc::from(repo_label(repo, 0))],
+            root_paths: canonical_root_paths(&[repo.to_path_buf()])?,
+            files,
+        },
+        stats,
+    })
+}
+
+/// Generate a report over `roots` plus the files captured in `snapshot`.
+///
+/// Snapshot roots are appended after `roots` (their `repo_id`s start at the number of distinct
+/// roots), so
+/// `cross_repo_only` can be used to report only matches against the snapshot. Snapshot files
+/// take part in every span/similarity detector but not in `file_duplicates`, which needs the
+/// original bytes.
+pub fn generate_duplication_report_against_snapshot(
+    roots: &[PathBuf],
+    snapshot: CorpusSnapshot,
+    options: &ScanOptions,
+) -> io::Result<ScanOutcome<crate::types::DuplicationReport>> {
+    super::generate_report(roots, Some(snapshot), ScanStats::default(), options)
+}
+
+/// Generate a report over the files captured in `snapshot` alone (e.g. merged shard partials).
+///
+/// As with [`generate_duplication_report_against_snapshot`], `file_duplicates` is always empty.
+pub fn generate_duplication_report_from_snapshot(
+    snapshot: CorpusSnapshot,
+    options: &ScanOptions,
+) -> io::Result<ScanOutcome<crate::types::DuplicationReport>> {
+    super::generate_report(&[], Some(snapshot), ScanStats::default(), options)
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn write_u32<W: Write>(w: &mut W, value: u32) -> io::Result<()> {
+    w.write_all(&value.to_le_bytes())
+}
+
+fn write_len<W: Write>(w: &mut W, value: usize) -> io::Result<()> {
+    w.write_all(&(value as u64).to_le_bytes())
+}
+
+fn write_str<W: Write>(w: &mut W, value: &str) -> io::Result<()> {
+    write_len(w, value.len())?;
+    w.write_all(value.as_bytes())
+}
+
+fn write_u32s<W: Write>(w: &mut W, values: &[u32]) -> io::Result<()> {
+    write_len(w, values.len())?;
+    for &value in values {
+        write_u32(w, value)?;
+    }
+    Ok(())
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_len<R: Read>(r: &mut R) -> io::Result<usize> {
+    let mut buf = [0u8; 8];
+    r.read_exact(&mut buf)?;
+    usize::try_from(u64::from_le_bytes(buf)).map_err(|_| invalid_data("length overflows usize"))
+}
+
+fn read_bytes<R: Read>(r: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_len(r)?;
+    let mut out = Vec::with_capacity(len.min(MAX_PREALLOC));
+    let read = r.take(len as u64).read_to_end(&mut out)?;
+    if read != len {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
+    Ok(out)
+}
+
+fn read_string<R: Read>(r: &mut R) -> io::Result<String> {
+    String::from_utf8(read_bytes(r)?).map_err(|_| invalid_data("snapshot string is not UTF-8"))
+}
+
+fn read_u32s<R: Read>(r: &mut R) -> io::Result<Vec<u32>> {
+    let len = read_len(r)?;
+    let mut out = Vec::with_capacity(len.min(MAX_PREALLOC));
+    for _ in 0..len {
+        out.push(read_u32(r)?);
+    }
+    Ok(out)
+}
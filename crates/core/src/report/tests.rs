@@ -1,14 +1,19 @@
 use std::fs;
 use std::io;
-use std::path::PathBuf;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use super::*;
+use crate::language::{DEFAULT_COMMENT_SYNTAX, comment_syntax_for_path};
 use crate::tokenize::tokenize_for_dup_detection;
-use crate::util::{line_for_pos, normalize_for_code_spans, normalize_whitespace};
+use crate::util::{
+    cfg_test_line_ranges, line_for_pos, normalize_for_code_spans,
+    normalize_lines_for_dup_detection, normalize_whitespace, read_line_bytes, split_lines,
+};
 use crate::{
-    DEFAULT_MAX_FILE_SIZE_BYTES, find_duplicate_code_spans, find_duplicate_code_spans_with_stats,
-    find_duplicate_files,
+    DEFAULT_MAX_FILE_SIZE_BYTES, DuplicateSpanGroup, GenealogyChange, GroupSuppression, LineRange,
+    RepoScanStats, ReportSection, ReportSort, SimilarityWindows, VirtualFile,
+    find_duplicate_code_spans, find_duplicate_code_spans_with_stats, find_duplicate_files,
 };
 
 #[test]
@@ -19,6 +24,76 @@ c";
     assert_eq!(normalize_whitespace(input), b"abc");
 }
 
+#[test]
+fn line_numbers_agree_across_newline_conventions() {
+    let lf = "fn a() {\n  one();\n}\n\n// note\n#[cfg(test)]\nfn b() { \"x\\\ny\" }\nlast";
+    let lines = |text: &str| {
+        let bytes = text.as_bytes();
+        let code = normalize_for_code_spans(bytes);
+        let code_lines: Vec<u32> = (0..code.chars.len())
+            .map(|i| line_for_pos(&code.line_starts, i))
+            .collect();
+        let split: Vec<Vec<u8>> = split_lines(bytes).map(<[u8]>::to_vec).collect();
+        let mut read = Vec::new();
+        let mut reader = io::BufReader::with_capacity(1, bytes);
+        let mut buf = Vec::new();
+        while read_line_bytes(&mut reader, &mut buf).unwrap() {
+            read.push(std::mem::take(&mut buf));
+        }
+        (
+            code_lines,
+            normalize_lines_for_dup_detection(bytes).line_lines,
+            tokenize_for_dup_detection(text, DEFAULT_COMMENT_SYNTAX).token_lines,
+            cfg_test_line_ranges(bytes),
+            split,
+            read,
+        )
+    };
+
+    let expected = lines(lf);
+    assert_eq!(expected.1, [1, 2, 5, 6, 7, 8, 9]);
+    assert_eq!(
+        expected.3,
+        [LineRange {
+            start_line: 6,
+            end_line: 8
+        }]
+    );
+    assert_eq!(expected.4.len(), 9);
+    assert_eq!(expected.4, expected.5);
+    for eol in ["\r\n", "\r"] {
+        assert_eq!(lines(&lf.replace('\n', eol)), expected, "{eol:?}");
+    }
+    let mixed: String = lf
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| format!("{line}{}", ["\r\n", "\r", "\n"][i % 3]))
+        .collect();
+    assert_eq!(lines(mixed.trim_end()), expected);
+}
+
+#[test]
+fn code_span_lines_match_for_lf_crlf_and_cr_files() -> io::Result<()> {
+    let root = temp_dir("newline_conventions");
+    fs::create_dir_all(&root)?;
+    let body = "// header\n\nfn compute_total(values: &[u32]) -> u32 {\n    values.iter().map(|v| v * 2).sum()\n}\n";
+    fs::write(root.join("lf.rs"), body)?;
+    fs::write(root.join("crlf.rs"), body.replace('\n', "\r\n"))?;
+    fs::write(root.join("cr.rs"), body.replace('\n', "\r"))?;
+
+    let options = ScanOptions {
+        min_match_len: 20,
+        ..ScanOptions::default()
+    };
+    let groups = find_duplicate_code_spans(&[root], &options)?;
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].occurrences.len(), 3);
+    for occ in &groups[0].occurrences {
+        assert_eq!((occ.start_line, occ.end_line), (1, 4), "{}", occ.path);
+    }
+    Ok(())
+}
+
 #[test]
 fn finds_duplicates_within_single_repo() -> io::Result<()> {
     let root = temp_dir("single");
@@ -150,6 +225,44 @@ fn scan_stats_counts_bucket_truncation() -> io::Result<()> {
     Ok(())
 }
 
+#[test]
+fn scan_stats_display_summarizes_nonzero_counters() {
+    let mut repo = ScanStats {
+        candidate_files: 3,
+        scanned_files: 2,
+        scanned_bytes: 4096,
+        skipped_binary: 1,
+        skipped_binary_bytes: 300,
+        ..ScanStats::default()
+    };
+    let mut stats = ScanStats {
+        fingerprints: 20,
+        skipped_bucket_truncated: 5,
+        per_repo: vec![RepoScanStats::new(0, "a".to_string(), repo.clone())],
+        ..repo.clone()
+    };
+    assert_eq!(
+        stats.nonzero_skips(),
+        [("binary", 1), ("bucket_truncated", 5)]
+    );
+    assert_eq!(
+        stats.to_string(),
+        "candidates=3 scanned=2 bytes=4096\nfingerprints=20 per_kb=5.0\nskipped:\n- binary=1 bytes=300\n- bucket_truncated=5\n"
+    );
+
+    repo.skipped_binary = 0;
+    repo.skipped_binary_bytes = 0;
+    stats
+        .per_repo
+        .push(RepoScanStats::new(1, "b".to_string(), repo));
+    assert!(
+        stats.to_string().ends_with(
+            "per root:\n- [a] candidates=3 scanned=2 bytes=4096 binary=1 binary_bytes=300\n- [b] candidates=3 scanned=2 bytes=4096\n"
+        ),
+        "{stats}"
+    );
+}
+
 #[test]
 fn code_spans_reject_min_match_len_zero() -> io::Result<()> {
     let root = temp_dir("invalid_min_match_len");
@@ -165,6 +278,51 @@ fn code_spans_reject_min_match_len_zero() -> io::Result<()> {
     Ok(())
 }
 
+#[test]
+fn adaptive_window_thins_fingerprints_of_long_files_but_keeps_long_matches() -> io::Result<()> {
+    let root = temp_dir("adaptive_window");
+    fs::create_dir_all(&root)?;
+    let shared: String = (0..40)
+        .map(|i| format!("ledger.post(account_{i}, amount * {i} + fee_{i});\n"))
+        .collect();
+    for name in ["a", "b"] {
+        let filler: String = (0..3000)
+            .map(|i| format!("let {name}_{i} = step_{name}({i}, {});\n", i * 7 + 3))
+            .collect();
+        fs::write(root.join(format!("{name}.rs")), format!("{filler}{shared}"))?;
+    }
+
+    let fixed =
+        find_duplicate_code_spans_with_stats(std::slice::from_ref(&root), &ScanOptions::default())?;
+    let mut options = ScanOptions {
+        adaptive_window: Some(4),
+        ..ScanOptions::default()
+    };
+    let adaptive = find_duplicate_code_spans_with_stats(std::slice::from_ref(&root), &options)?;
+    assert!(
+        adaptive.stats.fingerprints * 2 < fixed.stats.fingerprints,
+        "{} vs {}",
+        adaptive.stats.fingerprints,
+        fixed.stats.fingerprints
+    );
+    assert!(adaptive.stats.fingerprints_per_kb() < fixed.stats.fingerprints_per_kb());
+    for outcome in [&fixed, &adaptive] {
+        assert!(
+            outcome
+                .result
+                .iter()
+                .any(|group| group.normalized_len >= 1300 && group.occurrences.len() == 2),
+            "{:?}",
+            outcome.result
+        );
+    }
+
+    options.adaptive_window = Some(1);
+    let err = find_duplicate_code_spans_with_stats(&[root], &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
 #[test]
 fn report_rejects_invalid_similarity_threshold() -> io::Result<()> {
     let root = temp_dir("invalid_similarity_threshold");
@@ -271,6 +429,562 @@ fn report_truncates_file_duplicates() -> io::Result<()> {
     };
     let report = generate_duplication_report(&[root], &options)?;
     assert_eq!(report.file_duplicates.len(), 1);
+    let total = report.section_total(ReportSection::FileDuplicates);
+    assert_eq!(total.total_groups(), 2);
+    assert!(total.truncated());
+
+    let code_spans = report.section_total(ReportSection::CodeSpanDuplicates);
+    assert_eq!(code_spans.total_groups(), report.code_span_duplicates.len());
+    assert!(!code_spans.truncated());
+    assert_eq!(report.section_totals.len(), ReportSection::ALL.len());
+    Ok(())
+}
+
+#[test]
+fn suppressed_groups_are_dropped_before_truncation() -> io::Result<()> {
+    let root = temp_dir("report_suppressions");
+    fs::create_dir_all(&root)?;
+    for (name, text) in [
+        ("a", "same1"),
+        ("b", "same1"),
+        ("c", "same2"),
+        ("d", "same2"),
+        ("e", "same3"),
+        ("f", "same3"),
+    ] {
+        fs::write(root.join(format!("{name}.txt")), text)?;
+    }
+    let roots = std::slice::from_ref(&root);
+    let hashes: Vec<u64> = generate_duplication_report(roots, &ScanOptions::default())?
+        .file_duplicates
+        .iter()
+        .map(|group| group.content_hash)
+        .collect();
+    assert_eq!(hashes.len(), 3);
+
+    let mut options = ScanOptions {
+        max_report_items: 1,
+        ..ScanOptions::default()
+    };
+    options.group_suppressions = vec![
+        GroupSuppression::new(Some(ReportSection::FileDuplicates), hashes[0], true),
+        GroupSuppression::new(None, hashes[2], false),
+        GroupSuppression::new(Some(ReportSection::CodeSpanDuplicates), hashes[1], true),
+    ];
+    let outcome = generate_duplication_report_with_stats(roots, &options)?;
+    fs::remove_dir_all(&root)?;
+
+    // The next group moves up into the page, and the totals no longer count the dropped one.
+    let report = outcome.result;
+    assert_eq!(report.file_duplicates.len(), 1);
+    assert_eq!(report.file_duplicates[0].content_hash, hashes[1]);
+    assert_eq!(
+        report
+            .section_total(ReportSection::FileDuplicates)
+            .total_groups(),
+        2
+    );
+    assert_eq!(outcome.stats.suppressed_groups, 1);
+    // The inactive entry matches a group past the cut; the last one names the wrong section.
+    assert_eq!(outcome.stats.matched_suppressions, [0, 1]);
+    Ok(())
+}
+
+#[test]
+fn per_detector_limits_override_max_report_items() -> io::Result<()> {
+    let root = temp_dir("report_per_detector_limits");
+    fs::create_dir_all(&root)?;
+    for (name, text) in [
+        ("a", "same1"),
+        ("b", "same1"),
+        ("c", "same2"),
+        ("d", "same2"),
+    ] {
+        fs::write(root.join(format!("{name}.txt")), text)?;
+    }
+
+    let mut options = ScanOptions {
+        max_report_items: 1,
+        ..ScanOptions::default()
+    };
+    options
+        .max_items_per_detector
+        .insert(ReportSection::FileDuplicates, 5);
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert_eq!(report.file_duplicates.len(), 2);
+    assert!(
+        !report
+            .section_total(ReportSection::FileDuplicates)
+            .truncated()
+    );
+
+    // A section can be listed even when every other one is cut to nothing.
+    options.max_report_items = 0;
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert_eq!(report.file_duplicates.len(), 2);
+
+    options
+        .max_items_per_detector
+        .insert(ReportSection::FileDuplicates, 1);
+    options.max_report_items = 200;
+    let report = generate_duplication_report(&[root], &options)?;
+    assert_eq!(report.file_duplicates.len(), 1);
+    assert!(
+        report
+            .section_total(ReportSection::FileDuplicates)
+            .truncated()
+    );
+    Ok(())
+}
+
+#[test]
+fn report_prunes_groups_nested_in_a_longer_group() -> io::Result<()> {
+    let root = temp_dir("report_prune_contained");
+    fs::create_dir_all(&root)?;
+    let code = "function outer(a, b) {\n  let total = a + b;\n  if (total > 10) {\n    total = total - 10;\n    log(total, a, b);\n  }\n  return total * 2;\n}\n";
+    fs::write(root.join("a.js"), code)?;
+    fs::write(root.join("b.js"), code)?;
+
+    let options = ScanOptions {
+        min_match_len: 5,
+        min_token_len: 5,
+        prune_contained_groups: false,
+        ..ScanOptions::default()
+    };
+    let block_lines = |options: &ScanOptions| -> io::Result<Vec<(u32, u32)>> {
+        let report = generate_duplication_report(std::slice::from_ref(&root), options)?;
+        Ok(report
+            .block_duplicates
+            .iter()
+            .map(|group| {
+                (
+                    group.occurrences[0].start_line,
+                    group.occurrences[0].end_line,
+                )
+            })
+            .collect())
+    };
+    assert_eq!(block_lines(&options)?, vec![(1, 8), (3, 6)]);
+
+    let pruned = ScanOptions {
+        prune_contained_groups: true,
+        ..options.clone()
+    };
+    assert_eq!(block_lines(&pruned)?, vec![(1, 8)]);
+    Ok(())
+}
+
+#[test]
+fn report_keeps_separate_clones_on_one_minified_line() -> io::Result<()> {
+    let root = temp_dir("report_prune_same_line");
+    fs::create_dir_all(&root)?;
+    let alpha = "function alphaSum(x){var s=0;for(var i=0;i<x.length;i++){s+=x[i]*2;}return s;}";
+    let beta = "function betaValidate(y){return y.trim().toLowerCase();}";
+    fs::write(root.join("a.js"), format!("{alpha}var q=1;{beta}\n"))?;
+    fs::write(
+        root.join("b.js"),
+        format!("{alpha}window.zz=[7,8,9];{beta}\n"),
+    )?;
+
+    let options = ScanOptions {
+        min_match_len: 30,
+        prune_contained_groups: true,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(&[root], &options)?;
+    let mut cols: Vec<(u32, u32)> = report
+        .code_span_duplicates
+        .iter()
+        .map(|group| (group.occurrences[0].start_col, group.occurrences[0].end_col))
+        .collect();
+    cols.sort_unstable();
+    assert_eq!(cols.len(), 2, "{cols:?}");
+    assert!(cols[0].1 < cols[1].0, "{cols:?}");
+    Ok(())
+}
+
+#[test]
+fn report_pairs_files_with_nearly_identical_imports() -> io::Result<()> {
+    let root = temp_dir("report_import_duplicates");
+    fs::create_dir_all(&root)?;
+    let header = "import os, sys\nimport json\nimport re\nfrom collections import OrderedDict\n";
+    fs::write(root.join("a.py"), format!("{header}\nprint(1)\n"))?;
+    fs::write(
+        root.join("b.py"),
+        format!("{header}import logging\n\ndef main():\n    pass\n"),
+    )?;
+    fs::write(root.join("c.py"), "import os\nimport sys\nimport json\n")?;
+
+    let report = generate_duplication_report(std::slice::from_ref(&root), &ScanOptions::default())?;
+    assert_eq!(report.import_duplicates.len(), 1);
+    let pair = &report.import_duplicates[0];
+    assert_eq!((pair.a().path(), pair.b().path()), ("a.py", "b.py"));
+    assert!((pair.score() - 5.0 / 6.0).abs() < 1e-9);
+    assert_eq!(
+        pair.shared_imports(),
+        ["collections.OrderedDict", "json", "os", "re", "sys"]
+    );
+
+    let options = ScanOptions {
+        import_similarity_threshold: 0.5,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let pairs: Vec<(&str, &str)> = report
+        .import_duplicates
+        .iter()
+        .map(|pair| (pair.a().path(), pair.b().path()))
+        .collect();
+    assert_eq!(
+        pairs,
+        [("a.py", "b.py"), ("a.py", "c.py"), ("b.py", "c.py")]
+    );
+    Ok(())
+}
+
+#[test]
+fn report_groups_files_declaring_the_same_public_api() -> io::Result<()> {
+    let root = temp_dir("report_symbol_duplicates");
+    fs::create_dir_all(&root)?;
+    fs::write(
+        root.join("store.rs"),
+        "pub struct Store;\npub fn load() -> Store {\n    Store\n}\npub fn save(_: &Store) {}\nfn helper() {}\n",
+    )?;
+    fs::write(
+        root.join("store_copy.rs"),
+        "pub struct Store {\n    items: Vec<u8>,\n}\npub fn save(store: &Store) {\n    drop(store);\n}\npub fn load() -> Store {\n    Store { items: vec![] }\n}\n",
+    )?;
+    fs::write(
+        root.join("other.rs"),
+        "pub struct Store;\npub fn load() -> Store {\n    Store\n}\n",
+    )?;
+
+    let report = generate_duplication_report(std::slice::from_ref(&root), &ScanOptions::default())?;
+    assert_eq!(report.symbol_duplicates.len(), 1);
+    let group = &report.symbol_duplicates[0];
+    assert_eq!(group.symbols, ["Store", "load", "save"]);
+    let paths: Vec<&str> = group.files.iter().map(|file| file.path()).collect();
+    assert_eq!(paths, ["store.rs", "store_copy.rs"]);
+    assert_eq!(
+        report
+            .section_total(ReportSection::SymbolDuplicates)
+            .total_groups(),
+        1
+    );
+
+    let options = ScanOptions {
+        symbol_min_count: 2,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert_eq!(report.symbol_duplicates.len(), 1);
+
+    let options = ScanOptions {
+        symbol_min_count: 4,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(report.symbol_duplicates.is_empty());
+    Ok(())
+}
+
+#[test]
+fn report_keeps_duplicate_data_files_apart_from_code() -> io::Result<()> {
+    let root = temp_dir("report_data_duplicates");
+    fs::create_dir_all(root.join("fixtures"))?;
+    fs::create_dir_all(root.join("golden"))?;
+
+    let rows: Vec<String> = (0..40)
+        .map(|i| format!("{{\"id\": {i}, \"name\": \"user{i}\", \"active\": true}}"))
+        .collect();
+    fs::write(
+        root.join("fixtures/users.json"),
+        format!("[\n  {}\n]\n", rows.join(",\n  ")),
+    )?;
+    // The same fixture, minified.
+    fs::write(
+        root.join("golden/users.json"),
+        format!("[{}]", rows.join(",")).replace(' ', ""),
+    )?;
+    fs::write(root.join("fixtures/small.yaml"), "name: demo\n")?;
+    fs::write(root.join("golden/small.yaml"), "name: demo\n")?;
+
+    let report = generate_duplication_report(std::slice::from_ref(&root), &ScanOptions::default())?;
+    assert_eq!(report.data_duplicates.len(), 1);
+    let paths: Vec<&str> = report.data_duplicates[0]
+        .files
+        .iter()
+        .map(|file| file.path())
+        .collect();
+    assert_eq!(paths, ["fixtures/users.json", "golden/users.json"]);
+    assert!(report.file_duplicates.is_empty());
+    assert!(report.code_span_duplicates.is_empty());
+    assert!(report.line_span_duplicates.is_empty());
+    assert_eq!(
+        report
+            .section_total(ReportSection::DataDuplicates)
+            .total_groups(),
+        1
+    );
+
+    let options = ScanOptions {
+        data_min_bytes: 1,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert_eq!(report.data_duplicates.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn report_scores_groups_and_drops_low_entropy_ones() -> io::Result<()> {
+    let root = temp_dir("report_min_entropy");
+    fs::create_dir_all(&root)?;
+    let code = "function outer(a, b) {\n  let total = a + b;\n  log(total, a, b);\n  return total * 2;\n}\n";
+    let braces = "}\n".repeat(40);
+    fs::write(root.join("a.js"), format!("// a\n{code}"))?;
+    fs::write(root.join("b.js"), format!("// b\n{code}"))?;
+    fs::write(root.join("c.js"), format!("// c\n{braces}"))?;
+    fs::write(root.join("d.js"), format!("// d\n{braces}"))?;
+
+    let base = ScanOptions {
+        min_match_len: 20,
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    let entropies = |options: &ScanOptions| -> io::Result<Vec<f64>> {
+        let report = generate_duplication_report(std::slice::from_ref(&root), options)?;
+        Ok(report
+            .token_span_duplicates
+            .iter()
+            .map(|group| {
+                let quality = group.quality.expect("report groups carry quality");
+                assert!((0.0..=1.0).contains(&quality.punctuation_ratio));
+                quality.token_entropy
+            })
+            .collect())
+    };
+    let all = entropies(&base)?;
+    assert!(all.contains(&0.0));
+    assert!(all.iter().any(|&entropy| entropy >= 1.0));
+
+    let options = ScanOptions {
+        min_entropy: 1.0,
+        ..base.clone()
+    };
+    let kept = entropies(&options)?;
+    assert!(!kept.is_empty());
+    assert!(kept.iter().all(|&entropy| entropy >= 1.0));
+
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let diversity = report.token_span_duplicates[0]
+        .quality
+        .and_then(|quality| quality.identifier_diversity)
+        .expect("identifiers are read back from the source");
+    assert!(diversity > 0.0 && diversity < 1.0);
+
+    let invalid = ScanOptions {
+        min_entropy: -1.0,
+        ..ScanOptions::default()
+    };
+    let err = generate_duplication_report(std::slice::from_ref(&root), &invalid).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn report_stop_sequences_seed_no_token_span_match() -> io::Result<()> {
+    let root = temp_dir("report_stop_sequences");
+    fs::create_dir_all(&root)?;
+    let getters: String = ["id", "name", "email", "age", "city", "zip"]
+        .iter()
+        .map(|field| format!("public String get{field}() {{ return {field}; }}\n"))
+        .collect();
+    fs::write(root.join("A.java"), format!("// a\n{getters}"))?;
+    fs::write(root.join("B.java"), format!("// b\n{getters}"))?;
+
+    let options = ScanOptions {
+        min_token_len: 20,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(report.token_span_duplicates.is_empty());
+
+    let cleared = ScanOptions {
+        stop_sequences: vec![("java".to_string(), String::new())],
+        ..options.clone()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &cleared)?;
+    assert_eq!(report.token_span_duplicates.len(), 1);
+
+    for invalid in [("klingon", "} else {"), ("java", "getName")] {
+        let options = ScanOptions {
+            stop_sequences: vec![(invalid.0.to_string(), invalid.1.to_string())],
+            ..options.clone()
+        };
+        let err = generate_duplication_report(std::slice::from_ref(&root), &options).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    Ok(())
+}
+
+#[test]
+fn clone_genealogy_classifies_groups_between_snapshots() -> io::Result<()> {
+    let base = temp_dir("clone_genealogy");
+    let kept = "fn kept(values: &[u32]) -> u32 {\n    let mut total = 0;\n    for v in values {\n        total += v * 3;\n    }\n    total\n}\n";
+    let gone = "struct Gone { a: i64, b: String }\nimpl Gone {\n    fn show(&self) -> String {\n        format!(\"{}-{}\", self.a, self.b)\n    }\n}\n";
+    let moving = "enum Mode { Fast, Slow }\nfn pick(mode: Mode) -> &'static str {\n    match mode {\n        Mode::Fast => \"fast\",\n        Mode::Slow => \"slow\",\n    }\n}\n";
+    let fresh = "async fn fresh(client: &Client) -> Result<Vec<u8>, Error> {\n    let body = client.get(URL).await?;\n    Ok(body.bytes().await?.to_vec())\n}\n";
+    let write_corpus = |name: &str, files: &[(&str, &str)]| -> io::Result<PathBuf> {
+        let root = base.join(name).join("proj");
+        fs::create_dir_all(&root)?;
+        for (index, (path, text)) in files.iter().enumerate() {
+            fs::write(root.join(path), format!("// {index}\n{text}"))?;
+        }
+        Ok(root)
+    };
+    let old = write_corpus(
+        "old",
+        &[
+            ("a.rs", kept),
+            ("b.rs", kept),
+            ("c.rs", gone),
+            ("d.rs", gone),
+            ("e.rs", moving),
+            ("f.rs", moving),
+        ],
+    )?;
+    let new = write_corpus(
+        "new",
+        &[
+            ("a.rs", kept),
+            ("b.rs", kept),
+            ("k.rs", kept),
+            ("c.rs", gone),
+            ("e.rs", moving),
+            ("g.rs", moving),
+            ("x.rs", fresh),
+            ("y.rs", fresh),
+        ],
+    )?;
+
+    let options = ScanOptions {
+        min_token_len: 10,
+        max_report_items: 1,
+        ..ScanOptions::default()
+    };
+    let snapshot = |root: &PathBuf| build_corpus_snapshot(std::slice::from_ref(root), &options);
+    let genealogy = clone_genealogy(snapshot(&old)?.result, snapshot(&new)?.result, &options)?;
+    let mut token_changes: Vec<(GenealogyChange, String)> = genealogy
+        .lineages()
+        .iter()
+        .filter(|lineage| lineage.section() == ReportSection::TokenSpanDuplicates)
+        .map(|lineage| {
+            let group = lineage.after().or(lineage.before()).unwrap();
+            (lineage.change(), group.occurrences[0].path().to_string())
+        })
+        .collect();
+    token_changes.sort_by_key(|(change, path)| (change.as_str(), path.clone()));
+    assert_eq!(
+        token_changes,
+        [
+            (GenealogyChange::Added, "x.rs".to_string()),
+            (GenealogyChange::Grown, "a.rs".to_string()),
+            (GenealogyChange::Moved, "e.rs".to_string()),
+            (GenealogyChange::Removed, "c.rs".to_string()),
+        ]
+    );
+    assert!(genealogy.count(GenealogyChange::Added) >= 1);
+    assert_eq!(genealogy.count(GenealogyChange::Shrunk), 0);
+    assert!(genealogy.old_potential_savings_lines() > 0);
+    assert!(genealogy.new_potential_savings_lines() > 0);
+    fs::remove_dir_all(&base)?;
+    Ok(())
+}
+
+#[test]
+fn report_pages_through_sections() -> io::Result<()> {
+    let root = temp_dir("report_pages");
+    fs::create_dir_all(&root)?;
+    for (name, text) in [
+        ("a", "same1"),
+        ("b", "same1"),
+        ("c", "same2"),
+        ("d", "same2"),
+        ("e", "same3"),
+        ("f", "same3"),
+    ] {
+        fs::write(root.join(format!("{name}.txt")), text)?;
+    }
+
+    let full = generate_duplication_report(std::slice::from_ref(&root), &ScanOptions::default())?;
+    assert_eq!(full.file_duplicates.len(), 3);
+    let page = |offset: usize, limit: usize| -> io::Result<DuplicationReport> {
+        let options = ScanOptions {
+            section_offset: offset,
+            section_limit: Some(limit),
+            ..ScanOptions::default()
+        };
+        generate_duplication_report(std::slice::from_ref(&root), &options)
+    };
+
+    let second = page(1, 1)?;
+    assert_eq!(second.file_duplicates, full.file_duplicates[1..2]);
+    let total = second.section_total(ReportSection::FileDuplicates);
+    assert_eq!(total.total_groups(), 3);
+    assert!(total.truncated());
+
+    let rest = page(1, 10)?;
+    assert_eq!(rest.file_duplicates, full.file_duplicates[1..]);
+    assert!(page(5, 10)?.file_duplicates.is_empty());
+
+    let invalid = ScanOptions {
+        section_limit: Some(0),
+        ..ScanOptions::default()
+    };
+    let err = generate_duplication_report(std::slice::from_ref(&root), &invalid).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn report_filters_and_caps_occurrences() -> io::Result<()> {
+    let root = temp_dir("report_occurrence_limits");
+    fs::create_dir_all(&root)?;
+    for name in ["a.txt", "b.txt", "c.txt", "d.txt"] {
+        fs::write(root.join(name), "same1")?;
+    }
+    fs::write(root.join("e.txt"), "same2")?;
+    fs::write(root.join("f.txt"), "same2")?;
+
+    let options = ScanOptions {
+        min_occurrences: 3,
+        max_occurrences_per_group: Some(2),
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert_eq!(report.file_duplicates.len(), 1);
+    assert_eq!(report.file_duplicates[0].files.len(), 2);
+    assert_eq!(report.file_duplicates[0].truncated_files, 2);
+
+    let groups = find_duplicate_files(&[root], &options)?;
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].truncated_files, 2);
+    Ok(())
+}
+
+#[test]
+fn report_rejects_zero_max_occurrences_per_group() -> io::Result<()> {
+    let root = temp_dir("invalid_max_occurrences_per_group");
+    fs::create_dir_all(&root)?;
+
+    let options = ScanOptions {
+        max_occurrences_per_group: Some(0),
+        ..ScanOptions::default()
+    };
+
+    let err = generate_duplication_report_with_stats(&[root], &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
     Ok(())
 }
 
@@ -329,263 +1043,2599 @@ function g(y) { return y + 1; }
 }
 
 #[test]
-fn follow_symlinks_includes_symlinked_files_in_git_repo() -> io::Result<()> {
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::symlink;
-        use std::process::Stdio;
-
-        let root = temp_dir("symlink_git");
-        fs::create_dir_all(&root)?;
+fn line_span_min_lines_filters_short_matches() -> io::Result<()> {
+    let root = temp_dir("line_span_min_lines");
+    fs::create_dir_all(&root)?;
+    fs::write(
+        root.join("a.txt"),
+        "alpha one\nbeta two\nunique_a\ngamma three\ndelta four\nepsilon five\n",
+    )?;
+    fs::write(
+        root.join("b.txt"),
+        "alpha one\nbeta two\nunique_b\ngamma three\ndelta four\nepsilon five\n",
+    )?;
 
-        let git_ok = std::process::Command::new("git")
-            .arg("--version")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .is_ok_and(|s| s.success());
+    let options = ScanOptions {
+        min_match_len: 5,
+        line_span_window: 1,
+        ..ScanOptions::default()
+    };
+    let line_counts = |options: &ScanOptions| -> io::Result<Vec<u32>> {
+        let report = generate_duplication_report(std::slice::from_ref(&root), options)?;
+        let mut counts = report
+            .line_span_duplicates
+            .iter()
+            .map(|group| group.occurrences[0].end_line - group.occurrences[0].start_line + 1)
+            .collect::<Vec<_>>();
+        counts.sort_unstable();
+        Ok(counts)
+    };
+    assert_eq!(line_counts(&options)?, vec![2, 3]);
+    let three_lines = ScanOptions {
+        line_span_min_lines: 3,
+        ..options.clone()
+    };
+    assert_eq!(line_counts(&three_lines)?, vec![3]);
+
+    for invalid in [
+        ScanOptions {
+            line_span_min_lines: 0,
+            ..options.clone()
+        },
+        ScanOptions {
+            line_span_window: 0,
+            ..options.clone()
+        },
+    ] {
+        let err = generate_duplication_report(std::slice::from_ref(&root), &invalid).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    Ok(())
+}
+
+#[test]
+fn similarity_windows_cover_blockless_code() -> io::Result<()> {
+    let root = temp_dir("similarity_windows");
+    fs::create_dir_all(&root)?;
+    let body = |op: &str| {
+        (0..12)
+            .map(|i| format!("total = total {op} values[{i}] * scale\nlog(total, {i})\n"))
+            .collect::<String>()
+    };
+    fs::write(root.join("a.py"), body("+"))?;
+    fs::write(root.join("b.py"), format!("{}extra = 1\n", body("+")))?;
+
+    let options = ScanOptions {
+        min_match_len: 5,
+        min_token_len: 5,
+        similarity_threshold: 0.5,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(report.similar_blocks_minhash.is_empty());
+    assert!(report.similar_blocks_simhash.is_empty());
+
+    let windowed = ScanOptions {
+        similarity_windows: Some(SimilarityWindows::new(40, 20)?),
+        ..options.clone()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &windowed)?;
+    assert!(!report.similar_blocks_minhash.is_empty());
+    assert!(!report.similar_blocks_simhash.is_empty());
+    for pair in report
+        .similar_blocks_minhash
+        .iter()
+        .chain(&report.similar_blocks_simhash)
+    {
+        // Overlapping windows of one file are never paired.
+        assert!(
+            pair.a.path() != pair.b.path()
+                || pair.a.end_line() < pair.b.start_line()
+                || pair.b.end_line() < pair.a.start_line()
+        );
+    }
+
+    assert_eq!(
+        SimilarityWindows::new(0, 1).unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+    Ok(())
+}
+
+#[test]
+fn minhash_shape_and_shingle_size_are_configurable() -> io::Result<()> {
+    let root = temp_dir("minhash_shape");
+    fs::create_dir_all(&root)?;
+    fs::write(
+        root.join("a.js"),
+        "function f(x) {\n  let y = x + 1;\n  let z = y * 2;\n  return z - x;\n}\n",
+    )?;
+    fs::write(
+        root.join("b.js"),
+        "function g(x) {\n  let y = x + 1;\n  let z = y * 3;\n  return z + x;\n}\n",
+    )?;
+
+    let options = ScanOptions {
+        min_match_len: 5,
+        min_token_len: 5,
+        similarity_threshold: 0.0,
+        minhash_signature_size: 64,
+        minhash_bands: 64,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(!report.similar_blocks_minhash.is_empty());
+    for pair in &report.similar_blocks_minhash {
+        assert_eq!((pair.score * 64.0).fract(), 0.0, "{}", pair.score);
+    }
+
+    let long_shingles = ScanOptions {
+        shingle_size: 1000,
+        ..options.clone()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &long_shingles)?;
+    assert!(report.similar_blocks_minhash.is_empty());
+    assert!(report.similar_blocks_simhash.is_empty());
+
+    for invalid in [
+        ScanOptions {
+            minhash_bands: 5,
+            ..options.clone()
+        },
+        ScanOptions {
+            shingle_size: 0,
+            ..options.clone()
+        },
+        ScanOptions {
+            minhash_signature_size: 0,
+            ..options.clone()
+        },
+    ] {
+        let err = generate_duplication_report(std::slice::from_ref(&root), &invalid).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+    Ok(())
+}
+
+#[test]
+fn report_similarity_diff_is_opt_in() -> io::Result<()> {
+    let repo_a = temp_dir("similarity_diff_a");
+    let repo_b = temp_dir("similarity_diff_b");
+    fs::create_dir_all(&repo_a)?;
+    fs::create_dir_all(&repo_b)?;
+
+    fs::write(repo_a.join("a.js"), "function f(x) {\n  return x + 1;\n}\n")?;
+    fs::write(repo_b.join("b.js"), "function g(y) {\n  return y + 1;\n}\n")?;
+
+    let options = ScanOptions {
+        cross_repo_only: true,
+        min_token_len: 5,
+        similarity_threshold: 0.9,
+        ..ScanOptions::default()
+    };
+    let roots = [repo_a, repo_b];
+
+    let report = generate_duplication_report(&roots, &options)?;
+    assert!(!report.similar_blocks_minhash.is_empty());
+    assert!(
+        report
+            .similar_blocks_minhash
+            .iter()
+            .all(|p| p.diff.is_none())
+    );
+
+    let options = ScanOptions {
+        similarity_diff: true,
+        ..options
+    };
+    let report = generate_duplication_report(&roots, &options)?;
+    let pair = &report.similar_blocks_minhash[0];
+    let diff = pair.diff.as_deref().expect("diff should be populated");
+    assert!(diff.contains("-function f(x) {"));
+    assert!(diff.contains("+function g(y) {"));
+    assert!(diff.contains(" }"));
+    Ok(())
+}
+
+#[test]
+fn occurrence_ages_rank_drifted_groups_first() -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let root = temp_dir("occurrence_ages");
+    fs::create_dir_all(&root)?;
+    let git = |args: &[&str], date: &str| {
+        Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(args)
+            .current_dir(&root)
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    };
+    if !git(&["init"], "@1000000000 +0000") {
+        return Ok(());
+    }
+
+    let drifting = "function total(items) {\n  let sum = 0;\n  for (const item of items) {\n    sum += item.price * item.quantity;\n  }\n  return sum;\n}\n";
+    let stable = "function greet(user) {\n  const name = user.first + ' ' + user.last;\n  console.log('hello ' + name);\n  return name.length;\n}\n";
+    fs::write(root.join("a.js"), drifting)?;
+    fs::write(root.join("b.js"), drifting)?;
+    for name in ["c.js", "d.js", "e.js"] {
+        fs::write(root.join(name), stable)?;
+    }
+    assert!(git(&["add", "."], "@1000000000 +0000"));
+    assert!(git(&["commit", "-m", "init"], "@1000000000 +0000"));
+    // Only whitespace changes, so `b.js` stays a duplicate of `a.js` but is newer.
+    fs::write(root.join("b.js"), drifting.replace("  ", "    "))?;
+    assert!(git(&["commit", "-am", "reindent"], "@1700000000 +0000"));
+
+    let options = ScanOptions {
+        min_match_len: 20,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let groups = &report.code_span_duplicates;
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].occurrences.len(), 3);
+    assert!(groups[0].occurrences[0].last_modified().is_none());
+
+    let options = ScanOptions {
+        occurrence_ages: true,
+        ..options
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let groups = &report.code_span_duplicates;
+    assert_eq!(groups[0].occurrences.len(), 2);
+    assert_eq!(groups[0].age_spread(), Some(700_000_000));
+    let dates: Vec<_> = groups[0]
+        .occurrences
+        .iter()
+        .map(|o| (o.path(), o.last_modified()))
+        .collect();
+    assert_eq!(
+        dates,
+        [("a.js", Some(1_000_000_000)), ("b.js", Some(1_700_000_000))]
+    );
+    assert_eq!(groups[1].age_spread(), Some(0));
+
+    // Ranked before truncation: the drifted group is listed even when only one group fits.
+    let options = ScanOptions {
+        max_report_items: 1,
+        ..options
+    };
+    let report = generate_duplication_report(&[root], &options)?;
+    let groups = &report.code_span_duplicates;
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].age_spread(), Some(700_000_000));
+    Ok(())
+}
+
+#[test]
+fn report_similarity_clusters_join_pairs_sharing_blocks() -> io::Result<()> {
+    let root = temp_dir("similarity_clusters");
+    fs::create_dir_all(&root)?;
+
+    for name in ["a", "b", "c", "d"] {
+        fs::write(
+            root.join(format!("{name}.js")),
+            format!("function {name}(x) {{\n  return x + 1;\n}}\n"),
+        )?;
+    }
+    for name in ["p", "q"] {
+        fs::write(
+            root.join(format!("{name}.js")),
+            format!(
+                "function {name}(items) {{\n  const total = items.reduce((acc, item) => acc + item.size, 0);\n  return total / items.length;\n}}\n"
+            ),
+        )?;
+    }
+
+    let options = ScanOptions {
+        min_token_len: 5,
+        similarity_threshold: 0.9,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(report.similar_block_clusters.is_none());
+
+    let options = ScanOptions {
+        similarity_clusters: true,
+        ..options
+    };
+    let report = generate_duplication_report(&[root], &options)?;
+    let clusters = report
+        .similar_block_clusters
+        .expect("clusters should be populated");
+    let minhash: Vec<_> = clusters
+        .iter()
+        .filter(|cluster| cluster.section() == ReportSection::SimilarBlocksMinhash)
+        .collect();
+    let paths = |cluster: &crate::SimilarBlockCluster| -> Vec<String> {
+        let mut paths: Vec<String> = cluster
+            .blocks()
+            .iter()
+            .map(|block| block.path().to_string())
+            .collect();
+        paths.sort();
+        paths
+    };
+    assert_eq!(minhash.len(), 2);
+    assert_eq!(paths(minhash[0]), ["a.js", "b.js", "c.js", "d.js"]);
+    assert_eq!(paths(minhash[1]), ["p.js", "q.js"]);
+    assert!(minhash[0].preview().contains("return x + 1;"));
+    assert!(minhash[0].min_score() <= minhash[0].max_score());
+
+    let mut pairs: Vec<usize> = minhash.iter().flat_map(|c| c.pairs().to_vec()).collect();
+    pairs.sort_unstable();
+    assert_eq!(
+        pairs,
+        (0..report.similar_blocks_minhash.len()).collect::<Vec<_>>()
+    );
+    Ok(())
+}
+
+#[test]
+fn report_file_coverage_unions_duplicated_lines() -> io::Result<()> {
+    let root = temp_dir("file_coverage");
+    fs::create_dir_all(&root)?;
+
+    let shared = "function add(a, b) {\n  const sum = a + b;\n  return sum * 2 + 1;\n}\n";
+    fs::write(root.join("a.js"), shared)?;
+    fs::write(root.join("b.js"), shared)?;
+    fs::write(
+        root.join("c.js"),
+        format!("{shared}\nlet items = [1, 2, 3];\nexport default items;\n"),
+    )?;
+    fs::write(root.join("d.js"), "module.exports = {};\n")?;
+
+    let options = ScanOptions {
+        min_match_len: 20,
+        min_token_len: 15,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(report.file_coverage.is_none());
+
+    let options = ScanOptions {
+        file_coverage: true,
+        ..options
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let coverage = report.file_coverage.expect("coverage should be populated");
+
+    // Truncation, paging and occurrence caps only change which groups are listed.
+    let paged = ScanOptions {
+        max_report_items: 1,
+        section_offset: 1,
+        max_occurrences_per_group: Some(2),
+        ..options
+    };
+    let report = generate_duplication_report(&[root], &paged)?;
+    assert!(report.file_duplicates.is_empty());
+    assert_eq!(report.file_coverage.as_ref(), Some(&coverage));
+
+    let a = coverage.iter().find(|c| c.path() == "a.js").unwrap();
+    assert_eq!(a.total_lines(), 4);
+    assert_eq!(
+        a.covered_ranges(),
+        &[LineRange {
+            start_line: 1,
+            end_line: 4
+        }]
+    );
+    assert_eq!(a.covered_lines(), 4);
+
+    let c = coverage.iter().find(|c| c.path() == "c.js").unwrap();
+    assert_eq!(c.total_lines(), 7);
+    assert!(c.covered_lines() >= 1 && c.covered_lines() < 7);
+
+    assert!(coverage.iter().all(|c| c.path() != "d.js"));
+    Ok(())
+}
+
+#[test]
+fn report_file_similarity_lists_sparse_file_pairs() -> io::Result<()> {
+    let root = temp_dir("file_similarity");
+    fs::create_dir_all(&root)?;
+
+    let shared = "function add(a, b) {\n  const sum = a + b;\n  return sum * 2 + 1;\n}\n";
+    fs::write(root.join("a.js"), shared)?;
+    fs::write(root.join("b.js"), format!("{shared}export default add;\n"))?;
+    fs::write(
+        root.join("c.js"),
+        "class Queue { push(x) { this.items.push(x); } pop() { return this.items.shift(); } }\n",
+    )?;
+
+    let options = ScanOptions::default();
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(report.file_similarity.is_none());
+
+    let options = ScanOptions {
+        file_similarity: true,
+        ..options
+    };
+    let report = generate_duplication_report(&[root], &options)?;
+    let pairs = report.file_similarity.expect("matrix should be populated");
+    let first = pairs.first().expect("a.js and b.js should pair");
+    assert_eq!((first.a().path(), first.b().path()), ("a.js", "b.js"));
+    assert!(first.score() > 0.5 && first.score() < 1.0);
+    assert!(pairs.windows(2).all(|w| w[0].score() >= w[1].score()));
+    Ok(())
+}
+
+#[test]
+fn report_clone_clusters_merge_groups_touching_the_same_code() -> io::Result<()> {
+    let root = temp_dir("clone_clusters");
+    fs::create_dir_all(&root)?;
+
+    let add = "function add(a, b) {\n  const sum = a + b;\n  return sum * 2 + 1;\n}\n";
+    let queue = "class Queue {\n  push(item) { this.items.push(item); }\n  pop() { return this.items.shift(); }\n  size() { return this.items.length; }\n}\n";
+    fs::write(root.join("a.js"), add)?;
+    fs::write(root.join("b.js"), format!("{add}export default add;\n"))?;
+    fs::write(root.join("x.js"), format!("// x\n{queue}"))?;
+    fs::write(
+        root.join("y.js"),
+        format!("{queue}module.exports = Queue;\n"),
+    )?;
+
+    let options = ScanOptions {
+        min_match_len: 20,
+        min_token_len: 15,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(report.clone_clusters.is_none());
+
+    let options = ScanOptions {
+        clone_clusters: true,
+        ..options
+    };
+    let report = generate_duplication_report(&[root], &options)?;
+    let clusters = report.clone_clusters.expect("clusters should be populated");
+    assert_eq!(clusters.len(), 2);
+
+    let files = |cluster: &crate::CloneCluster| -> Vec<String> {
+        cluster
+            .files()
+            .iter()
+            .map(|file| file.path().to_string())
+            .collect()
+    };
+    assert_eq!(files(&clusters[0]), ["x.js", "y.js"]);
+    assert_eq!(files(&clusters[1]), ["a.js", "b.js"]);
+    assert!(clusters[0].affected_lines() > clusters[1].affected_lines());
+    assert!(clusters[1].affected_lines() <= 8);
+
+    let members: usize = clusters.iter().map(|c| c.members().len()).sum();
+    let groups = report.code_span_duplicates.len()
+        + report.line_span_duplicates.len()
+        + report.token_span_duplicates.len()
+        + report.block_duplicates.len()
+        + report.ast_subtree_duplicates.len()
+        + report.similar_blocks_minhash.len()
+        + report.similar_blocks_simhash.len()
+        + report.file_duplicates.len();
+    assert_eq!(members, groups);
+    assert!(clusters[1].members().len() > 1);
+    Ok(())
+}
+
+#[test]
+fn report_max_tokens_per_file_caps_token_detectors() -> io::Result<()> {
+    let root = temp_dir("max_tokens_per_file");
+    fs::create_dir_all(&root)?;
+
+    let body: String = (0..20)
+        .map(|i| format!("function f{i}(x) {{ return x * {i} + 1; }}\n"))
+        .collect();
+    fs::write(root.join("a.js"), &body)?;
+    fs::write(root.join("b.js"), &body)?;
+
+    let options = ScanOptions {
+        min_token_len: 20,
+        ..ScanOptions::default()
+    };
+    let outcome = generate_duplication_report_with_stats(std::slice::from_ref(&root), &options)?;
+    assert!(!outcome.result.token_span_duplicates.is_empty());
+    assert_eq!(outcome.stats.skipped_token_cap, 0);
+
+    let options = ScanOptions {
+        max_tokens_per_file: Some(10),
+        ..options
+    };
+    let outcome = generate_duplication_report_with_stats(std::slice::from_ref(&root), &options)?;
+    assert!(outcome.result.token_span_duplicates.is_empty());
+    assert!(outcome.result.block_duplicates.is_empty());
+    assert_eq!(outcome.stats.skipped_token_cap, 2);
+    assert!(!outcome.stats.has_fatal_skips());
+    assert_eq!(outcome.result.file_duplicates.len(), 1);
+
+    let options = ScanOptions {
+        max_tokens_per_file: Some(0),
+        ..options
+    };
+    let err = generate_duplication_report(&[root], &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn report_max_groups_per_detector_degrades_span_sections() -> io::Result<()> {
+    let root = temp_dir("max_groups_per_detector");
+    fs::create_dir_all(&root)?;
+
+    let chunk = |name: &str| -> String {
+        (0..12)
+            .map(|i| {
+                format!(
+                    "function {name}{i}(x) {{ return x * {i} + {}; }}\n",
+                    name.len()
+                )
+            })
+            .collect()
+    };
+    fs::write(root.join("a.js"), format!("{}// a\n", chunk("alpha")))?;
+    fs::write(root.join("b.js"), format!("// b\n{}", chunk("alpha")))?;
+    fs::write(root.join("c.js"), format!("{}// c\n", chunk("omega_long")))?;
+    fs::write(root.join("d.js"), format!("// d\n{}", chunk("omega_long")))?;
+
+    let options = ScanOptions {
+        min_token_len: 20,
+        ..ScanOptions::default()
+    };
+    let outcome = generate_duplication_report_with_stats(std::slice::from_ref(&root), &options)?;
+    assert!(outcome.result.line_span_duplicates.len() >= 2);
+    assert_eq!(outcome.stats.skipped_group_cap, 0);
+    assert!(
+        !outcome
+            .result
+            .section_total(ReportSection::LineSpanDuplicates)
+            .degraded()
+    );
+
+    let options = ScanOptions {
+        max_groups_per_detector: Some(1),
+        ..options
+    };
+    let outcome = generate_duplication_report_with_stats(std::slice::from_ref(&root), &options)?;
+    assert_eq!(outcome.result.line_span_duplicates.len(), 1);
+    assert!(outcome.stats.skipped_group_cap > 0);
+    assert!(outcome.stats.has_fatal_skips());
+    let total = outcome
+        .result
+        .section_total(ReportSection::LineSpanDuplicates);
+    assert!(total.degraded());
+    assert!(total.truncated());
+    // Only the kept group is in the total; the ones past the cap are counted apart.
+    assert_eq!(total.total_groups(), 1);
+    assert!(total.capped_groups() >= 1);
+    assert!(total.capped_groups() <= outcome.stats.skipped_group_cap);
+
+    // Which group is kept does not depend on hash map order.
+    for _ in 0..3 {
+        let again = generate_duplication_report_with_stats(std::slice::from_ref(&root), &options)?;
+        assert_eq!(again.result, outcome.result);
+        assert_eq!(
+            again.stats.skipped_group_cap,
+            outcome.stats.skipped_group_cap
+        );
+    }
+    assert!(
+        !outcome
+            .result
+            .section_total(ReportSection::FileDuplicates)
+            .degraded()
+    );
+
+    let options = ScanOptions {
+        max_groups_per_detector: Some(0),
+        ..options
+    };
+    let err = generate_duplication_report(&[root], &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn max_file_time_skips_files_whose_analysis_runs_over() -> io::Result<()> {
+    let root = temp_dir("max_file_time");
+    fs::create_dir_all(&root)?;
+
+    let body: String = (0..20)
+        .map(|i| format!("function f{i}(x) {{ return x * {i} + 1; }}\n"))
+        .collect();
+    fs::write(root.join("a.js"), &body)?;
+    fs::write(root.join("b.js"), &body)?;
+
+    let options = ScanOptions {
+        min_token_len: 20,
+        max_file_time: Some(Duration::from_secs(60)),
+        ..ScanOptions::default()
+    };
+    let outcome = generate_duplication_report_with_stats(std::slice::from_ref(&root), &options)?;
+    assert!(!outcome.result.token_span_duplicates.is_empty());
+    assert_eq!(outcome.stats.skipped_slow_file, 0);
+
+    // Any deadline has passed by the time normalization is done.
+    let options = ScanOptions {
+        max_file_time: Some(Duration::from_nanos(1)),
+        ..options
+    };
+    let outcome = generate_duplication_report_with_stats(std::slice::from_ref(&root), &options)?;
+    assert!(outcome.result.token_span_duplicates.is_empty());
+    assert!(outcome.result.file_duplicates.is_empty());
+    assert_eq!(outcome.stats.skipped_slow_file, 2);
+    assert_eq!(outcome.stats.scanned_files, 2);
+    assert!(!outcome.stats.has_fatal_skips());
+
+    let outcome = find_duplicate_code_spans_with_stats(std::slice::from_ref(&root), &options)?;
+    assert!(outcome.result.is_empty());
+    assert_eq!(outcome.stats.skipped_slow_file, 2);
+
+    let options = ScanOptions {
+        max_file_time: Some(Duration::ZERO),
+        ..options
+    };
+    let err = generate_duplication_report(&[root], &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn report_deduplicates_identical_groups_across_detectors() -> io::Result<()> {
+    let root = temp_dir("dedupe_detectors");
+    fs::create_dir_all(&root)?;
+
+    let block = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    fs::write(root.join("a.js"), format!("const a = 1;\n{block}"))?;
+    fs::write(root.join("b.js"), format!("let b = [];\n{block}"))?;
+
+    let options = ScanOptions {
+        min_match_len: 10,
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    let span_sections = |report: &DuplicationReport| {
+        [
+            report.ast_subtree_duplicates.clone(),
+            report.block_duplicates.clone(),
+            report.token_span_duplicates.clone(),
+            report.line_span_duplicates.clone(),
+            report.code_span_duplicates.clone(),
+        ]
+    };
+    let locations = |group: &DuplicateSpanGroup| {
+        let mut locs: Vec<_> = group
+            .occurrences
+            .iter()
+            .map(|o| (o.path().to_string(), o.start_line(), o.end_line()))
+            .collect();
+        locs.sort();
+        locs
+    };
+
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let before: Vec<_> = span_sections(&report)
+        .iter()
+        .flatten()
+        .map(locations)
+        .collect();
+    let mut unique = before.clone();
+    unique.sort();
+    unique.dedup();
+    assert!(
+        unique.len() < before.len(),
+        "fixture should overlap detectors"
+    );
+
+    let options = ScanOptions {
+        deduplicate_across_detectors: true,
+        ..options
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let sections = span_sections(&report);
+    let after: Vec<_> = sections.iter().flatten().map(locations).collect();
+    let mut sorted = after.clone();
+    sorted.sort();
+    assert_eq!(sorted, unique);
+    assert!(
+        sections
+            .iter()
+            .flatten()
+            .any(|g| !g.also_detected_by.is_empty())
+    );
+    assert!(report.ast_subtree_duplicates.iter().all(|g| {
+        !g.also_detected_by
+            .contains(&ReportSection::AstSubtreeDuplicates)
+    }));
+    // Folded groups leave the section totals, and pages are filled from what remains.
+    let span = [
+        ReportSection::AstSubtreeDuplicates,
+        ReportSection::BlockDuplicates,
+        ReportSection::TokenSpanDuplicates,
+        ReportSection::LineSpanDuplicates,
+        ReportSection::CodeSpanDuplicates,
+    ];
+    for (section, groups) in span.into_iter().zip(&sections) {
+        let total = report.section_total(section);
+        assert_eq!(total.total_groups(), groups.len(), "{section:?}");
+        assert!(!total.truncated(), "{section:?}");
+    }
+
+    let options = ScanOptions {
+        max_report_items: 1,
+        ..options
+    };
+    let paged = generate_duplication_report(&[root], &options)?;
+    for ((section, groups), listed) in span.into_iter().zip(&sections).zip(span_sections(&paged)) {
+        assert_eq!(paged.section_total(section).total_groups(), groups.len());
+        assert_eq!(listed.len(), groups.len().min(1), "{section:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn report_against_snapshot_matches_saved_corpus() -> io::Result<()> {
+    let corpus = temp_dir("snapshot_corpus");
+    let live = temp_dir("snapshot_live");
+    fs::create_dir_all(&corpus)?;
+    fs::create_dir_all(&live)?;
+
+    let shared = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    fs::write(corpus.join("lib.js"), format!("// vendored\n{shared}"))?;
+    fs::write(live.join("app.js"), format!("const x = 1;\n{shared}"))?;
+
+    let options = ScanOptions {
+        min_match_len: 10,
+        min_token_len: 10,
+        cross_repo_only: true,
+        ..ScanOptions::default()
+    };
+    let snapshot = build_corpus_snapshot(std::slice::from_ref(&corpus), &options)?.result;
+    assert_eq!(snapshot.file_count(), 1);
+
+    let mut bytes = Vec::new();
+    snapshot.write_to(&mut bytes)?;
+    let restored = CorpusSnapshot::read_from(bytes.as_slice())?;
+    assert_eq!(restored.file_count(), 1);
+
+    // The corpus no longer needs to exist on disk.
+    fs::remove_dir_all(&corpus)?;
+
+    let outcome = generate_duplication_report_against_snapshot(
+        std::slice::from_ref(&live),
+        restored,
+        &options,
+    )?;
+    let report = outcome.result;
+    let group = report
+        .token_span_duplicates
+        .first()
+        .expect("expected a match against the snapshot");
+    let mut repos: Vec<_> = group.occurrences.iter().map(|o| o.repo_id()).collect();
+    repos.sort();
+    repos.dedup();
+    assert_eq!(repos, vec![0, 1]);
+    assert!(
+        group
+            .occurrences
+            .iter()
+            .any(|o| o.repo_id() == 1 && o.path() == "lib.js")
+    );
+    assert!(!group.preview.is_empty());
+
+    let mut corrupt = bytes.clone();
+    corrupt[0] = b'X';
+    assert_eq!(
+        CorpusSnapshot::read_from(corrupt.as_slice())
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::InvalidData
+    );
+    bytes.truncate(bytes.len() / 2);
+    assert!(CorpusSnapshot::read_from(bytes.as_slice()).is_err());
+    Ok(())
+}
+
+#[test]
+fn report_lists_short_idioms_copied_across_files() -> io::Result<()> {
+    let root = temp_dir("report_common_idioms");
+    fs::create_dir_all(&root)?;
+    for (name, body) in [
+        ("load.go", "cfg, err := readConfig(path)"),
+        ("save.go", "err := flush()"),
+        ("parse.go", "doc, err := parser.Parse(input, strict)"),
+    ] {
+        fs::write(
+            root.join(name),
+            format!(
+                "package main\n\nfunc {}() {{\n\t{body}\n\tif err != nil {{\n\t\treturn nil, fmt.Errorf(\"failed: %w\", err)\n\t}}\n}}\n",
+                name.trim_end_matches(".go")
+            ),
+        )?;
+    }
+
+    let report = generate_duplication_report(std::slice::from_ref(&root), &ScanOptions::default())?;
+    assert!(report.common_idioms.is_none());
+    assert!(report.token_span_duplicates.is_empty());
+
+    let mut options = ScanOptions {
+        idiom_min_files: Some(3),
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let idioms = report.common_idioms.expect("idioms requested");
+    assert_eq!(idioms.len(), 1);
+    let paths: Vec<&str> = idioms[0]
+        .occurrences
+        .iter()
+        .map(|occ| occ.path.as_ref())
+        .collect();
+    assert_eq!(paths, ["load.go", "parse.go", "save.go"]);
+    assert!(
+        idioms[0].preview.contains("Errorf"),
+        "{}",
+        idioms[0].preview
+    );
+    assert!(
+        idioms[0]
+            .occurrences
+            .iter()
+            .all(|occ| occ.start_line <= 5 && occ.end_line >= 6)
+    );
+
+    options.idiom_min_files = Some(4);
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert_eq!(report.common_idioms.map(|idioms| idioms.len()), Some(0));
+
+    options.idiom_min_files = Some(1);
+    let err = generate_duplication_report(std::slice::from_ref(&root), &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn occurrences_name_the_function_they_sit_in() -> io::Result<()> {
+    let root = temp_dir("report_occurrence_scopes");
+    fs::create_dir_all(&root)?;
+    let body = "        let total = order.items().iter().map(|item| item.price * item.quantity).sum::<u64>();\n        let fee = total / 100 + order.shipping();\n        self.ledger.credit(order.customer(), total - fee);\n        self.audit.record(order.id(), total, fee);\n";
+    fs::write(
+        root.join("payments.rs"),
+        format!(
+            "pub struct PaymentService;\n\nimpl PaymentService {{\n    pub fn refund(&self, order: &Order) {{\n{body}    }}\n}}\n"
+        ),
+    )?;
+    fs::write(
+        root.join("billing.rs"),
+        format!("pub fn settle(order: &Order) {{\n    if order.ready() {{\n{body}    }}\n}}\n"),
+    )?;
+
+    let report = generate_duplication_report(std::slice::from_ref(&root), &ScanOptions::default())?;
+    let group = report
+        .code_span_duplicates
+        .first()
+        .expect("the copied body is reported");
+    let scopes: Vec<(&str, Option<&str>)> = group
+        .occurrences
+        .iter()
+        .map(|occ| (occ.path(), occ.scope()))
+        .collect();
+    assert_eq!(
+        scopes,
+        [
+            ("billing.rs", Some("settle")),
+            ("payments.rs", Some("PaymentService::refund"))
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn report_flags_license_conflicts_against_a_snapshot() -> io::Result<()> {
+    let corpus = temp_dir("license_corpus");
+    let live = temp_dir("license_live");
+    fs::create_dir_all(&corpus)?;
+    fs::create_dir_all(&live)?;
+
+    let copied = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    let shared = "function render(items) {\n  const out = [];\n  for (const item of items) {\n    out.push(item.name + ':' + item.value);\n  }\n  return out.join(',');\n}\n";
+    fs::write(
+        corpus.join("lib.js"),
+        format!("// SPDX-License-Identifier: GPL-3.0-only\n{copied}"),
+    )?;
+    fs::write(
+        corpus.join("render.js"),
+        format!("// SPDX-License-Identifier: MIT\n{shared}"),
+    )?;
+    fs::write(
+        live.join("app.js"),
+        format!(
+            "/*\n * Permission is hereby granted, free of charge, to any person\n */\n{copied}"
+        ),
+    )?;
+    fs::write(
+        live.join("view.js"),
+        format!("// SPDX-License-Identifier: MIT\n{shared}"),
+    )?;
+
+    let options = ScanOptions {
+        min_match_len: 10,
+        min_token_len: 10,
+        cross_repo_only: true,
+        ..ScanOptions::default()
+    };
+    let snapshot = build_corpus_snapshot(std::slice::from_ref(&corpus), &options)?.result;
+    let mut bytes = Vec::new();
+    snapshot.write_to(&mut bytes)?;
+    let restored = CorpusSnapshot::read_from(bytes.as_slice())?;
+
+    let options = ScanOptions {
+        license_conflicts: true,
+        ..options
+    };
+    let report = generate_duplication_report_against_snapshot(
+        std::slice::from_ref(&live),
+        restored,
+        &options,
+    )?
+    .result;
+    let conflicts = report
+        .license_conflicts
+        .expect("license conflicts should be populated");
+    // MIT code copied into an MIT file is fine.
+    assert!(conflicts.iter().all(|conflict| {
+        conflict
+            .files()
+            .iter()
+            .any(|file| file.license() == Some("GPL-3.0-only"))
+    }));
+    let copy = conflicts
+        .iter()
+        .find(|conflict| conflict.group().section == ReportSection::TokenSpanDuplicates)
+        .expect("the GPL copy should conflict");
+    let files: Vec<(&str, Option<&str>)> = copy
+        .files()
+        .iter()
+        .map(|file| (file.path(), file.license()))
+        .collect();
+    assert_eq!(
+        files,
+        [("app.js", Some("MIT")), ("lib.js", Some("GPL-3.0-only"))]
+    );
+    assert_eq!(copy.licenses(), [Some("GPL-3.0-only"), Some("MIT")]);
+    Ok(())
+}
+
+#[test]
+fn report_matches_virtual_files_against_roots() -> io::Result<()> {
+    let live = temp_dir("virtual_live");
+    fs::create_dir_all(&live)?;
+
+    let shared = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    fs::write(live.join("app.js"), format!("const x = 1;\n{shared}"))?;
+    let virtual_file = |path: &str, content: &str| VirtualFile {
+        path: path.to_string(),
+        content: content.to_string(),
+    };
+
+    let options = ScanOptions {
+        min_match_len: 10,
+        min_token_len: 10,
+        cross_repo_only: true,
+        ..ScanOptions::default()
+    };
+    let outcome = generate_duplication_report_with_virtual_files(
+        std::slice::from_ref(&live),
+        "stdin",
+        vec![
+            virtual_file(".\\gen\\out.js", &format!("// generated\n{shared}")),
+            virtual_file("gen/data.json", "{\"a\": 1}"),
+            virtual_file("gen/blob.js", "a\0b"),
+        ],
+        &options,
+    )?;
+    let group = outcome
+        .result
+        .token_span_duplicates
+        .first()
+        .expect("expected a match against the virtual file");
+    assert!(
+        group
+            .occurrences
+            .iter()
+            .any(|o| o.repo_id() == 1 && o.repo_label() == "stdin" && o.path() == "gen/out.js")
+    );
+    assert!(outcome.result.file_duplicates.is_empty());
+    assert_eq!(outcome.stats.skipped_binary, 1);
+    let labels: Vec<&str> = outcome
+        .stats
+        .per_repo
+        .iter()
+        .map(|repo| repo.repo_label.as_str())
+        .collect();
+    assert_eq!(labels.last(), Some(&"stdin"));
+    assert_eq!(outcome.stats.per_repo[1].stats.scanned_files, 3);
+
+    for path in [
+        "/etc/passwd",
+        "C:\\x.js",
+        "../x.js",
+        "a/../../x.js",
+        "./",
+        "",
+    ] {
+        let err = generate_duplication_report_with_virtual_files(
+            &[],
+            "stdin",
+            vec![virtual_file(path, "x")],
+            &options,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{path:?}");
+    }
+    let err = generate_duplication_report_with_virtual_files(
+        &[],
+        "stdin",
+        vec![virtual_file("a.js", "x"), virtual_file("./a.js", "y")],
+        &options,
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn previews_fall_back_to_normalized_code_when_files_change_after_the_scan() -> io::Result<()> {
+    use crate::{ProgressCallback, ScanPhase};
+
+    let root = temp_dir("stale_previews");
+    fs::create_dir_all(&root)?;
+    let shared = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    fs::write(root.join("a.js"), shared)?;
+    fs::write(root.join("b.js"), shared)?;
+
+    // Rewrite both files once detection starts, i.e. after they were scanned.
+    let files = [root.join("a.js"), root.join("b.js")];
+    let options = ScanOptions {
+        min_match_len: 10,
+        min_token_len: 10,
+        threads: 1,
+        progress: Some(ProgressCallback::new(move |p| {
+            if matches!(p.phase, ScanPhase::Detecting(_)) {
+                for file in &files {
+                    let _ = fs::write(file, "// rewritten\n");
+                }
+            }
+        })),
+        ..ScanOptions::default()
+    };
+    let outcome = generate_duplication_report_with_stats(std::slice::from_ref(&root), &options)?;
+    let group = &outcome.result.token_span_duplicates[0];
+    assert_eq!(
+        group.preview,
+        "functioncomputevalueslettotal0forconstvofvaluestotalv2returntotal"
+    );
+    assert!(outcome.stats.stale_previews > 0);
+    assert!(!outcome.stats.has_fatal_skips());
+    Ok(())
+}
+
+#[test]
+fn progress_callback_reports_phases_in_order() -> io::Result<()> {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{ProgressCallback, ScanPhase};
+
+    let root = temp_dir("progress");
+    fs::create_dir_all(&root)?;
+    fs::write(root.join("a.txt"), "alpha")?;
+    fs::write(root.join("b.txt"), "alpha")?;
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&events);
+    let options = ScanOptions {
+        progress: Some(ProgressCallback::new(move |p| {
+            sink.lock().unwrap().push((p.phase, p.scanned_files));
+        })),
+        ..ScanOptions::default()
+    };
+
+    generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let report_events = std::mem::take(&mut *events.lock().unwrap());
+    let scans: Vec<_> = report_events
+        .iter()
+        .filter(|(phase, _)| *phase == ScanPhase::Scanning)
+        .collect();
+    assert_eq!(scans.len(), 2);
+    assert_eq!(scans.last().unwrap().1, 2);
+    let first_detect = report_events
+        .iter()
+        .position(|(phase, _)| matches!(phase, ScanPhase::Detecting(_)))
+        .unwrap();
+    assert!(
+        report_events[..first_detect]
+            .iter()
+            .all(|(phase, _)| *phase == ScanPhase::Scanning)
+    );
+    assert_eq!(
+        report_events.last(),
+        Some(&(ScanPhase::Finished, 2)),
+        "{report_events:?}"
+    );
+
+    crate::find_duplicate_files(&[root], &options)?;
+    let file_events = events.lock().unwrap().clone();
+    assert!(file_events.contains(&(ScanPhase::Detecting(ReportSection::FileDuplicates), 2)));
+    assert_eq!(file_events.last(), Some(&(ScanPhase::Finished, 2)));
+    Ok(())
+}
+
+#[test]
+fn scan_stats_are_broken_down_per_repo() -> io::Result<()> {
+    let repo_a = temp_dir("per_repo_a");
+    let repo_b = temp_dir("per_repo_b");
+    fs::create_dir_all(&repo_a)?;
+    fs::create_dir_all(&repo_b)?;
+
+    fs::write(repo_a.join("a.txt"), "same")?;
+    fs::write(repo_a.join("big.txt"), "x".repeat(64))?;
+    fs::write(repo_b.join("b.txt"), "same")?;
+    fs::write(repo_b.join("bin.dat"), [0u8, 1, 2, 3])?;
+
+    let options = ScanOptions {
+        max_file_size: Some(16),
+        ..ScanOptions::default()
+    };
+    let roots = [repo_a, repo_b];
+    let outcome = crate::find_duplicate_files_with_stats(&roots, &options)?;
+    let stats = outcome.stats;
+
+    assert_eq!(stats.per_repo.len(), 2);
+    let a = &stats.per_repo[0];
+    let b = &stats.per_repo[1];
+    assert_eq!(a.repo_id, 0);
+    assert_eq!(b.repo_id, 1);
+    assert!(a.repo_label.starts_with("dup-code-check-core-per_repo_a"));
+
+    assert_eq!(a.stats.scanned_files, 1);
+    assert_eq!(a.stats.skipped_too_large, 1);
+    assert_eq!(a.stats.skipped_too_large_bytes, 64);
+    assert_eq!(a.stats.skipped_binary, 0);
+    assert_eq!(b.stats.candidate_files, 2);
+    assert_eq!(b.stats.skipped_too_large, 0);
+    assert_eq!(b.stats.skipped_binary, 1);
+    assert_eq!(b.stats.skipped_binary_bytes, 4);
+    assert_eq!(
+        stats.nonzero_skipped_bytes(),
+        [("too_large", 64), ("binary", 4)]
+    );
+
+    assert_eq!(
+        stats.scanned_files,
+        a.stats.scanned_files + b.stats.scanned_files
+    );
+    assert!(a.stats.per_repo.is_empty());
+
+    let outcome = generate_duplication_report_with_stats(&roots, &options)?;
+    assert_eq!(outcome.stats.per_repo.len(), 2);
+    assert_eq!(outcome.stats.per_repo[1].stats.skipped_binary, 1);
+    Ok(())
+}
+
+#[test]
+fn follow_symlinks_includes_symlinked_files_in_git_repo() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::symlink;
+        use std::process::Stdio;
+
+        let root = temp_dir("symlink_git");
+        fs::create_dir_all(&root)?;
+
+        let git_ok = std::process::Command::new("git")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+        if !git_ok {
+            return Ok(());
+        }
+
+        let init_ok = std::process::Command::new("git")
+            .arg("init")
+            .current_dir(&root)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+        if !init_ok {
+            return Ok(());
+        }
+
+        fs::write(
+            root.join("a.txt"),
+            "a b
+c",
+        )?;
+        fs::write(root.join("b.txt"), "ab	c")?;
+        symlink("a.txt", root.join("link.txt"))?;
+
+        let options_no = ScanOptions::default();
+        let groups_no = find_duplicate_files(std::slice::from_ref(&root), &options_no)?;
+        assert_eq!(groups_no.len(), 1);
+        assert_eq!(groups_no[0].files.len(), 2);
+
+        let options_yes = ScanOptions {
+            follow_symlinks: true,
+            ..ScanOptions::default()
+        };
+        let groups_yes = find_duplicate_files(&[root], &options_yes)?;
+        assert_eq!(groups_yes.len(), 1);
+        assert_eq!(groups_yes[0].files.len(), 3);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn snapshots_at_git_refs_trace_clones_introduced_between_them() -> io::Result<()> {
+    use std::process::Stdio;
+
+    let root = temp_dir("snapshot_git_refs");
+    fs::create_dir_all(&root)?;
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(args)
+            .current_dir(&root)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    };
+    if !git(&["init", "-q"]) {
+        return Ok(());
+    }
+
+    let body = "fn checksum(values: &[u64]) -> u64 {\n    let mut total = 0u64;\n    for v in values {\n        total = total.wrapping_mul(31).wrapping_add(*v);\n    }\n    total ^ (total >> 7)\n}\n";
+    fs::write(root.join("a.rs"), body)?;
+    assert!(git(&["add", "."]) && git(&["commit", "-q", "-m", "one"]) && git(&["tag", "v1"]));
+    fs::write(root.join("b.rs"), body)?;
+    assert!(git(&["add", "."]) && git(&["commit", "-q", "-m", "two"]));
+    // Neither ref has this file.
+    fs::write(root.join("c.rs"), body)?;
+
+    let options = ScanOptions {
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    let before = build_corpus_snapshot_at_git_ref(&root, "v1", &options)?.result;
+    let after = build_corpus_snapshot_at_git_ref(&root, "HEAD", &options)?.result;
+    assert_eq!(before.file_count(), 1);
+    assert_eq!(after.file_count(), 2);
+    assert_eq!(
+        after.repo_labels().collect::<Vec<_>>(),
+        [root.file_name().unwrap().to_str().unwrap()]
+    );
+
+    let genealogy = clone_genealogy(before, after, &options)?;
+    assert!(genealogy.count(GenealogyChange::Added) > 0);
+    assert_eq!(genealogy.count(GenealogyChange::Removed), 0);
+    assert_eq!(genealogy.old_potential_savings_lines(), 0);
+
+    let err = build_corpus_snapshot_at_git_ref(&root, "no-such-ref", &options).unwrap_err();
+    assert!(err.to_string().contains("no-such-ref"), "{err}");
+    let err = build_corpus_snapshot_at_git_ref(&root, "--output=x", &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn resurrection_reports_live_code_copied_from_deleted_files() -> io::Result<()> {
+    use std::process::Stdio;
+
+    let root = temp_dir("resurrection");
+    fs::create_dir_all(&root)?;
+    let git = |args: &[&str]| {
+        std::process::Command::new("git")
+            .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+            .args(args)
+            .current_dir(&root)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success())
+    };
+    if !git(&["init", "-q"]) {
+        return Ok(());
+    }
+
+    let body = "fn checksum(values: &[u64]) -> u64 {\n    let mut total = 0u64;\n    for v in values {\n        total = total.wrapping_mul(31).wrapping_add(*v);\n    }\n    total ^ (total >> 7)\n}\n";
+    fs::write(root.join("old.rs"), body)?;
+    fs::write(root.join("main.rs"), "fn main() {}\n")?;
+    let moved = "struct Config {\n    name: String,\n    retries: u32,\n}\n\nimpl Config {\n    fn new(name: &str) -> Self {\n        Self { name: name.to_string(), retries: 3 }\n    }\n}\n";
+    fs::write(root.join("moved.rs"), moved)?;
+    assert!(git(&["add", "."]) && git(&["commit", "-q", "-m", "one"]));
+    assert!(git(&["rm", "-q", "old.rs"]) && git(&["mv", "moved.rs", "kept.rs"]));
+    assert!(git(&["commit", "-q", "-m", "drop old.rs"]));
+    fs::write(root.join("new.rs"), format!("// restored by hand\n{body}"))?;
+    // Duplicates the moved file, which was never deleted.
+    fs::write(root.join("copy.rs"), moved)?;
+
+    let options = ScanOptions {
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    let report = generate_resurrection_report(&root, 1, &options)?.result;
+    assert!(!report.token_span_duplicates.is_empty());
+    assert!(report.file_duplicates.is_empty());
+    for group in &report.token_span_duplicates {
+        let mut places: Vec<(&str, &str)> = group
+            .occurrences
+            .iter()
+            .map(|o| (o.repo_label(), o.path()))
+            .collect();
+        places.sort();
+        places.dedup();
+        assert_eq!(places.len(), 2, "{places:?}");
+        assert_eq!(places[0].0, "deleted");
+        assert!(places[0].1.ends_with("/old.rs"), "{places:?}");
+        assert_eq!(places[1].1, "new.rs");
+    }
+
+    // The deletion is now two commits back.
+    assert!(git(&["add", "main.rs"]) && git(&["commit", "-q", "--allow-empty", "-m", "three"]));
+    let report = generate_resurrection_report(&root, 1, &options)?.result;
+    assert!(report.token_span_duplicates.is_empty());
+    let report = generate_resurrection_report(&root, 2, &options)?.result;
+    assert!(!report.token_span_duplicates.is_empty());
+
+    let err = generate_resurrection_report(&root, 0, &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn follow_symlinks_reads_each_target_under_one_root() -> io::Result<()> {
+    use std::os::unix::fs::symlink;
+
+    // `app/vendor` is a root of its own, and `app/lib.txt` links into it.
+    let app = temp_dir("symlink_targets_app");
+    let vendor = app.join("vendor");
+    fs::create_dir_all(&vendor)?;
+    fs::write(vendor.join("x.txt"), "shared content\n")?;
+    symlink("vendor/x.txt", app.join("lib.txt"))?;
+
+    let options = ScanOptions {
+        follow_symlinks: true,
+        respect_gitignore: false,
+        cross_repo_only: true,
+        ..ScanOptions::default()
+    };
+    let outcome = crate::find_duplicate_files_with_stats(&[app.clone(), vendor.clone()], &options)?;
+    assert!(outcome.result.is_empty());
+    assert_eq!(outcome.stats.skipped_symlink_duplicates, 1);
+    assert!(!outcome.stats.has_fatal_skips());
+
+    // Within one root, a link and its target are still both scanned.
+    let options = ScanOptions {
+        cross_repo_only: false,
+        ..options
+    };
+    let outcome = crate::find_duplicate_files_with_stats(&[app], &options)?;
+    assert_eq!(outcome.result.len(), 1);
+    assert_eq!(outcome.result[0].files.len(), 2);
+    assert_eq!(outcome.stats.skipped_symlink_duplicates, 0);
+    Ok(())
+}
+
+#[test]
+fn git_fast_path_still_used_with_budgets() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        use std::process::Stdio;
+
+        struct RestorePerm {
+            path: PathBuf,
+        }
+
+        impl Drop for RestorePerm {
+            fn drop(&mut self) {
+                let perms = fs::Permissions::from_mode(0o755);
+                let _ = fs::set_permissions(&self.path, perms);
+            }
+        }
+
+        let root = temp_dir("git_fast_path_budgets");
+        fs::create_dir_all(&root)?;
+
+        let git_ok = std::process::Command::new("git")
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
         if !git_ok {
             return Ok(());
         }
 
-        let init_ok = std::process::Command::new("git")
-            .arg("init")
-            .current_dir(&root)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .is_ok_and(|s| s.success());
-        if !init_ok {
-            return Ok(());
+        let init_ok = std::process::Command::new("git")
+            .arg("init")
+            .current_dir(&root)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|s| s.success());
+        if !init_ok {
+            return Ok(());
+        }
+
+        fs::write(
+            root.join("a.txt"),
+            "a b
+c",
+        )?;
+        fs::write(root.join("b.txt"), "ab	c")?;
+
+        // Make an unreadable directory; `git ls-files --others` prints a warning but still exits 0.
+        // This makes the walk-based scanner accumulate PermissionDenied, while the git fast path doesn't.
+        let secret_dir = root.join("secret_dir");
+        fs::create_dir_all(&secret_dir)?;
+        let mut perms = fs::metadata(&secret_dir)?.permissions();
+        perms.set_mode(0o000);
+        fs::set_permissions(&secret_dir, perms)?;
+        let _guard = RestorePerm {
+            path: secret_dir.clone(),
+        };
+
+        // `maxFiles`: stop scanning once the file-count budget is hit.
+        let options_files = ScanOptions {
+            max_files: Some(1),
+            ..ScanOptions::default()
+        };
+        let outcome_files =
+            crate::find_duplicate_files_with_stats(std::slice::from_ref(&root), &options_files)?;
+        assert_eq!(outcome_files.stats.git_fast_path_fallbacks, 0);
+        assert_eq!(outcome_files.stats.skipped_permission_denied, 0);
+        assert!(outcome_files.stats.skipped_budget_max_files > 0);
+
+        // `maxTotalBytes`: files that would exceed the budget are skipped.
+        let options_bytes = ScanOptions {
+            max_total_bytes: Some(1),
+            ..ScanOptions::default()
+        };
+        let outcome_bytes =
+            crate::find_duplicate_files_with_stats(std::slice::from_ref(&root), &options_bytes)?;
+        assert_eq!(outcome_bytes.stats.git_fast_path_fallbacks, 0);
+        assert_eq!(outcome_bytes.stats.skipped_permission_denied, 0);
+        assert!(outcome_bytes.stats.skipped_budget_max_total_bytes > 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn follow_symlinks_does_not_escape_root() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::symlink;
+
+        let root = temp_dir("symlink_escape_root");
+        let external = temp_dir("symlink_escape_external");
+        fs::create_dir_all(&root)?;
+        fs::create_dir_all(&external)?;
+
+        fs::write(root.join("a.txt"), "same")?;
+        fs::write(external.join("b.txt"), "same")?;
+        symlink(&external, root.join("ext"))?;
+
+        let options = ScanOptions {
+            follow_symlinks: true,
+            ..ScanOptions::default()
+        };
+        let outcome = crate::find_duplicate_files_with_stats(&[root], &options)?;
+        assert_eq!(outcome.result.len(), 0);
+        assert_eq!(outcome.stats.skipped_outside_root, 1);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn scanning_skips_permission_denied_files() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        struct RestorePerm {
+            path: PathBuf,
+            mode: u32,
+        }
+
+        impl Drop for RestorePerm {
+            fn drop(&mut self) {
+                let perms = fs::Permissions::from_mode(self.mode);
+                let _ = fs::set_permissions(&self.path, perms);
+            }
         }
 
+        let root = temp_dir("perm_denied");
+        fs::create_dir_all(&root)?;
         fs::write(
             root.join("a.txt"),
             "a b
 c",
         )?;
         fs::write(root.join("b.txt"), "ab	c")?;
-        symlink("a.txt", root.join("link.txt"))?;
 
-        let options_no = ScanOptions::default();
-        let groups_no = find_duplicate_files(std::slice::from_ref(&root), &options_no)?;
-        assert_eq!(groups_no.len(), 1);
-        assert_eq!(groups_no[0].files.len(), 2);
+        let secret_path = root.join("secret.txt");
+        fs::write(&secret_path, "ab	c")?;
+        let _guard = RestorePerm {
+            path: secret_path.clone(),
+            mode: 0o644,
+        };
 
-        let options_yes = ScanOptions {
-            follow_symlinks: true,
+        let mut perms = fs::metadata(&secret_path)?.permissions();
+        perms.set_mode(0o000);
+        fs::set_permissions(&secret_path, perms)?;
+
+        // This test only makes sense when the environment enforces PermissionDenied on unreadable
+        // files. When running as `root`, `chmod 000` is not sufficient.
+        match fs::File::open(&secret_path) {
+            Ok(_) => return Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {}
+            Err(_) => return Ok(()),
+        }
+
+        let options = ScanOptions::default();
+        let groups = find_duplicate_files(std::slice::from_ref(&root), &options)?;
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files.len(), 2);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn tokenize_drops_comments_by_language() {
+    let tokens = |path: &str, text: &str| {
+        tokenize_for_dup_detection(text, comment_syntax_for_path(path)).tokens
+    };
+
+    // Rust block comments nest; C ones end at the first `*/`.
+    let plain = tokens("a.rs", "fn x() {}");
+    assert_eq!(tokens("a.rs", "/* a /* b */ c */ fn x() {}"), plain);
+    assert_ne!(tokens("a.c", "/* a /* b */ c */ fn x() {}"), plain);
+
+    // Python/Ruby `#` comments run from anywhere on the line; `//` is floor division.
+    assert_eq!(tokens("a.py", "x = 1  # set x"), tokens("a.py", "x = 1"));
+    assert_eq!(tokens("a.py", "a // b").len(), 4);
+    assert_eq!(
+        tokens("a.rb", "puts \"a #{b}\""),
+        tokens("a.rb", "puts \"\"")
+    );
+
+    // `#` is code in Rust and dropped as a directive in C.
+    assert_eq!(tokens("a.rs", "#[derive(Debug)]\nstruct S;").len(), 10);
+    assert_eq!(
+        tokens("a.c", "#include <x.h>\nint y;"),
+        tokens("a.c", "int y;")
+    );
+
+    // A shebang is dropped everywhere, but `#![...]` is a Rust inner attribute.
+    let script = tokenize_for_dup_detection(
+        "#!/usr/bin/env node\nlet a = 1;",
+        comment_syntax_for_path("cli.js"),
+    );
+    assert_eq!(script.tokens, tokens("cli.js", "let a = 1;"));
+    assert_eq!(script.token_lines[0], 2);
+    assert_eq!(tokens("lib.rs", "#![allow(x)]").len(), 8);
+}
+
+#[test]
+fn tokenize_tracks_string_start_line() {
+    let text = "let a = \"x\ny\";\nlet b = 1;\n";
+    let tokens = tokenize_for_dup_detection(text, DEFAULT_COMMENT_SYNTAX);
+
+    let str_idx = tokens
+        .tokens
+        .iter()
+        .position(|&tok| tok == 3)
+        .expect("should contain TOK_STR");
+    assert_eq!(tokens.token_lines[str_idx], 1);
+
+    let semi_idx = tokens
+        .tokens
+        .iter()
+        .position(|&tok| tok == 10_000 + u32::from(b';'))
+        .expect("should contain ';' token");
+    assert_eq!(tokens.token_lines[semi_idx], 2);
+
+    let let_positions: Vec<usize> = tokens
+        .tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &tok)| (tok == 122).then_some(i))
+        .collect();
+    assert!(let_positions.len() >= 2);
+    assert_eq!(tokens.token_lines[let_positions[1]], 3);
+}
+
+fn temp_dir(suffix: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock should be monotonic")
+        .as_nanos();
+    std::env::temp_dir().join(format!("dup-code-check-core-{suffix}-{nanos}"))
+}
+
+#[test]
+fn truncated_sections_keep_the_same_tied_groups_on_every_run() -> io::Result<()> {
+    let root = temp_dir("tied_truncation");
+    fs::create_dir_all(&root)?;
+    // Thirty groups of three same-sized files: they tie on every key but the group id.
+    for i in 0..30 {
+        for copy in ["a", "b", "c"] {
+            fs::write(
+                root.join(format!("{copy}{i:02}.txt")),
+                format!("tied-{i:02}"),
+            )?;
+        }
+    }
+    let options = ScanOptions {
+        max_report_items: 5,
+        threads: 4,
+        ..ScanOptions::default()
+    };
+    let roots = std::slice::from_ref(&root);
+    let first = generate_duplication_report(roots, &options)?;
+    for _ in 0..4 {
+        assert_eq!(generate_duplication_report(roots, &options)?, first);
+    }
+    fs::remove_dir_all(&root)?;
+
+    let hashes: Vec<u64> = first
+        .file_duplicates
+        .iter()
+        .map(|g| g.content_hash)
+        .collect();
+    assert_eq!(hashes.len(), 5);
+    assert!(
+        hashes.windows(2).all(|pair| pair[0] < pair[1]),
+        "{hashes:?}"
+    );
+    for group in &first.file_duplicates {
+        let paths: Vec<&str> = group.files.iter().map(|f| f.path.as_ref()).collect();
+        assert!(paths.windows(2).all(|pair| pair[0] < pair[1]), "{paths:?}");
+    }
+    Ok(())
+}
+
+#[test]
+fn sampled_reports_are_deterministic_and_record_the_sample() -> io::Result<()> {
+    let root = temp_dir("sample");
+    fs::create_dir_all(root.join("src"))?;
+    for i in 0..40 {
+        fs::write(
+            root.join(format!("src/m{i}.js")),
+            format!("export const v{i} = {i};\n"),
+        )?;
+    }
+
+    let roots = std::slice::from_ref(&root);
+    let report = generate_duplication_report(roots, &ScanOptions::default())?;
+    assert!(report.sample.is_none());
+
+    let options = ScanOptions {
+        sample: Some(crate::ScanSample::files(7)?),
+        ..ScanOptions::default()
+    };
+    let outcome = generate_duplication_report_with_stats(roots, &options)?;
+    let sample = outcome.result.sample.expect("sample should be recorded");
+    assert_eq!((sample.sampled_files(), sample.candidate_files()), (7, 40));
+    assert_eq!(outcome.stats.skipped_not_sampled, 33);
+    assert_eq!(outcome.stats.scanned_files, 7);
+    assert_eq!(sample.extrapolate(14), 80);
+
+    let options = ScanOptions {
+        sample: Some(crate::ScanSample::fraction(0.5)?),
+        ..ScanOptions::default()
+    };
+    let first = generate_duplication_report_with_stats(roots, &options)?;
+    let second = generate_duplication_report_with_stats(roots, &options)?;
+    assert_eq!(first.stats.scanned_files, second.stats.scanned_files);
+    let sample = first.result.sample.expect("sample should be recorded");
+    assert!(sample.sampled_files() > 5 && sample.sampled_files() < 35);
+    assert_eq!(sample.candidate_files(), 40);
+
+    let scanned = |files: u64| -> io::Result<u64> {
+        let options = ScanOptions {
+            sample: Some(crate::ScanSample::files(files)?),
             ..ScanOptions::default()
         };
-        let groups_yes = find_duplicate_files(&[root], &options_yes)?;
-        assert_eq!(groups_yes.len(), 1);
-        assert_eq!(groups_yes[0].files.len(), 3);
+        Ok(generate_duplication_report_with_stats(roots, &options)?
+            .stats
+            .scanned_files)
+    };
+    assert_eq!(scanned(100)?, 40);
+    assert!(crate::ScanSample::fraction(0.0).is_err());
+    assert!(crate::ScanSample::files(0).is_err());
+    Ok(())
+}
+
+#[test]
+fn merged_shard_snapshots_match_a_full_scan() -> io::Result<()> {
+    let root = temp_dir("shards");
+    fs::create_dir_all(root.join("src"))?;
+
+    let shared = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    let similar = "function scale(items) {\n  let sum = 1;\n  for (const item of items) {\n    sum += item * 3;\n  }\n  return sum;\n}\n";
+    let minified = "function alphaSum(x){var s=0;for(var i=0;i<x.length;i++){s+=x[i]*2;}return s;}";
+    fs::write(root.join("a.js"), format!("// a\n{shared}{minified}\n"))?;
+    fs::write(root.join("src/b.js"), format!("const b = 1;\n{shared}"))?;
+    fs::write(
+        root.join("src/c.js"),
+        format!("{similar}var q=1;{minified}\n"),
+    )?;
+    for i in 0..3 {
+        fs::write(
+            root.join(format!("src/other{i}.js")),
+            format!("let v{i} = {i};\n"),
+        )?;
+    }
+
+    let options = ScanOptions {
+        min_match_len: 10,
+        min_token_len: 10,
+        file_coverage: true,
+        file_similarity: true,
+        clone_clusters: true,
+        similarity_clusters: true,
+        similarity_diff: true,
+        ..ScanOptions::default()
+    };
+    let roots = std::slice::from_ref(&root);
+
+    let mut partials = Vec::new();
+    let mut skipped_other_shard = 0;
+    for index in (0..3).rev() {
+        let sharded = ScanOptions {
+            shard: Some(crate::ScanShard::new(index, 3)?),
+            ..options.clone()
+        };
+        let outcome = build_corpus_snapshot(roots, &sharded)?;
+        skipped_other_shard += outcome.stats.skipped_other_shard;
+        let mut bytes = Vec::new();
+        outcome.result.write_to(&mut bytes)?;
+        partials.push(CorpusSnapshot::read_from(bytes.as_slice())?);
+    }
+    assert_eq!(skipped_other_shard, 6 * 2);
+
+    let merged = CorpusSnapshot::merge(partials)?;
+    assert_eq!(merged.file_count(), 6);
+    assert_eq!(merged.repo_labels().count(), 1);
+
+    // Partials carry the scanned bytes, so columns, previews, scopes and identifier stats come
+    // out as in a full scan; only the root's directory is unknown.
+    let mut from_shards = generate_duplication_report_from_snapshot(merged, &options)?.result;
+    let mut full = generate_duplication_report(roots, &options)?;
+    assert!(full.file_duplicates.is_empty());
+    assert!(!full.token_span_duplicates.is_empty());
+    assert!(!full.similar_blocks_minhash.is_empty());
+    assert!(
+        full.code_span_duplicates
+            .iter()
+            .flat_map(|group| &group.occurrences)
+            .any(|occ| occ.start_col().is_some())
+    );
+    from_shards.roots.clear();
+    full.roots.clear();
+    assert_eq!(format!("{from_shards:?}"), format!("{full:?}"));
+
+    assert!(crate::ScanShard::new(3, 3).is_err());
+    assert!(crate::ScanShard::new(0, 0).is_err());
+    Ok(())
+}
+
+#[test]
+fn merged_shards_keep_roots_that_share_a_basename() -> io::Result<()> {
+    let base = temp_dir("shard-basenames");
+    let roots = [base.join("a/src"), base.join("b/src")];
+    let shared = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    for root in &roots {
+        fs::create_dir_all(root)?;
+        fs::write(root.join("lib.js"), shared)?;
+    }
+    let options = ScanOptions {
+        min_match_len: 10,
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+
+    let partial = |roots: &[PathBuf], index: u32| -> io::Result<CorpusSnapshot> {
+        let sharded = ScanOptions {
+            shard: Some(crate::ScanShard::new(index, 2)?),
+            ..options.clone()
+        };
+        let mut bytes = Vec::new();
+        build_corpus_snapshot(roots, &sharded)?
+            .result
+            .write_to(&mut bytes)?;
+        CorpusSnapshot::read_from(bytes.as_slice())
+    };
+    let merged = CorpusSnapshot::merge([partial(&roots, 0)?, partial(&roots, 1)?])?;
+    assert_eq!(merged.file_count(), 2);
+    assert_eq!(merged.repo_labels().collect::<Vec<_>>(), ["src", "src"]);
+    let report = generate_duplication_report_from_snapshot(merged, &options)?.result;
+    let full = generate_duplication_report(&roots, &options)?;
+    assert_eq!(
+        report.token_span_duplicates.len(),
+        full.token_span_duplicates.len()
+    );
+    assert!(!report.token_span_duplicates.is_empty());
+
+    let err = CorpusSnapshot::merge([partial(&roots, 0)?, partial(&roots[..1], 1)?]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn code_span_occurrences_report_byte_columns() -> io::Result<()> {
+    let root = temp_dir("columns");
+    fs::create_dir_all(&root)?;
+
+    let clone = "function area(w,h){return w*h+w-h;}";
+    fs::write(root.join("a.min.js"), format!("var q=0;{clone}var z=9;\n"))?;
+    fs::write(root.join("b.min.js"), format!("\n  {clone}\n"))?;
+
+    let options = ScanOptions {
+        min_match_len: 20,
+        // Minified bundles are a default exclude.
+        default_excludes: Vec::new(),
+        ..ScanOptions::default()
+    };
+    let groups = find_duplicate_code_spans(std::slice::from_ref(&root), &options)?;
+    let group = groups.first().expect("expected a code span group");
+
+    let location = |path: &str| {
+        let occ = group
+            .occurrences
+            .iter()
+            .find(|o| o.path() == path)
+            .expect("occurrence");
+        (
+            occ.start_line(),
+            occ.start_col(),
+            occ.end_line(),
+            occ.end_col(),
+        )
+    };
+    // The span ends at the last identifier char (`h`), not the trailing punctuation.
+    let last = clone.rfind('h').unwrap() as u32 + 1;
+    assert_eq!(location("a.min.js"), (1, Some(9), 1, Some(8 + last)));
+    assert_eq!(location("b.min.js"), (2, Some(3), 2, Some(2 + last)));
+
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let occ = &report.code_span_duplicates[0].occurrences[0];
+    assert!(occ.start_col().is_some() && occ.end_col().is_some());
+    Ok(())
+}
+
+#[test]
+fn corpus_index_measures_one_file_against_the_corpus() -> io::Result<()> {
+    let corpus = temp_dir("index_corpus");
+    fs::create_dir_all(&corpus)?;
+    let shared = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    fs::write(corpus.join("lib.js"), format!("// vendored\n{shared}"))?;
+    fs::write(corpus.join("copy.js"), shared)?;
+    fs::write(
+        corpus.join("other.js"),
+        "export const answer = { value: 42, label: \"unrelated\" };\n",
+    )?;
+
+    let options = ScanOptions {
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    let snapshot = build_corpus_snapshot(std::slice::from_ref(&corpus), &options)?.result;
+    let index = CorpusIndex::new(snapshot, &options)?;
+    assert_eq!(index.snapshot().file_count(), 3);
+
+    let query = VirtualFile {
+        path: "src/new.js".to_string(),
+        content: format!("const a = 1;\nconst b = 2;\n{shared}"),
+    };
+    let dup = index.file_duplication(&query)?;
+    assert_eq!(dup.path(), "src/new.js");
+    assert_eq!(dup.total_lines(), 9);
+    assert_eq!(
+        dup.covered_ranges(),
+        &[LineRange {
+            start_line: 3,
+            end_line: 9
+        }]
+    );
+    assert_eq!(dup.covered_lines(), 7);
+    assert!((dup.percent() - 700.0 / 9.0).abs() < 1e-9);
+    let partners: Vec<_> = dup
+        .partners()
+        .iter()
+        .map(|p| (p.path(), p.shared_lines()))
+        .collect();
+    assert_eq!(partners, vec![("copy.js", 7), ("lib.js", 7)]);
+
+    let on_disk = temp_dir("index_query").join("fresh.js");
+    fs::create_dir_all(on_disk.parent().expect("parent"))?;
+    fs::write(&on_disk, "let unrelated = true;\n")?;
+    let dup = index.file_duplication_at(&on_disk)?;
+    assert_eq!(dup.covered_lines(), 0);
+    assert_eq!(dup.percent(), 0.0);
+    assert!(dup.partners().is_empty());
+
+    let binary = VirtualFile {
+        path: "blob.js".to_string(),
+        content: "a\0b".to_string(),
+    };
+    assert_eq!(
+        index.file_duplication(&binary).unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    let escaping = VirtualFile {
+        path: "../x.js".to_string(),
+        content: shared.to_string(),
+    };
+    assert_eq!(
+        index.file_duplication(&escaping).unwrap_err().kind(),
+        io::ErrorKind::InvalidInput
+    );
+    Ok(())
+}
+
+#[test]
+fn report_lists_roots_and_can_emit_absolute_paths() -> io::Result<()> {
+    let root = temp_dir("absolute");
+    fs::create_dir_all(&root)?;
+    let shared = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    fs::write(root.join("a.js"), shared)?;
+    fs::write(root.join("b.js"), shared)?;
+
+    let options = ScanOptions {
+        min_match_len: 10,
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    let roots = std::slice::from_ref(&root);
+    let report = generate_duplication_report(roots, &options)?;
+    assert_eq!(report.roots.len(), 1);
+    assert_eq!(report.roots[0].path.as_deref(), Some(root.as_path()));
+    assert_eq!(report.file_duplicates[0].files[0].path(), "a.js");
+
+    let absolute = ScanOptions {
+        emit_absolute_paths: true,
+        file_coverage: true,
+        ..options.clone()
+    };
+    let report = generate_duplication_report(roots, &absolute)?;
+    let expected = root.join("a.js").to_string_lossy().into_owned();
+    assert_eq!(report.file_duplicates[0].files[0].path(), expected);
+    let occ = &report.token_span_duplicates[0].occurrences[0];
+    assert_eq!(occ.path(), expected);
+    assert!(!report.token_span_duplicates[0].preview.is_empty());
+    let coverage = report.file_coverage.expect("coverage");
+    assert!(
+        coverage
+            .iter()
+            .all(|c| c.path().starts_with(&*root.to_string_lossy()))
+    );
+
+    let groups = find_duplicate_files(roots, &absolute)?;
+    assert_eq!(groups[0].files[0].path(), expected);
+    Ok(())
+}
+
+#[test]
+fn path_prefix_map_rewrites_output_paths() -> io::Result<()> {
+    let root = temp_dir("prefix_map");
+    fs::create_dir_all(root.join("src"))?;
+    let shared = "function compute(values) {\n  let total = 0;\n  for (const v of values) {\n    total += v * 2;\n  }\n  return total;\n}\n";
+    fs::write(root.join("src/a.js"), shared)?;
+    fs::write(root.join("src/b.js"), shared)?;
+
+    let mut options = ScanOptions {
+        min_match_len: 10,
+        min_token_len: 10,
+        emit_absolute_paths: true,
+        ..ScanOptions::default()
+    };
+    options.path_prefix_map = vec![
+        (PathBuf::from("/nowhere"), PathBuf::from("/unused")),
+        (root.clone(), PathBuf::from("/checkout")),
+    ];
+    let roots = std::slice::from_ref(&root);
+    let report = generate_duplication_report(roots, &options)?;
+    assert_eq!(
+        report.roots[0].path.as_deref(),
+        Some(Path::new("/checkout"))
+    );
+    let expected = Path::new("/checkout/src/a.js")
+        .to_string_lossy()
+        .into_owned();
+    assert_eq!(report.file_duplicates[0].files[0].path(), expected);
+    assert_eq!(
+        report.token_span_duplicates[0].occurrences[0].path(),
+        expected
+    );
+
+    // Relative paths only match relative prefixes, on whole components.
+    options.emit_absolute_paths = false;
+    options.path_prefix_map = vec![
+        (PathBuf::from("sr"), PathBuf::from("lib")),
+        (PathBuf::from("src"), PathBuf::new()),
+    ];
+    let groups = find_duplicate_files(roots, &options)?;
+    assert_eq!(groups[0].files[0].path(), "a.js");
+
+    options.path_prefix_map = vec![(PathBuf::new(), PathBuf::from("x"))];
+    let err = generate_duplication_report(roots, &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn report_sections_are_ordered_deterministically() -> io::Result<()> {
+    let root = temp_dir("deterministic_order");
+    fs::create_dir_all(&root)?;
+    for name in ["a", "b", "c", "d", "e", "f"] {
+        fs::write(
+            root.join(format!("{name}.js")),
+            format!(
+                "function {name}(x) {{\n  let y = x + 1;\n  return y * 2;\n}}\nfunction {name}2(z) {{\n  let w = z - 1;\n  return w / 3;\n}}\n"
+            ),
+        )?;
+    }
+
+    let options = ScanOptions {
+        min_match_len: 5,
+        min_token_len: 5,
+        similarity_threshold: 0.5,
+        simhash_max_distance: 10,
+        ..ScanOptions::default()
+    };
+    let first = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(first.similar_blocks_minhash.len() > 1);
+    for _ in 0..4 {
+        let again = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+        assert_eq!(again, first);
+    }
+
+    let loc = |o: &crate::DuplicateSpanOccurrence| {
+        (
+            o.repo_id(),
+            o.path().to_owned(),
+            o.start_line(),
+            o.end_line(),
+        )
+    };
+    for pairs in [&first.similar_blocks_minhash, &first.similar_blocks_simhash] {
+        for w in pairs.windows(2) {
+            let ka = (-w[0].score, loc(&w[0].a), loc(&w[0].b));
+            let kb = (-w[1].score, loc(&w[1].a), loc(&w[1].b));
+            assert!(ka <= kb, "{ka:?} > {kb:?}");
+        }
+    }
+    for groups in [
+        &first.code_span_duplicates,
+        &first.line_span_duplicates,
+        &first.token_span_duplicates,
+        &first.block_duplicates,
+        &first.ast_subtree_duplicates,
+    ] {
+        for w in groups.windows(2) {
+            let ka = (
+                std::cmp::Reverse(w[0].occurrences.len()),
+                std::cmp::Reverse(w[0].normalized_len),
+                w[0].content_hash,
+            );
+            let kb = (
+                std::cmp::Reverse(w[1].occurrences.len()),
+                std::cmp::Reverse(w[1].normalized_len),
+                w[1].content_hash,
+            );
+            assert!(ka <= kb);
+        }
     }
+    Ok(())
+}
+
+#[test]
+fn unicode_normalization_matches_fullwidth_and_decomposed_code() -> io::Result<()> {
+    let root = temp_dir("unicode_normalization");
+    fs::create_dir_all(&root)?;
+    fs::write(root.join("ascii.js"), "totalValuesCount = countAllItems;\n")?;
+    fs::write(
+        root.join("fullwidth.js"),
+        "ｔｏｔａｌＶａｌｕｅｓＣｏｕｎｔ = ｃｏｕｎｔＡｌｌＩｔｅｍｓ;\n",
+    )?;
+    fs::write(
+        root.join("nfc.js"),
+        "let r\u{e9}sum\u{e9}_ann\u{e9}e_\u{e9}l\u{e8}ve = 0;\n",
+    )?;
+    fs::write(
+        root.join("nfd.js"),
+        "let re\u{301}sume\u{301}_anne\u{301}e_e\u{301}le\u{300}ve = 0;\n",
+    )?;
+
+    let roots = std::slice::from_ref(&root);
+    let options = ScanOptions {
+        min_match_len: 10,
+        ..ScanOptions::default()
+    };
+    let paths = |groups: &[DuplicateSpanGroup]| {
+        let mut paths: Vec<Vec<String>> = groups
+            .iter()
+            .map(|g| g.occurrences.iter().map(|o| o.path().to_owned()).collect())
+            .collect();
+        paths.sort();
+        paths
+    };
+
+    let plain = find_duplicate_code_spans(roots, &options)?;
+    assert!(paths(&plain).is_empty());
 
+    let nfc = ScanOptions {
+        unicode_normalization: Some(crate::UnicodeNormalization::Nfc),
+        ..options.clone()
+    };
+    assert_eq!(
+        paths(&find_duplicate_code_spans(roots, &nfc)?),
+        [["nfc.js", "nfd.js"]]
+    );
+
+    let nfkc = ScanOptions {
+        unicode_normalization: Some(crate::UnicodeNormalization::Nfkc),
+        ..options
+    };
+    let groups = find_duplicate_code_spans(roots, &nfkc)?;
+    assert_eq!(
+        paths(&groups),
+        [["ascii.js", "fullwidth.js"], ["nfc.js", "nfd.js"]]
+    );
+    // Columns of lines changed by normalization are not reported (they would not match the file).
+    let fullwidth = groups
+        .iter()
+        .flat_map(|g| &g.occurrences)
+        .find(|o| o.path() == "fullwidth.js")
+        .expect("fullwidth occurrence");
+    assert_eq!(fullwidth.start_col(), None);
     Ok(())
 }
 
 #[test]
-fn git_fast_path_still_used_with_budgets() -> io::Result<()> {
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        use std::process::Stdio;
+fn test_code_is_tagged_and_can_be_filtered() -> io::Result<()> {
+    let root = temp_dir("test_code_filter");
+    fs::create_dir_all(root.join("tests"))?;
+    let helper = "fn checksum(values: &[u64]) -> u64 {\n    let mut total = 0;\n    for v in values {\n        total = total * 31 + v;\n    }\n    total\n}\n";
+    fs::write(root.join("lib.rs"), helper)?;
+    fs::write(root.join("tests").join("fixture.rs"), helper)?;
+    fs::write(
+        root.join("other.rs"),
+        format!("pub fn run() {{}}\n\n#[cfg(test)]\nmod tests {{\n{helper}}}\n"),
+    )?;
 
-        struct RestorePerm {
-            path: PathBuf,
-        }
+    let roots = std::slice::from_ref(&root);
+    let options = ScanOptions {
+        min_match_len: 20,
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(roots, &options)?;
+    let tagged: Vec<(String, bool)> = report.token_span_duplicates[0]
+        .occurrences
+        .iter()
+        .map(|o| (o.path().to_owned(), o.is_test()))
+        .collect();
+    assert_eq!(
+        tagged,
+        [
+            ("lib.rs".to_owned(), false),
+            ("other.rs".to_owned(), true),
+            ("tests/fixture.rs".to_owned(), true),
+        ]
+    );
+    assert!(report.file_duplicates[0].files.iter().any(|f| f.is_test()));
+
+    let exclude = ScanOptions {
+        test_code_filter: Some(crate::TestCodeFilter::ExcludeTests),
+        ..options.clone()
+    };
+    let report = generate_duplication_report(roots, &exclude)?;
+    assert!(report.file_duplicates.is_empty());
+    assert!(report.token_span_duplicates.is_empty());
+    assert!(report.similar_blocks_minhash.is_empty());
+
+    let only = ScanOptions {
+        test_code_filter: Some(crate::TestCodeFilter::TestsOnly),
+        ..options
+    };
+    let groups = find_duplicate_code_spans(roots, &only)?;
+    assert!(!groups.is_empty());
+    assert!(
+        groups
+            .iter()
+            .flat_map(|g| &g.occurrences)
+            .all(|o| o.is_test())
+    );
+    Ok(())
+}
 
-        impl Drop for RestorePerm {
-            fn drop(&mut self) {
-                let perms = fs::Permissions::from_mode(0o755);
-                let _ = fs::set_permissions(&self.path, perms);
-            }
-        }
+#[test]
+fn span_groups_estimate_potential_savings() -> io::Result<()> {
+    let root = temp_dir("savings");
+    fs::create_dir_all(&root)?;
+    let helper = "fn checksum(values: &[u64]) -> u64 {\n    let mut total = 0;\n    for v in values {\n        total = total * 31 + v;\n    }\n    total\n}\n";
+    for name in ["a.rs", "b.rs", "c.rs"] {
+        fs::write(root.join(name), helper)?;
+    }
 
-        let root = temp_dir("git_fast_path_budgets");
-        fs::create_dir_all(&root)?;
+    let roots = std::slice::from_ref(&root);
+    let options = ScanOptions {
+        min_match_len: 20,
+        min_token_len: 10,
+        max_occurrences_per_group: Some(1),
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(roots, &options)?;
+    let group = &report.block_duplicates[0];
+    assert_eq!(group.occurrences.len(), 1);
+    assert_eq!(group.truncated_occurrences, 2);
+    let span_lines = u64::from(group.occurrences[0].end_line - group.occurrences[0].start_line) + 1;
+    assert_eq!(group.duplicated_lines, 3 * span_lines);
+    assert_eq!(group.potential_savings_lines, 2 * span_lines);
+
+    let groups = find_duplicate_code_spans(roots, &options)?;
+    assert!(groups.iter().all(|g| g.potential_savings_lines > 0));
+    Ok(())
+}
 
-        let git_ok = std::process::Command::new("git")
-            .arg("--version")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .is_ok_and(|s| s.success());
-        if !git_ok {
-            return Ok(());
-        }
+#[test]
+fn potential_savings_count_each_removed_line_once() -> io::Result<()> {
+    let root = temp_dir("savings_dedup");
+    fs::create_dir_all(&root)?;
+    let helper = "fn checksum(values: &[u64]) -> u64 {\n    let mut total = 0;\n    for v in values {\n        total = total * 31 + v;\n    }\n    total\n}\n";
+    for name in ["a.rs", "b.rs", "c.rs"] {
+        fs::write(root.join(name), helper)?;
+    }
 
-        let init_ok = std::process::Command::new("git")
-            .arg("init")
-            .current_dir(&root)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .is_ok_and(|s| s.success());
-        if !init_ok {
-            return Ok(());
-        }
+    let options = ScanOptions {
+        min_match_len: 20,
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(&[root], &options)?;
+    let sections = [
+        &report.code_span_duplicates,
+        &report.token_span_duplicates,
+        &report.block_duplicates,
+    ];
+    assert!(sections.iter().all(|section| !section.is_empty()));
+
+    // a.rs keeps the helper; the 7 lines of b.rs and of c.rs go, however many detectors
+    // report them.
+    assert_eq!(report.potential_savings_lines(), 14);
+    let per_group: u64 = sections
+        .into_iter()
+        .flatten()
+        .map(|group| group.potential_savings_lines)
+        .sum();
+    assert!(per_group > 14);
+    Ok(())
+}
 
+#[test]
+fn report_is_identical_for_any_thread_count() -> io::Result<()> {
+    let root = temp_dir("threads");
+    fs::create_dir_all(&root)?;
+    for i in 0..6 {
         fs::write(
-            root.join("a.txt"),
-            "a b
-c",
+            root.join(format!("f{i}.rs")),
+            format!(
+                "fn compute_{i}(values: &[u64]) -> u64 {{\n    let mut total = {i};\n    for v in values {{\n        if *v % 2 == 0 {{\n            total = total * 31 + v;\n        }} else {{\n            total ^= v << {i};\n        }}\n    }}\n    total\n}}\n"
+            ),
         )?;
-        fs::write(root.join("b.txt"), "ab	c")?;
-
-        // Make an unreadable directory; `git ls-files --others` prints a warning but still exits 0.
-        // This makes the walk-based scanner accumulate PermissionDenied, while the git fast path doesn't.
-        let secret_dir = root.join("secret_dir");
-        fs::create_dir_all(&secret_dir)?;
-        let mut perms = fs::metadata(&secret_dir)?.permissions();
-        perms.set_mode(0o000);
-        fs::set_permissions(&secret_dir, perms)?;
-        let _guard = RestorePerm {
-            path: secret_dir.clone(),
-        };
-
-        // `maxFiles`: stop scanning once the file-count budget is hit.
-        let options_files = ScanOptions {
-            max_files: Some(1),
-            ..ScanOptions::default()
-        };
-        let outcome_files =
-            crate::find_duplicate_files_with_stats(std::slice::from_ref(&root), &options_files)?;
-        assert_eq!(outcome_files.stats.git_fast_path_fallbacks, 0);
-        assert_eq!(outcome_files.stats.skipped_permission_denied, 0);
-        assert!(outcome_files.stats.skipped_budget_max_files > 0);
-
-        // `maxTotalBytes`: files that would exceed the budget are skipped.
-        let options_bytes = ScanOptions {
-            max_total_bytes: Some(1),
-            ..ScanOptions::default()
-        };
-        let outcome_bytes =
-            crate::find_duplicate_files_with_stats(std::slice::from_ref(&root), &options_bytes)?;
-        assert_eq!(outcome_bytes.stats.git_fast_path_fallbacks, 0);
-        assert_eq!(outcome_bytes.stats.skipped_permission_denied, 0);
-        assert!(outcome_bytes.stats.skipped_budget_max_total_bytes > 0);
     }
 
+    let roots = std::slice::from_ref(&root);
+    let options = ScanOptions {
+        min_match_len: 20,
+        min_token_len: 10,
+        similarity_threshold: 0.5,
+        max_report_items: 3,
+        threads: 1,
+        ..ScanOptions::default()
+    };
+    let single = generate_duplication_report_with_stats(roots, &options)?;
+    assert!(!single.result.token_span_duplicates.is_empty());
+    for threads in [2, 4, 16] {
+        let multi = generate_duplication_report_with_stats(
+            roots,
+            &ScanOptions {
+                threads,
+                ..options.clone()
+            },
+        )?;
+        assert_eq!(multi.result, single.result, "threads={threads}");
+        assert_eq!(multi.stats, single.stats, "threads={threads}");
+    }
     Ok(())
 }
 
 #[test]
-fn follow_symlinks_does_not_escape_root() -> io::Result<()> {
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::symlink;
-
-        let root = temp_dir("symlink_escape_root");
-        let external = temp_dir("symlink_escape_external");
-        fs::create_dir_all(&root)?;
-        fs::create_dir_all(&external)?;
-
-        fs::write(root.join("a.txt"), "same")?;
-        fs::write(external.join("b.txt"), "same")?;
-        symlink(&external, root.join("ext"))?;
+fn code_span_previews_are_built_for_reported_groups() -> io::Result<()> {
+    let root = temp_dir("code_span_previews");
+    fs::create_dir_all(&root)?;
+    let helper = "fn checksum(values: &[u64]) -> u64 {\n    let mut total = 0;\n    for v in values {\n        total = total * 31 + v;\n    }\n    total\n}\n";
+    fs::write(root.join("a.rs"), format!("// a\n{helper}"))?;
+    fs::write(root.join("b.rs"), format!("const B: u8 = 1;\n\n{helper}"))?;
+    let normalized = normalize_for_code_spans(helper.as_bytes()).chars;
 
-        let options = ScanOptions {
-            follow_symlinks: true,
-            ..ScanOptions::default()
-        };
-        let outcome = crate::find_duplicate_files_with_stats(&[root], &options)?;
-        assert_eq!(outcome.result.len(), 0);
-        assert_eq!(outcome.stats.skipped_outside_root, 1);
+    let roots = std::slice::from_ref(&root);
+    let options = ScanOptions {
+        min_match_len: 20,
+        ..ScanOptions::default()
+    };
+    let groups = find_duplicate_code_spans(roots, &options)?;
+    let report = generate_duplication_report(roots, &options)?;
+    for group in groups.iter().chain(&report.code_span_duplicates) {
+        assert_eq!(group.preview.len(), group.normalized_len.min(80));
+        assert!(
+            normalized
+                .windows(group.preview.len())
+                .any(|w| w == group.preview.as_bytes()),
+            "{group:?}"
+        );
     }
-
+    assert!(!report.code_span_duplicates.is_empty());
     Ok(())
 }
 
 #[test]
-fn scanning_skips_permission_denied_files() -> io::Result<()> {
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-
-        struct RestorePerm {
-            path: PathBuf,
-            mode: u32,
-        }
-
-        impl Drop for RestorePerm {
-            fn drop(&mut self) {
-                let perms = fs::Permissions::from_mode(self.mode);
-                let _ = fs::set_permissions(&self.path, perms);
-            }
-        }
-
-        let root = temp_dir("perm_denied");
-        fs::create_dir_all(&root)?;
-        fs::write(
-            root.join("a.txt"),
-            "a b
-c",
-        )?;
-        fs::write(root.join("b.txt"), "ab	c")?;
+fn previews_honor_length_and_context_options() -> io::Result<()> {
+    let root = temp_dir("preview_options");
+    fs::create_dir_all(&root)?;
+    let helper = "fn checksum(values: &[u64]) -> u64 {\n    let mut total = 0;\n    for v in values {\n        total = total * 31 + v;\n    }\n    total\n}\n";
+    fs::write(root.join("a.rs"), format!("// header a\n{helper}"))?;
+    fs::write(root.join("b.rs"), format!("// header b\n{helper}"))?;
 
-        let secret_path = root.join("secret.txt");
-        fs::write(&secret_path, "ab	c")?;
-        let _guard = RestorePerm {
-            path: secret_path.clone(),
-            mode: 0o644,
-        };
+    let roots = std::slice::from_ref(&root);
+    let options = ScanOptions {
+        min_match_len: 20,
+        min_token_len: 10,
+        preview_max_chars: Some(12),
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(roots, &options)?;
+    assert_eq!(report.code_span_duplicates[0].preview, "fnchecksumva");
+    assert_eq!(report.block_duplicates[0].preview.chars().count(), 12);
 
-        let mut perms = fs::metadata(&secret_path)?.permissions();
-        perms.set_mode(0o000);
-        fs::set_permissions(&secret_path, perms)?;
+    let options = ScanOptions {
+        preview_max_chars: None,
+        preview_context_lines: 1,
+        ..options
+    };
+    let report = generate_duplication_report(roots, &options)?;
+    assert!(
+        report.code_span_duplicates[0]
+            .preview
+            .starts_with("// header a\nfn checksum"),
+        "{:?}",
+        report.code_span_duplicates[0].preview
+    );
+    let groups = find_duplicate_code_spans(roots, &options)?;
+    assert!(groups[0].preview.starts_with("// header a\n"));
+    Ok(())
+}
 
-        // This test only makes sense when the environment enforces PermissionDenied on unreadable
-        // files. When running as `root`, `chmod 000` is not sufficient.
-        match fs::File::open(&secret_path) {
-            Ok(_) => return Ok(()),
-            Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {}
-            Err(_) => return Ok(()),
-        }
+#[test]
+fn span_groups_preview_the_first_occurrence_in_each_other_repo() -> io::Result<()> {
+    let root_a = temp_dir("occurrence_previews_a");
+    let root_b = temp_dir("occurrence_previews_b");
+    fs::create_dir_all(&root_a)?;
+    fs::create_dir_all(&root_b)?;
+    let helper = "fn checksum(values: &[u64]) -> u64 {\n    let mut total = 0;\n    for v in values {\n        total = total * 31 + v;\n    }\n    total\n}\n";
+    fs::write(root_a.join("a.rs"), helper)?;
+    fs::write(root_a.join("a2.rs"), helper)?;
+    fs::write(root_b.join("b.rs"), helper.replace("    ", "\t"))?;
 
-        let options = ScanOptions::default();
-        let groups = find_duplicate_files(std::slice::from_ref(&root), &options)?;
+    let options = ScanOptions {
+        min_match_len: 20,
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(&[root_a, root_b], &options)?;
+    let group = &report.code_span_duplicates[0];
+    assert_eq!(group.occurrences.len(), 3);
+    assert_eq!(group.occurrence_previews.len(), 1);
+    let other = &group.occurrence_previews[0];
+    assert_eq!(group.occurrences[other.occurrence].repo_id(), 1);
+    assert!(
+        other
+            .preview
+            .starts_with("fn checksum(values: &[u64]) -> u64 {\n\tlet mut total")
+    );
+    Ok(())
+}
 
-        assert_eq!(groups.len(), 1);
-        assert_eq!(groups[0].files.len(), 2);
+#[test]
+fn report_sort_orders_sections() -> io::Result<()> {
+    let root = temp_dir("report_sort");
+    fs::create_dir_all(&root)?;
+    let short = "value = compute(alpha, beta) + gamma\n";
+    let long = "result = transform(first_input, second_input, third_input) * scale\n";
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        fs::write(root.join(name), short)?;
+    }
+    for name in ["d.txt", "e.txt"] {
+        fs::write(root.join(name), long)?;
     }
 
+    let sections = |sort: ReportSort| -> io::Result<Vec<usize>> {
+        let options = ScanOptions {
+            min_match_len: 20,
+            report_sort: sort,
+            ..ScanOptions::default()
+        };
+        let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+        Ok(report
+            .file_duplicates
+            .iter()
+            .map(|g| g.files.len())
+            .chain(
+                report
+                    .code_span_duplicates
+                    .iter()
+                    .map(|g| g.occurrences.len()),
+            )
+            .collect())
+    };
+    assert_eq!(sections(ReportSort::Occurrences)?, [3, 2, 3, 2]);
+    assert_eq!(sections(ReportSort::Size)?, [2, 3, 2, 3]);
+    assert_eq!(sections(ReportSort::Path)?, [3, 2, 3, 2]);
     Ok(())
 }
 
 #[test]
-fn tokenize_tracks_string_start_line() {
-    let text = "let a = \"x\ny\";\nlet b = 1;\n";
-    let tokens = tokenize_for_dup_detection(text);
+fn language_token_scales_adjust_min_token_len_per_file() -> io::Result<()> {
+    let root = temp_dir("language_scales");
+    fs::create_dir_all(&root)?;
+    let code = "total = compute(alpha, beta) + offset;\n";
+    for name in ["a.py", "b.py", "a.java", "b.java"] {
+        fs::write(root.join(name), code)?;
+    }
 
-    let str_idx = tokens
-        .tokens
-        .iter()
-        .position(|&tok| tok == 3)
-        .expect("should contain TOK_STR");
-    assert_eq!(tokens.token_lines[str_idx], 1);
+    let token_span_paths = |options: &ScanOptions| -> io::Result<Vec<String>> {
+        let report = generate_duplication_report(std::slice::from_ref(&root), options)?;
+        Ok(report
+            .token_span_duplicates
+            .iter()
+            .flat_map(|g| g.occurrences.iter().map(|o| o.path.to_string()))
+            .collect())
+    };
+    // 12 tokens: enough for Python (10 × 0.8), not for Java (10 × 1.5).
+    let mut options = ScanOptions {
+        min_token_len: 10,
+        ..ScanOptions::default()
+    };
+    assert_eq!(token_span_paths(&options)?, ["a.py", "b.py"]);
 
-    let semi_idx = tokens
-        .tokens
-        .iter()
-        .position(|&tok| tok == 10_000 + u32::from(b';'))
-        .expect("should contain ';' token");
-    assert_eq!(tokens.token_lines[semi_idx], 2);
+    options.language_token_scales = vec![("java".to_string(), 1.0)];
+    assert_eq!(
+        token_span_paths(&options)?,
+        ["a.java", "a.py", "b.java", "b.py"]
+    );
 
-    let let_positions: Vec<usize> = tokens
-        .tokens
-        .iter()
-        .enumerate()
-        .filter_map(|(i, &tok)| (tok == 122).then_some(i))
-        .collect();
-    assert!(let_positions.len() >= 2);
-    assert_eq!(tokens.token_lines[let_positions[1]], 3);
+    options.language_token_scales = vec![("cobol".to_string(), 1.0)];
+    let err = generate_duplication_report(std::slice::from_ref(&root), &options).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
 }
 
-fn temp_dir(suffix: &str) -> PathBuf {
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("clock should be monotonic")
-        .as_nanos();
-    std::env::temp_dir().join(format!("dup-code-check-core-{suffix}-{nanos}"))
+#[test]
+fn path_filter_keeps_groups_involving_matching_paths() -> io::Result<()> {
+    let root = temp_dir("path_filter");
+    fs::create_dir_all(root.join("src").join("payments"))?;
+    fs::create_dir_all(root.join("src").join("orders"))?;
+    let shared = "let total = compute_total(items, tax_rate, discount);\n";
+    let other = "let report = render_report(rows, columns, options_for_report);\n";
+    fs::write(root.join("src/payments/charge.txt"), shared)?;
+    fs::write(root.join("src/orders/cart.txt"), shared)?;
+    fs::write(root.join("src/orders/invoice.txt"), shared)?;
+    fs::write(root.join("src/orders/a.txt"), other)?;
+    fs::write(root.join("src/orders/b.txt"), other)?;
+
+    let mut options = ScanOptions {
+        min_match_len: 20,
+        path_filter: vec!["src/payments/**".to_string()],
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert_eq!(report.file_duplicates.len(), 1);
+    assert_eq!(report.file_duplicates[0].files.len(), 3);
+    assert_eq!(report.code_span_duplicates.len(), 1);
+    assert_eq!(report.code_span_duplicates[0].occurrences.len(), 3);
+
+    options.path_filter_trim = true;
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let group = &report.code_span_duplicates[0];
+    let paths: Vec<&str> = group.occurrences.iter().map(|o| o.path()).collect();
+    assert_eq!(paths, ["src/payments/charge.txt"]);
+    assert_eq!(group.truncated_occurrences, 2);
+    assert_eq!(report.file_duplicates[0].truncated_files, 2);
+    Ok(())
+}
+
+#[test]
+fn builtin_and_custom_skips_drop_boilerplate_spans() -> io::Result<()> {
+    let root = temp_dir("skips");
+    fs::create_dir_all(&root)?;
+    let header = "// Licensed under the Apache License, Version 2.0 (the \"License\");\n\
+        // you may not use this file except in compliance with the License.\n\
+        // You may obtain a copy of the License at\n\
+        //\n\
+        //     http://www.apache.org/licenses/LICENSE-2.0\n";
+    let shared = "let total = compute_total(items, tax_rate, discount_for_customer);\n";
+    fs::write(
+        root.join("a.rs"),
+        format!("{header}fn alpha() {{ one(); }}\n"),
+    )?;
+    fs::write(
+        root.join("b.rs"),
+        format!("{header}struct Beta {{ two: u8 }}\n"),
+    )?;
+    fs::write(root.join("c.txt"), shared)?;
+    fs::write(root.join("d.txt"), format!("// other\n{shared}"))?;
+
+    let mut options = ScanOptions {
+        min_match_len: 20,
+        ..ScanOptions::default()
+    };
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    let paths = |groups: &[DuplicateSpanGroup]| -> Vec<Vec<String>> {
+        groups
+            .iter()
+            .map(|group| {
+                group
+                    .occurrences
+                    .iter()
+                    .map(|occ| occ.path().to_string())
+                    .collect()
+            })
+            .collect()
+    };
+    assert_eq!(paths(&report.code_span_duplicates), [["c.txt", "d.txt"]]);
+    assert!(report.line_span_duplicates.iter().all(|group| {
+        group
+            .occurrences
+            .iter()
+            .all(|occ| occ.path().ends_with(".txt"))
+    }));
+
+    options.skip_snippets.push(shared.to_string());
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert!(report.code_span_duplicates.is_empty());
+
+    options.skip_snippets.clear();
+    options.builtin_skips = false;
+    let report = generate_duplication_report(std::slice::from_ref(&root), &options)?;
+    assert_eq!(
+        paths(&report.code_span_duplicates),
+        [["a.rs", "b.rs"], ["c.txt", "d.txt"]]
+    );
+    Ok(())
 }
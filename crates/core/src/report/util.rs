@@ -1,12 +1,28 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::fs;
 use std::io::BufRead;
-use std::io::BufReader;
 use std::path::Path;
 
-use crate::types::{DuplicateGroup, DuplicateSpanGroup};
+use ignore::gitignore::Gitignore;
+
+use crate::scan::build_ignore_patterns;
+use crate::types::{
+    DuplicateFile, DuplicateGroup, DuplicateSpanGroup, DuplicateSpanOccurrence,
+    ImportDuplicatePair, ReportSection, ReportSort, ScanOptions, ScanStats, SimilarityPair,
+};
+use crate::util::{
+    LINE_PREVIEW_CHARS, TestRegions, apply_test_filter_to_groups, apply_test_filter_to_span_groups,
+    cap_group_occurrences, cap_span_group_occurrences, fill_occurrence_previews,
+    fill_span_group_line_stats, make_preview_ascii, mark_test_occurrences, preview_from_lines,
+    read_line_bytes, read_unchanged, retain_groups_with_min_occurrences,
+    retain_span_groups_with_min_occurrences,
+};
 
 use super::ScannedTextFile;
+use super::ages::OccurrenceDater;
+use super::coverage::CoveredLines;
+use super::quality::{fill_identifier_diversity, fill_token_quality};
 
 fn truncate_to_char_boundary(s: &mut String, max_bytes: usize) {
     if s.len() <= max_bytes {
@@ -20,51 +36,190 @@ fn truncate_to_char_boundary(s: &mut String, max_bytes: usize) {
     s.truncate(end);
 }
 
-fn preview_from_file_lines(
-    path: &Path,
+pub(super) fn fill_missing_previews_from_files(
+    files: &[ScannedTextFile],
+    groups: &mut [DuplicateSpanGroup],
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) {
+    let max_chars = options.preview_max_chars(LINE_PREVIEW_CHARS);
+    if groups.is_empty() || max_chars == 0 {
+        return;
+    }
+
+    let mut sources = SourceFiles::new(files);
+
+    for group in groups.iter_mut() {
+        if !group.preview.is_empty() {
+            continue;
+        }
+        // Stdin files and stale files cannot be read; use the first occurrence that can.
+        let Some(occ) = group
+            .occurrences
+            .iter()
+            .find(|occ| sources.is_readable(occ))
+        else {
+            continue;
+        };
+
+        group.preview = sources.preview(occ, options, max_chars, stats);
+    }
+    fill_occurrence_previews_from_files(files, groups, options, stats);
+}
+
+/// Fill [`DuplicateSpanGroup::occurrence_previews`] from the source lines of each other repo's
+/// first occurrence.
+pub(super) fn fill_occurrence_previews_from_files(
+    files: &[ScannedTextFile],
+    groups: &mut [DuplicateSpanGroup],
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) {
+    let max_chars = options.preview_max_chars(LINE_PREVIEW_CHARS);
+    if groups.is_empty() || max_chars == 0 {
+        return;
+    }
+
+    let mut sources = SourceFiles::new(files);
+    fill_occurrence_previews(groups, |occ| {
+        sources.preview(occ, options, max_chars, stats)
+    });
+}
+
+/// Scanned files as read back from disk (or from a snapshot's captured bytes) for previews,
+/// diffs and identifier stats.
+///
+/// Files may change or vanish between the scan and these re-reads, so each file is read at most
+/// once and only used while its bytes still hash to [`ScannedTextFile::content_hash`]; otherwise
+/// it is stale.
+pub(super) struct SourceFiles<'a> {
+    files: &'a [ScannedTextFile],
+    /// Indices of the files read from disk or carrying their snapshot bytes, by
+    /// `(repo_id, path)`.
+    by_path: HashMap<(usize, &'a str), usize>,
+    /// Re-read contents by file index, `None` for stale files.
+    contents: HashMap<usize, Option<Cow<'a, [u8]>>>,
+}
+
+impl<'a> SourceFiles<'a> {
+    pub(super) fn new(files: &'a [ScannedTextFile]) -> Self {
+        Self {
+            files,
+            by_path: files
+                .iter()
+                .enumerate()
+                .filter(|(_, file)| file.abs_path.is_some() || file.source.is_some())
+                .map(|(file_id, file)| ((file.repo_id, file.path.as_ref()), file_id))
+                .collect(),
+            contents: HashMap::new(),
+        }
+    }
+
+    pub(super) fn is_readable(&self, occ: &DuplicateSpanOccurrence) -> bool {
+        self.by_path.contains_key(&(occ.repo_id, occ.path.as_ref()))
+    }
+
+    fn file_id(&self, occ: &DuplicateSpanOccurrence) -> Option<usize> {
+        self.by_path.get(&(occ.repo_id, occ.path.as_ref())).copied()
+    }
+
+    /// The current bytes of `occ`'s file; `None` when it is not readable or stale.
+    pub(super) fn contents(&mut self, occ: &DuplicateSpanOccurrence) -> Option<&[u8]> {
+        let file_id = self.file_id(occ)?;
+        let file = &self.files[file_id];
+        self.contents
+            .entry(file_id)
+            .or_insert_with(|| match &file.source {
+                Some(source) => Some(Cow::Borrowed(source)),
+                None => {
+                    read_unchanged(file.abs_path.as_deref()?, file.content_hash).map(Cow::Owned)
+                }
+            })
+            .as_deref()
+    }
+
+    /// Preview of `occ` from its source lines. For a stale file it falls back to the normalized
+    /// code of those lines and counts in [`ScanStats::stale_previews`]; it is empty for files
+    /// that are not readable.
+    pub(super) fn preview(
+        &mut self,
+        occ: &DuplicateSpanOccurrence,
+        options: &ScanOptions,
+        max_chars: usize,
+        stats: &mut ScanStats,
+    ) -> String {
+        if let Some(bytes) = self.contents(occ) {
+            return preview_from_lines(
+                bytes,
+                occ.start_line,
+                occ.end_line,
+                options.preview_context_lines,
+                max_chars,
+            );
+        }
+        let Some(file_id) = self.file_id(occ) else {
+            return String::new();
+        };
+        stats.stale_previews = stats.stale_previews.saturating_add(1);
+        normalized_preview(
+            &self.files[file_id],
+            occ.start_line,
+            occ.end_line,
+            max_chars,
+        )
+    }
+
+    /// Lines `start_line..=end_line` of `occ` (at most `max_lines`); `None` when the file is not
+    /// readable or stale.
+    pub(super) fn lines(
+        &mut self,
+        occ: &DuplicateSpanOccurrence,
+        max_lines: usize,
+    ) -> Option<Vec<String>> {
+        read_line_range(self.contents(occ)?, occ.start_line, occ.end_line, max_lines)
+    }
+}
+
+/// The normalized code (see [`ScannedTextFile::code_chars`]) of lines `start_line..=end_line`.
+fn normalized_preview(
+    file: &ScannedTextFile,
     start_line: u32,
     end_line: u32,
-    max_bytes: usize,
+    max_chars: usize,
 ) -> String {
-    if start_line == 0 || end_line == 0 || start_line > end_line || max_bytes == 0 {
-        return String::new();
-    }
-
-    let file = match fs::File::open(path) {
-        Ok(f) => f,
-        Err(_) => return String::new(),
+    let line_start = |line: u32| {
+        file.code_line_starts
+            .get(line.saturating_sub(1) as usize)
+            .map_or(file.code_chars.len(), |&start| start as usize)
     };
-    let mut reader = BufReader::new(file);
+    let start = line_start(start_line);
+    let end = line_start(end_line.saturating_add(1)).max(start);
+    make_preview_ascii(&file.code_chars[start..end], max_chars)
+}
 
-    let mut out = String::new();
+fn read_line_range(
+    mut reader: impl BufRead,
+    start_line: u32,
+    end_line: u32,
+    max_lines: usize,
+) -> Option<Vec<String>> {
+    if start_line == 0 || end_line == 0 || start_line > end_line || max_lines == 0 {
+        return None;
+    }
+
+    let mut out = Vec::new();
     let mut line_no: u32 = 1;
     let mut buf: Vec<u8> = Vec::new();
 
     loop {
         buf.clear();
-        let n = match reader.read_until(b'\n', &mut buf) {
-            Ok(n) => n,
-            Err(_) => break,
-        };
-        if n == 0 {
+        if !read_line_bytes(&mut reader, &mut buf).ok()? {
             break;
         }
 
         if line_no >= start_line && line_no <= end_line {
-            let mut slice = buf.as_slice();
-            if slice.ends_with(b"\n") {
-                slice = &slice[..slice.len() - 1];
-            }
-            if slice.ends_with(b"\r") {
-                slice = &slice[..slice.len() - 1];
-            }
-
-            if !out.is_empty() {
-                out.push('\n');
-            }
-            out.push_str(std::string::String::from_utf8_lossy(slice).as_ref());
-            if out.len() >= max_bytes {
-                truncate_to_char_boundary(&mut out, max_bytes);
+            out.push(String::from_utf8_lossy(&buf).into_owned());
+            if out.len() >= max_lines {
                 break;
             }
         }
@@ -75,58 +230,464 @@ fn preview_from_file_lines(
         line_no = line_no.saturating_add(1);
     }
 
+    Some(out)
+}
+
+/// Render a minimal line diff (LCS-based) between `a` and `b`.
+///
+/// Lines are compared with surrounding whitespace trimmed, since all detectors are
+/// whitespace-insensitive. Unchanged lines are prefixed with `' '`, lines only in `a` with `'-'`,
+/// and lines only in `b` with `'+'`.
+pub(super) fn line_diff(a: &[String], b: &[String]) -> String {
+    let n = a.len();
+    let m = b.len();
+
+    // lcs[i][j] = LCS length of a[i..] and b[j..]
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[at(i, j)] = if a[i].trim() == b[j].trim() {
+                lcs[at(i + 1, j + 1)] + 1
+            } else {
+                lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let (mut i, mut j) = (0usize, 0usize);
+    while i < n || j < m {
+        if i < n && j < m && a[i].trim() == b[j].trim() {
+            out.push(' ');
+            out.push_str(&a[i]);
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[at(i + 1, j)] >= lcs[at(i, j + 1)]) {
+            out.push('-');
+            out.push_str(&a[i]);
+            i += 1;
+        } else {
+            out.push('+');
+            out.push_str(&b[j]);
+            j += 1;
+        }
+        out.push('\n');
+    }
     out
 }
 
-pub(super) fn fill_missing_previews_from_files(
+pub(super) fn fill_similarity_diffs_from_files(
     files: &[ScannedTextFile],
-    groups: &mut [DuplicateSpanGroup],
+    pairs: &mut [SimilarityPair],
+    max_lines: usize,
     max_bytes: usize,
 ) {
-    if groups.is_empty() || max_bytes == 0 {
+    if pairs.is_empty() || max_lines == 0 || max_bytes == 0 {
         return;
     }
 
-    let mut by_path: HashMap<(usize, &str), &Path> = HashMap::new();
-    for file in files {
-        by_path.insert((file.repo_id, file.path.as_ref()), file.abs_path.as_path());
-    }
+    let mut sources = SourceFiles::new(files);
 
-    for group in groups {
-        if !group.preview.is_empty() {
-            continue;
-        }
-        let Some(occ) = group.occurrences.first() else {
-            continue;
-        };
-        let Some(path) = by_path.get(&(occ.repo_id, occ.path.as_ref())) else {
+    for pair in pairs {
+        let (Some(a), Some(b)) = (
+            sources.lines(&pair.a, max_lines),
+            sources.lines(&pair.b, max_lines),
+        ) else {
             continue;
         };
-
-        group.preview = preview_from_file_lines(path, occ.start_line, occ.end_line, max_bytes);
+        let mut diff = line_diff(&a, &b);
+        truncate_to_char_boundary(&mut diff, max_bytes);
+        pair.diff = Some(diff);
     }
 }
 
-pub(super) fn sort_duplicate_groups_for_report(groups: &mut [DuplicateGroup]) {
+pub(super) fn occurrence_sort_key(
+    occ: &DuplicateSpanOccurrence,
+) -> (usize, &str, u32, u32, u32, u32) {
+    (
+        occ.repo_id,
+        occ.path.as_ref(),
+        occ.start_line,
+        occ.end_line,
+        occ.start_col,
+        occ.end_col,
+    )
+}
+
+fn file_sort_key(file: &DuplicateFile) -> (usize, &str) {
+    (file.repo_id, file.path.as_ref())
+}
+
+/// Bytes deduplicating a file group would remove.
+fn file_group_savings(group: &DuplicateGroup) -> usize {
+    group.files.len().saturating_sub(1) * group.normalized_len
+}
+
+/// Sort a file section into a total order, so `max_report_items` keeps the same groups on
+/// every run: the `sort` key, then file count and size (descending), then the group id (the
+/// content hash), then the file locations. Files are put in location order first, which is
+/// also the order `max_occurrences_per_group` keeps.
+pub(super) fn sort_duplicate_groups_for_report(groups: &mut [DuplicateGroup], sort: ReportSort) {
+    for group in groups.iter_mut() {
+        group
+            .files
+            .sort_by(|a, b| file_sort_key(a).cmp(&file_sort_key(b)));
+    }
     groups.sort_by(|a, b| {
-        b.files
-            .len()
-            .cmp(&a.files.len())
+        let primary = match sort {
+            ReportSort::Occurrences => Ordering::Equal,
+            ReportSort::Size => b.normalized_len.cmp(&a.normalized_len),
+            ReportSort::Score => file_group_savings(b).cmp(&file_group_savings(a)),
+            ReportSort::Path => a
+                .files
+                .first()
+                .map(file_sort_key)
+                .cmp(&b.files.first().map(file_sort_key)),
+        };
+        primary
+            .then_with(|| b.files.len().cmp(&a.files.len()))
             .then_with(|| b.normalized_len.cmp(&a.normalized_len))
             .then_with(|| a.content_hash.cmp(&b.content_hash))
+            .then_with(|| {
+                a.files
+                    .iter()
+                    .map(file_sort_key)
+                    .cmp(b.files.iter().map(file_sort_key))
+            })
     });
 }
 
-pub(super) fn sort_span_groups_for_report(groups: &mut [DuplicateSpanGroup]) {
+/// Sort a span section into the same total order as [`sort_duplicate_groups_for_report`],
+/// with occurrences in place of files; `potential_savings_lines` must be filled in for
+/// [`ReportSort::Score`].
+pub(super) fn sort_span_groups_for_report(groups: &mut [DuplicateSpanGroup], sort: ReportSort) {
+    for group in groups.iter_mut() {
+        group
+            .occurrences
+            .sort_by(|a, b| occurrence_sort_key(a).cmp(&occurrence_sort_key(b)));
+    }
     groups.sort_by(|a, b| {
-        b.occurrences
-            .len()
-            .cmp(&a.occurrences.len())
+        let primary = match sort {
+            ReportSort::Occurrences => Ordering::Equal,
+            ReportSort::Size => b.normalized_len.cmp(&a.normalized_len),
+            ReportSort::Score => b.potential_savings_lines.cmp(&a.potential_savings_lines),
+            ReportSort::Path => a
+                .occurrences
+                .first()
+                .map(occurrence_sort_key)
+                .cmp(&b.occurrences.first().map(occurrence_sort_key)),
+        };
+        primary
+            .then_with(|| b.occurrences.len().cmp(&a.occurrences.len()))
             .then_with(|| b.normalized_len.cmp(&a.normalized_len))
             .then_with(|| a.content_hash.cmp(&b.content_hash))
+            .then_with(|| {
+                a.occurrences
+                    .iter()
+                    .map(occurrence_sort_key)
+                    .cmp(b.occurrences.iter().map(occurrence_sort_key))
+            })
+    });
+}
+
+/// Matcher for [`ScanOptions::path_filter`]; `None` when no filter is set.
+fn path_filter_matcher(options: &ScanOptions) -> Option<Gitignore> {
+    if options.path_filter.is_empty() {
+        return None;
+    }
+    // Patterns are checked by `ScanOptions::validate_for_file_duplicates` before scanning.
+    build_ignore_patterns(Path::new(""), &options.path_filter).ok()
+}
+
+fn path_matches(matcher: &Gitignore, path: &str) -> bool {
+    matcher.matched_path_or_any_parents(path, false).is_ignore()
+}
+
+/// Drop the groups of `section` an active [`ScanOptions::group_suppressions`] entry matches,
+/// and record every matching entry in `stats`.
+fn drop_suppressed_groups<T>(
+    groups: &mut Vec<T>,
+    section: ReportSection,
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+    content_hash: impl Fn(&T) -> u64,
+) {
+    if options.group_suppressions.is_empty() {
+        return;
+    }
+    let before = groups.len();
+    groups.retain(|group| {
+        let mut keep = true;
+        for (index, suppression) in options.group_suppressions.iter().enumerate() {
+            if suppression.matches(section, content_hash(group)) {
+                stats.matched_suppressions.push(index);
+                keep &= !suppression.is_active();
+            }
+        }
+        keep
+    });
+    stats.matched_suppressions.sort_unstable();
+    stats.matched_suppressions.dedup();
+    stats.suppressed_groups += (before - groups.len()) as u64;
+}
+
+/// Keep the [`ScanOptions::report_page`] of a sorted section.
+pub(super) fn take_report_page<T>(
+    items: &mut Vec<T>,
+    section: ReportSection,
+    options: &ScanOptions,
+) {
+    let (offset, len) = options.report_page(section);
+    items.drain(..offset.min(items.len()));
+    items.truncate(len);
+}
+
+/// Tag test occurrences, drop pairs rejected by the test and path filters, sort, and truncate
+/// a similarity section. Returns the pair count before truncation.
+pub(super) fn finalize_similarity_pairs_for_report(
+    pairs: &mut Vec<SimilarityPair>,
+    section: ReportSection,
+    files: &[ScannedTextFile],
+    options: &ScanOptions,
+) -> usize {
+    mark_test_occurrences(
+        pairs.iter_mut().flat_map(|p| [&mut p.a, &mut p.b]),
+        &test_regions_by_file(files),
+    );
+    if let Some(filter) = options.test_code_filter {
+        pairs.retain(|p| filter.keeps(p.a.is_test) && filter.keeps(p.b.is_test));
+    }
+    if let Some(matcher) = path_filter_matcher(options) {
+        pairs.retain(|p| path_matches(&matcher, &p.a.path) || path_matches(&matcher, &p.b.path));
+    }
+    sort_similarity_pairs_for_report(pairs, options.report_sort);
+    let total = pairs.len();
+    take_report_page(pairs, section, options);
+    total
+}
+
+/// Drop import pairs rejected by the test and path filters, sort by score (descending) then
+/// paths, and truncate. Returns the pair count before truncation.
+pub(super) fn finalize_import_pairs_for_report(
+    pairs: &mut Vec<ImportDuplicatePair>,
+    options: &ScanOptions,
+) -> usize {
+    if let Some(filter) = options.test_code_filter {
+        pairs.retain(|p| filter.keeps(p.a.is_test) && filter.keeps(p.b.is_test));
+    }
+    if let Some(matcher) = path_filter_matcher(options) {
+        pairs.retain(|p| path_matches(&matcher, &p.a.path) || path_matches(&matcher, &p.b.path));
+    }
+    pairs.sort_by(|x, y| {
+        let primary = match options.report_sort {
+            ReportSort::Path => file_sort_key(&x.a).cmp(&file_sort_key(&y.a)),
+            ReportSort::Occurrences | ReportSort::Size | ReportSort::Score => Ordering::Equal,
+        };
+        primary
+            .then_with(|| y.score.total_cmp(&x.score))
+            .then_with(|| file_sort_key(&x.a).cmp(&file_sort_key(&y.a)))
+            .then_with(|| file_sort_key(&x.b).cmp(&file_sort_key(&y.b)))
+    });
+    let total = pairs.len();
+    take_report_page(pairs, ReportSection::ImportDuplicates, options);
+    total
+}
+
+/// Sort similarity pairs by score (descending), then by location, so equal scores never fall
+/// back to hash-map iteration order. [`ReportSort::Path`] puts the locations first.
+///
+/// Each pair is first oriented so that `a` is its first location: which block a detector saw
+/// first depends on the order files were scanned in (walk order, shard partials).
+fn sort_similarity_pairs_for_report(pairs: &mut [SimilarityPair], sort: ReportSort) {
+    for pair in pairs.iter_mut() {
+        if occurrence_sort_key(&pair.b) < occurrence_sort_key(&pair.a) {
+            std::mem::swap(&mut pair.a, &mut pair.b);
+        }
+    }
+    pairs.sort_by(|a, b| {
+        let primary = match sort {
+            ReportSort::Path => occurrence_sort_key(&a.a).cmp(&occurrence_sort_key(&b.a)),
+            ReportSort::Occurrences | ReportSort::Size | ReportSort::Score => Ordering::Equal,
+        };
+        primary
+            .then_with(|| b.score.total_cmp(&a.score))
+            .then_with(|| occurrence_sort_key(&a.a).cmp(&occurrence_sort_key(&b.a)))
+            .then_with(|| occurrence_sort_key(&a.b).cmp(&occurrence_sort_key(&b.b)))
+            .then_with(|| a.distance.cmp(&b.distance))
     });
 }
 
+pub(super) fn test_regions_by_file(files: &[ScannedTextFile]) -> TestRegions<'_> {
+    files
+        .iter()
+        .filter(|file| !file.test_ranges.is_empty())
+        .map(|file| {
+            (
+                (file.repo_id, file.path.as_ref()),
+                file.test_ranges.as_slice(),
+            )
+        })
+        .collect()
+}
+
+/// Apply occurrence filters and suppressions, sort, and truncate a file-duplicate section.
+/// Returns the group count before truncation. With [`ScanOptions::file_coverage`], the files
+/// of every group are recorded in `covered` first.
+pub(super) fn finalize_duplicate_groups_for_report(
+    groups: &mut Vec<DuplicateGroup>,
+    section: ReportSection,
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+    covered: Option<&mut CoveredLines>,
+) -> usize {
+    apply_test_filter_to_groups(groups, options.test_code_filter, options.cross_repo_only);
+    let path_filter = path_filter_matcher(options);
+    if let Some(matcher) = &path_filter {
+        groups.retain(|g| g.files.iter().any(|f| path_matches(matcher, &f.path)));
+    }
+    retain_groups_with_min_occurrences(groups, options.min_occurrences);
+    drop_suppressed_groups(groups, section, options, stats, |g| g.content_hash);
+    sort_duplicate_groups_for_report(groups, options.report_sort);
+    if let Some(covered) = covered
+        && options.file_coverage
+    {
+        covered.add_file_groups(groups);
+    }
+    let total = groups.len();
+    take_report_page(groups, section, options);
+    if let Some(matcher) = &path_filter
+        && options.path_filter_trim
+    {
+        for group in groups.iter_mut() {
+            let before = group.files.len();
+            group.files.retain(|f| path_matches(matcher, &f.path));
+            group.truncated_files += before - group.files.len();
+        }
+    }
+    cap_group_occurrences(groups, options.max_occurrences_per_group);
+    total
+}
+
+/// Drop span groups whose every occurrence lies within an occurrence of a longer group in the
+/// same section: the longer group already reports that code.
+///
+/// Occurrences are compared by line and column when both have columns (code spans), so two
+/// clones side by side on one minified line are both kept; otherwise by line range alone.
+fn prune_contained_span_groups(groups: &mut Vec<DuplicateSpanGroup>) {
+    /// `(start_line, end_line, columns, normalized_len)` of every occurrence, per file.
+    type FileSpans = Vec<(u32, u32, Option<(u32, u32)>, usize)>;
+    let mut spans: HashMap<(usize, &str), FileSpans> = HashMap::new();
+    for group in groups.iter() {
+        for occ in &group.occurrences {
+            spans
+                .entry((occ.repo_id, occ.path.as_ref()))
+                .or_default()
+                .push((
+                    occ.start_line,
+                    occ.end_line,
+                    occurrence_columns(occ),
+                    group.normalized_len,
+                ));
+        }
+    }
+    let contained: Vec<bool> = groups
+        .iter()
+        .map(|group| {
+            !group.occurrences.is_empty()
+                && group.occurrences.iter().all(|occ| {
+                    let cols = occurrence_columns(occ);
+                    spans[&(occ.repo_id, occ.path.as_ref())].iter().any(
+                        |&(start, end, outer_cols, len)| {
+                            len > group.normalized_len
+                                && match (outer_cols, cols) {
+                                    (Some((start_col, end_col)), Some((occ_start, occ_end))) => {
+                                        (start, start_col) <= (occ.start_line, occ_start)
+                                            && (occ.end_line, occ_end) <= (end, end_col)
+                                    }
+                                    _ => start <= occ.start_line && occ.end_line <= end,
+                                }
+                        },
+                    )
+                })
+        })
+        .collect();
+    let mut contained = contained.into_iter();
+    groups.retain(|_| !contained.next().unwrap_or(false));
+}
+
+/// Where on its start and end lines `occ` begins and ends: the normalized code-char indices
+/// the code-span detector records, or byte columns once resolved; `None` when unknown.
+fn occurrence_columns(occ: &DuplicateSpanOccurrence) -> Option<(u32, u32)> {
+    occ.code_char_cols
+        .or((occ.start_col > 0 && occ.end_col > 0).then_some((occ.start_col, occ.end_col)))
+}
+
+/// Tag test occurrences, apply occurrence filters and suppressions, sort, and truncate a
+/// span-group section. Returns the group count before truncation. With
+/// [`ScanOptions::file_coverage`], the occurrences of every group are recorded in `covered`
+/// first. With `ages` ([`ScanOptions::occurrence_ages`]), groups are ranked by age spread
+/// before the page is taken.
+///
+/// Occurrence capping happens after sorting so ranking still reflects the full occurrence count.
+pub(super) fn finalize_span_groups_for_report(
+    groups: &mut Vec<DuplicateSpanGroup>,
+    section: ReportSection,
+    files: &[ScannedTextFile],
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+    covered: &mut CoveredLines,
+    ages: Option<&OccurrenceDater<'_>>,
+) -> usize {
+    mark_test_occurrences(
+        groups.iter_mut().flat_map(|g| g.occurrences.iter_mut()),
+        &test_regions_by_file(files),
+    );
+    apply_test_filter_to_span_groups(groups, options.test_code_filter, options.cross_repo_only);
+    let path_filter = path_filter_matcher(options);
+    if let Some(matcher) = &path_filter {
+        groups.retain(|g| g.occurrences.iter().any(|o| path_matches(matcher, &o.path)));
+    }
+    retain_span_groups_with_min_occurrences(groups, options.min_occurrences);
+    fill_token_quality(groups, files);
+    if options.min_entropy > 0.0 {
+        groups.retain(|g| {
+            g.quality
+                .is_none_or(|quality| quality.token_entropy >= options.min_entropy)
+        });
+    }
+    if options.prune_contained_groups {
+        prune_contained_span_groups(groups);
+    }
+    drop_suppressed_groups(groups, section, options, stats, |g| g.content_hash);
+    fill_span_group_line_stats(groups);
+    sort_span_groups_for_report(groups, options.report_sort);
+    if let Some(ages) = ages {
+        ages.rank_by_age_spread(groups);
+    }
+    if options.file_coverage {
+        covered.add_span_groups(groups);
+    }
+    let total = groups.len();
+    // Folding groups across sections changes what is left to page through, so with
+    // `deduplicate_across_detectors` the page is taken once the sections are folded.
+    if !options.deduplicate_across_detectors {
+        take_report_page(groups, section, options);
+    }
+    if let Some(matcher) = &path_filter
+        && options.path_filter_trim
+    {
+        for group in groups.iter_mut() {
+            let before = group.occurrences.len();
+            group.occurrences.retain(|o| path_matches(matcher, &o.path));
+            group.truncated_occurrences += before - group.occurrences.len();
+        }
+    }
+    cap_span_group_occurrences(groups, options.max_occurrences_per_group);
+    fill_identifier_diversity(groups, files);
+    total
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,4 +706,85 @@ mod tests {
         truncate_to_char_boundary(&mut s, 4);
         assert_eq!(s, "你");
     }
+
+    #[test]
+    fn span_section_order_does_not_depend_on_input_order() {
+        let occurrence = |path: &str, start_line: u32| DuplicateSpanOccurrence {
+            repo_id: 0,
+            repo_label: "r".into(),
+            path: path.into(),
+            start_line,
+            end_line: start_line + 3,
+            start_col: 0,
+            end_col: 0,
+            code_char_cols: None,
+            is_test: false,
+            scope: None,
+            last_modified: None,
+        };
+        let group =
+            |content_hash: u64, occurrences: Vec<DuplicateSpanOccurrence>| DuplicateSpanGroup {
+                content_hash,
+                normalized_len: 10,
+                preview: String::new(),
+                occurrences,
+                truncated_occurrences: 0,
+                duplicated_lines: 0,
+                potential_savings_lines: 3,
+                also_detected_by: Vec::new(),
+                occurrence_previews: Vec::new(),
+                quality: None,
+            };
+        // Every group ties on the sort keys; two even share their group id.
+        let groups = vec![
+            group(2, vec![occurrence("b.rs", 1), occurrence("a.rs", 1)]),
+            group(1, vec![occurrence("c.rs", 5), occurrence("a.rs", 9)]),
+            group(1, vec![occurrence("b.rs", 3), occurrence("a.rs", 9)]),
+            group(3, vec![occurrence("a.rs", 20), occurrence("a.rs", 2)]),
+        ];
+        let reversed: Vec<DuplicateSpanGroup> = groups
+            .iter()
+            .rev()
+            .cloned()
+            .map(|mut group| {
+                group.occurrences.reverse();
+                group
+            })
+            .collect();
+        for sort in [
+            ReportSort::Occurrences,
+            ReportSort::Size,
+            ReportSort::Score,
+            ReportSort::Path,
+        ] {
+            let (mut forward, mut backward) = (groups.clone(), reversed.clone());
+            sort_span_groups_for_report(&mut forward, sort);
+            sort_span_groups_for_report(&mut backward, sort);
+            assert_eq!(forward, backward, "{sort:?}");
+        }
+        let mut sorted = groups;
+        sort_span_groups_for_report(&mut sorted, ReportSort::Score);
+        let order: Vec<(u64, &str)> = sorted
+            .iter()
+            .map(|g| (g.content_hash, g.occurrences[1].path.as_ref()))
+            .collect();
+        assert_eq!(order, [(1, "b.rs"), (1, "c.rs"), (2, "b.rs"), (3, "a.rs")]);
+    }
+
+    #[test]
+    fn line_diff_marks_changed_lines_and_ignores_indentation() {
+        let a: Vec<String> = ["fn f() {", "    let x = 1;", "    x + 1", "}"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let b: Vec<String> = ["fn f() {", "  let x = 2;", "  x + 1", "}"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let diff = line_diff(&a, &b);
+        assert_eq!(
+            diff,
+            " fn f() {\n-    let x = 1;\n+  let x = 2;\n     x + 1\n }\n"
+        );
+    }
 }
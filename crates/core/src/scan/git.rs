@@ -3,13 +3,19 @@ use std::fs;
 use std::io;
 use std::io::BufRead;
 use std::io::BufReader;
+use std::io::{Read, Write};
 use std::ops::ControlFlow;
 use std::path::{Component, Path, PathBuf};
 use std::process::{Command, Stdio};
 
-use crate::types::{ScanOptions, ScanStats};
+use ignore::gitignore::Gitignore;
 
-use super::{Repo, RepoFile, ignore_dirs_contains, should_stop_due_to_max_files};
+use crate::skip_log::SkipReason;
+use crate::types::{ScanOptions, ScanStats, VirtualFile};
+
+use super::{
+    CASE_INSENSITIVE_PATHS, Repo, RepoFile, ignore_dirs_contains, should_stop_due_to_max_files,
+};
 
 #[cfg(not(test))]
 const ENV_GIT_BIN: &str = "DUP_CODE_CHECK_GIT_BIN";
@@ -105,9 +111,51 @@ pub(super) fn with_test_git_exe<R>(exe: &Path, f: impl FnOnce() -> R) -> R {
     })
 }
 
+/// Whether `root` lies inside a Git work tree.
+pub(crate) fn is_git_work_tree(root: &Path) -> bool {
+    Command::new(git_exe())
+        .arg("-C")
+        .arg(root)
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .is_ok_and(|out| out.status.success() && out.stdout.starts_with(b"true"))
+}
+
+/// Commit time (Unix seconds) of the last commit that changed lines `start_line..=end_line` of
+/// `path` under `root`, via `git log -L`; `None` when Git cannot tell (untracked file, lines
+/// past the committed end, Git missing).
+pub(crate) fn line_range_last_modified(
+    root: &Path,
+    path: &str,
+    start_line: u32,
+    end_line: u32,
+) -> Option<u64> {
+    let out = Command::new(git_exe())
+        .arg("-C")
+        .arg(root)
+        .args(["log", "-1", "--format=%ct", "--no-patch"])
+        .arg(format!("-L{start_line},{end_line}:{path}"))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
 pub(super) fn try_visit_repo_files_via_git<F>(
     repo: &Repo,
     options: &ScanOptions,
+    excludes: &Gitignore,
     stats: &mut ScanStats,
     on_file: &mut F,
 ) -> io::Result<Option<ControlFlow<()>>>
@@ -119,7 +167,7 @@ where
     }
 
     // Stream `git ls-files` in small batches to avoid collecting the full file list in memory.
-    let out = visit_repo_files_via_git_streaming(repo, options, stats, on_file)?;
+    let out = visit_repo_files_via_git_streaming(repo, options, excludes, stats, on_file)?;
     if out.is_none() {
         stats.git_fast_path_fallbacks = stats.git_fast_path_fallbacks.saturating_add(1);
     }
@@ -129,6 +177,7 @@ where
 fn visit_repo_files_via_git_streaming<F>(
     repo: &Repo,
     options: &ScanOptions,
+    excludes: &Gitignore,
     stats: &mut ScanStats,
     on_file: &mut F,
 ) -> io::Result<Option<ControlFlow<()>>>
@@ -256,6 +305,7 @@ where
         let flow = match visit_repo_files_via_git_batch(
             repo,
             options,
+            excludes,
             stats,
             on_file,
             &batch,
@@ -284,6 +334,7 @@ where
         let flow = match visit_repo_files_via_git_batch(
             repo,
             options,
+            excludes,
             stats,
             on_file,
             &batch,
@@ -327,6 +378,7 @@ where
 fn visit_repo_files_via_git_batch<F>(
     repo: &Repo,
     options: &ScanOptions,
+    excludes: &Gitignore,
     stats: &mut ScanStats,
     on_file: &mut F,
     rel_paths: &[PathBuf],
@@ -342,6 +394,7 @@ where
     for rel in rel_paths {
         if !super::is_safe_relative_path_buf(rel) {
             stats.skipped_outside_root = stats.skipped_outside_root.saturating_add(1);
+            options.log_skip(&repo.root.join(rel), SkipReason::OutsideRoot, None);
             continue;
         }
 
@@ -354,13 +407,13 @@ where
                 let Some(name) = name.to_str() else {
                     continue;
                 };
-                if ignore_dirs_contains(&options.ignore_dirs, name) {
+                if ignore_dirs_contains(&options.ignore_dirs, name, CASE_INSENSITIVE_PATHS) {
                     ignored = true;
                     break;
                 }
             }
         }
-        if ignored {
+        if ignored || excludes.matched_path_or_any_parents(rel, false).is_ignore() {
             continue;
         }
 
@@ -369,14 +422,17 @@ where
             Ok(m) => m,
             Err(err) if err.kind() == io::ErrorKind::NotFound => {
                 stats.skipped_not_found = stats.skipped_not_found.saturating_add(1);
+                options.log_skip(&abs_path, SkipReason::NotFound, None);
                 continue;
             }
             Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
                 stats.skipped_permission_denied = stats.skipped_permission_denied.saturating_add(1);
+                options.log_skip(&abs_path, SkipReason::PermissionDenied, None);
                 continue;
             }
             Err(_) => {
                 stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
+                options.log_skip(&abs_path, SkipReason::WalkErrors, None);
                 continue;
             }
         };
@@ -404,3 +460,250 @@ where
 
     Ok(ControlFlow::Continue(()))
 }
+
+/// The regular files tracked at `git_ref` in the repository at `repo`, read from its object
+/// store: neither the working tree nor the index is touched. Submodules and symlinks are left
+/// out, as are files under [`ScanOptions::ignore_dirs`] or matching
+/// [`ScanOptions::ignore_patterns`]; a blob over [`ScanOptions::max_file_size`] is skipped
+/// without being read. Content that is not UTF-8 is decoded lossily.
+pub(crate) fn read_files_at_git_ref(
+    repo: &Path,
+    git_ref: &str,
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) -> io::Result<Vec<VirtualFile>> {
+    if git_ref.is_empty() || git_ref.starts_with('-') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid git ref: {git_ref:?}"),
+        ));
+    }
+    let ignore_patterns = super::build_exclude_patterns(repo, options)?;
+
+    let listing = Command::new(git_exe())
+        .arg("-C")
+        .arg(repo)
+        .args(["ls-tree", "-r", "-z", "-l", "--full-tree"])
+        .arg(format!("{git_ref}^{{tree}}"))
+        .stdin(Stdio::null())
+        .output()?;
+    if !listing.status.success() {
+        return Err(io::Error::other(format!(
+            "git ls-tree {git_ref} failed: {}",
+            String::from_utf8_lossy(&listing.stderr).trim()
+        )));
+    }
+
+    // `<mode> <type> <object> <size>\t<path>`, NUL-terminated.
+    let mut blobs: Vec<(String, String)> = Vec::new();
+    for entry in listing.stdout.split(|&b| b == 0) {
+        let Some(tab) = entry.iter().position(|&b| b == b'\t') else {
+            continue;
+        };
+        let meta = String::from_utf8_lossy(&entry[..tab]);
+        let path = String::from_utf8_lossy(&entry[tab + 1..]).into_owned();
+        let mut fields = meta.split_ascii_whitespace();
+        let (Some(mode), Some("blob"), Some(object), Some(size)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        if mode == "120000" {
+            continue;
+        }
+        let rel = Path::new(&path);
+        if !keeps_history_path(repo, rel, options, &ignore_patterns, stats) {
+            continue;
+        }
+        let size: u64 = size.parse().unwrap_or(0);
+        if options.max_file_size.is_some_and(|max| size > max) {
+            stats.candidate_files = stats.candidate_files.saturating_add(1);
+            stats.skipped_too_large = stats.skipped_too_large.saturating_add(1);
+            stats.skipped_too_large_bytes = stats.skipped_too_large_bytes.saturating_add(size);
+            options.log_skip(&repo.join(rel), SkipReason::TooLarge, Some(size));
+            continue;
+        }
+        blobs.push((object.to_string(), path));
+    }
+
+    read_blobs(repo, blobs).map_err(|err| io::Error::new(err.kind(), format!("{err} ({git_ref})")))
+}
+
+/// The files deleted by the last `depth` commits reachable from `HEAD` of the repository at
+/// `repo`, as their content just before deletion, read from its object store. Each path is
+/// prefixed with the deleting commit's abbreviated hash (`3f2a1c9b0d/src/old.rs`), keeping the
+/// extension that picks the language; a blob deleted more than once is kept at its most recent
+/// deletion. The same filters as [`read_files_at_git_ref`] apply, except that oversized blobs are
+/// left to the per-file limits of the scan.
+pub(crate) fn read_deleted_files_in_history(
+    repo: &Path,
+    depth: usize,
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+) -> io::Result<Vec<VirtualFile>> {
+    let ignore_patterns = super::build_exclude_patterns(repo, options)?;
+
+    // `-n` counts commits before `--diff-filter` would drop any, so filter deletions here.
+    let log = Command::new(git_exe())
+        .arg("-C")
+        .arg(repo)
+        .args([
+            "log",
+            "--raw",
+            // A moved file is not a deleted one.
+            "-M",
+            "--no-abbrev",
+            "-z",
+            "--format=%H",
+        ])
+        .arg(format!("--max-count={depth}"))
+        .arg("HEAD")
+        .stdin(Stdio::null())
+        .output()?;
+    if !log.status.success() {
+        return Err(io::Error::other(format!(
+            "git log failed: {}",
+            String::from_utf8_lossy(&log.stderr).trim()
+        )));
+    }
+
+    // `<commit>` tokens, each followed by `:<old mode> <new mode> <old> <new> <status>` tokens
+    // and their `<path>` (renames and copies: `<old path>` and `<new path>`), all NUL-terminated.
+    let mut blobs: Vec<(String, String)> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    let mut commit = String::new();
+    let mut tokens = log.stdout.split(|&b| b == 0);
+    while let Some(token) = tokens.next() {
+        let token = String::from_utf8_lossy(token);
+        let token = token.trim_start_matches('\n');
+        let Some(raw) = token.strip_prefix(':') else {
+            if !token.is_empty() {
+                commit = token.to_string();
+            }
+            continue;
+        };
+        let Some(path) = tokens.next() else {
+            break;
+        };
+        let path = String::from_utf8_lossy(path).into_owned();
+        let mut fields = raw.split_ascii_whitespace();
+        let (Some(mode), Some(_), Some(object), Some(_), Some(status)) = (
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+        ) else {
+            continue;
+        };
+        if status.starts_with(['R', 'C']) {
+            tokens.next();
+        }
+        if status != "D" {
+            continue;
+        }
+        // Symlinks and submodules hold no code.
+        if matches!(mode, "120000" | "160000") {
+            continue;
+        }
+        let rel = Path::new(&path);
+        if !keeps_history_path(repo, rel, options, &ignore_patterns, stats)
+            || !seen.insert(object.to_string())
+        {
+            continue;
+        }
+        let short = &commit[..commit.len().min(10)];
+        blobs.push((object.to_string(), format!("{short}/{path}")));
+    }
+
+    read_blobs(repo, blobs)
+}
+
+/// Whether a path read from git history is scanned: it must stay inside the repository (else it
+/// counts as outside the root) and not be ignored by [`ScanOptions::ignore_dirs`] or `excludes`.
+fn keeps_history_path(
+    repo: &Path,
+    rel: &Path,
+    options: &ScanOptions,
+    excludes: &Gitignore,
+    stats: &mut ScanStats,
+) -> bool {
+    if !super::is_safe_relative_path_buf(rel) {
+        stats.skipped_outside_root = stats.skipped_outside_root.saturating_add(1);
+        options.log_skip(&repo.join(rel), SkipReason::OutsideRoot, None);
+        return false;
+    }
+    let ignored = rel.parent().is_some_and(|parent| {
+        parent.components().any(|component| {
+            matches!(component, Component::Normal(name) if name.to_str().is_some_and(|name| {
+                ignore_dirs_contains(&options.ignore_dirs, name, CASE_INSENSITIVE_PATHS)
+            }))
+        })
+    });
+    !ignored && !excludes.matched_path_or_any_parents(rel, false).is_ignore()
+}
+
+/// The content of each `(object, path)` blob, read through one `git cat-file --batch`.
+fn read_blobs(repo: &Path, blobs: Vec<(String, String)>) -> io::Result<Vec<VirtualFile>> {
+    let mut child = Command::new(git_exe())
+        .arg("-C")
+        .arg(repo)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(io::Error::other("git cat-file has no stdio"));
+    };
+    // Feed object names from another thread so neither pipe can fill up and stall git.
+    let objects: Vec<String> = blobs.iter().map(|(object, _)| object.clone()).collect();
+    let writer = std::thread::spawn(move || -> io::Result<()> {
+        for object in objects {
+            writeln!(stdin, "{object}")?;
+        }
+        Ok(())
+    });
+
+    let mut reader = BufReader::new(stdout);
+    let mut files = Vec::with_capacity(blobs.len());
+    let mut header = String::new();
+    let result = (|| -> io::Result<()> {
+        for (object, path) in blobs {
+            header.clear();
+            reader.read_line(&mut header)?;
+            // `<object> blob <size>`, or `<object> missing`.
+            let size = header
+                .trim_end()
+                .strip_prefix(object.as_str())
+                .and_then(|rest| rest.strip_prefix(" blob "))
+                .and_then(|size| size.parse::<usize>().ok())
+                .ok_or_else(|| {
+                    io::Error::other(format!("git cat-file: unexpected reply for {path}"))
+                })?;
+            let mut bytes = vec![0u8; size];
+            reader.read_exact(&mut bytes)?;
+            let mut newline = [0u8; 1];
+            reader.read_exact(&mut newline)?;
+            let content = String::from_utf8(bytes)
+                .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned());
+            files.push(VirtualFile { path, content });
+        }
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = child.kill();
+    }
+    let written = writer
+        .join()
+        .unwrap_or_else(|_| Err(io::Error::other("git cat-file writer panicked")));
+    let status = child.wait()?;
+    result?;
+    written?;
+    if !status.success() {
+        return Err(io::Error::other("git cat-file failed"));
+    }
+    Ok(files)
+}
@@ -1,10 +1,14 @@
+use std::borrow::Cow;
 use std::collections::HashSet;
 use std::fs;
 use std::io;
+use std::ops::ControlFlow;
 use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 
-use crate::types::{ScanOptions, ScanStats};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+
+use crate::types::{BudgetOrder, RepoScanStats, ScanOptions, ScanStats, sample_rank};
 
 mod git;
 mod read;
@@ -13,8 +17,13 @@ mod walker;
 #[cfg(test)]
 mod tests;
 
+pub(crate) use git::{
+    is_git_work_tree, line_range_last_modified, read_deleted_files_in_history,
+    read_files_at_git_ref,
+};
 pub(crate) use read::{
-    read_repo_file_bytes, read_repo_file_bytes_for_verification, read_repo_file_bytes_with_path,
+    ReadTargets, read_repo_file_bytes, read_repo_file_bytes_for_verification,
+    read_repo_file_bytes_with_path,
 };
 
 #[cfg(test)]
@@ -32,18 +41,29 @@ fn should_stop_due_to_max_files(options: &ScanOptions, stats: &mut ScanStats) ->
     true
 }
 
-pub(crate) fn validate_roots(roots: &[PathBuf]) -> io::Result<()> {
+/// Check that every root is a directory, and drop roots that resolve to the same directory as an
+/// earlier one (another spelling, or a symlink), counting them in
+/// [`ScanStats::skipped_duplicate_roots`]. The first spelling of each directory is kept.
+pub(crate) fn validate_roots(roots: &[PathBuf], stats: &mut ScanStats) -> io::Result<Vec<PathBuf>> {
+    let mut seen = HashSet::new();
+    let mut unique = Vec::with_capacity(roots.len());
     for root in roots {
-        let meta = fs::metadata(root)
-            .map_err(|err| io::Error::new(err.kind(), format!("root {}: {err}", root.display())))?;
+        let root_error =
+            |err: io::Error| io::Error::new(err.kind(), format!("root {}: {err}", root.display()));
+        let meta = fs::metadata(root).map_err(root_error)?;
         if !meta.is_dir() {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 format!("root {} is not a directory", root.display()),
             ));
         }
+        if seen.insert(root.canonicalize().map_err(root_error)?) {
+            unique.push(root.clone());
+        } else {
+            stats.skipped_duplicate_roots = stats.skipped_duplicate_roots.saturating_add(1);
+        }
     }
-    Ok(())
+    Ok(unique)
 }
 
 #[derive(Debug, Clone)]
@@ -51,6 +71,66 @@ pub(crate) struct Repo {
     pub(crate) id: usize,
     pub(crate) root: PathBuf,
     pub(crate) label: Arc<str>,
+    /// Files whose [`sample_rank`] is at or above this are left out; set by [`apply_sample`].
+    pub(crate) sample_cutoff: Option<u64>,
+}
+
+/// Resolve [`ScanOptions::sample`] into each repo's `sample_cutoff`.
+///
+/// A file count needs the rank of every candidate path, so the repos are walked once up front
+/// (listing only, no reads).
+pub(crate) fn apply_sample(repos: &mut [Repo], options: &ScanOptions) -> io::Result<()> {
+    let Some(sample) = options.sample else {
+        return Ok(());
+    };
+    let cutoff = if let Some(fraction) = sample.as_fraction() {
+        (fraction < 1.0).then(|| (fraction * 2f64.powi(64)) as u64)
+    } else {
+        let files = usize::try_from(sample.as_files().unwrap_or(u64::MAX)).unwrap_or(usize::MAX);
+        let walk_options = ScanOptions {
+            progress: None,
+            skip_log: None,
+            sample: None,
+            budget_order: BudgetOrder::WalkOrder,
+            ..options.clone()
+        };
+        let mut ranks = Vec::new();
+        let mut stats = ScanStats::default();
+        for repo in repos.iter() {
+            let flow = visit_repo_files(repo, &walk_options, &mut stats, |_stats, file| {
+                if let Some(rel) = partition_path(&repo.root, &file.abs_path) {
+                    ranks.push(sample_rank(&rel));
+                }
+                Ok(ControlFlow::Continue(()))
+            })?;
+            if flow.is_break() {
+                break;
+            }
+        }
+        (ranks.len() > files).then(|| *ranks.select_nth_unstable(files).1)
+    };
+    for repo in repos {
+        repo.sample_cutoff = cutoff;
+    }
+    Ok(())
+}
+
+/// `abs_path` relative to `root` with `/` separators, as used by [`ScanShard`] and
+/// [`ScanSample`]; `None` when it is not under `root`.
+///
+/// [`ScanShard`]: crate::ScanShard
+/// [`ScanSample`]: crate::ScanSample
+fn partition_path(root: &Path, abs_path: &Path) -> Option<String> {
+    let rel = abs_path.strip_prefix(root).ok()?;
+    Some(
+        rel.components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
 }
 
 #[derive(Debug, Clone)]
@@ -58,6 +138,34 @@ pub(crate) struct RepoFile {
     pub(crate) abs_path: PathBuf,
 }
 
+/// Run `scan_repo` for each root in order, recording each root's counters in `stats.per_repo`.
+///
+/// Stops early when `scan_repo` breaks; roots that were never visited get zeroed entries.
+pub(crate) fn scan_repos<F>(
+    repos: &[Repo],
+    stats: &mut ScanStats,
+    mut scan_repo: F,
+) -> io::Result<()>
+where
+    F: FnMut(&Repo, &mut ScanStats) -> io::Result<ControlFlow<()>>,
+{
+    let mut per_repo: Vec<RepoScanStats> = repos
+        .iter()
+        .map(|repo| RepoScanStats::new(repo.id, repo.label.to_string(), ScanStats::default()))
+        .collect();
+    for (entry, repo) in per_repo.iter_mut().zip(repos) {
+        let before = stats.clone();
+        let flow = scan_repo(repo, stats);
+        *entry = RepoScanStats::new(repo.id, repo.label.to_string(), stats.delta_since(&before));
+        if flow?.is_break() {
+            break;
+        }
+    }
+
+    stats.per_repo = per_repo;
+    Ok(())
+}
+
 pub(crate) fn repo_label(root: &Path, id: usize) -> String {
     root.file_name()
         .and_then(|s| s.to_str())
@@ -66,18 +174,64 @@ pub(crate) fn repo_label(root: &Path, id: usize) -> String {
         .unwrap_or_else(|| format!("repo{id}"))
 }
 
-fn ignore_dirs_contains(ignore_dirs: &HashSet<String>, name: &str) -> bool {
+/// Whether the usual filesystems of this platform compare paths case-insensitively (NTFS,
+/// APFS), so `Vendor/` and `vendor/` name the same directory.
+pub(crate) const CASE_INSENSITIVE_PATHS: bool = cfg!(any(windows, target_os = "macos"));
+
+/// `path` as a lookup key: case-folded when `case_insensitive`, so two spellings of one path
+/// compare equal.
+pub(crate) fn path_key(path: &str, case_insensitive: bool) -> Cow<'_, str> {
+    if case_insensitive {
+        Cow::Owned(path.to_lowercase())
+    } else {
+        Cow::Borrowed(path)
+    }
+}
+
+/// Matcher for [`ScanOptions::ignore_patterns`], anchored at `root`; case-insensitive where
+/// [`CASE_INSENSITIVE_PATHS`] holds.
+pub(crate) fn build_ignore_patterns(root: &Path, patterns: &[String]) -> io::Result<Gitignore> {
+    if patterns.is_empty() {
+        return Ok(Gitignore::empty());
+    }
+    let mut builder = GitignoreBuilder::new(root);
+    builder
+        .case_insensitive(CASE_INSENSITIVE_PATHS)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+    for pattern in patterns {
+        builder.add_line(None, pattern).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid ignore pattern {pattern:?}: {err}"),
+            )
+        })?;
+    }
+    builder
+        .build()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))
+}
+
+/// Matcher for [`ScanOptions::default_excludes`] followed by [`ScanOptions::ignore_patterns`],
+/// so a user `!pattern` can re-include a default exclude.
+pub(crate) fn build_exclude_patterns(root: &Path, options: &ScanOptions) -> io::Result<Gitignore> {
+    let patterns: Vec<String> = options
+        .default_excludes
+        .iter()
+        .chain(&options.ignore_patterns)
+        .cloned()
+        .collect();
+    build_ignore_patterns(root, &patterns)
+}
+
+fn ignore_dirs_contains(ignore_dirs: &HashSet<String>, name: &str, case_insensitive: bool) -> bool {
     if ignore_dirs.contains(name) {
         return true;
     }
-    #[cfg(windows)]
-    {
-        ignore_dirs.iter().any(|d| d.eq_ignore_ascii_case(name))
-    }
-    #[cfg(not(windows))]
-    {
-        false
+    if !case_insensitive {
+        return false;
     }
+    let name = path_key(name, true);
+    ignore_dirs.iter().any(|dir| path_key(dir, true) == name)
 }
 
 #[cfg(test)]
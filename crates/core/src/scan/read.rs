@@ -1,13 +1,17 @@
+use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
-use crate::types::{ScanOptions, ScanStats};
+use crate::skip_log::SkipReason;
+use crate::types::{BudgetOrder, FileListing, ReportSort, ScanOptions, ScanStats};
 #[cfg(test)]
 use crate::util::fnv1a64;
 
 use super::RepoFile;
 
+const READ_CHUNK_BYTES: usize = 16 * 1024;
+
 #[cfg(test)]
 type BeforeOpenHook = std::cell::RefCell<Option<Box<dyn FnMut(&Path)>>>;
 
@@ -44,13 +48,22 @@ pub(crate) fn make_rel_path(root: &Path, abs_path: &Path) -> String {
     }
 }
 
+/// Canonical targets read so far when following symlinks, with the id of the root that read
+/// each one first.
+pub(crate) type ReadTargets = HashMap<PathBuf, usize>;
+
+/// The path to read `repo_file` (of root `repo_id`) from: the file itself, or with
+/// [`ScanOptions::follow_symlinks`] its canonical target, which must lie inside the root and not
+/// have been read under another root (through a symlink, or nested roots) already.
 fn resolve_read_path(
     repo_file: &RepoFile,
+    repo_id: usize,
     canonical_root: Option<&Path>,
-    follow_symlinks: bool,
+    read_targets: &mut ReadTargets,
+    options: &ScanOptions,
     stats: &mut ScanStats,
 ) -> io::Result<Option<PathBuf>> {
-    if !follow_symlinks {
+    if !options.follow_symlinks {
         return Ok(Some(repo_file.abs_path.clone()));
     }
 
@@ -64,20 +77,30 @@ fn resolve_read_path(
         Ok(p) => p,
         Err(err) if err.kind() == io::ErrorKind::NotFound => {
             stats.skipped_not_found = stats.skipped_not_found.saturating_add(1);
+            options.log_skip(&repo_file.abs_path, SkipReason::NotFound, None);
             return Ok(None);
         }
         Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
             stats.skipped_permission_denied = stats.skipped_permission_denied.saturating_add(1);
+            options.log_skip(&repo_file.abs_path, SkipReason::PermissionDenied, None);
             return Ok(None);
         }
         Err(_) => {
             stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
+            options.log_skip(&repo_file.abs_path, SkipReason::WalkErrors, None);
             return Ok(None);
         }
     };
 
     if !resolved.starts_with(canonical_root) {
         stats.skipped_outside_root = stats.skipped_outside_root.saturating_add(1);
+        options.log_skip(&repo_file.abs_path, SkipReason::OutsideRoot, None);
+        return Ok(None);
+    }
+
+    if *read_targets.entry(resolved.clone()).or_insert(repo_id) != repo_id {
+        stats.skipped_symlink_duplicates = stats.skipped_symlink_duplicates.saturating_add(1);
+        options.log_skip(&repo_file.abs_path, SkipReason::SymlinkDuplicate, None);
         return Ok(None);
     }
 
@@ -86,19 +109,28 @@ fn resolve_read_path(
 
 pub(crate) fn read_repo_file_bytes(
     repo_file: &RepoFile,
+    repo_id: usize,
     canonical_root: Option<&Path>,
+    read_targets: &mut ReadTargets,
     options: &ScanOptions,
     stats: &mut ScanStats,
 ) -> io::Result<Option<Vec<u8>>> {
-    Ok(
-        read_repo_file_bytes_with_path(repo_file, canonical_root, options, stats)?
-            .map(|(bytes, _path)| bytes),
-    )
+    Ok(read_repo_file_bytes_with_path(
+        repo_file,
+        repo_id,
+        canonical_root,
+        read_targets,
+        options,
+        stats,
+    )?
+    .map(|(bytes, _path)| bytes))
 }
 
 pub(crate) fn read_repo_file_bytes_with_path(
     repo_file: &RepoFile,
+    repo_id: usize,
     canonical_root: Option<&Path>,
+    read_targets: &mut ReadTargets,
     options: &ScanOptions,
     stats: &mut ScanStats,
 ) -> io::Result<Option<(Vec<u8>, PathBuf)>> {
@@ -108,8 +140,14 @@ pub(crate) fn read_repo_file_bytes_with_path(
         return Ok(None);
     }
 
-    let Some(read_path) =
-        resolve_read_path(repo_file, canonical_root, options.follow_symlinks, stats)?
+    let Some(read_path) = resolve_read_path(
+        repo_file,
+        repo_id,
+        canonical_root,
+        read_targets,
+        options,
+        stats,
+    )?
     else {
         return Ok(None);
     };
@@ -120,20 +158,24 @@ pub(crate) fn read_repo_file_bytes_with_path(
         Ok(m) => {
             if m.file_type().is_symlink() {
                 stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
+                options.log_skip(&repo_file.abs_path, SkipReason::WalkErrors, None);
                 return Ok(None);
             }
             m
         }
         Err(err) if err.kind() == io::ErrorKind::NotFound => {
             stats.skipped_not_found = stats.skipped_not_found.saturating_add(1);
+            options.log_skip(&repo_file.abs_path, SkipReason::NotFound, None);
             return Ok(None);
         }
         Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
             stats.skipped_permission_denied = stats.skipped_permission_denied.saturating_add(1);
+            options.log_skip(&repo_file.abs_path, SkipReason::PermissionDenied, None);
             return Ok(None);
         }
         Err(_) => {
             stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
+            options.log_skip(&repo_file.abs_path, SkipReason::WalkErrors, None);
             return Ok(None);
         }
     };
@@ -142,6 +184,13 @@ pub(crate) fn read_repo_file_bytes_with_path(
         && metadata.len() > max_file_size
     {
         stats.skipped_too_large = stats.skipped_too_large.saturating_add(1);
+        stats.skipped_too_large_bytes =
+            stats.skipped_too_large_bytes.saturating_add(metadata.len());
+        options.log_skip(
+            &repo_file.abs_path,
+            SkipReason::TooLarge,
+            Some(metadata.len()),
+        );
         return Ok(None);
     }
 
@@ -150,6 +199,14 @@ pub(crate) fn read_repo_file_bytes_with_path(
     {
         stats.skipped_budget_max_total_bytes =
             stats.skipped_budget_max_total_bytes.saturating_add(1);
+        stats.skipped_budget_max_total_bytes_bytes = stats
+            .skipped_budget_max_total_bytes_bytes
+            .saturating_add(metadata.len());
+        options.log_skip(
+            &repo_file.abs_path,
+            SkipReason::BudgetMaxTotalBytes,
+            Some(metadata.len()),
+        );
         return Ok(None);
     }
 
@@ -164,14 +221,29 @@ pub(crate) fn read_repo_file_bytes_with_path(
         Ok(f) => f,
         Err(err) if err.kind() == io::ErrorKind::NotFound => {
             stats.skipped_not_found = stats.skipped_not_found.saturating_add(1);
+            options.log_skip(
+                &repo_file.abs_path,
+                SkipReason::NotFound,
+                Some(metadata.len()),
+            );
             return Ok(None);
         }
         Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
             stats.skipped_permission_denied = stats.skipped_permission_denied.saturating_add(1);
+            options.log_skip(
+                &repo_file.abs_path,
+                SkipReason::PermissionDenied,
+                Some(metadata.len()),
+            );
             return Ok(None);
         }
         Err(_) => {
             stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
+            options.log_skip(
+                &repo_file.abs_path,
+                SkipReason::WalkErrors,
+                Some(metadata.len()),
+            );
             return Ok(None);
         }
     };
@@ -184,19 +256,39 @@ pub(crate) fn read_repo_file_bytes_with_path(
             Ok(m) => m,
             Err(err) if err.kind() == io::ErrorKind::NotFound => {
                 stats.skipped_not_found = stats.skipped_not_found.saturating_add(1);
+                options.log_skip(
+                    &repo_file.abs_path,
+                    SkipReason::NotFound,
+                    Some(metadata.len()),
+                );
                 return Ok(None);
             }
             Err(err) if err.kind() == io::ErrorKind::PermissionDenied => {
                 stats.skipped_permission_denied = stats.skipped_permission_denied.saturating_add(1);
+                options.log_skip(
+                    &repo_file.abs_path,
+                    SkipReason::PermissionDenied,
+                    Some(metadata.len()),
+                );
                 return Ok(None);
             }
             Err(_) => {
                 stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
+                options.log_skip(
+                    &repo_file.abs_path,
+                    SkipReason::WalkErrors,
+                    Some(metadata.len()),
+                );
                 return Ok(None);
             }
         };
         if (metadata.dev(), metadata.ino()) != (opened.dev(), opened.ino()) {
             stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
+            options.log_skip(
+                &repo_file.abs_path,
+                SkipReason::WalkErrors,
+                Some(metadata.len()),
+            );
             return Ok(None);
         }
     }
@@ -207,11 +299,15 @@ pub(crate) fn read_repo_file_bytes_with_path(
     let max_file_size = options.max_file_size;
     let max_total_bytes = options.max_total_bytes;
 
-    let mut bytes: Vec<u8> = Vec::with_capacity(metadata_len.min(1024 * 1024) as usize);
+    // Size the buffer from the metadata and read straight into it, so large files are neither
+    // copied through a bounce buffer nor reallocated while growing. `probe` only handles files
+    // that grew since `metadata` was taken (and the final EOF read).
+    let expected_len = max_file_size.map_or(metadata_len, |max| metadata_len.min(max));
+    let mut bytes: Vec<u8> = Vec::with_capacity(usize::try_from(expected_len).unwrap_or(0));
     let mut total_read: u64 = 0;
-    let mut buf = [0u8; 16 * 1024];
+    let mut probe = [0u8; READ_CHUNK_BYTES];
     loop {
-        let mut limit = buf.len() as u64;
+        let mut limit = READ_CHUNK_BYTES as u64;
 
         if let Some(max_file_size) = max_file_size {
             let cap = max_file_size.saturating_add(1);
@@ -220,6 +316,13 @@ pub(crate) fn read_repo_file_bytes_with_path(
                 stats.scanned_files = stats.scanned_files.saturating_add(1);
                 stats.scanned_bytes = stats.scanned_bytes.saturating_add(total_read);
                 stats.skipped_too_large = stats.skipped_too_large.saturating_add(1);
+                stats.skipped_too_large_bytes =
+                    stats.skipped_too_large_bytes.saturating_add(metadata_len);
+                options.log_skip(
+                    &repo_file.abs_path,
+                    SkipReason::TooLarge,
+                    Some(metadata_len),
+                );
                 return Ok(None);
             }
             limit = limit.min(remaining);
@@ -236,15 +339,39 @@ pub(crate) fn read_repo_file_bytes_with_path(
                 stats.scanned_bytes = stats.scanned_bytes.saturating_add(total_read);
                 stats.skipped_budget_max_total_bytes =
                     stats.skipped_budget_max_total_bytes.saturating_add(1);
+                stats.skipped_budget_max_total_bytes_bytes = stats
+                    .skipped_budget_max_total_bytes_bytes
+                    .saturating_add(metadata_len);
+                options.log_skip(
+                    &repo_file.abs_path,
+                    SkipReason::BudgetMaxTotalBytes,
+                    Some(metadata_len),
+                );
                 return Ok(None);
             }
             limit = limit.min(remaining_budget);
         }
 
-        let n = match file.read(&mut buf[..limit as usize]) {
+        let start = bytes.len();
+        let spare = (bytes.capacity() - start) as u64;
+        let read = if spare > 0 {
+            bytes.resize(start + limit.min(spare) as usize, 0);
+            let read = file.read(&mut bytes[start..]);
+            bytes.truncate(start + read.as_ref().map_or(0, |&n| n));
+            read
+        } else {
+            file.read(&mut probe[..limit as usize])
+                .inspect(|&n| bytes.extend_from_slice(&probe[..n]))
+        };
+        let n = match read {
             Ok(n) => n,
             Err(_) => {
                 stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
+                options.log_skip(
+                    &repo_file.abs_path,
+                    SkipReason::WalkErrors,
+                    Some(metadata_len),
+                );
                 if total_read > 0 {
                     stats.scanned_files = stats.scanned_files.saturating_add(1);
                     stats.scanned_bytes = stats.scanned_bytes.saturating_add(total_read);
@@ -257,10 +384,12 @@ pub(crate) fn read_repo_file_bytes_with_path(
         }
 
         let new_total_read = total_read.saturating_add(n as u64);
-        if buf[..n].contains(&0) {
+        if bytes[start..].contains(&0) {
             stats.scanned_files = stats.scanned_files.saturating_add(1);
             stats.scanned_bytes = stats.scanned_bytes.saturating_add(new_total_read);
             stats.skipped_binary = stats.skipped_binary.saturating_add(1);
+            stats.skipped_binary_bytes = stats.skipped_binary_bytes.saturating_add(metadata_len);
+            options.log_skip(&repo_file.abs_path, SkipReason::Binary, Some(metadata_len));
             return Ok(None);
         }
 
@@ -270,10 +399,16 @@ pub(crate) fn read_repo_file_bytes_with_path(
             stats.scanned_files = stats.scanned_files.saturating_add(1);
             stats.scanned_bytes = stats.scanned_bytes.saturating_add(new_total_read);
             stats.skipped_too_large = stats.skipped_too_large.saturating_add(1);
+            stats.skipped_too_large_bytes =
+                stats.skipped_too_large_bytes.saturating_add(metadata_len);
+            options.log_skip(
+                &repo_file.abs_path,
+                SkipReason::TooLarge,
+                Some(metadata_len),
+            );
             return Ok(None);
         }
 
-        bytes.extend_from_slice(&buf[..n]);
         total_read = new_total_read;
     }
 
@@ -305,20 +440,82 @@ pub(crate) fn read_repo_file_bytes_for_verification(
     };
     let options = ScanOptions {
         ignore_dirs: std::collections::HashSet::new(),
+        ignore_patterns: Vec::new(),
+        default_excludes: Vec::new(),
         follow_symlinks,
         max_file_size,
         max_files: None,
         max_total_bytes: None,
+        budget_order: BudgetOrder::WalkOrder,
         max_normalized_chars: None,
         max_tokens: None,
         min_match_len: 1,
         min_token_len: 1,
+        adaptive_window: None,
         similarity_threshold: 0.0,
         simhash_max_distance: 0,
+        shingle_size: 5,
+        minhash_signature_size: 32,
+        minhash_bands: 8,
+        similarity_windows: None,
+        line_span_min_lines: 2,
+        line_span_window: 8,
+        symbol_min_count: 3,
+        import_min_count: 3,
+        import_similarity_threshold: 0.8,
+        data_min_bytes: 1024,
+        min_entropy: 0.0,
         max_report_items: 0,
+        max_items_per_detector: std::collections::HashMap::new(),
+        max_groups_per_detector: None,
         respect_gitignore: true,
+        use_global_gitignore: false,
+        file_listing: FileListing::Auto,
         cross_repo_only: false,
+        similarity_diff: false,
+        min_occurrences: 2,
+        max_occurrences_per_group: None,
+        file_coverage: false,
+        file_similarity: false,
+        clone_clusters: false,
+        similarity_clusters: false,
+        occurrence_ages: false,
+        license_conflicts: false,
+        idiom_min_files: None,
+        idiom_token_len: 12,
+        max_tokens_per_file: None,
+        max_file_time: None,
+        deduplicate_across_detectors: false,
+        emit_absolute_paths: false,
+        path_prefix_map: Vec::new(),
+        progress: None,
+        skip_log: None,
+        shard: None,
+        sample: None,
+        unicode_normalization: None,
+        test_code_filter: None,
+        threads: 1,
+        preview_max_chars: None,
+        preview_context_lines: 0,
+        report_sort: ReportSort::Occurrences,
+        section_offset: 0,
+        section_limit: None,
+        language_token_scales: Vec::new(),
+        path_filter: Vec::new(),
+        path_filter_trim: false,
+        builtin_skips: false,
+        skip_snippets: Vec::new(),
+        stop_sequences: Vec::new(),
+        prune_contained_groups: false,
+        group_suppressions: Vec::new(),
     };
     let mut stats = ScanStats::default();
-    read_repo_file_bytes(&repo_file, canonical_root, &options, &mut stats)
+    read_repo_file_bytes(
+        &repo_file,
+        0,
+        canonical_root,
+        &mut ReadTargets::new(),
+        &options,
+        &mut stats,
+    )
 }
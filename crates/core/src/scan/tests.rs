@@ -50,7 +50,16 @@ fn read_repo_file_bytes_enforces_max_file_size_during_read() -> io::Result<()> {
             let mut file = fs::OpenOptions::new().append(true).open(read_path).unwrap();
             file.write_all(&[b'a'; 32]).unwrap();
         },
-        || read_repo_file_bytes_with_path(&repo_file, None, &options, &mut stats),
+        || {
+            read_repo_file_bytes_with_path(
+                &repo_file,
+                0,
+                None,
+                &mut ReadTargets::new(),
+                &options,
+                &mut stats,
+            )
+        },
     )?;
 
     assert!(out.is_none());
@@ -86,7 +95,16 @@ fn read_repo_file_bytes_enforces_max_total_bytes_during_read() -> io::Result<()>
             let mut file = fs::OpenOptions::new().append(true).open(read_path).unwrap();
             file.write_all(&[b'a'; 64]).unwrap();
         },
-        || read_repo_file_bytes_with_path(&repo_file, None, &options, &mut stats),
+        || {
+            read_repo_file_bytes_with_path(
+                &repo_file,
+                0,
+                None,
+                &mut ReadTargets::new(),
+                &options,
+                &mut stats,
+            )
+        },
     )?;
 
     assert!(out.is_none());
@@ -243,6 +261,7 @@ fn git_streaming_handles_non_utf8_paths_on_unix_before_scanning() -> io::Result<
             id: 0,
             root: root.clone(),
             label: "test".into(),
+            sample_cutoff: None,
         };
         let options = ScanOptions {
             max_files: Some(10),
@@ -300,6 +319,7 @@ fn git_streaming_handles_non_utf8_paths_on_unix_after_scanning_started() -> io::
             id: 0,
             root: root.clone(),
             label: "test".into(),
+            sample_cutoff: None,
         };
         let options = ScanOptions {
             max_files: Some(FILES + 10),
@@ -356,6 +376,7 @@ fn git_streaming_metadata_error_is_counted_and_skipped() -> io::Result<()> {
             id: 0,
             root: root.clone(),
             label: "test".into(),
+            sample_cutoff: None,
         };
         let options = ScanOptions {
             max_files: Some(10),
@@ -414,6 +435,7 @@ fn git_fast_path_fallback_does_not_rescan_files() -> io::Result<()> {
             id: 0,
             root: root.clone(),
             label: "test".into(),
+            sample_cutoff: None,
         };
         let options = ScanOptions {
             max_files: Some(FILES + 10),
@@ -438,6 +460,276 @@ fn git_fast_path_fallback_does_not_rescan_files() -> io::Result<()> {
     Ok(())
 }
 
+#[test]
+fn default_excludes_skip_lockfiles_unless_cleared_or_negated() -> io::Result<()> {
+    let root = temp_dir("default_excludes");
+    fs::create_dir_all(root.join("web").join("proto"))?;
+    for rel in [
+        "main.rs",
+        "Cargo.lock",
+        "web/yarn.lock",
+        "web/app.min.js",
+        "web/proto/api.pb.go",
+        "web/schema_generated.ts",
+    ] {
+        fs::write(root.join(rel), "x")?;
+    }
+
+    let repo = Repo {
+        id: 0,
+        root: root.clone(),
+        label: "test".into(),
+        sample_cutoff: None,
+    };
+    let visit = |options: &ScanOptions| -> io::Result<Vec<String>> {
+        let mut visited = Vec::new();
+        let mut stats = ScanStats::default();
+        let flow = visit_repo_files(&repo, options, &mut stats, |_stats, file| {
+            visited.push(make_rel_path(&root, &file.abs_path));
+            Ok(ControlFlow::Continue(()))
+        })?;
+        assert_eq!(flow, ControlFlow::Continue(()));
+        visited.sort();
+        Ok(visited)
+    };
+
+    assert_eq!(visit(&ScanOptions::default())?, ["main.rs"]);
+    let options = ScanOptions {
+        ignore_patterns: vec!["!Cargo.lock".into()],
+        ..ScanOptions::default()
+    };
+    assert_eq!(visit(&options)?, ["Cargo.lock", "main.rs"]);
+    let options = ScanOptions {
+        default_excludes: Vec::new(),
+        ..ScanOptions::default()
+    };
+    assert_eq!(visit(&options)?.len(), 6);
+    Ok(())
+}
+
+#[test]
+fn ignore_patterns_skip_files_in_walker_and_git_fast_path() -> io::Result<()> {
+    let root = temp_dir("ignore_patterns_skip_files");
+    fs::create_dir_all(root.join("gen").join("deep"))?;
+    for rel in ["a.js", "a.min.js", "gen/deep/b.rs", "keep.min.js"] {
+        fs::write(root.join(rel), "x")?;
+    }
+
+    let repo = Repo {
+        id: 0,
+        root: root.clone(),
+        label: "test".into(),
+        sample_cutoff: None,
+    };
+    let options = ScanOptions {
+        ignore_patterns: vec!["*.min.js".into(), "!keep.min.js".into(), "gen/".into()],
+        ..ScanOptions::default()
+    };
+    let visit = |stats: &mut ScanStats| -> io::Result<Vec<String>> {
+        let mut visited = Vec::new();
+        let flow = visit_repo_files(&repo, &options, stats, |_stats, file| {
+            visited.push(make_rel_path(&root, &file.abs_path));
+            Ok(ControlFlow::Continue(()))
+        })?;
+        assert_eq!(flow, ControlFlow::Continue(()));
+        visited.sort();
+        Ok(visited)
+    };
+
+    let mut stats = ScanStats::default();
+    assert_eq!(visit(&mut stats)?, ["a.js", "keep.min.js"]);
+    assert_eq!(stats.candidate_files, 2);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let git_dir = root.join(".git");
+        fs::create_dir_all(&git_dir)?;
+        let fake_git_path = git_dir.join("fake_git.sh");
+        fs::write(
+            &fake_git_path,
+            fake_git_script_paths(&root, "a.js\\0a.min.js\\0gen/deep/b.rs\\0keep.min.js\\0"),
+        )?;
+        let mut perms = fs::metadata(&fake_git_path)?.permissions();
+        perms.set_mode(0o755);
+        fs::set_permissions(&fake_git_path, perms)?;
+
+        let mut stats = ScanStats::default();
+        let visited = git::with_test_git_exe(&fake_git_path, || visit(&mut stats))?;
+        assert_eq!(visited, ["a.js", "keep.min.js"]);
+        assert_eq!(stats.git_fast_path_fallbacks, 0);
+    }
+
+    let invalid = ScanOptions {
+        ignore_patterns: vec!["src/[z-a].rs".into()],
+        ..ScanOptions::default()
+    };
+    let err = invalid.validate_for_file_duplicates().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
+#[test]
+fn file_listing_strategies_agree_on_negated_and_nested_gitignores() -> io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    use crate::types::FileListing;
+
+    let root = temp_dir("file_listing_parity");
+    fs::create_dir_all(root.join("sub").join("build"))?;
+    fs::create_dir_all(root.join("build"))?;
+    let init_ok = Command::new("git")
+        .arg("init")
+        .current_dir(&root)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|s| s.success());
+    if !init_ok {
+        return Ok(());
+    }
+    fs::write(root.join(".gitignore"), "*.log\n!keep.log\nbuild/\n")?;
+    fs::write(root.join("sub").join(".gitignore"), "*.tmp\n!*.rs\n")?;
+    for rel in [
+        "a.rs",
+        "debug.log",
+        "keep.log",
+        "build/out.rs",
+        "sub/b.rs",
+        "sub/c.tmp",
+        "sub/build/d.rs",
+    ] {
+        fs::write(root.join(rel), "x")?;
+    }
+
+    let repo = Repo {
+        id: 0,
+        root: root.clone(),
+        label: "test".into(),
+        sample_cutoff: None,
+    };
+    let list = |file_listing: FileListing| -> io::Result<(Vec<String>, ScanStats)> {
+        let options = ScanOptions {
+            file_listing,
+            ..ScanOptions::default()
+        };
+        let mut stats = ScanStats::default();
+        let mut visited = Vec::new();
+        let flow = visit_repo_files(&repo, &options, &mut stats, |_stats, file| {
+            visited.push(make_rel_path(&root, &file.abs_path));
+            Ok(ControlFlow::Continue(()))
+        })?;
+        assert_eq!(flow, ControlFlow::Continue(()));
+        visited.sort();
+        Ok((visited, stats))
+    };
+
+    let (via_git, git_stats) = list(FileListing::GitCli)?;
+    let (via_walker, walker_stats) = list(FileListing::Walker)?;
+    assert_eq!(
+        via_git,
+        [
+            ".gitignore",
+            "a.rs",
+            "keep.log",
+            "sub/.gitignore",
+            "sub/b.rs"
+        ]
+    );
+    assert_eq!(via_walker, via_git);
+    assert_eq!(
+        (git_stats.git_listed_repos, git_stats.walker_listed_repos),
+        (1, 0)
+    );
+    assert_eq!(
+        (
+            walker_stats.git_listed_repos,
+            walker_stats.walker_listed_repos
+        ),
+        (0, 1)
+    );
+    Ok(())
+}
+
+#[test]
+fn budget_order_reads_files_in_a_stable_order() -> io::Result<()> {
+    use crate::types::BudgetOrder;
+
+    let root = temp_dir("budget_order");
+    fs::create_dir_all(root.join("d"))?;
+    for (rel, len) in [("b.rs", 30), ("d/a.rs", 10), ("c.rs", 20), ("a.rs", 20)] {
+        fs::write(root.join(rel), "x".repeat(len))?;
+    }
+    let repo = Repo {
+        id: 0,
+        root: root.clone(),
+        label: "test".into(),
+        sample_cutoff: None,
+    };
+    let visit = |budget_order: BudgetOrder, max_files: Option<usize>| -> io::Result<Vec<String>> {
+        let options = ScanOptions {
+            budget_order,
+            max_files,
+            ..ScanOptions::default()
+        };
+        let mut stats = ScanStats::default();
+        let mut visited = Vec::new();
+        let flow = visit_repo_files(&repo, &options, &mut stats, |stats, file| {
+            stats.scanned_files = stats.scanned_files.saturating_add(1);
+            visited.push(make_rel_path(&root, &file.abs_path));
+            Ok(ControlFlow::Continue(()))
+        })?;
+        assert_eq!(flow.is_break(), max_files.is_some());
+        Ok(visited)
+    };
+
+    assert_eq!(
+        visit(BudgetOrder::SmallestFirst, None)?,
+        ["d/a.rs", "a.rs", "c.rs", "b.rs"]
+    );
+    assert_eq!(
+        visit(BudgetOrder::LargestFirst, None)?,
+        ["b.rs", "a.rs", "c.rs", "d/a.rs"]
+    );
+    assert_eq!(visit(BudgetOrder::PathSorted, Some(2))?, ["a.rs", "b.rs"]);
+    assert_eq!(visit(BudgetOrder::WalkOrder, None)?.len(), 4);
+    Ok(())
+}
+
+#[test]
+fn forced_git_file_listing_fails_outside_git_checkouts() -> io::Result<()> {
+    use crate::types::FileListing;
+
+    let root = temp_dir("file_listing_git_outside_checkout");
+    fs::create_dir_all(&root)?;
+    fs::write(root.join("a.rs"), "x")?;
+    let repo = Repo {
+        id: 0,
+        root: root.clone(),
+        label: "test".into(),
+        sample_cutoff: None,
+    };
+    let options = ScanOptions {
+        file_listing: FileListing::GitCli,
+        ..ScanOptions::default()
+    };
+    let mut stats = ScanStats::default();
+    let err = visit_repo_files(&repo, &options, &mut stats, |_stats, _file| {
+        Ok(ControlFlow::Continue(()))
+    })
+    .unwrap_err();
+    assert!(err.to_string().contains("not a Git checkout"));
+
+    let invalid = ScanOptions {
+        follow_symlinks: true,
+        ..options
+    };
+    let err = invalid.validate_for_file_duplicates().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    Ok(())
+}
+
 #[test]
 fn read_repo_file_bytes_counts_binary_reads_in_scan_stats() -> io::Result<()> {
     let root = temp_dir("read_repo_file_bytes_binary_counts");
@@ -449,7 +741,14 @@ fn read_repo_file_bytes_counts_binary_reads_in_scan_stats() -> io::Result<()> {
 
     let options = ScanOptions::default();
     let mut stats = ScanStats::default();
-    let out = read_repo_file_bytes(&repo_file, None, &options, &mut stats)?;
+    let out = read_repo_file_bytes(
+        &repo_file,
+        0,
+        None,
+        &mut ReadTargets::new(),
+        &options,
+        &mut stats,
+    )?;
 
     assert!(out.is_none());
     assert_eq!(stats.skipped_binary, 1);
@@ -459,6 +758,83 @@ fn read_repo_file_bytes_counts_binary_reads_in_scan_stats() -> io::Result<()> {
     Ok(())
 }
 
+#[test]
+fn skip_log_reports_each_skipped_file_with_its_reason() -> io::Result<()> {
+    use crate::skip_log::{SkipCallback, SkipReason};
+    use crate::types::ScanShard;
+
+    let root = temp_dir("skip_log_reasons");
+    fs::create_dir_all(&root)?;
+    for i in 0..8 {
+        fs::write(root.join(format!("f{i}.txt")), format!("file {i}\n"))?;
+    }
+    fs::write(root.join("bin.dat"), b"hello\0world")?;
+
+    let logged = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = Arc::clone(&logged);
+    let options = ScanOptions {
+        shard: Some(ScanShard::new(0, 2)?),
+        skip_log: Some(SkipCallback::new(move |skipped| {
+            sink.lock()
+                .unwrap()
+                .push((skipped.path.to_path_buf(), skipped.reason, skipped.size));
+        })),
+        ..ScanOptions::default()
+    };
+    let outcome = crate::find_duplicate_files_with_stats(std::slice::from_ref(&root), &options)?;
+
+    let logged = logged.lock().unwrap();
+    let count = |reason| logged.iter().filter(|(_, r, _)| *r == reason).count() as u64;
+    assert!(outcome.stats.skipped_other_shard > 0);
+    assert_eq!(
+        count(SkipReason::OtherShard),
+        outcome.stats.skipped_other_shard
+    );
+    assert_eq!(count(SkipReason::Binary), outcome.stats.skipped_binary);
+    assert_eq!(
+        logged.len() as u64,
+        outcome.stats.skipped_other_shard + outcome.stats.skipped_binary
+    );
+    for (path, reason, size) in logged.iter() {
+        assert!(path.starts_with(&root));
+        match reason {
+            SkipReason::Binary => assert_eq!(*size, Some(11)),
+            _ => assert_eq!(*size, None),
+        }
+    }
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
+
+#[test]
+fn read_repo_file_bytes_reads_large_files_into_a_presized_buffer() -> io::Result<()> {
+    let root = temp_dir("read_repo_file_bytes_presized");
+    fs::create_dir_all(&root)?;
+    let path = root.join("big.txt");
+    let content: Vec<u8> = (0..200_000u32).map(|i| b'a' + (i % 26) as u8).collect();
+    fs::write(&path, &content)?;
+
+    let repo_file = RepoFile { abs_path: path };
+    let options = ScanOptions::default();
+    let mut stats = ScanStats::default();
+    let out = read_repo_file_bytes(
+        &repo_file,
+        0,
+        None,
+        &mut ReadTargets::new(),
+        &options,
+        &mut stats,
+    )?
+    .unwrap();
+
+    assert_eq!(out, content);
+    assert_eq!(out.capacity(), content.len());
+    assert_eq!(stats.scanned_bytes, content.len() as u64);
+
+    Ok(())
+}
+
 fn fake_git_script_non_utf8(repo: &Path, marker: &Path) -> String {
     let repo = sh_single_quote(repo.to_string_lossy().as_ref());
     let marker = sh_single_quote(marker.to_string_lossy().as_ref());
@@ -613,6 +989,84 @@ fn sh_single_quote(s: &str) -> String {
     format!("'{escaped}'")
 }
 
+#[test]
+fn case_insensitive_path_keys_fold_only_when_requested() {
+    assert_eq!(path_key("Vendor/Lib.rs", true), "vendor/lib.rs");
+    assert_eq!(path_key("Vendor/Lib.rs", false), "Vendor/Lib.rs");
+
+    let ignore_dirs: HashSet<String> = ["node_modules", "Vendor"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert!(ignore_dirs_contains(&ignore_dirs, "Vendor", false));
+    assert!(!ignore_dirs_contains(&ignore_dirs, "vendor", false));
+    assert!(ignore_dirs_contains(&ignore_dirs, "vendor", true));
+    assert!(ignore_dirs_contains(&ignore_dirs, "Node_Modules", true));
+    assert!(!ignore_dirs_contains(&ignore_dirs, "src", true));
+}
+
+/// Runs on the case-insensitive CI platforms (Windows, macOS).
+#[cfg(any(windows, target_os = "macos"))]
+#[test]
+fn case_insensitive_ignore_rules_match_any_case() -> io::Result<()> {
+    let root = temp_dir("case_insensitive_ignore_rules");
+    fs::create_dir_all(root.join("Vendor"))?;
+    fs::create_dir_all(root.join("src"))?;
+    fs::write(root.join("Vendor").join("a.txt"), "same\n")?;
+    fs::write(root.join("src").join("b.GEN.txt"), "same\n")?;
+    fs::write(root.join("src").join("c.txt"), "same\n")?;
+    fs::write(root.join("src").join("d.txt"), "same\n")?;
+
+    let mut options = ScanOptions {
+        respect_gitignore: false,
+        ignore_patterns: vec!["*.gen.txt".to_string()],
+        ..ScanOptions::default()
+    };
+    options.ignore_dirs.insert("vendor".to_string());
+    let outcome = crate::find_duplicate_files_with_stats(std::slice::from_ref(&root), &options)?;
+    assert_eq!(outcome.stats.candidate_files, 2);
+    assert_eq!(outcome.result.len(), 1);
+    assert_eq!(outcome.result[0].files.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn aliased_roots_are_scanned_once() -> io::Result<()> {
+    let root = temp_dir("aliased_roots");
+    fs::create_dir_all(&root)?;
+    fs::write(root.join("a.txt"), "same\n")?;
+    fs::write(root.join("b.txt"), "same\n")?;
+
+    let mut roots = vec![root.clone(), root.join(".")];
+    #[cfg(unix)]
+    {
+        let link = temp_dir("aliased_roots_link");
+        std::os::unix::fs::symlink(&root, &link)?;
+        roots.push(link);
+    }
+
+    let options = ScanOptions::default();
+    let outcome = crate::find_duplicate_files_with_stats(&roots, &options)?;
+    assert_eq!(
+        outcome.stats.skipped_duplicate_roots,
+        roots.len() as u64 - 1
+    );
+    assert!(outcome.stats.has_fatal_skips());
+    assert_eq!(outcome.stats.per_repo.len(), 1);
+    assert_eq!(outcome.result.len(), 1);
+    assert_eq!(outcome.result[0].files.len(), 2);
+
+    let cross_repo = ScanOptions {
+        cross_repo_only: true,
+        ..ScanOptions::default()
+    };
+    let report = crate::generate_duplication_report_with_stats(&roots, &cross_repo)?;
+    assert_eq!(report.result.roots.len(), 1);
+    assert!(report.result.file_duplicates.is_empty());
+    assert!(report.result.code_span_duplicates.is_empty());
+    Ok(())
+}
+
 fn temp_dir(suffix: &str) -> PathBuf {
     let nanos = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -620,3 +1074,32 @@ fn temp_dir(suffix: &str) -> PathBuf {
         .as_nanos();
     std::env::temp_dir().join(format!("dup-code-check-core-{suffix}-{nanos}"))
 }
+
+#[test]
+fn corpus_fingerprint_changes_with_the_scanned_files_only() -> io::Result<()> {
+    let root = temp_dir("fingerprint");
+    fs::create_dir_all(&root)?;
+    fs::write(root.join("a.rs"), "fn a() {}\n")?;
+    fs::write(root.join("b.rs"), "fn b() {}\n")?;
+    let roots = [root.clone()];
+    let options = ScanOptions {
+        ignore_patterns: vec!["*.log".to_string()],
+        ..ScanOptions::default()
+    };
+
+    let first = crate::corpus_fingerprint(&roots, &options)?;
+    assert_eq!(crate::corpus_fingerprint(&roots, &options)?, first);
+
+    fs::write(root.join("debug.log"), "ignored\n")?;
+    assert_eq!(crate::corpus_fingerprint(&roots, &options)?, first);
+
+    fs::write(root.join("b.rs"), "fn b() { 1 }\n")?;
+    let edited = crate::corpus_fingerprint(&roots, &options)?;
+    assert_ne!(edited, first);
+
+    fs::rename(root.join("b.rs"), root.join("c.rs"))?;
+    assert_ne!(crate::corpus_fingerprint(&roots, &options)?, edited);
+
+    fs::remove_dir_all(&root)?;
+    Ok(())
+}
@@ -1,4 +1,6 @@
+use std::cmp::Reverse;
 use std::collections::HashSet;
+use std::fs;
 use std::io;
 use std::ops::ControlFlow;
 use std::path::{Component, Path, PathBuf};
@@ -7,9 +9,14 @@ use std::sync::atomic::{AtomicU64, Ordering};
 
 use ignore::WalkBuilder;
 
-use crate::types::{ScanOptions, ScanStats};
+use crate::progress::ScanPhase;
+use crate::skip_log::SkipReason;
+use crate::types::{BudgetOrder, FileListing, ScanOptions, ScanShard, ScanStats, sample_rank};
 
-use super::{Repo, RepoFile, ignore_dirs_contains, should_stop_due_to_max_files};
+use super::{
+    CASE_INSENSITIVE_PATHS, Repo, RepoFile, build_exclude_patterns, ignore_dirs_contains,
+    partition_path, path_key, should_stop_due_to_max_files,
+};
 
 pub(crate) fn visit_repo_files<F>(
     repo: &Repo,
@@ -20,7 +27,89 @@ pub(crate) fn visit_repo_files<F>(
 where
     F: FnMut(&mut ScanStats, RepoFile) -> io::Result<ControlFlow<()>>,
 {
-    fn normalize_relative_path(rel: &Path) -> PathBuf {
+    let keep = |stats: &mut ScanStats, file: &RepoFile| {
+        if let Some(shard) = options.shard
+            && !in_shard(shard, &repo.root, &file.abs_path)
+        {
+            stats.skipped_other_shard = stats.skipped_other_shard.saturating_add(1);
+            options.log_skip(&file.abs_path, SkipReason::OtherShard, None);
+            return false;
+        }
+        if let Some(cutoff) = repo.sample_cutoff
+            && !in_sample(cutoff, &repo.root, &file.abs_path)
+        {
+            stats.skipped_not_sampled = stats.skipped_not_sampled.saturating_add(1);
+            options.log_skip(&file.abs_path, SkipReason::NotSampled, None);
+            return false;
+        }
+        true
+    };
+
+    if options.budget_order == BudgetOrder::WalkOrder {
+        return list_repo_files(repo, options, stats, |stats, file| {
+            if !keep(stats, &file) {
+                return Ok(ControlFlow::Continue(()));
+            }
+            let flow = on_file_cb(stats, file);
+            options.emit_progress(ScanPhase::Scanning, stats);
+            flow
+        });
+    }
+
+    // List the whole root before reading anything, so the budgets keep the same files however
+    // the directory entries happen to be ordered.
+    let mut files = Vec::new();
+    let flow = list_repo_files(repo, options, stats, |stats, file| {
+        if keep(stats, &file) {
+            files.push(file);
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+    if flow.is_break() {
+        return Ok(flow);
+    }
+    sort_for_budget(&mut files, options.budget_order);
+    for file in files {
+        let flow = on_file_cb(stats, file)?;
+        options.emit_progress(ScanPhase::Scanning, stats);
+        if flow.is_break() || should_stop_due_to_max_files(options, stats) {
+            return Ok(ControlFlow::Break(()));
+        }
+    }
+    Ok(ControlFlow::Continue(()))
+}
+
+/// Order `files` for [`ScanOptions::budget_order`]; ties and unreadable sizes (sorted last) fall
+/// back to the path.
+fn sort_for_budget(files: &mut [RepoFile], order: BudgetOrder) {
+    let size = |file: &RepoFile| fs::metadata(&file.abs_path).ok().map(|m| m.len());
+    match order {
+        BudgetOrder::WalkOrder => {}
+        BudgetOrder::PathSorted => files.sort_by(|a, b| a.abs_path.cmp(&b.abs_path)),
+        BudgetOrder::SmallestFirst => files.sort_by_cached_key(|file| {
+            let len = size(file);
+            (len.is_none(), len, file.abs_path.clone())
+        }),
+        BudgetOrder::LargestFirst => files.sort_by_cached_key(|file| {
+            let len = size(file);
+            (len.is_none(), Reverse(len), file.abs_path.clone())
+        }),
+    }
+}
+
+/// The candidate files of `repo`, before sharding and sampling, in walk order.
+fn list_repo_files<F>(
+    repo: &Repo,
+    options: &ScanOptions,
+    stats: &mut ScanStats,
+    mut on_file_cb: F,
+) -> io::Result<ControlFlow<()>>
+where
+    F: FnMut(&mut ScanStats, RepoFile) -> io::Result<ControlFlow<()>>,
+{
+    /// `rel` without `.`/prefix components, case-folded where [`CASE_INSENSITIVE_PATHS`] holds
+    /// (Git may list a path in another case than the directory entry has).
+    fn relative_path_key(rel: &Path) -> PathBuf {
         let mut out = PathBuf::new();
         for component in rel.components() {
             let Component::Normal(name) = component else {
@@ -28,6 +117,9 @@ where
             };
             out.push(name);
         }
+        if CASE_INSENSITIVE_PATHS {
+            out = PathBuf::from(path_key(&out.to_string_lossy(), true).as_ref());
+        }
         out
     }
 
@@ -36,29 +128,50 @@ where
         return Ok(ControlFlow::Break(()));
     }
 
+    let excludes = build_exclude_patterns(&repo.root, options)?;
+
     // Only used when the Git fast path partially scans and then falls back to the walker.
     // Store relative paths (normalized) to avoid repeating the root prefix for every entry.
     let mut visited_via_git_rel: Vec<PathBuf> = Vec::new();
 
-    if options.respect_gitignore
-        && !options.follow_symlinks
+    let git_listing = match options.file_listing {
+        FileListing::Auto => options.respect_gitignore && !options.follow_symlinks,
+        FileListing::GitCli => true,
+        FileListing::Walker => false,
+    };
+    if git_listing
         && let Some(flow) = {
             let mut on_git_file = |stats: &mut ScanStats, file: RepoFile| {
                 if let Ok(rel) = file.abs_path.strip_prefix(&repo.root) {
-                    visited_via_git_rel.push(normalize_relative_path(rel));
+                    visited_via_git_rel.push(relative_path_key(rel));
                 }
                 on_file_cb(stats, file)
             };
-            super::git::try_visit_repo_files_via_git(repo, options, stats, &mut on_git_file)?
+            super::git::try_visit_repo_files_via_git(
+                repo,
+                options,
+                &excludes,
+                stats,
+                &mut on_git_file,
+            )?
         }
     {
+        stats.git_listed_repos = stats.git_listed_repos.saturating_add(1);
         return Ok(flow);
     }
+    if options.file_listing == FileListing::GitCli {
+        return Err(io::Error::other(format!(
+            "file_listing=git: {} is not a Git checkout or `git ls-files` failed",
+            repo.root.display()
+        )));
+    }
+    stats.walker_listed_repos = stats.walker_listed_repos.saturating_add(1);
 
     let visited_via_git_rel: Option<HashSet<PathBuf>> =
         (!visited_via_git_rel.is_empty()).then(|| visited_via_git_rel.into_iter().collect());
 
     let ignore_dirs = options.ignore_dirs.clone();
+    let walk_excludes = excludes.clone();
     let follow_symlinks = options.follow_symlinks;
     let respect_gitignore = options.respect_gitignore;
     let is_git_repo = repo.root.join(".git").exists();
@@ -76,6 +189,12 @@ where
     let skipped_not_found_cloned = Arc::clone(&skipped_not_found);
     let skipped_permission_denied_cloned = Arc::clone(&skipped_permission_denied);
     let skipped_walk_errors_cloned = Arc::clone(&skipped_walk_errors);
+    let filter_skip_log = options.skip_log.clone();
+    let log_filter_skip = move |path: &Path, reason| {
+        if let Some(skip_log) = &filter_skip_log {
+            skip_log.emit(path, reason, None);
+        }
+    };
 
     let mut builder = WalkBuilder::new(&repo.root);
     builder
@@ -83,7 +202,7 @@ where
         .follow_links(follow_symlinks)
         .ignore(false)
         .git_ignore(respect_gitignore)
-        .git_global(respect_gitignore && is_git_repo)
+        .git_global(respect_gitignore && (is_git_repo || options.use_global_gitignore))
         .git_exclude(respect_gitignore && is_git_repo)
         .parents(false)
         .require_git(false);
@@ -98,12 +217,15 @@ where
             }
 
             let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+            if walk_excludes.matched(entry.path(), is_dir).is_ignore() {
+                return false;
+            }
             if !is_dir {
                 return true;
             }
 
             if let Some(name) = entry.file_name().to_str()
-                && ignore_dirs_contains(&ignore_dirs, name)
+                && ignore_dirs_contains(&ignore_dirs, name, CASE_INSENSITIVE_PATHS)
             {
                 return false;
             }
@@ -116,21 +238,26 @@ where
                     Ok(resolved) => {
                         if !resolved.starts_with(canonical_root) {
                             skipped_outside_root_cloned.fetch_add(1, Ordering::Relaxed);
+                            log_filter_skip(entry.path(), SkipReason::OutsideRoot);
                             return false;
                         }
                     }
                     Err(err) => {
-                        match err.kind() {
+                        let reason = match err.kind() {
                             io::ErrorKind::NotFound => {
                                 skipped_not_found_cloned.fetch_add(1, Ordering::Relaxed);
+                                SkipReason::NotFound
                             }
                             io::ErrorKind::PermissionDenied => {
                                 skipped_permission_denied_cloned.fetch_add(1, Ordering::Relaxed);
+                                SkipReason::PermissionDenied
                             }
                             _ => {
                                 skipped_walk_errors_cloned.fetch_add(1, Ordering::Relaxed);
+                                SkipReason::WalkErrors
                             }
-                        }
+                        };
+                        log_filter_skip(entry.path(), reason);
                         return false;
                     }
                 }
@@ -159,21 +286,24 @@ where
         let entry = match result {
             Ok(e) => e,
             Err(err) => {
-                if let Some(io_err) = err.io_error() {
-                    match io_err.kind() {
-                        io::ErrorKind::NotFound => {
-                            stats.skipped_not_found = stats.skipped_not_found.saturating_add(1);
-                            continue;
-                        }
-                        io::ErrorKind::PermissionDenied => {
-                            stats.skipped_permission_denied =
-                                stats.skipped_permission_denied.saturating_add(1);
-                            continue;
-                        }
-                        _ => {}
+                let reason = match err.io_error().map(io::Error::kind) {
+                    Some(io::ErrorKind::NotFound) => {
+                        stats.skipped_not_found = stats.skipped_not_found.saturating_add(1);
+                        SkipReason::NotFound
+                    }
+                    Some(io::ErrorKind::PermissionDenied) => {
+                        stats.skipped_permission_denied =
+                            stats.skipped_permission_denied.saturating_add(1);
+                        SkipReason::PermissionDenied
+                    }
+                    _ => {
+                        stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
+                        SkipReason::WalkErrors
                     }
+                };
+                if let Some(path) = walk_error_path(&err) {
+                    options.log_skip(path, reason, None);
                 }
-                stats.skipped_walk_errors = stats.skipped_walk_errors.saturating_add(1);
                 continue;
             }
         };
@@ -191,7 +321,7 @@ where
         let abs_path = entry.into_path();
         if let Some(visited) = visited_via_git_rel.as_ref()
             && let Ok(rel) = abs_path.strip_prefix(&repo.root)
-            && visited.contains(rel)
+            && visited.contains(&relative_path_key(rel))
         {
             continue;
         }
@@ -217,3 +347,22 @@ where
 
     Ok(ControlFlow::Continue(()))
 }
+
+fn walk_error_path(err: &ignore::Error) -> Option<&Path> {
+    match err {
+        ignore::Error::WithPath { path, .. } => Some(path),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error_path(err)
+        }
+        _ => None,
+    }
+}
+
+// Files that cannot be relativized are kept; the reader counts them as relativize failures.
+fn in_shard(shard: ScanShard, root: &Path, abs_path: &Path) -> bool {
+    partition_path(root, abs_path).is_none_or(|rel| shard.contains(&rel))
+}
+
+fn in_sample(cutoff: u64, root: &Path, abs_path: &Path) -> bool {
+    partition_path(root, abs_path).is_none_or(|rel| sample_rank(&rel) < cutoff)
+}
@@ -0,0 +1,83 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Why a file was left out of a scan; each reason matches one `skipped_*` counter in
+/// [`crate::ScanStats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SkipReason {
+    NotFound,
+    PermissionDenied,
+    TooLarge,
+    Binary,
+    OutsideRoot,
+    RelativizeFailed,
+    WalkErrors,
+    BudgetMaxTotalBytes,
+    BudgetMaxNormalizedChars,
+    BudgetMaxTokens,
+    SlowFile,
+    OtherShard,
+    NotSampled,
+    SymlinkDuplicate,
+}
+
+impl SkipReason {
+    /// The `scanStats` key without its `skipped` prefix, e.g. `tooLarge`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SkipReason::NotFound => "notFound",
+            SkipReason::PermissionDenied => "permissionDenied",
+            SkipReason::TooLarge => "tooLarge",
+            SkipReason::Binary => "binary",
+            SkipReason::OutsideRoot => "outsideRoot",
+            SkipReason::RelativizeFailed => "relativizeFailed",
+            SkipReason::WalkErrors => "walkErrors",
+            SkipReason::BudgetMaxTotalBytes => "budgetMaxTotalBytes",
+            SkipReason::BudgetMaxNormalizedChars => "budgetMaxNormalizedChars",
+            SkipReason::BudgetMaxTokens => "budgetMaxTokens",
+            SkipReason::SlowFile => "slowFile",
+            SkipReason::OtherShard => "otherShard",
+            SkipReason::NotSampled => "notSampled",
+            SkipReason::SymlinkDuplicate => "symlinkDuplicates",
+        }
+    }
+}
+
+/// One skipped file passed to a [`SkipCallback`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SkippedFile<'a> {
+    /// The path as walked: the root joined with the file's relative path.
+    pub path: &'a Path,
+    pub reason: SkipReason,
+    /// File size in bytes, when the file was stat'ed before it was skipped.
+    pub size: Option<u64>,
+}
+
+/// Skip hook stored in [`crate::ScanOptions::skip_log`].
+///
+/// Called once per skipped file, at the same point its `skipped_*` counter is bumped, so the
+/// calls for a reason add up to that counter except where no path is known (a failed `git
+/// ls-files` stream, a walk error without a path) and for budgets that stop the walk
+/// (`maxFiles`), which end the scan rather than skip one file. May be called from the walker's
+/// filter, so keep it cheap.
+#[derive(Clone)]
+pub struct SkipCallback(Arc<dyn Fn(&SkippedFile<'_>) + Send + Sync>);
+
+impl SkipCallback {
+    pub fn new(callback: impl Fn(&SkippedFile<'_>) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(callback))
+    }
+
+    pub(crate) fn emit(&self, path: &Path, reason: SkipReason, size: Option<u64>) {
+        (self.0)(&SkippedFile { path, reason, size });
+    }
+}
+
+impl fmt::Debug for SkipCallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SkipCallback(..)")
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::language::{
+    CommentSyntax, DEFAULT_COMMENT_SYNTAX, HashComments, Language, detect_language,
+    language_by_name,
+};
+use crate::tokenize::{is_structural, tokenize_for_dup_detection};
+use crate::types::ScanOptions;
+use crate::util::{
+    normalize_for_code_spans, normalize_lines_for_dup_detection, normalize_unicode,
+    winnowed_fingerprints, winnowed_fingerprints_u8,
+};
+
+/// Comment boilerplate shipped with the tool: license headers and generated-code banners.
+/// Matching is on identifier characters, so comment markers and indentation do not matter.
+/// The token detector drops comments, so these only feed the code-span and line detectors.
+const BUILTIN_COMMENT_SKIPS: &[&str] = &[
+    // MIT
+    "Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the \"Software\"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED \"AS IS\", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.",
+    // Apache-2.0
+    "Licensed under the Apache License, Version 2.0 (the \"License\");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an \"AS IS\" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.",
+    // GPL-3.0-or-later
+    "This program is free software: you can redistribute it and/or modify
+it under the terms of the GNU General Public License as published by
+the Free Software Foundation, either version 3 of the License, or
+(at your option) any later version.
+
+This program is distributed in the hope that it will be useful,
+but WITHOUT ANY WARRANTY; without even the implied warranty of
+MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+GNU General Public License for more details.
+
+You should have received a copy of the GNU General Public License
+along with this program.  If not, see <https://www.gnu.org/licenses/>.",
+    // BSD
+    "Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS \"AS IS\"
+AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.",
+    // Generated-code banners
+    "Code generated by protoc-gen-go. DO NOT EDIT.",
+    "This file was automatically generated and should not be edited.",
+    "<auto-generated>
+This code was generated by a tool.
+Changes to this file may cause incorrect behavior and will be lost if
+the code is regenerated.
+</auto-generated>",
+];
+
+/// Code boilerplate shipped with the tool: serde's generated visitor impls and accessor pairs.
+/// Identifiers are normalized away by the token detector, so any field name matches there.
+const BUILTIN_CODE_SKIPS: &[&str] = &[
+    // serde's generated field visitor
+    "impl<'de> _serde::Deserialize<'de> for __Field {
+    #[inline]
+    fn deserialize<__D>(__deserializer: __D) -> _serde::__private::Result<Self, __D::Error>
+    where
+        __D: _serde::Deserializer<'de>,
+    {
+        _serde::Deserializer::deserialize_identifier(__deserializer, __FieldVisitor)
+    }
+}",
+    "fn expecting(&self, __formatter: &mut _serde::__private::Formatter) -> _serde::__private::fmt::Result {
+    _serde::__private::Formatter::write_str(__formatter, \"field identifier\")
+}
+
+fn visit_u64<__E>(self, __value: u64) -> _serde::__private::Result<Self::Value, __E>
+where
+    __E: _serde::de::Error,
+{",
+    // Accessor pairs
+    "public String getName() {
+    return name;
+}
+
+public void setName(String name) {
+    this.name = name;
+}",
+    "pub fn name(&self) -> &str {
+    &self.name
+}
+
+pub fn set_name(&mut self, name: String) {
+    self.name = name;
+}",
+    "get name(): string {
+    return this._name;
+}
+
+set name(value: string) {
+    this._name = value;
+}",
+];
+
+/// Stop sequences shared by the C-family languages.
+const C_FAMILY_STOPS: &[&str] = &[
+    "} else {",
+    "} else if (",
+    "return;",
+    "return null;",
+    "return this.name;",
+    "break;",
+    "continue;",
+];
+
+/// Idioms shipped with the tool, per language: a run of them (e.g. a class of getters, a
+/// chain of `} else {` arms) seeds no token-span match. Identifiers and literals are normalized
+/// away, so `return null;` also covers `return true;` and `return x;`.
+const BUILTIN_STOP_SEQUENCES: &[(&str, &[&str])] = &[
+    (
+        "c",
+        &[
+            "} else {",
+            "return;",
+            "return 0;",
+            "return -1;",
+            "break;",
+            "continue;",
+        ],
+    ),
+    (
+        "cpp",
+        &[
+            "} else {",
+            "return;",
+            "return 0;",
+            "return -1;",
+            "break;",
+            "continue;",
+        ],
+    ),
+    ("csharp", C_FAMILY_STOPS),
+    (
+        "go",
+        &[
+            "} else {",
+            "if err != nil { return err }",
+            "if err != nil { return nil, err }",
+            "return nil",
+            "break",
+            "continue",
+        ],
+    ),
+    (
+        "java",
+        &[
+            "} else {",
+            "} else if (",
+            "return;",
+            "return null;",
+            "return this.name;",
+            "public String getName() { return name; }",
+            "break;",
+            "continue;",
+        ],
+    ),
+    ("javascript", C_FAMILY_STOPS),
+    ("kotlin", &["} else {", "return null", "return this.name"]),
+    ("php", C_FAMILY_STOPS),
+    (
+        "python",
+        &[
+            "else:",
+            "return None",
+            "return self.name",
+            "break",
+            "continue",
+        ],
+    ),
+    ("ruby", &["else", "return nil", "break"]),
+    (
+        "rust",
+        &[
+            "} else {",
+            "Ok(())",
+            "return None;",
+            "None => None,",
+            "_ => {}",
+            "break;",
+            "continue;",
+        ],
+    ),
+    ("scala", &["} else {", "case _ =>"]),
+    ("swift", &["} else {", "return nil", "break", "continue"]),
+    ("typescript", C_FAMILY_STOPS),
+];
+
+/// Whether `code` tokenizes (as `language`) to something a stop sequence can be made of: at
+/// least one keyword or punctuation token.
+pub(crate) fn is_stop_sequence(code: &str, language: &Language) -> bool {
+    tokenize_for_dup_detection(code, language.comments)
+        .tokens
+        .into_iter()
+        .any(is_structural)
+}
+
+/// The token sequences of [`BUILTIN_STOP_SEQUENCES`] and [`ScanOptions::stop_sequences`], by
+/// language name.
+pub(crate) struct StopSequences {
+    by_language: HashMap<&'static str, Vec<Vec<u32>>>,
+}
+
+impl StopSequences {
+    pub(crate) fn new(options: &ScanOptions) -> Self {
+        let mut code: HashMap<&'static str, Vec<&str>> = BUILTIN_STOP_SEQUENCES
+            .iter()
+            .map(|&(name, sequences)| (name, sequences.to_vec()))
+            .collect();
+        let mut overridden = HashSet::new();
+        for (name, sequence) in &options.stop_sequences {
+            let Some(language) = language_by_name(name) else {
+                continue;
+            };
+            let sequences = code.entry(language.name).or_default();
+            if overridden.insert(language.name) {
+                sequences.clear();
+            }
+            if !sequence.trim().is_empty() {
+                sequences.push(sequence);
+            }
+        }
+        let by_language = code
+            .into_iter()
+            .filter_map(|(name, sequences)| {
+                let language = language_by_name(name)?;
+                let sequences: Vec<Vec<u32>> = sequences
+                    .into_iter()
+                    .map(|sequence| tokenize_for_dup_detection(sequence, language.comments).tokens)
+                    .filter(|tokens| tokens.iter().copied().any(is_structural))
+                    .collect();
+                (!sequences.is_empty()).then_some((language.name, sequences))
+            })
+            .collect();
+        Self { by_language }
+    }
+
+    /// For each token of the file at `path`, how many tokens from it on belong to occurrences
+    /// of its language's stop sequences (`0` outside them); empty when the language has none.
+    pub(crate) fn covered_runs(&self, path: &str, tokens: &[u32]) -> Vec<u32> {
+        let Some(sequences) =
+            detect_language(path).and_then(|language| self.by_language.get(language.name))
+        else {
+            return Vec::new();
+        };
+        let mut covered = vec![false; tokens.len()];
+        for start in 0..tokens.len() {
+            for sequence in sequences {
+                if tokens[start..].starts_with(sequence) {
+                    covered[start..start + sequence.len()].fill(true);
+                }
+            }
+        }
+        let mut runs = vec![0u32; tokens.len()];
+        let mut run = 0u32;
+        for (pos, &covered) in covered.iter().enumerate().rev() {
+            run = if covered { run + 1 } else { 0 };
+            runs[pos] = run;
+        }
+        runs
+    }
+}
+
+/// The snippets whose fingerprints the span detectors skip: the built-in list (unless
+/// [`ScanOptions::builtin_skips`] is off) plus [`ScanOptions::skip_snippets`], normalized like
+/// scanned files.
+pub(crate) struct SkipList {
+    /// Snippets for every detector.
+    code: Vec<Vec<u8>>,
+    /// Snippets for the detectors that see comments.
+    comments: Vec<Vec<u8>>,
+}
+
+impl SkipList {
+    pub(crate) fn new(options: &ScanOptions) -> Self {
+        let normalize = |snippet: &str| {
+            normalize_unicode(snippet.as_bytes(), options.unicode_normalization).into_owned()
+        };
+        let builtin = |snippets: &'static [&'static str]| {
+            let snippets = if options.builtin_skips { snippets } else { &[] };
+            snippets.iter().map(|snippet| normalize(snippet))
+        };
+        Self {
+            code: builtin(BUILTIN_CODE_SKIPS)
+                .chain(
+                    options
+                        .skip_snippets
+                        .iter()
+                        .map(|snippet| normalize(snippet)),
+                )
+                .collect(),
+            comments: builtin(BUILTIN_COMMENT_SKIPS).collect(),
+        }
+    }
+
+    fn with_comments(&self) -> impl Iterator<Item = &[u8]> {
+        self.code.iter().chain(&self.comments).map(Vec::as_slice)
+    }
+
+    /// Every `k`-char fingerprint of the snippets, as the code-span detector hashes them.
+    pub(crate) fn code_span_fingerprints(&self, k: usize) -> HashSet<u64> {
+        self.with_comments()
+            .flat_map(|snippet| {
+                winnowed_fingerprints_u8(&normalize_for_code_spans(snippet).chars, k, 1)
+            })
+            .map(|(hash, _)| hash)
+            .collect()
+    }
+
+    /// Every `k`-line fingerprint of the snippets, as the line-span detector hashes them.
+    pub(crate) fn line_fingerprints(&self, k: usize) -> HashSet<u64> {
+        self.with_comments()
+            .flat_map(|snippet| {
+                winnowed_fingerprints(
+                    &normalize_lines_for_dup_detection(snippet).line_tokens,
+                    k,
+                    1,
+                )
+            })
+            .map(|(hash, _)| hash)
+            .collect()
+    }
+
+    /// Every `k`-token fingerprint of the snippets, as the token-span detector hashes them.
+    ///
+    /// Snippets have no language, so they are tokenized both with `#` lines dropped (C-family
+    /// directives) and kept (Rust/PHP attributes).
+    pub(crate) fn token_fingerprints(&self, k: usize) -> HashSet<u64> {
+        let syntaxes = [
+            DEFAULT_COMMENT_SYNTAX,
+            CommentSyntax {
+                hash: HashComments::Code,
+                ..DEFAULT_COMMENT_SYNTAX
+            },
+        ];
+        self.code
+            .iter()
+            .flat_map(|snippet| {
+                let text = String::from_utf8_lossy(snippet).into_owned();
+                syntaxes.into_iter().flat_map(move |syntax| {
+                    winnowed_fingerprints(&tokenize_for_dup_detection(&text, syntax).tokens, k, 1)
+                })
+            })
+            .map(|(hash, _)| hash)
+            .collect()
+    }
+}
@@ -0,0 +1,207 @@
+use crate::language::detect_language;
+use crate::util::split_text_lines;
+
+/// Keywords that introduce a named declaration; the declared name is the next identifier.
+const DECLARATION_KEYWORDS: &[&str] = &[
+    "class",
+    "const",
+    "def",
+    "enum",
+    "fn",
+    "fun",
+    "func",
+    "function",
+    "interface",
+    "let",
+    "mod",
+    "module",
+    "namespace",
+    "object",
+    "protocol",
+    "record",
+    "struct",
+    "trait",
+    "type",
+    "union",
+    "val",
+    "var",
+];
+
+/// Words between a declaration keyword and the name (`def self.name`, `static mut NAME`).
+const SKIPPED_WORDS: &[&str] = &["mut", "self"];
+
+/// Words that never name a symbol when a visibility marker is followed by a plain member
+/// declaration (`public static int count;`).
+const NON_NAMES: &[&str] = &[
+    "abstract", "async", "default", "extern", "final", "import", "mut", "override", "partial",
+    "readonly", "sealed", "static", "unsafe", "use", "virtual", "void",
+];
+
+/// Which lines of a language declare public symbols.
+#[derive(Debug, Clone, Copy)]
+enum Exports {
+    /// Lines starting with a visibility keyword (`pub`, `export`, `public`).
+    Marker(&'static str),
+    /// Like `Marker`, plus top-level declarations without any visibility keyword.
+    MarkerOrTopLevel(&'static str),
+    /// Top-level declarations whose name does not start with `_` (Python, Ruby).
+    TopLevel,
+    /// Top-level declarations whose name starts with an uppercase letter (Go).
+    Capitalized,
+}
+
+fn exports_for_path(path: &str) -> Option<Exports> {
+    let exports = match detect_language(path)?.name {
+        "rust" => Exports::Marker("pub"),
+        "javascript" | "typescript" => Exports::Marker("export"),
+        "java" | "csharp" => Exports::Marker("public"),
+        "kotlin" | "php" | "scala" | "swift" => Exports::MarkerOrTopLevel("public"),
+        "python" | "ruby" => Exports::TopLevel,
+        "go" => Exports::Capitalized,
+        _ => return None,
+    };
+    Some(exports)
+}
+
+/// Names of the public functions, types and constants declared in the file at `path`, sorted
+/// and deduplicated.
+///
+/// A line-based heuristic: a declaration counts when its line starts with the language's
+/// visibility keyword (`pub fn`, `export const`, `public class`) or, for languages without
+/// one, when it sits at the top level (Python, Ruby, Go). Files of other languages (C, C++,
+/// unknown extensions) declare nothing.
+pub(crate) fn public_symbols(text: &str, path: &str) -> Vec<String> {
+    let Some(exports) = exports_for_path(path) else {
+        return Vec::new();
+    };
+    let mut symbols: Vec<String> = split_text_lines(text)
+        .filter_map(|line| declared_public_symbol(line, exports))
+        .map(str::to_string)
+        .collect();
+    symbols.sort_unstable();
+    symbols.dedup();
+    symbols
+}
+
+fn declared_public_symbol(line: &str, exports: Exports) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let top_level = trimmed.len() == line.len();
+    let marker = match exports {
+        Exports::Marker(marker) | Exports::MarkerOrTopLevel(marker) => Some(marker),
+        Exports::TopLevel | Exports::Capitalized => None,
+    };
+    if let Some(rest) = marker.and_then(|marker| strip_word(trimmed, marker)) {
+        return declared_name(rest, true);
+    }
+    if !top_level {
+        return None;
+    }
+    match exports {
+        Exports::Marker(_) => None,
+        Exports::MarkerOrTopLevel(_) => {
+            let first = words(trimmed).next()?;
+            let hidden = ["private", "protected", "internal", "fileprivate"].contains(&first);
+            if hidden {
+                return None;
+            }
+            declared_name(trimmed, false)
+        }
+        Exports::TopLevel => declared_name(trimmed, false).filter(|name| !name.starts_with('_')),
+        Exports::Capitalized => declared_name(trimmed, false)
+            .filter(|name| name.chars().next().is_some_and(char::is_uppercase)),
+    }
+}
+
+/// `line` without its leading `word`, if `word` is followed by whitespace (`pub(crate)` does
+/// not count as `pub`).
+fn strip_word<'a>(line: &'a str, word: &str) -> Option<&'a str> {
+    let rest = line.strip_prefix(word)?;
+    rest.starts_with(char::is_whitespace).then_some(rest)
+}
+
+/// The name a declaration introduces: the identifier after its declaration keyword, or with
+/// `member_fallback`, the last identifier before `(`, `=`, `:`, `;` or `{` (`static int
+/// count;`, `void run(`).
+fn declared_name(declaration: &str, member_fallback: bool) -> Option<&str> {
+    let head = declaration
+        .split(['(', '=', ':', ';', '{'])
+        .next()
+        .unwrap_or_default();
+    let head_words: Vec<&str> = words(head).collect();
+    if matches!(head_words.as_slice(), [.., "func"]) {
+        // Go method: `func (r *Repo) Name(`.
+        let (_, after_receiver) = declaration.split_once(')')?;
+        return declared_name(after_receiver, false).or_else(|| words(after_receiver).next());
+    }
+    let mut iter = head_words.iter().copied().peekable();
+    while let Some(word) = iter.next() {
+        if !DECLARATION_KEYWORDS.contains(&word) {
+            continue;
+        }
+        while let Some(&next) = iter.peek() {
+            if DECLARATION_KEYWORDS.contains(&next) || SKIPPED_WORDS.contains(&next) {
+                iter.next();
+            } else {
+                return Some(next);
+            }
+        }
+    }
+    let is_import = matches!(head_words.first(), Some(&("use" | "import")));
+    if !member_fallback || is_import || head.len() == declaration.len() {
+        return None;
+    }
+    head_words
+        .last()
+        .copied()
+        .filter(|word| !NON_NAMES.contains(word) && !DECLARATION_KEYWORDS.contains(word))
+}
+
+/// Identifiers in `text` (runs of alphanumerics, `_` and `$` not starting with a digit).
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .filter(|word| word.chars().next().is_some_and(|c| !c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_public_declarations_per_language() {
+        let rust = "pub fn parse() {}\npub(crate) fn helper() {}\nfn private() {}\n\
+                    impl A {\n    pub async fn run(&self) {}\n}\npub const fn len() {}\n\
+                    pub static mut COUNT: u32 = 0;\npub struct Config {\npub use a::b;\n";
+        assert_eq!(
+            public_symbols(rust, "src/lib.rs"),
+            ["COUNT", "Config", "len", "parse", "run"]
+        );
+
+        let ts = "export function load(a) {}\nexport const MAX = 1;\nfunction inner() {}\n\
+                  export default class Store {}\nexport { a, b };\nexport type { T };\n";
+        assert_eq!(public_symbols(ts, "store.ts"), ["MAX", "Store", "load"]);
+
+        let python = "def load():\n    def inner():\n        pass\ndef _private():\n\
+                      class Store:\n    def get(self):\n";
+        assert_eq!(public_symbols(python, "store.py"), ["Store", "load"]);
+
+        let go = "func Load() {}\nfunc (s *Store) Get(k string) {}\nfunc inner() {}\n\
+                  type Store struct {}\nvar maxItems = 1\n";
+        assert_eq!(public_symbols(go, "store.go"), ["Get", "Load", "Store"]);
+
+        let java = "public class Store {\n    public static final int MAX = 1;\n\
+                    public static <T> void put(T value) {\n    private int count;\n\
+                    public Store() {}\n";
+        assert_eq!(public_symbols(java, "Store.java"), ["MAX", "Store", "put"]);
+
+        let kotlin = "fun load() {}\nprivate fun hidden() {}\nclass Store {\n";
+        assert_eq!(public_symbols(kotlin, "Store.kt"), ["Store", "load"]);
+
+        assert!(public_symbols("int load(void) { return 0; }\n", "load.c").is_empty());
+    }
+
+    #[test]
+    fn lone_carriage_returns_end_lines() {
+        let python = "class Store:\r    def get(self):\r        pass\rdef load():\r";
+        assert_eq!(public_symbols(python, "store.py"), ["Store", "load"]);
+    }
+}
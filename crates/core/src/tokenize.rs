@@ -1,3 +1,15 @@
+//! Normalized token streams and the brace block tree the block detectors work on.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::language::{
+    CommentSyntax, HashComments, comment_syntax_for_path, min_token_len_for_path,
+};
+use crate::types::ScanOptions;
+use crate::util::{FileDeadline, is_line_end, normalize_unicode};
+
 #[derive(Debug, Clone)]
 pub(crate) struct BlockNode {
     pub(crate) start_token: usize,
@@ -14,49 +26,123 @@ pub(crate) struct TokenizedText {
     pub(crate) token_lines: Vec<u32>,
 }
 
-pub(crate) fn tokenize_for_dup_detection(text: &str) -> TokenizedText {
-    const TOK_IDENT: u32 = 1;
-    const TOK_NUM: u32 = 2;
-    const TOK_STR: u32 = 3;
-    const TOK_PUNCT_BASE: u32 = 10_000;
-
-    fn keyword_token(ident: &str) -> Option<u32> {
-        Some(match ident {
-            "if" => 100,
-            "else" => 101,
-            "for" => 102,
-            "while" => 103,
-            "do" => 104,
-            "switch" => 105,
-            "case" => 106,
-            "break" => 107,
-            "continue" => 108,
-            "return" => 109,
-            "try" => 110,
-            "catch" => 111,
-            "finally" => 112,
-            "throw" => 113,
-            "fn" => 114,
-            "function" => 115,
-            "class" => 116,
-            "struct" => 117,
-            "enum" => 118,
-            "impl" => 119,
-            "trait" => 120,
-            "const" => 121,
-            "let" => 122,
-            "var" => 123,
-            "static" => 124,
-            "public" => 125,
-            "private" => 126,
-            "protected" => 127,
-            "async" => 128,
-            "await" => 129,
-            _ => return None,
-        })
+const TOK_IDENT: u32 = 1;
+const TOK_NUM: u32 = 2;
+const TOK_STR: u32 = 3;
+const TOK_KEYWORD_BASE: u32 = 100;
+const TOK_PUNCT_BASE: u32 = 10_000;
+
+/// Keywords get their own token (`TOK_KEYWORD_BASE + index`); other identifiers collapse to
+/// `TOK_IDENT`.
+const KEYWORDS: &[&str] = &[
+    "if",
+    "else",
+    "for",
+    "while",
+    "do",
+    "switch",
+    "case",
+    "break",
+    "continue",
+    "return",
+    "try",
+    "catch",
+    "finally",
+    "throw",
+    "fn",
+    "function",
+    "class",
+    "struct",
+    "enum",
+    "impl",
+    "trait",
+    "const",
+    "let",
+    "var",
+    "static",
+    "public",
+    "private",
+    "protected",
+    "async",
+    "await",
+];
+
+fn keyword_token(ident: &str) -> Option<u32> {
+    let index = KEYWORDS.iter().position(|keyword| *keyword == ident)?;
+    Some(TOK_KEYWORD_BASE + index as u32)
+}
+
+/// A readable name for a token produced by [`tokenize_for_dup_detection`]: `<ident>`, `<num>`,
+/// `<str>`, the keyword, or the punctuation byte.
+pub(crate) fn token_label(token: u32) -> String {
+    match token {
+        TOK_IDENT => "<ident>".to_string(),
+        TOK_NUM => "<num>".to_string(),
+        TOK_STR => "<str>".to_string(),
+        _ => {
+            if let Some(keyword) = token
+                .checked_sub(TOK_KEYWORD_BASE)
+                .and_then(|index| KEYWORDS.get(index as usize))
+            {
+                return (*keyword).to_string();
+            }
+            match token
+                .checked_sub(TOK_PUNCT_BASE)
+                .and_then(|byte| u8::try_from(byte).ok())
+            {
+                Some(byte) if byte.is_ascii_graphic() => char::from(byte).to_string(),
+                Some(byte) => format!("<0x{byte:02x}>"),
+                None => format!("<{token}>"),
+            }
+        }
     }
+}
+
+/// How many tokenizer steps run between [`FileDeadline`] checks.
+const DEADLINE_CHECK_INTERVAL: usize = 4096;
 
+/// Tokenize `text`, dropping whitespace, the `comments` of its language and a leading `#!`
+/// shebang line.
+pub(crate) fn tokenize_for_dup_detection(text: &str, comments: CommentSyntax) -> TokenizedText {
+    tokenize_for_dup_detection_within(text, comments, FileDeadline::NONE)
+        .expect("an unbounded deadline never expires")
+}
+
+/// [`tokenize_for_dup_detection`], giving up (`None`) once `deadline` expires.
+pub(crate) fn tokenize_for_dup_detection_within(
+    text: &str,
+    comments: CommentSyntax,
+    deadline: FileDeadline,
+) -> Option<TokenizedText> {
+    tokenize(text, comments, deadline, |_| {})
+}
+
+/// The identifier names (keywords excluded) that [`tokenize_for_dup_detection`] collapses to
+/// `<ident>`, in order.
+pub(crate) fn identifier_names(text: &str, comments: CommentSyntax) -> Vec<&str> {
+    let mut names = Vec::new();
+    tokenize(text, comments, FileDeadline::NONE, |name| names.push(name));
+    names
+}
+
+/// Whether `token` is a punctuation byte rather than an identifier, keyword or literal.
+pub(crate) fn is_punctuation(token: u32) -> bool {
+    token >= TOK_PUNCT_BASE
+}
+
+/// Whether `token` is a keyword or punctuation rather than a normalized identifier or literal.
+pub(crate) fn is_structural(token: u32) -> bool {
+    token >= TOK_KEYWORD_BASE
+}
+
+fn tokenize<'a>(
+    text: &'a str,
+    comments: CommentSyntax,
+    deadline: FileDeadline,
+    mut on_identifier: impl FnMut(&'a str),
+) -> Option<TokenizedText> {
     let bytes = text.as_bytes();
+    let mut steps = 0usize;
     let mut i = 0usize;
     let mut line: u32 = 1;
     let mut at_line_start = true;
@@ -64,9 +150,20 @@ pub(crate) fn tokenize_for_dup_detection(text: &str) -> TokenizedText {
     let mut tokens = Vec::new();
     let mut token_lines = Vec::new();
 
+    // `#![...]` is a Rust inner attribute, not a shebang.
+    if bytes.starts_with(b"#!") && bytes.get(2) != Some(&b'[') {
+        while i < bytes.len() && !is_line_end(bytes, i) {
+            i += 1;
+        }
+    }
+
     while i < bytes.len() {
+        steps += 1;
+        if steps.is_multiple_of(DEADLINE_CHECK_INTERVAL) && deadline.expired() {
+            return None;
+        }
         let b = bytes[i];
-        if b == b'\n' {
+        if is_line_end(bytes, i) {
             line = line.saturating_add(1);
             i += 1;
             at_line_start = true;
@@ -80,31 +177,46 @@ pub(crate) fn tokenize_for_dup_detection(text: &str) -> TokenizedText {
         let was_at_line_start = at_line_start;
         at_line_start = false;
 
-        if b == b'/' && i + 1 < bytes.len() && bytes[i + 1] == b'/' {
+        if comments.slash_line && b == b'/' && i + 1 < bytes.len() && bytes[i + 1] == b'/' {
             i += 2;
-            while i < bytes.len() && bytes[i] != b'\n' {
+            while i < bytes.len() && !is_line_end(bytes, i) {
                 i += 1;
             }
             continue;
         }
-        if b == b'/' && i + 1 < bytes.len() && bytes[i + 1] == b'*' {
+        if comments.block && b == b'/' && i + 1 < bytes.len() && bytes[i + 1] == b'*' {
             i += 2;
+            let mut depth = 1usize;
             while i + 1 < bytes.len() {
-                if bytes[i] == b'\n' {
+                if is_line_end(bytes, i) {
                     line = line.saturating_add(1);
                     at_line_start = true;
                 }
                 if bytes[i] == b'*' && bytes[i + 1] == b'/' {
                     i += 2;
-                    break;
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                    continue;
+                }
+                if comments.nested_blocks && bytes[i] == b'/' && bytes[i + 1] == b'*' {
+                    i += 2;
+                    depth += 1;
+                    continue;
                 }
                 i += 1;
             }
             continue;
         }
-        if b == b'#' && was_at_line_start {
+        let hash_comment = match comments.hash {
+            HashComments::Code => false,
+            HashComments::LineStart => was_at_line_start,
+            HashComments::Anywhere => true,
+        };
+        if b == b'#' && hash_comment {
             i += 1;
-            while i < bytes.len() && bytes[i] != b'\n' {
+            while i < bytes.len() && !is_line_end(bytes, i) {
                 i += 1;
             }
             continue;
@@ -116,10 +228,14 @@ pub(crate) fn tokenize_for_dup_detection(text: &str) -> TokenizedText {
             i += 1;
             while i < bytes.len() {
                 let c = bytes[i];
-                if c == b'\n' {
+                if is_line_end(bytes, i) {
                     line = line.saturating_add(1);
                 }
                 if c == b'\\' && i + 1 < bytes.len() {
+                    // An escaped line break (line continuation) still ends a line.
+                    if is_line_end(bytes, i + 1) {
+                        line = line.saturating_add(1);
+                    }
                     i += 2;
                     continue;
                 }
@@ -146,7 +262,10 @@ pub(crate) fn tokenize_for_dup_detection(text: &str) -> TokenizedText {
                 }
             }
             let ident = &text[start..i];
-            let tok = keyword_token(ident).unwrap_or(TOK_IDENT);
+            let tok = keyword_token(ident).unwrap_or_else(|| {
+                on_identifier(ident);
+                TOK_IDENT
+            });
             tokens.push(tok);
             token_lines.push(line);
             continue;
@@ -167,14 +286,13 @@ pub(crate) fn tokenize_for_dup_detection(text: &str) -> TokenizedText {
         i += 1;
     }
 
-    TokenizedText {
+    Some(TokenizedText {
         tokens,
         token_lines,
-    }
+    })
 }
 
 pub(crate) fn parse_brace_blocks(tokens: &[u32], token_lines: &[u32]) -> Vec<BlockNode> {
-    const TOK_PUNCT_BASE: u32 = 10_000;
     let open = TOK_PUNCT_BASE + u32::from(b'{');
     let close = TOK_PUNCT_BASE + u32::from(b'}');
 
@@ -211,3 +329,135 @@ pub(crate) fn parse_brace_blocks(tokens: &[u32], token_lines: &[u32]) -> Vec<Blo
 
     nodes
 }
+
+/// The brace block tree of one file, as produced by [`blocks_for_file`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileBlocks {
+    /// `min_token_len` for this file after the per-language scale; shorter blocks are not
+    /// reported.
+    pub min_token_len: usize,
+    /// Whether `max_tokens_per_file` cut the token stream short; blocks past the cap are
+    /// missing and blocks open at the cap are unclosed.
+    pub token_cap_hit: bool,
+    /// Blocks in order of their opening brace.
+    pub blocks: Vec<FileBlock>,
+}
+
+/// One `{ ... }` block and the lengths the block detectors compare to `min_token_len`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FileBlock {
+    /// Lines of the opening and closing brace; equal for an unclosed block.
+    pub start_line: u32,
+    pub end_line: u32,
+    /// `1` for top-level blocks.
+    pub depth: u32,
+    /// Indices of the directly nested blocks in [`FileBlocks::blocks`].
+    pub children: Vec<usize>,
+    /// Tokens between the braces, compared by `blockDuplicates`; `0` for unclosed or empty
+    /// blocks, which neither detector considers.
+    pub token_len: usize,
+    /// Length of the subtree signature compared by `astSubtreeDuplicates`: the block's own
+    /// tokens, with each nested block counting as two.
+    pub subtree_len: usize,
+}
+
+/// Tokenize `path` and parse its brace blocks the way the report scan does, to see why the
+/// block and AST-subtree detectors did or didn't consider a region.
+pub fn blocks_for_file(path: &Path, options: &ScanOptions) -> io::Result<FileBlocks> {
+    let bytes = fs::read(path)?;
+    let text_bytes = normalize_unicode(&bytes, options.unicode_normalization);
+    let text = String::from_utf8_lossy(&text_bytes);
+    let path_str = path.to_string_lossy().replace('\\', "/");
+    let mut tokenized = tokenize_for_dup_detection(&text, comment_syntax_for_path(&path_str));
+    let token_cap_hit = options
+        .max_tokens_per_file
+        .is_some_and(|cap| tokenized.tokens.len() > cap);
+    if let Some(cap) = options.max_tokens_per_file {
+        tokenized.tokens.truncate(cap);
+        tokenized.token_lines.truncate(cap);
+    }
+
+    let nodes = parse_brace_blocks(&tokenized.tokens, &tokenized.token_lines);
+    let blocks = nodes
+        .iter()
+        .map(|node| {
+            let token_len = node.end_token.saturating_sub(node.start_token + 1);
+            // Each nested block, braces included, is replaced by a marker and its hash.
+            let subtree_len = if token_len == 0 {
+                0
+            } else {
+                node.children.iter().fold(token_len, |len, &child| {
+                    let child = &nodes[child];
+                    len + 2 - (child.end_token - child.start_token + 1)
+                })
+            };
+            FileBlock {
+                start_line: node.start_line,
+                end_line: node.end_line,
+                depth: node.depth,
+                children: node.children.clone(),
+                token_len,
+                subtree_len,
+            }
+        })
+        .collect();
+
+    Ok(FileBlocks {
+        min_token_len: min_token_len_for_path(options, &path_str),
+        token_cap_hit,
+        blocks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_report_lines_nesting_and_detector_lengths() {
+        let dir = std::env::temp_dir().join(format!("dcc-blocks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.rs");
+        fs::write(
+            &path,
+            "fn a() {\n    if x {\n        y();\n    }\n}\nfn b() {}\nfn c() {\n",
+        )
+        .unwrap();
+
+        let options = ScanOptions {
+            min_token_len: 5,
+            ..ScanOptions::default()
+        };
+        let tree = blocks_for_file(&path, &options).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(tree.min_token_len, 5);
+        assert!(!tree.token_cap_hit);
+        let found: Vec<(u32, u32, u32, usize, usize)> = tree
+            .blocks
+            .iter()
+            .map(|b| {
+                (
+                    b.start_line,
+                    b.end_line,
+                    b.depth,
+                    b.token_len,
+                    b.subtree_len,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, 5, 1, 8, 4),
+                (2, 4, 2, 4, 4),
+                (6, 6, 1, 0, 0),
+                (7, 7, 1, 0, 0)
+            ]
+        );
+        assert_eq!(tree.blocks[0].children, [1]);
+        assert!(tree.blocks[1..].iter().all(|b| b.children.is_empty()));
+    }
+}
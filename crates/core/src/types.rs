@@ -1,6 +1,16 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
+
+use crate::language::language_by_name;
+use crate::progress::{ProgressCallback, ScanPhase};
+use crate::scan::{build_exclude_patterns, build_ignore_patterns};
+use crate::skip_log::{SkipCallback, SkipReason};
+use crate::skips::is_stop_sequence;
+use crate::util::fnv1a64;
 
 /// Scan configuration shared by the CLI and the core APIs.
 ///
@@ -10,19 +20,504 @@ use std::sync::Arc;
 #[non_exhaustive]
 pub struct ScanOptions {
     pub ignore_dirs: HashSet<String>,
+    /// Gitignore-syntax patterns, relative to each root, for files and directories to skip
+    /// (e.g. `*.min.js`, `generated/**`, `!keep.rs`). Applied on top of `.gitignore` rules.
+    pub ignore_patterns: Vec<String>,
+    /// Gitignore-syntax patterns for lockfiles and well-known generated files, skipped like
+    /// [`Self::ignore_patterns`] and matched before them, so `!Cargo.lock` there re-includes one.
+    /// Defaults to [`default_exclude_patterns`]; clear it to scan them.
+    pub default_excludes: Vec<String>,
     pub max_file_size: Option<u64>,
     pub max_files: Option<usize>,
     pub max_total_bytes: Option<u64>,
+    /// Which files of each root the budgets ([`Self::max_files`], [`Self::max_total_bytes`] and
+    /// the report-mode char/token budgets) keep. Anything but [`BudgetOrder::WalkOrder`] lists
+    /// the whole root before reading it, so a budgeted scan picks the same files on every run.
+    pub budget_order: BudgetOrder,
     pub max_normalized_chars: Option<usize>,
     pub max_tokens: Option<usize>,
     pub min_match_len: usize,
     pub min_token_len: usize,
+    /// Widen the winnowing window of long files so their fingerprint count grows more slowly:
+    /// past 16 Ki normalized units (chars for code spans, tokens, lines), every doubling of a
+    /// file's length adds one base window, up to this many times the base window (`>= 2`).
+    /// Matches this many times longer than the ones a fixed window always finds (e.g.
+    /// `growth × min_match_len` chars) are still always found; shorter ones may be missed
+    /// between long files. `None` (the default) keeps the window fixed.
+    pub adaptive_window: Option<usize>,
     pub similarity_threshold: f64,
     pub simhash_max_distance: u32,
+    /// Report mode: tokens per shingle in the MinHash and SimHash detectors. Smaller values
+    /// favor recall on short functions, larger ones precision on long procedures.
+    pub shingle_size: usize,
+    /// Report mode: MinHash signature length; pair scores are multiples of its inverse.
+    pub minhash_signature_size: usize,
+    /// Report mode: LSH bands the MinHash signature is split into; must divide
+    /// [`Self::minhash_signature_size`]. More (shorter) bands surface more candidate pairs.
+    pub minhash_bands: usize,
+    /// Report mode: compare fixed token windows instead of shallow brace blocks in the MinHash
+    /// and SimHash detectors; `None` (the default) uses blocks.
+    pub similarity_windows: Option<SimilarityWindows>,
+    /// Report mode: fewest lines a `lineSpanDuplicates` match may span. Raise it to drop noisy
+    /// two-line matches without touching [`Self::min_match_len`].
+    pub line_span_min_lines: usize,
+    /// Report mode: winnowing window of the line-span detector, in lines. Matches of at least
+    /// `window + 1` lines are always found; smaller windows keep more fingerprints.
+    pub line_span_window: usize,
+    /// Report mode: fewest public symbols two files must both declare before
+    /// `symbolDuplicates` reports their identical API.
+    pub symbol_min_count: usize,
+    /// Report mode: fewest imports a file needs before `importDuplicates` compares it.
+    pub import_min_count: usize,
+    /// Report mode: minimum Jaccard similarity (`0..=1`) of two files' import sets for
+    /// `importDuplicates`.
+    pub import_similarity_threshold: f64,
+    /// Report mode: smallest data file (JSON, YAML, CSV, XML, snapshots), in bytes, that
+    /// `dataDuplicates` compares. Data files never enter the code detectors, so smaller ones
+    /// are not reported at all.
+    pub data_min_bytes: u64,
+    /// Report mode: drop span groups whose [`GroupQuality::token_entropy`] is below this many
+    /// bits per token, such as runs of closing braces or delimiter boilerplate (`0` keeps all).
+    pub min_entropy: f64,
     pub max_report_items: usize,
+    /// Report mode: per-section overrides of [`Self::max_report_items`], e.g. thousands of file
+    /// duplicates but only the top 50 similarity pairs. Unlisted sections use
+    /// `max_report_items`.
+    pub max_items_per_detector: HashMap<ReportSection, usize>,
+    /// Code spans and report modes: safety cap on the groups each winnowing span detector (code,
+    /// line and token spans; not blocks, AST subtrees or similarity) holds in memory, against
+    /// pathological corpora such as a huge vendored tree scanned twice. Past it the detector
+    /// only counts new groups, in [`ScanStats::skipped_group_cap`] (an upper bound once more
+    /// groups are counted than kept), and flags its section [`SectionTotal::degraded`]. `None`
+    /// lifts the cap.
+    pub max_groups_per_detector: Option<usize>,
     pub respect_gitignore: bool,
+    /// With [`Self::respect_gitignore`], also apply the user's global Git ignores
+    /// (`core.excludesFile`, else `$XDG_CONFIG_HOME/git/ignore`) to roots that are not Git
+    /// checkouts, such as an exported tarball. Inside a checkout they always apply.
+    pub use_global_gitignore: bool,
+    /// How each root's files are listed: `git ls-files` with a fallback to the built-in walker
+    /// (the default), or one of them only. The two can disagree on edge cases such as negated
+    /// patterns, so forcing one makes scans reproducible across machines.
+    pub file_listing: FileListing,
     pub cross_repo_only: bool,
     pub follow_symlinks: bool,
+    pub similarity_diff: bool,
+    /// Only report groups with at least this many occurrences (values below 2 have no effect).
+    pub min_occurrences: usize,
+    /// Cap the occurrences listed per group; the remainder is counted in `truncated_occurrences`.
+    pub max_occurrences_per_group: Option<usize>,
+    /// Report mode: compute [`DuplicationReport::file_coverage`].
+    pub file_coverage: bool,
+    /// Report mode: compute [`DuplicationReport::file_similarity`].
+    pub file_similarity: bool,
+    /// Report mode: compute [`DuplicationReport::clone_clusters`].
+    pub clone_clusters: bool,
+    /// Report mode: compute [`DuplicationReport::similar_block_clusters`].
+    pub similarity_clusters: bool,
+    /// Report mode: date each span occurrence by the last commit touching its lines
+    /// (`git log -L`; see [`DuplicateSpanOccurrence::last_modified`]) and list the groups whose
+    /// copies were last changed furthest apart first within each section (see
+    /// [`DuplicateSpanGroup::age_spread`]). Groups are ranked before truncation; Git runs once per
+    /// occurrence of the first 500 groups of each section.
+    pub occurrence_ages: bool,
+    /// Report mode: compute [`DuplicationReport::license_conflicts`].
+    pub license_conflicts: bool,
+    /// Report mode: compute [`DuplicationReport::common_idioms`], the token sequences found in
+    /// at least this many files (`>= 2`); `None` (the default) skips the pass.
+    pub idiom_min_files: Option<usize>,
+    /// Report mode: shortest idiom, in tokens. Usually well below [`Self::min_token_len`], so
+    /// that short repeated patterns (error handling, guard clauses) are found.
+    pub idiom_token_len: usize,
+    /// Report mode: only the first `n` tokens of each file take part in the token-based
+    /// detectors (token spans, blocks, AST subtrees, similarity). Truncated files are counted in
+    /// [`ScanStats::skipped_token_cap`].
+    pub max_tokens_per_file: Option<usize>,
+    /// Give up on a file whose normalization and tokenization take longer than this (code
+    /// spans and report modes); it is left out of every detector and counted in
+    /// [`ScanStats::skipped_slow_file`]. Off by default.
+    pub max_file_time: Option<Duration>,
+    /// Report mode: when several span detectors report exactly the same occurrences, keep only
+    /// the most specific one (AST subtree > block > token span > line span > code span) and
+    /// record the others in [`DuplicateSpanGroup::also_detected_by`]. Groups are folded before
+    /// the sections are paged, so pages and [`SectionTotal`]s only count the groups kept.
+    pub deduplicate_across_detectors: bool,
+    /// Report paths as `root.join(path)` instead of relative to the root (files loaded from a
+    /// snapshot keep their relative paths, since their root is not on disk).
+    pub emit_absolute_paths: bool,
+    /// Rewrite result paths (after [`Self::emit_absolute_paths`]) and report root paths that
+    /// start with the first path of an entry to start with the second one instead, like a
+    /// compiler's debug prefix map. Prefixes match whole path components; when several entries
+    /// match, the last one wins.
+    pub path_prefix_map: Vec<(PathBuf, PathBuf)>,
+    /// Optional progress hook, called as files are scanned and detectors run.
+    pub progress: Option<ProgressCallback>,
+    /// Optional hook called with the path and reason of every file the scan skips.
+    pub skip_log: Option<SkipCallback>,
+    /// Only scan the files that belong to this shard; the rest are counted in
+    /// [`ScanStats::skipped_other_shard`].
+    pub shard: Option<ScanShard>,
+    /// Only scan a deterministic sample of the files; the rest are counted in
+    /// [`ScanStats::skipped_not_sampled`] and reports carry a [`ReportSample`] to extrapolate
+    /// from. Applied after [`Self::shard`].
+    pub sample: Option<ScanSample>,
+    /// Unicode-normalize file text before the code-span, line and token detectors see it, so
+    /// code that differs only in normalization form (or fullwidth forms, with NFKC) matches.
+    /// `None` (the default) skips the extra pass. File duplicates still compare raw bytes.
+    pub unicode_normalization: Option<UnicodeNormalization>,
+    /// Drop test or non-test occurrences before groups are filtered and ranked. Test code is
+    /// detected heuristically (test directories/file names, `#[cfg(test)]` items); every
+    /// occurrence reports the result via `is_test()` either way.
+    pub test_code_filter: Option<TestCodeFilter>,
+    /// Report mode: worker threads for the detectors; `0` (the default) uses one per available
+    /// CPU. The report is identical for every thread count.
+    pub threads: usize,
+    /// Maximum preview length in chars; `None` keeps the defaults (80 normalized chars for code
+    /// spans, 120 chars for previews read from file lines). `0` omits previews.
+    pub preview_max_chars: Option<usize>,
+    /// Lines of context before/after the span included in previews read from files. When
+    /// non-zero, code-span previews also come from the file lines instead of normalized code.
+    pub preview_context_lines: usize,
+    /// Report mode: how each section is ordered before `max_report_items` truncation, so the
+    /// cut keeps the groups that matter most for the workflow.
+    pub report_sort: ReportSort,
+    /// Report mode: groups to skip at the start of each sorted section, for paging through
+    /// large results together with [`Self::section_limit`].
+    pub section_offset: usize,
+    /// Report mode: groups kept per section after [`Self::section_offset`]; `None` uses
+    /// [`Self::max_items_per_detector`] / [`Self::max_report_items`].
+    pub section_limit: Option<usize>,
+    /// Report mode: per-language `min_token_len` scales as `(language, scale)`, overriding the
+    /// built-in table (e.g. `java` 1.5, `python` 0.8). Languages are detected from file
+    /// extensions; the token-based detectors use `round(min_token_len × scale)` for each file.
+    pub language_token_scales: Vec<(String, f64)>,
+    /// Report mode: gitignore-style patterns (relative to each root); when set, only groups
+    /// and similar pairs with at least one occurrence matching a pattern are reported.
+    pub path_filter: Vec<String>,
+    /// Report mode: with [`Self::path_filter`], also drop the occurrences that do not match
+    /// from the kept groups; they are counted in `truncated_occurrences` / `truncated_files`.
+    pub path_filter_trim: bool,
+    /// Skip the fingerprints of the built-in boilerplate list (license headers, generated-code
+    /// banners, serde visitor impls, accessor pairs) in the code-span, line and token
+    /// detectors, so code made only of it seeds no match. On by default.
+    pub builtin_skips: bool,
+    /// More boilerplate snippets to skip like the built-in list, as source text.
+    pub skip_snippets: Vec<String>,
+    /// Report mode: per-language stop sequences as `(language, code)`. The token-span detector
+    /// seeds no match from a fingerprint made only of stop-sequence tokens (idioms such as
+    /// `} else {` or `if err != nil { return err }`). The entries for a language replace its
+    /// built-in list; an entry with empty code only clears it.
+    pub stop_sequences: Vec<(String, String)>,
+    /// Report mode: drop span groups whose every occurrence lies within an occurrence of a
+    /// longer group from the same detector, so one region is not counted twice. Code spans are
+    /// compared by line and column, other sections by line range. On by default.
+    pub prune_contained_groups: bool,
+    /// Report mode: groups to drop before each section is sorted and truncated, so that
+    /// dropping one neither leaves a hole in the page nor counts in the section totals. Which
+    /// entries matched a group, listed or not, is recorded in
+    /// [`ScanStats::matched_suppressions`].
+    pub group_suppressions: Vec<GroupSuppression>,
+}
+
+/// Section ordering for [`ScanOptions::report_sort`]. Every order falls back to the default
+/// tie-breakers (occurrences, size, hash, locations), so output stays deterministic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportSort {
+    /// Most occurrences first, then largest.
+    #[default]
+    Occurrences,
+    /// Largest `normalized_len` first.
+    Size,
+    /// Largest estimated saving first: `potential_savings_lines` for span groups,
+    /// `(files - 1) × normalized_len` for file duplicates. Similarity pairs sort by score under
+    /// every order except [`ReportSort::Path`].
+    Score,
+    /// By the location of the first occurrence (repo id, path, lines).
+    Path,
+}
+
+impl ReportSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Occurrences => "occurrences",
+            Self::Size => "size",
+            Self::Score => "score",
+            Self::Path => "path",
+        }
+    }
+}
+
+/// Which occurrences to keep for [`ScanOptions::test_code_filter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestCodeFilter {
+    /// Only production code.
+    ExcludeTests,
+    /// Only test code.
+    TestsOnly,
+}
+
+impl TestCodeFilter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ExcludeTests => "excludeTests",
+            Self::TestsOnly => "testsOnly",
+        }
+    }
+
+    pub(crate) fn keeps(self, is_test: bool) -> bool {
+        match self {
+            Self::ExcludeTests => !is_test,
+            Self::TestsOnly => is_test,
+        }
+    }
+}
+
+/// Unicode normalization form for [`ScanOptions::unicode_normalization`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeNormalization {
+    /// Canonical composition: precomposed and decomposed accents compare equal.
+    Nfc,
+    /// Compatibility composition: additionally folds fullwidth forms, ligatures, etc.
+    Nfkc,
+}
+
+impl UnicodeNormalization {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Nfc => "nfc",
+            Self::Nfkc => "nfkc",
+        }
+    }
+}
+
+/// Order in which each root's files are read, for [`ScanOptions::budget_order`]. Roots are still
+/// scanned one after another, so earlier roots consume the budgets first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BudgetOrder {
+    /// Directory walk (or `git ls-files`) order: no up-front listing, but which files fit a
+    /// budget can change with the filesystem.
+    #[default]
+    WalkOrder,
+    /// Smallest files first: the most files within [`ScanOptions::max_total_bytes`].
+    SmallestFirst,
+    /// Largest files first.
+    LargestFirst,
+    /// By path.
+    PathSorted,
+}
+
+impl BudgetOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::WalkOrder => "walk",
+            Self::SmallestFirst => "smallest",
+            Self::LargestFirst => "largest",
+            Self::PathSorted => "path",
+        }
+    }
+}
+
+/// File listing strategy for [`ScanOptions::file_listing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+pub enum FileListing {
+    /// `git ls-files` inside Git checkouts, falling back to the walker when Git is missing or
+    /// fails, and the walker everywhere else.
+    #[default]
+    Auto,
+    /// Only `git ls-files`: a root that is not a Git checkout, or where Git fails, is an error.
+    /// Requires [`ScanOptions::respect_gitignore`] and no [`ScanOptions::follow_symlinks`].
+    #[cfg_attr(feature = "serde", serde(rename = "git"))]
+    GitCli,
+    /// Only the built-in `ignore`-crate walker, even inside Git checkouts.
+    Walker,
+}
+
+impl FileListing {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::GitCli => "git",
+            Self::Walker => "walker",
+        }
+    }
+}
+
+/// Deterministic partition of candidate files, for splitting one scan across several jobs.
+///
+/// A file belongs to shard `index` of `count` when the FNV-1a hash of its root-relative path
+/// (with `/` separators) modulo `count` equals `index`, so every job scanning the same roots
+/// agrees on the partition regardless of where the roots are checked out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanShard {
+    index: u32,
+    count: u32,
+}
+
+impl ScanShard {
+    /// `index` is zero-based and must be `< count`.
+    pub fn new(index: u32, count: u32) -> io::Result<Self> {
+        if count == 0 || index >= count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "shard index must be < shard count (and count >= 1)",
+            ));
+        }
+        Ok(Self { index, count })
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Whether the file at `rel_path` (root-relative, `/`-separated) belongs to this shard.
+    pub fn contains(&self, rel_path: &str) -> bool {
+        fnv1a64(rel_path.as_bytes()) % u64::from(self.count) == u64::from(self.index)
+    }
+}
+
+/// A report group to drop, by the `content_hash` of a file, data, symbol or span group (see
+/// [`ScanOptions::group_suppressions`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GroupSuppression {
+    section: Option<ReportSection>,
+    content_hash: u64,
+    active: bool,
+}
+
+impl GroupSuppression {
+    /// `section` `None` matches the hash in every section. An inactive suppression (e.g. one
+    /// past its expiry date) drops nothing and only records whether the group still exists.
+    pub fn new(section: Option<ReportSection>, content_hash: u64, active: bool) -> Self {
+        Self {
+            section,
+            content_hash,
+            active,
+        }
+    }
+
+    pub fn section(&self) -> Option<ReportSection> {
+        self.section
+    }
+
+    pub fn content_hash(&self) -> u64 {
+        self.content_hash
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub(crate) fn matches(&self, section: ReportSection, content_hash: u64) -> bool {
+        self.content_hash == content_hash && self.section.is_none_or(|wanted| wanted == section)
+    }
+}
+
+/// Fixed token windows the similarity detectors compare instead of brace blocks (see
+/// [`ScanOptions::similarity_windows`]).
+///
+/// Windows of `size` tokens start every `stride` tokens, plus one at the end of each file so
+/// its tail is covered; files shorter than one window are skipped. This gives block-less or
+/// deeply nested code (Python, SQL, long functions) fuzzy matching.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimilarityWindows {
+    size: usize,
+    stride: usize,
+}
+
+impl SimilarityWindows {
+    /// `size` and `stride` are in tokens and must be `>= 1`.
+    pub fn new(size: usize, stride: usize) -> io::Result<Self> {
+        if size == 0 || stride == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "similarity window size and stride must be >= 1",
+            ));
+        }
+        Ok(Self { size, stride })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+}
+
+/// Deterministic subset of candidate files, for a quick estimate on a tree too large to scan.
+///
+/// Files are ranked by the FNV-1a hash of their root-relative path (with `/` separators, as for
+/// [`ScanShard`]) and the lowest-ranked ones are sampled, so reruns pick the same files and a
+/// path copied between roots is sampled in all of them or none.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanSample {
+    kind: SampleKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SampleKind {
+    Fraction(f64),
+    Files(u64),
+}
+
+impl ScanSample {
+    /// Sample roughly `fraction` of the files; must be in `(0, 1]`.
+    pub fn fraction(fraction: f64) -> io::Result<Self> {
+        if !fraction.is_finite() || fraction <= 0.0 || fraction > 1.0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "sample fraction must be in (0, 1]",
+            ));
+        }
+        Ok(Self {
+            kind: SampleKind::Fraction(fraction),
+        })
+    }
+
+    /// Sample exactly `files` files (or all of them, if there are fewer). Ranking needs every
+    /// path, so the roots are walked once more before scanning.
+    pub fn files(files: u64) -> io::Result<Self> {
+        if files == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "sample file count must be >= 1",
+            ));
+        }
+        Ok(Self {
+            kind: SampleKind::Files(files),
+        })
+    }
+
+    /// The fraction passed to [`Self::fraction`].
+    pub fn as_fraction(&self) -> Option<f64> {
+        match self.kind {
+            SampleKind::Fraction(fraction) => Some(fraction),
+            SampleKind::Files(_) => None,
+        }
+    }
+
+    /// The count passed to [`Self::files`].
+    pub fn as_files(&self) -> Option<u64> {
+        match self.kind {
+            SampleKind::Files(files) => Some(files),
+            SampleKind::Fraction(_) => None,
+        }
+    }
+}
+
+/// Rank of `rel_path` (root-relative, `/`-separated) for [`ScanSample`]: lower is sampled first.
+pub(crate) fn sample_rank(rel_path: &str) -> u64 {
+    fnv1a64(rel_path.as_bytes())
 }
 
 pub const DEFAULT_MAX_FILE_SIZE_BYTES: u64 = 10 * 1024 * 1024;
@@ -31,19 +526,74 @@ impl Default for ScanOptions {
     fn default() -> Self {
         Self {
             ignore_dirs: default_ignore_dirs(),
+            ignore_patterns: Vec::new(),
+            default_excludes: default_exclude_patterns(),
             max_file_size: Some(DEFAULT_MAX_FILE_SIZE_BYTES),
             max_files: None,
             max_total_bytes: None,
+            budget_order: BudgetOrder::WalkOrder,
             max_normalized_chars: None,
             max_tokens: None,
             min_match_len: 50,
             min_token_len: 50,
+            adaptive_window: None,
             similarity_threshold: 0.85,
             simhash_max_distance: 3,
+            shingle_size: 5,
+            minhash_signature_size: 32,
+            minhash_bands: 8,
+            similarity_windows: None,
+            line_span_min_lines: 2,
+            line_span_window: 8,
+            symbol_min_count: 3,
+            import_min_count: 3,
+            import_similarity_threshold: 0.8,
+            data_min_bytes: 1024,
+            min_entropy: 0.0,
             max_report_items: 200,
+            max_items_per_detector: HashMap::new(),
+            max_groups_per_detector: Some(1_000_000),
             respect_gitignore: true,
+            use_global_gitignore: false,
+            file_listing: FileListing::Auto,
             cross_repo_only: false,
             follow_symlinks: false,
+            similarity_diff: false,
+            min_occurrences: 2,
+            max_occurrences_per_group: None,
+            file_coverage: false,
+            file_similarity: false,
+            clone_clusters: false,
+            similarity_clusters: false,
+            occurrence_ages: false,
+            license_conflicts: false,
+            idiom_min_files: None,
+            idiom_token_len: 12,
+            max_tokens_per_file: None,
+            max_file_time: None,
+            deduplicate_across_detectors: false,
+            emit_absolute_paths: false,
+            path_prefix_map: Vec::new(),
+            progress: None,
+            skip_log: None,
+            shard: None,
+            sample: None,
+            unicode_normalization: None,
+            test_code_filter: None,
+            threads: 0,
+            preview_max_chars: None,
+            preview_context_lines: 0,
+            report_sort: ReportSort::Occurrences,
+            section_offset: 0,
+            section_limit: None,
+            language_token_scales: Vec::new(),
+            path_filter: Vec::new(),
+            path_filter_trim: false,
+            builtin_skips: true,
+            skip_snippets: Vec::new(),
+            stop_sequences: Vec::new(),
+            prune_contained_groups: true,
+            group_suppressions: Vec::new(),
         }
     }
 }
@@ -61,19 +611,109 @@ impl ScanOptions {
         self.validate_for_report()
     }
 
+    /// `path` with [`Self::path_prefix_map`] applied.
+    pub fn map_output_path(&self, path: &Path) -> PathBuf {
+        self.path_prefix_map
+            .iter()
+            .rev()
+            .find_map(|(from, to)| {
+                let rest = path.strip_prefix(from).ok()?;
+                Some(if rest.as_os_str().is_empty() {
+                    to.clone()
+                } else {
+                    to.join(rest)
+                })
+            })
+            .unwrap_or_else(|| path.to_path_buf())
+    }
+
+    /// [`Self::preview_max_chars`], or `default` when unset.
+    pub(crate) fn preview_max_chars(&self, default: usize) -> usize {
+        self.preview_max_chars.unwrap_or(default)
+    }
+
+    /// Groups a report section keeps: its [`Self::max_items_per_detector`] entry, else
+    /// [`Self::max_report_items`].
+    pub(crate) fn max_items(&self, section: ReportSection) -> usize {
+        self.max_items_per_detector
+            .get(&section)
+            .copied()
+            .unwrap_or(self.max_report_items)
+    }
+
+    /// The `(offset, len)` page of a sorted report section.
+    pub(crate) fn report_page(&self, section: ReportSection) -> (usize, usize) {
+        (
+            self.section_offset,
+            self.section_limit.unwrap_or(self.max_items(section)),
+        )
+    }
+
+    pub(crate) fn emit_progress(&self, phase: ScanPhase, stats: &ScanStats) {
+        if let Some(progress) = &self.progress {
+            progress.emit(phase, stats);
+        }
+    }
+
+    pub(crate) fn log_skip(&self, path: &Path, reason: SkipReason, size: Option<u64>) {
+        if let Some(skip_log) = &self.skip_log {
+            skip_log.emit(path, reason, size);
+        }
+    }
+
     /// Validate options used by file-duplicate scanning.
     pub fn validate_for_file_duplicates(&self) -> io::Result<()> {
+        if self.max_occurrences_per_group == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "max_occurrences_per_group must be >= 1",
+            ));
+        }
+        if self.file_listing == FileListing::GitCli
+            && (!self.respect_gitignore || self.follow_symlinks)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "file_listing=git requires respect_gitignore and no follow_symlinks",
+            ));
+        }
+        build_exclude_patterns(Path::new(""), self)?;
+        build_ignore_patterns(Path::new(""), &self.path_filter)?;
+        if self
+            .path_prefix_map
+            .iter()
+            .any(|(from, _)| from.as_os_str().is_empty())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "path_prefix_map: the prefix to replace must not be empty",
+            ));
+        }
         Ok(())
     }
 
     /// Validate options used by code-span scanning.
     pub fn validate_for_code_spans(&self) -> io::Result<()> {
+        self.validate_for_file_duplicates()?;
+
         if self.min_match_len == 0 {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "min_match_len must be >= 1",
             ));
         }
+        if self.adaptive_window.is_some_and(|growth| growth < 2) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "adaptive_window must be >= 2",
+            ));
+        }
+        if self.max_file_time == Some(Duration::ZERO) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "max_file_time must be > 0",
+            ));
+        }
         Ok(())
     }
 
@@ -103,6 +743,138 @@ impl ScanOptions {
             ));
         }
 
+        if self.shingle_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "shingle_size must be >= 1",
+            ));
+        }
+        if !(1..=1024).contains(&self.minhash_signature_size) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "minhash_signature_size must be in 1..=1024",
+            ));
+        }
+        if self.minhash_bands == 0
+            || !self
+                .minhash_signature_size
+                .is_multiple_of(self.minhash_bands)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "minhash_bands must be >= 1 and divide minhash_signature_size",
+            ));
+        }
+        if self.line_span_min_lines == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "line_span_min_lines must be >= 1",
+            ));
+        }
+        if self.line_span_window == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "line_span_window must be >= 1",
+            ));
+        }
+        if self.symbol_min_count == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "symbol_min_count must be >= 1",
+            ));
+        }
+        if self.import_min_count == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "import_min_count must be >= 1",
+            ));
+        }
+        if self.idiom_min_files.is_some_and(|files| files < 2) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "idiom_min_files must be >= 2",
+            ));
+        }
+        if self.idiom_token_len == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "idiom_token_len must be >= 1",
+            ));
+        }
+        if self.data_min_bytes == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "data_min_bytes must be >= 1",
+            ));
+        }
+        let threshold = self.import_similarity_threshold;
+        if !threshold.is_finite() || !(0.0..=1.0).contains(&threshold) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "import_similarity_threshold must be finite and in 0..=1",
+            ));
+        }
+        if !self.min_entropy.is_finite() || self.min_entropy < 0.0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "min_entropy must be finite and >= 0",
+            ));
+        }
+
+        if self.section_limit == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "section_limit must be >= 1",
+            ));
+        }
+
+        if self.max_groups_per_detector == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "max_groups_per_detector must be >= 1",
+            ));
+        }
+
+        if self.max_tokens_per_file == Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "max_tokens_per_file must be >= 1",
+            ));
+        }
+
+        for (name, scale) in &self.language_token_scales {
+            if language_by_name(name).is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("language_token_scales: unknown language {name:?}"),
+                ));
+            }
+            if !scale.is_finite() || *scale <= 0.0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "language_token_scales: scale must be finite and > 0",
+                ));
+            }
+        }
+
+        for (name, code) in &self.stop_sequences {
+            let Some(language) = language_by_name(name) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("stop_sequences: unknown language {name:?}"),
+                ));
+            };
+            if !code.trim().is_empty() && !is_stop_sequence(code, language) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "stop_sequences: {code:?} needs a keyword or punctuation token \
+                         (identifiers and literals alone match almost anything)"
+                    ),
+                ));
+            }
+        }
+
         Ok(())
     }
 }
@@ -111,35 +883,308 @@ impl ScanOptions {
 ///
 /// This struct is `#[non_exhaustive]` so new counters can be added without breaking callers.
 /// Construct it via `ScanStats::default()` and then read/update fields as needed.
+///
+/// With the `serde` feature it (de)serializes with camelCase keys, as the CLI's `scanStats`;
+/// missing counters read as `0`. `Display` renders the `key=value` summary of `--stats`.
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase", default)
+)]
 #[non_exhaustive]
 pub struct ScanStats {
     pub candidate_files: u64,
     pub scanned_files: u64,
     pub scanned_bytes: u64,
     pub git_fast_path_fallbacks: u64,
+    /// Roots whose files were listed by `git ls-files` (see [`ScanOptions::file_listing`]).
+    pub git_listed_repos: u64,
+    /// Roots whose files were listed by the built-in walker, including Git fast path fallbacks.
+    pub walker_listed_repos: u64,
     pub skipped_not_found: u64,
     pub skipped_permission_denied: u64,
     pub skipped_too_large: u64,
+    /// Size of the files counted in `skipped_too_large`, as their metadata reported it; the
+    /// other `skipped_*_bytes` counters do the same for their reason.
+    pub skipped_too_large_bytes: u64,
     pub skipped_binary: u64,
+    pub skipped_binary_bytes: u64,
     pub skipped_outside_root: u64,
     pub skipped_relativize_failed: u64,
+    /// Roots dropped because they resolve to the same directory as an earlier root (e.g.
+    /// `./repo` and `/abs/path/repo`); otherwise every file would be a cross-repo duplicate of
+    /// itself.
+    pub skipped_duplicate_roots: u64,
     pub skipped_walk_errors: u64,
     pub skipped_budget_max_files: u64,
     pub skipped_budget_max_total_bytes: u64,
+    pub skipped_budget_max_total_bytes_bytes: u64,
     pub skipped_budget_max_normalized_chars: u64,
+    pub skipped_budget_max_normalized_chars_bytes: u64,
     pub skipped_budget_max_tokens: u64,
+    pub skipped_budget_max_tokens_bytes: u64,
     pub skipped_bucket_truncated: u64,
+    /// Groups a span detector counted but did not keep once it held
+    /// [`ScanOptions::max_groups_per_detector`] groups.
+    pub skipped_group_cap: u64,
+    /// Files whose token stream was truncated by `max_tokens_per_file` (not a fatal skip).
+    pub skipped_token_cap: u64,
+    /// Files given up on because their analysis exceeded [`ScanOptions::max_file_time`] (not a
+    /// fatal skip).
+    pub skipped_slow_file: u64,
+    pub skipped_slow_file_bytes: u64,
+    /// Files left to other shards by [`ScanOptions::shard`] (not a fatal skip).
+    pub skipped_other_shard: u64,
+    /// Files left out by [`ScanOptions::sample`] (not a fatal skip).
+    pub skipped_not_sampled: u64,
+    /// Files whose canonical target was already read through another path when
+    /// [`ScanOptions::follow_symlinks`] is on (a symlink into another root, two links to one file,
+    /// or nested roots), so the same bytes are not reported as their own duplicate (not a fatal
+    /// skip).
+    pub skipped_symlink_duplicates: u64,
+    /// Files split out of a file-duplicate candidate group because their bytes did not match
+    /// when re-read (a hash collision or a file changed mid-scan; not a fatal skip).
+    pub file_verification_mismatches: u64,
+    /// Fingerprints kept by winnowing, summed over the code-span, token-span and line-span
+    /// detectors (see [`Self::fingerprints_per_kb`]).
+    pub fingerprints: u64,
+    /// Report previews whose file changed or vanished after the scan, so they show the
+    /// normalized code instead of the source lines (not a fatal skip). Similarity diffs and
+    /// identifier stats are left out for such files.
+    pub stale_previews: u64,
+    /// Report groups dropped by [`ScanOptions::group_suppressions`].
+    pub suppressed_groups: u64,
+    /// Indices into [`ScanOptions::group_suppressions`] of the entries that matched a group
+    /// before truncation, ascending.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub matched_suppressions: Vec<usize>,
+    /// Breakdown of the walk/read counters by root, in root order.
+    ///
+    /// Counters updated after the walk (e.g. `skipped_bucket_truncated`) are only aggregated.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Vec::is_empty"))]
+    pub per_repo: Vec<RepoScanStats>,
+}
+
+/// Scan counters attributed to a single root (see [`ScanStats::per_repo`]).
+///
+/// Serialized as `repoId`, `repoLabel` and the counters of `stats` side by side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "camelCase")
+)]
+#[non_exhaustive]
+pub struct RepoScanStats {
+    pub repo_id: usize,
+    pub repo_label: String,
+    /// How this root's files were listed ([`FileListing::GitCli`] or [`FileListing::Walker`]);
+    /// `None` for roots never walked, e.g. after a budget stopped the scan.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub file_listing: Option<FileListing>,
+    /// Counters for this root only; `stats.per_repo` is always empty.
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub stats: ScanStats,
+}
+
+impl RepoScanStats {
+    pub fn new(repo_id: usize, repo_label: String, stats: ScanStats) -> Self {
+        let file_listing = if stats.git_listed_repos > 0 {
+            Some(FileListing::GitCli)
+        } else if stats.walker_listed_repos > 0 {
+            Some(FileListing::Walker)
+        } else {
+            None
+        };
+        Self {
+            repo_id,
+            repo_label,
+            file_listing,
+            stats,
+        }
+    }
 }
 
 impl ScanStats {
+    pub(crate) fn delta_since(&self, before: &ScanStats) -> ScanStats {
+        ScanStats {
+            candidate_files: self.candidate_files.saturating_sub(before.candidate_files),
+            scanned_files: self.scanned_files.saturating_sub(before.scanned_files),
+            scanned_bytes: self.scanned_bytes.saturating_sub(before.scanned_bytes),
+            git_fast_path_fallbacks: self
+                .git_fast_path_fallbacks
+                .saturating_sub(before.git_fast_path_fallbacks),
+            git_listed_repos: self
+                .git_listed_repos
+                .saturating_sub(before.git_listed_repos),
+            walker_listed_repos: self
+                .walker_listed_repos
+                .saturating_sub(before.walker_listed_repos),
+            skipped_not_found: self
+                .skipped_not_found
+                .saturating_sub(before.skipped_not_found),
+            skipped_permission_denied: self
+                .skipped_permission_denied
+                .saturating_sub(before.skipped_permission_denied),
+            skipped_too_large: self
+                .skipped_too_large
+                .saturating_sub(before.skipped_too_large),
+            skipped_too_large_bytes: self
+                .skipped_too_large_bytes
+                .saturating_sub(before.skipped_too_large_bytes),
+            skipped_binary: self.skipped_binary.saturating_sub(before.skipped_binary),
+            skipped_binary_bytes: self
+                .skipped_binary_bytes
+                .saturating_sub(before.skipped_binary_bytes),
+            skipped_outside_root: self
+                .skipped_outside_root
+                .saturating_sub(before.skipped_outside_root),
+            skipped_relativize_failed: self
+                .skipped_relativize_failed
+                .saturating_sub(before.skipped_relativize_failed),
+            skipped_duplicate_roots: self
+                .skipped_duplicate_roots
+                .saturating_sub(before.skipped_duplicate_roots),
+            skipped_walk_errors: self
+                .skipped_walk_errors
+                .saturating_sub(before.skipped_walk_errors),
+            skipped_budget_max_files: self
+                .skipped_budget_max_files
+                .saturating_sub(before.skipped_budget_max_files),
+            skipped_budget_max_total_bytes: self
+                .skipped_budget_max_total_bytes
+                .saturating_sub(before.skipped_budget_max_total_bytes),
+            skipped_budget_max_total_bytes_bytes: self
+                .skipped_budget_max_total_bytes_bytes
+                .saturating_sub(before.skipped_budget_max_total_bytes_bytes),
+            skipped_budget_max_normalized_chars: self
+                .skipped_budget_max_normalized_chars
+                .saturating_sub(before.skipped_budget_max_normalized_chars),
+            skipped_budget_max_normalized_chars_bytes: self
+                .skipped_budget_max_normalized_chars_bytes
+                .saturating_sub(before.skipped_budget_max_normalized_chars_bytes),
+            skipped_budget_max_tokens: self
+                .skipped_budget_max_tokens
+                .saturating_sub(before.skipped_budget_max_tokens),
+            skipped_budget_max_tokens_bytes: self
+                .skipped_budget_max_tokens_bytes
+                .saturating_sub(before.skipped_budget_max_tokens_bytes),
+            skipped_bucket_truncated: self
+                .skipped_bucket_truncated
+                .saturating_sub(before.skipped_bucket_truncated),
+            skipped_group_cap: self
+                .skipped_group_cap
+                .saturating_sub(before.skipped_group_cap),
+            skipped_token_cap: self
+                .skipped_token_cap
+                .saturating_sub(before.skipped_token_cap),
+            skipped_slow_file: self
+                .skipped_slow_file
+                .saturating_sub(before.skipped_slow_file),
+            skipped_slow_file_bytes: self
+                .skipped_slow_file_bytes
+                .saturating_sub(before.skipped_slow_file_bytes),
+            skipped_other_shard: self
+                .skipped_other_shard
+                .saturating_sub(before.skipped_other_shard),
+            skipped_not_sampled: self
+                .skipped_not_sampled
+                .saturating_sub(before.skipped_not_sampled),
+            skipped_symlink_duplicates: self
+                .skipped_symlink_duplicates
+                .saturating_sub(before.skipped_symlink_duplicates),
+            file_verification_mismatches: self
+                .file_verification_mismatches
+                .saturating_sub(before.file_verification_mismatches),
+            fingerprints: self.fingerprints.saturating_sub(before.fingerprints),
+            stale_previews: self.stale_previews.saturating_sub(before.stale_previews),
+            suppressed_groups: self
+                .suppressed_groups
+                .saturating_sub(before.suppressed_groups),
+            matched_suppressions: Vec::new(),
+            per_repo: Vec::new(),
+        }
+    }
+
+    /// [`Self::fingerprints`] per KiB of [`Self::scanned_bytes`]; `None` before any bytes were
+    /// scanned. Tune [`ScanOptions::adaptive_window`] when this is high on large files.
+    #[must_use]
+    pub fn fingerprints_per_kb(&self) -> Option<f64> {
+        (self.scanned_bytes > 0)
+            .then(|| self.fingerprints as f64 * 1024.0 / self.scanned_bytes as f64)
+    }
+
+    /// The non-zero skip counters (and other not-scanned tallies), by their short `--stats`
+    /// names such as `too_large` or `bucket_truncated`, in a fixed order.
+    #[must_use]
+    pub fn nonzero_skips(&self) -> Vec<(&'static str, u64)> {
+        let mut skips: Vec<(&'static str, u64)> = vec![
+            ("not_found", self.skipped_not_found),
+            ("permission_denied", self.skipped_permission_denied),
+            ("too_large", self.skipped_too_large),
+            ("binary", self.skipped_binary),
+            ("outside_root", self.skipped_outside_root),
+            ("relativize_failed", self.skipped_relativize_failed),
+            ("duplicate_roots", self.skipped_duplicate_roots),
+            ("walk_errors", self.skipped_walk_errors),
+            ("bucket_truncated", self.skipped_bucket_truncated),
+            ("group_cap", self.skipped_group_cap),
+            ("budget_max_files", self.skipped_budget_max_files),
+            (
+                "budget_max_total_bytes",
+                self.skipped_budget_max_total_bytes,
+            ),
+            (
+                "budget_max_normalized_chars",
+                self.skipped_budget_max_normalized_chars,
+            ),
+            ("budget_max_tokens", self.skipped_budget_max_tokens),
+            ("token_cap", self.skipped_token_cap),
+            ("slow_file", self.skipped_slow_file),
+            ("other_shard", self.skipped_other_shard),
+            ("not_sampled", self.skipped_not_sampled),
+            ("symlink_duplicates", self.skipped_symlink_duplicates),
+            ("verification_mismatch", self.file_verification_mismatches),
+            ("stale_preview", self.stale_previews),
+        ];
+        skips.retain(|(_, v)| *v > 0);
+        skips
+    }
+
+    /// The non-zero `skipped_*_bytes` counters, by the short names of [`Self::nonzero_skips`].
+    #[must_use]
+    pub fn nonzero_skipped_bytes(&self) -> Vec<(&'static str, u64)> {
+        let mut bytes: Vec<(&'static str, u64)> = vec![
+            ("too_large", self.skipped_too_large_bytes),
+            ("binary", self.skipped_binary_bytes),
+            (
+                "budget_max_total_bytes",
+                self.skipped_budget_max_total_bytes_bytes,
+            ),
+            (
+                "budget_max_normalized_chars",
+                self.skipped_budget_max_normalized_chars_bytes,
+            ),
+            ("budget_max_tokens", self.skipped_budget_max_tokens_bytes),
+            ("slow_file", self.skipped_slow_file_bytes),
+        ];
+        bytes.retain(|(_, v)| *v > 0);
+        bytes
+    }
+
     #[must_use]
     pub fn has_fatal_skips(&self) -> bool {
         self.skipped_permission_denied > 0
             || self.skipped_outside_root > 0
             || self.skipped_relativize_failed > 0
+            || self.skipped_duplicate_roots > 0
             || self.skipped_walk_errors > 0
             || self.skipped_bucket_truncated > 0
+            || self.skipped_group_cap > 0
             || self.skipped_budget_max_files > 0
             || self.skipped_budget_max_total_bytes > 0
             || self.skipped_budget_max_normalized_chars > 0
@@ -147,12 +1192,98 @@ impl ScanStats {
     }
 }
 
+/// One `key=value` line per topic: totals, fingerprints, non-zero skips and, with more than one
+/// root, a line per root.
+impl fmt::Display for ScanStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "candidates={} scanned={} bytes={}",
+            self.candidate_files, self.scanned_files, self.scanned_bytes
+        )?;
+        if self.git_fast_path_fallbacks > 0 {
+            writeln!(
+                f,
+                "git_fast_path_fallbacks={}",
+                self.git_fast_path_fallbacks
+            )?;
+        }
+        if let Some(per_kb) = self.fingerprints_per_kb().filter(|_| self.fingerprints > 0) {
+            writeln!(f, "fingerprints={} per_kb={per_kb:.1}", self.fingerprints)?;
+        }
+
+        let skips = self.nonzero_skips();
+        if !skips.is_empty() {
+            let bytes = self.nonzero_skipped_bytes();
+            writeln!(f, "skipped:")?;
+            for (k, v) in skips {
+                write!(f, "- {k}={v}")?;
+                if let Some((_, b)) = bytes.iter().find(|(name, _)| *name == k) {
+                    write!(f, " bytes={b}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+
+        if self.per_repo.len() > 1 {
+            writeln!(f, "per root:")?;
+            for repo in &self.per_repo {
+                let s = &repo.stats;
+                write!(
+                    f,
+                    "- [{}] candidates={} scanned={} bytes={}",
+                    repo.repo_label, s.candidate_files, s.scanned_files, s.scanned_bytes
+                )?;
+                if let Some(listing) = repo.file_listing {
+                    write!(f, " listing={}", listing.as_str())?;
+                }
+                for (k, v) in s.nonzero_skips() {
+                    write!(f, " {k}={v}")?;
+                }
+                for (k, b) in s.nonzero_skipped_bytes() {
+                    write!(f, " {k}_bytes={b}")?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ScanOutcome<T> {
     pub result: T,
     pub stats: ScanStats,
 }
 
+/// Lockfiles and generated sources: machine-written, so their duplication is noise.
+pub fn default_exclude_patterns() -> Vec<String> {
+    [
+        "package-lock.json",
+        "npm-shrinkwrap.json",
+        "yarn.lock",
+        "pnpm-lock.yaml",
+        "bun.lock",
+        "Cargo.lock",
+        "Gemfile.lock",
+        "composer.lock",
+        "poetry.lock",
+        "Pipfile.lock",
+        "uv.lock",
+        "go.sum",
+        "*.min.js",
+        "*.min.css",
+        "*.pb.go",
+        "*_pb2.py",
+        "*_pb2_grpc.py",
+        "*_generated.*",
+        "*.generated.*",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect()
+}
+
 pub fn default_ignore_dirs() -> HashSet<String> {
     [
         ".git",
@@ -177,6 +1308,7 @@ pub struct DuplicateFile {
     pub(crate) repo_id: usize,
     pub(crate) repo_label: Arc<str>,
     pub(crate) path: Arc<str>,
+    pub(crate) is_test: bool,
 }
 
 impl DuplicateFile {
@@ -191,6 +1323,11 @@ impl DuplicateFile {
     pub fn path(&self) -> &str {
         self.path.as_ref()
     }
+
+    /// Whether the path looks like test code (see [`ScanOptions::test_code_filter`]).
+    pub fn is_test(&self) -> bool {
+        self.is_test
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -198,15 +1335,44 @@ pub struct DuplicateGroup {
     pub content_hash: u64,
     pub normalized_len: usize,
     pub files: Vec<DuplicateFile>,
+    /// Number of files omitted from `files` due to `max_occurrences_per_group`.
+    pub truncated_files: usize,
 }
 
+/// Files that declare the same set of public symbols (see
+/// [`DuplicationReport::symbol_duplicates`]).
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct DuplicateSpanOccurrence {
-    pub(crate) repo_id: usize,
-    pub(crate) repo_label: Arc<str>,
+pub struct SymbolDuplicateGroup {
+    /// Hash of the sorted symbol names.
+    pub content_hash: u64,
+    /// The shared public symbols, sorted.
+    pub symbols: Vec<String>,
+    pub files: Vec<DuplicateFile>,
+    /// Number of files omitted from `files` due to `max_occurrences_per_group`.
+    pub truncated_files: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateSpanOccurrence {
+    pub(crate) repo_id: usize,
+    pub(crate) repo_label: Arc<str>,
     pub(crate) path: Arc<str>,
     pub(crate) start_line: u32,
     pub(crate) end_line: u32,
+    /// 1-based byte column of the first matched byte on `start_line` (0 = unknown).
+    pub(crate) start_col: u32,
+    /// 1-based byte column of the last matched byte on `end_line` (0 = unknown).
+    pub(crate) end_col: u32,
+    /// Index of the first/last normalized code char within `start_line`/`end_line`, recorded
+    /// by the code-span detector until [`crate::util::resolve_code_span_columns`] turns it into
+    /// byte columns.
+    pub(crate) code_char_cols: Option<(u32, u32)>,
+    pub(crate) is_test: bool,
+    /// Qualified name of the innermost function/type around the occurrence.
+    pub(crate) scope: Option<Arc<str>>,
+    /// Commit time of the last change to the occurrence's lines; set by
+    /// [`ScanOptions::occurrence_ages`].
+    pub(crate) last_modified: Option<u64>,
 }
 
 impl DuplicateSpanOccurrence {
@@ -229,14 +1395,159 @@ impl DuplicateSpanOccurrence {
     pub fn end_line(&self) -> u32 {
         self.end_line
     }
+
+    /// 1-based byte column where the match starts on [`Self::start_line`].
+    ///
+    /// Only known for code-span occurrences whose file could be re-read; other detectors work
+    /// on whole lines or tokens.
+    pub fn start_col(&self) -> Option<u32> {
+        (self.start_col != 0).then_some(self.start_col)
+    }
+
+    /// 1-based byte column of the last matched byte on [`Self::end_line`] (inclusive).
+    pub fn end_col(&self) -> Option<u32> {
+        (self.end_col != 0).then_some(self.end_col)
+    }
+
+    /// Whether the occurrence looks like test code: a test path, or a line inside a
+    /// `#[cfg(test)]` item (see [`ScanOptions::test_code_filter`]).
+    pub fn is_test(&self) -> bool {
+        self.is_test
+    }
+
+    /// Qualified name of the innermost named function, method or type containing the whole
+    /// occurrence, e.g. `PaymentService::refund` (see [`crate::context::enclosing_scope`]).
+    /// `None` at top level.
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
+
+    /// Unix time (seconds) of the last commit that changed any of the occurrence's lines, with
+    /// [`ScanOptions::occurrence_ages`]. `None` otherwise, and for lines Git does not track
+    /// (untracked files, roots outside a checkout, snapshot files).
+    pub fn last_modified(&self) -> Option<u64> {
+        self.last_modified
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DuplicateSpanGroup {
     pub content_hash: u64,
     pub normalized_len: usize,
     pub preview: String,
     pub occurrences: Vec<DuplicateSpanOccurrence>,
+    /// Number of occurrences omitted from `occurrences` due to `max_occurrences_per_group`.
+    pub truncated_occurrences: usize,
+    /// Lines covered by all occurrences (truncated ones included), counting each occurrence as
+    /// long as the shortest one.
+    pub duplicated_lines: u64,
+    /// Lines that deduplicating would remove: `(occurrences - 1) × span lines`.
+    pub potential_savings_lines: u64,
+    /// Other report sections that found exactly the same occurrences; only populated when
+    /// [`ScanOptions::deduplicate_across_detectors`] folds their groups into this one.
+    pub also_detected_by: Vec<ReportSection>,
+    /// Previews of the first occurrence in each other repo than the one `preview` shows, so
+    /// both sides of a cross-repo match can be reviewed without opening files. Capped at a few
+    /// entries; empty when every occurrence comes from one repo.
+    pub occurrence_previews: Vec<OccurrencePreview>,
+    /// How much information the duplicated code carries; set in report mode only.
+    pub quality: Option<GroupQuality>,
+}
+
+impl DuplicateSpanGroup {
+    /// Seconds between the most and the least recently changed of the dated occurrences (see
+    /// [`DuplicateSpanOccurrence::last_modified`]); `None` with fewer than two. A wide spread
+    /// means one copy kept changing while its twin went stale, so the two have likely drifted
+    /// apart.
+    pub fn age_spread(&self) -> Option<u64> {
+        let dates: Vec<u64> = self
+            .occurrences
+            .iter()
+            .filter_map(|o| o.last_modified)
+            .collect();
+        if dates.len() < 2 {
+            return None;
+        }
+        Some(dates.iter().max()? - dates.iter().min()?)
+    }
+}
+
+/// Quality metrics of a [`DuplicateSpanGroup`], computed over its first occurrence.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GroupQuality {
+    /// Shannon entropy of the normalized tokens, in bits per token: near 0 for runs of braces
+    /// or delimiters, typically 3–5 for ordinary code.
+    pub token_entropy: f64,
+    /// Fraction of the tokens that are punctuation.
+    pub punctuation_ratio: f64,
+    /// Distinct identifier names divided by identifier uses, read from the source lines of the
+    /// first occurrence with a readable file; `None` without one (stdin or stale files) or
+    /// when the span has no identifiers.
+    pub identifier_diversity: Option<f64>,
+}
+
+/// Preview of one occurrence of a [`DuplicateSpanGroup`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OccurrencePreview {
+    /// Index into [`DuplicateSpanGroup::occurrences`].
+    pub occurrence: usize,
+    pub preview: String,
+}
+
+/// Identifies one section of a [`DuplicationReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ReportSection {
+    FileDuplicates,
+    CodeSpanDuplicates,
+    LineSpanDuplicates,
+    TokenSpanDuplicates,
+    BlockDuplicates,
+    AstSubtreeDuplicates,
+    SimilarBlocksMinhash,
+    SimilarBlocksSimhash,
+    SymbolDuplicates,
+    ImportDuplicates,
+    DataDuplicates,
+}
+
+impl ReportSection {
+    pub const ALL: [ReportSection; 11] = [
+        ReportSection::FileDuplicates,
+        ReportSection::CodeSpanDuplicates,
+        ReportSection::LineSpanDuplicates,
+        ReportSection::TokenSpanDuplicates,
+        ReportSection::BlockDuplicates,
+        ReportSection::AstSubtreeDuplicates,
+        ReportSection::SimilarBlocksMinhash,
+        ReportSection::SimilarBlocksSimhash,
+        ReportSection::SymbolDuplicates,
+        ReportSection::ImportDuplicates,
+        ReportSection::DataDuplicates,
+    ];
+
+    /// The section's JSON key (camelCase).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReportSection::FileDuplicates => "fileDuplicates",
+            ReportSection::CodeSpanDuplicates => "codeSpanDuplicates",
+            ReportSection::LineSpanDuplicates => "lineSpanDuplicates",
+            ReportSection::TokenSpanDuplicates => "tokenSpanDuplicates",
+            ReportSection::BlockDuplicates => "blockDuplicates",
+            ReportSection::AstSubtreeDuplicates => "astSubtreeDuplicates",
+            ReportSection::SimilarBlocksMinhash => "similarBlocksMinhash",
+            ReportSection::SimilarBlocksSimhash => "similarBlocksSimhash",
+            ReportSection::SymbolDuplicates => "symbolDuplicates",
+            ReportSection::ImportDuplicates => "importDuplicates",
+            ReportSection::DataDuplicates => "dataDuplicates",
+        }
+    }
+
+    /// Parse a JSON key as returned by [`ReportSection::as_str`].
+    pub fn parse(raw: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|section| section.as_str() == raw)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -245,6 +1556,462 @@ pub struct SimilarityPair {
     pub b: DuplicateSpanOccurrence,
     pub score: f64,
     pub distance: Option<u32>,
+    /// Line diff between the two blocks (`-` lines from `a`, `+` lines from `b`).
+    ///
+    /// Only populated when [`ScanOptions::similarity_diff`] is enabled.
+    pub diff: Option<String>,
+}
+
+/// Inclusive, 1-based line range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LineRange {
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
+impl LineRange {
+    pub fn line_count(&self) -> u32 {
+        self.end_line
+            .saturating_sub(self.start_line)
+            .saturating_add(1)
+    }
+}
+
+/// Two files importing nearly the same paths (see [`DuplicationReport::import_duplicates`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportDuplicatePair {
+    pub(crate) a: DuplicateFile,
+    pub(crate) b: DuplicateFile,
+    pub(crate) score: f64,
+    pub(crate) shared_imports: Vec<String>,
+}
+
+impl ImportDuplicatePair {
+    pub fn a(&self) -> &DuplicateFile {
+        &self.a
+    }
+
+    pub fn b(&self) -> &DuplicateFile {
+        &self.b
+    }
+
+    /// Jaccard similarity of the two import sets, in `0..=1`.
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+
+    /// Paths both files import, sorted.
+    pub fn shared_imports(&self) -> &[String] {
+        &self.shared_imports
+    }
+}
+
+/// Two files whose whole-file MinHash signatures share at least one LSH band.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSimilarity {
+    pub(crate) a: DuplicateFile,
+    pub(crate) b: DuplicateFile,
+    pub(crate) score: f64,
+}
+
+impl FileSimilarity {
+    pub fn a(&self) -> &DuplicateFile {
+        &self.a
+    }
+
+    pub fn b(&self) -> &DuplicateFile {
+        &self.b
+    }
+
+    /// Estimated Jaccard similarity of the files' token shingles, in `0..=1`.
+    pub fn score(&self) -> f64 {
+        self.score
+    }
+}
+
+/// One reported group, by position in its report section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClusterMember {
+    pub section: ReportSection,
+    /// Index into the section's `Vec` in the [`DuplicationReport`].
+    pub index: usize,
+}
+
+/// Report groups that touch the same code: their occurrences overlap, they span exactly the
+/// same files, or they have the same content hash. Fixing one usually means fixing them all,
+/// so each cluster is one refactoring task.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloneCluster {
+    pub(crate) members: Vec<ClusterMember>,
+    pub(crate) files: Vec<FileCoverage>,
+}
+
+impl CloneCluster {
+    /// The cluster's groups, in section then index order.
+    pub fn members(&self) -> &[ClusterMember] {
+        &self.members
+    }
+
+    /// Every file the cluster touches with the lines it covers there, sorted by repo and path.
+    /// Similarity pairs count here, unlike in [`DuplicationReport::file_coverage`].
+    pub fn files(&self) -> &[FileCoverage] {
+        &self.files
+    }
+
+    /// Lines covered by the cluster across all files, each line counted once: an estimate of
+    /// the code the refactoring touches.
+    pub fn affected_lines(&self) -> u64 {
+        self.files
+            .iter()
+            .map(|file| u64::from(file.covered_lines()))
+            .sum()
+    }
+}
+
+/// Blocks connected by the similarity pairs of one section: the connected components of the
+/// graph with a node per block and an edge per pair.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimilarBlockCluster {
+    pub(crate) section: ReportSection,
+    /// Most connected block first.
+    pub(crate) blocks: Vec<DuplicateSpanOccurrence>,
+    pub(crate) pairs: Vec<usize>,
+    pub(crate) preview: String,
+    pub(crate) min_score: f64,
+    pub(crate) max_score: f64,
+}
+
+impl SimilarBlockCluster {
+    /// [`ReportSection::SimilarBlocksMinhash`] or [`ReportSection::SimilarBlocksSimhash`].
+    pub fn section(&self) -> ReportSection {
+        self.section
+    }
+
+    /// The distinct blocks of the cluster, by number of pairs they are in (most first), then
+    /// by location.
+    pub fn blocks(&self) -> &[DuplicateSpanOccurrence] {
+        &self.blocks
+    }
+
+    /// The block similar to the most others, which [`Self::preview`] shows.
+    pub fn representative(&self) -> &DuplicateSpanOccurrence {
+        &self.blocks[0]
+    }
+
+    /// Indices of the cluster's pairs in the section's `Vec`, ascending.
+    pub fn pairs(&self) -> &[usize] {
+        &self.pairs
+    }
+
+    /// Source lines of the representative block; empty when previews are off or its file
+    /// cannot be read back.
+    pub fn preview(&self) -> &str {
+        &self.preview
+    }
+
+    /// Lowest similarity score among the cluster's pairs.
+    pub fn min_score(&self) -> f64 {
+        self.min_score
+    }
+
+    /// Highest similarity score among the cluster's pairs.
+    pub fn max_score(&self) -> f64 {
+        self.max_score
+    }
+}
+
+/// How a clone group changed between two corpora (see [`crate::clone_genealogy`]). Groups are
+/// traced by their content: the section plus content hash and normalized length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GenealogyChange {
+    /// Content duplicated in the new corpus only.
+    Added,
+    /// Content duplicated in the old corpus only.
+    Removed,
+    /// Same content, more occurrences.
+    Grown,
+    /// Same content, fewer (but still several) occurrences.
+    Shrunk,
+    /// Same content and occurrence count, but not in the same files (renamed or moved code).
+    Moved,
+    /// Same content, occurrence count and files; lines may have shifted.
+    Unchanged,
+}
+
+impl GenealogyChange {
+    pub const ALL: [GenealogyChange; 6] = [
+        GenealogyChange::Added,
+        GenealogyChange::Removed,
+        GenealogyChange::Grown,
+        GenealogyChange::Shrunk,
+        GenealogyChange::Moved,
+        GenealogyChange::Unchanged,
+    ];
+
+    /// The change's JSON name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GenealogyChange::Added => "added",
+            GenealogyChange::Removed => "removed",
+            GenealogyChange::Grown => "grown",
+            GenealogyChange::Shrunk => "shrunk",
+            GenealogyChange::Moved => "moved",
+            GenealogyChange::Unchanged => "unchanged",
+        }
+    }
+}
+
+/// One span group traced from the old corpus to the new one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloneLineage {
+    pub(crate) section: ReportSection,
+    pub(crate) change: GenealogyChange,
+    pub(crate) old: Option<DuplicateSpanGroup>,
+    pub(crate) new: Option<DuplicateSpanGroup>,
+}
+
+impl CloneLineage {
+    pub fn section(&self) -> ReportSection {
+        self.section
+    }
+
+    pub fn change(&self) -> GenealogyChange {
+        self.change
+    }
+
+    /// The group in the old corpus; `None` when [`GenealogyChange::Added`].
+    pub fn before(&self) -> Option<&DuplicateSpanGroup> {
+        self.old.as_ref()
+    }
+
+    /// The group in the new corpus; `None` when [`GenealogyChange::Removed`].
+    pub fn after(&self) -> Option<&DuplicateSpanGroup> {
+        self.new.as_ref()
+    }
+}
+
+/// Every span group of two corpora, classified by how it changed from the old corpus to the
+/// new one (see [`crate::clone_genealogy`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloneGenealogy {
+    pub(crate) lineages: Vec<CloneLineage>,
+    pub(crate) old_potential_savings_lines: u64,
+    pub(crate) new_potential_savings_lines: u64,
+}
+
+impl CloneGenealogy {
+    /// The traced groups in [`ReportSection::ALL`] order; within a section, the new corpus's
+    /// groups in report order, then the removed ones.
+    pub fn lineages(&self) -> &[CloneLineage] {
+        &self.lineages
+    }
+
+    /// How many groups changed in the given way.
+    pub fn count(&self, change: GenealogyChange) -> usize {
+        self.lineages
+            .iter()
+            .filter(|lineage| lineage.change == change)
+            .count()
+    }
+
+    /// [`DuplicationReport::potential_savings_lines`] of the old corpus.
+    pub fn old_potential_savings_lines(&self) -> u64 {
+        self.old_potential_savings_lines
+    }
+
+    /// [`DuplicationReport::potential_savings_lines`] of the new corpus.
+    pub fn new_potential_savings_lines(&self) -> u64 {
+        self.new_potential_savings_lines
+    }
+}
+
+/// A reported group spanning several repos whose files carry different license headers, e.g.
+/// MIT code copied from an Apache-2.0 corpus, or a copy whose header was stripped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LicenseConflict {
+    pub(crate) group: ClusterMember,
+    pub(crate) files: Vec<LicensedFile>,
+}
+
+impl LicenseConflict {
+    /// The conflicting group, by position in its report section.
+    pub fn group(&self) -> ClusterMember {
+        self.group
+    }
+
+    /// The group's files with their licenses, sorted by repo and path.
+    pub fn files(&self) -> &[LicensedFile] {
+        &self.files
+    }
+
+    /// The distinct licenses among [`Self::files`], sorted; `None` (no license header) sorts
+    /// first.
+    pub fn licenses(&self) -> Vec<Option<&str>> {
+        let mut licenses: Vec<Option<&str>> =
+            self.files.iter().map(LicensedFile::license).collect();
+        licenses.sort_unstable();
+        licenses.dedup();
+        licenses
+    }
+}
+
+/// A file of a [`LicenseConflict`] and the license its header declares.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LicensedFile {
+    pub(crate) repo_id: usize,
+    pub(crate) repo_label: Arc<str>,
+    pub(crate) path: Arc<str>,
+    pub(crate) license: Option<Arc<str>>,
+}
+
+impl LicensedFile {
+    pub fn repo_id(&self) -> usize {
+        self.repo_id
+    }
+
+    pub fn repo_label(&self) -> &str {
+        self.repo_label.as_ref()
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.as_ref()
+    }
+
+    /// The SPDX identifier or expression of the file's license header; `None` when the header
+    /// declares no recognizable license.
+    pub fn license(&self) -> Option<&str> {
+        self.license.as_deref()
+    }
+}
+
+/// A file given by path and content instead of read from a root (see
+/// [`crate::generate_duplication_report_with_virtual_files`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualFile {
+    /// Relative, `/`- or `\`-separated path; it picks the language and appears in the report.
+    pub path: String,
+    pub content: String,
+}
+
+/// Lines of one file covered by at least one duplicate group (union across detectors).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub(crate) repo_id: usize,
+    pub(crate) repo_label: Arc<str>,
+    pub(crate) path: Arc<str>,
+    pub(crate) total_lines: u32,
+    pub(crate) covered_ranges: Vec<LineRange>,
+}
+
+impl FileCoverage {
+    pub fn repo_id(&self) -> usize {
+        self.repo_id
+    }
+
+    pub fn repo_label(&self) -> &str {
+        self.repo_label.as_ref()
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.as_ref()
+    }
+
+    pub fn total_lines(&self) -> u32 {
+        self.total_lines
+    }
+
+    /// Sorted, non-overlapping, non-adjacent covered ranges.
+    pub fn covered_ranges(&self) -> &[LineRange] {
+        &self.covered_ranges
+    }
+
+    pub fn covered_lines(&self) -> u32 {
+        self.covered_ranges
+            .iter()
+            .map(LineRange::line_count)
+            .fold(0u32, u32::saturating_add)
+    }
+
+    /// Covered fraction in `0..=1` (`0` for empty files).
+    pub fn covered_ratio(&self) -> f64 {
+        if self.total_lines == 0 {
+            return 0.0;
+        }
+        (f64::from(self.covered_lines()) / f64::from(self.total_lines)).min(1.0)
+    }
+}
+
+/// How much of one file is copied from a [`crate::CorpusIndex`] (see
+/// [`crate::CorpusIndex::file_duplication`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDuplication {
+    pub(crate) path: Arc<str>,
+    pub(crate) total_lines: u32,
+    pub(crate) covered_ranges: Vec<LineRange>,
+    pub(crate) partners: Vec<DuplicationPartner>,
+}
+
+impl FileDuplication {
+    pub fn path(&self) -> &str {
+        self.path.as_ref()
+    }
+
+    pub fn total_lines(&self) -> u32 {
+        self.total_lines
+    }
+
+    /// Sorted, non-overlapping, non-adjacent ranges that match some corpus file.
+    pub fn covered_ranges(&self) -> &[LineRange] {
+        &self.covered_ranges
+    }
+
+    pub fn covered_lines(&self) -> u32 {
+        self.covered_ranges
+            .iter()
+            .map(LineRange::line_count)
+            .fold(0u32, u32::saturating_add)
+    }
+
+    /// Covered share in `0..=100` (`0` for empty files).
+    pub fn percent(&self) -> f64 {
+        if self.total_lines == 0 {
+            return 0.0;
+        }
+        (100.0 * f64::from(self.covered_lines()) / f64::from(self.total_lines)).min(100.0)
+    }
+
+    /// Corpus files the covered lines were matched in, most shared lines first.
+    pub fn partners(&self) -> &[DuplicationPartner] {
+        &self.partners
+    }
+}
+
+/// A corpus file sharing code with the file of a [`FileDuplication`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicationPartner {
+    pub(crate) repo_id: usize,
+    pub(crate) repo_label: Arc<str>,
+    pub(crate) path: Arc<str>,
+    pub(crate) shared_lines: u32,
+}
+
+impl DuplicationPartner {
+    pub fn repo_id(&self) -> usize {
+        self.repo_id
+    }
+
+    pub fn repo_label(&self) -> &str {
+        self.repo_label.as_ref()
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.as_ref()
+    }
+
+    /// Lines of the queried file that match this partner.
+    pub fn shared_lines(&self) -> u32 {
+        self.shared_lines
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -257,4 +2024,234 @@ pub struct DuplicationReport {
     pub ast_subtree_duplicates: Vec<DuplicateSpanGroup>,
     pub similar_blocks_minhash: Vec<SimilarityPair>,
     pub similar_blocks_simhash: Vec<SimilarityPair>,
+    /// Files declaring the same public API (functions, types, exported constants), often the
+    /// first sign of a copied module whose bodies have since diverged.
+    pub symbol_duplicates: Vec<SymbolDuplicateGroup>,
+    /// File pairs whose import sections are nearly identical, which strongly correlates with
+    /// copy-pasted modules.
+    pub import_duplicates: Vec<ImportDuplicatePair>,
+    /// Whole-file duplicates among data files (fixtures, golden outputs), kept apart from the
+    /// code findings in `file_duplicates` (see [`ScanOptions::data_min_bytes`]).
+    pub data_duplicates: Vec<DuplicateGroup>,
+    /// Per-file duplicated line ranges; `None` unless [`ScanOptions::file_coverage`] is set.
+    ///
+    /// Built from every file/span group that passes the filters, before truncation, paging and
+    /// occurrence caps, so unlisted groups count too; similarity pairs are not counted since
+    /// they are near-misses rather than duplicates.
+    pub file_coverage: Option<Vec<FileCoverage>>,
+    /// Sparse pairwise file similarity; `None` unless [`ScanOptions::file_similarity`] is set.
+    ///
+    /// Not truncated by `max_report_items`, and not affected by the test or path filters.
+    pub file_similarity: Option<Vec<FileSimilarity>>,
+    /// Related groups merged into refactoring work items; `None` unless
+    /// [`ScanOptions::clone_clusters`] is set.
+    ///
+    /// Built from the reported groups (after truncation), largest cluster first.
+    pub clone_clusters: Option<Vec<CloneCluster>>,
+    /// Similarity pairs joined through the blocks they share, so a block similar to many
+    /// others shows up once; `None` unless [`ScanOptions::similarity_clusters`] is set.
+    ///
+    /// Built from the reported pairs (after truncation), minhash clusters first, then the
+    /// largest cluster first within each section.
+    pub similar_block_clusters: Option<Vec<SimilarBlockCluster>>,
+    /// Cross-repo groups whose files declare different licenses; `None` unless
+    /// [`ScanOptions::license_conflicts`] is set.
+    ///
+    /// Built from the reported file, span and similarity groups (after truncation), in section
+    /// then index order.
+    pub license_conflicts: Option<Vec<LicenseConflict>>,
+    /// Short token sequences repeated across many files, most widespread first: candidates for
+    /// a shared helper; `None` unless [`ScanOptions::idiom_min_files`] is set.
+    ///
+    /// Found below the span thresholds, so they overlap the other sections. Truncated by
+    /// `max_report_items`; not affected by the test or path filters.
+    pub common_idioms: Option<Vec<DuplicateSpanGroup>>,
+    /// How much of the tree was scanned; `None` unless [`ScanOptions::sample`] is set.
+    ///
+    /// Every other field describes the sample only.
+    pub sample: Option<ReportSample>,
+    /// The roots the report's `repo_id`s refer to, in id order.
+    pub roots: Vec<ReportRoot>,
+    /// Each section's size before [`ScanOptions::max_report_items`] truncation, in
+    /// [`ReportSection::ALL`] order.
+    pub section_totals: Vec<SectionTotal>,
+}
+
+impl DuplicationReport {
+    /// The [`SectionTotal`] of `section`.
+    pub fn section_total(&self, section: ReportSection) -> SectionTotal {
+        self.section_totals
+            .iter()
+            .copied()
+            .find(|total| total.section == section)
+            .unwrap_or(SectionTotal::empty(section))
+    }
+
+    /// Lines that deduplicating the listed span groups would remove, each line counted once.
+    ///
+    /// Every group keeps its first occurrence (by file and line) and removes the others; the
+    /// removed lines are merged per file across all span sections, so a region found by several
+    /// detectors, or by a group nested in a longer one, counts once. Occurrences truncated by
+    /// `max_occurrences_per_group` have no location and add their span length each, as in
+    /// [`DuplicateSpanGroup::potential_savings_lines`].
+    pub fn potential_savings_lines(&self) -> u64 {
+        let mut removed: HashMap<(usize, &str), Vec<(u32, u32)>> = HashMap::new();
+        let mut truncated_lines = 0u64;
+        for group in [
+            &self.code_span_duplicates,
+            &self.line_span_duplicates,
+            &self.token_span_duplicates,
+            &self.block_duplicates,
+            &self.ast_subtree_duplicates,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let Some(kept) = group
+                .occurrences
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, occ)| (occ.repo_id, occ.path.as_ref(), occ.start_line))
+                .map(|(index, _)| index)
+            else {
+                continue;
+            };
+            for (index, occ) in group.occurrences.iter().enumerate() {
+                if index != kept && occ.start_line > 0 && occ.end_line >= occ.start_line {
+                    removed
+                        .entry((occ.repo_id, occ.path.as_ref()))
+                        .or_default()
+                        .push((occ.start_line, occ.end_line));
+                }
+            }
+            let span_lines = group
+                .occurrences
+                .iter()
+                .map(|occ| u64::from(occ.end_line.saturating_sub(occ.start_line)) + 1)
+                .min()
+                .unwrap_or(0);
+            truncated_lines += group.truncated_occurrences as u64 * span_lines;
+        }
+
+        let mut lines = truncated_lines;
+        for mut ranges in removed.into_values() {
+            ranges.sort_unstable();
+            let mut counted_to = 0u32;
+            for (start, end) in ranges {
+                let start = start.max(counted_to.saturating_add(1));
+                if end >= start {
+                    lines += u64::from(end - start) + 1;
+                    counted_to = end;
+                }
+            }
+        }
+        lines
+    }
+}
+
+/// How many groups one report section had before truncation (see
+/// [`DuplicationReport::section_totals`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionTotal {
+    pub(crate) section: ReportSection,
+    pub(crate) total_groups: usize,
+    pub(crate) truncated: bool,
+    pub(crate) degraded: bool,
+    pub(crate) capped_groups: u64,
+}
+
+impl SectionTotal {
+    pub(crate) fn empty(section: ReportSection) -> Self {
+        Self {
+            section,
+            total_groups: 0,
+            truncated: false,
+            degraded: false,
+            capped_groups: 0,
+        }
+    }
+
+    pub fn section(&self) -> ReportSection {
+        self.section
+    }
+
+    /// Groups (pairs, for the similarity sections) that passed every filter, before
+    /// `max_report_items` (or the [`ScanOptions::section_offset`] /
+    /// [`ScanOptions::section_limit`] page) cut the list.
+    ///
+    /// Groups folded into another section by [`ScanOptions::deduplicate_across_detectors`] are
+    /// not counted.
+    pub fn total_groups(&self) -> usize {
+        self.total_groups
+    }
+
+    /// Whether groups before or after the reported page were left out of the section.
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Whether the detector hit [`ScanOptions::max_groups_per_detector`] and only counted the
+    /// groups past it: the section lists a subset, and [`Self::capped_groups`] counts the rest.
+    pub fn degraded(&self) -> bool {
+        self.degraded
+    }
+
+    /// Groups the detector only counted past [`ScanOptions::max_groups_per_detector`], not
+    /// included in [`Self::total_groups`]. An upper bound: once more groups are counted than
+    /// kept, a group matched several times is counted several times (see
+    /// [`ScanStats::skipped_group_cap`]).
+    pub fn capped_groups(&self) -> u64 {
+        self.capped_groups
+    }
+}
+
+/// The share of files a sampled report was built from (see [`DuplicationReport::sample`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportSample {
+    pub(crate) sampled_files: u64,
+    pub(crate) candidate_files: u64,
+}
+
+impl ReportSample {
+    /// Files that made it into the sample.
+    pub fn sampled_files(&self) -> u64 {
+        self.sampled_files
+    }
+
+    /// Files the sample was drawn from (the whole tree, or the shard with
+    /// [`ScanOptions::shard`]).
+    pub fn candidate_files(&self) -> u64 {
+        self.candidate_files
+    }
+
+    /// `sampled_files / candidate_files`, in `0..=1` (`1` when there were no candidates).
+    pub fn ratio(&self) -> f64 {
+        if self.candidate_files == 0 {
+            return 1.0;
+        }
+        self.sampled_files as f64 / self.candidate_files as f64
+    }
+
+    /// Scale a count measured on the sample up to the whole tree.
+    ///
+    /// This assumes duplication is spread evenly. A duplicate is only found when all of its
+    /// copies are sampled, so cross-file duplication is undercounted and the estimate is
+    /// closer to a lower bound.
+    pub fn extrapolate(&self, observed: u64) -> u64 {
+        let ratio = self.ratio();
+        if ratio <= 0.0 {
+            return 0;
+        }
+        (observed as f64 / ratio).round() as u64
+    }
+}
+
+/// One root of a [`DuplicationReport`] (see [`DuplicationReport::roots`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ReportRoot {
+    pub id: usize,
+    pub label: String,
+    /// The root directory as passed to the scan; `None` for roots loaded from a snapshot.
+    pub path: Option<PathBuf>,
 }
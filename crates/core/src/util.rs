@@ -1,7 +1,17 @@
-use std::collections::HashSet;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::types::DuplicateSpanOccurrence;
+use unicode_normalization::UnicodeNormalization as _;
+
+use crate::types::{
+    DuplicateGroup, DuplicateSpanGroup, DuplicateSpanOccurrence, LineRange, OccurrencePreview,
+    ScanOptions, TestCodeFilter, UnicodeNormalization,
+};
 
 #[derive(Debug, Clone)]
 pub(crate) struct NormalizedFileView<'a> {
@@ -10,6 +20,9 @@ pub(crate) struct NormalizedFileView<'a> {
     pub(crate) rel_path: Arc<str>,
     pub(crate) normalized: &'a [u32],
     pub(crate) line_map: &'a [u32],
+    /// Per position of `normalized`, how many items from it on belong to stop sequences
+    /// ([`crate::skips::StopSequences`]); empty when none apply.
+    pub(crate) stop_runs: &'a [u32],
 }
 
 #[derive(Debug)]
@@ -19,6 +32,10 @@ pub(crate) struct NormalizedCodeFile {
     pub(crate) rel_path: Arc<str>,
     pub(crate) normalized: Vec<u8>,
     pub(crate) line_starts: Vec<u32>,
+    pub(crate) test_ranges: Vec<LineRange>,
+    /// [`fnv1a64`] of the bytes that were scanned, so a file that changed before it is re-read
+    /// for columns is noticed.
+    pub(crate) content_hash: u64,
 }
 
 #[derive(Debug, Clone)]
@@ -35,7 +52,6 @@ pub(crate) struct SpanGroupBuilder {
     pub(crate) content_hash: u64,
     pub(crate) normalized_len: usize,
     pub(crate) sample: Vec<u32>,
-    pub(crate) preview: String,
     pub(crate) occurrences: Vec<DuplicateSpanOccurrence>,
     pub(crate) occurrence_keys: HashSet<(usize, usize)>,
     pub(crate) repo_ids: HashSet<usize>,
@@ -122,12 +138,116 @@ pub(crate) fn whitespace_insensitive_fingerprint(bytes: &[u8]) -> WhitespaceInse
     }
 }
 
+/// Per-file analysis deadline from [`crate::ScanOptions::max_file_time`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FileDeadline(Option<Instant>);
+
+impl FileDeadline {
+    /// A deadline that never expires.
+    pub(crate) const NONE: Self = Self(None);
+
+    pub(crate) fn start(limit: Option<Duration>) -> Self {
+        Self(limit.and_then(|limit| Instant::now().checked_add(limit)))
+    }
+
+    pub(crate) fn expired(self) -> bool {
+        self.0.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// Apply [`crate::ScanOptions::unicode_normalization`] to file bytes.
+///
+/// ASCII and non-UTF-8 input is returned unchanged. Normalization never adds or removes line
+/// breaks, so line numbers stay valid for the original file.
+pub(crate) fn normalize_unicode(bytes: &[u8], form: Option<UnicodeNormalization>) -> Cow<'_, [u8]> {
+    let Some(form) = form else {
+        return Cow::Borrowed(bytes);
+    };
+    if bytes.is_ascii() {
+        return Cow::Borrowed(bytes);
+    }
+    let Ok(text) = std::str::from_utf8(bytes) else {
+        return Cow::Borrowed(bytes);
+    };
+    let normalized: String = match form {
+        UnicodeNormalization::Nfc => text.nfc().collect(),
+        UnicodeNormalization::Nfkc => text.nfkc().collect(),
+    };
+    Cow::Owned(normalized.into_bytes())
+}
+
+/// Whether the byte at `i` ends a line: `\n`, the `\n` of `\r\n`, or a lone `\r` (classic Mac).
+///
+/// Every line count in the crate goes through this (or [`split_lines`] /
+/// [`read_line_bytes`]), so files with mixed endings get the same line numbers from every
+/// detector and preview.
+pub(crate) fn is_line_end(bytes: &[u8], i: usize) -> bool {
+    match bytes[i] {
+        b'\n' => true,
+        b'\r' => bytes.get(i + 1) != Some(&b'\n'),
+        _ => false,
+    }
+}
+
+/// Split `bytes` at each line ending (see [`is_line_end`]), without the terminators. Like
+/// `split(b'\n')`, a trailing terminator yields a final empty line.
+pub(crate) fn split_lines(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = Some(bytes);
+    std::iter::from_fn(move || {
+        let current = rest?;
+        match current.iter().position(|&b| b == b'\n' || b == b'\r') {
+            Some(pos) => {
+                let crlf = current[pos] == b'\r' && current.get(pos + 1) == Some(&b'\n');
+                rest = Some(&current[pos + 1 + usize::from(crlf)..]);
+                Some(&current[..pos])
+            }
+            None => {
+                rest = None;
+                Some(current)
+            }
+        }
+    })
+}
+
+/// [`split_lines`] over text, for the line-based heuristics that look at a file as `&str`.
+pub(crate) fn split_text_lines(text: &str) -> impl Iterator<Item = &str> {
+    split_lines(text.as_bytes())
+        .map(|line| std::str::from_utf8(line).expect("text split at ASCII line ends stays UTF-8"))
+}
+
+/// Read one line into `buf` (appending, without its terminator), ending at `\n`, `\r\n` or a
+/// lone `\r`. Returns `false` at end of input.
+pub(crate) fn read_line_bytes(reader: &mut impl BufRead, buf: &mut Vec<u8>) -> io::Result<bool> {
+    let mut read_any = false;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(read_any);
+        }
+        read_any = true;
+        let Some(pos) = available.iter().position(|&b| b == b'\n' || b == b'\r') else {
+            let len = available.len();
+            buf.extend_from_slice(available);
+            reader.consume(len);
+            continue;
+        };
+        let cr = available[pos] == b'\r';
+        buf.extend_from_slice(&available[..pos]);
+        reader.consume(pos + 1);
+        // The `\n` of a `\r\n` may start the next buffer.
+        if cr && reader.fill_buf()?.first() == Some(&b'\n') {
+            reader.consume(1);
+        }
+        return Ok(true);
+    }
+}
+
 pub(crate) fn normalize_for_code_spans(bytes: &[u8]) -> NormalizedText {
     let mut chars = Vec::new();
     let mut line_starts: Vec<u32> = vec![0];
 
-    for &b in bytes {
-        if b == b'\n' {
+    for (i, &b) in bytes.iter().enumerate() {
+        if is_line_end(bytes, i) {
             line_starts.push(u32::try_from(chars.len()).unwrap_or(u32::MAX));
             continue;
         }
@@ -139,6 +259,51 @@ pub(crate) fn normalize_for_code_spans(bytes: &[u8]) -> NormalizedText {
     NormalizedText { chars, line_starts }
 }
 
+#[derive(Debug)]
+pub(crate) struct LineNormalizedText {
+    pub(crate) line_tokens: Vec<u32>,
+    pub(crate) line_lines: Vec<u32>,
+    pub(crate) line_lens: Vec<usize>,
+}
+
+/// One hash per non-empty line of identifier characters, for the line-span detector.
+pub(crate) fn normalize_lines_for_dup_detection(bytes: &[u8]) -> LineNormalizedText {
+    let mut line: u32 = 1;
+    let mut current: Vec<u32> = Vec::new();
+
+    let mut line_tokens = Vec::new();
+    let mut line_lines = Vec::new();
+    let mut line_lens = Vec::new();
+
+    for (i, &b) in bytes.iter().enumerate() {
+        if is_line_end(bytes, i) {
+            if !current.is_empty() {
+                line_lens.push(current.len());
+                line_tokens.push(fold_u64_to_u32(fnv1a64_u32(&current)));
+                line_lines.push(line);
+            }
+            current.clear();
+            line = line.saturating_add(1);
+            continue;
+        }
+        if b.is_ascii_alphanumeric() || b == b'_' {
+            current.push(u32::from(b));
+        }
+    }
+
+    if !current.is_empty() {
+        line_lens.push(current.len());
+        line_tokens.push(fold_u64_to_u32(fnv1a64_u32(&current)));
+        line_lines.push(line);
+    }
+
+    LineNormalizedText {
+        line_tokens,
+        line_lines,
+        line_lens,
+    }
+}
+
 pub(crate) fn fold_u64_to_u32(value: u64) -> u32 {
     (value as u32) ^ ((value >> 32) as u32)
 }
@@ -413,6 +578,166 @@ pub(crate) fn make_preview_ascii(codepoints: &[u8], max_len: usize) -> String {
         .collect()
 }
 
+/// Default preview length of code-span groups (normalized code chars).
+pub(crate) const CODE_SPAN_PREVIEW_CHARS: usize = 80;
+
+/// Default preview length of groups previewed from file lines (line/token spans, blocks).
+pub(crate) const LINE_PREVIEW_CHARS: usize = 120;
+
+/// Read lines `start_line..=end_line` of `path` (plus `context_lines` on either side) as a
+/// preview of at most `max_chars` chars; empty when the file cannot be read.
+pub(crate) fn preview_from_file_lines(
+    path: &Path,
+    start_line: u32,
+    end_line: u32,
+    context_lines: usize,
+    max_chars: usize,
+) -> String {
+    match fs::File::open(path) {
+        Ok(file) => preview_from_lines(
+            BufReader::new(file),
+            start_line,
+            end_line,
+            context_lines,
+            max_chars,
+        ),
+        Err(_) => String::new(),
+    }
+}
+
+/// [`preview_from_file_lines`] over the lines of `reader`.
+pub(crate) fn preview_from_lines(
+    mut reader: impl BufRead,
+    start_line: u32,
+    end_line: u32,
+    context_lines: usize,
+    max_chars: usize,
+) -> String {
+    if start_line == 0 || end_line == 0 || start_line > end_line || max_chars == 0 {
+        return String::new();
+    }
+    let context = u32::try_from(context_lines).unwrap_or(u32::MAX);
+    let start_line = start_line.saturating_sub(context).max(1);
+    let end_line = end_line.saturating_add(context);
+
+    let mut out = String::new();
+    let mut out_chars = 0usize;
+    let mut line_no: u32 = 1;
+    let mut buf: Vec<u8> = Vec::new();
+
+    loop {
+        buf.clear();
+        match read_line_bytes(&mut reader, &mut buf) {
+            Ok(true) => {}
+            Ok(false) | Err(_) => break,
+        }
+
+        if line_no >= start_line && line_no <= end_line {
+            if !out.is_empty() {
+                out.push('\n');
+                out_chars += 1;
+            }
+            for ch in String::from_utf8_lossy(&buf).chars() {
+                if out_chars >= max_chars {
+                    break;
+                }
+                out.push(ch);
+                out_chars += 1;
+            }
+            if out_chars >= max_chars {
+                break;
+            }
+        }
+
+        if line_no >= end_line {
+            break;
+        }
+        line_no = line_no.saturating_add(1);
+    }
+
+    out
+}
+
+/// Most [`DuplicateSpanGroup::occurrence_previews`] entries kept per group.
+pub(crate) const MAX_OCCURRENCE_PREVIEWS: usize = 4;
+
+/// Fill [`DuplicateSpanGroup::occurrence_previews`] for the first occurrence of every repo other
+/// than the first occurrence's, using `preview_of` (empty previews are skipped). Groups that
+/// already have some are left alone.
+pub(crate) fn fill_occurrence_previews(
+    groups: &mut [DuplicateSpanGroup],
+    mut preview_of: impl FnMut(&DuplicateSpanOccurrence) -> String,
+) {
+    for group in groups {
+        if !group.occurrence_previews.is_empty() {
+            continue;
+        }
+        let Some(first) = group.occurrences.first() else {
+            continue;
+        };
+        let mut seen_repos = vec![first.repo_id];
+        for (index, occ) in group.occurrences.iter().enumerate().skip(1) {
+            if group.occurrence_previews.len() >= MAX_OCCURRENCE_PREVIEWS {
+                break;
+            }
+            if seen_repos.contains(&occ.repo_id) {
+                continue;
+            }
+            seen_repos.push(occ.repo_id);
+            let preview = preview_of(occ);
+            if !preview.is_empty() {
+                group.occurrence_previews.push(OccurrencePreview {
+                    occurrence: index,
+                    preview,
+                });
+            }
+        }
+    }
+}
+
+/// Build previews for code-span groups once they have been filtered and truncated; groups that
+/// already have one (read from file lines) are left alone.
+///
+/// Every occurrence of a group has the same normalized text, so the first one is read back from
+/// its file view (via `code_char_cols`; call this before [`resolve_code_span_columns`]).
+pub(crate) fn fill_code_span_previews(
+    groups: &mut [DuplicateSpanGroup],
+    files: &[NormalizedCodeFileView<'_>],
+    max_chars: usize,
+) {
+    let by_path: HashMap<(usize, &str), &NormalizedCodeFileView<'_>> = files
+        .iter()
+        .map(|file| ((file.repo_id, file.rel_path.as_ref()), file))
+        .collect();
+    for group in groups {
+        if !group.preview.is_empty() {
+            continue;
+        }
+        let Some(occ) = group.occurrences.first() else {
+            continue;
+        };
+        let (Some(file), Some((start_in_line, _))) = (
+            by_path.get(&(occ.repo_id, occ.path.as_ref())),
+            occ.code_char_cols,
+        ) else {
+            continue;
+        };
+        let line_start = file
+            .line_starts
+            .get(occ.start_line as usize - 1)
+            .copied()
+            .unwrap_or(0);
+        let start = (line_start + start_in_line) as usize;
+        let end = start
+            .saturating_add(group.normalized_len.min(max_chars))
+            .min(file.normalized.len());
+        group.preview = make_preview_ascii(
+            file.normalized.get(start..end).unwrap_or_default(),
+            max_chars,
+        );
+    }
+}
+
 pub(crate) fn add_occurrence_view(
     builder: &mut SpanGroupBuilder,
     file: &NormalizedFileView<'_>,
@@ -446,9 +771,324 @@ pub(crate) fn add_occurrence_view(
         path: Arc::clone(&file.rel_path),
         start_line,
         end_line,
+        start_col: 0,
+        end_col: 0,
+        code_char_cols: None,
+        is_test: false,
+        scope: None,
+        last_modified: None,
+    });
+}
+
+/// The bytes of the file at `path` when they still hash to `content_hash`, the hash of the bytes
+/// that were scanned; `None` when it cannot be read or has changed since.
+pub(crate) fn read_unchanged(path: &Path, content_hash: u64) -> Option<Vec<u8>> {
+    let bytes = fs::read(path).ok()?;
+    (fnv1a64(&bytes) == content_hash).then_some(bytes)
+}
+
+/// Turn the normalized-char offsets recorded by the code-span detector into byte columns from
+/// the start/end lines of `bytes`, the occurrence's file as scanned (see [`read_unchanged`]).
+///
+/// Without `bytes` (stale or unreadable files) the columns stay unknown.
+pub(crate) fn resolve_code_span_columns(
+    occ: &mut DuplicateSpanOccurrence,
+    bytes: Option<&[u8]>,
+    unicode_normalization: Option<UnicodeNormalization>,
+) {
+    let Some((start_idx, end_idx)) = occ.code_char_cols.take() else {
+        return;
+    };
+    let Some(bytes) = bytes else {
+        return;
+    };
+    let mut lines = split_lines(bytes);
+    let start_line = lines.nth(occ.start_line.saturating_sub(1) as usize);
+    let end_line = if occ.end_line == occ.start_line {
+        start_line
+    } else {
+        lines.nth((occ.end_line - occ.start_line - 1) as usize)
+    };
+    // Normalization may turn non-ASCII characters into code chars the raw line lacks.
+    let normalized_away = |line: &[u8]| unicode_normalization.is_some() && !line.is_ascii();
+    if start_line.is_some_and(normalized_away) || end_line.is_some_and(normalized_away) {
+        return;
+    }
+    let (Some(start_col), Some(end_col)) = (
+        start_line.and_then(|line| byte_col_of_code_char(line, start_idx)),
+        end_line.and_then(|line| byte_col_of_code_char(line, end_idx)),
+    ) else {
+        return;
+    };
+    occ.start_col = start_col;
+    occ.end_col = end_col;
+}
+
+/// 1-based byte column of the `index`-th (0-based) byte kept by [`normalize_for_code_spans`].
+fn byte_col_of_code_char(line: &[u8], index: u32) -> Option<u32> {
+    let (pos, _) = line
+        .iter()
+        .enumerate()
+        .filter(|&(_, &b)| b.is_ascii_alphanumeric() || b == b'_')
+        .nth(index as usize)?;
+    u32::try_from(pos + 1).ok()
+}
+
+/// Rewrites root-relative result paths to `root.join(path)` for
+/// [`crate::ScanOptions::emit_absolute_paths`], then applies
+/// [`crate::ScanOptions::path_prefix_map`].
+///
+/// Only `repo_id`s below `roots.len()` are made absolute; others (snapshot roots) stay
+/// relative.
+pub(crate) struct OutputPaths<'a> {
+    roots: &'a [PathBuf],
+    options: &'a ScanOptions,
+    cache: HashMap<(usize, Arc<str>), Arc<str>>,
+}
+
+impl<'a> OutputPaths<'a> {
+    pub(crate) fn new(roots: &'a [PathBuf], options: &'a ScanOptions) -> Self {
+        Self {
+            roots,
+            options,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Whether [`Self::rewrite`] changes any path.
+    pub(crate) fn is_active(options: &ScanOptions) -> bool {
+        options.emit_absolute_paths || !options.path_prefix_map.is_empty()
+    }
+
+    pub(crate) fn rewrite(&mut self, repo_id: usize, path: &mut Arc<str>) {
+        let (roots, options) = (self.roots, self.options);
+        let rewritten = self
+            .cache
+            .entry((repo_id, Arc::clone(path)))
+            .or_insert_with(|| {
+                let full = match roots.get(repo_id) {
+                    Some(root) if options.emit_absolute_paths => root.join(path.as_ref()),
+                    _ => PathBuf::from(path.as_ref()),
+                };
+                Arc::from(options.map_output_path(&full).to_string_lossy().as_ref())
+            });
+        *path = Arc::clone(rewritten);
+    }
+
+    pub(crate) fn rewrite_groups(&mut self, groups: &mut [DuplicateGroup]) {
+        for file in groups.iter_mut().flat_map(|g| g.files.iter_mut()) {
+            self.rewrite(file.repo_id, &mut file.path);
+        }
+    }
+
+    pub(crate) fn rewrite_span_groups(&mut self, groups: &mut [DuplicateSpanGroup]) {
+        for occ in groups.iter_mut().flat_map(|g| g.occurrences.iter_mut()) {
+            self.rewrite(occ.repo_id, &mut occ.path);
+        }
+    }
+}
+
+/// Heuristic test-code check on a root-relative, `/`-separated path: test directories
+/// (`test/`, `tests/`, `__tests__/`, `spec/`) and common test file names (`*_test.go`,
+/// `test_*.py`, `*.test.ts`, `*.spec.js`, `FooTest.java`, ...).
+pub(crate) fn is_test_path(path: &str) -> bool {
+    let mut segments = path.rsplit('/');
+    let file_name = segments.next().unwrap_or(path);
+    if segments.any(|dir| matches!(dir, "test" | "tests" | "__tests__" | "spec")) {
+        return true;
+    }
+    let stem = file_name.split('.').next().unwrap_or(file_name);
+    stem.ends_with("_test")
+        || stem.ends_with("_spec")
+        || stem.starts_with("test_")
+        || stem.ends_with("Test")
+        || stem.ends_with("Tests")
+        || file_name.contains(".test.")
+        || file_name.contains(".spec.")
+}
+
+/// Line ranges of items annotated with `#[cfg(test)]`, from the attribute to the end of the
+/// item (its matching `}`, or the `;` of a braceless item).
+pub(crate) fn cfg_test_line_ranges(bytes: &[u8]) -> Vec<LineRange> {
+    const ATTR: &[u8] = b"#[cfg(test)]";
+
+    let mut out = Vec::new();
+    let mut line: u32 = 1;
+    let mut i = 0usize;
+    while i < bytes.len() {
+        if is_line_end(bytes, i) {
+            line = line.saturating_add(1);
+            i += 1;
+            continue;
+        }
+        if !bytes[i..].starts_with(ATTR) {
+            i += 1;
+            continue;
+        }
+
+        let start_line = line;
+        let mut depth = 0usize;
+        i += ATTR.len();
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\n' | b'\r' if is_line_end(bytes, i) => line = line.saturating_add(1),
+                b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                    while i + 1 < bytes.len() && !is_line_end(bytes, i + 1) {
+                        i += 1;
+                    }
+                }
+                b'"' => {
+                    i += 1;
+                    while i < bytes.len() && bytes[i] != b'"' {
+                        if bytes[i] == b'\\' {
+                            i += 1;
+                            if i == bytes.len() {
+                                break;
+                            }
+                        }
+                        if is_line_end(bytes, i) {
+                            line = line.saturating_add(1);
+                        }
+                        i += 1;
+                    }
+                }
+                b'{' => depth += 1,
+                b'}' if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                b';' if depth == 0 => break,
+                _ => {}
+            }
+            i += 1;
+        }
+        out.push(LineRange {
+            start_line,
+            end_line: line,
+        });
+        i += 1;
+    }
+    out
+}
+
+/// Test regions of scanned files, keyed by `(repo_id, path)`; files without any are omitted.
+pub(crate) type TestRegions<'a> = HashMap<(usize, &'a str), &'a [LineRange]>;
+
+/// Set `is_test` on occurrences from their path and the `#[cfg(test)]` regions of their file.
+pub(crate) fn mark_test_occurrences<'o>(
+    occurrences: impl IntoIterator<Item = &'o mut DuplicateSpanOccurrence>,
+    regions: &TestRegions<'_>,
+) {
+    for occ in occurrences {
+        let in_region = regions
+            .get(&(occ.repo_id, occ.path.as_ref()))
+            .is_some_and(|ranges| {
+                ranges
+                    .iter()
+                    .any(|r| r.start_line <= occ.start_line && occ.start_line <= r.end_line)
+            });
+        occ.is_test = in_region || is_test_path(&occ.path);
+    }
+}
+
+/// Drop the files rejected by `filter`, then the groups that no longer form a duplicate.
+pub(crate) fn apply_test_filter_to_groups(
+    groups: &mut Vec<DuplicateGroup>,
+    filter: Option<TestCodeFilter>,
+    cross_repo_only: bool,
+) {
+    let Some(filter) = filter else {
+        return;
+    };
+    groups.retain_mut(|g| {
+        g.files.retain(|f| filter.keeps(f.is_test));
+        let repos: HashSet<usize> = g.files.iter().map(|f| f.repo_id).collect();
+        g.files.len() >= 2 && (!cross_repo_only || repos.len() >= 2)
+    });
+}
+
+/// Drop the occurrences rejected by `filter`, then the groups that no longer form a duplicate.
+pub(crate) fn apply_test_filter_to_span_groups(
+    groups: &mut Vec<DuplicateSpanGroup>,
+    filter: Option<TestCodeFilter>,
+    cross_repo_only: bool,
+) {
+    let Some(filter) = filter else {
+        return;
+    };
+    groups.retain_mut(|g| {
+        g.occurrences.retain(|o| filter.keeps(o.is_test));
+        let repos: HashSet<usize> = g.occurrences.iter().map(|o| o.repo_id).collect();
+        g.occurrences.len() >= 2 && (!cross_repo_only || repos.len() >= 2)
     });
 }
 
+/// Fill [`DuplicateSpanGroup::duplicated_lines`] / `potential_savings_lines`; call before
+/// occurrences are capped so the estimate covers every occurrence.
+pub(crate) fn fill_span_group_line_stats(groups: &mut [DuplicateSpanGroup]) {
+    for group in groups {
+        let span_lines = group
+            .occurrences
+            .iter()
+            .map(|o| u64::from(o.end_line.saturating_sub(o.start_line)) + 1)
+            .min()
+            .unwrap_or(0);
+        let count = (group.occurrences.len() + group.truncated_occurrences) as u64;
+        group.duplicated_lines = count * span_lines;
+        group.potential_savings_lines = count.saturating_sub(1) * span_lines;
+    }
+}
+
+pub(crate) fn retain_groups_with_min_occurrences(
+    groups: &mut Vec<DuplicateGroup>,
+    min_occurrences: usize,
+) {
+    if min_occurrences <= 2 {
+        return;
+    }
+    groups.retain(|g| g.files.len() >= min_occurrences);
+}
+
+pub(crate) fn retain_span_groups_with_min_occurrences(
+    groups: &mut Vec<DuplicateSpanGroup>,
+    min_occurrences: usize,
+) {
+    if min_occurrences <= 2 {
+        return;
+    }
+    groups.retain(|g| g.occurrences.len() >= min_occurrences);
+}
+
+pub(crate) fn cap_group_occurrences(groups: &mut [DuplicateGroup], max_occurrences: Option<usize>) {
+    let Some(max_occurrences) = max_occurrences else {
+        return;
+    };
+    for group in groups {
+        if group.files.len() > max_occurrences {
+            group.truncated_files = group.truncated_files + group.files.len() - max_occurrences;
+            group.files.truncate(max_occurrences);
+        }
+    }
+}
+
+pub(crate) fn cap_span_group_occurrences(
+    groups: &mut [DuplicateSpanGroup],
+    max_occurrences: Option<usize>,
+) {
+    let Some(max_occurrences) = max_occurrences else {
+        return;
+    };
+    for group in groups {
+        if group.occurrences.len() > max_occurrences {
+            group.truncated_occurrences =
+                group.truncated_occurrences + group.occurrences.len() - max_occurrences;
+            group.occurrences.truncate(max_occurrences);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -471,4 +1111,92 @@ mod tests {
         assert_eq!(fp.prefix, *b"abcdefghijklmnop");
         assert_eq!(fp.suffix, *b"cdefghijklmnopqr");
     }
+
+    #[test]
+    fn byte_col_skips_non_code_chars() {
+        let line = b"  a = f(b_1);";
+        assert_eq!(byte_col_of_code_char(line, 0), Some(3));
+        assert_eq!(byte_col_of_code_char(line, 1), Some(7));
+        assert_eq!(byte_col_of_code_char(line, 4), Some(11));
+        assert_eq!(byte_col_of_code_char(line, 5), None);
+    }
+
+    #[test]
+    fn code_span_columns_need_the_scanned_bytes() {
+        let path = std::env::temp_dir().join(format!("dcc-columns-{}.rs", std::process::id()));
+        let scanned = b"x\n  a = f(b_1);\n";
+        fs::write(&path, scanned).unwrap();
+        let changed = read_unchanged(&path, fnv1a64(b"other"));
+        let bytes = read_unchanged(&path, fnv1a64(scanned));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(changed, None);
+        assert_eq!(bytes.as_deref(), Some(&scanned[..]));
+
+        let occurrence = || DuplicateSpanOccurrence {
+            repo_id: 0,
+            repo_label: "r".into(),
+            path: "a.rs".into(),
+            start_line: 2,
+            end_line: 2,
+            start_col: 0,
+            end_col: 0,
+            code_char_cols: Some((1, 4)),
+            is_test: false,
+            scope: None,
+            last_modified: None,
+        };
+        let mut occ = occurrence();
+        resolve_code_span_columns(&mut occ, bytes.as_deref(), None);
+        assert_eq!(
+            (occ.start_col, occ.end_col, occ.code_char_cols),
+            (7, 11, None)
+        );
+        let mut stale = occurrence();
+        resolve_code_span_columns(&mut stale, changed.as_deref(), None);
+        assert_eq!(
+            (stale.start_col, stale.end_col, stale.code_char_cols),
+            (0, 0, None)
+        );
+    }
+
+    #[test]
+    fn test_paths_are_detected_heuristically() {
+        for path in [
+            "tests/api.rs",
+            "src/__tests__/button.tsx",
+            "pkg/server_test.go",
+            "test_utils.py",
+            "src/button.test.ts",
+            "app/models/user.spec.js",
+            "src/main/java/FooTest.java",
+        ] {
+            assert!(is_test_path(path), "{path}");
+        }
+        for path in [
+            "src/lib.rs",
+            "src/contest.rs",
+            "latest/main.go",
+            "testing.py",
+        ] {
+            assert!(!is_test_path(path), "{path}");
+        }
+    }
+
+    #[test]
+    fn cfg_test_ranges_cover_the_annotated_item() {
+        let src = b"fn a() {}\n\n#[cfg(test)]\nmod tests {\n    fn s() { let _ = \"}\"; }\n}\n\n#[cfg(test)]\nuse std::fs;\nfn b() {}\n";
+        assert_eq!(
+            cfg_test_line_ranges(src),
+            [
+                LineRange {
+                    start_line: 3,
+                    end_line: 6
+                },
+                LineRange {
+                    start_line: 8,
+                    end_line: 9
+                },
+            ]
+        );
+    }
 }
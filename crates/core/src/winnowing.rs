@@ -25,12 +25,83 @@ struct MatchKey {
 
 const MAX_BUCKET: usize = 512;
 
+/// Files longer than this many normalized units (chars, tokens or lines) get a wider window
+/// under [`crate::ScanOptions::adaptive_window`].
+const ADAPTIVE_WINDOW_MIN_LEN: usize = 16 * 1024;
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct WinnowingParams {
     pub(crate) min_len: usize,
     pub(crate) fingerprint_len: usize,
     pub(crate) window_size: usize,
+    /// Largest multiple of `window_size` a long file's window may grow to (`1` = fixed).
+    pub(crate) max_window_growth: usize,
     pub(crate) cross_repo_only: bool,
+    /// Groups held before new ones are only counted (see
+    /// [`crate::ScanOptions::max_groups_per_detector`]).
+    pub(crate) max_groups: Option<usize>,
+}
+
+impl WinnowingParams {
+    /// The window for a file of `len` units: `window_size`, plus one more `window_size` for
+    /// every doubling past [`ADAPTIVE_WINDOW_MIN_LEN`], up to `max_window_growth` times.
+    ///
+    /// Any two windows share the minimum of a common run at least as long as the larger one,
+    /// so a match of `max_window_growth × window_size + fingerprint_len - 1` units is still
+    /// always seeded, whatever the lengths of its files.
+    pub(crate) fn window_for_len(self, len: usize) -> usize {
+        if self.max_window_growth <= 1 || len <= ADAPTIVE_WINDOW_MIN_LEN {
+            return self.window_size;
+        }
+        let doublings = (len / ADAPTIVE_WINDOW_MIN_LEN).ilog2() as usize;
+        self.window_size
+            .saturating_mul((doublings + 1).min(self.max_window_growth))
+    }
+}
+
+/// Counts the groups dropped once [`WinnowingParams::max_groups`] groups are held, in
+/// [`ScanStats::skipped_group_cap`].
+///
+/// Dropped groups are told apart by key, but at most `max_groups` keys are remembered, so the
+/// counting never holds more keys than there are kept groups. Once the keys are full, each
+/// match of a group not among them counts again, so the count is then an upper bound.
+struct CappedGroups {
+    keys: HashSet<(u64, usize)>,
+    max_keys: usize,
+}
+
+impl CappedGroups {
+    fn new(max_groups: Option<usize>) -> Self {
+        Self {
+            keys: HashSet::new(),
+            max_keys: max_groups.unwrap_or(0),
+        }
+    }
+
+    fn count(&mut self, key: (u64, usize), stats: &mut ScanStats) {
+        let seen = if self.keys.len() < self.max_keys {
+            !self.keys.insert(key)
+        } else {
+            self.keys.contains(&key)
+        };
+        if !seen {
+            stats.skipped_group_cap = stats.skipped_group_cap.saturating_add(1);
+        }
+    }
+}
+
+/// The fingerprint buckets to match, one per fingerprint. Under a group cap, which groups are
+/// kept depends on the order the buckets are visited in, so they are then visited in
+/// fingerprint order rather than hash map order.
+fn fingerprint_buckets(
+    fingerprints: HashMap<u64, Vec<FingerprintOcc>>,
+    max_groups: Option<usize>,
+) -> Vec<Vec<FingerprintOcc>> {
+    let mut buckets: Vec<(u64, Vec<FingerprintOcc>)> = fingerprints.into_iter().collect();
+    if max_groups.is_some() {
+        buckets.sort_unstable_by_key(|&(hash, _)| hash);
+    }
+    buckets.into_iter().map(|(_, occs)| occs).collect()
 }
 
 fn truncate_bucket_by_repo(
@@ -78,12 +149,46 @@ fn truncate_bucket_by_repo(
     out
 }
 
+/// Whether a fingerprint bucket has occurrences in at least two repos. With `cross_repo_only`,
+/// other buckets cannot yield a match and are skipped before any pair work (or truncation).
+fn spans_multiple_repos(
+    occs: &[FingerprintOcc],
+    repo_id_for_file: impl Fn(usize) -> usize,
+) -> bool {
+    let mut repo_ids = occs.iter().map(|o| repo_id_for_file(o.file_id));
+    let first = repo_ids.next();
+    repo_ids.any(|repo_id| Some(repo_id) != first)
+}
+
+/// For each entry of `occs`, the index of its first pairing partner: the next entry, or with
+/// `cross_repo_only` the first entry of the next repo. In that case `occs` is sorted by repo
+/// (stably) first, so same-repo pairs are never expanded.
+fn pair_starts(
+    occs: &mut [FingerprintOcc],
+    repo_id_for_file: impl Fn(usize) -> usize,
+    params: WinnowingParams,
+) -> Vec<usize> {
+    if !params.cross_repo_only {
+        return (1..=occs.len()).collect();
+    }
+
+    occs.sort_by_key(|o| repo_id_for_file(o.file_id));
+    let mut starts = vec![occs.len(); occs.len()];
+    for i in (0..occs.len().saturating_sub(1)).rev() {
+        starts[i] = if repo_id_for_file(occs[i].file_id) == repo_id_for_file(occs[i + 1].file_id) {
+            starts[i + 1]
+        } else {
+            i + 1
+        };
+    }
+    starts
+}
+
 #[derive(Debug)]
 struct AsciiSpanGroupBuilder {
     content_hash: u64,
     normalized_len: usize,
     sample: Vec<u8>,
-    preview: String,
     occurrences: Vec<DuplicateSpanOccurrence>,
     occurrence_keys: HashSet<(usize, usize)>,
     repo_ids: HashSet<usize>,
@@ -103,8 +208,20 @@ fn add_occurrence_ascii_view(
         return;
     }
 
+    let end = start.saturating_add(len - 1);
     let start_line = line_for_pos(file.line_starts, start);
-    let end_line = line_for_pos(file.line_starts, start.saturating_add(len - 1));
+    let end_line = line_for_pos(file.line_starts, end);
+    // Offsets of the first/last char within their lines; resolved to byte columns later.
+    let index_in_line = |pos: usize, line: u32| {
+        let line_start = file
+            .line_starts
+            .get(line as usize - 1)
+            .copied()
+            .unwrap_or(0);
+        u32::try_from(pos)
+            .unwrap_or(u32::MAX)
+            .saturating_sub(line_start)
+    };
 
     builder.repo_ids.insert(file.repo_id);
     builder.occurrences.push(DuplicateSpanOccurrence {
@@ -113,6 +230,15 @@ fn add_occurrence_ascii_view(
         path: Arc::clone(&file.rel_path),
         start_line,
         end_line,
+        start_col: 0,
+        end_col: 0,
+        code_char_cols: Some((
+            index_in_line(start, start_line),
+            index_in_line(end, end_line),
+        )),
+        is_test: false,
+        scope: None,
+        last_modified: None,
     });
 }
 
@@ -150,8 +276,14 @@ fn finalize_span_groups_ascii(
             out.push(DuplicateSpanGroup {
                 content_hash: builder.content_hash,
                 normalized_len: builder.normalized_len,
-                preview: builder.preview,
+                preview: String::new(),
                 occurrences: builder.occurrences,
+                truncated_occurrences: 0,
+                duplicated_lines: 0,
+                potential_savings_lines: 0,
+                occurrence_previews: Vec::new(),
+                also_detected_by: Vec::new(),
+                quality: None,
             });
         }
     }
@@ -169,8 +301,8 @@ fn finalize_span_groups_ascii(
 pub(crate) fn detect_duplicate_span_groups_winnowing_ascii<'a>(
     files: &[NormalizedCodeFileView<'a>],
     params: WinnowingParams,
+    skip: &HashSet<u64>,
     accept_match: impl Fn(usize, usize, usize) -> bool,
-    preview_from_occurrence: impl Fn(usize, u32, u32, &[u8]) -> String,
     stats: &mut ScanStats,
 ) -> Vec<DuplicateSpanGroup> {
     if files.is_empty()
@@ -186,9 +318,17 @@ pub(crate) fn detect_duplicate_span_groups_winnowing_ascii<'a>(
         if file.normalized.len() < params.min_len {
             continue;
         }
-        for (hash, pos) in
-            winnowed_fingerprints_u8(file.normalized, params.fingerprint_len, params.window_size)
-        {
+        let prints = winnowed_fingerprints_u8(
+            file.normalized,
+            params.fingerprint_len,
+            params.window_for_len(file.normalized.len()),
+        );
+        stats.fingerprints = stats.fingerprints.saturating_add(prints.len() as u64);
+        for (hash, pos) in prints {
+            // Known boilerplate (`ScanOptions::builtin_skips` / `skip_snippets`) seeds no match.
+            if skip.contains(&hash) {
+                continue;
+            }
             fingerprints
                 .entry(hash)
                 .or_default()
@@ -198,11 +338,17 @@ pub(crate) fn detect_duplicate_span_groups_winnowing_ascii<'a>(
 
     let mut seen_matches: HashSet<MatchKey> = HashSet::new();
     let mut groups: HashMap<(u64, usize), Vec<AsciiSpanGroupBuilder>> = HashMap::new();
+    let mut group_count = 0usize;
+    let mut capped = CappedGroups::new(params.max_groups);
 
-    for mut occs in fingerprints.into_values() {
+    for mut occs in fingerprint_buckets(fingerprints, params.max_groups) {
         if occs.len() <= 1 {
             continue;
         }
+        if params.cross_repo_only && !spans_multiple_repos(&occs, |file_id| files[file_id].repo_id)
+        {
+            continue;
+        }
         let original_len = occs.len();
         if original_len > MAX_BUCKET {
             occs = truncate_bucket_by_repo(occs, |file_id| files[file_id].repo_id, MAX_BUCKET);
@@ -210,17 +356,15 @@ pub(crate) fn detect_duplicate_span_groups_winnowing_ascii<'a>(
                 .skipped_bucket_truncated
                 .saturating_add((original_len - occs.len()) as u64);
         }
+        let pair_starts = pair_starts(&mut occs, |file_id| files[file_id].repo_id, params);
 
         for i in 0..occs.len() {
-            for j in (i + 1)..occs.len() {
+            for j in pair_starts[i]..occs.len() {
                 let a = occs[i];
                 let b = occs[j];
                 if a.file_id == b.file_id && a.pos == b.pos {
                     continue;
                 }
-                if params.cross_repo_only && files[a.file_id].repo_id == files[b.file_id].repo_id {
-                    continue;
-                }
 
                 let (start_a, start_b, len) = match maximal_match_u8(
                     files[a.file_id].normalized,
@@ -265,6 +409,15 @@ pub(crate) fn detect_duplicate_span_groups_winnowing_ascii<'a>(
                 let sample_slice = &files[file_a].normalized[start_a..start_a + len];
                 let content_hash = fnv1a64_u8_as_u32(sample_slice);
 
+                if params.max_groups.is_some_and(|max| group_count >= max)
+                    && !groups.get(&(content_hash, len)).is_some_and(|bucket| {
+                        bucket.iter().any(|g| g.sample.as_slice() == sample_slice)
+                    })
+                {
+                    capped.count((content_hash, len), stats);
+                    continue;
+                }
+
                 let bucket = groups.entry((content_hash, len)).or_default();
                 let builder = match bucket
                     .iter_mut()
@@ -272,16 +425,11 @@ pub(crate) fn detect_duplicate_span_groups_winnowing_ascii<'a>(
                 {
                     Some(existing) => existing,
                     None => {
-                        let start_line = line_for_pos(files[file_a].line_starts, start_a);
-                        let end_line = line_for_pos(files[file_a].line_starts, start_a + len - 1);
-                        let preview =
-                            preview_from_occurrence(file_a, start_line, end_line, sample_slice);
-
+                        group_count += 1;
                         bucket.push(AsciiSpanGroupBuilder {
                             content_hash,
                             normalized_len: len,
                             sample: sample_slice.to_vec(),
-                            preview,
                             occurrences: Vec::new(),
                             occurrence_keys: HashSet::new(),
                             repo_ids: HashSet::new(),
@@ -303,8 +451,8 @@ pub(crate) fn detect_duplicate_span_groups_winnowing_ascii<'a>(
 pub(crate) fn detect_duplicate_span_groups_winnowing<'a>(
     files: &[NormalizedFileView<'a>],
     params: WinnowingParams,
+    skip: &HashSet<u64>,
     accept_match: impl Fn(usize, usize, usize) -> bool,
-    preview_from_occurrence: impl Fn(usize, u32, u32, &[u32]) -> String,
     stats: &mut ScanStats,
 ) -> Vec<DuplicateSpanGroup> {
     if files.is_empty()
@@ -320,9 +468,25 @@ pub(crate) fn detect_duplicate_span_groups_winnowing<'a>(
         if file.normalized.len() < params.min_len {
             continue;
         }
-        for (hash, pos) in
-            winnowed_fingerprints(file.normalized, params.fingerprint_len, params.window_size)
-        {
+        let prints = winnowed_fingerprints(
+            file.normalized,
+            params.fingerprint_len,
+            params.window_for_len(file.normalized.len()),
+        );
+        stats.fingerprints = stats.fingerprints.saturating_add(prints.len() as u64);
+        for (hash, pos) in prints {
+            // Known boilerplate (`ScanOptions::builtin_skips` / `skip_snippets`) seeds no match.
+            if skip.contains(&hash) {
+                continue;
+            }
+            // Neither does a window made only of stop sequences (`ScanOptions::stop_sequences`).
+            if file
+                .stop_runs
+                .get(pos)
+                .is_some_and(|&run| run as usize >= params.fingerprint_len)
+            {
+                continue;
+            }
             fingerprints
                 .entry(hash)
                 .or_default()
@@ -332,11 +496,17 @@ pub(crate) fn detect_duplicate_span_groups_winnowing<'a>(
 
     let mut seen_matches: HashSet<MatchKey> = HashSet::new();
     let mut groups: HashMap<(u64, usize), Vec<SpanGroupBuilder>> = HashMap::new();
+    let mut group_count = 0usize;
+    let mut capped = CappedGroups::new(params.max_groups);
 
-    for mut occs in fingerprints.into_values() {
+    for mut occs in fingerprint_buckets(fingerprints, params.max_groups) {
         if occs.len() <= 1 {
             continue;
         }
+        if params.cross_repo_only && !spans_multiple_repos(&occs, |file_id| files[file_id].repo_id)
+        {
+            continue;
+        }
         let original_len = occs.len();
         if original_len > MAX_BUCKET {
             occs = truncate_bucket_by_repo(occs, |file_id| files[file_id].repo_id, MAX_BUCKET);
@@ -344,17 +514,15 @@ pub(crate) fn detect_duplicate_span_groups_winnowing<'a>(
                 .skipped_bucket_truncated
                 .saturating_add((original_len - occs.len()) as u64);
         }
+        let pair_starts = pair_starts(&mut occs, |file_id| files[file_id].repo_id, params);
 
         for i in 0..occs.len() {
-            for j in (i + 1)..occs.len() {
+            for j in pair_starts[i]..occs.len() {
                 let a = occs[i];
                 let b = occs[j];
                 if a.file_id == b.file_id && a.pos == b.pos {
                     continue;
                 }
-                if params.cross_repo_only && files[a.file_id].repo_id == files[b.file_id].repo_id {
-                    continue;
-                }
 
                 let (start_a, start_b, len) = match maximal_match(
                     files[a.file_id].normalized,
@@ -399,6 +567,15 @@ pub(crate) fn detect_duplicate_span_groups_winnowing<'a>(
                 let sample_slice = &files[file_a].normalized[start_a..start_a + len];
                 let content_hash = fnv1a64_u32(sample_slice);
 
+                if params.max_groups.is_some_and(|max| group_count >= max)
+                    && !groups.get(&(content_hash, len)).is_some_and(|bucket| {
+                        bucket.iter().any(|g| g.sample.as_slice() == sample_slice)
+                    })
+                {
+                    capped.count((content_hash, len), stats);
+                    continue;
+                }
+
                 let bucket = groups.entry((content_hash, len)).or_default();
                 let builder = match bucket
                     .iter_mut()
@@ -406,21 +583,11 @@ pub(crate) fn detect_duplicate_span_groups_winnowing<'a>(
                 {
                     Some(existing) => existing,
                     None => {
-                        let start_line = files[file_a].line_map.get(start_a).copied().unwrap_or(1);
-                        let end_line = files[file_a]
-                            .line_map
-                            .get(start_a + len - 1)
-                            .copied()
-                            .unwrap_or(start_line);
-
-                        let preview =
-                            preview_from_occurrence(file_a, start_line, end_line, sample_slice);
-
+                        group_count += 1;
                         bucket.push(SpanGroupBuilder {
                             content_hash,
                             normalized_len: len,
                             sample: sample_slice.to_vec(),
-                            preview,
                             occurrences: Vec::new(),
                             occurrence_keys: HashSet::new(),
                             repo_ids: HashSet::new(),
@@ -473,8 +640,14 @@ pub(crate) fn finalize_span_groups(
             out.push(DuplicateSpanGroup {
                 content_hash: builder.content_hash,
                 normalized_len: builder.normalized_len,
-                preview: builder.preview,
+                preview: String::new(),
                 occurrences: builder.occurrences,
+                truncated_occurrences: 0,
+                duplicated_lines: 0,
+                potential_savings_lines: 0,
+                occurrence_previews: Vec::new(),
+                also_detected_by: Vec::new(),
+                quality: None,
             });
         }
     }
@@ -488,3 +661,62 @@ pub(crate) fn finalize_span_groups(
     });
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn occ(file_id: usize) -> FingerprintOcc {
+        FingerprintOcc { file_id, pos: 0 }
+    }
+
+    #[test]
+    fn cross_repo_buckets_only_pair_different_repos() {
+        // Files 0 and 2 belong to repo 0, file 1 to repo 1.
+        let repo_of = |file_id: usize| usize::from(file_id == 1);
+        let params = WinnowingParams {
+            min_len: 1,
+            fingerprint_len: 1,
+            window_size: 1,
+            max_window_growth: 1,
+            cross_repo_only: true,
+            max_groups: None,
+        };
+
+        assert!(!spans_multiple_repos(&[occ(0), occ(2)], repo_of));
+        assert!(spans_multiple_repos(&[occ(0), occ(1)], repo_of));
+
+        let mut occs = vec![occ(0), occ(1), occ(2)];
+        let starts = pair_starts(&mut occs, repo_of, params);
+        let mut pairs = Vec::new();
+        for i in 0..occs.len() {
+            for j in starts[i]..occs.len() {
+                pairs.push((occs[i].file_id, occs[j].file_id));
+            }
+        }
+        assert_eq!(pairs, [(0, 1), (2, 1)]);
+
+        let params = WinnowingParams {
+            cross_repo_only: false,
+            ..params
+        };
+        assert_eq!(pair_starts(&mut occs, repo_of, params), [1, 2, 3]);
+    }
+
+    #[test]
+    fn capped_groups_remember_at_most_max_groups_keys() {
+        let mut stats = ScanStats::default();
+        let mut capped = CappedGroups::new(Some(2));
+        for key in [(1, 4), (1, 4), (2, 4), (2, 4)] {
+            capped.count(key, &mut stats);
+        }
+        assert_eq!(stats.skipped_group_cap, 2);
+
+        // The keys are full: a third group counts on every match.
+        capped.count((3, 4), &mut stats);
+        capped.count((3, 4), &mut stats);
+        capped.count((1, 4), &mut stats);
+        assert_eq!(stats.skipped_group_cap, 4);
+        assert_eq!(capped.keys.len(), 2);
+    }
+}